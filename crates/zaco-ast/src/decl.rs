@@ -49,6 +49,15 @@ pub struct Param {
     pub ownership: Option<Ownership>,
     pub optional: bool,
     pub is_rest: bool,
+    /// Set on constructor parameter properties (`private readonly id: number`)
+    pub property: Option<ParamProperty>,
+}
+
+/// Modifiers that turn a constructor parameter into a field assigned from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamProperty {
+    pub access: AccessModifier,
+    pub is_readonly: bool,
 }
 
 /// Class declaration
@@ -107,6 +116,8 @@ pub enum ClassMember {
         is_readonly: bool,
         is_abstract: bool,
         is_optional: bool,
+        /// `name!: T` — assigned somewhere the checker can't see
+        is_definite: bool,
         is_override: bool,
         decorators: Vec<Node<Expr>>,
     },
@@ -140,6 +151,82 @@ pub enum ClassMember {
     },
 }
 
+/// What a decorator is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecoratorKind {
+    Class,
+    Method,
+    Field,
+}
+
+impl DecoratorKind {
+    /// The `kind` a decorator's context object reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DecoratorKind::Class => "class",
+            DecoratorKind::Method => "method",
+            DecoratorKind::Field => "field",
+        }
+    }
+
+    /// Name of the type of a decorator's context object
+    pub fn context_type_name(self) -> &'static str {
+        match self {
+            DecoratorKind::Class => "ClassDecoratorContext",
+            DecoratorKind::Method => "ClassMethodDecoratorContext",
+            DecoratorKind::Field => "ClassFieldDecoratorContext",
+        }
+    }
+}
+
+/// One decorator call made when a class is defined
+#[derive(Debug, Clone, Copy)]
+pub struct DecoratorApplication<'a> {
+    pub decorator: &'a Node<Expr>,
+    pub kind: DecoratorKind,
+    /// The decorated member, None for a class decorator
+    pub member: Option<&'a PropertyName>,
+    pub is_static: bool,
+    pub is_private: bool,
+}
+
+impl ClassDecl {
+    /// Decorator calls in the order the class definition makes them: member
+    /// decorators in member order, then the class decorators. Decorators
+    /// stacked on one target apply bottom-up, nearest first.
+    pub fn decorator_applications(&self) -> Vec<DecoratorApplication<'_>> {
+        let mut applications = Vec::new();
+        for member in &self.members {
+            let (name, decorators, access, is_static, kind) = match member {
+                ClassMember::Method { name, decorators, access, is_static, .. } => {
+                    (name, decorators, access, *is_static, DecoratorKind::Method)
+                }
+                ClassMember::Property { name, decorators, access, is_static, .. } => {
+                    (name, decorators, access, *is_static, DecoratorKind::Field)
+                }
+                _ => continue,
+            };
+            let is_private = *access == AccessModifier::Private
+                || matches!(name, PropertyName::Ident(ident) if ident.value.name.starts_with('#'));
+            applications.extend(decorators.iter().rev().map(|decorator| DecoratorApplication {
+                decorator,
+                kind,
+                member: Some(name),
+                is_static,
+                is_private,
+            }));
+        }
+        applications.extend(self.decorators.iter().rev().map(|decorator| DecoratorApplication {
+            decorator,
+            kind: DecoratorKind::Class,
+            member: None,
+            is_static: false,
+            is_private: false,
+        }));
+        applications
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AccessModifier {
     Public,
@@ -181,6 +268,104 @@ pub struct EnumMember {
     pub init: Option<Node<Expr>>,
 }
 
+/// Compile-time value of an enum member
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnumValue {
+    Number(f64),
+    String(String),
+}
+
+impl EnumDecl {
+    /// Evaluate the members' values in order. A member without an
+    /// initializer follows the previous numeric member, starting at 0.
+    /// Initializers may use literals, arithmetic, bitwise and shift
+    /// operators, string concatenation and earlier members, bare or as
+    /// `Enum.Member`. A member whose value isn't a constant is None, and so
+    /// is an uninitialized member following it or a string member.
+    pub fn member_values(&self) -> Vec<(String, Option<EnumValue>)> {
+        let mut values: Vec<(String, Option<EnumValue>)> = Vec::new();
+        for member in &self.members {
+            let value = match &member.init {
+                Some(init) => self.const_value(&init.value, &values),
+                None => match values.last() {
+                    None => Some(EnumValue::Number(0.0)),
+                    Some((_, Some(EnumValue::Number(n)))) => Some(EnumValue::Number(n + 1.0)),
+                    Some(_) => None,
+                },
+            };
+            values.push((member.name.value.name.clone(), value));
+        }
+        values
+    }
+
+    fn const_value(&self, expr: &Expr, earlier: &[(String, Option<EnumValue>)]) -> Option<EnumValue> {
+        let member = |name: &str| earlier.iter().find(|(n, _)| n == name).and_then(|(_, v)| v.clone());
+        match expr {
+            Expr::Literal(Literal::Number(n)) => Some(EnumValue::Number(*n)),
+            Expr::Literal(Literal::String(s)) => Some(EnumValue::String(s.clone())),
+            Expr::Template { parts, exprs } if exprs.is_empty() => Some(EnumValue::String(parts.concat())),
+            Expr::Paren(inner) => self.const_value(&inner.value, earlier),
+            Expr::Ident(ident) => member(&ident.name),
+            Expr::Member { object, property, computed: false } => match &object.value {
+                Expr::Ident(ident) if ident.name == self.name.value.name => member(&property.value.name),
+                _ => None,
+            },
+            Expr::Unary { op, expr } => {
+                let EnumValue::Number(n) = self.const_value(&expr.value, earlier)? else {
+                    return None;
+                };
+                match op {
+                    UnaryOp::Plus => Some(EnumValue::Number(n)),
+                    UnaryOp::Minus => Some(EnumValue::Number(-n)),
+                    UnaryOp::BitNot => Some(EnumValue::Number(!to_int32(n) as f64)),
+                    _ => None,
+                }
+            }
+            Expr::Binary { left, op, right } => {
+                let left = self.const_value(&left.value, earlier)?;
+                let right = self.const_value(&right.value, earlier)?;
+                let (l, r) = match (left, right) {
+                    (EnumValue::Number(l), EnumValue::Number(r)) => (l, r),
+                    (left, right) if *op == BinaryOp::Add => {
+                        let text = |v: EnumValue| match v {
+                            EnumValue::Number(n) => format!("{}", n),
+                            EnumValue::String(s) => s,
+                        };
+                        return Some(EnumValue::String(text(left) + &text(right)));
+                    }
+                    _ => return None,
+                };
+                let shift = (to_int32(r) as u32) & 31;
+                let value = match op {
+                    BinaryOp::Add => l + r,
+                    BinaryOp::Sub => l - r,
+                    BinaryOp::Mul => l * r,
+                    BinaryOp::Div => l / r,
+                    BinaryOp::Mod => l % r,
+                    BinaryOp::Pow => l.powf(r),
+                    BinaryOp::BitAnd => (to_int32(l) & to_int32(r)) as f64,
+                    BinaryOp::BitOr => (to_int32(l) | to_int32(r)) as f64,
+                    BinaryOp::BitXor => (to_int32(l) ^ to_int32(r)) as f64,
+                    BinaryOp::LeftShift => to_int32(l).wrapping_shl(shift) as f64,
+                    BinaryOp::RightShift => (to_int32(l) >> shift) as f64,
+                    BinaryOp::UnsignedRightShift => ((to_int32(l) as u32) >> shift) as f64,
+                    _ => return None,
+                };
+                Some(EnumValue::Number(value))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// JavaScript's ToInt32, used by the bitwise operators
+fn to_int32(n: f64) -> i32 {
+    if !n.is_finite() {
+        return 0;
+    }
+    (n.trunc().rem_euclid(4294967296.0) as u32) as i32
+}
+
 /// Module/namespace declaration
 #[derive(Debug, Clone, PartialEq)]
 pub struct ModuleDecl {
@@ -64,6 +64,7 @@ pub enum Expr {
         params: Vec<Param>,
         return_type: Option<Box<Node<Type>>>,
         body: ArrowBody,
+        is_async: bool,
     },
 
     /// Function expression: function name?(params) { body }
@@ -145,7 +146,10 @@ pub enum Expr {
     /// Tagged template literal: tag`template`
     TaggedTemplate {
         tag: Box<Node<Expr>>,
+        /// Parts with escape sequences processed
         parts: Vec<String>,
+        /// Parts as written in the source
+        raw: Vec<String>,
         exprs: Vec<Node<Expr>>,
     },
 
@@ -280,6 +284,18 @@ pub enum ObjectProperty {
         return_type: Option<Box<Node<Type>>>,
         body: Node<BlockStmt>,
     },
+    /// `get name() { ... }`
+    Getter {
+        key: PropertyName,
+        return_type: Option<Box<Node<Type>>>,
+        body: Node<BlockStmt>,
+    },
+    /// `set name(value) { ... }`
+    Setter {
+        key: PropertyName,
+        param: Param,
+        body: Node<BlockStmt>,
+    },
     Spread(Node<Expr>),
 }
 
@@ -292,6 +308,158 @@ pub enum PropertyName {
     Computed(Box<Node<Expr>>),
 }
 
+/// Well-known symbols that can key a member (`[Symbol.iterator]() {}`)
+pub const WELL_KNOWN_SYMBOLS: &[&str] = &["iterator", "asyncIterator", "dispose", "asyncDispose"];
+
+impl Expr {
+    /// Whether evaluating this expression awaits, leaving out the bodies of
+    /// the functions it creates, which await on their own
+    pub fn awaits(&self) -> bool {
+        let awaits = |expr: &Node<Expr>| expr.value.awaits();
+        let key_awaits = |key: &PropertyName| matches!(key, PropertyName::Computed(expr) if awaits(expr));
+        match self {
+            Expr::Await(_) => true,
+            Expr::Binary { left, right, .. } => awaits(left) || awaits(right),
+            Expr::Assignment { target, value, .. } => awaits(target) || awaits(value),
+            Expr::Call { callee, args, .. }
+            | Expr::New { callee, args, .. }
+            | Expr::OptionalCall { callee, args, .. } => awaits(callee) || args.iter().any(awaits),
+            Expr::Index { object, index } | Expr::OptionalIndex { object, index } => awaits(object) || awaits(index),
+            Expr::Unary { expr, .. }
+            | Expr::TypeCast { expr, .. }
+            | Expr::Satisfies { expr, .. }
+            | Expr::Member { object: expr, .. }
+            | Expr::OptionalMember { object: expr, .. }
+            | Expr::Paren(expr)
+            | Expr::Clone(expr)
+            | Expr::Spread(expr)
+            | Expr::NonNullAssertion(expr) => awaits(expr),
+            Expr::Array(elements) => elements.iter().flatten().any(awaits),
+            Expr::Object(properties) => properties.iter().any(|property| match property {
+                ObjectProperty::Property { key, value, .. } => key_awaits(key) || awaits(value),
+                ObjectProperty::Method { key, .. }
+                | ObjectProperty::Getter { key, .. }
+                | ObjectProperty::Setter { key, .. } => key_awaits(key),
+                ObjectProperty::Spread(expr) => awaits(expr),
+            }),
+            Expr::Ternary { condition, then_expr, else_expr } => {
+                awaits(condition) || awaits(then_expr) || awaits(else_expr)
+            }
+            Expr::Template { exprs, .. } | Expr::Sequence(exprs) => exprs.iter().any(awaits),
+            Expr::TaggedTemplate { tag, exprs, .. } => awaits(tag) || exprs.iter().any(awaits),
+            Expr::Yield { argument, .. } => argument.as_deref().is_some_and(awaits),
+            Expr::Literal(_)
+            | Expr::Ident(_)
+            | Expr::Arrow { .. }
+            | Expr::Function { .. }
+            | Expr::This
+            | Expr::Super
+            | Expr::MetaProperty { .. } => false,
+        }
+    }
+
+    /// Call `f` with every identifier this expression reads or assigns,
+    /// including those in nested function bodies. Shadowing is not
+    /// tracked, so a name may be reported that a local binding hides.
+    pub fn for_each_ident(&self, f: &mut dyn FnMut(&Ident)) {
+        let stmts = |block: &BlockStmt, f: &mut dyn FnMut(&Ident)| {
+            block.stmts.iter().for_each(|stmt| stmt.value.for_each_ident(f))
+        };
+        match self {
+            Expr::Ident(ident) => f(ident),
+            Expr::Binary { left, right, .. }
+            | Expr::Assignment { target: left, value: right, .. }
+            | Expr::Index { object: left, index: right }
+            | Expr::OptionalIndex { object: left, index: right } => {
+                left.value.for_each_ident(f);
+                right.value.for_each_ident(f);
+            }
+            Expr::Call { callee, args, .. }
+            | Expr::New { callee, args, .. }
+            | Expr::OptionalCall { callee, args, .. }
+            | Expr::TaggedTemplate { tag: callee, exprs: args, .. } => {
+                callee.value.for_each_ident(f);
+                args.iter().for_each(|arg| arg.value.for_each_ident(f));
+            }
+            Expr::Unary { expr, .. }
+            | Expr::TypeCast { expr, .. }
+            | Expr::Satisfies { expr, .. }
+            | Expr::Member { object: expr, .. }
+            | Expr::OptionalMember { object: expr, .. }
+            | Expr::Await(expr)
+            | Expr::Paren(expr)
+            | Expr::Clone(expr)
+            | Expr::Spread(expr)
+            | Expr::NonNullAssertion(expr) => expr.value.for_each_ident(f),
+            Expr::Array(elements) => elements.iter().flatten().for_each(|elem| elem.value.for_each_ident(f)),
+            Expr::Object(properties) => {
+                for property in properties {
+                    match property {
+                        ObjectProperty::Property { key, value, .. } => {
+                            if let PropertyName::Computed(key) = key {
+                                key.value.for_each_ident(f);
+                            }
+                            value.value.for_each_ident(f);
+                        }
+                        ObjectProperty::Method { body, .. }
+                        | ObjectProperty::Getter { body, .. }
+                        | ObjectProperty::Setter { body, .. } => stmts(&body.value, f),
+                        ObjectProperty::Spread(expr) => expr.value.for_each_ident(f),
+                    }
+                }
+            }
+            Expr::Arrow { body: ArrowBody::Expr(expr), .. } => expr.value.for_each_ident(f),
+            Expr::Arrow { body: ArrowBody::Block(body), .. } | Expr::Function { body, .. } => stmts(&body.value, f),
+            Expr::Ternary { condition, then_expr, else_expr } => {
+                for expr in [condition, then_expr, else_expr] {
+                    expr.value.for_each_ident(f);
+                }
+            }
+            Expr::Template { exprs, .. } | Expr::Sequence(exprs) => {
+                exprs.iter().for_each(|expr| expr.value.for_each_ident(f))
+            }
+            Expr::Yield { argument, .. } => {
+                if let Some(argument) = argument {
+                    argument.value.for_each_ident(f);
+                }
+            }
+            Expr::Literal(_) | Expr::This | Expr::Super | Expr::MetaProperty { .. } => {}
+        }
+    }
+
+    /// The name after `Symbol.` when this is a well-known symbol such as
+    /// `Symbol.iterator`.
+    pub fn well_known_symbol(&self) -> Option<&str> {
+        match self {
+            Expr::Member { object, property, computed: false } => match &object.value {
+                Expr::Ident(ident) if ident.name == "Symbol" => WELL_KNOWN_SYMBOLS
+                    .iter()
+                    .find(|name| **name == property.value.name)
+                    .copied(),
+                _ => None,
+            },
+            Expr::Paren(inner) => inner.value.well_known_symbol(),
+            _ => None,
+        }
+    }
+}
+
+impl PropertyName {
+    /// Member name for a `[Symbol.x]` key: `@@x`, which no identifier can
+    /// spell.
+    pub fn symbol_key(&self) -> Option<String> {
+        match self {
+            PropertyName::Computed(expr) => expr.value.well_known_symbol().map(symbol_key),
+            _ => None,
+        }
+    }
+}
+
+/// Member name under which the well-known symbol `Symbol.<name>` is stored
+pub fn symbol_key(name: &str) -> String {
+    format!("@@{}", name)
+}
+
 // Display implementations
 
 impl fmt::Display for BinaryOp {
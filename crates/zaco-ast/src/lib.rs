@@ -22,6 +22,12 @@ impl Span {
         Self { start, end, file_id }
     }
 
+    /// Spans are end-exclusive, so an empty one covers no source text
+    /// (only the end-of-file token has one)
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
     pub fn merge(&self, other: &Span) -> Span {
         Span {
             start: self.start.min(other.start),
@@ -244,6 +250,7 @@ mod tests {
                     is_readonly: false,
                     is_abstract: false,
                     is_optional: false,
+                    is_definite: false,
                     is_override: false,
                     decorators: vec![],
                 }
@@ -42,6 +42,20 @@ pub enum ImportSpecifier {
     },
 }
 
+impl ImportDecl {
+    /// Whether a specifier of this import names only a type, through
+    /// `import type { .. }` or `import { type Foo }`
+    pub fn is_type_specifier(&self, specifier: &ImportSpecifier) -> bool {
+        self.type_only || matches!(specifier, ImportSpecifier::Named { type_only: true, .. })
+    }
+
+    /// Whether the whole import is erased at run time: every specifier is
+    /// type-only. `import "module"` has none and is kept for its side effects.
+    pub fn is_type_only(&self) -> bool {
+        !self.specifiers.is_empty() && self.specifiers.iter().all(|spec| self.is_type_specifier(spec))
+    }
+}
+
 /// Export declaration
 #[derive(Debug, Clone, PartialEq)]
 pub enum ExportDecl {
@@ -82,3 +96,40 @@ pub struct Program {
     pub items: Vec<Node<ModuleItem>>,
     pub span: Span,
 }
+
+impl Program {
+    /// Items of the program's `declare global { .. }` blocks, which add to
+    /// the global scope of every module
+    pub fn global_declarations(&self) -> impl Iterator<Item = &Node<ModuleItem>> {
+        self.items
+            .iter()
+            .filter_map(|item| match &item.value {
+                ModuleItem::Decl(decl) => match &decl.value {
+                    Decl::Module(ModuleDecl {
+                        name: ModuleName::Ident(name),
+                        body: ModuleBody::Block(items),
+                        is_declare: true,
+                    }) if name.value.name == "global" => Some(items),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .flatten()
+    }
+
+    /// The span of the first top-level item that awaits, making the module's
+    /// initialization asynchronous
+    pub fn top_level_await(&self) -> Option<Span> {
+        let decl_awaits = |decl: &Decl| matches!(decl, Decl::Var(var) if var.awaits());
+        self.items
+            .iter()
+            .find(|item| match &item.value {
+                ModuleItem::Stmt(stmt) => stmt.value.awaits(),
+                ModuleItem::Decl(decl) => decl_awaits(&decl.value),
+                ModuleItem::Export(ExportDecl::Decl(decl)) => decl_awaits(&decl.value),
+                ModuleItem::Export(ExportDecl::Default(expr)) => expr.value.awaits(),
+                _ => false,
+            })
+            .map(|item| item.span)
+    }
+}
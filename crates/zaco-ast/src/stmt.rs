@@ -95,6 +95,156 @@ pub enum Stmt {
     Debugger,
 }
 
+impl Stmt {
+    /// Whether this statement awaits outside the functions nested in it:
+    /// an `await` expression or a `for await` loop. At the top level of a
+    /// module, that is a top-level await.
+    pub fn awaits(&self) -> bool {
+        let awaits = |expr: &Node<Expr>| expr.value.awaits();
+        let block_awaits = |block: &BlockStmt| block.stmts.iter().any(|stmt| stmt.value.awaits());
+        match self {
+            Stmt::Expr(expr) | Stmt::Throw(expr) => awaits(expr),
+            Stmt::Return(expr) => expr.as_ref().is_some_and(awaits),
+            Stmt::VarDecl(decl) => decl.awaits(),
+            Stmt::If { condition, then_stmt, else_stmt } => {
+                awaits(condition) || then_stmt.value.awaits() || else_stmt.as_ref().is_some_and(|s| s.value.awaits())
+            }
+            Stmt::For { init, condition, update, body } => {
+                let init_awaits = match init {
+                    Some(ForInit::VarDecl(decl)) => decl.awaits(),
+                    Some(ForInit::Expr(expr)) => awaits(expr),
+                    None => false,
+                };
+                init_awaits || condition.iter().chain(update).any(awaits) || body.value.awaits()
+            }
+            Stmt::ForOf { is_await: true, .. } => true,
+            Stmt::ForIn { right, body, .. } | Stmt::ForOf { right, body, .. } => awaits(right) || body.value.awaits(),
+            Stmt::While { condition, body } | Stmt::DoWhile { body, condition } => {
+                awaits(condition) || body.value.awaits()
+            }
+            Stmt::Block(block) => block_awaits(block),
+            Stmt::Try { block, catch, finally } => {
+                let catch_body = catch.as_ref().map(|catch| &catch.body);
+                std::iter::once(block).chain(catch_body).chain(finally).any(|block| block_awaits(&block.value))
+            }
+            Stmt::Switch { discriminant, cases } => {
+                awaits(discriminant)
+                    || cases.iter().any(|case| {
+                        case.test.as_ref().is_some_and(awaits) || case.consequent.iter().any(|stmt| stmt.value.awaits())
+                    })
+            }
+            Stmt::Labeled { stmt, .. } => stmt.value.awaits(),
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::Empty | Stmt::Debugger => false,
+        }
+    }
+
+    /// Call `f` with every identifier this statement's expressions mention
+    /// (see `Expr::for_each_ident`)
+    pub fn for_each_ident(&self, f: &mut dyn FnMut(&Ident)) {
+        let decl_idents = |decl: &VarDecl, f: &mut dyn FnMut(&Ident)| {
+            for init in decl.declarations.iter().filter_map(|d| d.init.as_ref()) {
+                init.value.for_each_ident(f);
+            }
+        };
+        let block_idents = |block: &BlockStmt, f: &mut dyn FnMut(&Ident)| {
+            block.stmts.iter().for_each(|stmt| stmt.value.for_each_ident(f))
+        };
+        match self {
+            Stmt::Expr(expr) | Stmt::Throw(expr) | Stmt::Return(Some(expr)) => expr.value.for_each_ident(f),
+            Stmt::VarDecl(decl) => decl_idents(decl, f),
+            Stmt::If { condition, then_stmt, else_stmt } => {
+                condition.value.for_each_ident(f);
+                then_stmt.value.for_each_ident(f);
+                if let Some(else_stmt) = else_stmt {
+                    else_stmt.value.for_each_ident(f);
+                }
+            }
+            Stmt::For { init, condition, update, body } => {
+                match init {
+                    Some(ForInit::VarDecl(decl)) => decl_idents(decl, f),
+                    Some(ForInit::Expr(expr)) => expr.value.for_each_ident(f),
+                    None => {}
+                }
+                condition.iter().chain(update).for_each(|expr| expr.value.for_each_ident(f));
+                body.value.for_each_ident(f);
+            }
+            Stmt::ForIn { right, body, .. }
+            | Stmt::ForOf { right, body, .. }
+            | Stmt::While { condition: right, body }
+            | Stmt::DoWhile { body, condition: right } => {
+                right.value.for_each_ident(f);
+                body.value.for_each_ident(f);
+            }
+            Stmt::Block(block) => block_idents(block, f),
+            Stmt::Try { block, catch, finally } => {
+                let catch_body = catch.as_ref().map(|catch| &catch.body);
+                for block in std::iter::once(block).chain(catch_body).chain(finally) {
+                    block_idents(&block.value, f);
+                }
+            }
+            Stmt::Switch { discriminant, cases } => {
+                discriminant.value.for_each_ident(f);
+                for case in cases {
+                    if let Some(test) = &case.test {
+                        test.value.for_each_ident(f);
+                    }
+                    case.consequent.iter().for_each(|stmt| stmt.value.for_each_ident(f));
+                }
+            }
+            Stmt::Labeled { stmt, .. } => stmt.value.for_each_ident(f),
+            Stmt::Return(None) | Stmt::Break(_) | Stmt::Continue(_) | Stmt::Empty | Stmt::Debugger => {}
+        }
+    }
+
+    /// Collect the `var` declarations this statement hoists to the enclosing
+    /// function: its own and those nested in blocks, loop bodies, `try` and
+    /// `switch`, but not those inside functions or classes. The `var` of a
+    /// `for...in`/`for...of` head stays with its loop.
+    pub fn collect_hoisted_vars<'a>(&'a self, out: &mut Vec<&'a VarDecl>) {
+        match self {
+            Stmt::VarDecl(decl) if decl.kind == VarDeclKind::Var => out.push(decl),
+            Stmt::If { then_stmt, else_stmt, .. } => {
+                then_stmt.value.collect_hoisted_vars(out);
+                if let Some(else_stmt) = else_stmt {
+                    else_stmt.value.collect_hoisted_vars(out);
+                }
+            }
+            Stmt::For { init, body, .. } => {
+                if let Some(ForInit::VarDecl(decl)) = init {
+                    if decl.kind == VarDeclKind::Var {
+                        out.push(decl);
+                    }
+                }
+                body.value.collect_hoisted_vars(out);
+            }
+            Stmt::ForIn { body, .. }
+            | Stmt::ForOf { body, .. }
+            | Stmt::While { body, .. }
+            | Stmt::DoWhile { body, .. }
+            | Stmt::Labeled { stmt: body, .. } => body.value.collect_hoisted_vars(out),
+            Stmt::Block(block) => {
+                for stmt in &block.stmts {
+                    stmt.value.collect_hoisted_vars(out);
+                }
+            }
+            Stmt::Try { block, catch, finally } => {
+                let catch_body = catch.as_ref().map(|catch| &catch.body);
+                for block in std::iter::once(block).chain(catch_body).chain(finally) {
+                    for stmt in &block.value.stmts {
+                        stmt.value.collect_hoisted_vars(out);
+                    }
+                }
+            }
+            Stmt::Switch { cases, .. } => {
+                for stmt in cases.iter().flat_map(|case| &case.consequent) {
+                    stmt.value.collect_hoisted_vars(out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 /// Block statement
 #[derive(Debug, Clone, PartialEq)]
 pub struct BlockStmt {
@@ -108,6 +258,24 @@ pub struct VarDecl {
     pub declarations: Vec<VarDeclarator>,
 }
 
+impl VarDecl {
+    /// Whether an initializer awaits (see `Stmt::awaits`)
+    pub fn awaits(&self) -> bool {
+        self.declarations.iter().filter_map(|d| d.init.as_ref()).any(|init| init.value.awaits())
+    }
+}
+
+impl VarDecl {
+    /// Source range of the declarators (the keyword is not part of the
+    /// node), or `None` when there are none
+    pub fn span(&self) -> Option<Span> {
+        let first = self.declarations.first()?;
+        let last = self.declarations.last()?;
+        let end = last.init.as_ref().map_or(&last.pattern.span, |init| &init.span);
+        Some(first.pattern.span.merge(end))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VarDeclKind {
     Let,
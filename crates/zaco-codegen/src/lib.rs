@@ -6,6 +6,7 @@
 mod error;
 mod runtime;
 mod translator;
+pub mod wasm;
 
 pub use error::CodegenError;
 
@@ -16,7 +17,7 @@ use std::collections::HashMap;
 
 // Import from zaco_ir with explicit names to avoid conflicts
 use zaco_ir::{
-    FuncId, IrFunction, IrModule, IrType,
+    Constant, FuncId, IrFunction, IrModule, IrType,
 };
 
 use crate::runtime::{RuntimeFunctions, declare_runtime_functions};
@@ -38,6 +39,11 @@ pub struct CodeGenerator {
     runtime_funcs: RuntimeFunctions,
     /// String literal data IDs
     string_data_map: HashMap<usize, cranelift_module::DataId>,
+    /// Whether RefCount/Clone instructions emit rc_inc/rc_dec calls.
+    /// Disabled when the runtime is built with an arena allocator.
+    ref_counting: bool,
+    /// Target assembly of each compiled function, collected for `--emit asm`
+    disassembly: Option<Vec<(String, String)>>,
 }
 
 impl CodeGenerator {
@@ -82,11 +88,48 @@ impl CodeGenerator {
             func_id_map: HashMap::new(),
             runtime_funcs: RuntimeFunctions::default(),
             string_data_map: HashMap::new(),
+            ref_counting: true,
+            disassembly: None,
         })
     }
 
+    /// Enable or disable ref counting calls. Pass `false` when linking
+    /// against the arena runtime, where objects are never freed individually.
+    pub fn with_ref_counting(mut self, enabled: bool) -> Self {
+        self.ref_counting = enabled;
+        self
+    }
+
+    /// Compile a complete IR module and return the target assembly of each
+    /// function, as printed by Cranelift after register allocation.
+    pub fn disassemble_module(mut self, ir_module: &IrModule) -> Result<String, CodegenError> {
+        self.disassembly = Some(Vec::new());
+        self.compile_functions(ir_module)?;
+        let mut out = String::new();
+        for (name, asm) in self.disassembly.take().unwrap_or_default() {
+            out.push_str(&format!("function {}:\n", name));
+            for line in asm.lines() {
+                out.push_str(&format!("  {}\n", line));
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
     /// Compile a complete IR module to object file bytes
     pub fn compile_module(mut self, ir_module: &IrModule) -> Result<Vec<u8>, CodegenError> {
+        self.compile_functions(ir_module)?;
+
+        // Finalize the module and produce object file (consumes self.module)
+        let object_product = self.module.finish();
+
+        Ok(object_product
+            .emit()
+            .map_err(|e| CodegenError::new(format!("Failed to emit object file: {}", e)))?)
+    }
+
+    /// Declare everything the module references and compile each function
+    fn compile_functions(&mut self, ir_module: &IrModule) -> Result<(), CodegenError> {
         // Declare runtime functions first
         declare_runtime_functions(&mut self.module, &mut self.runtime_funcs, self.pointer_type)?;
 
@@ -100,17 +143,19 @@ impl CodeGenerator {
             self.declare_string_literal(idx, string)?;
         }
 
+        for (name, ty, init) in &ir_module.globals {
+            self.define_global(name, ty, init.as_ref())?;
+        }
+
+        if !ir_module.worker_entries.is_empty() {
+            self.declare_worker_table(ir_module)?;
+        }
+
         // Compile each function
         for function in &ir_module.functions {
             self.compile_function(function, ir_module)?;
         }
-
-        // Finalize the module and produce object file (consumes self.module)
-        let object_product = self.module.finish();
-
-        Ok(object_product
-            .emit()
-            .map_err(|e| CodegenError::new(format!("Failed to emit object file: {}", e)))?)
+        Ok(())
     }
 
     /// Declare a function signature in the module
@@ -172,6 +217,76 @@ impl CodeGenerator {
         Ok(())
     }
 
+    /// Define a module global as a writable 8-byte data object holding its
+    /// initializer, or zero
+    fn define_global(&mut self, name: &str, ty: &IrType, init: Option<&Constant>) -> Result<(), CodegenError> {
+        let bits: u64 = match init {
+            Some(Constant::I64(value)) => *value as u64,
+            Some(Constant::F64(value)) => value.to_bits(),
+            Some(Constant::Bool(value)) => *value as u64,
+            Some(Constant::Null) | None => 0,
+            Some(other) => {
+                return Err(CodegenError::new(format!(
+                    "Global '{}' of type {:?} cannot be initialized with {:?}",
+                    name, ty, other
+                )))
+            }
+        };
+        let mut data_desc = DataDescription::new();
+        data_desc.define(bits.to_le_bytes().to_vec().into_boxed_slice());
+        let data_id = self
+            .module
+            .declare_data(name, Linkage::Local, true, false)
+            .map_err(|e| CodegenError::new(format!("Failed to declare global: {}", e)))?;
+        self.module
+            .define_data(data_id, &data_desc)
+            .map_err(|e| CodegenError::new(format!("Failed to define global: {}", e)))?;
+        Ok(())
+    }
+
+    /// Emit `__zaco_worker_table`, the registry the runtime's
+    /// `zaco_worker_spawn` searches: `{ name: *const c_char, entry: fn }`
+    /// pairs, terminated by a null entry.
+    fn declare_worker_table(&mut self, ir_module: &IrModule) -> Result<(), CodegenError> {
+        let ptr_size = self.pointer_type.bytes() as usize;
+        let mut table = DataDescription::new();
+        table.define(vec![0u8; (ir_module.worker_entries.len() + 1) * 2 * ptr_size].into_boxed_slice());
+
+        for (i, (name, trampoline)) in ir_module.worker_entries.iter().enumerate() {
+            let mut name_desc = DataDescription::new();
+            let mut bytes = name.as_bytes().to_vec();
+            bytes.push(0);
+            name_desc.define(bytes.into_boxed_slice());
+            let name_id = self
+                .module
+                .declare_data(&format!("__zaco_worker_name_{}", i), Linkage::Local, false, false)
+                .map_err(|e| CodegenError::new(format!("Failed to declare worker name: {}", e)))?;
+            self.module
+                .define_data(name_id, &name_desc)
+                .map_err(|e| CodegenError::new(format!("Failed to define worker name: {}", e)))?;
+
+            let func = ir_module
+                .find_function(trampoline)
+                .and_then(|f| self.func_id_map.get(&f.id))
+                .ok_or_else(|| CodegenError::new(format!("Worker trampoline {} not declared", trampoline)))?;
+
+            let offset = (i * 2 * ptr_size) as u32;
+            let name_gv = self.module.declare_data_in_data(name_id, &mut table);
+            table.write_data_addr(offset, name_gv, 0);
+            let func_ref = self.module.declare_func_in_data(*func, &mut table);
+            table.write_function_addr(offset + ptr_size as u32, func_ref);
+        }
+
+        let table_id = self
+            .module
+            .declare_data("__zaco_worker_table", Linkage::Export, false, false)
+            .map_err(|e| CodegenError::new(format!("Failed to declare worker table: {}", e)))?;
+        self.module
+            .define_data(table_id, &table)
+            .map_err(|e| CodegenError::new(format!("Failed to define worker table: {}", e)))?;
+        Ok(())
+    }
+
     /// Compile a single function
     pub fn compile_function(
         &mut self,
@@ -215,7 +330,8 @@ impl CodeGenerator {
             ir_func,
             ir_module,
             pointer_type,
-        );
+        )
+        .with_ref_counting(self.ref_counting);
 
         // Translate the function (pass builder by value since finalize() consumes it)
         translator.translate(builder)?;
@@ -229,10 +345,16 @@ impl CodeGenerator {
         }
 
         // Define the function in the module
+        self.ctx.set_disasm(self.disassembly.is_some());
         self.module
             .define_function(clif_func_id, &mut self.ctx)
             .map_err(|e| CodegenError::new(format!("Failed to define function: {}", e)))?;
 
+        if let Some(disassembly) = &mut self.disassembly {
+            let asm = self.ctx.compiled_code().and_then(|code| code.vcode.clone()).unwrap_or_default();
+            disassembly.push((ir_func.name.clone(), asm));
+        }
+
         // Clear the context for the next function
         self.module.clear_context(&mut self.ctx);
 
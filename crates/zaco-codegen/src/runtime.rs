@@ -15,6 +15,7 @@ pub(crate) struct RuntimeFunctions {
     pub(crate) zaco_rc_dec: Option<ClifFuncId>,
     pub(crate) zaco_str_concat: Option<ClifFuncId>,
     pub(crate) zaco_str_new: Option<ClifFuncId>,
+    pub(crate) zaco_undefined: Option<ClifFuncId>,
     pub(crate) zaco_print_str: Option<ClifFuncId>,
     pub(crate) zaco_print_i64: Option<ClifFuncId>,
     pub(crate) zaco_print_f64: Option<ClifFuncId>,
@@ -38,6 +39,7 @@ pub(crate) struct RuntimeFunctions {
     pub(crate) zaco_math_min: Option<ClifFuncId>,
     pub(crate) zaco_math_max: Option<ClifFuncId>,
     pub(crate) zaco_math_trunc: Option<ClifFuncId>,
+    pub(crate) zaco_js_mod: Option<ClifFuncId>,
     pub(crate) zaco_math_pi: Option<ClifFuncId>,
     pub(crate) zaco_math_e: Option<ClifFuncId>,
     // JSON functions
@@ -131,7 +133,8 @@ pub(crate) struct RuntimeFunctions {
     pub(crate) zaco_runtime_init: Option<ClifFuncId>,
     pub(crate) zaco_runtime_shutdown: Option<ClifFuncId>,
     // Exception handling
-    pub(crate) zaco_try_push: Option<ClifFuncId>,
+    pub(crate) zaco_try_enter: Option<ClifFuncId>,
+    pub(crate) setjmp: Option<ClifFuncId>,
     pub(crate) zaco_try_pop: Option<ClifFuncId>,
     pub(crate) zaco_throw: Option<ClifFuncId>,
     pub(crate) zaco_get_error: Option<ClifFuncId>,
@@ -159,6 +162,7 @@ impl RuntimeFunctions {
             "zaco_rc_dec" => self.zaco_rc_dec,
             "zaco_str_concat" => self.zaco_str_concat,
             "zaco_str_new" => self.zaco_str_new,
+            "zaco_undefined" => self.zaco_undefined,
             "zaco_print_str" => self.zaco_print_str,
             "zaco_print_i64" => self.zaco_print_i64,
             "zaco_print_f64" => self.zaco_print_f64,
@@ -182,6 +186,7 @@ impl RuntimeFunctions {
             "zaco_math_min" => self.zaco_math_min,
             "zaco_math_max" => self.zaco_math_max,
             "zaco_math_trunc" => self.zaco_math_trunc,
+            "zaco_js_mod" => self.zaco_js_mod,
             "zaco_math_pi" => self.zaco_math_pi,
             "zaco_math_e" => self.zaco_math_e,
             // JSON functions
@@ -273,7 +278,8 @@ impl RuntimeFunctions {
             "zaco_runtime_init" => self.zaco_runtime_init,
             "zaco_runtime_shutdown" => self.zaco_runtime_shutdown,
             // Exception handling
-            "zaco_try_push" => self.zaco_try_push,
+            "zaco_try_enter" => self.zaco_try_enter,
+            "_setjmp" => self.setjmp,
             "zaco_try_pop" => self.zaco_try_pop,
             "zaco_throw" => self.zaco_throw,
             "zaco_get_error" => self.zaco_get_error,
@@ -358,6 +364,14 @@ pub(crate) fn declare_runtime_functions(
         .map_err(|e| CodegenError::new(format!("Failed to declare zaco_str_new: {}", e)))?;
     runtime_funcs.zaco_str_new = Some(str_new_id);
 
+    // zaco_undefined() -> ptr  (the shared `undefined` sentinel)
+    let mut undefined_sig = module.make_signature();
+    undefined_sig.returns.push(AbiParam::new(pointer_type));
+    let undefined_id = module
+        .declare_function("zaco_undefined", Linkage::Import, &undefined_sig)
+        .map_err(|e| CodegenError::new(format!("Failed to declare zaco_undefined: {}", e)))?;
+    runtime_funcs.zaco_undefined = Some(undefined_id);
+
     // zaco_print_str(ptr)
     let mut print_str_sig = module.make_signature();
     print_str_sig.params.push(AbiParam::new(pointer_type));
@@ -560,6 +574,16 @@ pub(crate) fn declare_runtime_functions(
         .map_err(|e| CodegenError::new(format!("Failed to declare zaco_math_trunc: {}", e)))?;
     runtime_funcs.zaco_math_trunc = Some(math_trunc_id);
 
+    // zaco_js_mod(f64, f64) -> f64  (the `%` operator on numbers)
+    let mut js_mod_sig = module.make_signature();
+    js_mod_sig.params.push(AbiParam::new(types::F64));
+    js_mod_sig.params.push(AbiParam::new(types::F64));
+    js_mod_sig.returns.push(AbiParam::new(types::F64));
+    let js_mod_id = module
+        .declare_function("zaco_js_mod", Linkage::Import, &js_mod_sig)
+        .map_err(|e| CodegenError::new(format!("Failed to declare zaco_js_mod: {}", e)))?;
+    runtime_funcs.zaco_js_mod = Some(js_mod_id);
+
     // zaco_math_pi() -> f64
     let mut math_pi_sig = module.make_signature();
     math_pi_sig.returns.push(AbiParam::new(types::F64));
@@ -1281,13 +1305,24 @@ pub(crate) fn declare_runtime_functions(
 
     // ========== Exception Handling ==========
 
-    // zaco_try_push() -> i64  (returns 0 on initial call, 1 on exception)
-    let mut try_push_sig = module.make_signature();
-    try_push_sig.returns.push(AbiParam::new(types::I64));
-    let try_push_id = module
-        .declare_function("zaco_try_push", Linkage::Import, &try_push_sig)
-        .map_err(|e| CodegenError::new(format!("Failed to declare zaco_try_push: {}", e)))?;
-    runtime_funcs.zaco_try_push = Some(try_push_id);
+    // zaco_try_enter() -> ptr  (jmp_buf of a freshly pushed try slot)
+    let mut try_enter_sig = module.make_signature();
+    try_enter_sig.returns.push(AbiParam::new(pointer_type));
+    let try_enter_id = module
+        .declare_function("zaco_try_enter", Linkage::Import, &try_enter_sig)
+        .map_err(|e| CodegenError::new(format!("Failed to declare zaco_try_enter: {}", e)))?;
+    runtime_funcs.zaco_try_enter = Some(try_enter_id);
+
+    // _setjmp(buf: ptr) -> i64  (0 on initial call, 1 when zaco_throw jumps back).
+    // libc returns an int; both setjmp and longjmp write it with a 32-bit
+    // move on x86_64 and aarch64, so the upper half is always zero.
+    let mut setjmp_sig = module.make_signature();
+    setjmp_sig.params.push(AbiParam::new(pointer_type));
+    setjmp_sig.returns.push(AbiParam::new(types::I64));
+    let setjmp_id = module
+        .declare_function("_setjmp", Linkage::Import, &setjmp_sig)
+        .map_err(|e| CodegenError::new(format!("Failed to declare _setjmp: {}", e)))?;
+    runtime_funcs.setjmp = Some(setjmp_id);
 
     // zaco_try_pop()
     let try_pop_sig = module.make_signature();
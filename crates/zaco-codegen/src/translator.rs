@@ -38,6 +38,8 @@ pub(crate) struct FunctionTranslator<'a> {
     ir_module: &'a IrModule,
     /// Cached pointer type
     pointer_type: Type,
+    /// Emit rc_inc/rc_dec calls for RefCount/Clone instructions
+    ref_counting: bool,
 }
 
 /// Key for value mapping (Local or Temp)
@@ -68,9 +70,16 @@ impl<'a> FunctionTranslator<'a> {
             ir_func,
             ir_module,
             pointer_type,
+            ref_counting: true,
         }
     }
 
+    /// Enable or disable ref counting calls (disabled for arena allocation)
+    pub(crate) fn with_ref_counting(mut self, enabled: bool) -> Self {
+        self.ref_counting = enabled;
+        self
+    }
+
     /// Convert IR type to Cranelift type
     fn ir_type_to_cranelift(&self, ir_type: &IrType) -> Result<Type, CodegenError> {
         let cl_type = match ir_type {
@@ -190,8 +199,13 @@ impl<'a> FunctionTranslator<'a> {
             }
 
             Instruction::Call { dest, func, args } => {
-                let result = self.translate_call(builder, func, args)?;
+                let result = match func {
+                    IrValue::Const(Constant::Str(_)) => self.translate_call(builder, func, args)?,
+                    // A function value: call the address it holds
+                    _ => self.translate_indirect_call(builder, func, args, dest.as_ref())?,
+                };
                 if let (Some(dest), Some(val)) = (dest, result) {
+                    let val = self.coerce_call_result(builder, dest, val);
                     self.store_to_place(builder, dest, val)?;
                 }
             }
@@ -205,7 +219,18 @@ impl<'a> FunctionTranslator<'a> {
             }
 
             Instruction::Alloc { dest, ty } => {
-                let size = ty.size_bytes() as i64;
+                // Struct values are pointers, so size_bytes() is 8; allocate
+                // room for the fields themselves.
+                let size = match ty {
+                    IrType::Struct(struct_id) => self
+                        .ir_module
+                        .structs
+                        .iter()
+                        .find(|s| s.id == *struct_id)
+                        .map(|s| s.fields.iter().map(|(_, f)| f.size_bytes()).sum::<usize>())
+                        .unwrap_or_else(|| ty.size_bytes()),
+                    _ => ty.size_bytes(),
+                } as i64;
                 let size_val = builder.ins().iconst(types::I64, size);
 
                 let alloc_fn = self
@@ -229,6 +254,9 @@ impl<'a> FunctionTranslator<'a> {
                 builder.ins().call(func_ref, &[ptr]);
             }
 
+            // Arena-allocated objects live until exit; skip the count updates
+            Instruction::RefCount { .. } if !self.ref_counting => {}
+
             Instruction::RefCount { value, delta } => {
                 let ptr = self.translate_value(builder, value)?;
                 if *delta > 0 {
@@ -265,7 +293,7 @@ impl<'a> FunctionTranslator<'a> {
             Instruction::Clone { dest, source } => {
                 // Copy the pointer and increment reference count
                 let val = self.translate_value(builder, source)?;
-                if let Some(rc_inc_fn) = self.runtime_funcs.zaco_rc_inc {
+                if let Some(rc_inc_fn) = self.runtime_funcs.zaco_rc_inc.filter(|_| self.ref_counting) {
                     let func_ref = self.module.declare_func_in_func(rc_inc_fn, builder.func);
                     builder.ins().call(func_ref, &[val]);
                 }
@@ -606,6 +634,35 @@ impl<'a> FunctionTranslator<'a> {
             Constant::F64(f) => builder.ins().f64const(*f),
             Constant::Bool(b) => builder.ins().iconst(types::I8, if *b { 1 } else { 0 }),
             Constant::Null => builder.ins().iconst(self.pointer_type, 0),
+            Constant::Undefined => {
+                let undefined_fn = self
+                    .runtime_funcs
+                    .zaco_undefined
+                    .ok_or_else(|| CodegenError::new("zaco_undefined not declared"))?;
+                let func_ref = self.module.declare_func_in_func(undefined_fn, builder.func);
+                let call = builder.ins().call(func_ref, &[]);
+                builder.inst_results(call)[0]
+            }
+            Constant::FuncAddr(name) => {
+                // A function of another module compiled separately is an extern
+                let clif_func_id = match self.ir_module.find_function(name).and_then(|f| self.func_id_map.get(&f.id)) {
+                    Some(&id) => id,
+                    None => self.declare_extern_function(name)?.ok_or_else(|| {
+                        CodegenError::new(format!("Function '{}' not declared", name))
+                    })?,
+                };
+                let func_ref = self.module.declare_func_in_func(clif_func_id, builder.func);
+                builder.ins().func_addr(self.pointer_type, func_ref)
+            }
+            Constant::GlobalAddr(name) => {
+                // Defined by CodeGenerator::define_global; declaring it again returns its id
+                let data_id = self
+                    .module
+                    .declare_data(name, Linkage::Local, true, false)
+                    .map_err(|e| CodegenError::new(format!("Global '{}' not declared: {}", name, e)))?;
+                let gv = self.module.declare_data_in_func(data_id, builder.func);
+                builder.ins().global_value(self.pointer_type, gv)
+            }
             Constant::Str(s) => {
                 // Look up interned string in string_data_map
                 if let Some(idx) = self.ir_module.string_literals.iter().position(|lit| lit == s) {
@@ -645,7 +702,7 @@ impl<'a> FunctionTranslator<'a> {
 
     /// Translate a binary operation
     fn translate_binop(
-        &self,
+        &mut self,
         builder: &mut FunctionBuilder,
         op: BinOp,
         lhs: ClifValue,
@@ -654,6 +711,14 @@ impl<'a> FunctionTranslator<'a> {
         let lhs_ty = builder.func.dfg.value_type(lhs);
         let is_float = lhs_ty == types::F64;
 
+        // Bitwise operators on numbers work on their 32-bit integer values
+        if is_float && matches!(op, BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr) {
+            let lhs = Self::to_int32(builder, lhs);
+            let rhs = Self::to_int32(builder, rhs);
+            let result = self.translate_binop(builder, op, lhs, rhs)?;
+            return Ok(builder.ins().fcvt_from_sint(types::F64, result));
+        }
+
         let val = match op {
             BinOp::Add => {
                 if is_float { builder.ins().fadd(lhs, rhs) }
@@ -683,11 +748,15 @@ impl<'a> FunctionTranslator<'a> {
             }
             BinOp::Mod => {
                 if is_float {
-                    // fmod: a - floor(a/b) * b
-                    let div = builder.ins().fdiv(lhs, rhs);
-                    let floored = builder.ins().floor(div);
-                    let product = builder.ins().fmul(floored, rhs);
-                    builder.ins().fsub(lhs, product)
+                    // JS `%` takes the sign of the dividend (C fmod); there
+                    // is no Cranelift instruction for it
+                    let js_mod_fn = self
+                        .runtime_funcs
+                        .zaco_js_mod
+                        .ok_or_else(|| CodegenError::new("zaco_js_mod not declared"))?;
+                    let func_ref = self.module.declare_func_in_func(js_mod_fn, builder.func);
+                    let call = builder.ins().call(func_ref, &[lhs, rhs]);
+                    builder.inst_results(call)[0]
                 } else {
                     // Guard: replace 0 divisor with 1 to avoid trap
                     let zero = builder.ins().iconst(types::I64, 0);
@@ -758,11 +827,22 @@ impl<'a> FunctionTranslator<'a> {
                     builder.ins().icmp(IntCC::Equal, operand, zero)
                 }
             }
+            UnOp::BitNot if is_float => {
+                let int = Self::to_int32(builder, operand);
+                let inverted = builder.ins().bnot(int);
+                builder.ins().fcvt_from_sint(types::F64, inverted)
+            }
             UnOp::BitNot => builder.ins().bnot(operand),
         };
         Ok(val)
     }
 
+    /// JavaScript's ToInt32 of a number: its integer part modulo 2^32
+    fn to_int32(builder: &mut FunctionBuilder, value: ClifValue) -> ClifValue {
+        let wide = builder.ins().fcvt_to_sint_sat(types::I64, value);
+        builder.ins().ireduce(types::I32, wide)
+    }
+
     /// Coerce arguments to match a function's expected signature.
     /// Handles I8→I64 extension (bool→int), etc.
     fn coerce_call_args(
@@ -793,6 +873,10 @@ impl<'a> FunctionTranslator<'a> {
                             // Truncate larger int to smaller
                             builder.ins().ireduce(expected_ty, val)
                         }
+                    } else if actual_ty == types::F64 && expected_ty == types::I64 {
+                        // Number passed through a pointer-sized slot (e.g. zaco_box_set):
+                        // keep the raw bits
+                        builder.ins().bitcast(types::I64, MemFlags::new(), val)
                     } else {
                         val
                     }
@@ -803,6 +887,34 @@ impl<'a> FunctionTranslator<'a> {
             .collect()
     }
 
+    /// Coerce a call result to the type of its destination temp or local.
+    /// Pointer-sized results read back into a number or boolean (e.g. from
+    /// zaco_box_get) are reinterpreted rather than converted.
+    fn coerce_call_result(
+        &self,
+        builder: &mut FunctionBuilder,
+        dest: &Place,
+        val: ClifValue,
+    ) -> ClifValue {
+        if !dest.projections.is_empty() {
+            return val;
+        }
+        let Some(dest_ty) = self
+            .infer_value_ir_type(&dest.base)
+            .and_then(|ty| self.ir_type_to_cranelift(&ty).ok())
+        else {
+            return val;
+        };
+        let actual_ty = builder.func.dfg.value_type(val);
+        if actual_ty == types::I64 && dest_ty == types::F64 {
+            builder.ins().bitcast(types::F64, MemFlags::new(), val)
+        } else if actual_ty.is_int() && dest_ty.is_int() && actual_ty.bits() > dest_ty.bits() {
+            builder.ins().ireduce(dest_ty, val)
+        } else {
+            val
+        }
+    }
+
     /// Call a cranelift function with argument coercion
     fn call_with_coercion(
         &self,
@@ -854,31 +966,11 @@ impl<'a> FunctionTranslator<'a> {
                 }
 
                 // 3. Try extern functions declared in the IR module
-                for ext in &self.ir_module.extern_functions {
-                    if ext.name == *name {
-                        let mut sig = self.module.make_signature();
-                        for param_ty in &ext.params {
-                            let cl_type = self.ir_type_to_cranelift(param_ty)?;
-                            sig.params.push(AbiParam::new(cl_type));
-                        }
-                        if ext.return_type != IrType::Void {
-                            let cl_type = self.ir_type_to_cranelift(&ext.return_type)?;
-                            sig.returns.push(AbiParam::new(cl_type));
-                        }
-                        let clif_func_id = self
-                            .module
-                            .declare_function(name, Linkage::Import, &sig)
-                            .map_err(|e| {
-                                CodegenError::new(format!(
-                                    "Failed to declare extern function {}: {}",
-                                    name, e
-                                ))
-                            })?;
-                        let func_ref =
-                            self.module
-                                .declare_func_in_func(clif_func_id, builder.func);
-                        return self.call_with_coercion(builder, func_ref, arg_vals);
-                    }
+                if let Some(clif_func_id) = self.declare_extern_function(name)? {
+                    let func_ref =
+                        self.module
+                            .declare_func_in_func(clif_func_id, builder.func);
+                    return self.call_with_coercion(builder, func_ref, arg_vals);
                 }
 
                 Err(CodegenError::new(format!(
@@ -892,6 +984,55 @@ impl<'a> FunctionTranslator<'a> {
         }
     }
 
+    /// Declare the extern function `name` of the IR module as an import,
+    /// or None when the module declares no such extern
+    fn declare_extern_function(&mut self, name: &str) -> Result<Option<ClifFuncId>, CodegenError> {
+        let Some(ext) = self.ir_module.extern_functions.iter().find(|ext| ext.name == name) else {
+            return Ok(None);
+        };
+        let mut sig = self.module.make_signature();
+        for param_ty in &ext.params {
+            let cl_type = self.ir_type_to_cranelift(param_ty)?;
+            sig.params.push(AbiParam::new(cl_type));
+        }
+        if ext.return_type != IrType::Void {
+            let cl_type = self.ir_type_to_cranelift(&ext.return_type)?;
+            sig.returns.push(AbiParam::new(cl_type));
+        }
+        self.module
+            .declare_function(name, Linkage::Import, &sig)
+            .map(Some)
+            .map_err(|e| CodegenError::new(format!("Failed to declare extern function {}: {}", name, e)))
+    }
+
+    /// Call the code address `func` evaluates to. The signature follows the
+    /// arguments and the type of `dest`, as the lowerer types both after
+    /// the function being called.
+    fn translate_indirect_call(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        func: &IrValue,
+        args: &[IrValue],
+        dest: Option<&Place>,
+    ) -> Result<Option<ClifValue>, CodegenError> {
+        let callee = self.translate_value(builder, func)?;
+        let arg_vals: Vec<ClifValue> = args
+            .iter()
+            .map(|arg| self.translate_value(builder, arg))
+            .collect::<Result<_, _>>()?;
+
+        let mut sig = self.module.make_signature();
+        for val in &arg_vals {
+            sig.params.push(AbiParam::new(builder.func.dfg.value_type(*val)));
+        }
+        if let Some(ret_ty) = dest.and_then(|dest| self.infer_value_ir_type(&dest.base)) {
+            sig.returns.push(AbiParam::new(self.ir_type_to_cranelift(&ret_ty)?));
+        }
+        let sig_ref = builder.import_signature(sig);
+        let call = builder.ins().call_indirect(sig_ref, callee, &arg_vals);
+        Ok(builder.inst_results(call).first().copied())
+    }
+
     /// Store a value to a place (handle projections)
     fn store_to_place(
         &mut self,
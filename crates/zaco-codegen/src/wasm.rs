@@ -0,0 +1,887 @@
+//! WebAssembly module emission for `--emit wasm`
+//!
+//! Cranelift has no wasm32 backend, so this encodes the module directly from
+//! Zaco IR. It covers the numeric core of the language: functions over
+//! numbers and booleans, structured and unstructured control flow, and calls.
+//! Every runtime function the program calls becomes an import from the `zaco`
+//! module for the host to provide; only the ones `runtime/zaco_wasm_host.mjs`
+//! implements (console output and number helpers) are accepted. String literals are NUL-terminated bytes in the exported
+//! linear memory and pointers are 32-bit offsets into it; heap objects,
+//! closures and strings built at run time are reported as unsupported.
+
+use std::collections::HashMap;
+
+use zaco_ir::{
+    BinOp, BlockId, Constant, Instruction, IrFunction, IrModule, IrType, LocalId, Place, RValue,
+    TempId, Terminator, UnOp, Value,
+};
+
+use crate::CodegenError;
+
+const I32: u8 = 0x7F;
+const I64: u8 = 0x7E;
+const F64: u8 = 0x7C;
+
+/// Module that imported runtime functions are resolved from
+const IMPORT_MODULE: &str = "zaco";
+
+/// Address of the first string literal; everything below stays unused so
+/// that 0 can be the null pointer
+const DATA_START: u32 = 16;
+
+const WASM_PAGE_SIZE: u32 = 65536;
+
+/// Runtime functions `runtime/zaco_wasm_host.mjs` implements; calls to any
+/// other runtime function are rejected rather than left to trap in the host
+const HOST_FUNCTIONS: &[&str] = &[
+    "zaco_console_put_str",
+    "zaco_console_put_f64",
+    "zaco_console_put_i64",
+    "zaco_console_put_bool",
+    "zaco_console_line_end",
+    "zaco_print_str",
+    "zaco_js_mod",
+];
+
+/// Compile an IR module to the bytes of a WebAssembly binary module
+pub fn compile_module(ir_module: &IrModule) -> Result<Vec<u8>, CodegenError> {
+    let mut types = TypeTable::default();
+    let mut data = DataSegment::default();
+    for literal in &ir_module.string_literals {
+        data.intern(literal);
+    }
+
+    let mut functions = HashMap::new();
+    let mut imports = collect_imports(ir_module, &mut types)?;
+    let import_count = imports.len() as u32;
+    let mut function_types = Vec::with_capacity(ir_module.functions.len());
+    for (i, function) in ir_module.functions.iter().enumerate() {
+        let signature = FuncType {
+            params: function.params.iter().map(|(_, ty)| value_type(ty)).collect::<Option<_>>().ok_or_else(
+                || CodegenError::new(format!("function '{}' has a void parameter", function.name)),
+            )?,
+            results: value_type(&function.return_type).into_iter().collect(),
+        };
+        function_types.push(types.intern(&signature));
+        functions.insert(function.name.as_str(), (import_count + i as u32, signature));
+    }
+    for (name, (index, signature)) in imports.drain() {
+        functions.insert(name, (index, signature));
+    }
+
+    let mut bodies = Vec::with_capacity(ir_module.functions.len());
+    for function in &ir_module.functions {
+        let encoder = FunctionEncoder::new(function, &functions, &mut data)?;
+        bodies.push(encoder.encode()?);
+    }
+
+    let mut out = b"\0asm".to_vec();
+    out.extend_from_slice(&1u32.to_le_bytes());
+
+    // Type section
+    let mut section = Vec::new();
+    uleb(&mut section, types.entries.len() as u64);
+    for ty in &types.entries {
+        section.push(0x60);
+        vec_bytes(&mut section, &ty.params);
+        vec_bytes(&mut section, &ty.results);
+    }
+    write_section(&mut out, 1, &section);
+
+    // Import section, in index order
+    let mut imported: Vec<(&str, u32, &FuncType)> = functions
+        .iter()
+        .filter(|(_, (index, _))| *index < import_count)
+        .map(|(name, (index, ty))| (*name, *index, ty))
+        .collect();
+    imported.sort_by_key(|(_, index, _)| *index);
+    let mut section = Vec::new();
+    uleb(&mut section, imported.len() as u64);
+    for (name, _, ty) in imported {
+        name_bytes(&mut section, IMPORT_MODULE);
+        name_bytes(&mut section, name);
+        section.push(0x00);
+        uleb(&mut section, types.index_of(ty) as u64);
+    }
+    write_section(&mut out, 2, &section);
+
+    // Function section
+    let mut section = Vec::new();
+    uleb(&mut section, function_types.len() as u64);
+    for type_index in &function_types {
+        uleb(&mut section, *type_index as u64);
+    }
+    write_section(&mut out, 3, &section);
+
+    // Memory section: one memory large enough for the string data
+    let data_end = DATA_START + data.bytes.len() as u32;
+    let pages = data_end.div_ceil(WASM_PAGE_SIZE).max(1);
+    let mut section = Vec::new();
+    uleb(&mut section, 1);
+    section.push(0x00);
+    uleb(&mut section, pages as u64);
+    write_section(&mut out, 5, &section);
+
+    // Export section: the memory, `main` and public functions
+    let mut exports: Vec<(&str, u8, u32)> = vec![("memory", 0x02, 0)];
+    for (i, function) in ir_module.functions.iter().enumerate() {
+        if function.name == "main" || function.is_public {
+            exports.push((function.name.as_str(), 0x00, import_count + i as u32));
+        }
+    }
+    let mut section = Vec::new();
+    uleb(&mut section, exports.len() as u64);
+    for (name, kind, index) in exports {
+        name_bytes(&mut section, name);
+        section.push(kind);
+        uleb(&mut section, index as u64);
+    }
+    write_section(&mut out, 7, &section);
+
+    // Code section
+    let mut section = Vec::new();
+    uleb(&mut section, bodies.len() as u64);
+    for body in &bodies {
+        vec_bytes(&mut section, body);
+    }
+    write_section(&mut out, 10, &section);
+
+    // Data section
+    if !data.bytes.is_empty() {
+        let mut section = Vec::new();
+        uleb(&mut section, 1);
+        section.push(0x00);
+        section.push(0x41);
+        sleb(&mut section, DATA_START as i64);
+        section.push(0x0B);
+        vec_bytes(&mut section, &data.bytes);
+        write_section(&mut out, 11, &section);
+    }
+
+    Ok(out)
+}
+
+/// A wasm function type: parameter and result value types
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FuncType {
+    params: Vec<u8>,
+    results: Vec<u8>,
+}
+
+#[derive(Default)]
+struct TypeTable {
+    entries: Vec<FuncType>,
+}
+
+impl TypeTable {
+    fn intern(&mut self, ty: &FuncType) -> usize {
+        if let Some(index) = self.entries.iter().position(|t| t == ty) {
+            return index;
+        }
+        self.entries.push(ty.clone());
+        self.entries.len() - 1
+    }
+
+    fn index_of(&self, ty: &FuncType) -> usize {
+        self.entries.iter().position(|t| t == ty).expect("function type was interned")
+    }
+}
+
+/// String literals laid out in linear memory
+#[derive(Default)]
+struct DataSegment {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl DataSegment {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&offset) = self.offsets.get(s) {
+            return offset;
+        }
+        let offset = DATA_START + self.bytes.len() as u32;
+        self.bytes.extend_from_slice(s.as_bytes());
+        self.bytes.push(0);
+        self.offsets.insert(s.to_string(), offset);
+        offset
+    }
+}
+
+/// The wasm value type of an IR type; pointers are 32-bit memory offsets
+fn value_type(ty: &IrType) -> Option<u8> {
+    match ty {
+        IrType::Void => None,
+        IrType::I64 => Some(I64),
+        IrType::F64 => Some(F64),
+        _ => Some(I32),
+    }
+}
+
+/// Find every call to a function outside the module and give it an import.
+/// The signature comes from the first call site; later calls are converted
+/// to it.
+fn collect_imports<'a>(
+    ir_module: &'a IrModule,
+    types: &mut TypeTable,
+) -> Result<HashMap<&'a str, (u32, FuncType)>, CodegenError> {
+    let mut imports: HashMap<&str, (u32, FuncType)> = HashMap::new();
+    let mut add = |name: &'a str, ty: FuncType, imports: &mut HashMap<&'a str, (u32, FuncType)>| {
+        if !imports.contains_key(name) {
+            types.intern(&ty);
+            let index = imports.len() as u32;
+            imports.insert(name, (index, ty));
+        }
+    };
+
+    for function in &ir_module.functions {
+        for block in &function.blocks {
+            for instruction in &block.instructions {
+                match instruction {
+                    Instruction::Call { dest, func: Value::Const(Constant::Str(name)), args } => {
+                        if ir_module.find_function(name).is_some() {
+                            continue;
+                        }
+                        if !HOST_FUNCTIONS.contains(&name.as_str()) {
+                            // Rejected where it is called, so that errors come
+                            // out in program order
+                            continue;
+                        }
+                        let params = args
+                            .iter()
+                            .map(|arg| function.value_type(arg).as_ref().and_then(value_type))
+                            .collect::<Option<Vec<_>>>()
+                            .ok_or_else(|| {
+                                CodegenError::new(format!("call to '{}' has an argument of unknown type", name))
+                            })?;
+                        let results = dest
+                            .as_ref()
+                            .and_then(|place| function.value_type(&place.base))
+                            .as_ref()
+                            .and_then(value_type)
+                            .into_iter()
+                            .collect();
+                        add(name, FuncType { params, results }, &mut imports);
+                    }
+                    Instruction::Assign { value: RValue::BinaryOp { op: BinOp::Mod, left, .. }, .. }
+                        if function.value_type(left) == Some(IrType::F64) =>
+                    {
+                        let ty = FuncType { params: vec![F64, F64], results: vec![F64] };
+                        add("zaco_js_mod", ty, &mut imports);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(imports)
+}
+
+fn unsupported(what: &str) -> CodegenError {
+    CodegenError::new(format!("WebAssembly output does not support {} yet", what))
+}
+
+/// Encodes one IR function as a wasm function body.
+///
+/// The control flow graph is kept as is: a `$pc` local holds the index of
+/// the block to run next, and a loop dispatches on it with `br_table` into
+/// one nested block per IR block.
+struct FunctionEncoder<'a> {
+    function: &'a IrFunction,
+    functions: &'a HashMap<&'a str, (u32, FuncType)>,
+    data: &'a mut DataSegment,
+    locals: HashMap<LocalId, (u32, u8)>,
+    temps: HashMap<TempId, (u32, u8)>,
+    block_positions: HashMap<BlockId, u32>,
+    local_decls: Vec<u8>,
+    pc: u32,
+    code: Vec<u8>,
+}
+
+impl<'a> FunctionEncoder<'a> {
+    fn new(
+        function: &'a IrFunction,
+        functions: &'a HashMap<&'a str, (u32, FuncType)>,
+        data: &'a mut DataSegment,
+    ) -> Result<Self, CodegenError> {
+        let mut locals = HashMap::new();
+        let mut local_decls = Vec::new();
+        let mut next = 0u32;
+        for (id, ty) in &function.params {
+            let ty = value_type(ty).ok_or_else(|| unsupported("a void parameter"))?;
+            locals.insert(*id, (next, ty));
+            next += 1;
+        }
+        for (id, ty) in &function.locals {
+            if locals.contains_key(id) {
+                continue;
+            }
+            // Void locals never hold a value; give them a slot anyway so
+            // every local has an index
+            let ty = value_type(ty).unwrap_or(I32);
+            locals.insert(*id, (next, ty));
+            local_decls.push(ty);
+            next += 1;
+        }
+        let mut temps = HashMap::new();
+        for (id, ty) in &function.temps {
+            let ty = value_type(ty).unwrap_or(I32);
+            temps.insert(*id, (next, ty));
+            local_decls.push(ty);
+            next += 1;
+        }
+        let pc = next;
+        local_decls.push(I32);
+
+        let block_positions =
+            function.blocks.iter().enumerate().map(|(i, block)| (block.id, i as u32)).collect();
+
+        Ok(Self {
+            function,
+            functions,
+            data,
+            locals,
+            temps,
+            block_positions,
+            local_decls,
+            pc,
+            code: Vec::new(),
+        })
+    }
+
+    fn encode(mut self) -> Result<Vec<u8>, CodegenError> {
+        let count = self.function.blocks.len() as u32;
+        let entry = self.block_position(self.function.entry_block)?;
+
+        self.i32_const(entry as i32);
+        self.local_set(self.pc);
+        // loop
+        self.code.extend_from_slice(&[0x03, 0x40]);
+        for _ in 0..count {
+            self.code.extend_from_slice(&[0x02, 0x40]);
+        }
+        self.local_get(self.pc);
+        self.code.push(0x0E);
+        uleb(&mut self.code, count as u64);
+        for depth in 0..count {
+            uleb(&mut self.code, depth as u64);
+        }
+        uleb(&mut self.code, count.saturating_sub(1) as u64);
+
+        for (position, block) in self.function.blocks.iter().enumerate() {
+            // Closing block `position` puts its code right after it, where
+            // the loop is `count - 1 - position` labels out
+            self.code.push(0x0B);
+            let loop_depth = count - 1 - position as u32;
+            for instruction in &block.instructions {
+                self.instruction(instruction)?;
+            }
+            self.terminator(&block.terminator, loop_depth)?;
+        }
+        self.code.push(0x0B);
+        self.code.push(0x00);
+        self.code.push(0x0B);
+
+        let mut body = Vec::new();
+        let mut groups: Vec<(u32, u8)> = Vec::new();
+        for &ty in &self.local_decls {
+            match groups.last_mut() {
+                Some((n, last)) if *last == ty => *n += 1,
+                _ => groups.push((1, ty)),
+            }
+        }
+        uleb(&mut body, groups.len() as u64);
+        for (n, ty) in groups {
+            uleb(&mut body, n as u64);
+            body.push(ty);
+        }
+        body.extend_from_slice(&self.code);
+        Ok(body)
+    }
+
+    fn block_position(&self, id: BlockId) -> Result<u32, CodegenError> {
+        self.block_positions
+            .get(&id)
+            .copied()
+            .ok_or_else(|| CodegenError::new(format!("block {} not found in '{}'", id, self.function.name)))
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) -> Result<(), CodegenError> {
+        match instruction {
+            Instruction::Assign { dest, value } => {
+                let (index, ty) = self.place(dest)?;
+                let from = self.rvalue(value)?;
+                self.convert(from, ty);
+                self.local_set(index);
+            }
+            Instruction::Call { dest, func, args } => {
+                let name = match func {
+                    Value::Const(Constant::Str(name)) => name.as_str(),
+                    _ => return Err(unsupported("calling a function value")),
+                };
+                let (index, ty) = self
+                    .functions
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| unsupported(&format!("the runtime function '{}'", name)))?;
+                if args.len() != ty.params.len() {
+                    return Err(CodegenError::new(format!(
+                        "'{}' is called with {} arguments but was imported with {}",
+                        name,
+                        args.len(),
+                        ty.params.len()
+                    )));
+                }
+                for (arg, &param) in args.iter().zip(&ty.params) {
+                    let from = self.value(arg)?;
+                    self.convert(from, param);
+                }
+                self.code.push(0x10);
+                uleb(&mut self.code, index as u64);
+                if let Some(&result) = ty.results.first() {
+                    match dest {
+                        Some(dest) => {
+                            let (dest_index, dest_ty) = self.place(dest)?;
+                            self.convert(result, dest_ty);
+                            self.local_set(dest_index);
+                        }
+                        None => self.code.push(0x1A),
+                    }
+                }
+            }
+            // Nothing is heap allocated, so there are no counts to keep
+            Instruction::RefCount { .. } => {}
+            // Control flow lives in the block terminator
+            Instruction::Return(_) | Instruction::Branch { .. } | Instruction::Jump(_) => {}
+            Instruction::Alloc { .. } => return Err(unsupported("heap allocation")),
+            Instruction::Free { .. } => return Err(unsupported("freeing memory")),
+            Instruction::Clone { .. } => return Err(unsupported("cloning values")),
+            Instruction::Store { .. } | Instruction::Load { .. } => {
+                return Err(unsupported("memory access through pointers"))
+            }
+        }
+        Ok(())
+    }
+
+    fn terminator(&mut self, terminator: &Terminator, loop_depth: u32) -> Result<(), CodegenError> {
+        match terminator {
+            Terminator::Return(value) => {
+                match (value, value_type(&self.function.return_type)) {
+                    (Some(value), Some(ret)) => {
+                        let from = self.value(value)?;
+                        self.convert(from, ret);
+                    }
+                    (None, Some(ret)) => self.zero(ret),
+                    (_, None) => {}
+                }
+                self.code.push(0x0F);
+            }
+            Terminator::Jump(target) => {
+                let target = self.block_position(*target)?;
+                self.i32_const(target as i32);
+                self.local_set(self.pc);
+                self.br(loop_depth);
+            }
+            Terminator::Branch { cond, then_block, else_block } => {
+                let then_block = self.block_position(*then_block)?;
+                let else_block = self.block_position(*else_block)?;
+                self.i32_const(then_block as i32);
+                self.i32_const(else_block as i32);
+                let from = self.value(cond)?;
+                self.truthy(from);
+                self.code.push(0x1B);
+                self.local_set(self.pc);
+                self.br(loop_depth);
+            }
+            Terminator::Unreachable => self.code.push(0x00),
+        }
+        Ok(())
+    }
+
+    fn place(&self, place: &Place) -> Result<(u32, u8), CodegenError> {
+        if !place.projections.is_empty() {
+            return Err(unsupported("field and element access"));
+        }
+        match &place.base {
+            Value::Local(id) => self.locals.get(id).copied(),
+            Value::Temp(id) => self.temps.get(id).copied(),
+            Value::Const(_) => None,
+        }
+        .ok_or_else(|| CodegenError::new(format!("invalid assignment target in '{}'", self.function.name)))
+    }
+
+    /// Push a value and return its wasm type
+    fn value(&mut self, value: &Value) -> Result<u8, CodegenError> {
+        match value {
+            Value::Local(_) | Value::Temp(_) => {
+                let (index, ty) = self.place(&Place::from_value(value.clone()))?;
+                self.local_get(index);
+                Ok(ty)
+            }
+            Value::Const(Constant::I64(n)) => {
+                self.code.push(0x42);
+                sleb(&mut self.code, *n);
+                Ok(I64)
+            }
+            Value::Const(Constant::F64(n)) => {
+                self.f64_const(*n);
+                Ok(F64)
+            }
+            Value::Const(Constant::Bool(b)) => {
+                self.i32_const(*b as i32);
+                Ok(I32)
+            }
+            Value::Const(Constant::Str(s)) => {
+                let offset = self.data.intern(s);
+                self.i32_const(offset as i32);
+                Ok(I32)
+            }
+            Value::Const(Constant::Null) => {
+                self.i32_const(0);
+                Ok(I32)
+            }
+            Value::Const(Constant::Undefined) => Err(unsupported("undefined")),
+            Value::Const(Constant::FuncAddr(_)) => Err(unsupported("function references")),
+            Value::Const(Constant::GlobalAddr(_)) => Err(unsupported("module globals")),
+        }
+    }
+
+    /// Push the result of an rvalue and return its wasm type
+    fn rvalue(&mut self, value: &RValue) -> Result<u8, CodegenError> {
+        match value {
+            RValue::Use(value) => self.value(value),
+            RValue::BinaryOp { op, left, right } => self.binary_op(*op, left, right),
+            RValue::UnaryOp { op, operand } => self.unary_op(*op, operand),
+            RValue::Cast { value, ty } => {
+                let from = self.value(value)?;
+                let to = value_type(ty).ok_or_else(|| unsupported("casting to void"))?;
+                self.convert(from, to);
+                Ok(to)
+            }
+            RValue::StructInit { .. } => Err(unsupported("objects")),
+            RValue::ArrayInit(_) => Err(unsupported("arrays")),
+            RValue::StrConcat(_) => Err(unsupported("string concatenation")),
+        }
+    }
+
+    fn binary_op(&mut self, op: BinOp, left: &Value, right: &Value) -> Result<u8, CodegenError> {
+        let ty = self
+            .function
+            .value_type(left)
+            .as_ref()
+            .and_then(value_type)
+            .ok_or_else(|| CodegenError::new(format!("operand of unknown type in '{}'", self.function.name)))?;
+
+        // Bitwise operators on numbers work on their 32-bit integer values
+        if ty == F64 && matches!(op, BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr) {
+            self.operand(left, F64)?;
+            self.int32();
+            self.operand(right, F64)?;
+            self.int32();
+            self.code.push(int_opcode(op, I32));
+            self.code.push(0xB7);
+            return Ok(F64);
+        }
+
+        if ty == F64 && op == BinOp::Mod {
+            self.operand(left, F64)?;
+            self.operand(right, F64)?;
+            let (index, _) = self.functions["zaco_js_mod"];
+            self.code.push(0x10);
+            uleb(&mut self.code, index as u64);
+            return Ok(F64);
+        }
+
+        // Integer division and remainder by zero give 0 instead of trapping
+        if ty == I64 && matches!(op, BinOp::Div | BinOp::Mod) {
+            self.code.extend_from_slice(&[0x42, 0x00]);
+            self.operand(left, I64)?;
+            self.code.extend_from_slice(&[0x42, 0x01]);
+            self.operand(right, I64)?;
+            self.operand(right, I64)?;
+            self.code.extend_from_slice(&[0x50, 0x1B]);
+            self.code.push(if op == BinOp::Div { 0x7F } else { 0x81 });
+            self.operand(right, I64)?;
+            self.code.extend_from_slice(&[0x50, 0x1B]);
+            return Ok(I64);
+        }
+
+        self.operand(left, ty)?;
+        self.operand(right, ty)?;
+        let result = match op {
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => I32,
+            _ => ty,
+        };
+        let opcode = if ty == F64 {
+            match op {
+                BinOp::Add => 0xA0,
+                BinOp::Sub => 0xA1,
+                BinOp::Mul => 0xA2,
+                BinOp::Div => 0xA3,
+                BinOp::Eq => 0x61,
+                BinOp::Ne => 0x62,
+                BinOp::Lt => 0x63,
+                BinOp::Gt => 0x64,
+                BinOp::Le => 0x65,
+                BinOp::Ge => 0x66,
+                _ => return Err(unsupported(&format!("'{}' on numbers", op))),
+            }
+        } else {
+            int_opcode(op, ty)
+        };
+        self.code.push(opcode);
+        Ok(result)
+    }
+
+    fn unary_op(&mut self, op: UnOp, operand: &Value) -> Result<u8, CodegenError> {
+        match op {
+            UnOp::Neg => {
+                let ty = self.function.value_type(operand).as_ref().and_then(value_type).unwrap_or(F64);
+                match ty {
+                    F64 => {
+                        self.operand(operand, F64)?;
+                        self.code.push(0x9A);
+                    }
+                    I64 => {
+                        self.code.extend_from_slice(&[0x42, 0x00]);
+                        self.operand(operand, I64)?;
+                        self.code.push(0x7D);
+                    }
+                    _ => {
+                        self.i32_const(0);
+                        self.operand(operand, I32)?;
+                        self.code.push(0x6B);
+                    }
+                }
+                Ok(ty)
+            }
+            UnOp::Not => {
+                let from = self.value(operand)?;
+                self.truthy(from);
+                self.code.push(0x45);
+                Ok(I32)
+            }
+            UnOp::BitNot => {
+                let ty = self.value(operand)?;
+                match ty {
+                    F64 => {
+                        self.int32();
+                        self.i32_const(-1);
+                        self.code.extend_from_slice(&[0x73, 0xB7]);
+                    }
+                    I64 => {
+                        self.code.extend_from_slice(&[0x42, 0x7F, 0x85]);
+                    }
+                    _ => {
+                        self.i32_const(-1);
+                        self.code.push(0x73);
+                    }
+                }
+                Ok(ty)
+            }
+        }
+    }
+
+    /// Push a value converted to `ty`
+    fn operand(&mut self, value: &Value, ty: u8) -> Result<(), CodegenError> {
+        let from = self.value(value)?;
+        self.convert(from, ty);
+        Ok(())
+    }
+
+    /// Convert the value on top of the stack between wasm types
+    fn convert(&mut self, from: u8, to: u8) {
+        match (from, to) {
+            (F64, I64) => self.code.extend_from_slice(&[0xFC, 0x06]),
+            (F64, I32) => self.int32(),
+            (I64, F64) => self.code.push(0xB9),
+            (I64, I32) => self.code.push(0xA7),
+            (I32, F64) => self.code.push(0xB8),
+            (I32, I64) => self.code.push(0xAD),
+            _ => {}
+        }
+    }
+
+    /// Turn the value on top of the stack into an i32 condition
+    fn truthy(&mut self, from: u8) {
+        match from {
+            // |x| > 0 is false for both 0 and NaN
+            F64 => {
+                self.code.push(0x99);
+                self.f64_const(0.0);
+                self.code.push(0x64);
+            }
+            I64 => self.code.extend_from_slice(&[0x50, 0x45]),
+            _ => {}
+        }
+    }
+
+    /// JavaScript's ToInt32 of a number: its integer part modulo 2^32
+    fn int32(&mut self) {
+        self.code.extend_from_slice(&[0xFC, 0x06, 0xA7]);
+    }
+
+    fn zero(&mut self, ty: u8) {
+        match ty {
+            F64 => self.f64_const(0.0),
+            I64 => self.code.extend_from_slice(&[0x42, 0x00]),
+            _ => self.i32_const(0),
+        }
+    }
+
+    fn i32_const(&mut self, n: i32) {
+        self.code.push(0x41);
+        sleb(&mut self.code, n as i64);
+    }
+
+    fn f64_const(&mut self, n: f64) {
+        self.code.push(0x44);
+        self.code.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn local_get(&mut self, index: u32) {
+        self.code.push(0x20);
+        uleb(&mut self.code, index as u64);
+    }
+
+    fn local_set(&mut self, index: u32) {
+        self.code.push(0x21);
+        uleb(&mut self.code, index as u64);
+    }
+
+    fn br(&mut self, depth: u32) {
+        self.code.push(0x0C);
+        uleb(&mut self.code, depth as u64);
+    }
+}
+
+/// Opcode of an integer binary operator for i32 or i64 operands
+fn int_opcode(op: BinOp, ty: u8) -> u8 {
+    let (i32_op, i64_op) = match op {
+        BinOp::Add => (0x6A, 0x7C),
+        BinOp::Sub => (0x6B, 0x7D),
+        BinOp::Mul => (0x6C, 0x7E),
+        BinOp::Div => (0x6D, 0x7F),
+        BinOp::Mod => (0x6F, 0x81),
+        BinOp::Eq => (0x46, 0x51),
+        BinOp::Ne => (0x47, 0x52),
+        BinOp::Lt => (0x48, 0x53),
+        BinOp::Gt => (0x4A, 0x55),
+        BinOp::Le => (0x4C, 0x57),
+        BinOp::Ge => (0x4E, 0x59),
+        BinOp::And | BinOp::BitAnd => (0x71, 0x83),
+        BinOp::Or | BinOp::BitOr => (0x72, 0x84),
+        BinOp::BitXor => (0x73, 0x85),
+        BinOp::Shl => (0x74, 0x86),
+        BinOp::Shr => (0x75, 0x87),
+    };
+    if ty == I64 { i64_op } else { i32_op }
+}
+
+fn write_section(out: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    out.push(id);
+    vec_bytes(out, contents);
+}
+
+fn vec_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    uleb(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn name_bytes(out: &mut Vec<u8>, name: &str) {
+    vec_bytes(out, name.as_bytes());
+}
+
+fn uleb(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn sleb(out: &mut Vec<u8>, mut n: i64) {
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        let done = (n == 0 && byte & 0x40 == 0) || (n == -1 && byte & 0x40 != 0);
+        if done {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zaco_ir::FuncId;
+
+    #[test]
+    fn test_leb128_encoding() {
+        let mut out = Vec::new();
+        uleb(&mut out, 624485);
+        assert_eq!(out, [0xE5, 0x8E, 0x26]);
+        out.clear();
+        sleb(&mut out, -123456);
+        assert_eq!(out, [0xC0, 0xBB, 0x78]);
+        out.clear();
+        sleb(&mut out, 64);
+        assert_eq!(out, [0xC0, 0x00]);
+    }
+
+    #[test]
+    fn test_module_exports_main_and_memory() {
+        let mut module = IrModule::new();
+        let mut main = IrFunction::new(FuncId(0), "main".to_string(), vec![], IrType::I64);
+        let entry = main.new_block();
+        main.block_mut(entry).set_terminator(Terminator::Return(Some(Value::Const(Constant::I64(0)))));
+        module.add_function(main);
+
+        let bytes = compile_module(&module).unwrap();
+        assert_eq!(&bytes[..8], b"\0asm\x01\0\0\0");
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("memory") && text.contains("main"));
+    }
+
+    #[test]
+    fn test_heap_objects_are_unsupported() {
+        let mut module = IrModule::new();
+        let mut main = IrFunction::new(FuncId(0), "main".to_string(), vec![], IrType::I64);
+        let temp = main.add_temp(IrType::Array(Box::new(IrType::F64)));
+        let entry = main.new_block();
+        let block = main.block_mut(entry);
+        block.push_instruction(Instruction::Assign {
+            dest: Place::from_temp(temp),
+            value: RValue::ArrayInit(vec![]),
+        });
+        block.set_terminator(Terminator::Return(Some(Value::Const(Constant::I64(0)))));
+        module.add_function(main);
+
+        let err = compile_module(&module).unwrap_err();
+        assert!(err.message.contains("arrays"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_runtime_functions_missing_from_host_are_unsupported() {
+        let mut module = IrModule::new();
+        let mut main = IrFunction::new(FuncId(0), "main".to_string(), vec![], IrType::I64);
+        let temp = main.add_temp(IrType::I64);
+        let entry = main.new_block();
+        let block = main.block_mut(entry);
+        block.push_instruction(Instruction::Call {
+            dest: Some(Place::from_temp(temp)),
+            func: Value::Const(Constant::Str("zaco_str_len".to_string())),
+            args: vec![Value::Const(Constant::Str("abc".to_string()))],
+        });
+        block.set_terminator(Terminator::Return(Some(Value::Const(Constant::I64(0)))));
+        module.add_function(main);
+
+        let err = compile_module(&module).unwrap_err();
+        assert!(err.message.contains("'zaco_str_len'"), "{}", err.message);
+    }
+}
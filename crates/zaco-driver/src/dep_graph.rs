@@ -1,7 +1,8 @@
 //! Dependency graph for multi-file compilation
 
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
 
 /// Node in the dependency graph representing a module
 #[derive(Debug, Clone)]
@@ -10,8 +11,42 @@ pub struct ModuleNode {
     pub path: PathBuf,
     /// Modules this module imports from (dependencies)
     pub dependencies: Vec<PathBuf>,
-    /// Exported symbols from this module
+    /// Modules imported or re-exported from only for their types. They are
+    /// erased at run time, so they aren't dependencies.
+    pub type_dependencies: Vec<PathBuf>,
+    /// Built-in modules (fs, path, ...) imported; the runtime provides
+    /// them, so they have no node
+    pub builtins: Vec<String>,
+    /// Exported symbols from this module that exist at run time
     pub exports: HashSet<String>,
+    /// Exported names usable as types: interfaces, type aliases, classes,
+    /// enums and type-only exports. A name here but not in `exports` is
+    /// erased at run time.
+    pub type_exports: HashSet<String>,
+    /// Names re-exported from other modules, folded into `exports` and
+    /// `type_exports` by `resolve_reexports`
+    pub reexports: Vec<ReExport>,
+    /// Whether the export sets are complete; false when a re-export comes
+    /// from a module outside the graph, such as an npm package
+    pub exports_known: bool,
+    /// Whether the module's top-level code awaits. Its initialization then
+    /// waits on the modules it imports, so it cannot be part of a cycle.
+    pub top_level_await: bool,
+}
+
+/// An `export ... from` clause of a module
+#[derive(Debug, Clone)]
+pub enum ReExport {
+    /// `export { imported as exported } from "source"`, or the type-only
+    /// `export type { .. }` and `export { type .. }` forms
+    Named {
+        source: PathBuf,
+        imported: String,
+        exported: String,
+        type_only: bool,
+    },
+    /// `export * from "source"` and `export type * from "source"`
+    All { source: PathBuf, type_only: bool },
 }
 
 /// Dependency graph for tracking module dependencies
@@ -41,11 +76,116 @@ impl DepGraph {
         let node = ModuleNode {
             path: path.clone(),
             dependencies,
+            type_dependencies: Vec::new(),
+            builtins: Vec::new(),
             exports,
+            type_exports: HashSet::new(),
+            reexports: Vec::new(),
+            exports_known: true,
+            top_level_await: false,
         };
         self.modules.insert(path, node);
     }
 
+    /// Record the type names a module exports and what it re-exports
+    pub fn set_type_exports(&mut self, path: &PathBuf, type_exports: HashSet<String>, reexports: Vec<ReExport>) {
+        if let Some(node) = self.modules.get_mut(path) {
+            node.type_exports = type_exports;
+            node.reexports = reexports;
+        }
+    }
+
+    /// Record the imports of a module that aren't dependencies: type-only
+    /// modules and built-in modules
+    pub fn set_other_imports(&mut self, path: &PathBuf, type_dependencies: Vec<PathBuf>, builtins: Vec<String>) {
+        if let Some(node) = self.modules.get_mut(path) {
+            node.type_dependencies = type_dependencies;
+            node.builtins = builtins;
+        }
+    }
+
+    /// Mark a module's exports as incomplete (see `ModuleNode::exports_known`)
+    pub fn mark_exports_unknown(&mut self, path: &PathBuf) {
+        if let Some(node) = self.modules.get_mut(path) {
+            node.exports_known = false;
+        }
+    }
+
+    /// Mark a module as awaiting at its top level (see `ModuleNode::top_level_await`)
+    pub fn mark_top_level_await(&mut self, path: &PathBuf) {
+        if let Some(node) = self.modules.get_mut(path) {
+            node.top_level_await = true;
+        }
+    }
+
+    /// Fold re-exported names into the export sets of the modules that
+    /// re-export them, following chains until nothing changes. A name keeps
+    /// the kind it has in its source: re-exporting an interface doesn't make
+    /// it a value, and a type-only re-export of a value is only a type.
+    pub fn resolve_reexports(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let paths: Vec<PathBuf> = self.modules.keys().cloned().collect();
+            for path in paths {
+                let node = &self.modules[&path];
+                let mut values = Vec::new();
+                let mut types = Vec::new();
+                let mut known = node.exports_known;
+                for reexport in &node.reexports {
+                    let (ReExport::Named { source, type_only, .. } | ReExport::All { source, type_only }) = reexport;
+                    let Some(target) = self.modules.get(source) else {
+                        known = false;
+                        continue;
+                    };
+                    known &= target.exports_known;
+                    match reexport {
+                        ReExport::Named { imported, exported, .. } => {
+                            if target.exports.contains(imported) {
+                                if *type_only {
+                                    types.push(exported.clone());
+                                } else {
+                                    values.push(exported.clone());
+                                }
+                            }
+                            if target.type_exports.contains(imported) {
+                                types.push(exported.clone());
+                            }
+                        }
+                        ReExport::All { .. } => {
+                            let names = target.exports.iter().filter(|name| *name != "default");
+                            if *type_only {
+                                types.extend(names.cloned());
+                            } else {
+                                values.extend(names.cloned());
+                            }
+                            types.extend(target.type_exports.iter().filter(|name| *name != "default").cloned());
+                        }
+                    }
+                }
+
+                let node = self.modules.get_mut(&path).unwrap();
+                for name in values {
+                    changed |= node.exports.insert(name);
+                }
+                for name in types {
+                    changed |= node.type_exports.insert(name);
+                }
+                if node.exports_known && !known {
+                    node.exports_known = false;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Names a module exports only as types, or None when its exports
+    /// aren't fully known
+    pub fn type_only_exports(&self, path: &PathBuf) -> Option<HashSet<String>> {
+        let node = self.modules.get(path).filter(|node| node.exports_known)?;
+        Some(node.type_exports.difference(&node.exports).cloned().collect())
+    }
+
     /// Get a module node by path
     pub fn get_module(&self, path: &PathBuf) -> Option<&ModuleNode> {
         self.modules.get(path)
@@ -64,14 +204,25 @@ impl DepGraph {
         for path in self.modules.keys() {
             if !visited.contains(path) {
                 if let Some(cycle) = self.detect_cycle_util(path, &mut visited, &mut rec_stack) {
-                    return Err(format!(
+                    let mut message = format!(
                         "Circular dependency detected: {}",
                         cycle
                             .iter()
                             .map(|p| p.display().to_string())
                             .collect::<Vec<_>>()
                             .join(" -> ")
-                    ));
+                    );
+                    // A module in the cycle awaiting at its top level would
+                    // wait on itself; name a dependency over the entry, which runs last.
+                    let awaits = |p: &&PathBuf| self.modules.get(*p).is_some_and(|node| node.top_level_await);
+                    let awaiting: Vec<&PathBuf> = cycle.iter().filter(awaits).collect();
+                    if let Some(awaiting) = awaiting.iter().find(|p| Some(**p) != self.entry()).or(awaiting.first()) {
+                        message.push_str(&format!(
+                            " ({} uses top-level await, which would deadlock on the cycle)",
+                            awaiting.display()
+                        ));
+                    }
+                    return Err(message);
                 }
             }
         }
@@ -179,6 +330,189 @@ impl DepGraph {
     pub fn entry(&self) -> Option<&PathBuf> {
         self.entry.as_ref()
     }
+
+    /// Render the graph in Graphviz DOT format. Modules are labelled by
+    /// their path relative to the entry's directory; the entry is bold, npm
+    /// packages are blue, built-in modules are dashed ellipses, type-only
+    /// imports are dashed edges and modules and imports forming a cycle are
+    /// red. With `max_depth`, only modules at most that many imports away
+    /// from the entry are shown.
+    pub fn to_dot(&self, max_depth: Option<usize>) -> String {
+        let modules = self.modules_by_depth(max_depth);
+        let shown: HashSet<&PathBuf> = modules.iter().map(|(node, _)| &node.path).collect();
+        let root = self.label_root();
+        let label = |path: &Path| dot_quote(&relative_label(path, root));
+
+        let mut out = String::from("digraph deps {\n    rankdir=LR;\n    node [shape=box];\n");
+        let mut builtins: Vec<&String> = Vec::new();
+        for (node, depth) in &modules {
+            let mut attrs = Vec::new();
+            if self.entry.as_ref() == Some(&node.path) {
+                attrs.push("style=bold");
+            }
+            if self.in_cycle(&node.path) {
+                attrs.push("color=red");
+            } else if is_package(&node.path) {
+                attrs.push("color=blue");
+            }
+            let _ = writeln!(out, "    {}{};", label(&node.path), dot_attrs(&attrs));
+            if max_depth.is_none_or(|max| *depth < max) {
+                for name in &node.builtins {
+                    if !builtins.contains(&name) {
+                        builtins.push(name);
+                    }
+                }
+            }
+        }
+        for name in &builtins {
+            let _ = writeln!(out, "    {} [shape=ellipse, style=dashed];", dot_quote(name));
+        }
+
+        for (node, depth) in &modules {
+            for dep in node.dependencies.iter().filter(|dep| shown.contains(dep)) {
+                let attrs: &[&str] = if self.reaches(dep, &node.path) { &["color=red"] } else { &[] };
+                let _ = writeln!(out, "    {} -> {}{};", label(&node.path), label(dep), dot_attrs(attrs));
+            }
+            for dep in node.type_dependencies.iter().filter(|dep| shown.contains(dep)) {
+                let _ = writeln!(out, "    {} -> {} [style=dashed];", label(&node.path), label(dep));
+            }
+            if max_depth.is_none_or(|max| *depth < max) {
+                for name in &node.builtins {
+                    let _ = writeln!(out, "    {} -> {};", label(&node.path), dot_quote(name));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Render the graph as JSON: the entry and, for each module within
+    /// `max_depth` imports of it, its depth, imports and exports. Paths are
+    /// relative to the entry's directory, as in `to_dot`.
+    pub fn to_json(&self, max_depth: Option<usize>) -> String {
+        let modules = self.modules_by_depth(max_depth);
+        let shown: HashSet<&PathBuf> = modules.iter().map(|(node, _)| &node.path).collect();
+        let root = self.label_root();
+        let paths = |paths: &[PathBuf]| {
+            json_array(paths.iter().filter(|path| shown.contains(path)).map(|path| relative_label(path, root)))
+        };
+        let sorted = |names: &HashSet<String>| {
+            let mut names: Vec<String> = names.iter().cloned().collect();
+            names.sort();
+            json_array(names.into_iter())
+        };
+
+        let entry = self.entry.as_deref().map_or("null".to_string(), |path| json_string(&relative_label(path, root)));
+        let mut out = format!("{{\n  \"entry\": {},\n  \"modules\": [", entry);
+        for (i, (node, depth)) in modules.iter().enumerate() {
+            let _ = write!(
+                out,
+                "{}\n    {{\"path\": {}, \"depth\": {}, \"package\": {}, \"in_cycle\": {}, \"dependencies\": {}, \
+                 \"type_dependencies\": {}, \"builtins\": {}, \"exports\": {}, \"type_exports\": {}}}",
+                if i == 0 { "" } else { "," },
+                json_string(&relative_label(&node.path, root)),
+                depth,
+                is_package(&node.path),
+                self.in_cycle(&node.path),
+                paths(&node.dependencies),
+                paths(&node.type_dependencies),
+                json_array(node.builtins.iter().cloned()),
+                sorted(&node.exports),
+                sorted(&node.type_exports),
+            );
+        }
+        out.push_str("\n  ]\n}\n");
+        out
+    }
+
+    /// Modules reachable from the entry through any import, breadth first
+    /// and in import order, with their distance from the entry
+    fn modules_by_depth(&self, max_depth: Option<usize>) -> Vec<(&ModuleNode, usize)> {
+        let mut result = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<(&PathBuf, usize)> = self.entry.iter().map(|entry| (entry, 0)).collect();
+        while let Some((path, depth)) = queue.pop_front() {
+            if !seen.insert(path) {
+                continue;
+            }
+            let Some(node) = self.modules.get(path) else { continue };
+            result.push((node, depth));
+            if max_depth.is_none_or(|max| depth < max) {
+                queue.extend(node.dependencies.iter().chain(&node.type_dependencies).map(|dep| (dep, depth + 1)));
+            }
+        }
+        result
+    }
+
+    /// Whether `to` can be reached from `from` through run-time imports
+    fn reaches(&self, from: &PathBuf, to: &PathBuf) -> bool {
+        let mut stack = vec![from];
+        let mut seen = HashSet::new();
+        while let Some(path) = stack.pop() {
+            if path == to {
+                return true;
+            }
+            if seen.insert(path) {
+                if let Some(node) = self.modules.get(path) {
+                    stack.extend(&node.dependencies);
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether a module is part of an import cycle
+    fn in_cycle(&self, path: &PathBuf) -> bool {
+        self.modules.get(path).is_some_and(|node| node.dependencies.iter().any(|dep| self.reaches(dep, path)))
+    }
+
+    /// The directory module labels are relative to
+    fn label_root(&self) -> Option<&Path> {
+        self.entry.as_deref().and_then(Path::parent)
+    }
+}
+
+/// A module's path relative to `root`, or in full when it lies outside it
+fn relative_label(path: &Path, root: Option<&Path>) -> String {
+    root.and_then(|root| path.strip_prefix(root).ok()).unwrap_or(path).display().to_string()
+}
+
+/// Whether a module belongs to an npm package
+fn is_package(path: &Path) -> bool {
+    path.components().any(|component| component.as_os_str() == "node_modules")
+}
+
+fn dot_quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn dot_attrs(attrs: &[&str]) -> String {
+    if attrs.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", attrs.join(", "))
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_array(values: impl Iterator<Item = String>) -> String {
+    format!("[{}]", values.map(|value| json_string(&value)).collect::<Vec<_>>().join(", "))
 }
 
 impl Default for DepGraph {
@@ -229,6 +563,21 @@ mod tests {
         assert!(graph.detect_cycles().is_err());
     }
 
+    #[test]
+    fn test_cycle_through_top_level_await_names_the_deadlock() {
+        let mut graph = DepGraph::new();
+
+        let a = PathBuf::from("a.ts");
+        let b = PathBuf::from("b.ts");
+
+        graph.add_module(a.clone(), vec![b.clone()], HashSet::new());
+        graph.add_module(b.clone(), vec![a.clone()], HashSet::new());
+        graph.mark_top_level_await(&b);
+
+        let err = graph.detect_cycles().unwrap_err();
+        assert!(err.ends_with("(b.ts uses top-level await, which would deadlock on the cycle)"), "{}", err);
+    }
+
     #[test]
     fn test_diamond_dependency() {
         let mut graph = DepGraph::new();
@@ -257,4 +606,72 @@ mod tests {
         assert!(b_idx < d_idx);
         assert!(c_idx < d_idx);
     }
+
+    #[test]
+    fn test_dot_output_limits_depth_and_marks_cycles() {
+        let mut graph = DepGraph::new();
+
+        let main = PathBuf::from("/src/main.ts");
+        let a = PathBuf::from("/src/a.ts");
+        let b = PathBuf::from("/src/b.ts");
+        let pkg = PathBuf::from("/src/node_modules/pkg/index.ts");
+
+        // main -> a <-> b, main -> pkg, and a imports the fs built-in
+        graph.add_module(main.clone(), vec![a.clone(), pkg.clone()], HashSet::new());
+        graph.add_module(a.clone(), vec![b.clone()], HashSet::new());
+        graph.set_other_imports(&a, vec![], vec!["fs".to_string()]);
+        graph.add_module(b.clone(), vec![a.clone()], HashSet::new());
+        graph.add_module(pkg.clone(), vec![], HashSet::new());
+        graph.set_entry(main);
+
+        assert_eq!(
+            graph.to_dot(None),
+            "digraph deps {\n    rankdir=LR;\n    node [shape=box];\n    \"main.ts\" [style=bold];\n    \
+             \"a.ts\" [color=red];\n    \"node_modules/pkg/index.ts\" [color=blue];\n    \"b.ts\" [color=red];\n    \
+             \"fs\" [shape=ellipse, style=dashed];\n    \"main.ts\" -> \"a.ts\";\n    \
+             \"main.ts\" -> \"node_modules/pkg/index.ts\";\n    \"a.ts\" -> \"b.ts\" [color=red];\n    \
+             \"a.ts\" -> \"fs\";\n    \"b.ts\" -> \"a.ts\" [color=red];\n}\n"
+        );
+        assert_eq!(
+            graph.to_dot(Some(0)),
+            "digraph deps {\n    rankdir=LR;\n    node [shape=box];\n    \"main.ts\" [style=bold];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_reexport_chains_keep_type_only_names() {
+        let mut graph = DepGraph::new();
+
+        let a = PathBuf::from("a.ts");
+        let b = PathBuf::from("b.ts");
+        let c = PathBuf::from("c.ts");
+
+        // a exports a value, a class and an interface; b re-exports all of
+        // a and one name type-only; c re-exports a value of b as a type
+        graph.add_module(a.clone(), vec![], HashSet::from(["run".to_string(), "Task".to_string()]));
+        graph.set_type_exports(&a, HashSet::from(["Task".to_string(), "Options".to_string()]), vec![]);
+        graph.add_module(b.clone(), vec![a.clone()], HashSet::new());
+        graph.set_type_exports(&b, HashSet::new(), vec![
+            ReExport::All { source: a.clone(), type_only: false },
+            ReExport::Named { source: a.clone(), imported: "Task".into(), exported: "Job".into(), type_only: true },
+        ]);
+        graph.add_module(c.clone(), vec![], HashSet::new());
+        graph.set_type_exports(&c, HashSet::new(), vec![
+            ReExport::Named { source: b.clone(), imported: "run".into(), exported: "Runner".into(), type_only: true },
+            ReExport::Named { source: b.clone(), imported: "Options".into(), exported: "Options".into(), type_only: false },
+        ]);
+
+        graph.resolve_reexports();
+
+        let b_types = graph.type_only_exports(&b).unwrap();
+        assert_eq!(b_types, HashSet::from(["Options".to_string(), "Job".to_string()]));
+        assert!(graph.get_module(&b).unwrap().exports.contains("Task"));
+        let c_types = graph.type_only_exports(&c).unwrap();
+        assert_eq!(c_types, HashSet::from(["Runner".to_string(), "Options".to_string()]));
+
+        // Exports re-exported from outside the graph aren't known
+        graph.mark_exports_unknown(&a);
+        graph.resolve_reexports();
+        assert!(graph.type_only_exports(&c).is_none());
+    }
 }
@@ -0,0 +1,222 @@
+//! Longer explanations of the diagnostic codes, printed by
+//! `zaco --explain <code>`.
+
+/// The explanation of each code, keyed as it appears in a report
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "E0001",
+        "\
+A character sequence could not be turned into a token.
+
+The lexer stops at text that is not part of the language: a string or
+template literal that is never closed, a character that cannot start a
+token, or a malformed number.
+
+Erroneous code example:
+
+    let greeting = \"hello;
+
+Close the literal (or escape the character) to fix it:
+
+    let greeting = \"hello\";
+",
+    ),
+    (
+        "E1000",
+        "\
+The tokens do not form a valid program.
+
+The parser expected one construct and found another: a missing closing
+bracket, an operator without an operand, or a keyword in a place it
+cannot appear. The report points at the first token it could not use.
+
+Erroneous code example:
+
+    let total = (price + ;
+
+Complete the expression and balance the brackets:
+
+    let total = (price + tax);
+",
+    ),
+    (
+        "E2000",
+        "\
+The program does not type check.
+
+Every expression has a type, and each use must fit the type expected
+there: a value assigned to an annotated variable, an argument passed to a
+parameter, a property read from an object that has it. Ownership is
+checked in the same pass, so a value used after it was moved, or a
+mutable borrow taken while another borrow is live, is reported here too.
+
+When the expected type was declared elsewhere, a second label points at
+the declaration.
+
+Erroneous code example:
+
+    function area(width: number, height: number): number {
+        return width * height;
+    }
+    let size: number = area(2, \"3\");
+
+Pass a value of the declared type:
+
+    let size: number = area(2, 3);
+",
+    ),
+    (
+        "E2001",
+        "\
+A value's type does not match the type expected where it is used.
+
+The expected type comes from an annotation (on a variable, parameter or
+return type) or from an earlier use. A second label points at the
+annotation when there is one.
+
+Erroneous code example:
+
+    let count: number = \"three\";
+
+Give the value the expected type, or change the annotation:
+
+    let count: number = 3;
+",
+    ),
+    (
+        "E2002",
+        "\
+A name is used that is not declared in any enclosing scope.
+
+The name may be misspelled, declared in a block that has already ended,
+or defined in another module without being imported. When a declared
+name is close, the error suggests it.
+
+Erroneous code example:
+
+    let total = 1;
+    console.log(totl);
+
+Use the declared name:
+
+    console.log(total);
+",
+    ),
+    (
+        "E2004",
+        "\
+A value is used after ownership of it was moved elsewhere.
+
+Assigning an owned variable to another one moves the value: the old
+binding can no longer be used. Borrow it with `ref` instead, or make an
+independent copy with `clone`.
+
+Erroneous code example:
+
+    let data: number[] = [1, 2];
+    let copy: number[] = [];
+    copy = data;
+    console.log(data);
+
+Copy the value rather than moving it:
+
+    copy = clone data;
+",
+    ),
+    (
+        "E2008",
+        "\
+A `const` binding is assigned a new value.
+
+Erroneous code example:
+
+    const limit = 10;
+    limit = 20;
+
+Declare the variable with `let` if it needs to change:
+
+    let limit = 10;
+    limit = 20;
+",
+    ),
+    (
+        "E2022",
+        "\
+A `// @zaco-expect-error` comment is followed by a line without a type error.
+
+The directive suppresses the type errors on the next line, so that code
+which must not compile can be kept in a program. When the line compiles,
+the directive is reported: the error it expected has gone away.
+
+Erroneous code example:
+
+    // @zaco-expect-error
+    let count: number = 3;
+
+Remove the directive, or put it before the line with the error:
+
+    let count: number = 3;
+",
+    ),
+    (
+        "E3000",
+        "\
+The program type checks but cannot be compiled to native code.
+
+Some constructs are valid TypeScript but need information the compiler
+only has at compile time: `spawn()` takes the name of an exported
+function as a string literal, a `const enum` has no object at run time,
+and a name imported with `import type` cannot be used as a value.
+
+Erroneous code example:
+
+    const enum Color { Red, Green }
+    console.log(Color);
+
+Use the members, which are replaced by their values:
+
+    console.log(Color.Red);
+",
+    ),
+    (
+        "E3001",
+        "\
+The type checker accepted a construct that the compiler cannot translate yet.
+
+This is a gap in the compiler, not a mistake in the program: the two
+phases disagree on what the language includes. Please report it with the
+construct the error points at. Until it is fixed, rewrite the construct,
+or pass `--allow-unsupported` to compile the rest of the program without it.
+
+Erroneous code example:
+
+    let i = 0;
+    do {
+        i++;
+    } while (i < 3);
+
+Write the loop another way:
+
+    let i = 0;
+    while (i < 3) {
+        i++;
+    }
+",
+    ),
+];
+
+/// The explanation for `code` (case-insensitive), or `None` if there is
+/// no such code. Kinds of type error without an entry of their own share
+/// the general one of `E2000`.
+pub fn explain(code: &str) -> Option<&'static str> {
+    let lookup = |code: &str| {
+        EXPLANATIONS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(code))
+            .map(|(_, text)| *text)
+    };
+    let is_type_error = code.len() == 5
+        && code.get(..2).is_some_and(|phase| phase.eq_ignore_ascii_case("E2"))
+        && code.bytes().skip(2).all(|b| b.is_ascii_digit());
+    lookup(code).or_else(|| if is_type_error { lookup("E2000") } else { None })
+}
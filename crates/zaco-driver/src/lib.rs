@@ -9,6 +9,10 @@ pub mod dep_graph;
 pub mod package_json;
 pub mod npm_resolver;
 pub mod dts_loader;
+pub mod pipeline;
+pub mod link;
+pub mod explain;
 
 pub use resolver::{ModuleResolver, ResolvedModule};
-pub use dep_graph::DepGraph;
+pub use dep_graph::{DepGraph, ReExport};
+pub use pipeline::{compile, AllocStrategy, CompileOptions, CompileOutput, Diagnostic, Input, ParseCache};
@@ -0,0 +1,254 @@
+//! Linking compiled programs with the Zaco runtime: an executable with
+//! `cc`, or a static library with `ar`.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{AllocStrategy, CompileOptions};
+
+/// Find the runtime C source file, searching common locations.
+pub fn find_runtime_source(input_path: &Path) -> Option<PathBuf> {
+    // 1. Check ZACO_RUNTIME_C environment variable
+    if let Ok(env_path) = std::env::var("ZACO_RUNTIME_C") {
+        let p = PathBuf::from(env_path);
+        if p.exists() {
+            return Some(p);
+        }
+    }
+
+    // 2. Search relative paths
+    let candidates = [
+        // Relative to the input file's directory
+        input_path
+            .parent()
+            .map(|p| p.join("runtime/zaco_runtime.c")),
+        // Relative to CWD
+        Some(PathBuf::from("runtime/zaco_runtime.c")),
+    ];
+
+    for candidate in candidates.iter().flatten() {
+        if candidate.exists() {
+            return Some(candidate.clone());
+        }
+    }
+
+    // 3. Try to find via the executable's location
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(exe_dir) = exe.parent() {
+            // Try sibling directory (e.g., installed layout)
+            let runtime = exe_dir.join("../runtime/zaco_runtime.c");
+            if runtime.exists() {
+                return Some(runtime);
+            }
+            // Try share directory (e.g., /usr/local/share/zaco/runtime)
+            let runtime = exe_dir.join("../share/zaco/runtime/zaco_runtime.c");
+            if runtime.exists() {
+                return Some(runtime);
+            }
+        }
+    }
+
+    None
+}
+
+/// Find the Rust runtime static library (.a), searching common locations.
+fn find_rust_runtime(c_runtime_path: &Path) -> Option<PathBuf> {
+    // 1. Check ZACO_RUNTIME_RS environment variable
+    if let Ok(env_path) = std::env::var("ZACO_RUNTIME_RS") {
+        let p = PathBuf::from(env_path);
+        if p.exists() {
+            return Some(p);
+        }
+    }
+
+    // 2. Derive from C runtime location (sibling directory)
+    if let Some(runtime_dir) = c_runtime_path.parent() {
+        let candidate = runtime_dir.join("zaco_runtime_rs/target/release/libzaco_runtime_rs.a");
+        if candidate.exists() {
+            return Some(candidate);
+        }
+    }
+
+    // 3. Relative to CWD
+    let cwd_candidate = PathBuf::from("runtime/zaco_runtime_rs/target/release/libzaco_runtime_rs.a");
+    if cwd_candidate.exists() {
+        return Some(cwd_candidate);
+    }
+
+    // 4. Relative to compiler executable
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(exe_dir) = exe.parent() {
+            let candidate = exe_dir.join("../runtime/zaco_runtime_rs/target/release/libzaco_runtime_rs.a");
+            if candidate.exists() {
+                return Some(candidate);
+            }
+        }
+    }
+
+    None
+}
+
+/// Link an object file from `codegen` with the runtime at `runtime_path`
+/// (see `find_runtime_source`) into an executable at `output_path`.
+pub fn link_executable(
+    object_bytes: &[u8],
+    output_path: &Path,
+    runtime_path: Option<&Path>,
+    options: &CompileOptions,
+) -> io::Result<()> {
+    let temp_dir = std::env::temp_dir();
+    let pid = std::process::id();
+    let temp_obj = temp_dir.join(format!("zaco_temp_{}.o", pid));
+    fs::write(&temp_obj, object_bytes)?;
+
+    let linker = "cc";
+
+    let mut cmd = Command::new(linker);
+    cmd.arg("-o").arg(output_path);
+
+    // On macOS, suppress linker warnings about missing platform load
+    // commands in Cranelift-generated object files (Cranelift doesn't
+    // emit Mach-O platform metadata).
+    if cfg!(target_os = "macos") {
+        cmd.arg("-Wl,-w");
+    }
+
+    // The Mach-O main thread stack size is fixed at link time; on Linux the
+    // runtime raises the stack rlimit at startup instead (see below)
+    if let (Some(stack_size), true) = (options.stack_size, cfg!(target_os = "macos")) {
+        cmd.arg(format!("-Wl,-stack_size,{:#x}", stack_size));
+    }
+
+    // Add the compiled object file
+    cmd.arg(&temp_obj);
+
+    // Compile and link the C runtime if available
+    if let Some(rt_path) = runtime_path {
+        if options.verbose {
+            println!("  Using C runtime: {}", rt_path.display());
+        }
+        // Compile runtime.c to .o and link together
+        let temp_rt_obj = temp_dir.join(format!("zaco_runtime_{}.o", pid));
+        if let Err(e) = compile_runtime_object(rt_path, &temp_rt_obj, options) {
+            let _ = fs::remove_file(&temp_obj);
+            return Err(e);
+        }
+        cmd.arg(&temp_rt_obj);
+
+        // Link the Rust runtime static library
+        let rust_runtime_lib = find_rust_runtime(rt_path);
+
+        if let Some(ref rust_runtime_lib) = rust_runtime_lib {
+            if options.verbose {
+                println!("  Using Rust runtime: {}", rust_runtime_lib.display());
+            }
+            cmd.arg(&rust_runtime_lib);
+
+            // Add required linker flags for Rust runtime on macOS
+            if cfg!(target_os = "macos") {
+                cmd.arg("-framework").arg("CoreFoundation");
+                cmd.arg("-framework").arg("Security");
+                cmd.arg("-framework").arg("SystemConfiguration");
+                cmd.arg("-lpthread");
+                cmd.arg("-ldl");
+            }
+        } else {
+            eprintln!("Warning: Rust runtime library not found");
+            eprintln!("To build it, run: cd runtime/zaco_runtime_rs && cargo build --release");
+        }
+
+        let status = cmd.status()?;
+
+        // Clean up temp files
+        let _ = fs::remove_file(&temp_obj);
+        let _ = fs::remove_file(&temp_rt_obj);
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Linker exited with status: {}", status),
+            ))
+        }
+    } else {
+        // No runtime — link just the object file (will fail if runtime symbols are referenced)
+        if options.verbose {
+            println!("  Warning: Runtime not found, linking without it");
+        }
+        let status = cmd.status()?;
+        let _ = fs::remove_file(&temp_obj);
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Linker exited with status: {}", status),
+            ))
+        }
+    }
+}
+
+/// Compile the C runtime at `rt_path` to an object file at `out`
+fn compile_runtime_object(rt_path: &Path, out: &Path, options: &CompileOptions) -> io::Result<()> {
+    let mut rt_cmd = Command::new("cc");
+    // The arena variant is the same source built with ZACO_ALLOC_ARENA
+    if options.alloc_strategy == AllocStrategy::Arena {
+        rt_cmd.arg("-DZACO_ALLOC_ARENA");
+    }
+    if let Some(stack_size) = options.stack_size {
+        rt_cmd.arg(format!("-DZACO_STACK_SIZE={}", stack_size));
+    }
+    let rt_status = rt_cmd.args(["-c", "-o"]).arg(out).arg(rt_path).status()?;
+
+    if rt_status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Failed to compile runtime.c",
+        ))
+    }
+}
+
+/// Write a static library holding the compiled program and, when found, the
+/// C runtime. Callers link it together with the Rust runtime library and
+/// call `zaco_lib_init` before any exported function.
+pub fn archive_library(
+    object_bytes: &[u8],
+    lib_path: &Path,
+    runtime_path: Option<&Path>,
+    options: &CompileOptions,
+) -> io::Result<()> {
+    let temp_dir = std::env::temp_dir();
+    let pid = std::process::id();
+    let temp_obj = temp_dir.join(format!("zaco_lib_{}.o", pid));
+    let temp_rt_obj = temp_dir.join(format!("zaco_runtime_{}.o", pid));
+    fs::write(&temp_obj, object_bytes)?;
+
+    // ar appends to an existing archive, so start from scratch
+    let _ = fs::remove_file(lib_path);
+    let mut cmd = Command::new("ar");
+    cmd.arg("rcs").arg(lib_path).arg(&temp_obj);
+    let result = match runtime_path {
+        Some(rt_path) => compile_runtime_object(rt_path, &temp_rt_obj, options).map(|_| {
+            cmd.arg(&temp_rt_obj);
+        }),
+        None => Ok(()),
+    }
+    .and_then(|_| cmd.status())
+    .and_then(|status| {
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("ar exited with status: {}", status)))
+        }
+    });
+
+    let _ = fs::remove_file(&temp_obj);
+    let _ = fs::remove_file(&temp_rt_obj);
+    result
+}
@@ -1,15 +1,16 @@
 use ariadne::{Color, Label, Report, ReportKind, Source};
 use clap::{Parser, Subcommand, ValueEnum};
-use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::fs;
-use std::hash::{Hash, Hasher};
-use std::io;
-use std::path::PathBuf;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitCode};
+use std::time::Instant;
+use zaco_ast::{Literal, ModuleItem};
 use zaco_lexer::{Lexer, Token, TokenKind};
 
-use zaco_driver::{ModuleResolver, ResolvedModule, DepGraph};
-use zaco_driver::dts_loader;
+use zaco_driver::{link, pipeline};
+use zaco_driver::{AllocStrategy, CompileOptions, Diagnostic, Input, ParseCache};
 
 #[derive(Parser)]
 #[command(
@@ -18,17 +19,23 @@ use zaco_driver::dts_loader;
     about = "Zaco TypeScript Compiler with ownership semantics",
     long_about = "A TypeScript compiler that adds Rust-like ownership tracking\nto catch memory bugs at compile time."
 )]
+#[command(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
 struct Cli {
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+
+    /// Explain a diagnostic code (e.g. E2000) in detail
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Compile a TypeScript file to an executable
     Compile {
-        /// Input TypeScript file
-        input: PathBuf,
+        /// Input TypeScript file (several with `--emit lib`)
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
 
         /// Output file path
         #[arg(short, long)]
@@ -38,20 +45,93 @@ enum Commands {
         #[arg(long, default_value = "exe")]
         emit: EmitMode,
 
+        /// With `--emit obj`, write one object per module to this directory,
+        /// laid out like the sources, instead of a single merged object
+        #[arg(long, value_name = "DIR", conflicts_with = "output")]
+        out_dir: Option<PathBuf>,
+
         /// Target triple (e.g., x86_64-apple-darwin)
         #[arg(long)]
         target: Option<String>,
 
+        /// Insert runtime checks (null receivers throw a catchable TypeError)
+        #[arg(long)]
+        checked: bool,
+
+        /// Runtime allocation strategy
+        #[arg(long, default_value = "rc")]
+        alloc_strategy: AllocStrategy,
+
+        /// Profile calls and allocations; the summary is printed at exit
+        /// (or written as JSON to $ZACO_PROFILE_OUT)
+        #[arg(long)]
+        instrument: bool,
+
+        /// Main thread stack size in bytes, for deeply recursive programs
+        #[arg(long, value_name = "BYTES")]
+        stack_size: Option<u64>,
+
+        /// Stop reporting errors after the first N unique ones (0 for no limit)
+        #[arg(long, value_name = "N", default_value_t = 50)]
+        max_errors: usize,
+
+        /// Log each node the lowerer visits and each runtime extern it
+        /// declares to stderr
+        #[arg(long)]
+        trace_lowering: bool,
+
+        /// Compile code the compiler does not support yet by leaving it
+        /// out, rather than reporting it as an error
+        #[arg(long)]
+        allow_unsupported: bool,
+
+        /// With `--emit deps`, only show modules at most N imports away
+        /// from the entry
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+
+        /// Define a constant for the program: `true`, `false`, a number or
+        /// a string. Branches it decides are left out (repeatable)
+        #[arg(long = "define", value_name = "NAME=VALUE", value_parser = parse_define)]
+        defines: Vec<(String, Literal)>,
+
         /// Enable verbose output
         #[arg(short, long)]
         verbose: bool,
     },
 
-    /// Type check a TypeScript file without compiling
+    /// Type check a TypeScript file, or every file in a directory, without compiling
     Check {
-        /// Input TypeScript file
+        /// Input TypeScript file, a directory to check every `.ts` file in,
+        /// or `-` to read stdin
         input: PathBuf,
 
+        /// Stop reporting errors after the first N unique ones (0 for no limit)
+        #[arg(long, value_name = "N", default_value_t = 50)]
+        max_errors: usize,
+
+        /// Enable verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Compile and run every *.test.ts file in a directory (or a single file)
+    Test {
+        /// Test file, or directory to search recursively
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Insert runtime checks (null receivers throw a catchable TypeError)
+        #[arg(long)]
+        checked: bool,
+
+        /// Enable verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Start an interactive session: statements persist, expressions print their value
+    Repl {
         /// Enable verbose output
         #[arg(short, long)]
         verbose: bool,
@@ -59,7 +139,7 @@ enum Commands {
 
     /// Lex a TypeScript file and show tokens (debug)
     Lex {
-        /// Input TypeScript file
+        /// Input TypeScript file, or `-` to read stdin
         input: PathBuf,
 
         /// Show token positions
@@ -69,7 +149,7 @@ enum Commands {
 
     /// Parse a TypeScript file and show AST (debug)
     Parse {
-        /// Input TypeScript file
+        /// Input TypeScript file, or `-` to read stdin
         input: PathBuf,
 
         /// Pretty print the AST
@@ -86,222 +166,356 @@ enum EmitMode {
     Ir,
     /// Emit object file only
     Obj,
+    /// Emit target assembly for each function (debug output)
+    Asm,
+    /// Emit a static library exporting the inputs' exported functions
+    Lib,
+    /// Print the module dependency graph in DOT format, without type checking
+    Deps,
+    /// Print the module dependency graph and each module's exports as JSON
+    DepsJson,
+    /// Emit a WebAssembly module; run it with runtime/zaco_wasm_host.mjs
+    Wasm,
     /// Emit executable (default)
     Exe,
 }
 
+/// Parse a `--define NAME=VALUE`. The value is `true`, `false`, `null`,
+/// `undefined` or a number as written, and otherwise a string, which may be
+/// quoted.
+fn parse_define(define: &str) -> Result<(String, Literal), String> {
+    let (name, value) = define.split_once('=').ok_or_else(|| format!("expected NAME=VALUE, found `{}`", define))?;
+    let mut chars = name.chars();
+    let starts_ident = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$');
+    if !starts_ident || !chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+        return Err(format!("`{}` is not a valid identifier", name));
+    }
+    let literal = match value {
+        "true" => Literal::Boolean(true),
+        "false" => Literal::Boolean(false),
+        "null" => Literal::Null,
+        "undefined" => Literal::Undefined,
+        _ => match value.parse::<f64>() {
+            Ok(n) if n.is_finite() => Literal::Number(n),
+            _ => {
+                let unquoted = [('"', '"'), ('\'', '\'')].iter().find_map(|&(open, close)| {
+                    value.strip_prefix(open)?.strip_suffix(close)
+                });
+                Literal::String(unquoted.unwrap_or(value).to_string())
+            }
+        },
+    };
+    Ok((name.to_string(), literal))
+}
+
 fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    match cli.command {
+    let Some(command) = cli.command else {
+        return explain_command(cli.explain.as_deref().unwrap_or_default());
+    };
+
+    match command {
         Commands::Compile {
-            input,
+            inputs,
             output,
             emit,
+            out_dir,
             target,
+            checked,
+            alloc_strategy,
+            instrument,
+            stack_size,
+            max_errors,
+            trace_lowering,
+            allow_unsupported,
+            depth,
+            defines,
             verbose,
-        } => compile_command(input, output, emit, target, verbose),
-        Commands::Check { input, verbose } => check_command(input, verbose),
+        } => compile_command(
+            inputs,
+            out_dir.map_or(Destination::File(output), Destination::Dir),
+            emit,
+            target,
+            CompileOptions {
+                inputs: Vec::new(),
+                library: false,
+                checked,
+                alloc_strategy,
+                instrument,
+                stack_size,
+                trace_lowering,
+                allow_unsupported,
+                defines: defines.into_iter().collect(),
+                verbose,
+            },
+            (max_errors > 0).then_some(max_errors),
+            depth,
+        ),
+        Commands::Check { input, max_errors, verbose } => {
+            check_command(input, (max_errors > 0).then_some(max_errors), verbose)
+        }
+        Commands::Test { path, checked, verbose } => test_command(path, checked, verbose),
+        Commands::Repl { verbose } => repl_command(verbose),
         Commands::Lex { input, positions } => lex_command(input, positions),
         Commands::Parse { input, pretty } => parse_command(input, pretty),
     }
 }
 
+/// `zaco --explain <code>`
+fn explain_command(code: &str) -> ExitCode {
+    match zaco_driver::explain::explain(code) {
+        Some(text) => {
+            print!("{}", text);
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("Error: no extended explanation for '{}'", code);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Where `zaco compile` writes what it emits
+enum Destination {
+    /// `-o`, or else the entry's file stem
+    File(Option<PathBuf>),
+    /// `--out-dir`: one object per module
+    Dir(PathBuf),
+}
+
+/// `zaco compile`. `options` has every flag but the inputs, which are
+/// filled in once they're resolved.
 fn compile_command(
-    input: PathBuf,
-    output: Option<PathBuf>,
+    inputs: Vec<PathBuf>,
+    destination: Destination,
     emit: EmitMode,
     target: Option<String>,
-    verbose: bool,
+    mut options: CompileOptions,
+    max_errors: Option<usize>,
+    depth: Option<usize>,
 ) -> ExitCode {
-    if verbose {
-        println!("Compiling: {}", input.display());
+    if inputs.len() > 1 && !matches!(emit, EmitMode::Lib) {
+        eprintln!("Error: compiling several input files requires --emit lib");
+        return ExitCode::FAILURE;
+    }
+    if matches!(destination, Destination::Dir(_)) && !matches!(emit, EmitMode::Obj) {
+        eprintln!("Error: --out-dir requires --emit obj");
+        return ExitCode::FAILURE;
+    }
+
+    if options.verbose {
+        for input in &inputs {
+            println!("Compiling: {}", input.display());
+        }
         if let Some(ref t) = target {
             println!("Target: {}", t);
         }
         println!("Emit mode: {:?}", emit);
+        println!("Alloc strategy: {:?}", options.alloc_strategy);
     }
 
-    // Canonicalize input path
-    let input = match input.canonicalize() {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Error resolving input path: {}", e);
-            return ExitCode::FAILURE;
-        }
-    };
-
-    // Build dependency graph by discovering all imports
-    if verbose {
-        println!("\n[Phase 0] Discovering module dependencies...");
-    }
-
-    let mut dep_graph = DepGraph::new();
-    let base_dir = input.parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
-    let resolver = ModuleResolver::new(base_dir);
-    let mut parse_cache: HashMap<PathBuf, (String, Program)> = HashMap::new();
-
-    match discover_modules(&input, &resolver, &mut dep_graph, verbose, &mut parse_cache) {
-        Ok(_) => {}
-        Err(e) => {
-            eprintln!("Module discovery error: {}", e);
-            return ExitCode::FAILURE;
+    // Canonicalize input paths
+    for input in &inputs {
+        match input.canonicalize() {
+            Ok(p) => options.inputs.push(Input::File(p)),
+            Err(e) => {
+                eprintln!("Error resolving input path {}: {}", input.display(), e);
+                return ExitCode::FAILURE;
+            }
         }
     }
+    options.library = matches!(emit, EmitMode::Lib);
 
-    dep_graph.set_entry(input.clone());
+    let mut parse_cache = ParseCache::new();
 
-    // Check for circular dependencies
-    if let Err(e) = dep_graph.detect_cycles() {
-        eprintln!("Error: {}", e);
-        return ExitCode::FAILURE;
-    }
-
-    // Get compilation order (topological sort)
-    let compilation_order = match dep_graph.topological_sort() {
-        Ok(order) => order,
-        Err(e) => {
-            eprintln!("Error determining compilation order: {}", e);
-            return ExitCode::FAILURE;
+    // Determine output path
+    let output_path = match destination {
+        Destination::File(output) => output.unwrap_or_else(|| {
+            let stem = options.inputs[0].path().file_stem().unwrap_or_default().to_string_lossy();
+            PathBuf::from(stem.to_string())
+        }),
+        Destination::Dir(out_dir) => {
+            return match build_objects(&options, &out_dir, max_errors, &mut parse_cache) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(()) => ExitCode::FAILURE,
+            };
         }
     };
-
-    if verbose {
-        println!("  Discovered {} modules", compilation_order.len());
-        for (i, module_path) in compilation_order.iter().enumerate() {
-            println!("    {}. {}", i + 1, module_path.display());
+    match build_program(&options, &output_path, &emit, max_errors, depth, &mut parse_cache) {
+        Ok(()) => {
+            if matches!(emit, EmitMode::Exe) {
+                println!("Executable written to: {}", output_path.display());
+            }
+            ExitCode::SUCCESS
         }
+        Err(()) => ExitCode::FAILURE,
     }
+}
 
-    // Compile each module in order and collect IR modules (preserving compilation order)
-    let mut module_irs: Vec<(PathBuf, zaco_ir::IrModule)> = Vec::new();
-    let mut func_id_offset: usize = 0;
-    let mut struct_id_offset: usize = 0;
-
-    for module_path in &compilation_order {
-        if verbose {
-            println!("\n[Compiling] {}", module_path.display());
-        }
-
-        // Entry module (the user's input file) gets "main" wrapper;
-        // all other modules get "__module_init_<name>" wrappers.
-        let is_entry = *module_path == input;
-        let module_name = if is_entry {
-            None
-        } else {
-            Some(module_path_to_init_name(module_path))
-        };
-
-        let ir_module = match compile_single_module(
-            module_path,
-            &emit,
-            verbose,
-            &mut parse_cache,
-            module_name.as_deref(),
-            func_id_offset,
-            struct_id_offset,
-        ) {
-            Ok(ir) => ir,
-            Err(_) => return ExitCode::FAILURE,
-        };
-
-        // Update offsets for the next module to avoid FuncId/StructId collisions
-        func_id_offset = ir_module.next_func_id;
-        struct_id_offset = ir_module.next_struct_id;
-
-        module_irs.push((module_path.clone(), ir_module));
+/// Compile `options.inputs` (already canonicalized) and emit the program as
+/// `emit` at `output_path`. Only `--emit lib` takes more than one input.
+/// Errors are reported as they occur. `zaco test` shares `parse_cache`
+/// across test files, so helper modules they import are only parsed once.
+fn build_program(
+    options: &CompileOptions,
+    output_path: &Path,
+    emit: &EmitMode,
+    max_errors: Option<usize>,
+    depth: Option<usize>,
+    parse_cache: &mut ParseCache,
+) -> Result<(), ()> {
+    let report = |diagnostics, parse_cache: &ParseCache| report_diagnostics(diagnostics, parse_cache, max_errors);
+
+    let dep_graph = pipeline::discover(options, parse_cache).map_err(|d| report(d, parse_cache))?;
+
+    // The graph is printed as discovered: nothing is type checked, and
+    // cycles are highlighted rather than rejected
+    if matches!(emit, EmitMode::Deps) {
+        print!("{}", dep_graph.to_dot(depth));
+        return Ok(());
     }
-
-    // Merge all IR modules into one
-    if verbose {
-        println!("\n[Phase 4.5] Merging IR modules...");
+    if matches!(emit, EmitMode::DepsJson) {
+        print!("{}", dep_graph.to_json(depth));
+        return Ok(());
     }
 
-    let mut merged_ir = merge_ir_modules(module_irs);
-
-    // Inject calls to __module_init_* functions at the start of "main"'s entry block.
-    // This ensures all dependency modules' top-level code runs before the entry module.
-    inject_module_init_calls(&mut merged_ir);
-
-    if verbose {
-        println!(
-            "  {} functions, {} string literals",
-            merged_ir.functions.len(),
-            merged_ir.string_literals.len()
-        );
+    if matches!(emit, EmitMode::Ast) {
+        for module_path in dep_graph.topological_sort().unwrap_or_default() {
+            println!("AST for {}:", module_path.display());
+            println!("{:#?}", parse_cache[&module_path].1);
+        }
     }
 
+    let merged_ir = pipeline::lower(options, &dep_graph, parse_cache).map_err(|d| report(d, parse_cache))?;
+
     if matches!(emit, EmitMode::Ir) {
         dump_ir(&merged_ir);
-        return ExitCode::SUCCESS;
-    }
-
-    // Phase 5: IR → Native Code (Cranelift)
-    if verbose {
-        println!("\n[Phase 5] Generating native code...");
+        return Ok(());
     }
 
-    let codegen = match zaco_codegen::CodeGenerator::new() {
-        Ok(cg) => cg,
-        Err(e) => {
-            eprintln!("Codegen initialization error: {}", e);
-            return ExitCode::FAILURE;
-        }
-    };
-
-    let object_bytes = match codegen.compile_module(&merged_ir) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            eprintln!("Codegen error: {}", e);
-            return ExitCode::FAILURE;
+    if matches!(emit, EmitMode::Wasm) {
+        if options.verbose {
+            println!("\n[Phase 5] Generating WebAssembly...");
         }
-    };
+        let wasm_bytes = match zaco_codegen::wasm::compile_module(&merged_ir) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("{}", e);
+                return Err(());
+            }
+        };
+        let wasm_path = output_path.with_extension("wasm");
+        return match fs::write(&wasm_path, &wasm_bytes) {
+            Ok(_) => {
+                println!("WebAssembly module written to: {}", wasm_path.display());
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Error writing WebAssembly module: {}", e);
+                Err(())
+            }
+        };
+    }
 
-    if verbose {
-        println!("  {} bytes of object code generated", object_bytes.len());
+    if matches!(emit, EmitMode::Asm) {
+        let codegen = pipeline::code_generator(options).map_err(|d| report(vec![d], parse_cache))?;
+        return match codegen.disassemble_module(&merged_ir) {
+            Ok(asm) => {
+                print!("{}", asm);
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Codegen error: {}", e);
+                Err(())
+            }
+        };
     }
 
-    // Determine output path
-    let output_path = output.unwrap_or_else(|| {
-        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
-        PathBuf::from(stem.to_string())
-    });
+    // Phase 5: IR → Native Code (Cranelift)
+    let object_bytes = pipeline::codegen(&merged_ir, options).map_err(|d| report(vec![d], parse_cache))?;
 
     if matches!(emit, EmitMode::Obj) {
         let obj_path = output_path.with_extension("o");
         match fs::write(&obj_path, &object_bytes) {
             Ok(_) => {
                 println!("Object file written to: {}", obj_path.display());
-                return ExitCode::SUCCESS;
+                return Ok(());
             }
             Err(e) => {
                 eprintln!("Error writing object file: {}", e);
-                return ExitCode::FAILURE;
+                return Err(());
             }
         }
     }
 
+    // Find the runtime source
+    let runtime_path = link::find_runtime_source(options.inputs[0].path());
+
+    if matches!(emit, EmitMode::Lib) {
+        let lib_path = output_path.with_extension("a");
+        return match link::archive_library(&object_bytes, &lib_path, runtime_path.as_deref(), options) {
+            Ok(()) => {
+                println!("Library written to: {}", lib_path.display());
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Archiving error: {}", e);
+                Err(())
+            }
+        };
+    }
+
     // Phase 6: Linking
-    if verbose {
+    if options.verbose {
         println!("\n[Phase 6] Linking...");
     }
 
-    // Find the runtime source
-    let runtime_path = find_runtime_source(&input);
+    link::link_executable(&object_bytes, output_path, runtime_path.as_deref(), options).map_err(|e| {
+        eprintln!("Linking error: {}", e);
+    })
+}
 
-    match link_executable(&object_bytes, &output_path, runtime_path.as_deref(), verbose) {
-        Ok(_) => {
-            println!("Executable written to: {}", output_path.display());
-            ExitCode::SUCCESS
-        }
-        Err(e) => {
-            eprintln!("Linking error: {}", e);
-            ExitCode::FAILURE
+/// `--emit obj --out-dir`: compile each module of the program to an object
+/// of its own under `out_dir`, at the module's path relative to the
+/// directory all the modules share, for linking outside the compiler.
+fn build_objects(
+    options: &CompileOptions,
+    out_dir: &Path,
+    max_errors: Option<usize>,
+    parse_cache: &mut ParseCache,
+) -> Result<(), ()> {
+    let report = |diagnostics, parse_cache: &ParseCache| report_diagnostics(diagnostics, parse_cache, max_errors);
+
+    let dep_graph = pipeline::discover(options, parse_cache).map_err(|d| report(d, parse_cache))?;
+    let module_irs =
+        pipeline::lower_separately(options, &dep_graph, parse_cache).map_err(|d| report(d, parse_cache))?;
+
+    let mut root = options.inputs[0].path().parent().unwrap_or(Path::new("")).to_path_buf();
+    while !module_irs.iter().all(|(path, _)| path.starts_with(&root)) && root.pop() {}
+
+    for (module_path, ir_module) in &module_irs {
+        let object_bytes = pipeline::codegen(ir_module, options).map_err(|d| report(vec![d], parse_cache))?;
+        let relative = module_path.strip_prefix(&root).unwrap_or(module_path);
+        let obj_path = out_dir.join(relative).with_extension("o");
+        let written = obj_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&obj_path, &object_bytes));
+        if let Err(e) = written {
+            eprintln!("Error writing object file {}: {}", obj_path.display(), e);
+            return Err(());
         }
+        println!("Object file written to: {}", obj_path.display());
     }
+    Ok(())
 }
 
-fn check_command(input: PathBuf, verbose: bool) -> ExitCode {
+fn check_command(input: PathBuf, max_errors: Option<usize>, verbose: bool) -> ExitCode {
+    if input.is_dir() {
+        return check_project(&input, max_errors, verbose);
+    }
+
     if verbose {
         println!("Type checking: {}", input.display());
     }
@@ -314,16 +528,79 @@ fn check_command(input: PathBuf, verbose: bool) -> ExitCode {
         }
     };
 
-    let filename = input.to_string_lossy().to_string();
+    match check_source(&source_name(&input), &source, max_errors) {
+        Ok(()) => {
+            println!("Type check passed!");
+            ExitCode::SUCCESS
+        }
+        Err(_) => ExitCode::FAILURE,
+    }
+}
 
+/// `zaco check <dir>`: type check every `.ts` file under `dir`, skipping
+/// what `.gitignore` files and hidden paths exclude, like `tsc --noEmit`
+fn check_project(dir: &Path, max_errors: Option<usize>, verbose: bool) -> ExitCode {
+    let mut files: Vec<PathBuf> = ignore::WalkBuilder::new(dir)
+        .require_git(false)
+        .build()
+        .flatten()
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.is_file() && name.ends_with(".ts") && !name.ends_with(".d.ts")
+        })
+        .collect();
+    files.sort();
+
+    let mut errors = 0;
+    let mut failed_files = 0;
+    for file in &files {
+        if verbose {
+            println!("Type checking: {}", file.display());
+        }
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", file.display(), e);
+                errors += 1;
+                failed_files += 1;
+                continue;
+            }
+        };
+        if let Err(count) = check_source(&file.to_string_lossy(), &source, max_errors) {
+            errors += count;
+            failed_files += 1;
+        }
+    }
+
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    if failed_files == 0 {
+        println!("Type check passed! ({} file{})", files.len(), plural(files.len()));
+        ExitCode::SUCCESS
+    } else {
+        println!(
+            "Found {} error{} in {} of {} file{}",
+            errors,
+            plural(errors),
+            failed_files,
+            files.len(),
+            plural(files.len())
+        );
+        ExitCode::FAILURE
+    }
+}
+
+/// Lex, parse and type check one file, reporting its errors. Returns how
+/// many errors were found.
+fn check_source(filename: &str, source: &str, max_errors: Option<usize>) -> Result<(), usize> {
     // Lex
-    let mut lexer = Lexer::new(&source);
+    let mut lexer = Lexer::new(source);
     let tokens = lexer.tokenize();
 
-    let has_errors = tokens.iter().any(|t| t.kind == TokenKind::Error);
-    if has_errors {
-        report_lexer_errors(&tokens, &filename, &source);
-        return ExitCode::FAILURE;
+    let lexical = tokens.iter().filter(|t| t.kind == TokenKind::Error).count();
+    if lexical > 0 {
+        report_lexer_errors(&tokens, filename, source, max_errors);
+        return Err(lexical);
     }
 
     // Parse
@@ -331,43 +608,219 @@ fn check_command(input: PathBuf, verbose: bool) -> ExitCode {
     let program = match parser.parse_program() {
         Ok(prog) => prog,
         Err(errors) => {
-            for err in &errors {
-                report_error(
-                    "E1000",
-                    "Parse error",
-                    &err.message,
-                    err.span.start,
-                    err.span.end,
-                    &filename,
-                    &source,
-                );
+            report_errors(errors.iter().map(Diagnostic::parse), filename, source, max_errors);
+            return Err(errors.len());
+        }
+    };
+
+    // Type check
+    let errors = pipeline::apply_expect_errors(source, zaco_typeck::check_program(&program).err().unwrap_or_default());
+    if errors.is_empty() {
+        return Ok(());
+    }
+    report_errors(errors.iter().map(Diagnostic::from_type_error), filename, source, max_errors);
+    Err(errors.len())
+}
+
+fn test_command(path: PathBuf, checked: bool, verbose: bool) -> ExitCode {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        collect_test_files(&path, &mut files);
+        files.sort();
+    } else {
+        files.push(path.clone());
+    }
+    if files.is_empty() {
+        eprintln!("No *.test.ts files found in {}", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    let options = CompileOptions { checked, verbose, ..CompileOptions::default() };
+    let out_dir = std::env::temp_dir().join(format!("zaco_test_{}", std::process::id()));
+    if let Err(e) = fs::create_dir_all(&out_dir) {
+        eprintln!("Error creating {}: {}", out_dir.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    let started = Instant::now();
+    let mut parse_cache = ParseCache::new();
+    let mut failed = 0;
+    for (i, file) in files.iter().enumerate() {
+        let file_started = Instant::now();
+        let exe = out_dir.join(format!("test_{}", i));
+        let built = file
+            .canonicalize()
+            .map_err(|e| eprintln!("Error resolving {}: {}", file.display(), e))
+            .and_then(|input| {
+                let options = CompileOptions { inputs: vec![Input::File(input)], ..options.clone() };
+                build_program(&options, &exe, &EmitMode::Exe, None, None, &mut parse_cache)
+            });
+        let (passed, stdout, stderr) = match built.map(|_| Command::new(&exe).output()) {
+            Ok(Ok(output)) => {
+                let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+                // An escaped assertion normally exits 1; don't let a forced exit code hide it
+                let passed = output.status.success() && !stderr.contains("Uncaught exception: AssertionError");
+                (passed, String::from_utf8_lossy(&output.stdout).into_owned(), stderr)
+            }
+            Ok(Err(e)) => (false, String::new(), format!("failed to run test executable: {}", e)),
+            Err(()) => (false, String::new(), "failed to compile".to_string()),
+        };
+        let elapsed = file_started.elapsed().as_secs_f64() * 1000.0;
+
+        println!("{} {} ({:.0} ms)", if passed { "PASS" } else { "FAIL" }, file.display(), elapsed);
+        let shown = if passed { stdout } else { stdout + &stderr };
+        for line in shown.lines() {
+            println!("  {}", line);
+        }
+        if !passed {
+            failed += 1;
+        }
+    }
+    let _ = fs::remove_dir_all(&out_dir);
+
+    println!(
+        "\nTest files: {} passed, {} failed, {} total ({:.0} ms)",
+        files.len() - failed,
+        failed,
+        files.len(),
+        started.elapsed().as_secs_f64() * 1000.0
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Collect `*.test.ts` files under `dir`, skipping hidden directories and
+/// node_modules.
+fn collect_test_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" {
+                collect_test_files(&path, files);
             }
+        } else if name.ends_with(".test.ts") {
+            files.push(path);
+        }
+    }
+}
+
+/// Read inputs from stdin until EOF or `.exit`. There is no JIT backend, so
+/// every input recompiles the session so far into an executable and runs it;
+/// only the output the new input adds is shown. Statements that compile are
+/// kept in the session, and a lone expression prints its value instead.
+fn repl_command(verbose: bool) -> ExitCode {
+    let out_dir = std::env::temp_dir().join(format!("zaco_repl_{}", std::process::id()));
+    let out_dir = match fs::create_dir_all(&out_dir).and_then(|_| out_dir.canonicalize()) {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("Error creating {}: {}", out_dir.display(), e);
             return ExitCode::FAILURE;
         }
     };
+    let session_file = out_dir.join("repl.ts");
+    let options = CompileOptions { inputs: vec![Input::File(session_file.clone())], verbose, ..CompileOptions::default() };
+    let exe = out_dir.join("repl");
+
+    let interactive = io::stdin().is_terminal();
+    let mut session = String::new();
+    // Length of the output the kept statements produce
+    let mut shown = 0;
+    let mut pending = String::new();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("{}", if pending.is_empty() { "> " } else { "... " });
+            let _ = io::stdout().flush();
+        }
+        let Some(Ok(line)) = lines.next() else { break };
+        if pending.is_empty() && line.trim() == ".exit" {
+            break;
+        }
+        pending.push_str(&line);
+        pending.push('\n');
+        // Keep reading while brackets are open, so blocks can span lines
+        let depth: i32 = pending
+            .chars()
+            .map(|c| match c {
+                '{' | '(' | '[' => 1,
+                '}' | ')' | ']' => -1,
+                _ => 0,
+            })
+            .sum();
+        if depth > 0 {
+            continue;
+        }
+        let input = std::mem::take(&mut pending);
+        if input.trim().is_empty() {
+            continue;
+        }
 
-    // Type check
-    match zaco_typeck::check_program(&program) {
-        Ok(_) => {
-            println!("Type check passed!");
-            ExitCode::SUCCESS
+        let echo = repl_echoes(&input);
+        let candidate = if echo {
+            format!("{}console.log({});\n", session, input.trim().trim_end_matches(';'))
+        } else {
+            format!("{}{}", session, input)
+        };
+        if let Err(e) = fs::write(&session_file, &candidate) {
+            eprintln!("Error writing {}: {}", session_file.display(), e);
+            continue;
         }
-        Err(errors) => {
-            for err in &errors {
-                let msg = err.kind.to_string();
-                report_error(
-                    "E2000",
-                    "Type error",
-                    &msg,
-                    err.span.start,
-                    err.span.end,
-                    &filename,
-                    &source,
-                );
+        let mut parse_cache = ParseCache::new();
+        if build_program(&options, &exe, &EmitMode::Exe, None, None, &mut parse_cache).is_err() {
+            continue;
+        }
+        let output = match Command::new(&exe).output() {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("Error running {}: {}", exe.display(), e);
+                continue;
             }
-            ExitCode::FAILURE
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        print!("{}", stdout.get(shown..).unwrap_or_default());
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        let _ = io::stdout().flush();
+        if output.status.success() && !echo {
+            session = candidate;
+            shown = stdout.len();
         }
     }
+    let _ = fs::remove_dir_all(&out_dir);
+    ExitCode::SUCCESS
+}
+
+/// Whether a REPL input is a lone expression whose value should be printed.
+/// Assignments, updates and `console` calls run for their effect instead.
+fn repl_echoes(input: &str) -> bool {
+    use zaco_ast::{Expr, Stmt, UnaryOp};
+
+    let tokens = Lexer::new(input).tokenize();
+    if tokens.iter().any(|t| t.kind == TokenKind::Error) {
+        return false;
+    }
+    let Ok(program) = zaco_parser::Parser::new(tokens).parse_program() else {
+        return false;
+    };
+    let [item] = program.items.as_slice() else { return false };
+    let ModuleItem::Stmt(stmt) = &item.value else { return false };
+    let Stmt::Expr(expr) = &stmt.value else { return false };
+    match &expr.value {
+        Expr::Assignment { .. } => false,
+        Expr::Unary { op, .. } => !matches!(
+            op,
+            UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement
+        ),
+        Expr::Call { callee, .. } => !matches!(
+            &callee.value,
+            Expr::Member { object, .. } if matches!(&object.value, Expr::Ident(ident) if ident.name == "console")
+        ),
+        _ => true,
+    }
 }
 
 fn lex_command(input: PathBuf, positions: bool) -> ExitCode {
@@ -379,7 +832,7 @@ fn lex_command(input: PathBuf, positions: bool) -> ExitCode {
         }
     };
 
-    let filename = input.to_string_lossy().to_string();
+    let filename = source_name(&input);
 
     let mut lexer = Lexer::new(&source);
     let tokens = lexer.tokenize();
@@ -409,7 +862,7 @@ fn lex_command(input: PathBuf, positions: bool) -> ExitCode {
     let error_count = tokens.iter().filter(|t| t.kind == TokenKind::Error).count();
     if error_count > 0 {
         println!("\nLexer errors found: {}", error_count);
-        report_lexer_errors(&tokens, &filename, &source);
+        report_lexer_errors(&tokens, &filename, &source, None);
         return ExitCode::FAILURE;
     }
 
@@ -425,14 +878,14 @@ fn parse_command(input: PathBuf, _pretty: bool) -> ExitCode {
         }
     };
 
-    let filename = input.to_string_lossy().to_string();
+    let filename = source_name(&input);
 
     let mut lexer = Lexer::new(&source);
     let tokens = lexer.tokenize();
 
     let has_errors = tokens.iter().any(|t| t.kind == TokenKind::Error);
     if has_errors {
-        report_lexer_errors(&tokens, &filename, &source);
+        report_lexer_errors(&tokens, &filename, &source, None);
         return ExitCode::FAILURE;
     }
 
@@ -443,17 +896,7 @@ fn parse_command(input: PathBuf, _pretty: bool) -> ExitCode {
             ExitCode::SUCCESS
         }
         Err(errors) => {
-            for err in &errors {
-                report_error(
-                    "E1000",
-                    "Parse error",
-                    &err.message,
-                    err.span.start,
-                    err.span.end,
-                    &filename,
-                    &source,
-                );
-            }
+            report_errors(errors.iter().map(Diagnostic::parse), &filename, &source, None);
             ExitCode::FAILURE
         }
     }
@@ -461,119 +904,181 @@ fn parse_command(input: PathBuf, _pretty: bool) -> ExitCode {
 
 // Helper functions
 
-fn read_source_file(path: &PathBuf) -> io::Result<String> {
+/// Read an input file; `-` reads standard input
+fn read_source_file(path: &Path) -> io::Result<String> {
+    if path == Path::new("-") {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        return Ok(source);
+    }
     fs::read_to_string(path)
 }
 
-fn report_lexer_errors(tokens: &[Token], filename: &str, source: &str) {
-    for token in tokens.iter().filter(|t| t.kind == TokenKind::Error) {
-        report_error(
-            "E0001",
-            "Lexical error",
-            &token.value,
-            token.span.start,
-            token.span.end,
-            filename,
-            source,
-        );
+/// The name diagnostics give an input file
+fn source_name(path: &Path) -> String {
+    if path == Path::new("-") {
+        "<stdin>".to_string()
+    } else {
+        path.to_string_lossy().into_owned()
     }
 }
 
-fn report_error(code: &str, title: &str, message: &str, start: usize, end: usize, filename: &str, source: &str) {
-    let span = (filename, start..end);
-    Report::build(ReportKind::Error, span.clone())
-        .with_code(code)
-        .with_message(title)
-        .with_label(
-            Label::new(span)
-                .with_message(message)
-                .with_color(Color::Red),
-        )
-        .finish()
-        .print((filename, Source::from(source)))
-        .unwrap();
+fn report_lexer_errors(tokens: &[Token], filename: &str, source: &str, max_errors: Option<usize>) {
+    let errors = tokens.iter().filter(|t| t.kind == TokenKind::Error).map(Diagnostic::lexical);
+    report_errors(errors, filename, source, max_errors);
 }
 
-/// Find the runtime C source file, searching common locations.
-fn find_runtime_source(input_path: &PathBuf) -> Option<PathBuf> {
-    // 1. Check ZACO_RUNTIME_C environment variable
-    if let Ok(env_path) = std::env::var("ZACO_RUNTIME_C") {
-        let p = PathBuf::from(env_path);
-        if p.exists() {
-            return Some(p);
-        }
-    }
+/// Report one file's errors (see `DiagnosticSink`).
+fn report_errors(
+    errors: impl IntoIterator<Item = Diagnostic>,
+    filename: &str,
+    source: &str,
+    max_errors: Option<usize>,
+) {
+    let mut sink = DiagnosticSink::new(max_errors);
+    sink.extend(errors);
+    sink.report(filename, source);
+}
 
-    // 2. Search relative paths
-    let candidates = [
-        // Relative to the input file's directory
-        input_path
-            .parent()
-            .map(|p| p.join("runtime/zaco_runtime.c")),
-        // Relative to CWD
-        Some(PathBuf::from("runtime/zaco_runtime.c")),
-    ];
-
-    for candidate in candidates.iter().flatten() {
-        if candidate.exists() {
-            return Some(candidate.clone());
+/// Report the errors of a failed build: those in a file against its
+/// source, the ones about the whole program as a line each.
+fn report_diagnostics(diagnostics: Vec<Diagnostic>, parse_cache: &ParseCache, max_errors: Option<usize>) {
+    let mut by_file: Vec<(PathBuf, Vec<Diagnostic>)> = Vec::new();
+    for diagnostic in diagnostics {
+        let Some(file) = diagnostic.file.clone() else {
+            eprintln!("{}: {}", diagnostic.title, diagnostic.message);
+            continue;
+        };
+        match by_file.iter_mut().find(|(path, _)| *path == file) {
+            Some((_, errors)) => errors.push(diagnostic),
+            None => by_file.push((file, vec![diagnostic])),
         }
     }
-
-    // 3. Try to find via the executable's location
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(exe_dir) = exe.parent() {
-            // Try sibling directory (e.g., installed layout)
-            let runtime = exe_dir.join("../runtime/zaco_runtime.c");
-            if runtime.exists() {
-                return Some(runtime);
-            }
-            // Try share directory (e.g., /usr/local/share/zaco/runtime)
-            let runtime = exe_dir.join("../share/zaco/runtime/zaco_runtime.c");
-            if runtime.exists() {
-                return Some(runtime);
-            }
-        }
+    for (file, errors) in by_file {
+        // A module that failed to parse isn't in the cache
+        let source = match parse_cache.get(&file) {
+            Some((source, _)) => source.clone(),
+            None => fs::read_to_string(&file).unwrap_or_default(),
+        };
+        report_errors(errors, &file.to_string_lossy(), &source, max_errors);
     }
+}
 
-    None
+/// A diagnostic as a `DiagnosticSink` reports it, with how many times its
+/// message was repeated right after it.
+#[derive(Debug)]
+struct Reported {
+    diagnostic: Diagnostic,
+    repeats: usize,
 }
 
-/// Find the Rust runtime static library (.a), searching common locations.
-fn find_rust_runtime(c_runtime_path: &std::path::Path) -> Option<PathBuf> {
-    // 1. Check ZACO_RUNTIME_RS environment variable
-    if let Ok(env_path) = std::env::var("ZACO_RUNTIME_RS") {
-        let p = PathBuf::from(env_path);
-        if p.exists() {
-            return Some(p);
-        }
+/// Collects the diagnostics of one file before reporting them. Identical
+/// (code, span, message) ones are reported once, a run of the same message
+/// as its first with a repeat count, and after `max_errors` the rest are
+/// only counted. Lexical and parse errors come before type errors, which
+/// they usually cause.
+struct DiagnosticSink {
+    max_errors: Option<usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticSink {
+    fn new(max_errors: Option<usize>) -> Self {
+        Self { max_errors, diagnostics: Vec::new() }
     }
 
-    // 2. Derive from C runtime location (sibling directory)
-    if let Some(runtime_dir) = c_runtime_path.parent() {
-        let candidate = runtime_dir.join("zaco_runtime_rs/target/release/libzaco_runtime_rs.a");
-        if candidate.exists() {
-            return Some(candidate);
-        }
+    fn extend(&mut self, diagnostics: impl IntoIterator<Item = Diagnostic>) {
+        self.diagnostics.extend(diagnostics);
     }
 
-    // 3. Relative to CWD
-    let cwd_candidate = PathBuf::from("runtime/zaco_runtime_rs/target/release/libzaco_runtime_rs.a");
-    if cwd_candidate.exists() {
-        return Some(cwd_candidate);
+    /// The diagnostics to show, in order, and how many more are left out
+    fn reports(mut self) -> (Vec<Reported>, usize) {
+        // The first digit of a code numbers its phase (E0001 lexing, E1000
+        // parsing, E2xxx type checking, ...); within a phase, source order
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.code.get(..2));
+        let mut seen = HashSet::new();
+        let mut reported: Vec<Reported> = Vec::new();
+        let mut suppressed = 0;
+        // Whether the last unique diagnostic was shown, so a repeat of its
+        // message continues its run
+        let mut last_shown = false;
+        for diagnostic in self.diagnostics {
+            if !seen.insert((diagnostic.code, diagnostic.message.clone(), diagnostic.start, diagnostic.end)) {
+                continue;
+            }
+            match reported.last_mut() {
+                Some(last)
+                    if last_shown
+                        && last.diagnostic.code == diagnostic.code
+                        && last.diagnostic.message == diagnostic.message =>
+                {
+                    last.repeats += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            last_shown = self.max_errors.is_none_or(|max| reported.len() < max);
+            if !last_shown {
+                suppressed += 1;
+                continue;
+            }
+            reported.push(Reported { diagnostic, repeats: 0 });
+        }
+        (reported, suppressed)
     }
 
-    // 4. Relative to compiler executable
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(exe_dir) = exe.parent() {
-            let candidate = exe_dir.join("../runtime/zaco_runtime_rs/target/release/libzaco_runtime_rs.a");
-            if candidate.exists() {
-                return Some(candidate);
+    fn report(self, filename: &str, source: &str) {
+        let max_errors = self.max_errors;
+        let (reported, suppressed) = self.reports();
+        for Reported { diagnostic, repeats } in &reported {
+            report_error(diagnostic, filename, source);
+            if *repeats > 0 {
+                println!("previous error repeated {} time{}", repeats, if *repeats == 1 { "" } else { "s" });
             }
         }
+        if suppressed > 0 {
+            println!(
+                "{} more error{} not shown (--max-errors {})",
+                suppressed,
+                if suppressed == 1 { "" } else { "s" },
+                max_errors.unwrap_or_default()
+            );
+        }
     }
+}
 
-    None
+/// The source range a label underlines. Spans are end-exclusive, so an
+/// empty one (an error at the end-of-file token) would get an empty label;
+/// it covers the character there, or the last one at the end of the file.
+fn label_range(start: usize, end: usize, source: &str) -> std::ops::Range<usize> {
+    if end > start {
+        start..end
+    } else if let Some(ch) = source.get(start..).and_then(|rest| rest.chars().next()) {
+        start..start + ch.len_utf8()
+    } else {
+        let last = source.get(..start).and_then(|text| text.chars().next_back()).map_or(0, char::len_utf8);
+        start - last..start
+    }
+}
+
+fn report_error(diagnostic: &Diagnostic, filename: &str, source: &str) {
+    let span = (filename, label_range(diagnostic.start, diagnostic.end, source));
+    let mut report = Report::build(ReportKind::Error, span.clone())
+        .with_code(diagnostic.code)
+        .with_message(diagnostic.title)
+        .with_label(
+            Label::new(span)
+                .with_message(&diagnostic.message)
+                .with_color(Color::Red),
+        );
+    for (message, start, end) in &diagnostic.labels {
+        report = report.with_label(
+            Label::new((filename, label_range(*start, *end, source)))
+                .with_message(message)
+                .with_color(Color::Blue),
+        );
+    }
+    report.finish().print((filename, Source::from(source))).unwrap();
 }
 
 /// Dump IR module for --emit ir.
@@ -607,579 +1112,89 @@ fn dump_ir(module: &zaco_ir::IrModule) {
     }
 }
 
-fn link_executable(
-    object_bytes: &[u8],
-    output_path: &PathBuf,
-    runtime_path: Option<&std::path::Path>,
-    verbose: bool,
-) -> io::Result<()> {
-    let temp_dir = std::env::temp_dir();
-    let pid = std::process::id();
-    let temp_obj = temp_dir.join(format!("zaco_temp_{}.o", pid));
-    fs::write(&temp_obj, object_bytes)?;
-
-    let linker = "cc";
-
-    let mut cmd = Command::new(linker);
-    cmd.arg("-o").arg(output_path);
-
-    // On macOS, suppress linker warnings about missing platform load
-    // commands in Cranelift-generated object files (Cranelift doesn't
-    // emit Mach-O platform metadata).
-    if cfg!(target_os = "macos") {
-        cmd.arg("-Wl,-w");
-    }
-
-    // Add the compiled object file
-    cmd.arg(&temp_obj);
-
-    // Compile and link the C runtime if available
-    if let Some(rt_path) = runtime_path {
-        if verbose {
-            println!("  Using C runtime: {}", rt_path.display());
-        }
-        // Compile runtime.c to .o and link together
-        let temp_rt_obj = temp_dir.join(format!("zaco_runtime_{}.o", pid));
-        let rt_status = Command::new("cc")
-            .args(["-c", "-o"])
-            .arg(&temp_rt_obj)
-            .arg(rt_path)
-            .status()?;
-
-        if !rt_status.success() {
-            let _ = fs::remove_file(&temp_obj);
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to compile runtime.c",
-            ));
-        }
-        cmd.arg(&temp_rt_obj);
-
-        // Link the Rust runtime static library
-        let rust_runtime_lib = find_rust_runtime(rt_path);
-
-        if let Some(ref rust_runtime_lib) = rust_runtime_lib {
-            if verbose {
-                println!("  Using Rust runtime: {}", rust_runtime_lib.display());
-            }
-            cmd.arg(&rust_runtime_lib);
-
-            // Add required linker flags for Rust runtime on macOS
-            if cfg!(target_os = "macos") {
-                cmd.arg("-framework").arg("CoreFoundation");
-                cmd.arg("-framework").arg("Security");
-                cmd.arg("-framework").arg("SystemConfiguration");
-                cmd.arg("-lpthread");
-                cmd.arg("-ldl");
-            }
-        } else {
-            eprintln!("Warning: Rust runtime library not found");
-            eprintln!("To build it, run: cd runtime/zaco_runtime_rs && cargo build --release");
-        }
-
-        let status = cmd.status()?;
-
-        // Clean up temp files
-        let _ = fs::remove_file(&temp_obj);
-        let _ = fs::remove_file(&temp_rt_obj);
-
-        if status.success() {
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Linker exited with status: {}", status),
-            ))
-        }
-    } else {
-        // No runtime — link just the object file (will fail if runtime symbols are referenced)
-        if verbose {
-            println!("  Warning: Runtime not found, linking without it");
-        }
-        let status = cmd.status()?;
-        let _ = fs::remove_file(&temp_obj);
-
-        if status.success() {
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Linker exited with status: {}", status),
-            ))
-        }
-    }
-}
-
-// ============================================================================
-// Module system helper functions
-// ============================================================================
-
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::path::Path;
-use zaco_ast::{ExportDecl, ImportDecl, ModuleItem, Program};
-
-/// Discover all modules starting from an entry point.
-/// Returns a cache of parsed programs to avoid re-parsing during compilation.
-fn discover_modules(
-    entry: &Path,
-    resolver: &ModuleResolver,
-    graph: &mut DepGraph,
-    verbose: bool,
-    parse_cache: &mut HashMap<PathBuf, (String, Program)>,
-) -> Result<(), String> {
-    let mut queue: VecDeque<PathBuf> = VecDeque::new();
-    let mut visited: HashSet<PathBuf> = HashSet::new();
-
-    queue.push_back(entry.to_path_buf());
-
-    while let Some(current_path) = queue.pop_front() {
-        if visited.contains(&current_path) {
-            continue;
-        }
-        visited.insert(current_path.clone());
-
-        // Read and parse the module
-        let source = fs::read_to_string(&current_path).map_err(|e| {
-            format!(
-                "Failed to read module {}: {}",
-                current_path.display(),
-                e
-            )
-        })?;
-
-        let mut lexer = Lexer::new(&source);
-        let tokens = lexer.tokenize();
-
-        let has_errors = tokens.iter().any(|t| t.kind == TokenKind::Error);
-        if has_errors {
-            return Err(format!(
-                "Lexer errors in module: {}",
-                current_path.display()
-            ));
-        }
-
-        let mut parser = zaco_parser::Parser::new(tokens);
-        let program = parser.parse_program().map_err(|errors| {
-            format!(
-                "Parse errors in module {}: {}",
-                current_path.display(),
-                errors
-                    .iter()
-                    .map(|e| e.message.clone())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            )
-        })?;
-
-        // Extract imports and exports
-        let (imports, exports) = extract_imports_exports(&program);
-
-        // Resolve imports to module paths
-        let mut dependencies = Vec::new();
-        for import in &imports {
-            match resolver.resolve(&import.source, &current_path) {
-                Ok(ResolvedModule::LocalFile(path)) => {
-                    dependencies.push(path.clone());
-                    queue.push_back(path);
-                }
-                Ok(ResolvedModule::Builtin(name)) => {
-                    if verbose {
-                        println!("  Note: Skipping built-in module: {}", name);
-                    }
-                }
-                Ok(ResolvedModule::Package(path)) => {
-                    // NPM package resolved successfully
-                    if verbose {
-                        println!("  Resolved NPM package '{}' to: {}", import.source, path.display());
-                    }
-
-                    // If it's a .d.ts file, load type declarations but don't compile
-                    if path.extension().and_then(|s| s.to_str()) == Some("ts")
-                        && path.to_string_lossy().ends_with(".d.ts") {
-                        if verbose {
-                            println!("  Loading type declarations from: {}", path.display());
-                        }
-                        // Load declarations for type checking
-                        match dts_loader::DtsLoader::load_declarations(&path) {
-                            Ok(decls) => {
-                                if verbose {
-                                    println!("    Loaded {} type declarations", decls.len());
-                                }
-                                // Type declarations are loaded but not added to compilation queue
-                            }
-                            Err(e) => {
-                                if verbose {
-                                    println!("    Warning: Failed to load .d.ts: {}", e);
-                                }
-                            }
-                        }
-                    } else {
-                        // Regular package file - add to compilation queue
-                        dependencies.push(path.clone());
-                        queue.push_back(path);
-                    }
-                }
-                Ok(ResolvedModule::PackageNotFound { name: pkg_name, reason }) => {
-                    return Err(format!(
-                        "Cannot resolve import '{}' in {}: package '{}' not found ({})",
-                        import.source,
-                        current_path.display(),
-                        pkg_name,
-                        reason,
-                    ));
-                }
-                Err(e) => {
-                    return Err(format!(
-                        "Failed to resolve import '{}' in {}: {}",
-                        import.source,
-                        current_path.display(),
-                        e
-                    ));
-                }
-            }
-        }
-
-        graph.add_module(current_path.clone(), dependencies, exports);
-
-        // Cache the parsed program to avoid re-parsing during compilation
-        parse_cache.insert(current_path, (source, program));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_lowering_error_in_for_initializer_points_at_it() {
+        let source = "const enum Color { Red }\nfor (let c = Color, i = 0; i < 1; i++) {}\n";
+        let tokens = Lexer::new(source).tokenize();
+        let program = zaco_parser::Parser::new(tokens).parse_program().unwrap();
+        let errors = zaco_ir::lower::Lowerer::new().lower_program(&program).unwrap_err();
+        assert_eq!(&source[errors[0].span.start..errors[0].span.end], "Color");
     }
 
-    Ok(())
-}
-
-/// Extract imports and exports from a program AST
-fn extract_imports_exports(program: &Program) -> (Vec<ImportDecl>, HashSet<String>) {
-    let mut imports = Vec::new();
-    let mut exports = HashSet::new();
-
-    for item in &program.items {
-        match &item.value {
-            ModuleItem::Import(import_decl) => {
-                imports.push(import_decl.clone());
-            }
-            ModuleItem::Export(export_decl) => {
-                extract_export_names(export_decl, &mut exports);
-            }
-            _ => {}
-        }
+    #[test]
+    fn test_empty_spans_label_one_character() {
+        let source = "const x = (1 + 2";
+        assert_eq!(label_range(6, 7, source), 6..7);
+        assert_eq!(label_range(6, 6, source), 6..7);
+        // The end-of-file token labels the last character
+        assert_eq!(label_range(source.len(), source.len(), source), 15..16);
+        assert_eq!(label_range(0, 0, ""), 0..0);
     }
 
-    (imports, exports)
-}
-
-/// Extract exported names from an export declaration
-fn extract_export_names(export_decl: &ExportDecl, exports: &mut HashSet<String>) {
-    match export_decl {
-        ExportDecl::Named { specifiers, .. } => {
-            for spec in specifiers {
-                let name = if let Some(ref exported) = spec.exported {
-                    exported.value.name.clone()
-                } else {
-                    spec.local.value.name.clone()
-                };
-                exports.insert(name);
-            }
-        }
-        ExportDecl::Default(_) | ExportDecl::DefaultDecl(_) => {
-            exports.insert("default".to_string());
-        }
-        ExportDecl::All { as_name, .. } => {
-            if let Some(ref name) = as_name {
-                exports.insert(name.value.name.clone());
-            }
-        }
-        ExportDecl::Decl(decl) => {
-            // Extract the name from the declaration
-            use zaco_ast::Decl;
-            match &decl.value {
-                Decl::Function(func) => {
-                    exports.insert(func.name.value.name.clone());
-                }
-                Decl::Var(var_decl) => {
-                    for declarator in &var_decl.declarations {
-                        if let zaco_ast::Pattern::Ident { name, .. } = &declarator.pattern.value {
-                            exports.insert(name.value.name.clone());
-                        }
-                    }
-                }
-                Decl::Class(class) => {
-                    exports.insert(class.name.value.name.clone());
-                }
-                Decl::TypeAlias(alias) => {
-                    exports.insert(alias.name.value.name.clone());
-                }
-                Decl::Interface(iface) => {
-                    exports.insert(iface.name.value.name.clone());
-                }
-                Decl::Enum(enum_decl) => {
-                    exports.insert(enum_decl.name.value.name.clone());
-                }
-                _ => {}
-            }
-        }
+    fn diagnostic(code: &'static str, message: &str, start: usize) -> Diagnostic {
+        Diagnostic::new(code, "", message.to_string(), start, start + 1)
     }
-}
 
-/// Compile a single module (typecheck, lower to IR).
-/// Uses cached parse results when available to avoid re-parsing.
-fn compile_single_module(
-    module_path: &Path,
-    emit: &EmitMode,
-    verbose: bool,
-    parse_cache: &mut HashMap<PathBuf, (String, Program)>,
-    module_name: Option<&str>,
-    func_id_offset: usize,
-    struct_id_offset: usize,
-) -> Result<zaco_ir::IrModule, ()> {
-    // Use cached parse result if available, otherwise parse from scratch
-    let (source, program) = if let Some(cached) = parse_cache.remove(module_path) {
-        cached
-    } else {
-        let source = fs::read_to_string(module_path).map_err(|e| {
-            eprintln!("Error reading {}: {}", module_path.display(), e);
-        })?;
-
-        let mut lexer = Lexer::new(&source);
-        let tokens = lexer.tokenize();
-
-        let has_errors = tokens.iter().any(|t| t.kind == TokenKind::Error);
-        if has_errors {
-            let filename = module_path.to_string_lossy().to_string();
-            report_lexer_errors(&tokens, &filename, &source);
-            return Err(());
-        }
-
-        let mut parser = zaco_parser::Parser::new(tokens);
-        let program = match parser.parse_program() {
-            Ok(prog) => prog,
-            Err(errors) => {
-                let filename = module_path.to_string_lossy().to_string();
-                for err in &errors {
-                    report_error(
-                        "E1000",
-                        "Parse error",
-                        &err.message,
-                        err.span.start,
-                        err.span.end,
-                        &filename,
-                        &source,
-                    );
-                }
-                return Err(());
-            }
-        };
-
-        (source, program)
-    };
-
-    let filename = module_path.to_string_lossy().to_string();
-
-    if matches!(emit, EmitMode::Ast) {
-        println!("AST for {}:", filename);
-        println!("{:#?}", program);
+    fn summarize(sink: DiagnosticSink) -> (Vec<(&'static str, String, usize, usize)>, usize) {
+        let (reported, suppressed) = sink.reports();
+        let reported = reported
+            .into_iter()
+            .map(|r| (r.diagnostic.code, r.diagnostic.message, r.diagnostic.start, r.repeats))
+            .collect();
+        (reported, suppressed)
     }
 
-    // Phase 3: Type checking
-    let _typed_program = match zaco_typeck::check_program(&program) {
-        Ok(typed) => typed,
-        Err(errors) => {
-            for err in &errors {
-                let msg = err.kind.to_string();
-                report_error(
-                    "E2000",
-                    "Type error",
-                    &msg,
-                    err.span.start,
-                    err.span.end,
-                    &filename,
-                    &source,
-                );
-            }
-            return Err(());
-        }
-    };
-
-    // Phase 4: AST → IR lowering
-    let lowerer = {
-        let l = zaco_ir::lower::Lowerer::new()
-            .with_func_id_offset(func_id_offset)
-            .with_struct_id_offset(struct_id_offset)
-            .with_file_path(module_path.to_string_lossy().into_owned());
-        if let Some(name) = module_name {
-            l.with_module_name(name.to_string())
-        } else {
-            l
-        }
-    };
-    let ir_module = match lowerer.lower_program(&program) {
-        Ok(module) => module,
-        Err(errors) => {
-            for err in &errors {
-                report_error(
-                    "E3000",
-                    "Lowering error",
-                    &err.message,
-                    err.span.start,
-                    err.span.end,
-                    &filename,
-                    &source,
-                );
-            }
-            return Err(());
-        }
-    };
-
-    if verbose {
-        println!(
-            "  Compiled: {} ({} functions)",
-            filename,
-            ir_module.functions.len()
+    #[test]
+    fn test_sink_dedups_and_groups_repeated_messages() {
+        let mut sink = DiagnosticSink::new(None);
+        sink.extend(vec![
+            diagnostic("E2000", "mismatch", 0),
+            diagnostic("E2000", "mismatch", 0),
+            diagnostic("E2000", "mismatch", 5),
+            diagnostic("E2000", "mismatch", 9),
+            diagnostic("E2000", "undefined variable 'y'", 12),
+            diagnostic("E2000", "mismatch", 20),
+        ]);
+        let (reported, suppressed) = summarize(sink);
+        assert_eq!(
+            reported,
+            vec![
+                ("E2000", "mismatch".to_string(), 0, 2),
+                ("E2000", "undefined variable 'y'".to_string(), 12, 0),
+                ("E2000", "mismatch".to_string(), 20, 0),
+            ]
         );
+        assert_eq!(suppressed, 0);
     }
 
-    Ok(ir_module)
-}
-
-/// Merge multiple IR modules into a single module (order-preserving).
-///
-/// User-defined functions are all included (no name-based dedup — each module
-/// now has uniquely-named wrappers via `__module_init_<name>` prefixing).
-/// Only extern function *declarations* are deduplicated (safe — they're just declarations).
-fn merge_ir_modules(
-    module_irs: Vec<(PathBuf, zaco_ir::IrModule)>,
-) -> zaco_ir::IrModule {
-    let mut merged = zaco_ir::IrModule::new();
-
-    for (_path, ir_module) in module_irs {
-        // Merge all user-defined functions without name-based dedup
-        for func in ir_module.functions {
-            merged.add_function(func);
-        }
-
-        // Merge structs
-        for struct_def in ir_module.structs {
-            merged.add_struct(struct_def);
-        }
-
-        // Merge globals
-        for (name, ty, init) in ir_module.globals {
-            merged.add_global(name, ty, init);
-        }
-
-        // Merge string literals
-        for lit in ir_module.string_literals {
-            merged.intern_string(lit);
-        }
-
-        // Merge extern function declarations (deduplicate by name — safe for declarations)
-        for ext_func in ir_module.extern_functions {
-            if !merged
-                .extern_functions
-                .iter()
-                .any(|ef| ef.name == ext_func.name)
-            {
-                merged.extern_functions.push(ext_func);
-            }
-        }
-    }
-
-    merged
-}
-
-/// Derive a safe init function name from a module's file path.
-/// Includes the parent directory for readability plus a hash suffix of the full
-/// path to guarantee uniqueness even when multiple modules share the same
-/// parent+stem (e.g., `x/a/index.ts` vs `y/a/index.ts`).
-/// e.g., "x/a/index.ts" → "a_index_1a2b3c4d"
-/// Characters that aren't alphanumeric or underscore are replaced with '_'.
-fn module_path_to_init_name(path: &Path) -> String {
-    let stem = path
-        .file_stem()
-        .unwrap_or_default()
-        .to_string_lossy();
-    let parent = path
-        .parent()
-        .and_then(|p| p.file_name())
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_default();
-
-    let readable = if parent.is_empty() {
-        stem.to_string()
-    } else {
-        format!("{}_{}", parent, stem)
-    };
-
-    let sanitized: String = readable
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
-        .collect();
-
-    // Append hash of full path for uniqueness
-    let mut hasher = DefaultHasher::new();
-    path.hash(&mut hasher);
-    let hash = hasher.finish();
-
-    format!("{}_{:08x}", sanitized, hash as u32)
-}
-
-/// Inject calls to all `__module_init_*` functions at the start of "main"'s entry block.
-/// This ensures dependency modules' top-level code runs before the entry module's code.
-fn inject_module_init_calls(module: &mut zaco_ir::IrModule) {
-    // Collect names of all __module_init_* functions
-    let init_names: Vec<String> = module
-        .functions
-        .iter()
-        .filter(|f| f.name.starts_with("__module_init_"))
-        .map(|f| f.name.clone())
-        .collect();
-
-    if init_names.is_empty() {
-        return;
-    }
-
-    // Find the "main" function and inject calls at the start of its entry block
-    if let Some(main_func) = module.functions.iter_mut().find(|f| f.name == "main") {
-        let entry_block = main_func.entry_block;
-
-        // Build Call instructions for each init function
-        let mut init_calls: Vec<zaco_ir::Instruction> = Vec::new();
-        for name in &init_names {
-            init_calls.push(zaco_ir::Instruction::Call {
-                dest: None,
-                func: zaco_ir::Value::Const(zaco_ir::Constant::Str(name.clone())),
-                args: vec![],
-            });
-        }
-
-        // Prepend init calls before existing instructions in the entry block
-        if let Some(block) = main_func.blocks.iter_mut().find(|b| b.id == entry_block) {
-            let existing = std::mem::take(&mut block.instructions);
-            block.instructions = init_calls;
-            block.instructions.extend(existing);
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::Path;
-
     #[test]
-    fn test_module_path_to_init_name_no_collision() {
-        let name1 = module_path_to_init_name(Path::new("x/a/index.ts"));
-        let name2 = module_path_to_init_name(Path::new("y/a/index.ts"));
-        assert_ne!(
-            name1, name2,
-            "Different paths with same stem should produce different init names"
-        );
-
-        // Same path should produce same name
-        let name3 = module_path_to_init_name(Path::new("x/a/index.ts"));
+    fn test_sink_caps_errors_and_reports_parse_errors_first() {
+        let mut sink = DiagnosticSink::new(Some(3));
+        sink.extend((0..5).map(|i| diagnostic("E2000", &format!("type {}", i), i)));
+        sink.extend(vec![diagnostic("E1000", "expected ';'", 40), diagnostic("E1000", "expected ';'", 44)]);
+        let (reported, suppressed) = summarize(sink);
         assert_eq!(
-            name1, name3,
-            "Same path should produce same init name"
+            reported,
+            vec![
+                ("E1000", "expected ';'".to_string(), 40, 1),
+                ("E2000", "type 0".to_string(), 0, 0),
+                ("E2000", "type 1".to_string(), 1, 0),
+            ]
         );
+        assert_eq!(suppressed, 3);
+    }
+
+    #[test]
+    fn test_parse_define_values() {
+        assert_eq!(parse_define("DEBUG=false"), Ok(("DEBUG".to_string(), Literal::Boolean(false))));
+        assert_eq!(parse_define("LEVEL=2.5"), Ok(("LEVEL".to_string(), Literal::Number(2.5))));
+        assert_eq!(parse_define("MODE=prod"), Ok(("MODE".to_string(), Literal::String("prod".to_string()))));
+        assert_eq!(parse_define("VERSION=\"1.0\""), Ok(("VERSION".to_string(), Literal::String("1.0".to_string()))));
+        assert_eq!(parse_define("EMPTY="), Ok(("EMPTY".to_string(), Literal::String(String::new()))));
+        assert!(parse_define("DEBUG").is_err());
+        assert!(parse_define("2FAST=true").is_err());
     }
 }
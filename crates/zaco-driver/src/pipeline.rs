@@ -0,0 +1,1168 @@
+//! The compilation pipeline: module discovery, type checking and lowering
+//! of each module, merging, and native code generation.
+//!
+//! `compile` runs every phase and returns the object code; the CLI calls
+//! the phases one by one so it can stop after any of them.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use zaco_ast::{ExportDecl, ImportDecl, Literal, ModuleItem, Node, Program};
+use zaco_codegen::CodeGenerator;
+use zaco_ir::lower::ModuleExports;
+use zaco_ir::IrModule;
+use zaco_lexer::{Lexer, Token, TokenKind};
+use zaco_typeck::{TypeError, TypeErrorKind};
+
+use crate::dts_loader;
+use crate::{DepGraph, ModuleResolver, ReExport, ResolvedModule};
+
+/// Parsed modules by path, with their source. Sharing one across builds
+/// (as `zaco test` does) parses the modules they have in common once.
+pub type ParseCache = HashMap<PathBuf, (String, Program)>;
+
+/// A module to compile
+#[derive(Clone, Debug)]
+pub enum Input {
+    /// A file on disk
+    File(PathBuf),
+    /// Source text standing in for the file at `path`; its imports are
+    /// resolved relative to that path
+    Source { path: PathBuf, source: String },
+}
+
+impl Input {
+    pub fn path(&self) -> &Path {
+        match self {
+            Input::File(path) | Input::Source { path, .. } => path,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum AllocStrategy {
+    /// Reference-counted heap allocation (default)
+    #[default]
+    Rc,
+    /// Bump arena released at exit; ref counting is skipped
+    Arena,
+}
+
+/// What to compile and how.
+#[derive(Clone, Debug, Default)]
+pub struct CompileOptions {
+    /// The modules to compile. The first is the entry, whose top-level code
+    /// becomes `main`; only a library takes more than one.
+    pub inputs: Vec<Input>,
+    /// Build a library: there is no `main`, and the exported
+    /// `zaco_lib_init` runs every module's top-level code
+    pub library: bool,
+    /// Insert runtime checks (null receivers throw a catchable TypeError)
+    pub checked: bool,
+    pub alloc_strategy: AllocStrategy,
+    /// Profile calls and allocations
+    pub instrument: bool,
+    /// Main thread stack size in bytes, applied when linking
+    pub stack_size: Option<u64>,
+    /// Log each node the lowerer visits to stderr
+    pub trace_lowering: bool,
+    /// Leave out constructs the lowerer does not support instead of
+    /// reporting them
+    pub allow_unsupported: bool,
+    /// Constants from `--define`
+    pub defines: HashMap<String, Literal>,
+    /// Print progress through the phases
+    pub verbose: bool,
+}
+
+/// The result of a successful `compile`
+#[derive(Debug)]
+pub struct CompileOutput {
+    /// The merged IR of every module
+    pub ir: IrModule,
+    /// The object file, ready to be linked with the runtime
+    pub object: Vec<u8>,
+}
+
+/// An error found while compiling: its message and span in `file`, plus
+/// secondary labels pointing at related code. Errors about the program as
+/// a whole (an import cycle, a failed code generator) have no file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Numbers the phase: E0001 lexing, E1000 parsing, E2xxx type
+    /// checking (one code per kind of type error), E3000 lowering, and
+    /// E3001 for a construct only the type checker accepts
+    pub code: &'static str,
+    pub title: &'static str,
+    pub file: Option<PathBuf>,
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+    /// (message, start, end) of each secondary label
+    pub labels: Vec<(String, usize, usize)>,
+}
+
+impl Diagnostic {
+    pub fn new(code: &'static str, title: &'static str, message: String, start: usize, end: usize) -> Self {
+        Self { code, title, file: None, message, start, end, labels: Vec::new() }
+    }
+
+    /// An error about the program rather than a place in a file
+    pub fn program(title: &'static str, message: String) -> Self {
+        Self::new("", title, message, 0, 0)
+    }
+
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+
+    /// The error of an `Error` token
+    pub fn lexical(token: &Token) -> Self {
+        Self::new("E0001", "Lexical error", token.value.clone(), token.span.start, token.span.end)
+    }
+
+    pub fn parse(err: &zaco_parser::ParseError) -> Self {
+        Self::new("E1000", "Parse error", err.message.clone(), err.span.start, err.span.end)
+    }
+
+    /// A type error, labelling where its expected type was declared
+    pub fn from_type_error(err: &zaco_typeck::TypeError) -> Self {
+        let mut diagnostic = Self::new(err.kind.code(), "Type error", err.kind.to_string(), err.span.start, err.span.end);
+        if let Some((span, message)) = err.related.as_deref() {
+            diagnostic.labels.push((message.clone(), span.start, span.end));
+        }
+        diagnostic
+    }
+
+    /// A lowering error. One for a construct the lowerer cannot handle yet
+    /// is the compiler's fault, since the type checker accepted it
+    pub fn lowering(err: &zaco_ir::lower::LowerError) -> Self {
+        if err.unsupported {
+            let title = "Internal compiler error: accepted by the type checker, rejected by the lowerer (please report)";
+            return Self::new("E3001", title, err.message.clone(), err.span.start, err.span.end);
+        }
+        Self::new("E3000", "Lowering error", err.message.clone(), err.span.start, err.span.end)
+    }
+}
+
+/// Compile `options.inputs` to an object file.
+///
+/// ```no_run
+/// use zaco_driver::{CompileOptions, Input};
+///
+/// let options = CompileOptions {
+///     inputs: vec![Input::Source { path: "main.ts".into(), source: "console.log(1);".into() }],
+///     ..Default::default()
+/// };
+/// let output = zaco_driver::compile(&options).unwrap();
+/// assert!(!output.object.is_empty());
+/// ```
+pub fn compile(options: &CompileOptions) -> Result<CompileOutput, Vec<Diagnostic>> {
+    let mut parse_cache = ParseCache::new();
+    let dep_graph = discover(options, &mut parse_cache)?;
+    let ir = lower(options, &dep_graph, &parse_cache)?;
+    let object = codegen(&ir, options).map_err(|e| vec![e])?;
+    Ok(CompileOutput { ir, object })
+}
+
+/// Parse the inputs and every module they import, building the dependency
+/// graph. Nothing is type checked yet.
+pub fn discover(options: &CompileOptions, parse_cache: &mut ParseCache) -> Result<DepGraph, Vec<Diagnostic>> {
+    if options.verbose {
+        println!("\n[Phase 0] Discovering module dependencies...");
+    }
+
+    let Some(entry) = options.inputs.first() else {
+        return Err(vec![Diagnostic::program("Error", "no input to compile".to_string())]);
+    };
+    for input in &options.inputs {
+        if let Input::Source { path, source } = input {
+            let program = parse_module(path, source)?;
+            parse_cache.insert(path.clone(), (source.clone(), program));
+        }
+    }
+
+    let mut dep_graph = DepGraph::new();
+    let base_dir = entry.path().parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let resolver = ModuleResolver::new(base_dir);
+    for input in &options.inputs {
+        discover_modules(input.path(), &resolver, &mut dep_graph, options.verbose, parse_cache)?;
+    }
+
+    dep_graph.set_entry(entry.path().to_path_buf());
+    dep_graph.resolve_reexports();
+    Ok(dep_graph)
+}
+
+/// Type check and lower each module of `dep_graph` in dependency order,
+/// then merge them into one IR module.
+pub fn lower(options: &CompileOptions, dep_graph: &DepGraph, parse_cache: &ParseCache) -> Result<IrModule, Vec<Diagnostic>> {
+    let module_irs = lower_modules(options, dep_graph, parse_cache)?;
+
+    // Merge all IR modules into one
+    if options.verbose {
+        println!("\n[Phase 4.5] Merging IR modules...");
+    }
+
+    let mut merged_ir = merge_ir_modules(module_irs).map_err(|e| vec![Diagnostic::program("Error", e)])?;
+
+    if options.library {
+        add_lib_init(&mut merged_ir);
+    } else {
+        // Inject calls to __module_init_* functions at the start of "main"'s entry block.
+        // This ensures all dependency modules' top-level code runs before the entry module.
+        let init_names = module_init_names(&merged_ir);
+        inject_module_init_calls(&mut merged_ir, init_names);
+    }
+
+    if options.verbose {
+        println!(
+            "  {} functions, {} string literals",
+            merged_ir.functions.len(),
+            merged_ir.string_literals.len()
+        );
+    }
+
+    Ok(merged_ir)
+}
+
+/// Like `lower`, but keep the modules of a program apart so each can be
+/// compiled to an object of its own. The functions a module uses from
+/// another are exported there and declared as externs where they are used.
+pub fn lower_separately(
+    options: &CompileOptions,
+    dep_graph: &DepGraph,
+    parse_cache: &ParseCache,
+) -> Result<Vec<(PathBuf, IrModule)>, Vec<Diagnostic>> {
+    let mut module_irs = lower_modules(options, dep_graph, parse_cache)?;
+    let error = |e: String| vec![Diagnostic::program("Error", e)];
+
+    // Each object with workers would define its own `__zaco_worker_table`
+    let mut spawning = module_irs.iter().filter(|(_, m)| !m.worker_entries.is_empty()).map(|(p, _)| p);
+    if let (Some(first), Some(second)) = (spawning.next(), spawning.next()) {
+        return Err(error(format!(
+            "both {} and {} spawn workers, which separate objects do not support",
+            first.display(),
+            second.display()
+        )));
+    }
+
+    rename_shared_private_functions(&mut module_irs).map_err(error)?;
+
+    let init_names: Vec<String> = module_irs.iter().flat_map(|(_, m)| module_init_names(m)).collect();
+    if let Some((_, entry)) = module_irs.iter_mut().find(|(_, m)| m.find_function("main").is_some()) {
+        inject_module_init_calls(entry, init_names);
+    }
+
+    link_separate_modules(&mut module_irs);
+    Ok(module_irs)
+}
+
+/// Type check and lower each module of `dep_graph` in dependency order
+fn lower_modules(
+    options: &CompileOptions,
+    dep_graph: &DepGraph,
+    parse_cache: &ParseCache,
+) -> Result<Vec<(PathBuf, IrModule)>, Vec<Diagnostic>> {
+    dep_graph.detect_cycles().map_err(|e| vec![Diagnostic::program("Error", e)])?;
+
+    let compilation_order = dep_graph
+        .topological_sort()
+        .map_err(|e| vec![Diagnostic::program("Error determining compilation order", e)])?;
+
+    if options.verbose {
+        println!("  Discovered {} modules", compilation_order.len());
+        for (i, module_path) in compilation_order.iter().enumerate() {
+            println!("    {}. {}", i + 1, module_path.display());
+        }
+    }
+
+    // Compile each module in order and collect IR modules (preserving compilation order)
+    let entry = dep_graph.entry();
+    let mut module_irs: Vec<(PathBuf, IrModule)> = Vec::new();
+    let mut func_id_offset: usize = 0;
+    let mut struct_id_offset: usize = 0;
+    let mut run_time_exports: HashMap<PathBuf, ModuleExports> = HashMap::new();
+
+    for module_path in &compilation_order {
+        if options.verbose {
+            println!("\n[Compiling] {}", module_path.display());
+        }
+
+        // Entry module (the user's input file) gets "main" wrapper;
+        // all other modules get "__module_init_<name>" wrappers. A library
+        // has no main, so its entries are initialized like dependencies.
+        let is_entry = Some(module_path) == entry && !options.library;
+        let module_name = if is_entry {
+            None
+        } else {
+            Some(module_path_to_init_name(module_path))
+        };
+
+        let ir_module = compile_single_module(
+            module_path,
+            parse_cache,
+            dep_graph,
+            module_name.as_deref(),
+            (func_id_offset, struct_id_offset),
+            &run_time_exports,
+            options,
+        )
+        .map_err(|errors| errors.into_iter().map(|e| e.in_file(module_path)).collect::<Vec<_>>())?;
+
+        // Modules compiled later call this one's exported functions directly
+        if let Some((_, program)) = parse_cache.get(module_path) {
+            run_time_exports.insert(module_path.clone(), ModuleExports::of(program, &ir_module));
+        }
+
+        // Update offsets for the next module to avoid FuncId/StructId collisions
+        func_id_offset = ir_module.next_func_id;
+        struct_id_offset = ir_module.next_struct_id;
+
+        module_irs.push((module_path.clone(), ir_module));
+    }
+
+    Ok(module_irs)
+}
+
+/// The native code generator for `options`
+pub fn code_generator(options: &CompileOptions) -> Result<CodeGenerator, Diagnostic> {
+    match CodeGenerator::new() {
+        Ok(cg) => Ok(cg.with_ref_counting(options.alloc_strategy == AllocStrategy::Rc)),
+        Err(e) => Err(Diagnostic::program("Codegen initialization error", e.to_string())),
+    }
+}
+
+/// Generate the object file for a merged IR module
+pub fn codegen(module: &IrModule, options: &CompileOptions) -> Result<Vec<u8>, Diagnostic> {
+    if options.verbose {
+        println!("\n[Phase 5] Generating native code...");
+    }
+
+    let object_bytes = code_generator(options)?
+        .compile_module(module)
+        .map_err(|e| Diagnostic::program("Codegen error", e.to_string()))?;
+
+    if options.verbose {
+        println!("  {} bytes of object code generated", object_bytes.len());
+    }
+    Ok(object_bytes)
+}
+
+/// Lex and parse one module
+fn parse_module(path: &Path, source: &str) -> Result<Program, Vec<Diagnostic>> {
+    let tokens = Lexer::new(source).tokenize();
+    let lexical: Vec<Diagnostic> = tokens
+        .iter()
+        .filter(|t| t.kind == TokenKind::Error)
+        .map(|t| Diagnostic::lexical(t).in_file(path))
+        .collect();
+    if !lexical.is_empty() {
+        return Err(lexical);
+    }
+
+    zaco_parser::Parser::new(tokens)
+        .parse_program()
+        .map_err(|errors| errors.iter().map(|err| Diagnostic::parse(err).in_file(path)).collect())
+}
+
+/// The comment directive that expects a type error on the next line
+const EXPECT_ERROR: &str = "@zaco-expect-error";
+
+/// Apply the `// @zaco-expect-error` comments in `source` to its type
+/// errors. Each one suppresses the errors starting on the line after it,
+/// and is an error itself when there are none, like `@ts-expect-error`.
+pub fn apply_expect_errors(source: &str, mut errors: Vec<TypeError>) -> Vec<TypeError> {
+    if !source.contains(EXPECT_ERROR) {
+        return errors;
+    }
+    let mut lexer = Lexer::new(source);
+    lexer.tokenize();
+
+    let mut unused = Vec::new();
+    for comment in lexer.comments() {
+        let is_directive = source[comment.start..comment.end]
+            .strip_prefix("//")
+            .and_then(|text| text.trim_start().strip_prefix(EXPECT_ERROR))
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace));
+        if !is_directive {
+            continue;
+        }
+        let next_line = source[comment.end..].find('\n').map(|newline| {
+            let start = comment.end + newline + 1;
+            start..=source[start..].find('\n').map_or(source.len(), |len| start + len)
+        });
+        let count = errors.len();
+        if let Some(line) = next_line {
+            errors.retain(|err| !line.contains(&err.span.start));
+        }
+        if errors.len() == count {
+            unused.push(TypeError::new(TypeErrorKind::UnusedExpectError, *comment));
+        }
+    }
+    errors.extend(unused);
+    errors
+}
+
+/// Discover all modules starting from an entry point.
+/// Parsed programs go into `parse_cache` so compilation does not re-parse
+/// them; modules already in the cache are not parsed again.
+fn discover_modules(
+    entry: &Path,
+    resolver: &ModuleResolver,
+    graph: &mut DepGraph,
+    verbose: bool,
+    parse_cache: &mut ParseCache,
+) -> Result<(), Vec<Diagnostic>> {
+    let discovery_error = |message: String| vec![Diagnostic::program("Module discovery error", message)];
+    let mut queue: VecDeque<PathBuf> = VecDeque::new();
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+
+    queue.push_back(entry.to_path_buf());
+
+    while let Some(current_path) = queue.pop_front() {
+        if visited.contains(&current_path) {
+            continue;
+        }
+        visited.insert(current_path.clone());
+
+        // Read and parse the module, unless an earlier build already did
+        if !parse_cache.contains_key(&current_path) {
+            let source = fs::read_to_string(&current_path).map_err(|e| {
+                discovery_error(format!(
+                    "Failed to read module {}: {}",
+                    current_path.display(),
+                    e
+                ))
+            })?;
+            let program = parse_module(&current_path, &source)?;
+            parse_cache.insert(current_path.clone(), (source, program));
+        }
+        let (_, program) = &parse_cache[&current_path];
+
+        // Extract imports and exports
+        let (imports, exports, type_exports) = extract_imports_exports(program);
+
+        // Resolve imports to module paths. A type-only import is erased at
+        // run time, so its module is still parsed (the type checker reads
+        // its exports) but is not a dependency and cannot close a cycle.
+        let mut dependencies = Vec::new();
+        let mut type_dependencies = Vec::new();
+        let mut builtins = Vec::new();
+        for import in &imports {
+            match resolver.resolve(&import.source, &current_path) {
+                Ok(ResolvedModule::LocalFile(path)) => {
+                    if import.is_type_only() {
+                        type_dependencies.push(path.clone());
+                    } else {
+                        dependencies.push(path.clone());
+                    }
+                    queue.push_back(path);
+                }
+                Ok(ResolvedModule::Builtin(name)) => {
+                    if verbose {
+                        println!("  Note: Skipping built-in module: {}", name);
+                    }
+                    if !builtins.contains(&name) {
+                        builtins.push(name);
+                    }
+                }
+                Ok(ResolvedModule::Package(path)) => {
+                    // NPM package resolved successfully
+                    if verbose {
+                        println!("  Resolved NPM package '{}' to: {}", import.source, path.display());
+                    }
+
+                    // If it's a .d.ts file, load type declarations but don't compile
+                    if path.extension().and_then(|s| s.to_str()) == Some("ts")
+                        && path.to_string_lossy().ends_with(".d.ts") {
+                        if verbose {
+                            println!("  Loading type declarations from: {}", path.display());
+                        }
+                        // Load declarations for type checking
+                        match dts_loader::DtsLoader::load_declarations(&path) {
+                            Ok(decls) => {
+                                if verbose {
+                                    println!("    Loaded {} type declarations", decls.len());
+                                }
+                                // Type declarations are loaded but not added to compilation queue
+                            }
+                            Err(e) => {
+                                if verbose {
+                                    println!("    Warning: Failed to load .d.ts: {}", e);
+                                }
+                            }
+                        }
+                    } else {
+                        // Regular package file - add to compilation queue
+                        dependencies.push(path.clone());
+                        queue.push_back(path);
+                    }
+                }
+                Ok(ResolvedModule::PackageNotFound { name: pkg_name, reason }) => {
+                    return Err(discovery_error(format!(
+                        "Cannot resolve import '{}' in {}: package '{}' not found ({})",
+                        import.source,
+                        current_path.display(),
+                        pkg_name,
+                        reason,
+                    )));
+                }
+                Err(e) => {
+                    return Err(discovery_error(format!(
+                        "Failed to resolve import '{}' in {}: {}",
+                        import.source,
+                        current_path.display(),
+                        e
+                    )));
+                }
+            }
+        }
+
+        // Modules re-exported from are discovered like imports. A type-only
+        // re-export is erased and, like a type-only import, adds no edge.
+        let mut reexports = Vec::new();
+        let mut exports_known = true;
+        for item in &program.items {
+            let (source, erased) = match &item.value {
+                ModuleItem::Export(ExportDecl::Named { specifiers, source: Some(source), type_only }) => {
+                    (source, *type_only || specifiers.iter().all(|spec| spec.type_only))
+                }
+                ModuleItem::Export(ExportDecl::All { source, type_only, .. }) => (source, *type_only),
+                _ => continue,
+            };
+            let Ok(ResolvedModule::LocalFile(path)) = resolver.resolve(source, &current_path) else {
+                exports_known = false;
+                continue;
+            };
+            match &item.value {
+                ModuleItem::Export(ExportDecl::Named { specifiers, type_only, .. }) => {
+                    reexports.extend(specifiers.iter().map(|spec| ReExport::Named {
+                        source: path.clone(),
+                        imported: spec.local.value.name.clone(),
+                        exported: spec.exported.as_ref().unwrap_or(&spec.local).value.name.clone(),
+                        type_only: *type_only || spec.type_only,
+                    }));
+                }
+                ModuleItem::Export(ExportDecl::All { as_name: None, type_only, .. }) => {
+                    reexports.push(ReExport::All { source: path.clone(), type_only: *type_only });
+                }
+                _ => {}
+            }
+            if erased {
+                type_dependencies.push(path.clone());
+            } else {
+                dependencies.push(path.clone());
+            }
+            queue.push_back(path);
+        }
+
+        graph.add_module(current_path.clone(), dependencies, exports);
+        graph.set_type_exports(&current_path, type_exports, reexports);
+        graph.set_other_imports(&current_path, type_dependencies, builtins);
+        if !exports_known {
+            graph.mark_exports_unknown(&current_path);
+        }
+        if program.top_level_await().is_some() {
+            graph.mark_top_level_await(&current_path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Extract imports and exports from a program AST. Exports are split into
+/// run-time values and names usable as types; `export ... from` clauses
+/// are left to `discover_modules`.
+fn extract_imports_exports(program: &Program) -> (Vec<ImportDecl>, HashSet<String>, HashSet<String>) {
+    use zaco_ast::Decl;
+    let mut imports = Vec::new();
+    let mut exports = HashSet::new();
+    let mut type_exports = HashSet::new();
+
+    // Interfaces and type aliases have no run-time value; classes and
+    // enums are both
+    let mut declared_types = HashMap::new();
+    for item in &program.items {
+        let decl = match &item.value {
+            ModuleItem::Decl(decl) => decl,
+            ModuleItem::Export(ExportDecl::Decl(decl)) => &**decl,
+            _ => continue,
+        };
+        match &decl.value {
+            Decl::TypeAlias(alias) => declared_types.insert(alias.name.value.name.clone(), false),
+            Decl::Interface(iface) => declared_types.insert(iface.name.value.name.clone(), false),
+            Decl::Class(class) => declared_types.insert(class.name.value.name.clone(), true),
+            Decl::Enum(e) => declared_types.insert(e.name.value.name.clone(), true),
+            _ => None,
+        };
+    }
+
+    for item in &program.items {
+        match &item.value {
+            ModuleItem::Import(import_decl) => {
+                imports.push(import_decl.clone());
+            }
+            ModuleItem::Export(export_decl) => {
+                extract_export_names(export_decl, &declared_types, &mut exports, &mut type_exports);
+            }
+            _ => {}
+        }
+    }
+
+    (imports, exports, type_exports)
+}
+
+/// What the local modules a program imports from export, keyed by import
+/// source: the names usable as types, for checking type-only imports, and
+/// the names that are only types, which can't be imported as values.
+/// Modules whose exports aren't fully known are left out.
+fn imported_module_exports(
+    program: &Program,
+    module_path: &Path,
+    dep_graph: &DepGraph,
+) -> (HashMap<String, HashSet<String>>, HashMap<String, HashSet<String>>) {
+    let resolver = ModuleResolver::new(module_path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf());
+    let mut module_types = HashMap::new();
+    let mut type_only_exports = HashMap::new();
+    for item in &program.items {
+        let ModuleItem::Import(import) = &item.value else {
+            continue;
+        };
+        let Ok(ResolvedModule::LocalFile(path)) = resolver.resolve(&import.source, module_path) else {
+            continue;
+        };
+        let (Some(node), Some(type_only)) = (dep_graph.get_module(&path), dep_graph.type_only_exports(&path)) else {
+            continue;
+        };
+        module_types.insert(import.source.clone(), node.type_exports.clone());
+        type_only_exports.insert(import.source.clone(), type_only);
+    }
+    (module_types, type_only_exports)
+}
+
+/// What each local module `program` imports exports at run time, keyed by
+/// import source as written. Modules are compiled after their
+/// dependencies, so theirs are in `run_time_exports` already.
+fn imported_run_time_exports(
+    program: &Program,
+    module_path: &Path,
+    run_time_exports: &HashMap<PathBuf, ModuleExports>,
+) -> HashMap<String, ModuleExports> {
+    let resolver = ModuleResolver::new(module_path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf());
+    let mut exports = HashMap::new();
+    for item in &program.items {
+        let ModuleItem::Import(import) = &item.value else {
+            continue;
+        };
+        if let Ok(ResolvedModule::LocalFile(path)) = resolver.resolve(&import.source, module_path) {
+            if let Some(module_exports) = run_time_exports.get(&path) {
+                exports.insert(import.source.clone(), module_exports.clone());
+            }
+        }
+    }
+    exports
+}
+
+/// The `declare global` items of the program's other modules, in path
+/// order. A module's own are declared by its type checker.
+fn ambient_globals(
+    module_path: &Path,
+    dep_graph: &DepGraph,
+    parse_cache: &ParseCache,
+) -> Vec<Node<ModuleItem>> {
+    let mut modules = dep_graph.all_modules();
+    modules.sort();
+    modules
+        .into_iter()
+        .filter(|path| path.as_path() != module_path)
+        .filter_map(|path| parse_cache.get(path))
+        .flat_map(|(_, program)| program.global_declarations().cloned())
+        .collect()
+}
+
+/// Extract exported names from an export declaration into the run-time
+/// `exports` and the names usable as types. `declared_types` maps the
+/// module's type declarations to whether they also declare a value.
+fn extract_export_names(
+    export_decl: &ExportDecl,
+    declared_types: &HashMap<String, bool>,
+    exports: &mut HashSet<String>,
+    type_exports: &mut HashSet<String>,
+) {
+    match export_decl {
+        ExportDecl::Named { specifiers, source, type_only } => {
+            for spec in specifiers {
+                let name = if let Some(ref exported) = spec.exported {
+                    exported.value.name.clone()
+                } else {
+                    spec.local.value.name.clone()
+                };
+                if *type_only || spec.type_only {
+                    type_exports.insert(name);
+                } else if source.is_none() {
+                    match declared_types.get(&spec.local.value.name) {
+                        Some(true) => {
+                            type_exports.insert(name.clone());
+                            exports.insert(name);
+                        }
+                        Some(false) => {
+                            type_exports.insert(name);
+                        }
+                        None => {
+                            exports.insert(name);
+                        }
+                    }
+                }
+            }
+        }
+        ExportDecl::Default(_) | ExportDecl::DefaultDecl(_) => {
+            exports.insert("default".to_string());
+        }
+        ExportDecl::All { as_name, type_only, .. } => {
+            if let Some(ref name) = as_name {
+                if *type_only {
+                    type_exports.insert(name.value.name.clone());
+                } else {
+                    exports.insert(name.value.name.clone());
+                }
+            }
+        }
+        ExportDecl::Decl(decl) => {
+            // Extract the name from the declaration
+            use zaco_ast::Decl;
+            match &decl.value {
+                Decl::Function(func) => {
+                    exports.insert(func.name.value.name.clone());
+                }
+                Decl::Var(var_decl) => {
+                    for declarator in &var_decl.declarations {
+                        if let zaco_ast::Pattern::Ident { name, .. } = &declarator.pattern.value {
+                            exports.insert(name.value.name.clone());
+                        }
+                    }
+                }
+                Decl::Class(class) => {
+                    exports.insert(class.name.value.name.clone());
+                    type_exports.insert(class.name.value.name.clone());
+                }
+                Decl::TypeAlias(alias) => {
+                    type_exports.insert(alias.name.value.name.clone());
+                }
+                Decl::Interface(iface) => {
+                    type_exports.insert(iface.name.value.name.clone());
+                }
+                Decl::Enum(enum_decl) => {
+                    exports.insert(enum_decl.name.value.name.clone());
+                    type_exports.insert(enum_decl.name.value.name.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Type check one parsed module and lower it to IR, numbering its
+/// functions and structs from `id_offsets`
+fn compile_single_module(
+    module_path: &Path,
+    parse_cache: &ParseCache,
+    dep_graph: &DepGraph,
+    module_name: Option<&str>,
+    id_offsets: (usize, usize),
+    run_time_exports: &HashMap<PathBuf, ModuleExports>,
+    options: &CompileOptions,
+) -> Result<IrModule, Vec<Diagnostic>> {
+    let Some((source, program)) = parse_cache.get(module_path) else {
+        return Err(vec![Diagnostic::program("Error", format!("module {} was not discovered", module_path.display()))]);
+    };
+
+    // Phase 3: Type checking
+    let (module_types, type_only_exports) = imported_module_exports(program, module_path, dep_graph);
+    let mut checker = zaco_typeck::TypeChecker::new()
+        .with_defines(options.defines.clone())
+        .with_module_types(module_types)
+        .with_ambient_globals(ambient_globals(module_path, dep_graph, parse_cache));
+    let type_errors = apply_expect_errors(source, checker.check_program(program).err().unwrap_or_default());
+    if !type_errors.is_empty() {
+        return Err(type_errors.iter().map(Diagnostic::from_type_error).collect());
+    }
+
+    // Phase 4: AST → IR lowering
+    let lowerer = {
+        let (func_id_offset, struct_id_offset) = id_offsets;
+        let l = zaco_ir::lower::Lowerer::new()
+            .with_func_id_offset(func_id_offset)
+            .with_struct_id_offset(struct_id_offset)
+            .with_checked(options.checked)
+            .with_instrument(options.instrument)
+            .with_trace(options.trace_lowering)
+            .with_allow_unsupported(options.allow_unsupported)
+            .with_defines(options.defines.clone())
+            .with_type_only_exports(type_only_exports)
+            .with_module_exports(imported_run_time_exports(program, module_path, run_time_exports))
+            .with_file_path(module_path.to_string_lossy().into_owned());
+        if let Some(name) = module_name {
+            l.with_module_name(name.to_string())
+        } else {
+            l
+        }
+    };
+    let ir_module = lowerer
+        .lower_program(program)
+        .map_err(|errors| errors.iter().map(Diagnostic::lowering).collect::<Vec<_>>())?;
+
+    if options.verbose {
+        println!(
+            "  Compiled: {} ({} functions)",
+            module_path.display(),
+            ir_module.functions.len()
+        );
+    }
+
+    Ok(ir_module)
+}
+
+/// Merge multiple IR modules into a single module (order-preserving).
+///
+/// User-defined functions are all included (no name-based dedup — each module
+/// now has uniquely-named wrappers via `__module_init_<name>` prefixing),
+/// once `rename_shared_private_functions` has made their names unique.
+/// Only extern function *declarations* are deduplicated (safe — they're just declarations).
+fn merge_ir_modules(
+    mut module_irs: Vec<(PathBuf, IrModule)>,
+) -> Result<IrModule, String> {
+    let mut merged = IrModule::new();
+
+    rename_shared_private_functions(&mut module_irs)?;
+
+    for (_, ir_module) in module_irs {
+        // Merge all user-defined functions without name-based dedup
+        for func in ir_module.functions {
+            merged.add_function(func);
+        }
+
+        // Merge structs
+        for struct_def in ir_module.structs {
+            merged.add_struct(struct_def);
+        }
+
+        // Merge globals
+        for (name, ty, init) in ir_module.globals {
+            merged.add_global(name, ty, init);
+        }
+
+        // Merge string literals
+        for lit in ir_module.string_literals {
+            merged.intern_string(lit);
+        }
+
+        // Merge extern function declarations (deduplicate by name — safe for declarations)
+        for ext_func in ir_module.extern_functions {
+            if !merged
+                .extern_functions
+                .iter()
+                .any(|ef| ef.name == ext_func.name)
+            {
+                merged.extern_functions.push(ext_func);
+            }
+        }
+
+        merged.worker_entries.extend(ir_module.worker_entries);
+    }
+
+    Ok(merged)
+}
+
+/// Give each private function whose name another module also defines
+/// its module's init name as a suffix, along with the calls to it. Two
+/// modules exporting the same name is an error.
+fn rename_shared_private_functions(module_irs: &mut [(PathBuf, IrModule)]) -> Result<(), String> {
+    // Which modules define each function name, and which of those export it
+    let mut definers: HashMap<&str, Vec<(&Path, bool)>> = HashMap::new();
+    for (path, ir_module) in module_irs.iter() {
+        for func in &ir_module.functions {
+            definers
+                .entry(func.name.as_str())
+                .or_default()
+                .push((path.as_path(), func.is_public));
+        }
+    }
+    let mut shared_names: HashSet<String> = HashSet::new();
+    for (name, defs) in &definers {
+        let exporters: Vec<&Path> = defs.iter().filter(|(_, public)| *public).map(|(p, _)| *p).collect();
+        if exporters.len() > 1 {
+            return Err(format!(
+                "function '{}' is exported by both {} and {}",
+                name,
+                exporters[0].display(),
+                exporters[1].display()
+            ));
+        }
+        if defs.len() > 1 {
+            shared_names.insert(name.to_string());
+        }
+    }
+
+    for (path, ir_module) in module_irs.iter_mut() {
+        let renames: HashMap<String, String> = ir_module
+            .functions
+            .iter()
+            .filter(|f| !f.is_public && shared_names.contains(&f.name))
+            .map(|f| (f.name.clone(), format!("{}__{}", f.name, module_path_to_init_name(path))))
+            .collect();
+        if !renames.is_empty() {
+            rename_functions(ir_module, &renames);
+        }
+    }
+    Ok(())
+}
+
+/// Rename functions within one module, rewriting the calls and function
+/// addresses that refer to them by name
+fn rename_functions(module: &mut IrModule, renames: &HashMap<String, String>) {
+    use zaco_ir::{Constant, Instruction, RValue, Value};
+
+    let rename_value = |value: &mut Value| {
+        if let Value::Const(Constant::Str(name) | Constant::FuncAddr(name)) = value {
+            if let Some(new_name) = renames.get(name.as_str()) {
+                *name = new_name.clone();
+            }
+        }
+    };
+    let rename_addr = |value: &mut Value| {
+        if let Value::Const(Constant::FuncAddr(_)) = value {
+            rename_value(value);
+        }
+    };
+
+    for func in &mut module.functions {
+        if let Some(new_name) = renames.get(&func.name) {
+            func.name = new_name.clone();
+        }
+        for block in &mut func.blocks {
+            for inst in &mut block.instructions {
+                match inst {
+                    Instruction::Call { func, args, .. } => {
+                        rename_value(func);
+                        args.iter_mut().for_each(rename_addr);
+                    }
+                    Instruction::Assign { value: RValue::Use(value), .. } => rename_addr(value),
+                    _ => {}
+                }
+            }
+        }
+    }
+    for (_, trampoline) in &mut module.worker_entries {
+        if let Some(new_name) = renames.get(trampoline.as_str()) {
+            *trampoline = new_name.clone();
+        }
+    }
+}
+
+/// Derive a safe init function name from a module's file path.
+/// Includes the parent directory for readability plus a hash suffix of the full
+/// path to guarantee uniqueness even when multiple modules share the same
+/// parent+stem (e.g., `x/a/index.ts` vs `y/a/index.ts`).
+/// e.g., "x/a/index.ts" → "a_index_1a2b3c4d"
+/// Characters that aren't alphanumeric or underscore are replaced with '_'.
+fn module_path_to_init_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let parent = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let readable = if parent.is_empty() {
+        stem.to_string()
+    } else {
+        format!("{}_{}", parent, stem)
+    };
+
+    let sanitized: String = readable
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+
+    // Append hash of full path for uniqueness
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    format!("{}_{:08x}", sanitized, hash as u32)
+}
+
+/// The names of the `__module_init_*` functions `module` defines
+fn module_init_names(module: &IrModule) -> Vec<String> {
+    module
+        .functions
+        .iter()
+        .filter(|f| f.name.starts_with("__module_init_"))
+        .map(|f| f.name.clone())
+        .collect()
+}
+
+/// Inject calls to the `init_names` functions at the start of "main"'s entry block.
+/// This ensures dependency modules' top-level code runs before the entry module's code.
+fn inject_module_init_calls(module: &mut IrModule, init_names: Vec<String>) {
+    if init_names.is_empty() {
+        return;
+    }
+
+    // Find the "main" function and inject calls at the start of its entry block
+    if let Some(main_func) = module.functions.iter_mut().find(|f| f.name == "main") {
+        let entry_block = main_func.entry_block;
+
+        // Build Call instructions for each init function
+        let mut init_calls: Vec<zaco_ir::Instruction> = Vec::new();
+        for name in &init_names {
+            init_calls.push(zaco_ir::Instruction::Call {
+                dest: None,
+                func: zaco_ir::Value::Const(zaco_ir::Constant::Str(name.clone())),
+                args: vec![],
+            });
+        }
+
+        // Prepend init calls before existing instructions in the entry block
+        if let Some(block) = main_func.blocks.iter_mut().find(|b| b.id == entry_block) {
+            let existing = std::mem::take(&mut block.instructions);
+            block.instructions = init_calls;
+            block.instructions.extend(existing);
+        }
+    }
+}
+
+/// Let modules compiled to separate objects call each other. A function a
+/// module refers to by name but does not define is declared there as an
+/// extern, and exported by the module that defines it. Every module also
+/// gets the structs of the others, for the classes it uses across modules.
+fn link_separate_modules(module_irs: &mut [(PathBuf, IrModule)]) {
+    use zaco_ir::{Constant, ExternFunction, Instruction, RValue, Value};
+
+    // The module defining each function, and its signature
+    let mut definers: HashMap<String, (usize, ExternFunction)> = HashMap::new();
+    for (i, (_, ir_module)) in module_irs.iter().enumerate() {
+        for func in &ir_module.functions {
+            let params = func.params.iter().map(|(_, ty)| ty.clone()).collect();
+            let signature = ExternFunction { name: func.name.clone(), params, return_type: func.return_type.clone() };
+            definers.insert(func.name.clone(), (i, signature));
+        }
+    }
+    let structs: Vec<_> = module_irs.iter().flat_map(|(_, m)| m.structs.iter().cloned()).collect();
+
+    let mut exported: Vec<(usize, String)> = Vec::new();
+    for (i, (_, ir_module)) in module_irs.iter_mut().enumerate() {
+        let mut referenced: Vec<&str> = Vec::new();
+        for func in &ir_module.functions {
+            for inst in func.blocks.iter().flat_map(|b| &b.instructions) {
+                // Calls name their callee; other values only refer to a
+                // function by its address
+                let (callee, values) = match inst {
+                    Instruction::Call { func, args, .. } => (Some(func), args.as_slice()),
+                    Instruction::Assign { value: RValue::Use(value), .. } => (None, std::slice::from_ref(value)),
+                    _ => continue,
+                };
+                if let Some(Value::Const(Constant::Str(name))) = callee {
+                    referenced.push(name);
+                }
+                for value in callee.into_iter().chain(values) {
+                    if let Value::Const(Constant::FuncAddr(name)) = value {
+                        referenced.push(name);
+                    }
+                }
+            }
+        }
+
+        let mut externs = Vec::new();
+        for name in referenced {
+            let Some((owner, signature)) = definers.get(name) else { continue };
+            if *owner == i || externs.iter().any(|ext: &ExternFunction| ext.name == name) {
+                continue;
+            }
+            externs.push(signature.clone());
+            exported.push((*owner, name.to_string()));
+        }
+        ir_module.extern_functions.extend(externs);
+
+        let own: HashSet<_> = ir_module.structs.iter().map(|s| s.id).collect();
+        ir_module.structs.extend(structs.iter().filter(|s| !own.contains(&s.id)).cloned());
+    }
+
+    for (owner, name) in exported {
+        if let Some(func) = module_irs[owner].1.functions.iter_mut().find(|f| f.name == name) {
+            func.is_public = true;
+        }
+    }
+}
+
+/// Add the exported `zaco_lib_init` function of a library: it starts the
+/// runtime and runs every module's top-level code, in dependency order.
+fn add_lib_init(module: &mut IrModule) {
+    use zaco_ir::{Constant, FuncId, Instruction, IrFunction, IrType, Terminator, Value};
+
+    let id = module.functions.iter().map(|f| f.id.0 + 1).max().unwrap_or(0);
+    let mut init = IrFunction::new(FuncId(id), "zaco_lib_init".to_string(), vec![], IrType::Void);
+    init.is_public = true;
+    let entry = init.new_block();
+    init.entry_block = entry;
+
+    let callees = std::iter::once("zaco_runtime_init".to_string()).chain(
+        module
+            .functions
+            .iter()
+            .filter(|f| f.name.starts_with("__module_init_"))
+            .map(|f| f.name.clone()),
+    );
+    for name in callees {
+        init.block_mut(entry).push_instruction(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str(name)),
+            args: vec![],
+        });
+    }
+    init.block_mut(entry).set_terminator(Terminator::Return(None));
+    module.add_function(init);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_path_to_init_name_no_collision() {
+        let name1 = module_path_to_init_name(Path::new("x/a/index.ts"));
+        let name2 = module_path_to_init_name(Path::new("y/a/index.ts"));
+        assert_ne!(
+            name1, name2,
+            "Different paths with same stem should produce different init names"
+        );
+
+        // Same path should produce same name
+        let name3 = module_path_to_init_name(Path::new("x/a/index.ts"));
+        assert_eq!(
+            name1, name3,
+            "Same path should produce same init name"
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch_labels_the_annotation() {
+        let source = "let count: number = 1;\ncount = \"a\";\n";
+        let tokens = Lexer::new(source).tokenize();
+        let program = zaco_parser::Parser::new(tokens).parse_program().unwrap();
+        let errors = zaco_typeck::check_program(&program).unwrap_err();
+        let diagnostic = Diagnostic::from_type_error(&errors[0]);
+        assert_eq!(&source[diagnostic.start..diagnostic.end], "count = \"a\"");
+        let (message, start, end) = &diagnostic.labels[0];
+        assert_eq!(message, "expected type declared here");
+        assert_eq!(&source[*start..*end], "number");
+    }
+}
@@ -80,10 +80,10 @@ impl ModuleResolver {
     fn is_builtin(specifier: &str) -> bool {
         matches!(
             specifier,
-            "fs" | "path" | "http" | "https" | "os" | "process" | "events"
+            "fs" | "path" | "http" | "https" | "os" | "process" | "events" | "worker"
                 | "url" | "crypto" | "util" | "stream" | "buffer"
                 | "child_process" | "net" | "tls" | "dns" | "querystring"
-                | "assert" | "zlib"
+                | "assert" | "zlib" | "sqlite" | "timers" | "glob"
         )
     }
 
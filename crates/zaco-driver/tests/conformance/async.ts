@@ -0,0 +1,13 @@
+// Async functions and await
+
+async function double(n: number): Promise<number> {
+    return n * 2;
+}
+
+async function run(): Promise<void> {
+    const a = await double(1);
+    const b = await double(a);
+    console.log(a, b);
+}
+
+run();
@@ -0,0 +1,45 @@
+// Classes: fields, constructors, inheritance, getters and chaining
+
+class Shape {
+    name: string;
+
+    constructor(name: string) {
+        this.name = name;
+    }
+
+    area(): number {
+        return 0;
+    }
+
+    describe(): string {
+        return `a ${this.name}`;
+    }
+}
+
+class Rect extends Shape {
+    constructor(private width: number, private height: number) {
+        super("rect");
+    }
+
+    area(): number {
+        return this.width * this.height;
+    }
+
+    get perimeter(): number {
+        return 2 * (this.width + this.height);
+    }
+}
+
+class Counter {
+    count: number = 0;
+
+    increment(): Counter {
+        this.count++;
+        return this;
+    }
+}
+
+const rect = new Rect(3, 4);
+console.log(rect.describe(), rect.area(), rect.perimeter);
+const counter = new Counter().increment().increment();
+console.log(counter.count);
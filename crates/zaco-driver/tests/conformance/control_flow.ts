@@ -0,0 +1,43 @@
+// Loops, switch, break and continue, and exceptions
+
+function classify(n: number): string {
+    switch (n % 3) {
+        case 0:
+            return "fizz";
+        case 1:
+            return "one";
+        default:
+            return "other";
+    }
+}
+
+let sum = 0;
+for (let i = 0; i < 10; i++) {
+    if (i === 7) {
+        break;
+    }
+    if (i % 2 === 0) {
+        continue;
+    }
+    sum += i;
+}
+
+let n = 0;
+while (n < 3) {
+    n++;
+}
+
+const values: number[] = [4, 5, 6];
+let product: number = 1;
+for (const value of values) {
+    product *= value;
+}
+
+try {
+    throw "boom";
+} catch (e) {
+    console.log("caught", e);
+} finally {
+    console.log("done");
+}
+console.log(sum, n, product, classify(3), classify(4));
@@ -0,0 +1,18 @@
+// Objects, arrays, interfaces, destructuring and nullish values
+
+interface User {
+    name: string;
+    age: number;
+    email?: string;
+}
+
+const ada: User = { name: "ada", age: 36 };
+const alan: User = { name: "alan", age: 41, email: "alan@example.com" };
+const users: User[] = [ada, alan];
+
+const { name, age } = ada;
+const [first, second] = [1, 2];
+users.push({ name: "grace", age: 45 });
+
+console.log(name, age, first, second, users.length);
+console.log(alan.email ?? "none", ada.email ?? "none");
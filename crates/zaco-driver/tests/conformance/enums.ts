@@ -0,0 +1,22 @@
+// Enums and generic classes
+
+enum Direction {
+    Up,
+    Down,
+}
+
+enum Color {
+    Red = "red",
+    Green = "green",
+}
+
+class Box<T> {
+    constructor(public value: T) {}
+
+    get(): T {
+        return this.value;
+    }
+}
+
+const box = new Box<string>("boxed");
+console.log(Direction.Down, Color.Green, box.get());
@@ -0,0 +1,17 @@
+// Functions, closures and recursion
+
+function makeAdder(n: number): (x: number) => number {
+    return (x: number) => x + n;
+}
+
+function factorial(n: number): number {
+    return n <= 1 ? 1 : n * factorial(n - 1);
+}
+
+function greet(greeting: string, name: string): string {
+    return `${greeting}, ${name}`;
+}
+
+const addTwo = makeAdder(2);
+console.log(addTwo(40), addTwo(addTwo(1)), factorial(5));
+console.log(greet("hello", "ada"), greet("hi", "alan"));
@@ -0,0 +1,10 @@
+// String methods and templates
+
+const text = "  Hello, World  ";
+const trimmed = text.trim();
+const words = trimmed.split(", ");
+const shout = `${trimmed.toUpperCase()}!`;
+
+console.log(trimmed.length, words.length, shout);
+console.log(trimmed.startsWith("Hello"), trimmed.indexOf("World"), trimmed.slice(0, 5));
+console.log("ab".repeat(3), trimmed.includes("World"));
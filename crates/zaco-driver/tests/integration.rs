@@ -22,6 +22,22 @@ fn zaco_binary() -> PathBuf {
 
 /// Compile a TypeScript snippet and run the resulting executable, returning stdout.
 fn compile_and_run(source: &str) -> String {
+    compile_and_run_with_args(source, &[])
+}
+
+/// Like `compile_and_run`, passing extra flags to `zaco compile`.
+fn compile_and_run_with_args(source: &str, extra_args: &[&str]) -> String {
+    let run_output = compile_and_run_output(source, extra_args);
+    String::from_utf8_lossy(&run_output.stdout).to_string()
+}
+
+/// Compile with extra flags and run, returning the full process output.
+fn compile_and_run_output(source: &str, extra_args: &[&str]) -> std::process::Output {
+    compile_and_run_output_env(source, extra_args, &[])
+}
+
+/// Like `compile_and_run_output`, running the program with extra environment variables.
+fn compile_and_run_output_env(source: &str, extra_args: &[&str], env: &[(&str, &str)]) -> std::process::Output {
     use std::sync::atomic::{AtomicUsize, Ordering};
     static COUNTER: AtomicUsize = AtomicUsize::new(0);
     let id = COUNTER.fetch_add(1, Ordering::SeqCst);
@@ -42,6 +58,7 @@ fn compile_and_run(source: &str) -> String {
         .arg(&output_path)
         .arg("--emit")
         .arg("exe")
+        .args(extra_args)
         // Set working directory to workspace root so runtime is found
         .current_dir(
             PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -64,6 +81,7 @@ fn compile_and_run(source: &str) -> String {
 
     // Run
     let run_output = Command::new(&output_path)
+        .envs(env.iter().copied())
         .output()
         .expect("Failed to run compiled executable");
 
@@ -71,11 +89,16 @@ fn compile_and_run(source: &str) -> String {
     let _ = fs::remove_file(&input_path);
     let _ = fs::remove_file(&output_path);
 
-    String::from_utf8_lossy(&run_output.stdout).to_string()
+    run_output
 }
 
 /// Compile a TypeScript snippet and return IR output.
 fn compile_to_ir(source: &str) -> String {
+    compile_to_emit(source, "ir")
+}
+
+/// Compile a TypeScript snippet with a printing `--emit` mode and return its output.
+fn compile_to_emit(source: &str, emit: &str) -> String {
     use std::sync::atomic::{AtomicUsize, Ordering};
     static IR_COUNTER: AtomicUsize = AtomicUsize::new(1000);
     let id = IR_COUNTER.fetch_add(1, Ordering::SeqCst);
@@ -90,7 +113,7 @@ fn compile_to_ir(source: &str) -> String {
         .arg("compile")
         .arg(&input_path)
         .arg("--emit")
-        .arg("ir")
+        .arg(emit)
         .current_dir(
             PathBuf::from(env!("CARGO_MANIFEST_DIR"))
                 .parent()
@@ -204,6 +227,21 @@ console.log(result);
     assert_eq!(output.trim(), "2");
 }
 
+#[test]
+fn test_mod_takes_sign_of_dividend() {
+    let output = compile_and_run(
+        r#"
+let a: number = -7;
+let b: number = 3;
+console.log(a % b, 5.5 % 2, 7 % -3, -5.5 % 2);
+let c: number = -10;
+c %= 4;
+console.log(c);
+"#,
+    );
+    assert_eq!(output.trim(), "-1 1.5 1 -1.5\n-2");
+}
+
 #[test]
 fn test_multiple_args() {
     let output = compile_and_run(
@@ -240,228 +278,3333 @@ console.log("result:", 42);
     assert_eq!(output.trim(), "result: 42");
 }
 
-// ============================================================================
-// IR Emission
-// ============================================================================
+#[test]
+fn test_undefined_is_distinct_from_null() {
+    let output = compile_and_run(
+        r#"
+console.log(null === undefined, null == undefined);
+console.log(null !== undefined, null != undefined);
+console.log(typeof undefined, typeof null, typeof void 0);
+let u = undefined;
+console.log(u === undefined, u === null, u == null);
+console.log(u);
+if (!u) {
+  console.log("falsy");
+}
+"#,
+    );
+    assert_eq!(
+        output.trim(),
+        "false true\ntrue false\nundefined object undefined\ntrue false true\nundefined\nfalsy"
+    );
+}
 
 #[test]
-fn test_ir_emission() {
-    let ir = compile_to_ir(r#"console.log("test");"#);
-    assert!(ir.contains("fn main("));
-    assert!(ir.contains("zaco_print_str"));
-    assert!(ir.contains("Return"));
+fn test_console_format_specifiers() {
+    let output = compile_and_run(
+        r#"
+const x: number = 42.7;
+console.log("value: %d", x);
+console.log("%s is %i years, %f", "Ann", 30.9, 1.5);
+console.log("obj %o json %j", { a: 1, b: "x" }, { a: 1, tags: ["t"] });
+console.log("100%% sure", "extra", 3);
+console.log("%s missing %d");
+console.log({ a: 1, nested: { b: [true, false] } });
+console.log("n=", 1.5, true, null, undefined);
+"#,
+    );
+    assert_eq!(
+        output,
+        "value: 42\n\
+Ann is 30 years, 1.5\n\
+obj { a: 1, b: 'x' } json {\"a\":1,\"tags\":[\"t\"]}\n\
+100% sure extra 3\n\
+%s missing %d\n\
+{ a: 1, nested: { b: [ true, false ] } }\n\
+n= 1.5 true null undefined\n"
+    );
 }
 
-// ============================================================================
-// Control Flow
-// ============================================================================
+#[test]
+fn test_loose_equality_coerces() {
+    let output = compile_and_run(
+        r#"
+const one: number = 1;
+const s1: string = "1";
+console.log(1 == "1", 1 === "1", one == s1, one !== s1);
+console.log(0 == "", 16 == "0x10", 1 == "1e0", 1 == "abc", 10 == " 10 ");
+const t: boolean = true;
+console.log(t == 1, t == "1", false == "0", t === 1);
+console.log(null == 0, undefined == 0, null == undefined);
+"#,
+    );
+    assert_eq!(
+        output.trim(),
+        "true false true true\ntrue true true false true\ntrue true true false\nfalse false true"
+    );
+}
 
 #[test]
-fn test_if_true() {
+fn test_comma_operator() {
     let output = compile_and_run(
         r#"
-let x: number = 1;
-if (x) {
-    console.log("yes");
+function sideEffect(): number {
+  console.log("side effect");
+  return 1;
 }
+let x = (sideEffect(), 5);
+console.log(x);
+let i: number = 0;
+let j: number = 10;
+i++, j--;
+console.log(i, j);
 "#,
     );
-    assert_eq!(output.trim(), "yes");
+    assert_eq!(output.trim(), "side effect\n5\n1 9");
 }
 
 #[test]
-fn test_if_false() {
+fn test_sequence_in_for_update_and_statement() {
     let output = compile_and_run(
         r#"
-let x: number = 0;
-if (x) {
-    console.log("yes");
-} else {
-    console.log("no");
+function a(): number { console.log("a"); return 1; }
+function b(): string { console.log("b"); return "bee"; }
+const n: number = 5;
+for (let i = 0, j = n; i < j; i++, j--) {
+  console.log(i, j);
 }
+(a(), b());
+const last = (a(), b());
+console.log(last.length);
 "#,
     );
-    assert_eq!(output.trim(), "no");
+    assert_eq!(output, "0 5\n1 4\n2 3\na\nb\na\nb\n3\n");
 }
 
 // ============================================================================
-// Return Code
+// Structured Clone
 // ============================================================================
 
 #[test]
-fn test_exit_code_zero() {
-    let temp_dir = std::env::temp_dir().join("zaco_test_exit_code");
-    let _ = fs::create_dir_all(&temp_dir);
-    let input_path = temp_dir.join("test_exit.ts");
-    let output_path = temp_dir.join("test_exit");
-
-    fs::write(&input_path, r#"console.log("ok");"#).unwrap();
-
-    let zaco = zaco_binary();
-    Command::new(&zaco)
-        .arg("compile")
-        .arg(&input_path)
-        .arg("-o")
-        .arg(&output_path)
-        .arg("--emit")
-        .arg("exe")
-        .current_dir(
-            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-                .parent()
-                .unwrap()
-                .parent()
-                .unwrap(),
-        )
-        .output()
-        .expect("compile failed");
+fn test_structured_clone_copies_deeply() {
+    let output = compile_and_run(
+        r#"
+interface Item {
+  name: string;
+  tags: string[];
+  meta: { count: number };
+}
+const a: Item = { name: "ada", tags: ["x", "y"], meta: { count: 1 } };
+const b = structuredClone(a);
+b.meta.count = 5;
+console.log(a.tags[1], a.meta.count);
+console.log(b.name, b.tags[1], b.meta.count, b.tags === a.tags);
+console.log(structuredClone("text"), structuredClone(42));
+try {
+  structuredClone(() => 1);
+} catch (e) {
+  console.log(e);
+}
+"#,
+    );
+    assert_eq!(
+        output.trim(),
+        "y 1\nada y 5 false\ntext 42\nDataCloneError: a function could not be cloned"
+    );
+}
 
-    let status = Command::new(&output_path).status().expect("run failed");
-    assert!(status.success(), "Expected exit code 0");
+#[test]
+fn test_structured_clone_preserves_cycles() {
+    let output = compile_and_run(
+        r#"
+interface Node {
+  value: number;
+  next: Node | null;
+}
+const first: Node = { value: 1, next: null };
+const second: Node = { value: 2, next: first };
+first.next = second;
+const copy = structuredClone(first);
+console.log(copy.value, copy.next!.value, copy.next!.next === copy, copy === first);
 
-    let _ = fs::remove_file(&input_path);
-    let _ = fs::remove_file(&output_path);
+let head: Node = { value: 0, next: null };
+for (let i = 1; i < 10000; i++) {
+  const node: Node = { value: i, next: head };
+  head = node;
+}
+let cur: Node = structuredClone(head);
+let total: number = 0;
+for (let i = 0; i < 9999; i++) {
+  total += cur.value;
+  cur = cur.next!;
+}
+console.log(total + cur.value, cur.next === null);
+"#,
+    );
+    assert_eq!(output.trim(), "1 2 true false\n49995000 true");
 }
 
 // ============================================================================
-// Switch Statement
+// Console Extras
 // ============================================================================
 
 #[test]
-fn test_switch_basic_match() {
+fn test_console_table_snapshot() {
     let output = compile_and_run(
         r#"
-let x: number = 2;
-switch (x) {
-    case 1:
-        console.log("one");
-        break;
-    case 2:
-        console.log("two");
-        break;
-    case 3:
-        console.log("three");
-        break;
-}
+console.table([{ a: 1, b: "x" }, { a: 2, c: true }]);
+console.table([10, 20]);
 "#,
     );
-    assert_eq!(output.trim(), "two");
+    let expected = "\
+┌─────────┬───┬─────┬──────┐
+│ (index) │ a │ b   │ c    │
+├─────────┼───┼─────┼──────┤
+│ 0       │ 1 │ 'x' │      │
+│ 1       │ 2 │     │ true │
+└─────────┴───┴─────┴──────┘
+┌─────────┬────────┐
+│ (index) │ Values │
+├─────────┼────────┤
+│ 0       │ 10     │
+│ 1       │ 20     │
+└─────────┴────────┘
+";
+    assert_eq!(output, expected);
 }
 
 #[test]
-fn test_switch_default_case() {
+fn test_console_group_indents_output() {
     let output = compile_and_run(
         r#"
-let x: number = 99;
-switch (x) {
-    case 1:
-        console.log("one");
-        break;
-    default:
-        console.log("other");
+console.group("Outer");
+console.log("inside");
+console.group();
+console.log("deeper");
+console.groupEnd();
+console.groupEnd();
+console.log("after");
+"#,
+    );
+    assert_eq!(output, "Outer\n  inside\n    deeper\nafter\n");
 }
+
+#[test]
+fn test_console_assert_and_time() {
+    let run_output = compile_and_run_output(
+        r#"
+const n: number = 1;
+console.assert(n === 1, "never printed");
+console.assert(n === 2);
+console.assert(n === 2, "bad", n);
+console.time("work");
+console.timeEnd("work");
+console.timeEnd("missing");
 "#,
+        &[],
     );
-    assert_eq!(output.trim(), "other");
+    let stdout = String::from_utf8_lossy(&run_output.stdout);
+    assert!(stdout.starts_with("work: ") && stdout.trim_end().ends_with("ms"), "stdout: {}", stdout);
+    let stderr = String::from_utf8_lossy(&run_output.stderr);
+    assert_eq!(
+        stderr,
+        "Assertion failed\nAssertion failed: bad 1\nWarning: No such label 'missing' for console.timeEnd()\n"
+    );
+    assert_eq!(run_output.status.code(), Some(0));
 }
 
 #[test]
-fn test_switch_no_match_no_default() {
-    let output = compile_and_run(
+fn test_console_time_from_workers() {
+    let run_output = compile_and_run_output(
         r#"
-let x: number = 5;
-switch (x) {
-    case 1:
-        console.log("one");
-        break;
-    case 2:
-        console.log("two");
-        break;
+import { spawn } from "worker";
+
+export function timeMany(label: string): number {
+  for (let i: number = 0; i < 200; i++) {
+    console.time(label + i);
+  }
+  for (let i: number = 0; i < 200; i++) {
+    console.timeEnd(label + i);
+  }
+  return 200;
 }
-console.log("done");
+
+const a = spawn("timeMany", ["a"]);
+const b = spawn("timeMany", ["b"]);
+const c = spawn("timeMany", ["c"]);
+const d = spawn("timeMany", ["d"]);
+console.log(await a + await b + await c + await d);
 "#,
+        &[],
     );
-    assert_eq!(output.trim(), "done");
+    let stdout = String::from_utf8_lossy(&run_output.stdout);
+    assert_eq!(stdout.lines().filter(|line| line.ends_with("ms")).count(), 800, "stdout: {}", stdout);
+    assert_eq!(stdout.lines().last(), Some("800"));
+    assert_eq!(String::from_utf8_lossy(&run_output.stderr), "");
+    assert_eq!(run_output.status.code(), Some(0));
 }
 
-// ============================================================================
-// Switch IR Emission
-// ============================================================================
-
 #[test]
-fn test_switch_ir_emission() {
-    let ir = compile_to_ir(
+fn test_console_stream_routing() {
+    let run_output = compile_and_run_output(
         r#"
-let x: number = 1;
-switch (x) {
-    case 1:
-        console.log("one");
-        break;
-    default:
-        console.log("other");
-}
+console.log("log");
+console.info("info");
+console.warn("warn");
+console.error("error");
+console.debug("debug");
 "#,
+        &[],
+    );
+    assert_eq!(String::from_utf8_lossy(&run_output.stdout), "log\ninfo\n");
+    assert_eq!(String::from_utf8_lossy(&run_output.stderr), "warn\nerror\ndebug\n");
+}
+
+#[test]
+fn test_style_text_respects_tty_and_env() {
+    let source = r#"
+import { styleText } from "util";
+console.log(styleText("red", "alert"));
+console.log(styleText("bold", "strong"));
+"#;
+    // Test output is piped, so no escape codes unless forced
+    let plain = compile_and_run_output_env(source, &[], &[("FORCE_COLOR", "0")]);
+    assert_eq!(String::from_utf8_lossy(&plain.stdout), "alert\nstrong\n");
+
+    let forced = compile_and_run_output_env(source, &[], &[("FORCE_COLOR", "1")]);
+    assert_eq!(
+        String::from_utf8_lossy(&forced.stdout),
+        "\x1b[31malert\x1b[39m\n\x1b[1mstrong\x1b[22m\n"
     );
-    // Switch should generate Branch instructions for case matching
-    assert!(ir.contains("Branch"), "Switch IR should contain Branch terminators");
 }
 
 // ============================================================================
-// Module Resolution Failures
+// Method Chaining
 // ============================================================================
 
-/// Helper: compile source and expect it to fail. Returns (stdout, stderr).
-fn compile_should_fail(source: &str) -> (String, String) {
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    static FAIL_COUNTER: AtomicUsize = AtomicUsize::new(2000);
-    let id = FAIL_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let temp_dir = std::env::temp_dir().join(format!("zaco_test_{}", id));
-    let _ = fs::create_dir_all(&temp_dir);
+#[test]
+fn test_chained_string_methods() {
+    let output = compile_and_run(
+        r#"
+console.log("  hi  ".trim().toUpperCase());
+let padded: string = " ab ";
+console.log(padded.trim().length);
+"#,
+    );
+    assert_eq!(output.trim(), "HI\n2");
+}
 
-    let input_path = temp_dir.join("test_input.ts");
-    fs::write(&input_path, source).expect("Failed to write test input");
+#[test]
+fn test_method_call_on_call_result() {
+    let output = compile_and_run(
+        r#"
+class Greeter {
+  name: string;
+  constructor(name: string) { this.name = name; }
+  greet(): string { return "hi " + this.name; }
+}
+function make(): Greeter {
+  return new Greeter("bob");
+}
+console.log(make().greet().toUpperCase());
+"#,
+    );
+    assert_eq!(output.trim(), "HI BOB");
+}
 
-    let zaco = zaco_binary();
-    let output = Command::new(&zaco)
-        .arg("compile")
-        .arg(&input_path)
-        .arg("--emit")
-        .arg("ir")
-        .current_dir(
-            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-                .parent()
-                .unwrap()
-                .parent()
-                .unwrap(),
-        )
-        .output()
-        .expect("Failed to run zaco compiler");
+#[test]
+fn test_fluent_builder_returning_this() {
+    let output = compile_and_run(
+        r#"
+class Pizza {
+  size: number;
+  cheese: boolean;
+  topping: string;
+  constructor(size: number, cheese: boolean, topping: string) {
+    this.size = size;
+    this.cheese = cheese;
+    this.topping = topping;
+  }
+}
+class PizzaBuilder {
+  size: number = 0;
+  cheese: boolean = false;
+  topping: string = "";
+  setSize(size: number): this {
+    this.size = size;
+    return this;
+  }
+  setCheese(cheese: boolean): this {
+    this.cheese = cheese;
+    return this;
+  }
+  setTopping(topping: string): this {
+    this.topping = topping;
+    return this;
+  }
+  build(): Pizza {
+    return new Pizza(this.size, this.cheese, this.topping);
+  }
+}
+class LoggingBuilder extends PizzaBuilder {
+  log(): this {
+    console.log("size " + this.size);
+    return this;
+  }
+}
+const p = new PizzaBuilder().setSize(12).setCheese(true).setTopping("basil").build();
+console.log(p.size, p.cheese, p.topping);
+const q = new LoggingBuilder().setSize(8).log().setTopping("ham").build();
+console.log(q.size, q.cheese, q.topping);
+"#,
+    );
+    assert_eq!(output.trim(), "12 true basil\nsize 8\n8 false ham");
+}
 
-    let _ = fs::remove_file(&input_path);
+// ============================================================================
+// Template Literals
+// ============================================================================
 
-    assert!(
-        !output.status.success(),
-        "Expected compilation to fail but it succeeded"
+#[test]
+fn test_template_substitutions() {
+    let output = compile_and_run(
+        r#"
+const n: number = 2;
+const inner: string = `n=${n}`;
+console.log(`${inner}, next ${n + 1}, ${"done"}`);
+"#,
     );
-
-    (
-        String::from_utf8_lossy(&output.stdout).to_string(),
-        String::from_utf8_lossy(&output.stderr).to_string(),
-    )
+    assert_eq!(output, "n=2, next 3, done\n");
 }
 
 #[test]
-fn test_unresolved_import_fails_compilation() {
-    let (stdout, stderr) = compile_should_fail(
-        r#"import { foo } from "definitely-missing-package";
-console.log("ok");
+fn test_tagged_template_cooked_and_raw_parts() {
+    let output = compile_and_run(
+        r#"
+function tag(strings: TemplateStringsArray, value: number): string {
+  let text: string = "" + strings.length;
+  for (let i = 0; i < strings.length; i++) {
+    text = text + "|" + strings[i].length + ":" + strings.raw[i];
+  }
+  return text + "|" + value;
+}
+console.log(tag`a\n${40 + 2}\tb`);
+console.log(String.raw`C:\new\${1}\d ${6 * 7}`);
 "#,
     );
-    // Error should mention the missing package (may be in stdout or stderr)
-    let combined = format!("{}{}", stdout, stderr);
-    assert!(
-        combined.contains("not found") || combined.contains("Cannot resolve"),
-        "Error message should mention the missing package, got stdout={}, stderr={}",
-        stdout, stderr
+    // The cooked `a\n` is two characters, its raw form three
+    assert_eq!(output, "2|2:a\\n|2:\\tb|42\nC:\\new\\${1}\\d 42\n");
+}
+
+#[test]
+fn test_string_match_all_groups() {
+    let output = compile_and_run(
+        r#"
+for (const m of "a1b2".matchAll(/([a-z])(\d)/g)) {
+  console.log(m[0], m[1], m[2], m.index);
+}
+const words = [..."x10 y7".matchAll(/\w(\d+)/g)];
+const last: RegExpMatchArray = words[words.length - 1];
+console.log(words.length, last[1], last.index, 10 / 2 / 5);
+"#,
+    );
+    assert_eq!(output, "a1 a 1 0\nb2 b 2 2\n2 7 4 1\n");
+}
+
+#[test]
+fn test_string_repeat_and_substring_queries() {
+    let output = compile_and_run(
+        r#"
+const s = "hello";
+console.log("ab".repeat(3), "x".repeat(0) === "", s.startsWith("he"), s.startsWith("lo"));
+console.log(s.endsWith("lo"), s.endsWith("hello!"), s.includes("ell"), s.includes("xyz"));
+const n = -1;
+try {
+  console.log("x".repeat(n));
+} catch (e) {
+  console.log(e);
+}
+"#,
+    );
+    assert_eq!(output, "ababab true true false\ntrue false true false\nRangeError: Invalid count value: -1\n");
+}
+
+#[test]
+fn test_uri_component_and_base64_encoding() {
+    let output = compile_and_run(
+        r#"
+const query = encodeURIComponent("a b&c=é");
+console.log(encodeURIComponent("a b"), query, decodeURIComponent(query));
+console.log(btoa("hi"), atob(btoa("hi")), atob("aGVsbG8="), btoa("é"));
+try {
+  decodeURIComponent("%4");
+} catch (e) {
+  console.log("malformed");
+}
+"#,
+    );
+    assert_eq!(output, "a%20b a%20b%26c%3D%C3%A9 a b&c=é\naGk= hi hello 6Q==\nmalformed\n");
+}
+
+// ============================================================================
+// Classes
+// ============================================================================
+
+#[test]
+fn test_constructor_parameter_properties() {
+    let output = compile_and_run(
+        r#"
+class Animal {
+  constructor(public name: string) {}
+}
+class Dog extends Animal {
+  constructor(name: string, private readonly tricks: number) {
+    super(name);
+    console.log(this.name + " knows " + this.tricks);
+  }
+  describe(): string { return this.name + "/" + this.tricks; }
+}
+const d = new Dog("rex", 3);
+console.log(d.describe());
+console.log(d.name);
+"#,
+    );
+    assert_eq!(output.trim(), "rex knows 3\nrex/3\nrex");
+}
+
+#[test]
+fn test_parameter_property_modifiers() {
+    let output = compile_and_run(
+        r#"
+class Point {
+  constructor(public x: number, readonly y: number) {
+    console.log(this.x + this.y);
+  }
+  sum(): number { return this.x + this.y; }
+}
+const a = new Point(1, 2);
+console.log(a.sum());
+console.log(a.y);
+class Base {
+  constructor(protected label: string) {}
+}
+class Child extends Base {
+  constructor(label: string, private readonly size: number) {
+    const up = label + "!";
+    super(up);
+    console.log(this.label + " " + this.size);
+  }
+  show(): string { return this.label + "/" + this.size; }
+}
+const c = new Child("hi", 3);
+console.log(c.show());
+"#,
+    );
+    assert_eq!(output, "3\n3\n2\nhi! 3\nhi!/3\n");
+}
+
+#[test]
+fn test_class_field_initializers() {
+    let output = compile_and_run(
+        r#"
+class Counter {
+  count = 10;
+  label: string = "ticks";
+  step: number;
+  constructor(step: number) {
+    console.log(this.count);
+    this.step = step;
+  }
+  tick(): number { this.count = this.count + this.step; return this.count; }
+}
+const c = new Counter(5);
+console.log(c.tick());
+console.log(c.label);
+class Config {
+  retries = 3;
+  verbose = false;
+}
+const cfg = new Config();
+console.log(cfg.retries);
+console.log(cfg.verbose);
+class Base {
+  tag = "base";
+  constructor(public id: number) {}
+}
+class Derived extends Base {
+  twice = this.id * 2;
+}
+const d = new Derived(21);
+console.log(d.tag + " " + d.id + " " + d.twice);
+"#,
+    );
+    assert_eq!(output, "10\n15\nticks\n3\nfalse\nbase 21 42\n");
+}
+
+#[test]
+fn test_field_initialization_is_checked() {
+    let (stdout, stderr) = compile_should_fail(
+        r#"
+class Job {
+  id!: number;
+  note?: string;
+  attempts: number;
+  constructor(retry: boolean) {
+    if (retry) { this.attempts = 1; }
+  }
+}
+"#,
+    );
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(
+        combined.contains("property 'attempts' has no initializer and is not definitely assigned"),
+        "{}",
+        combined
+    );
+
+    let (stdout, stderr) = compile_should_fail(
+        r#"
+class Grid {
+  cells = size * size;
+  constructor(size: number) {}
+}
+"#,
+    );
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("cannot reference constructor parameter 'size'"), "{}", combined);
+}
+
+#[test]
+fn test_symbol_iterator_drives_for_of() {
+    let output = compile_and_run(
+        r#"
+class RangeIterator {
+  current: number;
+  end: number;
+  constructor(start: number, end: number) {
+    this.current = start;
+    this.end = end;
+  }
+  next(): { value: number; done: boolean } {
+    if (this.current < this.end) {
+      const value = this.current;
+      this.current = this.current + 1;
+      return { value: value, done: false };
+    }
+    return { value: 0, done: true };
+  }
+}
+class Range {
+  start: number;
+  end: number;
+  constructor(start: number, end: number) {
+    this.start = start;
+    this.end = end;
+  }
+  [Symbol.iterator](): RangeIterator {
+    return new RangeIterator(this.start, this.end);
+  }
+}
+for (const n of new Range(2, 5)) {
+  console.log(n);
+}
+let total: number = 0;
+for (const n of new Range(1, 11)) {
+  if (n == 4) continue;
+  if (n > 6) break;
+  total = total + n;
+}
+console.log(total);
+const it = new Range(7, 9)[Symbol.iterator]();
+console.log(it.current);
+console.log(Symbol.iterator === Symbol.iterator);
+console.log(Symbol.iterator === Symbol.dispose);
+"#,
+    );
+    assert_eq!(output, "2\n3\n4\n17\n7\ntrue\nfalse\n");
+}
+
+#[test]
+fn test_custom_iterables_follow_iterator_protocol() {
+    let output = compile_and_run(
+        r#"
+class WordIter {
+  words: string[];
+  i: number;
+  constructor(words: string[]) { this.words = words; this.i = 0; }
+  next(): IteratorResult<string> {
+    if (this.i >= this.words.length) return { value: "", done: true };
+    const w = this.words[this.i];
+    this.i = this.i + 1;
+    return { value: w, done: false };
+  }
+  return(): IteratorResult<string> {
+    console.log("closed after " + this.i);
+    return { value: "", done: true };
+  }
+}
+class Sentence {
+  words: string[];
+  constructor(words: string[]) { this.words = words; }
+  [Symbol.iterator](): WordIter { return new WordIter(this.words); }
+}
+class LoudSentence extends Sentence {}
+for (const w of new LoudSentence(["hello", "big", "world"])) {
+  console.log(w.toUpperCase());
+}
+const pair = new Sentence(["a", "b"]);
+let joined: string = "";
+for (const w of pair) {
+  for (const v of pair) {
+    joined = joined + w + v + ",";
+  }
+}
+console.log(joined);
+for (const w of new Sentence(["x", "y", "z"])) {
+  if (w == "y") break;
+  console.log(w);
+}
+"#,
+    );
+    assert_eq!(output, "HELLO\nBIG\nWORLD\naa,ab,ba,bb,\nx\nclosed after 2\n");
+}
+
+#[test]
+fn test_object_literal_iterator_is_rejected() {
+    let (stdout, stderr) = compile_should_fail(
+        r#"
+class Range {
+  constructor(public n: number) {}
+  [Symbol.iterator]() {
+    let i = 0;
+    const n = this.n;
+    return {
+      next() {
+        i = i + 1;
+        return { value: i, done: i > n };
+      }
+    };
+  }
+}
+for (const x of new Range(3)) {
+  console.log(x);
+}
+"#,
+    );
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("only class-based iterators are supported"), "{}", combined);
+}
+
+#[test]
+fn test_linked_list_iterates_with_for_of() {
+    let output = compile_and_run(
+        r#"
+class ListNode {
+  value: number;
+  next: ListNode | null;
+  constructor(value: number, next: ListNode | null) {
+    this.value = value;
+    this.next = next;
+  }
+}
+class ListIterator {
+  node: ListNode | null;
+  constructor(head: ListNode | null) {
+    this.node = head;
+  }
+  next(): { value: number; done: boolean } {
+    if (this.node === null) {
+      return { value: 0, done: true };
+    }
+    const node = this.node!;
+    this.node = node.next;
+    return { value: node.value, done: false };
+  }
+}
+class LinkedList {
+  head: ListNode | null = null;
+  size: number = 0;
+  push(value: number): this {
+    this.head = new ListNode(value, this.head);
+    this.size = this.size + 1;
+    return this;
+  }
+  [Symbol.iterator](): ListIterator {
+    return new ListIterator(this.head);
+  }
+}
+const list = new LinkedList().push(3).push(2).push(1);
+let sum: number = 0;
+for (const v of list) {
+  console.log(v * 10);
+  sum = sum + v;
+}
+console.log(sum, list.size);
+"#,
+    );
+    assert_eq!(output, "10\n20\n30\n6 3\n");
+}
+
+#[test]
+fn test_class_instances_coerce_through_to_string_and_value_of() {
+    let output = compile_and_run(
+        r#"
+class User {
+  name: string;
+  constructor(name: string) { this.name = name; }
+  toString(): string { return "User(" + this.name + ")"; }
+}
+class Plain {
+  x: number = 1;
+}
+class Money {
+  cents: number;
+  constructor(cents: number) { this.cents = cents; }
+  valueOf(): number { return this.cents; }
+}
+const user = new User("ann");
+console.log(`User: ${user}`);
+console.log("hi " + user);
+console.log(user + "!");
+console.log(user);
+const p = new Plain();
+console.log(`${p}`);
+console.log("p=" + p);
+console.log(p);
+const m = new Money(250);
+console.log(m + 50);
+console.log(m * 2);
+console.log(100 + m);
+console.log(m > 100, m < new Money(10));
+console.log("total " + m);
+const doubled: number = m * 2;
+console.log(doubled);
+"#,
+    );
+    assert_eq!(
+        output,
+        "User: User(ann)\nhi User(ann)\nUser(ann)!\nUser(ann)\n[object Plain]\np=[object Plain]\n[object Plain]\n\
+         300\n500\n350\ntrue false\ntotal 250\n500\n"
+    );
+}
+
+#[test]
+fn test_json_stringify_class_instances() {
+    let output = compile_and_run(
+        r#"
+class Address {
+  city: string;
+  zip: number;
+  constructor(city: string, zip: number) {
+    this.city = city;
+    this.zip = zip;
+  }
+}
+class Money {
+  cents: number;
+  constructor(cents: number) {
+    this.cents = cents;
+  }
+  toJSON(): string {
+    return "$" + this.cents / 100;
+  }
+}
+class User {
+  name: string;
+  admin: boolean;
+  tags: string[];
+  address: Address;
+  balance: Money;
+  #password: string;
+  constructor(name: string) {
+    this.name = name;
+    this.admin = true;
+    this.tags = ["a", "b"];
+    this.address = new Address("Oslo", 150);
+    this.balance = new Money(1250);
+    this.#password = "hunter2";
+  }
+}
+const user = new User("Ada");
+console.log(JSON.stringify(user, null, 2));
+console.log(JSON.stringify(user));
+console.log(JSON.stringify(user.address, null, "\t"));
+console.log(JSON.stringify(new Money(5)));
+"#,
+    );
+    assert_eq!(
+        output,
+        "{\n  \"name\": \"Ada\",\n  \"admin\": true,\n  \"tags\": [\n    \"a\",\n    \"b\"\n  ],\n  \
+         \"address\": {\n    \"city\": \"Oslo\",\n    \"zip\": 150\n  },\n  \"balance\": \"$12.5\"\n}\n\
+         {\"name\":\"Ada\",\"admin\":true,\"tags\":[\"a\",\"b\"],\"address\":{\"city\":\"Oslo\",\"zip\":150},\"balance\":\"$12.5\"}\n\
+         {\n\t\"city\": \"Oslo\",\n\t\"zip\": 150\n}\n\
+         \"$0.05\"\n"
+    );
+}
+
+#[test]
+fn test_json_stringify_replacer() {
+    let output = compile_and_run(
+        r#"
+class Inner {
+  x: number = 4;
+  w: number = 5;
+}
+class Point {
+  x: number = 1;
+  y: number = 2;
+  z: number = 3;
+  inner: Inner = new Inner();
+}
+const point = new Point();
+console.log(JSON.stringify(point, ["z", "x", "inner", "x"]));
+console.log(JSON.stringify(point, undefined, 0));
+const keys: string[] = ["y"];
+console.log(JSON.stringify(point, keys, 1));
+"#,
+    );
+    assert_eq!(
+        output,
+        "{\"z\":3,\"x\":1,\"inner\":{\"x\":4}}\n{\"x\":1,\"y\":2,\"z\":3,\"inner\":{\"x\":4,\"w\":5}}\n{\n \"y\": 2\n}\n"
+    );
+
+    // A replacer function is rejected rather than ignored
+    let (stdout, stderr) =
+        compile_should_fail("console.log(JSON.stringify({ a: 1 }, (key: string, value: number) => value));\n");
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("a `JSON.stringify` replacer other than an array of keys is not supported yet"), "{}", combined);
+}
+
+#[test]
+fn test_private_class_members() {
+    let output = compile_and_run(
+        r#"
+class Counter {
+  #count: number;
+  count: number;
+  constructor() {
+    this.#count = 0;
+    this.count = 100;
+  }
+  #bump(by: number): void {
+    this.#count = this.#count + by;
+  }
+  increment(): number {
+    this.#bump(2);
+    return this.#count;
+  }
+  static #label(): string { return "counter"; }
+  static describe(): string { return Counter.#label(); }
+  static isCounter(c: Counter): boolean { return #count in c; }
+}
+class Special extends Counter {
+  #count: number;
+  constructor() {
+    super();
+    this.#count = 7;
+  }
+  peek(): number { return this.#count; }
+}
+const c = new Counter();
+c.increment();
+console.log(c.increment());
+console.log(c.count);
+const s = new Special();
+console.log(s.increment());
+console.log(s.peek());
+console.log(Counter.isCounter(c));
+console.log(Counter.describe());
+"#,
+    );
+    // `#count` never collides with the public `count` or with Special's own `#count`
+    assert_eq!(output, "4\n100\n2\n7\ntrue\ncounter\n");
+}
+
+#[test]
+fn test_decorators_run_at_class_definition() {
+    let output = compile_and_run(
+        r#"
+let registry: string[] = [];
+function register(target: any, context: ClassDecoratorContext): void {
+  registry.push(context.name);
+}
+const logMethod = (target: any, context: ClassMethodDecoratorContext): void => {
+  console.log("method " + context.name);
+  if (context.static) { console.log("  static"); }
+};
+const tag = (label: string) => (target: any, context: ClassDecoratorContext): void => {
+  console.log("tag " + label + " on " + context.name);
+};
+
+@register
+class UserService {
+  @logMethod
+  find(): number { return 1; }
+  @logMethod
+  static create(): number { return 2; }
+}
+
+@register
+@tag("outer")
+@tag("inner")
+class OrderService {}
+
+for (const name of registry) {
+  console.log(name);
+}
+console.log(registry.length);
+"#,
+    );
+    // Member decorators run before class decorators; stacked ones bottom-up
+    assert_eq!(
+        output,
+        "method find\nmethod create\n  static\ntag inner on OrderService\ntag outer on OrderService\nUserService\nOrderService\n2\n"
+    );
+}
+
+#[test]
+fn test_method_decorator_wraps_method() {
+    let output = compile_and_run(
+        r#"
+function double(method: (x: number) => number, context: ClassMethodDecoratorContext): (x: number) => number {
+  return (x: number): number => method(x) * 2;
+}
+const plus = (n: number) => (method: (x: number) => number, context: ClassMethodDecoratorContext): (x: number) => number => {
+  return (x: number): number => method(x) + n;
+};
+
+class Scale {
+  factor: number;
+  constructor(factor: number) { this.factor = factor; }
+  @plus(1)
+  @double
+  apply(x: number): number { return x * this.factor; }
+  twice(x: number): number { return this.apply(x) + this.apply(x); }
+  @double
+  static unit(x: number): number { return x; }
+}
+
+const a = new Scale(3);
+const b = new Scale(10);
+console.log(a.apply(2));
+console.log(b.apply(1));
+console.log(a.twice(1));
+console.log(Scale.unit(5));
+"#,
+    );
+    // Each call runs the wrapper chain on its own receiver
+    assert_eq!(output, "13\n21\n14\n10\n");
+}
+
+#[test]
+fn test_class_decorator_replaces_class() {
+    let output = compile_and_run(
+        r#"
+let made: number = 0;
+function counted(target: (label: string) => Tag, context: ClassDecoratorContext): (label: string) => Tag {
+  return (label: string): Tag => {
+    made = made + 1;
+    return new target(label + "!");
+  };
+}
+
+@counted
+class Tag {
+  label: string;
+  constructor(label: string) { this.label = label; }
+}
+
+const t = new Tag("a");
+const u = new Tag("b");
+console.log(t.label);
+console.log(u.label);
+console.log(made);
+"#,
+    );
+    assert_eq!(output, "a!\nb!\n2\n");
+}
+
+#[test]
+fn test_decorator_signatures_are_checked() {
+    let (stdout, stderr) = compile_should_fail(
+        r#"
+const replace = (target: any, context: ClassDecoratorContext): number => 1;
+@replace
+class A {}
+"#,
+    );
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("type mismatch"), "{}", combined);
+
+    let (stdout, stderr) = compile_should_fail(
+        r#"
+const init = (target: undefined, context: ClassFieldDecoratorContext): number => 1;
+class C {
+  @init
+  x: number = 0;
+}
+"#,
+    );
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("a field decorator must return void"), "{}", combined);
+
+    let (stdout, stderr) = compile_should_fail(
+        r#"
+const onMethod = (target: any, context: ClassMethodDecoratorContext): void => {};
+@onMethod
+class B {}
+"#,
+    );
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("type mismatch"), "{}", combined);
+}
+
+// ============================================================================
+// Namespaces
+// ============================================================================
+
+#[test]
+fn test_namespace_members() {
+    let output = compile_and_run(
+        r#"
+namespace M { export function f() { return 1; } }
+console.log(M.f());
+namespace Geometry {
+  export const UNIT = 10;
+  const scale = 2;
+  export let area = UNIT * scale;
+  function square(n: number): number { return n * n; }
+  export function cube(n: number): number { return square(n) * n; }
+  export namespace Angles {
+    export function half(n: number): number { return n / 2; }
+  }
+}
+namespace A.B { export function label(): string { return "ab"; } }
+function total(): number { return M.f() + Geometry.UNIT; }
+console.log(Geometry.cube(3));
+console.log(Geometry.area);
+console.log(Geometry.Angles.half(9));
+console.log(A.B.label());
+console.log(total());
+"#,
+    );
+    assert_eq!(output, "1\n27\n20\n4.5\nab\n11\n");
+}
+
+// ============================================================================
+// Object Literals
+// ============================================================================
+
+#[test]
+fn test_object_literal_accessors() {
+    let output = compile_and_run(
+        r#"
+const cart = {
+  items: [10, 20, 12.5],
+  discount: 0,
+  get total(): number {
+    let sum: number = 0;
+    for (const item of this.items) {
+      sum = sum + item;
+    }
+    return sum - this.discount;
+  },
+  get count() {
+    return this.items.length;
+  },
+  set percentOff(p: number) {
+    this.discount = this.total * p / 100;
+  },
+};
+console.log(cart.total);
+console.log(cart.count);
+cart.percentOff = 10;
+console.log(cart.discount);
+console.log(cart.total);
+const label = {
+  first: "Ada",
+  last: "Lovelace",
+  get full(): string { return this.first + " " + this.last; },
+  set full(value: string) { this.first = value; },
+};
+label.full = "Grace";
+console.log(label.full);
+console.log(label);
+console.log(JSON.stringify({ x: 3, y: 4, get sum(): number { return this.x + this.y; } }));
+"#,
+    );
+    assert_eq!(
+        output,
+        "42.5\n3\n4.25\n38.25\nGrace Lovelace\n{ first: 'Grace', last: 'Lovelace', full: [Getter/Setter] }\n{\"x\":3,\"y\":4,\"sum\":7}\n"
+    );
+}
+
+#[test]
+fn test_object_statics() {
+    let output = compile_and_run(
+        r#"
+const prices = { apple: 1.5, pear: 2 };
+console.log(Object.keys(prices), Object.values(prices));
+for (const entry of Object.entries(prices)) {
+  console.log(entry[0].toUpperCase(), entry[1] * 2);
+}
+console.log(Object.fromEntries(Object.entries(prices)));
+const merged = Object.assign({ a: 1 }, { b: "x" }, { a: 3 });
+console.log(merged, merged.a);
+const frozen = Object.freeze({ x: 1 });
+try {
+  frozen.x = 10;
+} catch (e) {
+  console.log(e);
+}
+console.log(frozen.x);
+"#,
+    );
+    assert_eq!(
+        output,
+        "[ 'apple', 'pear' ] [ 1.5, 2 ]\nAPPLE 3\nPEAR 4\n{ apple: 1.5, pear: 2 }\n{ a: 3, b: 'x' } 3\n\
+         TypeError: Cannot assign to read only property 'x' of object\n1\n"
+    );
+}
+
+#[test]
+fn test_in_operator_looks_up_properties() {
+    let output = compile_and_run(
+        r##"
+const config = { port: 80, host: "x" };
+console.log("port" in config, "missing" in config);
+const key: string = "host";
+console.log(key in config);
+const xs: number[] = [1, 2, 3];
+console.log(2 in xs, 3 in xs, "length" in xs, "1" in xs, "01" in xs);
+class Point {
+  x: number = 1;
+  #secret: number = 2;
+  norm(): number { return this.x; }
+}
+class Point3 extends Point { z: number = 3; }
+const p = new Point3();
+console.log("x" in p, "z" in p, "norm" in p, "#secret" in p, "w" in p);
+"##,
+    );
+    assert_eq!(output, "true false\ntrue\ntrue false true true false\ntrue true true false false\n");
+}
+
+#[test]
+fn test_delete_removes_properties() {
+    let output = compile_and_run(
+        r##"
+const obj = { cache: 1, keep: 2, last: 3 };
+console.log(delete obj.cache, "cache" in obj, Object.keys(obj));
+const key: string = "last";
+delete obj[key];
+console.log(obj);
+const xs: number[] = [1, 2, 3];
+delete xs[1];
+console.log(xs.length, xs[0], xs[2]);
+const names: string[] = ["a", "b"];
+delete names[0];
+console.log(names[0], names[1]);
+const frozen = Object.freeze({ x: 1 });
+try { delete frozen.x; } catch (e) { console.log(e); }
+"##,
+    );
+    assert_eq!(
+        output,
+        "true false [ 'keep', 'last' ]\n{ keep: 2 }\n3 1 3\nundefined b\nTypeError: Cannot delete property 'x' of object\n"
+    );
+}
+
+// ============================================================================
+// Arrays
+// ============================================================================
+
+#[test]
+fn test_array_from_and_of() {
+    let output = compile_and_run(
+        r#"
+function* evens() { yield 2; yield 4; }
+const xs = [1, 2, 3];
+const copy: number[] = Array.from(xs);
+copy.push(4);
+console.log(Array.of(7, 8), xs, copy);
+console.log(Array.from("héllo"), Array.from(evens()));
+console.log(Array.from({ length: 4 }, (_, i) => i * i));
+const labels: string[] = Array.from("ab", (ch, i) => ch.toUpperCase() + i);
+console.log(labels, Array.from(xs, (x) => x * 10));
+"#,
+    );
+    assert_eq!(
+        output,
+        "[ 7, 8 ] [ 1, 2, 3 ] [ 1, 2, 3, 4 ]\n[ 'h', 'é', 'l', 'l', 'o' ] [ 2, 4 ]\n[ 0, 1, 4, 9 ]\n\
+         [ 'A0', 'B1' ] [ 10, 20, 30 ]\n"
+    );
+}
+
+#[test]
+fn test_spread_iterables_into_arrays() {
+    let output = compile_and_run(
+        r#"
+function* evens() { yield 2; yield 4; }
+const xs = [1, 2];
+console.log([...xs, 3], [0, ...evens(), ...xs], [..."hey"]);
+for (const ch of "añb") {
+  console.log(ch);
+}
+"#,
+    );
+    assert_eq!(output, "[ 1, 2, 3 ] [ 0, 2, 4, 1, 2 ] [ 'h', 'e', 'y' ]\na\nñ\nb\n");
+}
+
+#[test]
+fn test_presized_array_construction() {
+    let output = compile_and_run(
+        r#"
+const zeros: number[] = new Array(3).fill(0);
+const n = 5;
+const inner = Array(n).fill(7, 1, -1);
+const dashes = new Array<string>(2).fill("-");
+console.log(zeros, inner, inner.length, dashes);
+console.log(new Array<number>(4).fill(1, 2), Array(1, 2, 3), new Array(), Array(3).length);
+"#,
+    );
+    assert_eq!(output, "[ 0, 0, 0 ] [ 0, 7, 7, 7, 0 ] 5 [ '-', '-' ]\n[ 0, 0, 1, 1 ] [ 1, 2, 3 ] [] 3\n");
+}
+
+#[test]
+fn test_array_fill_reverse_and_copy_within() {
+    let output = compile_and_run(
+        r#"
+console.log([1, 2, 3].reverse(), [1, 2, 3].fill(9, 1), [1, 2, 3, 4].fill(0, -3, -1));
+const xs = [1, 2, 3, 4, 5];
+xs.copyWithin(0, 3);
+console.log(xs, [1, 2, 3, 4, 5].copyWithin(-2, 0, 2), [1, 2, 3, 4, 5].copyWithin(1, 0));
+const names: string[] = ["a", "b", "c"];
+names.reverse();
+console.log(names, names.reverse()[0]);
+"#,
+    );
+    assert_eq!(
+        output,
+        "[ 3, 2, 1 ] [ 1, 9, 9 ] [ 1, 0, 0, 4 ]\n[ 4, 5, 3, 4, 5 ] [ 1, 2, 3, 1, 2 ] [ 1, 1, 2, 3, 4 ]\n[ 'c', 'b', 'a' ] a\n"
+    );
+}
+
+#[test]
+fn test_array_concat_returns_a_new_array() {
+    let output = compile_and_run(
+        r#"
+console.log([1, 2].concat([3, 4], 5));
+const xs = [1, 2];
+const ys = xs.concat(3, [], [4]);
+const copy = xs.concat();
+console.log(xs, ys, ys.length, copy);
+const names: string[] = ["a"];
+console.log(names.concat(["b", "c"], "d"), names);
+"#,
+    );
+    assert_eq!(
+        output,
+        "[ 1, 2, 3, 4, 5 ]\n[ 1, 2 ] [ 1, 2, 3, 4 ] 4 [ 1, 2 ]\n[ 'a', 'b', 'c', 'd' ] [ 'a' ]\n"
+    );
+}
+
+#[test]
+fn test_destructure_tuple_returned_from_function() {
+    let output = compile_and_run(
+        r#"
+function parse(input: string): [boolean, string] {
+  if (input.length > 0) {
+    return [true, "parsed " + input];
+  }
+  return [false, "empty input"];
+}
+const [ok, msg] = parse("abc");
+if (ok) {
+  console.log(msg);
+}
+const [failed, reason] = parse("");
+if (!failed) {
+  console.log("error:", reason);
+}
+const result = parse("x");
+console.log(ok, failed, result, result[1]);
+"#,
+    );
+    assert_eq!(output, "parsed abc\nerror: empty input\ntrue false [ true, 'parsed x' ] parsed x\n");
+}
+
+#[test]
+fn test_multi_declarator_statements_with_patterns() {
+    let output = compile_and_run(
+        r#"
+function compute(n: number): number { return n * 10; }
+let a = 1, b = a + 1, c = compute(b);
+console.log(a, b, c);
+const double = (x: number): number => x * 2, four = double(2);
+const data = { name: "zaco", size: { w: 3, h: 4 } };
+const label = "n", { name, size: { w, h } } = data, area = w * h;
+console.log(four, label, name, area);
+const [[m, n], k] = [[1, 2], 3], total = m + n + k;
+console.log(total);
+for (let i = 0, j = i + 10; i < 2; i++) console.log(i, j);
+"#,
+    );
+    assert_eq!(output, "1 2 20\n4 n zaco 12\n6\n0 10\n1 10\n");
+}
+
+#[test]
+fn test_var_is_function_scoped_and_hoisted() {
+    let output = compile_and_run(
+        r#"
+function pick(flag: boolean): number {
+  if (flag) { var result = 10; } else { var result = 20; }
+  return result;
+}
+const describe = (n: number): string => {
+  if (n > 0) { var sign = "positive"; } else { var sign = "non-positive"; }
+  return sign;
+};
+console.log(before);
+var before = "set";
+var before;
+console.log(before);
+for (var i: number = 0; i < 3; i++) { var last = i * 2; }
+try { var inTry = "t"; } catch (e) {}
+console.log(i, last, inTry, pick(true), pick(false), describe(-1));
+var note = "a";
+var note = "b";
+console.log(note);
+console.log(count, count + 1, "n=" + count);
+var count: number = 2;
+console.log(count, 0 / 0, -1 / 0);
+"#,
+    );
+    assert_eq!(output, "undefined\nset\n3 4 t 10 20 non-positive\nb\nundefined NaN n=undefined\n2 NaN -Infinity\n");
+}
+
+// ============================================================================
+// IR Emission
+// ============================================================================
+
+#[test]
+fn test_ir_emission() {
+    let ir = compile_to_ir(r#"console.log("test");"#);
+    assert!(ir.contains("fn main("));
+    assert!(ir.contains("zaco_print_str"));
+    assert!(ir.contains("Return"));
+}
+
+#[test]
+fn test_trace_lowering_logs_to_stderr() {
+    let dir = std::env::temp_dir().join("zaco_test_trace_lowering");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.ts"), "function add(a: number, b: number): number {\n  return a + b;\n}\nconsole.log(add(1, 2));\n")
+        .unwrap();
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("main.ts"))
+        .args(["--emit", "ir", "--trace-lowering"])
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "[lower] decl Function @0..62\n\
+             [lower]   stmt Return @47..60\n\
+             [lower]     expr Binary @54..59\n"
+        ),
+        "got {}",
+        stderr
+    );
+    assert!(stderr.contains("[lower]     extern zaco_console_put_f64([F64]) -> Void\n"), "got {}", stderr);
+    // The IR itself still goes to stdout, and tracing is off by default
+    assert!(String::from_utf8_lossy(&output.stdout).contains("fn main("));
+    let quiet = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("main.ts"))
+        .args(["--emit", "ir"])
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!String::from_utf8_lossy(&quiet.stderr).contains("[lower]"));
+}
+
+#[test]
+fn test_global_this_lowers_like_bare_globals() {
+    let via_global_this = compile_to_ir(r#"console.log(globalThis.Math.floor(3.7));"#);
+    let bare = compile_to_ir(r#"console.log(Math.floor(3.7));"#);
+    assert!(bare.contains("fn main("));
+    assert_eq!(via_global_this, bare);
+
+    let output = compile_and_run(
+        r#"
+globalThis.console.log(globalThis.Math.floor(3.7));
+const biggest: number = globalThis.Math.max(2, 9);
+console.log(biggest);
+"#,
+    );
+    assert_eq!(output, "3\n9\n");
+}
+
+#[test]
+fn test_const_enum_members_are_inlined() {
+    // `Flags.B` lowers to the same bare constant as the literal
+    let inlined = compile_to_ir("const enum Flags { A = 1 << 0, B = 1 << 1 }\nconsole.log(Flags.B);\n");
+    let literal = compile_to_ir("console.log(2);\n");
+    assert!(literal.contains("fn main("));
+    assert_eq!(inlined, literal);
+
+    let output = compile_and_run(
+        r#"
+const enum Flags { None = 0, Read = 1 << 0, Write = 1 << 1, ReadWrite = Read | Flags.Write, Top = ~(-1 >>> 1) }
+const enum Label { Prefix = "zaco", Full = Prefix + "-" + "v" + 2 }
+enum Level { Low = 1, Mid, High = Mid * 10 }
+function canWrite(flags: Flags): boolean {
+  return (flags & Flags.Write) != 0;
+}
+const mode: Flags = Flags.ReadWrite;
+console.log(mode, canWrite(mode), canWrite(Flags.Read), Flags.Top);
+console.log(Label.Full, Level.Mid, Level.High);
+"#,
+    );
+    assert_eq!(output, "3 true false -2147483648\nzaco-v2 2 20\n");
+
+    // Non-constant initializers and run-time uses of a const enum
+    let dir = std::env::temp_dir().join("zaco_test_const_enum_errors");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let compile_error = |source: &str| {
+        fs::write(dir.join("main.ts"), source).unwrap();
+        let output = Command::new(zaco_binary())
+            .arg("compile")
+            .arg(dir.join("main.ts"))
+            .args(["--emit", "ir"])
+            .output()
+            .expect("Failed to run zaco compiler");
+        assert!(!output.status.success());
+        format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr))
+    };
+    let combined = compile_error("const enum Seed { A = 1, B = Math.random() }\nconsole.log(Seed.A);\n");
+    assert!(
+        combined.contains("const enum member 'B' must be initialized with a constant expression"),
+        "got {}",
+        combined
+    );
+    let combined = compile_error("const enum Seed { A = 1 }\nconsole.log(Seed);\n");
+    assert!(combined.contains("const enum 'Seed' has no run-time object"), "got {}", combined);
+}
+
+#[test]
+fn test_defines_eliminate_dead_branches() {
+    let source = r#"
+if (DEBUG) {
+  console.log("debug only", Math.sqrt(16));
+}
+console.log(MODE === "prod" ? "optimized" : "unoptimized", LEVEL + 1);
+"#;
+    let dir = std::env::temp_dir().join("zaco_test_defines");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.ts"), source).unwrap();
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("main.ts"))
+        .args(["--emit", "ir", "--trace-lowering"])
+        .args(["--define", "DEBUG=false", "--define", "MODE=prod", "--define", "LEVEL=2"])
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(output.status.success());
+    // Neither the dead branch nor its string and runtime extern are emitted
+    let release = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(release, compile_to_ir("console.log(\"optimized\", 2 + 1);\n"));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("zaco_math_sqrt"));
+
+    let debug = compile_and_run_with_args(source, &["--define", "DEBUG=true", "--define", "MODE=dev", "--define", "LEVEL=2"]);
+    assert_eq!(debug, "debug only 4\nunoptimized 3\n");
+}
+
+#[test]
+fn test_import_meta_locates_module() {
+    let output = compile_and_run(
+        r#"
+console.log(import.meta.url);
+console.log(import.meta.dirname);
+console.log(import.meta.filename == __filename);
+"#,
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    let path = lines[0].strip_prefix("file://").expect("not a file: URL");
+    assert!(path.ends_with("/test_input.ts"), "got {}", lines[0]);
+    assert_eq!(lines[1], path.trim_end_matches("/test_input.ts"));
+    assert_eq!(lines[2], "true");
+}
+
+#[test]
+fn test_asm_emission() {
+    let asm = compile_to_emit(
+        r#"
+function add(a: number, b: number): number {
+  return a + b;
+}
+console.log(add(1, 2));
+"#,
+        "asm",
+    );
+    let add = asm
+        .split("function add:\n")
+        .nth(1)
+        .and_then(|rest| rest.split("\n\n").next())
+        .expect("no disassembly for add");
+    assert!(add.lines().any(|line| line.trim() == "ret"), "got {}", add);
+    assert!(asm.contains("function main:\n"));
+}
+
+#[test]
+fn test_lib_emission_exports_every_entry() {
+    let dir = std::env::temp_dir().join("zaco_test_lib_entries");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    // Both entries define a private `helper`, which must not collide
+    fs::write(
+        dir.join("a.ts"),
+        "function helper(x: number): number { return x * 2; }\n\
+         export function double(x: number): number { return helper(x); }\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("b.ts"),
+        "function helper(x: number): number { return x + 1; }\n\
+         export function inc(x: number): number { return helper(x); }\n",
+    )
+    .unwrap();
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("a.ts"))
+        .arg(dir.join("b.ts"))
+        .args(["--emit", "lib", "-o"])
+        .arg(dir.join("libab"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let nm = Command::new("nm").arg(dir.join("libab.a")).output().expect("Failed to run nm");
+    let symbols = String::from_utf8_lossy(&nm.stdout);
+    for name in ["double", "inc", "zaco_lib_init"] {
+        assert!(
+            symbols.lines().any(|line| line.ends_with(&format!(" T {}", name))),
+            "{} not exported:\n{}",
+            name,
+            symbols
+        );
+    }
+    let helpers = symbols.lines().filter(|line| line.contains(" t helper__")).count();
+    assert_eq!(helpers, 2, "{}", symbols);
+
+    // Several inputs are only accepted for a library
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("a.ts"))
+        .arg(dir.join("b.ts"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_obj_out_dir_writes_an_object_per_module() {
+    let dir = std::env::temp_dir().join("zaco_test_out_dir");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src/lib")).unwrap();
+    fs::write(
+        dir.join("src/lib/util.ts"),
+        "export function twice(x: number): number { return x * 2; }\nconsole.log(\"util\");\n",
+    )
+    .unwrap();
+    fs::write(dir.join("src/entry.ts"), "import { twice } from \"./lib/util\";\nconsole.log(\"entry\");\n").unwrap();
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("src/entry.ts"))
+        .args(["--emit", "obj", "--out-dir"])
+        .arg(dir.join("build"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let symbols = |object: &str| {
+        let nm = Command::new("nm").arg(dir.join("build").join(object)).output().expect("Failed to run nm");
+        assert!(nm.status.success(), "{} was not written", object);
+        String::from_utf8_lossy(&nm.stdout).into_owned()
+    };
+    // The entry's main runs the other module's top-level code, defined there
+    let entry = symbols("entry.o");
+    let util = symbols("lib/util.o");
+    assert!(entry.lines().any(|line| line.ends_with(" T main")), "{}", entry);
+    assert!(entry.lines().any(|line| line.contains(" U __module_init_lib_util_")), "{}", entry);
+    assert!(util.lines().any(|line| line.contains(" T __module_init_lib_util_")), "{}", util);
+    assert!(util.lines().any(|line| line.ends_with(" T twice")), "{}", util);
+
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("src/entry.ts"))
+        .arg("--out-dir")
+        .arg(dir.join("build"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--out-dir requires --emit obj"));
+}
+
+// ============================================================================
+// Library API
+// ============================================================================
+
+#[test]
+fn test_library_compiles_source_to_object() {
+    use zaco_driver::{CompileOptions, Input};
+
+    let path = std::env::temp_dir().join("zaco_library_api.ts");
+    let options = |source: &str| CompileOptions {
+        inputs: vec![Input::Source { path: path.clone(), source: source.to_string() }],
+        ..CompileOptions::default()
+    };
+
+    let output = zaco_driver::compile(&options(
+        "function square(n: number): number { return n * n; }\nconsole.log(square(7));\n",
+    ))
+    .expect("source should compile");
+    assert!(output.ir.functions.iter().any(|f| f.name == "square"));
+    let magic = &output.object[..4];
+    assert!(
+        magic == b"\x7fELF" || magic == [0xcf, 0xfa, 0xed, 0xfe],
+        "not an object file: {:?}",
+        magic
+    );
+
+    // Errors come back as diagnostics rather than being printed
+    let diagnostics = zaco_driver::compile(&options("let x: number = \"a\";\n")).unwrap_err();
+    assert_eq!(diagnostics[0].code, "E2001");
+    assert_eq!(diagnostics[0].file.as_deref(), Some(path.as_path()));
+    let diagnostics = zaco_driver::compile(&options("let x = (1 + ;\n")).unwrap_err();
+    assert_eq!(diagnostics[0].code, "E1000");
+}
+
+// ============================================================================
+// Phase Conformance
+// ============================================================================
+
+/// Programs that type check but fail a later phase, with the reason. Each
+/// is a compiler bug; fix it, then remove the entry.
+const KNOWN_DISAGREEMENTS: &[(&str, &str)] = &[
+    ("examples/ownership.ts", "`clone` is not lowered"),
+    ("examples/npm_test/main.ts", "functions imported from npm packages are not lowered"),
+    ("examples/test.ts", "`fs.readFile` with a callback is not lowered"),
+];
+
+/// Run every phase but linking on `path`. On failure, returns the phase
+/// that rejected the program ("parse", "check", "lower" or "codegen") and
+/// its first diagnostic.
+fn compile_phases(path: &std::path::Path) -> Result<(), (&'static str, String)> {
+    use zaco_driver::{pipeline, CompileOptions, Diagnostic, Input, ParseCache};
+
+    let first = |diagnostics: &[Diagnostic]| {
+        diagnostics.first().map_or_else(String::new, |d| format!("[{}] {}", d.code, d.message))
+    };
+    let options = CompileOptions { inputs: vec![Input::File(path.to_path_buf())], ..CompileOptions::default() };
+    let mut parse_cache = ParseCache::new();
+    let graph = pipeline::discover(&options, &mut parse_cache).map_err(|d| ("parse", first(&d)))?;
+    let ir = pipeline::lower(&options, &graph, &parse_cache).map_err(|d| {
+        let phase = if d.iter().any(|d| d.code.starts_with("E3")) { "lower" } else { "check" };
+        (phase, first(&d))
+    })?;
+    pipeline::codegen(&ir, &options).map_err(|d| ("codegen", first(&[d])))?;
+    Ok(())
+}
+
+fn collect_ts_files(dir: &std::path::Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != "node_modules") {
+                collect_ts_files(&path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "ts") {
+            files.push(path);
+        }
+    }
+}
+
+/// A program the type checker accepts must make it through lowering and
+/// code generation: the snippets in tests/conformance are all valid, and
+/// the examples must not fail after type checking.
+#[test]
+fn test_type_checker_and_lowerer_agree() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let root = manifest_dir.parent().unwrap().parent().unwrap();
+    let mut failures = Vec::new();
+
+    let mut snippets = Vec::new();
+    collect_ts_files(&manifest_dir.join("tests/conformance"), &mut snippets);
+    assert!(!snippets.is_empty());
+    for path in snippets {
+        if let Err((phase, error)) = compile_phases(&path) {
+            failures.push(format!("{}: rejected at {}: {}", path.display(), phase, error));
+        }
+    }
+
+    let mut examples = Vec::new();
+    collect_ts_files(&root.join("examples"), &mut examples);
+    for path in examples {
+        let name = path.strip_prefix(root).unwrap().to_string_lossy().into_owned();
+        let known = KNOWN_DISAGREEMENTS.iter().any(|(known, _)| *known == name);
+        match compile_phases(&path) {
+            Err((phase @ ("lower" | "codegen"), error)) if !known => {
+                failures.push(format!("{}: type checks but is rejected at {}: {}", name, phase, error));
+            }
+            // Rejected before lowering, or a known disagreement
+            Err(_) => {}
+            Ok(()) if known => failures.push(format!("{}: compiles now; remove it from KNOWN_DISAGREEMENTS", name)),
+            Ok(()) => {}
+        }
+    }
+
+    assert!(failures.is_empty(), "phases disagree:\n{}", failures.join("\n"));
+}
+
+// ============================================================================
+// WebAssembly
+// ============================================================================
+
+#[test]
+fn test_emit_wasm_runs_in_host() {
+    let dir = std::env::temp_dir().join("zaco_test_wasm");
+    let _ = fs::create_dir_all(&dir);
+    fs::write(
+        dir.join("hello.ts"),
+        r#"function fib(n: number): number {
+  if (n < 2) { return n; }
+  return fib(n - 1) + fib(n - 2);
+}
+let total: number = 0;
+for (let i = 0; i < 10; i++) { total += i % 3; }
+console.log("hello from wasm");
+console.log(fib(20), total, total > 5, ~7);
+"#,
+    )
+    .unwrap();
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("hello.ts"))
+        .args(["--emit", "wasm", "-o"])
+        .arg(dir.join("hello"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let wasm = fs::read(dir.join("hello.wasm")).unwrap();
+    assert_eq!(&wasm[..4], b"\0asm");
+
+    let run = Command::new("node")
+        .arg(root.join("runtime/zaco_wasm_host.mjs"))
+        .arg(dir.join("hello.wasm"))
+        .output()
+        .expect("node is required to run the WebAssembly host");
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "hello from wasm\n6765 9 true -8\n");
+
+    // Heap values are outside the wasm subset and are reported, not miscompiled
+    fs::write(dir.join("array.ts"), "const xs: number[] = [1, 2];\nconsole.log(xs[0]);\n").unwrap();
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("array.ts"))
+        .args(["--emit", "wasm", "-o"])
+        .arg(dir.join("array"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not support arrays"));
+
+    // So are runtime functions the host does not implement
+    fs::write(dir.join("strlen.ts"), "const s: string = \"abc\";\nconsole.log(s.length);\n").unwrap();
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("strlen.ts"))
+        .args(["--emit", "wasm", "-o"])
+        .arg(dir.join("strlen"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'zaco_str_len'"));
+}
+
+// ============================================================================
+// Control Flow
+// ============================================================================
+
+#[test]
+fn test_if_true() {
+    let output = compile_and_run(
+        r#"
+let x: number = 1;
+if (x) {
+    console.log("yes");
+}
+"#,
+    );
+    assert_eq!(output.trim(), "yes");
+}
+
+#[test]
+fn test_if_false() {
+    let output = compile_and_run(
+        r#"
+let x: number = 0;
+if (x) {
+    console.log("yes");
+} else {
+    console.log("no");
+}
+"#,
+    );
+    assert_eq!(output.trim(), "no");
+}
+
+#[test]
+fn test_for_update_with_comma_sequence() {
+    let output = compile_and_run(
+        r#"
+let count: number = 0;
+for (let i = 0, j = 10; i < j; i++, j--) {
+    count++;
+}
+console.log(count);
+"#,
+    );
+    assert_eq!(output.trim(), "5");
+}
+
+#[test]
+fn test_for_init_with_multiple_declarators() {
+    let output = compile_and_run(
+        r#"
+let total: number = 0;
+for (let i = 0, n = 3; i < n; i++) {
+    total += n;
+}
+console.log(total);
+let a: number = 0;
+let b: number = 0;
+for (a = 1, b = 2; a < 3; a++) {
+    b += a;
+}
+console.log(a, b);
+"#,
+    );
+    assert_eq!(output.trim(), "9\n3 5");
+}
+
+#[test]
+fn test_nullish_chains_evaluate_their_operands_once() {
+    // Each lookup bumps a counter, so it shows how often a side of `??`
+    // or the base of an optional chain was evaluated
+    let output = compile_and_run(
+        r#"
+class Counter {
+    lookups: number = 0;
+    fallbacks: number = 0;
+    greet(name: string): string { return "hi " + name; }
+}
+interface Inner { label: string; }
+interface Outer { inner: Inner | null; }
+function missing(counter: Counter): string | null {
+    counter.lookups++;
+    return null;
+}
+function present(counter: Counter): string | null {
+    counter.lookups++;
+    return "value";
+}
+function fallback(counter: Counter): string {
+    counter.fallbacks++;
+    return "fallback";
+}
+function outer(counter: Counter): Outer | null {
+    counter.lookups++;
+    return { inner: { label: "deep" } };
+}
+function find(counter: Counter, found: boolean): Counter | null {
+    counter.lookups++;
+    return found ? counter : null;
+}
+
+let c = new Counter();
+console.log(missing(c) ?? fallback(c), c.lookups, c.fallbacks);
+c = new Counter();
+console.log(present(c) ?? fallback(c), c.lookups, c.fallbacks);
+c = new Counter();
+console.log(missing(c) ?? missing(c) ?? "last", c.lookups);
+c = new Counter();
+console.log(outer(c)?.inner?.label ?? "none", c.lookups);
+c = new Counter();
+console.log(find(c, true)?.greet("zaco") ?? "none", c.lookups);
+c = new Counter();
+console.log(find(c, false)?.greet(fallback(c)) ?? "skipped", c.lookups, c.fallbacks);
+c = new Counter();
+let slot: string | null = null;
+slot ??= fallback(c);
+slot ??= fallback(c);
+console.log(slot, c.fallbacks);
+const zero = 0;
+console.log(zero ?? 5, false ?? true);
+"#,
+    );
+    assert_eq!(
+        output.trim(),
+        "fallback 1 1\nvalue 1 0\nlast 2\ndeep 1\nhi zaco 1\nskipped 1 0\nfallback 1\n0 false"
+    );
+}
+
+#[test]
+fn test_void_call_in_value_position_is_undefined() {
+    let output = compile_and_run(
+        r#"
+function note(msg: string): void { console.log("note", msg); }
+function wrap(early: boolean): void {
+  if (early) return note("early");
+  note("late");
+}
+const x = note("a");
+console.log(x);
+const flag = true;
+const y = flag ? note("b") : note("c");
+console.log(y, "after");
+console.log(note("d"), "tail");
+wrap(true);
+wrap(false);
+"#,
+    );
+    assert_eq!(
+        output,
+        "note a\nundefined\nnote b\nundefined after\nnote d\nundefined tail\nnote early\nnote late\n"
+    );
+}
+
+// ============================================================================
+// Closures
+// ============================================================================
+
+#[test]
+fn test_closure_counter_mutates_outer_var() {
+    let output = compile_and_run(
+        r#"
+let n: number = 0;
+const inc = () => ++n;
+const peek = () => n;
+inc();
+console.log(inc());
+console.log(n);
+n = 10;
+console.log(peek());
+"#,
+    );
+    assert_eq!(output.trim(), "2\n2\n10");
+}
+
+#[test]
+fn test_update_evaluates_operand_once() {
+    let output = compile_and_run(
+        r#"
+class Counter { x: number; constructor(x: number) { this.x = x; } }
+let a: number[] = [10, 20];
+let i = 0;
+a[i++]++;
+console.log(i, a[0], a[1]);
+let boxes: { n: number }[] = [{ n: 1 }, { n: 5 }];
+let j = 0;
+boxes[j++].n++;
+let before = boxes[j++].n--;
+console.log(j, boxes[0].n, boxes[1].n, before);
+let counters: Counter[] = [new Counter(3)];
+let k = 0;
+++counters[k++].x;
+console.log(k, counters[0].x);
+"#,
+    );
+    assert_eq!(output.trim(), "1 11 20\n2 2 4 5\n1 4");
+}
+
+#[test]
+fn test_compound_assignment_to_members_and_elements() {
+    let output = compile_and_run(
+        r#"
+class Counter {
+  x: number;
+  constructor(x: number) { this.x = x; }
+  bump(): void { this.x += 5; }
+}
+let c = new Counter(10);
+c.bump();
+c.x *= 2;
+console.log(c.x);
+let o = { n: 1 };
+o.n += 4;
+console.log(o.n);
+let xs: number[] = [1, 2, 3];
+let k = 0;
+xs[k++] += 5;
+xs[2] = xs[2] + 1;
+xs[5] = 9;
+console.log(k, xs[0], xs[2], xs.length, xs[5]);
+let names: string[] = ["a"];
+names[0] += "b";
+console.log(names[0]);
+"#,
+    );
+    assert_eq!(output.trim(), "30\n5\n1 6 4 6 9\nab");
+}
+
+#[test]
+fn test_exponent_and_unsupported_assignments() {
+    let output = compile_and_run(
+        r#"
+import * as path from "path";
+let p: number = 2;
+p **= 10;
+let xs: number[] = [3];
+xs[0] **= 2;
+console.log(2 ** 3, p, xs[0]);
+console.log(path.join("a", "b"));
+"#,
+    );
+    assert_eq!(output.trim(), "8 1024 9\na/b");
+
+    // `&&=`, `>>>` and `??=` on a property are rejected, not dropped
+    for source in [
+        "let a: number = 1;\na &&= 2;\n",
+        "let a: number = 1;\nconsole.log(a >>> 1);\n",
+        "let o: { n?: number } = {};\no.n ??= 1;\n",
+    ] {
+        let (stdout, stderr) = compile_should_fail(source);
+        let combined = format!("{}{}", stdout, stderr);
+        assert!(combined.contains("is not supported yet"), "{}\n{}", source, combined);
+    }
+}
+
+#[test]
+fn test_returned_closure_outlives_creating_frame() {
+    let output = compile_and_run(
+        r#"
+function make() {
+  const x = 5;
+  return () => x;
+}
+function counter(start: number) {
+  let n: number = start;
+  return () => { n = n + 1; return n; };
+}
+const f = make();
+const c = counter(10);
+c();
+console.log(f());
+console.log(c());
+"#,
+    );
+    assert_eq!(output.trim(), "5\n12");
+}
+
+#[test]
+fn test_returned_closure_is_a_callable_value() {
+    let output = compile_and_run(
+        r#"
+function make() {
+  const x = 5;
+  return () => x;
+}
+function makeAdder(n: number): (x: number) => number {
+  return (x: number) => x + n;
+}
+function apply(f: (x: number) => number, v: number): number {
+  return f(v);
+}
+console.log(make()());
+console.log(makeAdder(10)(1));
+console.log(apply(makeAdder(3), 4));
+const add5 = makeAdder(5);
+console.log(apply(add5, 4));
+"#,
+    );
+    assert_eq!(output.trim(), "5\n11\n7\n9");
+}
+
+#[test]
+fn test_iife() {
+    let output = compile_and_run(
+        r#"
+const x = (() => 42)();
+const y = (function () { return 7; })();
+let base: number = 10;
+console.log(x);
+console.log(y);
+console.log(((k: number) => base + k)(5));
+"#,
+    );
+    assert_eq!(output.trim(), "42\n7\n15");
+}
+
+#[test]
+fn test_async_iife_runs_an_async_main() {
+    let output = compile_and_run(
+        r#"
+async function main(): Promise<number> {
+  console.log("main");
+  return 4;
+}
+(async () => { const v = await main(); console.log("arrow", v); })();
+(async function () { const v = await main(); console.log("function", v); })();
+console.log("after");
+"#,
+    );
+    assert_eq!(output.trim(), "main\narrow 4\nmain\nfunction 4\nafter");
+}
+
+#[test]
+fn test_calling_function_values() {
+    let output = compile_and_run(
+        r#"
+function apply(f: (x: number) => number, v: number): number {
+  return f(v);
+}
+function twice(f: (x: number) => number, v: number): number {
+  return apply(f, apply(f, v));
+}
+function shout(f: (s: string) => string, s: string): string {
+  return f(s) + "!";
+}
+function inc(x: number): number { return x + 1; }
+const base = 10;
+const addBase = (x: number) => x + base;
+console.log(apply(inc, 1), apply(addBase, 1), twice((x) => x * 3, 2));
+console.log(shout((s) => s.toUpperCase(), "hi"));
+const handlers = { add: (a: number, b: number) => a + b, label: (n: number) => "n=" + n };
+console.log(handlers.add(2, 3), handlers.label(7));
+"#,
+    );
+    assert_eq!(output.trim(), "2 11 18\nHI!\n5 n=7");
+}
+
+#[test]
+fn test_recursive_arrow_functions() {
+    let output = compile_and_run(
+        r#"
+const fib = (n: number): number => n < 2 ? n : fib(n - 1) + fib(n - 2);
+const fib2 = (n) => n < 2 ? n : fib2(n - 1) + fib2(n - 2);
+const fib3 = (n: number) => {
+  if (n < 2) return n;
+  return fib3(n - 1) + fib3(n - 2);
+};
+const step = 2;
+const hops = (n: number): number => n <= 0 ? 0 : 1 + hops(n - step);
+console.log(fib(10), fib2(10), fib3(10), hops(10));
+"#,
+    );
+    assert_eq!(output.trim(), "55 55 55 5");
+}
+
+#[test]
+fn test_arrow_return_type_covers_every_return() {
+    let output = compile_and_run(
+        r#"
+const double = (x: number) => { if (x < 0) return 0; return x * 2; };
+const label = (s: string) => { if (s.length == 0) return null; return s + "!"; };
+console.log(double(-1), double(4), label("hi"));
+"#,
+    );
+    assert_eq!(output.trim(), "0 8 hi!");
+
+    // An expression body lowers exactly like the block that returns it
+    assert_eq!(
+        compile_to_ir("const d = (x: number) => x * 2;\nconsole.log(d(2));\n"),
+        compile_to_ir("const d = (x: number) => { return x * 2; };\nconsole.log(d(2));\n")
+    );
+
+    let (stdout, stderr) =
+        compile_should_fail("const pick = (x: number) => { if (x < 0) return \"neg\"; return x; };\nconsole.log(pick(1));\n");
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("returning both str and f64"), "{}", combined);
+}
+
+#[test]
+fn test_arguments_object() {
+    let output = compile_and_run(
+        r#"
+function count(a: number): number {
+  return arguments.length;
+}
+function sum(): number {
+  let total: number = 0;
+  for (let i: number = 0; i < arguments.length; i = i + 1) {
+    total = total + arguments[i];
+  }
+  return total;
+}
+console.log(count());
+console.log(count(1, 2, 3));
+console.log(sum(1, 2, 3, 4));
+"#,
+    );
+    assert_eq!(output.trim(), "0\n3\n10");
+}
+
+// ============================================================================
+// Generators
+// ============================================================================
+
+#[test]
+fn test_generator_next_passes_value_into_yield() {
+    let output = compile_and_run(
+        r#"
+function* conversation(start: number): Generator<number, string, number> {
+  const x = yield start;
+  console.log("got " + x);
+  const y = yield x * 2;
+  console.log("got " + y);
+  return "bye";
+}
+const g = conversation(1);
+console.log(g.next(99).value);
+console.log(g.next(10).value);
+const last = g.next(5);
+console.log(last.done);
+console.log(g.next().done);
+
+function* names() {
+  yield "ann";
+  yield "bob";
+}
+const n = names();
+console.log(n.next().value);
+console.log(n.next().value);
+console.log(n.next().done);
+"#,
+    );
+    assert_eq!(output.trim(), "1\ngot 10\n20\ngot 5\ntrue\ntrue\nann\nbob\ntrue");
+}
+
+#[test]
+fn test_generator_throw_reaches_caller() {
+    let output = compile_and_run(
+        r#"
+function* g() {
+  yield 1;
+  throw "boom";
+}
+const it = g();
+try {
+  console.log(it.next().value);
+  console.log(it.next().value);
+  console.log("not reached");
+} catch (e) {
+  console.log("caught", e);
+}
+console.log(it.next().done);
+"#,
+    );
+    assert_eq!(output.trim(), "1\ncaught boom\ntrue");
+}
+
+#[test]
+fn test_generator_keeps_its_own_try_stack() {
+    // The body's try stays open across the yield while the caller throws
+    let output = compile_and_run(
+        r#"
+function* g() {
+  try {
+    yield 1;
+    throw "inner";
+  } catch (e) {
+    console.log("body caught", e);
+  }
+  yield 2;
+}
+const it = g();
+try {
+  console.log(it.next().value);
+  throw "outer";
+} catch (e) {
+  console.log("caller caught", e);
+}
+console.log(it.next().value);
+console.log(it.next().done);
+"#,
+    );
+    assert_eq!(output.trim(), "1\ncaller caught outer\nbody caught inner\n2\ntrue");
+}
+
+#[test]
+fn test_async_generator_for_await() {
+    let output = compile_and_run(
+        r#"
+async function double(n: number): Promise<number> {
+  return n * 2;
+}
+async function* doubled(limit: number): AsyncGenerator<number> {
+  for (let i = 1; i <= limit; i++) {
+    yield await double(i);
+  }
+}
+async function* words() {
+  yield "alpha";
+  yield "beta";
+  yield "gamma";
+}
+async function run() {
+  for await (const n of doubled(3)) {
+    console.log(n);
+  }
+  const w = words();
+  const first = await w.next();
+  console.log(first.value, first.done);
+  for await (const rest of w) {
+    console.log(rest);
+  }
+  const end = await w.next();
+  console.log(end.done);
+}
+run();
+"#,
+    );
+    assert_eq!(output.trim(), "2\n4\n6\nalpha false\nbeta\ngamma\ntrue");
+}
+
+#[test]
+fn test_for_await_over_promises_and_async_iterables() {
+    let output = compile_and_run(
+        r#"
+async function delayed(n: number): Promise<number> {
+  return n;
+}
+class Chunks {
+  chunks: string[];
+  i: number = 0;
+  constructor(chunks: string[]) { this.chunks = chunks; }
+  async next(): Promise<IteratorResult<string>> {
+    if (this.i >= this.chunks.length) return { value: "", done: true };
+    const chunk = this.chunks[this.i];
+    this.i = this.i + 1;
+    return { value: chunk, done: false };
+  }
+}
+class Stream {
+  chunks: string[];
+  constructor(chunks: string[]) { this.chunks = chunks; }
+  [Symbol.asyncIterator](): Chunks { return new Chunks(this.chunks); }
+}
+function stream(): Stream {
+  return new Stream(["alpha", "beta", "gamma"]);
+}
+async function run(): Promise<void> {
+  let total: number = 0;
+  for await (const n of [delayed(1), delayed(2), delayed(3)]) {
+    total = total + n;
+  }
+  console.log(total);
+  for await (const chunk of stream()) {
+    console.log(chunk.toUpperCase());
+  }
+  for await (const x of [4, 5]) {
+    console.log(x);
+  }
+}
+run();
+"#,
+    );
+    assert_eq!(output.trim(), "6\nALPHA\nBETA\nGAMMA\n4\n5");
+}
+
+// ============================================================================
+// Exceptions
+// ============================================================================
+
+#[test]
+fn test_throw_in_callee_is_caught() {
+    let output = compile_and_run(
+        r#"
+function fail(): void {
+  throw "boom";
+}
+function outer(): void {
+  fail();
+  console.log("unreachable");
+}
+try {
+  outer();
+  console.log("unreachable");
+} catch (e) {
+  console.log(e);
+}
+console.log("after");
+"#,
+    );
+    assert_eq!(output.trim(), "boom\nafter");
+}
+
+// ============================================================================
+// assert and zaco test
+// ============================================================================
+
+#[test]
+fn test_assert_failures_are_catchable() {
+    let output = compile_and_run(
+        r#"
+import assert from "assert";
+assert(1 + 1 == 2);
+assert.deepEqual({ name: "ada", tags: ["x"] }, { name: "ada", tags: ["x"] });
+assert.throws(() => { throw "bad"; });
+try {
+  assert.equal(1, 2);
+} catch (e) {
+  console.log(e);
+}
+try {
+  assert.deepEqual([1, 2], [1, 3], "arrays differ");
+} catch (e) {
+  console.log(e);
+}
+"#,
+    );
+    assert_eq!(output.trim(), "AssertionError: expected 1 to equal 2\nAssertionError: arrays differ");
+}
+
+#[test]
+fn test_util_is_deep_strict_equal() {
+    let output = compile_and_run(
+        r#"
+import { isDeepStrictEqual } from "util";
+const nan = 0 / 0;
+console.log(isDeepStrictEqual(nan, nan));
+console.log(isDeepStrictEqual(0, -0));
+console.log(isDeepStrictEqual([1, nan], [1, nan]));
+console.log(isDeepStrictEqual({ a: 1, b: "x" }, { b: "x", a: 1 }));
+console.log(isDeepStrictEqual({ xs: [1, 2] }, { xs: [1, 2, 3] }));
+"#,
+    );
+    assert_eq!(output.trim(), "true\nfalse\ntrue\ntrue\nfalse");
+}
+
+#[test]
+fn test_failed_test_case_sets_exit_code() {
+    let run_output = compile_and_run_output(
+        r#"
+import { equal } from "assert";
+test("adds", () => {
+  equal(1 + 2, 3);
+});
+test("concatenates", () => {
+  equal("a" + "b", "ba");
+});
+"#,
+        &[],
+    );
+    let stdout = String::from_utf8_lossy(&run_output.stdout);
+    assert!(stdout.contains("ok   adds"), "stdout: {}", stdout);
+    assert!(stdout.contains("FAIL concatenates"), "stdout: {}", stdout);
+    assert!(stdout.contains("AssertionError: expected \"ab\" to equal \"ba\""), "stdout: {}", stdout);
+    assert!(stdout.contains("1 passed, 1 failed"), "stdout: {}", stdout);
+    assert_eq!(run_output.status.code(), Some(1));
+}
+
+#[test]
+fn test_zaco_test_runs_test_files() {
+    let dir = std::env::temp_dir().join(format!("zaco_test_runner_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join("math.test.ts"), "import assert from \"assert\";\ntest(\"sum\", () => { assert.equal(2 + 2, 4); });\n").unwrap();
+    fs::write(dir.join("nested/fail.test.ts"), "import assert from \"assert\";\nassert.equal(1, 2);\n").unwrap();
+    fs::write(dir.join("helper.ts"), "console.log(\"not a test\");\n").unwrap();
+
+    let output = Command::new(zaco_binary())
+        .arg("test")
+        .arg(&dir)
+        .current_dir(PathBuf::from(env!("CARGO_MANIFEST_DIR")).parent().unwrap().parent().unwrap())
+        .output()
+        .expect("Failed to run zaco test");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("PASS") && stdout.contains("math.test.ts"), "stdout: {}", stdout);
+    assert!(stdout.contains("FAIL") && stdout.contains("fail.test.ts"), "stdout: {}", stdout);
+    assert!(stdout.contains("Test files: 1 passed, 1 failed, 2 total"), "stdout: {}", stdout);
+    assert!(!stdout.contains("helper.ts"), "stdout: {}", stdout);
+    assert!(!output.status.success());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ============================================================================
+// REPL
+// ============================================================================
+
+#[test]
+fn test_repl_keeps_bindings_between_inputs() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(zaco_binary())
+        .arg("repl")
+        .current_dir(PathBuf::from(env!("CARGO_MANIFEST_DIR")).parent().unwrap().parent().unwrap())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run zaco repl");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"let x = 2 + 3\nx * 2\nfunction twice(n: number): number {\n  return n * 2;\n}\nconsole.log(\"hi\")\nlet y: number = \"a\"\ntwice(x) + 1\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("10\nhi\n"), "stdout: {}", stdout);
+    // The rejected input reports its error and the session goes on without it
+    assert!(stdout.contains("type mismatch"), "stdout: {}", stdout);
+    assert!(stdout.ends_with("\n11\n"), "stdout: {}", stdout);
+    assert!(output.status.success());
+}
+
+// ============================================================================
+// SQLite
+// ============================================================================
+
+#[test]
+fn test_sqlite_example() {
+    let source = fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples/sqlite.ts"),
+    )
+    .expect("Failed to read examples/sqlite.ts");
+    assert_eq!(
+        compile_and_run(&source).trim(),
+        "3\n2 user1 1.5\n3 user2 3\n4 user3 4.5\nSqliteError: no such table: missing"
+    );
+}
+
+// ============================================================================
+// fetch
+// ============================================================================
+
+/// Serve `body` as JSON to a single request on a localhost port; returns the
+/// base URL and a handle yielding the raw request.
+fn serve_json_once(body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8_lossy(&request).into_owned()
+    });
+    (url, handle)
+}
+
+#[test]
+fn test_fetch_json_from_local_server() {
+    let (url, server) = serve_json_once(r#"{"id": 7, "title": "write tests", "tags": ["a", "b"]}"#);
+    let source = format!(
+        r#"
+interface Todo {{
+  id: number;
+  title: string;
+}}
+const res = await fetch("{}/todos/7", {{ headers: {{ "X-Token": "abc" }} }});
+console.log(res.status);
+console.log(res.ok);
+console.log(res.headers.get("content-type"));
+const todo: Todo = await res.json();
+console.log(todo.title);
+console.log(todo.id + 1);
+"#,
+        url
+    );
+    let output = compile_and_run(&source);
+    assert_eq!(output.trim(), "200\ntrue\napplication/json\nwrite tests\n8");
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("GET /todos/7 HTTP/1.1"), "got {}", request);
+    assert!(request.to_lowercase().contains("x-token: abc"), "got {}", request);
+}
+
+#[test]
+fn test_fetch_aborted_by_timeout_signal_is_caught() {
+    // Accept connections but never answer
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let source = format!(
+        r#"
+import {{ sleep }} from "timers";
+
+const signal = AbortSignal.timeout(100);
+try {{
+  const res = await fetch("{}/slow", {{ signal: signal }});
+  console.log(res.status);
+}} catch (e) {{
+  console.log(e);
+}}
+console.log(signal.aborted);
+
+const controller = new AbortController();
+controller.abort();
+try {{
+  await sleep(60000, {{ signal: controller.signal }});
+}} catch (e) {{
+  console.log(e);
+}}
+"#,
+        url
+    );
+    let output = compile_and_run(&source);
+    assert_eq!(
+        output.trim(),
+        "AbortError: This operation was aborted\ntrue\nAbortError: This operation was aborted"
+    );
+    drop(listener);
+}
+
+#[test]
+fn test_rejected_promise_caught_by_try_catch() {
+    let output = compile_and_run(
+        r#"
+async function fail(): Promise<number> {
+  throw "boom";
+}
+async function run() {
+  try {
+    await fail();
+    console.log("unreachable");
+  } catch (e) {
+    console.log(e);
+  }
+}
+run();
+"#,
+    );
+    assert_eq!(output.trim(), "boom");
+}
+
+#[test]
+fn test_rejected_promise_caught_by_catch_callback() {
+    let output = compile_and_run(
+        r#"
+async function fail(): Promise<number> {
+  throw "boom";
+}
+async function answer(): Promise<number> {
+  return 21;
+}
+fail().catch((e) => console.log("caught " + e)).finally(() => console.log("settled"));
+const doubled = await answer().then((n) => n * 2);
+console.log(doubled);
+"#,
+    );
+    assert_eq!(output.trim(), "caught boom\nsettled\n42");
+}
+
+#[test]
+fn test_unhandled_rejection_sets_exit_code() {
+    let run_output = compile_and_run_output(
+        r#"
+async function fail(): Promise<number> {
+  throw "lost";
+}
+fail();
+console.log("still running");
+"#,
+        &[],
+    );
+    assert_eq!(String::from_utf8_lossy(&run_output.stdout).trim(), "still running");
+    let stderr = String::from_utf8_lossy(&run_output.stderr);
+    assert!(stderr.contains("Uncaught (in promise): lost"), "stderr: {}", stderr);
+    assert_eq!(run_output.status.code(), Some(1));
+}
+
+// ============================================================================
+// Worker Threads
+// ============================================================================
+
+#[test]
+fn test_worker_spawn_parallel_sum() {
+    let source = fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples/workers.ts"),
+    )
+    .expect("Failed to read examples/workers.ts");
+    assert_eq!(compile_and_run(&source).trim(), "499999500000");
+}
+
+#[test]
+fn test_worker_spawn_clones_strings_and_arrays() {
+    let output = compile_and_run(
+        r#"
+import { spawn } from "worker";
+
+export function shout(word: string, times: number, loud: boolean): string {
+  let acc: string = "";
+  for (let i: number = 0; i < times; i++) {
+    acc = acc + word;
+  }
+  if (loud) {
+    return acc.toUpperCase();
+  }
+  return acc;
+}
+
+export function total(xs: number[]): number {
+  let t: number = 0;
+  for (let i: number = 0; i < xs.length; i++) {
+    t += xs[i];
+  }
+  return t;
+}
+
+console.log(await spawn("shout", ["ab", 3, true]));
+const nums = [1, 2, 3, 4.5];
+console.log(await spawn("total", [nums]));
+"#,
+    );
+    assert_eq!(output.trim(), "ABABAB\n10.5");
+}
+
+// ============================================================================
+// Glob
+// ============================================================================
+
+#[test]
+fn test_glob_sync_matches_fixture_tree() {
+    let root = std::env::temp_dir().join("zaco_test_glob_tree");
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["src/lib", ".hidden", "docs"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["src/a.ts", "src/b.js", "src/lib/c.ts", "src/lib/d.md", ".hidden/e.ts", "docs/x.md", "README.md"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    // A symlink back to the root must not be walked forever
+    std::os::unix::fs::symlink(&root, root.join("src/lib/loop")).unwrap();
+
+    let source = format!(
+        r#"
+import {{ globSync }} from "glob";
+
+function show(files: string[]) {{
+  let line: string = "";
+  for (let i = 0; i < files.length; i++) {{
+    line = line + (i > 0 ? "," : "") + files[i];
+  }}
+  console.log(line);
+}}
+
+const cwd = "{}";
+show(globSync("**/*.ts", {{ cwd: cwd }}));
+show(globSync("**/*.ts", {{ cwd: cwd, dot: true }}));
+show(globSync("src/*.{{ts,js}}", {{ cwd: cwd }}));
+show(globSync("src/[!a].*", {{ cwd: cwd }}));
+show(globSync("!**/*.{{ts,js,md}}", {{ cwd: cwd }}));
+"#,
+        root.display()
+    );
+    let output = compile_and_run(&source);
+    assert_eq!(
+        output.trim(),
+        "src/a.ts,src/lib/c.ts\n\
+         .hidden/e.ts,src/a.ts,src/lib/c.ts\n\
+         src/a.ts,src/b.js\n\
+         src/b.js\n\
+         docs,src,src/lib,src/lib/loop"
+    );
+
+    // fs exports the same function
+    let source = format!(
+        r#"
+import {{ globSync }} from "fs";
+console.log(globSync("*/?.md", {{ cwd: "{}" }})[0]);
+"#,
+        root.display()
+    );
+    assert_eq!(compile_and_run(&source).trim(), "docs/x.md");
+    let _ = fs::remove_dir_all(&root);
+}
+
+// ============================================================================
+// Return Code
+// ============================================================================
+
+#[test]
+fn test_exit_code_zero() {
+    let temp_dir = std::env::temp_dir().join("zaco_test_exit_code");
+    let _ = fs::create_dir_all(&temp_dir);
+    let input_path = temp_dir.join("test_exit.ts");
+    let output_path = temp_dir.join("test_exit");
+
+    fs::write(&input_path, r#"console.log("ok");"#).unwrap();
+
+    let zaco = zaco_binary();
+    Command::new(&zaco)
+        .arg("compile")
+        .arg(&input_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--emit")
+        .arg("exe")
+        .current_dir(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .parent()
+                .unwrap()
+                .parent()
+                .unwrap(),
+        )
+        .output()
+        .expect("compile failed");
+
+    let status = Command::new(&output_path).status().expect("run failed");
+    assert!(status.success(), "Expected exit code 0");
+
+    let _ = fs::remove_file(&input_path);
+    let _ = fs::remove_file(&output_path);
+}
+
+#[test]
+fn test_process_exit_runs_exit_handlers() {
+    let output = compile_and_run_output(
+        r#"
+process.on("exit", () => {
+  console.log("exit handler");
+});
+console.log(process.stdout.isTTY);
+console.log(process.stdout.columns > 0);
+process.exit(3);
+console.log("unreachable");
+"#,
+        &[],
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "false\ntrue\nexit handler");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+// ============================================================================
+// Process Signals
+// ============================================================================
+
+#[test]
+fn test_sigint_listener_runs_before_exit() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let temp_dir = std::env::temp_dir().join("zaco_test_sigint");
+    let _ = fs::create_dir_all(&temp_dir);
+    let input_path = temp_dir.join("test_sigint.ts");
+    let output_path = temp_dir.join("test_sigint");
+
+    fs::write(
+        &input_path,
+        r#"
+import { sleep } from "timers";
+
+let name = "worker";
+process.on("exit", () => {
+  console.log("exit handler");
+});
+process.on("SIGINT", () => {
+  console.log("caught SIGINT in " + name);
+  process.exit(0);
+});
+console.error("ready");
+await sleep(10000);
+console.log("unreachable");
+"#,
+    )
+    .unwrap();
+
+    let zaco = zaco_binary();
+    let compile_output = Command::new(&zaco)
+        .arg("compile")
+        .arg(&input_path)
+        .arg("-o")
+        .arg(&output_path)
+        .arg("--emit")
+        .arg("exe")
+        .current_dir(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .parent()
+                .unwrap()
+                .parent()
+                .unwrap(),
+        )
+        .output()
+        .expect("compile failed");
+    assert!(compile_output.status.success(), "{}", String::from_utf8_lossy(&compile_output.stderr));
+
+    let mut child = Command::new(&output_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("run failed");
+
+    // stderr is unbuffered: once "ready" arrives the listeners are registered
+    let mut ready = String::new();
+    BufReader::new(child.stderr.take().unwrap()).read_line(&mut ready).unwrap();
+    assert_eq!(ready.trim(), "ready");
+
+    let kill = Command::new("kill")
+        .arg("-INT")
+        .arg(child.id().to_string())
+        .status()
+        .expect("kill failed");
+    assert!(kill.success());
+
+    let output = child.wait_with_output().expect("wait failed");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "caught SIGINT in worker\nexit handler");
+    assert_eq!(output.status.code(), Some(0));
+
+    let _ = fs::remove_file(&input_path);
+    let _ = fs::remove_file(&output_path);
+}
+
+// ============================================================================
+// Switch Statement
+// ============================================================================
+
+#[test]
+fn test_switch_basic_match() {
+    let output = compile_and_run(
+        r#"
+let x: number = 2;
+switch (x) {
+    case 1:
+        console.log("one");
+        break;
+    case 2:
+        console.log("two");
+        break;
+    case 3:
+        console.log("three");
+        break;
+}
+"#,
+    );
+    assert_eq!(output.trim(), "two");
+}
+
+#[test]
+fn test_switch_default_case() {
+    let output = compile_and_run(
+        r#"
+let x: number = 99;
+switch (x) {
+    case 1:
+        console.log("one");
+        break;
+    default:
+        console.log("other");
+}
+"#,
+    );
+    assert_eq!(output.trim(), "other");
+}
+
+#[test]
+fn test_switch_no_match_no_default() {
+    let output = compile_and_run(
+        r#"
+let x: number = 5;
+switch (x) {
+    case 1:
+        console.log("one");
+        break;
+    case 2:
+        console.log("two");
+        break;
+}
+console.log("done");
+"#,
+    );
+    assert_eq!(output.trim(), "done");
+}
+
+// ============================================================================
+// Switch IR Emission
+// ============================================================================
+
+#[test]
+fn test_switch_ir_emission() {
+    let ir = compile_to_ir(
+        r#"
+let x: number = 1;
+switch (x) {
+    case 1:
+        console.log("one");
+        break;
+    default:
+        console.log("other");
+}
+"#,
+    );
+    // Switch should generate Branch instructions for case matching
+    assert!(ir.contains("Branch"), "Switch IR should contain Branch terminators");
+}
+
+// ============================================================================
+// Diagnostics
+// ============================================================================
+
+#[test]
+fn test_max_errors_caps_reported_diagnostics() {
+    let dir = std::env::temp_dir().join("zaco_test_max_errors");
+    let _ = fs::create_dir_all(&dir);
+    let check = |source: &str, extra: &[&str]| {
+        let input = dir.join("broken.ts");
+        fs::write(&input, source).unwrap();
+        let output = Command::new(zaco_binary())
+            .arg("check")
+            .arg(&input)
+            .args(extra)
+            .output()
+            .expect("Failed to run zaco check");
+        assert!(!output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    // The same error on every line is shown once
+    let mut repeated = String::from("let x: number = 0;\n");
+    for _ in 0..500 {
+        repeated.push_str("x = \"a\";\n");
+    }
+    let grouped = check(&repeated, &[]);
+    assert_eq!(grouped.matches("Type error").count(), 1);
+    assert!(grouped.contains("previous error repeated 499 times"), "{}", grouped);
+
+    // Distinct errors stop at 50 unless --max-errors says otherwise
+    let mut distinct = String::from("let x: number = 0;\n");
+    for i in 0..60 {
+        distinct.push_str(&format!("x = \"a{}\";\n", i));
+    }
+    let default = check(&distinct, &[]);
+    assert_eq!(default.matches("Type error").count(), 50);
+    assert!(default.contains("10 more errors not shown (--max-errors 50)"), "{}", default);
+
+    let capped = check(&distinct, &["--max-errors", "5"]);
+    assert_eq!(capped.matches("Type error").count(), 5);
+    assert!(capped.contains("55 more errors not shown (--max-errors 5)"), "{}", capped);
+
+    let all = check(&distinct, &["--max-errors", "0"]);
+    assert_eq!(all.matches("Type error").count(), 60);
+    assert!(!all.contains("not shown"));
+}
+
+#[test]
+fn test_check_directory_reports_each_bad_file() {
+    let dir = std::env::temp_dir().join(format!("zaco_test_check_project_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::create_dir_all(dir.join("generated")).unwrap();
+    fs::write(dir.join("src/ok.ts"), "let a: number = 1;\n").unwrap();
+    fs::write(dir.join("src/bad.ts"), "let b: number = \"x\";\nlet c: string = 2;\n").unwrap();
+    // Ignored files aren't checked
+    fs::write(dir.join("generated/out.ts"), "let d: number = \"y\";\n").unwrap();
+    fs::write(dir.join(".gitignore"), "generated/\n").unwrap();
+
+    let output = Command::new(zaco_binary()).arg("check").arg(&dir).output().expect("Failed to run zaco check");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("bad.ts") && !stdout.contains("out.ts"), "{}", stdout);
+    assert_eq!(stdout.matches("Type error").count(), 2, "{}", stdout);
+    assert!(stdout.contains("Found 2 errors in 1 of 2 files"), "{}", stdout);
+
+    fs::remove_file(dir.join("src/bad.ts")).unwrap();
+    let output = Command::new(zaco_binary()).arg("check").arg(&dir).output().expect("Failed to run zaco check");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Type check passed! (1 file)"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_check_reads_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let run = |command: &str, source: &str| {
+        let mut child = Command::new(zaco_binary())
+            .args([command, "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run zaco");
+        child.stdin.take().unwrap().write_all(source.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    let output = run("check", "let x: number = \"s\";\n");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Type error") && stdout.contains("<stdin>"), "{}", stdout);
+
+    assert!(run("check", "let x: number = 1;\n").status.success());
+    let parsed = run("parse", "let x = (1 + ;\n");
+    assert!(String::from_utf8_lossy(&parsed.stdout).contains("Parse error"));
+    let lexed = run("lex", "let x = 1;\n");
+    assert!(String::from_utf8_lossy(&lexed.stdout).contains("Tokens for <stdin>"));
+}
+
+#[test]
+fn test_type_errors_have_a_code_per_kind() {
+    let dir = std::env::temp_dir().join("zaco_test_type_error_codes");
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("main.ts");
+    fs::write(&input, "let count: number = \"three\";\nlet total = 1;\nconsole.log(totl);\n").unwrap();
+
+    let output = Command::new(zaco_binary())
+        .args(["check", input.to_str().unwrap()])
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run zaco");
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mismatch = stdout.find("[E2001]").expect(&stdout);
+    let undefined = stdout.find("[E2002]").expect(&stdout);
+    assert!(mismatch < undefined, "{}", stdout);
+
+    let explained = Command::new(zaco_binary()).args(["--explain", "E2002"]).output().expect("Failed to run zaco");
+    assert!(String::from_utf8_lossy(&explained.stdout).contains("not declared"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_unsupported_constructs_are_errors() {
+    let source = r#"let i = 0;
+do {
+    i++;
+} while (i < 3);
+console.log("after", i);
+"#;
+    let (stdout, stderr) = compile_should_fail(source);
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("a do...while loop is not supported yet"), "{}", combined);
+
+    // Opting out leaves the loop out of the program
+    assert_eq!(compile_and_run_with_args(source, &["--allow-unsupported"]).trim(), "after 0");
+}
+
+#[test]
+fn test_expect_error_suppresses_the_next_line() {
+    let output = compile_and_run(
+        r#"function double(n: number): number {
+    return n * 2;
+}
+// @zaco-expect-error
+let bad: number = "four";
+console.log(double(4));
+"#,
+    );
+    assert_eq!(output.trim(), "8");
+
+    let (stdout, stderr) = compile_should_fail("// @zaco-expect-error\nlet fine: number = 4;\nconsole.log(fine);\n");
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(combined.contains("unused '@zaco-expect-error' directive"), "{}", combined);
+}
+
+#[test]
+fn test_explain_prints_help_for_a_code() {
+    let output = Command::new(zaco_binary()).args(["--explain", "E2000"]).output().expect("Failed to run zaco");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("does not type check") && stdout.contains("example"), "{}", stdout);
+
+    let unknown = Command::new(zaco_binary()).args(["--explain", "E9999"]).output().expect("Failed to run zaco");
+    assert!(!unknown.status.success());
+    assert!(unknown.stdout.is_empty());
+}
+
+// ============================================================================
+// Module Resolution Failures
+// ============================================================================
+
+/// Helper: compile source and expect it to fail. Returns (stdout, stderr).
+fn compile_should_fail(source: &str) -> (String, String) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static FAIL_COUNTER: AtomicUsize = AtomicUsize::new(2000);
+    let id = FAIL_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let temp_dir = std::env::temp_dir().join(format!("zaco_test_{}", id));
+    let _ = fs::create_dir_all(&temp_dir);
+
+    let input_path = temp_dir.join("test_input.ts");
+    fs::write(&input_path, source).expect("Failed to write test input");
+
+    let zaco = zaco_binary();
+    let output = Command::new(&zaco)
+        .arg("compile")
+        .arg(&input_path)
+        .arg("--emit")
+        .arg("ir")
+        .current_dir(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .parent()
+                .unwrap()
+                .parent()
+                .unwrap(),
+        )
+        .output()
+        .expect("Failed to run zaco compiler");
+
+    let _ = fs::remove_file(&input_path);
+
+    assert!(
+        !output.status.success(),
+        "Expected compilation to fail but it succeeded"
+    );
+
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn test_unresolved_import_fails_compilation() {
+    let (stdout, stderr) = compile_should_fail(
+        r#"import { foo } from "definitely-missing-package";
+console.log("ok");
+"#,
+    );
+    // Error should mention the missing package (may be in stdout or stderr)
+    let combined = format!("{}{}", stdout, stderr);
+    assert!(
+        combined.contains("not found") || combined.contains("Cannot resolve"),
+        "Error message should mention the missing package, got stdout={}, stderr={}",
+        stdout, stderr
     );
 }
 
@@ -475,3 +3618,586 @@ console.log("ok");
     );
     assert!(ir.contains("fn main("), "Built-in import should compile to IR");
 }
+
+#[test]
+fn test_top_level_await_settles_before_importers_run() {
+    let dir = std::env::temp_dir().join("zaco_test_top_level_await");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("config.ts"),
+        "import { sleep } from \"timers\";\nconsole.log(\"config loading\");\nawait sleep(20);\n\
+         console.log(\"config loaded\");\nexport function ready(): boolean { return true; }\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("main.ts"),
+        "import { sleep } from \"timers\";\nimport { ready } from \"./config\";\nconsole.log(\"main\");\n\
+         await sleep(10);\nconsole.log(\"done\");\n",
+    )
+    .unwrap();
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let compile = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("main.ts"))
+        .arg("-o")
+        .arg(dir.join("main"))
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(compile.status.success(), "{}", String::from_utf8_lossy(&compile.stderr));
+    let run = Command::new(dir.join("main")).output().expect("Failed to run compiled executable");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "config loading\nconfig loaded\nmain\ndone\n");
+
+    // An awaiting module in an import cycle would wait on itself
+    fs::write(dir.join("config.ts"), "import { sleep } from \"timers\";\nimport \"./main\";\nawait sleep(20);\n")
+        .unwrap();
+    let compile = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("main.ts"))
+        .args(["--emit", "ir"])
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!compile.status.success());
+    let stderr = String::from_utf8_lossy(&compile.stderr);
+    assert!(stderr.contains("config.ts uses top-level await, which would deadlock on the cycle"), "{}", stderr);
+}
+
+#[test]
+fn test_local_module_imports_are_called() {
+    // main.ts calls add and multiply and reads PI from ./math
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let output_path = std::env::temp_dir().join("zaco_test_modules_main");
+    let compile = Command::new(zaco_binary())
+        .arg("compile")
+        .arg("examples/modules/main.ts")
+        .arg("-o")
+        .arg(&output_path)
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(compile.status.success(), "{}", String::from_utf8_lossy(&compile.stderr));
+    let run = Command::new(&output_path).output().expect("Failed to run compiled executable");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "Sum:\n15\nProduct:\n50\nPI:\n3.14159\n");
+
+    // An import the lowerer has no value for is rejected, not dropped
+    let dir = std::env::temp_dir().join("zaco_test_local_import_value");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("data.ts"), "export const items = [1, 2, 3];\n").unwrap();
+    fs::write(dir.join("main.ts"), "import { items } from \"./data\";\nconsole.log(items);\n").unwrap();
+    let compile = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("main.ts"))
+        .args(["--emit", "ir"])
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!compile.status.success());
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&compile.stdout),
+        String::from_utf8_lossy(&compile.stderr)
+    );
+    assert!(combined.contains("using `items` imported from \"./data\" as a value is not supported yet"), "{}", combined);
+}
+
+#[test]
+fn test_type_only_imports_do_not_form_cycles() {
+    // type_cycle_format.ts imports a type back from the entry module
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let output_path = std::env::temp_dir().join("zaco_test_type_cycle");
+    let compile = Command::new(zaco_binary())
+        .arg("compile")
+        .arg("examples/modules/type_cycle_main.ts")
+        .arg("-o")
+        .arg(&output_path)
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(compile.status.success(), "{}", String::from_utf8_lossy(&compile.stderr));
+    let run = Command::new(&output_path).output().expect("Failed to run compiled executable");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "zaco v1.2\n");
+
+    // Type-only names are checked against the module's exported types
+    let dir = std::env::temp_dir().join("zaco_test_type_only_missing");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("types.ts"), "export interface Config { name: string; }\n").unwrap();
+    fs::write(
+        dir.join("main.ts"),
+        "import type { Config, Settings } from \"./types\";\nconsole.log(\"ok\");\n",
+    )
+    .unwrap();
+    let compile = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(dir.join("main.ts"))
+        .args(["--emit", "ir"])
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(!compile.status.success());
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&compile.stdout),
+        String::from_utf8_lossy(&compile.stderr)
+    );
+    assert!(
+        combined.contains("Module './types' has no exported type 'Settings'"),
+        "got {}",
+        combined
+    );
+}
+
+#[test]
+fn test_type_only_exports_through_reexport_chains() {
+    // options.ts exports types both ways; barrel.ts re-exports them with a
+    // mix of type-only and value clauses
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let dir = std::env::temp_dir().join("zaco_test_type_only_exports");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("options.ts"),
+        "interface Options { verbose: boolean; }\ntype Level = number;\nconst greeting: string = \"hi\";\n\
+         export type { Options };\nexport { type Level, greeting };\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("barrel.ts"),
+        "export type { Options } from \"./options\";\nexport * from \"./options\";\n",
+    )
+    .unwrap();
+    let compile = |source: &str| {
+        fs::write(dir.join("main.ts"), source).unwrap();
+        let output = Command::new(zaco_binary())
+            .arg("compile")
+            .arg(dir.join("main.ts"))
+            .arg("-o")
+            .arg(dir.join("main"))
+            .current_dir(&root)
+            .output()
+            .expect("Failed to run zaco compiler");
+        let combined = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        (output.status.success(), combined)
+    };
+
+    // Types resolve through the chain, whichever way they're imported
+    let (ok, combined) = compile(
+        "import type { Options, Level } from \"./barrel\";\nimport { Options as Opts } from \"./barrel\";\n\
+         const o: Options = { verbose: true };\nconst p: Opts = { verbose: false };\nconst l: Level = 2;\n\
+         console.log(o.verbose, p.verbose, l);\n",
+    );
+    assert!(ok, "{}", combined);
+    let run = Command::new(dir.join("main")).output().expect("Failed to run compiled executable");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "true false 2\n");
+
+    // A type-only import of a name the chain doesn't export as a type
+    let (ok, combined) = compile("import type { Options, Verbosity } from \"./barrel\";\nconsole.log(\"ok\");\n");
+    assert!(!ok);
+    assert!(combined.contains("Module './barrel' has no exported type 'Verbosity'"), "got {}", combined);
+
+    // Using a type-only export as a value is reported at the import
+    let (ok, combined) = compile("import { Level, greeting } from \"./barrel\";\nconsole.log(Level);\n");
+    assert!(!ok);
+    assert!(
+        combined.contains("'Level' is exported from './barrel' as a type only and cannot be used as a value"),
+        "got {}",
+        combined
+    );
+    assert!(combined.contains("main.ts:1:10"), "got {}", combined);
+}
+
+#[test]
+fn test_declare_global_reaches_other_modules() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let dir = std::env::temp_dir().join("zaco_test_declare_global");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("globals.ts"),
+        "declare global {\n  const MY_FLAG: boolean;\n  var buildNumber: number;\n  interface AppInfo { name: string; }\n}\nexport {};\n",
+    )
+    .unwrap();
+    let compile = |source: &str| {
+        fs::write(dir.join("main.ts"), source).unwrap();
+        let output = Command::new(zaco_binary())
+            .arg("compile")
+            .arg(dir.join("main.ts"))
+            .arg("-o")
+            .arg(dir.join("main"))
+            .current_dir(&root)
+            .output()
+            .expect("Failed to run zaco compiler");
+        let combined = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        (output.status.success(), combined)
+    };
+
+    let uses_globals = "const info: AppInfo = { name: \"zaco\" };\nconsole.log(info.name);\n\
+                        if (false) {\n  const flag: boolean = MY_FLAG;\n  console.log(buildNumber);\n}\n";
+    let (ok, combined) = compile(&format!("import \"./globals\";\n{}", uses_globals));
+    assert!(ok, "{}", combined);
+    let run = Command::new(dir.join("main")).output().expect("Failed to run compiled executable");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "zaco\n");
+
+    // Without the module declaring them, the globals don't exist
+    let (ok, combined) = compile(uses_globals);
+    assert!(!ok);
+    assert!(combined.contains("AppInfo") || combined.contains("MY_FLAG"), "got {}", combined);
+}
+
+// ============================================================================
+// Dependency Graph
+// ============================================================================
+
+/// Run `zaco compile --emit <emit>` on a module under examples/modules and
+/// return its stdout.
+fn emit_module_graph(input: &str, emit: &str, extra_args: &[&str]) -> String {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(input)
+        .arg("--emit")
+        .arg(emit)
+        .args(extra_args)
+        .current_dir(&root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_emit_deps_prints_dot_graph() {
+    // main imports left and right, which both import shared
+    let dot = emit_module_graph("examples/modules/diamond/main.ts", "deps", &[]);
+    assert_eq!(
+        dot,
+        r#"digraph deps {
+    rankdir=LR;
+    node [shape=box];
+    "main.ts" [style=bold];
+    "left.ts";
+    "right.ts";
+    "options.ts";
+    "shared.ts";
+    "fs" [shape=ellipse, style=dashed];
+    "path" [shape=ellipse, style=dashed];
+    "main.ts" -> "left.ts";
+    "main.ts" -> "right.ts";
+    "main.ts" -> "options.ts" [style=dashed];
+    "main.ts" -> "fs";
+    "left.ts" -> "shared.ts";
+    "right.ts" -> "shared.ts";
+    "shared.ts" -> "path";
+}
+"#
+    );
+
+    let shallow = emit_module_graph("examples/modules/diamond/main.ts", "deps", &["--depth", "1"]);
+    assert!(shallow.contains("\"main.ts\" -> \"left.ts\";"), "got {}", shallow);
+    assert!(!shallow.contains("shared.ts") && !shallow.contains("\"path\""), "got {}", shallow);
+}
+
+#[test]
+fn test_emit_deps_highlights_cycles() {
+    // A circular import is drawn instead of rejected
+    let dot = emit_module_graph("examples/modules/circular_a.ts", "deps", &[]);
+    assert!(dot.contains("\"circular_a.ts\" -> \"circular_b.ts\" [color=red];"), "got {}", dot);
+    assert!(dot.contains("\"circular_b.ts\" -> \"circular_a.ts\" [color=red];"), "got {}", dot);
+}
+
+#[test]
+fn test_emit_deps_json_lists_exports() {
+    let json = emit_module_graph("examples/modules/diamond/main.ts", "deps-json", &[]);
+    assert!(json.starts_with("{\n  \"entry\": \"main.ts\","), "got {}", json);
+    assert!(
+        json.contains(r#"{"path": "shared.ts", "depth": 2, "package": false, "in_cycle": false, "dependencies": [], "type_dependencies": [], "builtins": ["path"], "exports": ["base"], "type_exports": []}"#),
+        "got {}",
+        json
+    );
+    assert!(json.contains(r#""type_exports": ["Options"]"#), "got {}", json);
+}
+
+// ============================================================================
+// Checked Mode
+// ============================================================================
+
+#[test]
+fn test_checked_null_receiver_is_catchable() {
+    let output = compile_and_run_with_args(
+        r#"
+class Point {
+  x: number;
+  constructor(x: number) { this.x = x; }
+  getX(): number { return this.x; }
+}
+class Holder {
+  point!: Point;
+  constructor() {}
+}
+let h: Holder = new Holder();
+let p: Point = h.point;
+try {
+  console.log(p.getX());
+} catch (e) {
+  console.log(e);
+}
+console.log("after");
+"#,
+        &["--checked"],
+    );
+    assert_eq!(
+        output.trim(),
+        "TypeError: cannot read property getX of null\nafter"
+    );
+}
+
+#[test]
+fn test_checked_guards_every_receiver() {
+    let output = compile_and_run_with_args(
+        r#"
+class Point {
+  x: number;
+  constructor(x: number) { this.x = x; }
+  getX(): number { return this.x; }
+}
+class Holder {
+  point!: Point;
+  constructor() {}
+}
+function missing(): Point { return undefined as any; }
+function empty(): { a: number } { return null as any; }
+let h: Holder = new Holder();
+try { console.log(h.point.x); } catch (e) { console.log(e); }
+try { console.log(missing().getX()); } catch (e) { console.log(e); }
+try { console.log(missing().x); } catch (e) { console.log(e); }
+try { console.log(empty().a); } catch (e) { console.log(e); }
+"#,
+        &["--checked"],
+    );
+    assert_eq!(
+        output.trim(),
+        "TypeError: cannot read property x of null\n\
+         TypeError: cannot read property getX of undefined\n\
+         TypeError: cannot read property x of undefined\n\
+         TypeError: cannot read property a of null"
+    );
+}
+
+// ============================================================================
+// Allocation Strategy
+// ============================================================================
+
+/// Compile a file with the given flags and return (stdout, wall time).
+fn compile_and_time(input_path: &std::path::Path, extra_args: &[&str]) -> (String, std::time::Duration) {
+    let workspace_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let output_path = std::env::temp_dir().join(format!(
+        "zaco_bench_{}_{}",
+        std::process::id(),
+        extra_args.join("_")
+    ));
+
+    let compile_output = Command::new(zaco_binary())
+        .arg("compile")
+        .arg(input_path)
+        .arg("-o")
+        .arg(&output_path)
+        .args(extra_args)
+        .current_dir(&workspace_root)
+        .output()
+        .expect("Failed to run zaco compiler");
+    assert!(
+        compile_output.status.success(),
+        "Compilation failed: {}",
+        String::from_utf8_lossy(&compile_output.stderr)
+    );
+
+    let start = std::time::Instant::now();
+    let run_output = Command::new(&output_path)
+        .output()
+        .expect("Failed to run compiled executable");
+    let elapsed = start.elapsed();
+    let _ = fs::remove_file(&output_path);
+
+    (String::from_utf8_lossy(&run_output.stdout).to_string(), elapsed)
+}
+
+#[test]
+fn test_arena_alloc_strategy_output_matches() {
+    let output = compile_and_run_with_args(
+        r#"
+import { join } from "path";
+class Point {
+  x: number;
+  y: number;
+  constructor(x: number, y: number) { this.x = x; this.y = y; }
+}
+let total: number = 0;
+let joined: number = 0;
+for (let i: number = 0; i < 1000; i = i + 1) {
+  let p: Point = new Point(i, 2);
+  total = total + p.x + p.y;
+  // Strings built by the Rust runtime come from the arena as well
+  joined = joined + join("dir", "file" + i).length;
+}
+console.log(total);
+console.log(joined);
+"#,
+        &["--alloc-strategy", "arena"],
+    );
+    assert_eq!(output.trim(), "501500\n10890");
+}
+
+/// Throughput comparison on an allocation-heavy fixture. Run with
+/// `cargo test -p zaco-driver --test integration -- --ignored --nocapture bench_alloc_strategy`.
+#[test]
+#[ignore]
+fn bench_alloc_strategy_throughput() {
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../examples/bench/alloc_heavy.ts");
+
+    let (rc_out, rc_time) = compile_and_time(&fixture, &["--alloc-strategy", "rc"]);
+    let (arena_out, arena_time) = compile_and_time(&fixture, &["--alloc-strategy", "arena"]);
+
+    assert_eq!(rc_out, arena_out);
+    println!(
+        "alloc_heavy.ts: rc {:?}, arena {:?} ({:.2}x)",
+        rc_time,
+        arena_time,
+        rc_time.as_secs_f64() / arena_time.as_secs_f64()
+    );
+}
+
+// ============================================================================
+// Instrumentation
+// ============================================================================
+
+#[test]
+fn test_instrument_profile_summary() {
+    let run_output = compile_and_run_output(
+        r#"
+class Point {
+  x: number;
+  constructor(x: number) { this.x = x; }
+  getX(): number { return this.x; }
+}
+function fib(n: number): number {
+  if (n < 2) {
+    return n;
+  }
+  return fib(n - 1) + fib(n - 2);
+}
+let total: number = 0;
+for (let i: number = 0; i < 3; i = i + 1) {
+  let p: Point = new Point(i);
+  total = total + p.getX();
+}
+console.log(fib(10));
+"#,
+        &["--instrument"],
+    );
+    assert_eq!(String::from_utf8_lossy(&run_output.stdout).trim(), "55");
+
+    // Timings vary between runs; snapshot everything else
+    let stderr = String::from_utf8_lossy(&run_output.stderr);
+    let summary: Vec<&str> = stderr
+        .lines()
+        .map(|line| line.split(", ").next().unwrap())
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            "== zaco profile: calls ==",
+            "Point_constructor: 3 calls",
+            "Point_getX: 3 calls",
+            "fib: 177 calls",
+            "== zaco profile: allocations ==",
+            "Point: 3",
+        ]
+    );
+}
+
+// ============================================================================
+// Stack overflow
+// ============================================================================
+
+#[test]
+fn test_unbounded_recursion_reports_range_error() {
+    let source = r#"
+function down(n: number): number {
+  return down(n + 1) + 1;
+}
+console.log(down(0));
+"#;
+    let run_output = compile_and_run_output(source, &[]);
+    let stderr = String::from_utf8_lossy(&run_output.stderr);
+    assert!(
+        stderr.starts_with("RangeError: Maximum call stack size exceeded\n"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    assert_eq!(run_output.status.code(), Some(3));
+
+    // Instrumented builds also name the function that overflowed
+    let run_output = compile_and_run_output(source, &["--instrument"]);
+    let stderr = String::from_utf8_lossy(&run_output.stderr);
+    assert!(stderr.contains("    at down\n"), "unexpected stderr: {}", stderr);
+    assert_eq!(run_output.status.code(), Some(3));
+}
+
+#[test]
+fn test_recursion_in_generator_reports_range_error() {
+    let run_output = compile_and_run_output(
+        r#"
+function down(n: number): number {
+  return down(n + 1) + 1;
+}
+function* g() {
+  yield down(0);
+}
+const it = g();
+console.log(it.next().value);
+"#,
+        &[],
+    );
+    let stderr = String::from_utf8_lossy(&run_output.stderr);
+    assert!(
+        stderr.starts_with("RangeError: Maximum call stack size exceeded\n"),
+        "unexpected stderr: {}",
+        stderr
+    );
+    assert_eq!(run_output.status.code(), Some(3));
+}
+
+#[test]
+fn test_stack_size_allows_deep_recursion() {
+    let run_output = compile_and_run_output(
+        r#"
+function sum(n: number): number {
+  if (n == 0) {
+    return 0;
+  }
+  return sum(n - 1) + 1;
+}
+console.log(sum(2000000));
+"#,
+        &["--stack-size", "1073741824"],
+    );
+    assert_eq!(String::from_utf8_lossy(&run_output.stdout).trim(), "2000000");
+}
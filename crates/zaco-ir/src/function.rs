@@ -2,7 +2,7 @@
 
 use zaco_ast::Span;
 
-use crate::{Block, BlockId, FuncId, FuncSignature, IrType, LocalId, StructId, TempId};
+use crate::{Block, BlockId, Constant, FuncId, FuncSignature, IrType, LocalId, StructId, TempId, Value};
 
 /// An IR function definition.
 #[derive(Debug, Clone, PartialEq)]
@@ -86,6 +86,19 @@ impl IrFunction {
         &self.blocks[id.0]
     }
 
+    /// Returns the type of a value used in this function, if known.
+    pub fn value_type(&self, value: &Value) -> Option<IrType> {
+        match value {
+            Value::Local(id) => self.locals.iter().find(|(l, _)| l == id).map(|(_, ty)| ty.clone()),
+            Value::Temp(id) => self.temps.iter().find(|(t, _)| t == id).map(|(_, ty)| ty.clone()),
+            Value::Const(Constant::I64(_)) => Some(IrType::I64),
+            Value::Const(Constant::F64(_)) => Some(IrType::F64),
+            Value::Const(Constant::Bool(_)) => Some(IrType::Bool),
+            Value::Const(Constant::Str(_)) => Some(IrType::Str),
+            Value::Const(Constant::Null | Constant::Undefined | Constant::FuncAddr(_) | Constant::GlobalAddr(_)) => Some(IrType::Ptr),
+        }
+    }
+
     /// Returns the function signature.
     pub fn signature(&self) -> FuncSignature {
         FuncSignature {
@@ -8,7 +8,7 @@ use std::fmt;
 use zaco_ast::*;
 
 use crate::{
-    BinOp, BlockId, Constant, FuncId, IrFunction, IrModule, IrStruct, IrType, Instruction, LocalId,
+    BinOp, BlockId, Constant, FuncId, FuncSignature, IrFunction, IrModule, IrStruct, IrType, Instruction, LocalId,
     Place, RValue, StructId, TempId, Terminator, UnOp, Value,
 };
 
@@ -17,6 +17,9 @@ use crate::{
 pub struct LowerError {
     pub message: String,
     pub span: Span,
+    /// The construct type checks but the lowerer cannot translate it: a
+    /// gap in the compiler rather than a mistake in the program
+    pub unsupported: bool,
 }
 
 impl LowerError {
@@ -24,8 +27,13 @@ impl LowerError {
         Self {
             message: message.into(),
             span,
+            unsupported: false,
         }
     }
+
+    pub fn unsupported(message: impl Into<String>, span: Span) -> Self {
+        Self { unsupported: true, ..Self::new(message, span) }
+    }
 }
 
 impl fmt::Display for LowerError {
@@ -36,6 +44,53 @@ impl fmt::Display for LowerError {
 
 impl std::error::Error for LowerError {}
 
+/// What a local module exports at run time, as seen by the modules that
+/// import it.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleExports {
+    /// Exported functions: name → (parameter types, return type)
+    pub functions: HashMap<String, (Vec<IrType>, IrType)>,
+    /// Exported `const`s initialized with a literal, which importers use
+    /// in its place
+    pub constants: HashMap<String, Literal>,
+}
+
+impl ModuleExports {
+    /// The exports of a lowered module: its public functions and the
+    /// literal `export const` declarations of its program.
+    pub fn of(program: &Program, module: &IrModule) -> Self {
+        let functions = module
+            .functions
+            .iter()
+            .filter(|func| func.is_public)
+            .map(|func| {
+                let params = func.params.iter().map(|(_, ty)| ty.clone()).collect();
+                (func.name.clone(), (params, func.return_type.clone()))
+            })
+            .collect();
+        let mut constants = HashMap::new();
+        for item in &program.items {
+            let ModuleItem::Export(ExportDecl::Decl(decl)) = &item.value else { continue };
+            let Decl::Var(var_decl) = &decl.value else { continue };
+            if var_decl.kind != VarDeclKind::Const {
+                continue;
+            }
+            for declarator in &var_decl.declarations {
+                if let (Pattern::Ident { name, .. }, Some(Node { value: Expr::Literal(literal), .. })) =
+                    (&declarator.pattern.value, &declarator.init)
+                {
+                    constants.insert(name.value.name.clone(), literal.clone());
+                }
+            }
+        }
+        Self { functions, constants }
+    }
+}
+
+/// Bits of the number `undefined` reads as (`ZACO_UNDEFINED_F64_BITS` in
+/// the runtime): a signalling NaN, which arithmetic turns into a plain NaN.
+const UNDEFINED_F64_BITS: u64 = 0x7FF4_0000_0000_0000;
+
 /// Variable info tracked during lowering.
 #[derive(Debug, Clone)]
 struct VarInfo {
@@ -67,6 +122,61 @@ struct ClassInfo {
     static_methods: Vec<String>,
     /// Static property names and types
     static_properties: Vec<(String, IrType)>,
+    /// Annotated return types of instance methods (own + inherited)
+    method_returns: Vec<(String, Type)>,
+    /// Global holding the constructor closure of a class with class
+    /// decorators, which may have replaced it; `new` calls through it
+    constructor_slot: Option<String>,
+}
+
+impl ClassInfo {
+    /// Index of a field in the struct layout. A subclass may declare its own
+    /// `#name` next to an inherited one of the same spelling; each class only
+    /// sees its own, which is the last one in its layout.
+    fn field_index(&self, name: &str) -> Option<usize> {
+        if name.starts_with('#') {
+            self.fields.iter().rposition(|(n, _)| n == name)
+        } else {
+            self.fields.iter().position(|(n, _)| n == name)
+        }
+    }
+}
+
+/// What flows through a generator: the type of the values it yields and
+/// the type `next(v)` sends back in as the result of a paused `yield`.
+/// An async generator's `next()` hands its result back in a promise.
+#[derive(Debug, Clone)]
+struct GeneratorTypes {
+    yield_ty: IrType,
+    next_ty: IrType,
+    is_async: bool,
+}
+
+/// An enum's members and their compile-time values (see `EnumDecl::member_values`).
+struct EnumInfo {
+    is_const: bool,
+    members: Vec<(String, Option<EnumValue>)>,
+}
+
+/// How an exported member of a namespace is reached from outside it.
+#[derive(Clone)]
+enum NamespaceMember {
+    /// A function, lowered under the given mangled name
+    Function(String),
+    /// A `const` initialized with a literal, used in place of the member
+    Literal(Literal),
+    /// Any other variable, bound in the enclosing scope under `<path>.<name>`
+    Binding(String),
+}
+
+/// The generator body being lowered.
+struct GeneratorCtx {
+    /// Parameter holding the generator handle
+    handle: LocalId,
+    /// Type of a `yield` expression's result
+    next_ty: IrType,
+    /// Type of the first value yielded, once one has been lowered
+    yield_ty: Option<IrType>,
 }
 
 /// Closure binding info tracked during lowering.
@@ -86,12 +196,15 @@ struct ClosureInfo {
 /// Scope for tracking variable bindings.
 struct Scope {
     vars: HashMap<String, VarInfo>,
+    /// `var`s hoisted into this scope, with the function that owns them
+    hoisted_vars: HashMap<String, FuncId>,
 }
 
 impl Scope {
     fn new() -> Self {
         Self {
             vars: HashMap::new(),
+            hoisted_vars: HashMap::new(),
         }
     }
 }
@@ -106,6 +219,18 @@ pub struct Lowerer {
     /// Maps imported names to their source module
     /// e.g., "readFileSync" → "fs", "join" → "path"
     imported_bindings: HashMap<String, String>,
+    /// Names each local module exports only as types, keyed by import source
+    type_only_exports: HashMap<String, HashSet<String>>,
+    /// What each imported local module exports at run time, keyed by import source
+    module_exports: HashMap<String, ModuleExports>,
+    /// Functions imported from local modules: local name → (exported name,
+    /// parameter types, return type)
+    imported_functions: HashMap<String, (String, Vec<IrType>, IrType)>,
+    /// Literal constants imported from local modules, by local name
+    imported_constants: HashMap<String, Literal>,
+    /// Value imports of a type-only export: local name → (source, import span).
+    /// Using one as a value is an error reported at the import.
+    type_only_bindings: HashMap<String, (String, Span)>,
     /// Loop context stack: (header_block, exit_block) for continue targets
     loop_stack: Vec<(BlockId, BlockId)>,
     /// Break target stack: exit blocks for loops and switch statements
@@ -118,23 +243,104 @@ pub struct Lowerer {
     next_struct_id: usize,
     /// Current `this` variable info (set when lowering class methods/constructors)
     this_var: Option<VarInfo>,
+    /// Shape of the object literal whose accessor is being lowered, which
+    /// its `this` refers to
+    this_object_shape: Option<Vec<(String, IrType)>>,
     /// Current class name (set when lowering class methods/constructors)
     current_class: Option<String>,
     /// Closure bindings: variable_name → ClosureInfo
     closure_bindings: HashMap<String, ClosureInfo>,
+    /// Functions that return a closure: function name → closure it returns.
+    /// The function returns the closure's env pointer; `env_local` is unset.
+    returned_closures: HashMap<String, ClosureInfo>,
+    /// The variable whose initializer is the arrow or function expression
+    /// about to be lowered, which can call itself through it
+    closure_binding_name: Option<String>,
+    /// Types for the unannotated parameters of the arrow or function
+    /// expression about to be lowered, from the function type it is
+    /// passed as
+    closure_param_types: Option<Vec<IrType>>,
+    /// Functions whose body reads `arguments`: name → declared parameter
+    /// types. They take the actual arguments as a trailing array parameter.
+    arguments_functions: HashMap<String, Vec<IrType>>,
+    /// Exported functions that `spawn` from "worker" can run: name → the
+    /// structured-clone shapes of their parameters (see `clone_shape`)
+    worker_functions: HashMap<String, String>,
+    /// Classes whose `__zaco_json_<Class>` serializer has been emitted
+    json_serializers: HashSet<String>,
+    /// Variables holding a Database from the "sqlite" module's `open`
+    sqlite_databases: HashSet<String>,
+    /// Variables holding a Response from an awaited `fetch`
+    fetch_responses: HashSet<String>,
+    /// Generator functions → what their generators yield and accept
+    generator_functions: HashMap<String, GeneratorTypes>,
+    /// Functions with a return type annotation → the annotated type, which
+    /// keeps what the IR type erases (such as a tuple's element types)
+    function_return_types: HashMap<String, Type>,
+    /// Variables holding a generator object
+    generator_vars: HashMap<String, GeneratorTypes>,
+    /// Variables holding an AbortController (the handle doubles as its signal)
+    abort_controllers: HashSet<String>,
+    /// Named plain-object types (interfaces and object type aliases):
+    /// name → field types
+    object_types: HashMap<String, Vec<(String, IrType)>>,
+    /// Interfaces and type aliases as written, for shapes that nest or
+    /// recurse (see `type_clone_shape`)
+    named_types: HashMap<String, Type>,
+    /// Enums by name. Member accesses with a compile-time value are
+    /// replaced by it.
+    enums: HashMap<String, EnumInfo>,
+    /// Namespaces by dotted path ("A", "A.B") → their exported members
+    namespaces: HashMap<String, HashMap<String, NamespaceMember>>,
+    /// Namespace bodies being lowered (innermost last): path, and the
+    /// mangled names of the functions declared directly in the body
+    namespace_scopes: Vec<(String, HashMap<String, String>)>,
+    /// Variables with a type annotation → the annotated type
+    value_types: HashMap<String, Type>,
+    /// Variables holding a plain object of known shape → its field types.
+    /// Fields are read with zaco_object_get_*.
+    object_shapes: HashMap<String, Vec<(String, IrType)>>,
+    /// Variables holding an array of plain objects → the elements' field types
+    element_shapes: HashMap<String, Vec<(String, IrType)>>,
     /// Next closure ID counter
     next_closure_id: usize,
     /// Parent class name for the current constructor (for super() resolution)
     current_class_parent: Option<String>,
     /// Current function being lowered (name, return_type) for recursive call detection
     current_function: Option<(String, IrType)>,
+    /// Promise returned by the async function being lowered; `return`
+    /// resolves it instead of returning the value directly
+    async_promise: Option<TempId>,
+    /// Generator body being lowered; `yield` and `return` talk to its handle
+    current_generator: Option<GeneratorCtx>,
     /// Whether the user program defines a function named "main"
     has_user_main: bool,
+    /// Whether the user program defines a top-level function named "test",
+    /// shadowing the test runner's `test(name, fn)`
+    has_user_test: bool,
     /// Optional module name for non-entry modules.
     /// When set, the top-level wrapper is named `__module_init_<name>` instead of "main".
     module_name: Option<String>,
     /// Source file path for __dirname/__filename resolution.
     file_path: Option<String>,
+    /// Checked mode: emit runtime guards (e.g. null receivers) that throw
+    /// catchable errors instead of crashing.
+    checked: bool,
+    /// Profiling instrumentation: function enter/exit and allocation counters.
+    instrument: bool,
+    /// Log lowered nodes and declared externs to stderr (`--trace-lowering`).
+    trace: bool,
+    /// Nesting depth of the node being lowered, for trace indentation.
+    trace_depth: usize,
+    /// Skip constructs the lowerer does not support instead of reporting
+    /// them (`--allow-unsupported`).
+    allow_unsupported: bool,
+    /// Constants from `--define NAME=value`, which replace reads of `NAME`
+    /// not shadowed by a local.
+    defines: HashMap<String, Literal>,
+    /// Module-level variables that function declarations or class members
+    /// use → (global holding the variable's box, the variable's type)
+    module_globals: HashMap<String, (String, IrType)>,
 }
 
 /// Context for lowering a single function body.
@@ -177,20 +383,56 @@ impl Lowerer {
             next_func_id: 0,
             scopes: Vec::new(),
             imported_bindings: HashMap::new(),
+            type_only_exports: HashMap::new(),
+            module_exports: HashMap::new(),
+            imported_functions: HashMap::new(),
+            imported_constants: HashMap::new(),
+            type_only_bindings: HashMap::new(),
             loop_stack: Vec::new(),
             break_stack: Vec::new(),
             extern_set: HashSet::new(),
             class_info: HashMap::new(),
             next_struct_id: 0,
             this_var: None,
+            this_object_shape: None,
             current_class: None,
             closure_bindings: HashMap::new(),
+            returned_closures: HashMap::new(),
+            closure_binding_name: None,
+            closure_param_types: None,
+            arguments_functions: HashMap::new(),
+            worker_functions: HashMap::new(),
+            json_serializers: HashSet::new(),
+            sqlite_databases: HashSet::new(),
+            fetch_responses: HashSet::new(),
+            generator_functions: HashMap::new(),
+            function_return_types: HashMap::new(),
+            generator_vars: HashMap::new(),
+            abort_controllers: HashSet::new(),
+            object_types: HashMap::new(),
+            named_types: HashMap::new(),
+            enums: HashMap::new(),
+            namespaces: HashMap::new(),
+            namespace_scopes: Vec::new(),
+            value_types: HashMap::new(),
+            object_shapes: HashMap::new(),
+            element_shapes: HashMap::new(),
             next_closure_id: 0,
             current_class_parent: None,
             current_function: None,
+            async_promise: None,
+            current_generator: None,
             has_user_main: false,
+            has_user_test: false,
             module_name: None,
             file_path: None,
+            checked: false,
+            instrument: false,
+            trace: false,
+            trace_depth: 0,
+            allow_unsupported: false,
+            defines: HashMap::new(),
+            module_globals: HashMap::new(),
         }
     }
 
@@ -207,6 +449,54 @@ impl Lowerer {
         self
     }
 
+    /// Enable checked mode, which guards member access and method calls
+    /// against null receivers.
+    pub fn with_checked(mut self, checked: bool) -> Self {
+        self.checked = checked;
+        self
+    }
+
+    /// Enable profiling instrumentation (`--instrument`).
+    pub fn with_instrument(mut self, instrument: bool) -> Self {
+        self.instrument = instrument;
+        self
+    }
+
+    /// Log each statement, declaration and expression as it is lowered, and
+    /// each runtime extern as it is declared, to stderr (`--trace-lowering`).
+    pub fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Skip unsupported constructs, as if they were not in the program,
+    /// rather than reporting them as errors (`--allow-unsupported`).
+    pub fn with_allow_unsupported(mut self, allow_unsupported: bool) -> Self {
+        self.allow_unsupported = allow_unsupported;
+        self
+    }
+
+    /// Provide the constants given with `--define`. Branches whose condition
+    /// they decide are not lowered at all.
+    pub fn with_defines(mut self, defines: HashMap<String, Literal>) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    /// Provide the names each imported local module exports only as types,
+    /// keyed by import source as written.
+    pub fn with_type_only_exports(mut self, type_only_exports: HashMap<String, HashSet<String>>) -> Self {
+        self.type_only_exports = type_only_exports;
+        self
+    }
+
+    /// Provide what each imported local module exports at run time, keyed
+    /// by import source as written.
+    pub fn with_module_exports(mut self, module_exports: HashMap<String, ModuleExports>) -> Self {
+        self.module_exports = module_exports;
+        self
+    }
+
     /// Set the starting FuncId offset so that IDs don't collide across modules.
     pub fn with_func_id_offset(mut self, offset: usize) -> Self {
         self.next_func_id = offset;
@@ -254,25 +544,395 @@ impl Lowerer {
         None
     }
 
+    /// Rewrite `globalThis.X...` (or a call through it) to `X...`, so globals
+    /// reached through `globalThis` lower exactly like the bare names. `None`
+    /// if `expr` doesn't go through `globalThis`, or a local shadows it or `X`.
+    fn strip_global_this(&self, expr: &Expr) -> Option<Expr> {
+        match expr {
+            Expr::Member { object, property, computed: false } => {
+                if matches!(&object.value, Expr::Ident(id) if id.name == "globalThis") {
+                    let shadowed = self.lookup_var("globalThis").is_some()
+                        || self.lookup_var(&property.value.name).is_some();
+                    return (!shadowed).then(|| Expr::Ident(property.value.clone()));
+                }
+                let stripped = self.strip_global_this(&object.value)?;
+                Some(Expr::Member {
+                    object: Box::new(Node::new(stripped, object.span)),
+                    property: property.clone(),
+                    computed: false,
+                })
+            }
+            Expr::Call { callee, type_args, args } => {
+                let stripped = self.strip_global_this(&callee.value)?;
+                Some(Expr::Call {
+                    callee: Box::new(Node::new(stripped, callee.span)),
+                    type_args: type_args.clone(),
+                    args: args.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// `obj[Symbol.iterator]` (and calls through it) as the named member
+    /// `obj.@@iterator`, which is how `[Symbol.iterator]` members are lowered.
+    fn symbol_member(&self, expr: &Expr) -> Option<Expr> {
+        match expr {
+            Expr::Index { object, index } => {
+                let symbol = index.value.well_known_symbol()?;
+                if self.lookup_var("Symbol").is_some() {
+                    return None;
+                }
+                Some(Expr::Member {
+                    object: object.clone(),
+                    property: Node::new(Ident::new(symbol_key(symbol)), index.span),
+                    computed: false,
+                })
+            }
+            Expr::Call { callee, type_args, args } => {
+                let member = self.symbol_member(&callee.value)?;
+                Some(Expr::Call {
+                    callee: Box::new(Node::new(member, callee.span)),
+                    type_args: type_args.clone(),
+                    args: args.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// `Ns.member` (and calls through it) as what the member was lowered to,
+    /// and, inside a namespace body, its functions' bare names as their
+    /// mangled ones. `None` if `expr` isn't one of those or a local shadows it.
+    fn namespace_member(&self, expr: &Expr) -> Option<Expr> {
+        match expr {
+            Expr::Member { object, property, computed: false } => {
+                let path = self.namespace_path(&object.value)?;
+                match self.namespaces.get(&path)?.get(&property.value.name)? {
+                    NamespaceMember::Function(name) | NamespaceMember::Binding(name) => {
+                        Some(Expr::Ident(Ident::new(name.clone())))
+                    }
+                    NamespaceMember::Literal(literal) => Some(Expr::Literal(literal.clone())),
+                }
+            }
+            Expr::Ident(ident) => {
+                if self.lookup_var(&ident.name).is_some() {
+                    return None;
+                }
+                self.namespace_scopes
+                    .iter()
+                    .rev()
+                    .find_map(|(_, functions)| functions.get(&ident.name))
+                    .map(|name| Expr::Ident(Ident::new(name.clone())))
+            }
+            Expr::Call { callee, type_args, args } => {
+                let member = self.namespace_member(&callee.value)?;
+                Some(Expr::Call {
+                    callee: Box::new(Node::new(member, callee.span)),
+                    type_args: type_args.clone(),
+                    args: args.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// `Array.of(a, b)`, and `Array(a, b)` or `new Array(a, b)` with other
+    /// than a single number, as the array literal `[a, b]`, unless a local
+    /// shadows `Array`.
+    fn array_of_literal(&self, expr: &Expr) -> Option<Expr> {
+        if let Some((args, _)) = self.array_constructor(expr) {
+            return match args {
+                [length] if matches!(self.infer_expr_type(&length.value), IrType::F64 | IrType::I64) => None,
+                _ => Some(Expr::Array(args.iter().cloned().map(Some).collect())),
+            };
+        }
+        let Expr::Call { callee, args, .. } = expr else { return None };
+        let Expr::Member { object, property, computed: false } = &callee.value else { return None };
+        match &object.value {
+            Expr::Ident(ident) if ident.name == "Array" && property.value.name == "of" && self.lookup_var("Array").is_none() => {
+                Some(Expr::Array(args.iter().cloned().map(Some).collect()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of a `--define`d name or a literal constant imported from
+    /// a local module, unless a local shadows it.
+    fn define_literal(&self, expr: &Expr) -> Option<Expr> {
+        match expr {
+            Expr::Ident(ident) if self.lookup_var(&ident.name).is_none() => {
+                let literal = self.defines.get(&ident.name).or_else(|| self.imported_constants.get(&ident.name));
+                literal.cloned().map(Expr::Literal)
+            }
+            _ => None,
+        }
+    }
+
+    /// Truthiness of a condition made only of literals and `--define`d
+    /// names, such as `DEBUG` or `MODE === "dev"`, which decides which
+    /// branch of an `if` or `?:` is lowered.
+    fn constant_condition(&self, expr: &Expr) -> Option<bool> {
+        if let Some(literal) = self.constant_literal(expr) {
+            return Some(match literal {
+                Literal::Number(n) => n != 0.0 && !n.is_nan(),
+                Literal::String(s) => !s.is_empty(),
+                Literal::Boolean(b) => b,
+                Literal::Null | Literal::Undefined => false,
+                Literal::RegExp { .. } => true,
+            });
+        }
+        match expr {
+            Expr::Paren(inner) => self.constant_condition(&inner.value),
+            Expr::Unary { op: UnaryOp::Not, expr } => self.constant_condition(&expr.value).map(|b| !b),
+            Expr::Binary { left, op: BinaryOp::And, right } => match self.constant_condition(&left.value)? {
+                true => self.constant_condition(&right.value),
+                false => Some(false),
+            },
+            Expr::Binary { left, op: BinaryOp::Or, right } => match self.constant_condition(&left.value)? {
+                true => Some(true),
+                false => self.constant_condition(&right.value),
+            },
+            Expr::Binary { left, op, right } if matches!(op, BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::StrictEq | BinaryOp::StrictNotEq) => {
+                let strict = matches!(op, BinaryOp::StrictEq | BinaryOp::StrictNotEq);
+                let equal = match (self.constant_literal(&left.value)?, self.constant_literal(&right.value)?) {
+                    (Literal::Number(a), Literal::Number(b)) => a == b,
+                    (Literal::String(a), Literal::String(b)) => a == b,
+                    (Literal::Boolean(a), Literal::Boolean(b)) => a == b,
+                    (Literal::Null, Literal::Null) | (Literal::Undefined, Literal::Undefined) => true,
+                    (Literal::Null, Literal::Undefined) | (Literal::Undefined, Literal::Null) => !strict,
+                    // Loose comparisons of different types coerce; leave those to runtime
+                    _ if strict => false,
+                    _ => return None,
+                };
+                Some(equal == matches!(op, BinaryOp::Eq | BinaryOp::StrictEq))
+            }
+            _ => None,
+        }
+    }
+
+    /// The literal an expression is, looking through parentheses and
+    /// `--define`d names.
+    fn constant_literal(&self, expr: &Expr) -> Option<Literal> {
+        match expr {
+            Expr::Literal(literal) => Some(literal.clone()),
+            Expr::Paren(inner) => self.constant_literal(&inner.value),
+            _ => match self.define_literal(expr)? {
+                Expr::Literal(literal) => Some(literal),
+                _ => None,
+            },
+        }
+    }
+
+    /// Arguments of `Array(...)` or `new Array(...)`, with the element type
+    /// given as a type argument, unless a local or class shadows `Array`.
+    fn array_constructor<'e>(&self, expr: &'e Expr) -> Option<(&'e [Node<Expr>], Option<IrType>)> {
+        let (Expr::Call { callee, type_args, args } | Expr::New { callee, type_args, args }) = expr else {
+            return None;
+        };
+        match &callee.value {
+            Expr::Ident(ident)
+                if ident.name == "Array" && self.lookup_var("Array").is_none() && !self.class_info.contains_key("Array") =>
+            {
+                let elem = type_args.as_ref().and_then(|type_args| type_args.first());
+                Some((args, elem.map(|elem| self.ast_type_to_ir(&elem.value))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Dotted path of the namespace `expr` names. Inside a namespace body,
+    /// nested namespaces are found by their own name.
+    fn namespace_path(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Ident(ident) => {
+                if self.lookup_var(&ident.name).is_some() {
+                    return None;
+                }
+                self.namespace_scopes
+                    .iter()
+                    .rev()
+                    .map(|(path, _)| format!("{}.{}", path, ident.name))
+                    .chain(std::iter::once(ident.name.clone()))
+                    .find(|path| self.namespaces.contains_key(path))
+            }
+            Expr::Member { object, property, computed: false } => {
+                let path = format!("{}.{}", self.namespace_path(&object.value)?, property.value.name);
+                self.namespaces.contains_key(&path).then_some(path)
+            }
+            _ => None,
+        }
+    }
+
     /// Ensure an extern function is declared in the module.
+    /// Write one `--trace-lowering` line, indented by the current nesting depth.
+    fn trace_line(&self, event: fmt::Arguments) {
+        eprintln!("[lower] {}{}", "  ".repeat(self.trace_depth), event);
+    }
+
+    /// Run `lower` for an AST node, tracing the node's kind and span and
+    /// indenting whatever it lowers in turn.
+    fn traced<N: fmt::Debug, T>(&mut self, what: &str, node: &N, span: &Span, lower: impl FnOnce(&mut Self) -> T) -> T {
+        if !self.trace {
+            return lower(self);
+        }
+        // The variant name is the leading identifier of the node's Debug output
+        let debug = format!("{:?}", node);
+        let kind = debug.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or_default();
+        self.trace_line(format_args!("{} {} @{}..{}", what, kind, span.start, span.end));
+        self.trace_depth += 1;
+        let result = lower(self);
+        self.trace_depth -= 1;
+        result
+    }
+
+    /// Report a construct the lowerer has no translation for. It is left
+    /// out of the program, which only compiles with `--allow-unsupported`.
+    fn unsupported(&mut self, construct: &str, span: &Span) {
+        if !self.allow_unsupported {
+            self.errors.push(LowerError::unsupported(
+                format!("{} is not supported yet (--allow-unsupported compiles the program without it)", construct),
+                *span,
+            ));
+        }
+    }
+
     fn ensure_extern(&mut self, name: &str, params: Vec<IrType>, ret: IrType) {
         if self.extern_set.insert(name.to_string()) {
+            if self.trace {
+                self.trace_line(format_args!("extern {}({:?}) -> {:?}", name, params, ret));
+            }
             self.module.add_extern_function(name.to_string(), params, ret);
         }
     }
 
+    /// In checked mode, throw `TypeError: cannot read property <prop> of null`
+    /// (or `of undefined`) when `receiver` is null or undefined. No-op
+    /// otherwise.
+    fn emit_receiver_guard(&mut self, ctx: &mut FuncCtx, receiver: Value, property: &str) {
+        if !self.checked {
+            return;
+        }
+        self.ensure_extern("zaco_check_receiver", vec![IrType::Ptr, IrType::Str], IrType::Void);
+        self.module.intern_string(property.to_string());
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_check_receiver".to_string())),
+            args: vec![receiver, Value::Const(Constant::Str(property.to_string()))],
+        });
+    }
+
+    /// Lower the receiver of a member read or method call, guarding it
+    /// against null and undefined in checked mode.
+    fn lower_receiver(&mut self, ctx: &mut FuncCtx, object: &Node<Expr>, property: &str) -> Option<Value> {
+        let receiver = self.lower_expr(ctx, &object.value, &object.span)?;
+        self.emit_receiver_guard(ctx, receiver.clone(), property);
+        Some(receiver)
+    }
+
     /// Lower an entire program into an IR module.
     pub fn lower_program(mut self, program: &Program) -> Result<IrModule, Vec<LowerError>> {
         // Detect if user defines a function named "main" — if so, we'll rename it
         // to avoid conflicting with the compiler-generated entry point wrapper.
+        // A function named "test" replaces the test runner's global.
+        for item in &program.items {
+            if let ModuleItem::Decl(decl_node) = &item.value {
+                if let Decl::Function(func_decl) = &decl_node.value {
+                    match func_decl.name.value.name.as_str() {
+                        "main" => self.has_user_main = true,
+                        "test" => self.has_user_test = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // Calls may precede the declaration, so find functions that read
+        // `arguments`, and the declared return types, up front
         for item in &program.items {
             if let ModuleItem::Decl(decl_node) = &item.value {
                 if let Decl::Function(func_decl) = &decl_node.value {
-                    if func_decl.name.value.name == "main" {
-                        self.has_user_main = true;
-                        break;
+                    if let Some(return_type) = &func_decl.return_type {
+                        self.function_return_types.insert(func_decl.name.value.name.clone(), return_type.value.clone());
                     }
+                    let uses_arguments = func_decl.body.as_ref().is_some_and(|body| {
+                        body.value.stmts.iter().any(|s| Self::stmt_uses_arguments(&s.value))
+                    });
+                    if uses_arguments {
+                        let param_types = func_decl.params.iter().map(|p| self.infer_param_type(p)).collect();
+                        self.arguments_functions.insert(func_decl.name.value.name.clone(), param_types);
+                    }
+                }
+            }
+        }
+
+        // A module that imports "worker" can spawn any of its exported
+        // functions whose parameters can be structured-cloned
+        let imports_worker = program.items.iter().any(|item| {
+            matches!(&item.value, ModuleItem::Import(import) if import.source == "worker")
+        });
+        if imports_worker {
+            for item in &program.items {
+                if let ModuleItem::Export(ExportDecl::Decl(decl)) = &item.value {
+                    if let Decl::Function(func_decl) = &decl.value {
+                        if func_decl.is_async || func_decl.is_generator {
+                            continue;
+                        }
+                        let shapes: Option<String> = func_decl.params.iter().map(Self::param_clone_shape).collect();
+                        if let Some(shapes) = shapes {
+                            self.worker_functions.insert(func_decl.name.value.name.clone(), shapes);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Decorator context objects, as lower_class_decorators builds them
+        for kind in [DecoratorKind::Class, DecoratorKind::Method, DecoratorKind::Field] {
+            let mut shape = vec![("kind".to_string(), IrType::Str), ("name".to_string(), IrType::Str)];
+            if kind != DecoratorKind::Class {
+                shape.push(("static".to_string(), IrType::Bool));
+                shape.push(("private".to_string(), IrType::Bool));
+            }
+            self.object_types.insert(kind.context_type_name().to_string(), shape);
+        }
+
+        // Interfaces and object type aliases give plain objects a shape
+        for item in &program.items {
+            let decl = match &item.value {
+                ModuleItem::Decl(decl) => &decl.value,
+                ModuleItem::Export(ExportDecl::Decl(decl)) => &decl.value,
+                _ => continue,
+            };
+            let (name, shape) = match decl {
+                Decl::Interface(interface) => {
+                    let ty = Type::Object(ObjectType { members: interface.members.clone() });
+                    self.named_types.insert(interface.name.value.name.clone(), ty);
+                    (&interface.name.value.name, Some(self.object_members_shape(&interface.members)))
+                }
+                Decl::TypeAlias(alias) => {
+                    self.named_types.insert(alias.name.value.name.clone(), alias.ty.value.clone());
+                    (&alias.name.value.name, self.annotation_shape(&alias.ty.value))
+                }
+                Decl::Enum(enum_decl) => {
+                    let name = enum_decl.name.value.name.clone();
+                    self.enums.insert(name.clone(), EnumInfo { is_const: enum_decl.is_const, members: enum_decl.member_values() });
+                    let primitive = match self.enum_ir_type(&name) {
+                        Some(IrType::F64) => PrimitiveType::Number,
+                        Some(IrType::Str) => PrimitiveType::String,
+                        _ => continue,
+                    };
+                    self.named_types.insert(name, Type::Primitive(primitive));
+                    continue;
                 }
+                Decl::Module(module) => {
+                    self.register_namespace(None, module);
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(shape) = shape {
+                self.object_types.insert(name.clone(), shape);
             }
         }
 
@@ -286,6 +946,8 @@ impl Lowerer {
             ("main".to_string(), IrType::I64)
         };
 
+        self.collect_module_globals(program, &wrapper_name);
+
         let wrapper_id = self.alloc_func_id();
         let mut wrapper_func = IrFunction::new(wrapper_id, wrapper_name, vec![], wrapper_ret);
         wrapper_func.is_public = true;
@@ -299,6 +961,10 @@ impl Lowerer {
         };
 
         self.push_scope();
+        self.hoist_vars(&mut ctx, program.items.iter().filter_map(|item| match &item.value {
+            ModuleItem::Stmt(stmt) => Some(stmt),
+            _ => None,
+        }));
 
         // Lower each top-level item
         for item in &program.items {
@@ -307,7 +973,41 @@ impl Lowerer {
 
         self.pop_scope();
 
-        if is_entry {
+        self.lower_worker_trampolines();
+
+        // Report rejections nothing awaited or caught and the results of
+        // test(); either failing makes the exit code 1
+        let mut exit_checks = Vec::new();
+        if self.uses_promises() {
+            exit_checks.push("zaco_check_unhandled_rejections");
+        }
+        if self.extern_set.contains("zaco_test_begin") {
+            exit_checks.push("zaco_test_report");
+        }
+        if is_entry && !exit_checks.is_empty() {
+            let mut code = None;
+            for check in exit_checks {
+                self.ensure_extern(check, vec![], IrType::I64);
+                let result = ctx.add_temp(IrType::I64);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(result)),
+                    func: Value::Const(Constant::Str(check.to_string())),
+                    args: vec![],
+                });
+                code = Some(match code {
+                    None => Value::Temp(result),
+                    Some(previous) => {
+                        let combined = ctx.add_temp(IrType::I64);
+                        ctx.emit(Instruction::Assign {
+                            dest: Place::from_temp(combined),
+                            value: RValue::BinaryOp { op: BinOp::BitOr, left: previous, right: Value::Temp(result) },
+                        });
+                        Value::Temp(combined)
+                    }
+                });
+            }
+            ctx.set_terminator(Terminator::Return(code));
+        } else if is_entry {
             // Return 0 from main
             let zero_temp = ctx.add_temp(IrType::I64);
             ctx.emit(Instruction::Assign {
@@ -322,11 +1022,23 @@ impl Lowerer {
 
         self.module.add_function(wrapper_func);
 
+        if self.instrument {
+            self.instrument_module(wrapper_id);
+        }
+
         // Record how many IDs were allocated so the driver can compute offsets
         // for subsequent modules during multi-module compilation.
         self.module.next_func_id = self.next_func_id;
         self.module.next_struct_id = self.next_struct_id;
 
+        // Every error has to point at the source it came from
+        #[cfg(test)]
+        debug_assert!(
+            program.span.is_empty() || self.errors.iter().all(|err| !err.span.is_empty()),
+            "lowering error without a source span: {:?}",
+            self.errors
+        );
+
         if self.errors.is_empty() {
             Ok(self.module)
         } else {
@@ -334,13 +1046,106 @@ impl Lowerer {
         }
     }
 
+    /// Whether the module creates promises (async functions, fetch, sleep),
+    /// so `main` has to check for unhandled rejections before exiting.
+    fn uses_promises(&self) -> bool {
+        self.extern_set.iter().any(|name| {
+            name.starts_with("zaco_promise_") || matches!(name.as_str(), "zaco_fetch" | "zaco_sleep")
+        })
+    }
+
+    /// Wrap every user function in `zaco_profile_enter`/`zaco_profile_exit`
+    /// and prefix each allocation site with `zaco_profile_alloc`. Names are
+    /// passed as interned string literals; the runtime keys its tables by
+    /// pointer. The module wrapper only gets allocation counters.
+    fn instrument_module(&mut self, wrapper_id: FuncId) {
+        self.ensure_extern("zaco_profile_enter", vec![IrType::Str], IrType::Void);
+        self.ensure_extern("zaco_profile_exit", vec![IrType::Str], IrType::Void);
+        self.ensure_extern("zaco_profile_alloc", vec![IrType::Str], IrType::Void);
+
+        let profile_call = |hook: &str, name: String| Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str(hook.to_string())),
+            args: vec![Value::Const(Constant::Str(name))],
+        };
+
+        let mut functions = std::mem::take(&mut self.module.functions);
+        for func in &mut functions {
+            for block in &mut func.blocks {
+                let mut instructions = Vec::with_capacity(block.instructions.len());
+                for instr in block.instructions.drain(..) {
+                    if let Some(kind) = self.alloc_site_kind(&instr) {
+                        self.module.intern_string(kind.clone());
+                        instructions.push(profile_call("zaco_profile_alloc", kind));
+                    }
+                    instructions.push(instr);
+                }
+                block.instructions = instructions;
+            }
+
+            if func.id == wrapper_id {
+                continue;
+            }
+            let name = func.name.clone();
+            self.module.intern_string(name.clone());
+            let entry = func.entry_block;
+            func.block_mut(entry)
+                .instructions
+                .insert(0, profile_call("zaco_profile_enter", name.clone()));
+            for block in &mut func.blocks {
+                if matches!(block.terminator, Terminator::Return(_)) {
+                    block.push_instruction(profile_call("zaco_profile_exit", name.clone()));
+                }
+            }
+        }
+        self.module.functions = functions;
+    }
+
+    /// Allocation kind reported by `--instrument` for an instruction, if it allocates.
+    fn alloc_site_kind(&self, instr: &Instruction) -> Option<String> {
+        let struct_name = |id: &StructId| {
+            self.module.structs.iter()
+                .find(|s| s.id == *id)
+                .map(|s| s.name.clone())
+        };
+        match instr {
+            Instruction::Alloc { ty: IrType::Struct(id), .. } => struct_name(id),
+            Instruction::Alloc { .. } => Some("Object".to_string()),
+            Instruction::Assign { value, .. } => match value {
+                RValue::StrConcat(_) => Some("String".to_string()),
+                RValue::ArrayInit(_) => Some("Array".to_string()),
+                RValue::StructInit { struct_id, .. } => struct_name(struct_id),
+                _ => None,
+            },
+            Instruction::Call { func: Value::Const(Constant::Str(name)), .. } => {
+                let kind = match name.as_str() {
+                    "zaco_array_new" => "Array",
+                    "zaco_object_new" => "Object",
+                    "zaco_f64_to_str" => "String",
+                    "zaco_box_new" => "Box",
+                    "zaco_promise_new" => "Promise",
+                    "zaco_generator_new" => "Generator",
+                    _ => return None,
+                };
+                Some(kind.to_string())
+            }
+            _ => None,
+        }
+    }
+
     fn lower_module_item(&mut self, ctx: &mut FuncCtx, item: &ModuleItem) {
         match item {
             ModuleItem::Stmt(stmt_node) => {
                 self.lower_stmt(ctx, &stmt_node.value, &stmt_node.span);
+                if let Stmt::VarDecl(var_decl) = &stmt_node.value {
+                    self.publish_module_globals(ctx, var_decl);
+                }
             }
             ModuleItem::Decl(decl_node) => {
                 self.lower_decl(ctx, &decl_node.value, &decl_node.span);
+                if let Decl::Var(var_decl) = &decl_node.value {
+                    self.publish_module_globals(ctx, var_decl);
+                }
             }
             ModuleItem::Import(import_decl) => {
                 self.lower_import(import_decl);
@@ -351,29 +1156,219 @@ impl Lowerer {
         }
     }
 
-    fn lower_import(&mut self, import_decl: &ImportDecl) {
-        let source = &import_decl.source;
-        for spec in &import_decl.specifiers {
-            match spec {
-                ImportSpecifier::Named { imported, local, .. } => {
-                    let local_name = local.as_ref().unwrap_or(imported).value.name.clone();
-                    self.imported_bindings.insert(local_name, source.clone());
+    /// Give each module-level variable that a function declaration or class
+    /// member mentions a global, through which the function reaches the box
+    /// the module body keeps the variable in. Its type is the declared one
+    /// until the declaration is lowered.
+    fn collect_module_globals(&mut self, program: &Program, wrapper_name: &str) {
+        let mut declarators = Vec::new();
+        let mut mentioned = HashSet::new();
+        let body_names = |stmts: &[Node<Stmt>]| {
+            let mut names = HashSet::new();
+            for stmt in stmts {
+                stmt.value.for_each_ident(&mut |ident| {
+                    names.insert(ident.name.clone());
+                });
+            }
+            names
+        };
+        // Names a body mentions, less the parameters that shadow them
+        let mut mention = |params: &[Param], mut names: HashSet<String>| {
+            for param in params {
+                if let Pattern::Ident { name, .. } = &param.pattern.value {
+                    names.remove(&name.value.name);
                 }
-                ImportSpecifier::Default(ident) => {
-                    self.imported_bindings.insert(ident.value.name.clone(), source.clone());
+            }
+            mentioned.extend(names);
+        };
+        for item in &program.items {
+            let decl = match &item.value {
+                ModuleItem::Stmt(stmt) => {
+                    if let Stmt::VarDecl(var_decl) = &stmt.value {
+                        declarators.extend(&var_decl.declarations);
+                    }
+                    continue;
                 }
-                ImportSpecifier::Namespace(ident) => {
-                    self.imported_bindings.insert(ident.value.name.clone(), source.clone());
+                ModuleItem::Decl(decl) => decl,
+                ModuleItem::Export(ExportDecl::Decl(decl) | ExportDecl::DefaultDecl(decl)) => &**decl,
+                _ => continue,
+            };
+            match &decl.value {
+                Decl::Var(var_decl) => declarators.extend(&var_decl.declarations),
+                Decl::Function(func_decl) => {
+                    if let Some(body) = &func_decl.body {
+                        mention(&func_decl.params, body_names(&body.value.stmts));
+                    }
                 }
-            }
-        }
-    }
+                Decl::Class(class_decl) => {
+                    for member in &class_decl.members {
+                        match member {
+                            ClassMember::Constructor { params, body: Some(body), .. }
+                            | ClassMember::Method { params, body: Some(body), .. } => {
+                                mention(params, body_names(&body.value.stmts))
+                            }
+                            ClassMember::Getter { body: Some(body), .. } => mention(&[], body_names(&body.value.stmts)),
+                            ClassMember::Setter { param, body: Some(body), .. } => {
+                                mention(std::slice::from_ref(param), body_names(&body.value.stmts))
+                            }
+                            ClassMember::Property { init: Some(init), .. } => {
+                                let mut names = HashSet::new();
+                                init.value.for_each_ident(&mut |ident| {
+                                    names.insert(ident.name.clone());
+                                });
+                                mention(&[], names)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        for declarator in declarators {
+            let Pattern::Ident { name, type_annotation, .. } = &declarator.pattern.value else {
+                continue;
+            };
+            let name = &name.value.name;
+            if !mentioned.contains(name) || self.module_globals.contains_key(name) {
+                continue;
+            }
+            let ir_type = match (type_annotation, &declarator.init) {
+                (Some(annotation), _) => self.ast_type_to_ir(&annotation.value),
+                (None, Some(init)) => Self::storage_type(self.infer_expr_type(&init.value)),
+                (None, None) => IrType::F64,
+            };
+            let global = format!("__{}_var_{}", wrapper_name, name);
+            self.module.add_global(global.clone(), IrType::Ptr, None);
+            self.module_globals.insert(name.clone(), (global, ir_type));
+        }
+    }
+
+    /// After a module-level declaration: move each variable it declares that
+    /// has a global into a box (unless a closure already boxed it) and store
+    /// the box in the global.
+    fn publish_module_globals(&mut self, ctx: &mut FuncCtx, var_decl: &VarDecl) {
+        for declarator in &var_decl.declarations {
+            let Pattern::Ident { name, .. } = &declarator.pattern.value else {
+                continue;
+            };
+            let name = &name.value.name;
+            let (Some((global, _)), Some(info)) = (self.module_globals.get(name).cloned(), self.lookup_var(name).cloned()) else {
+                continue;
+            };
+            let box_local = if info.is_boxed {
+                info.local_id
+            } else {
+                self.ensure_extern("zaco_box_new", vec![IrType::Ptr], IrType::Ptr);
+                let box_local = ctx.add_local(IrType::Ptr);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_local(box_local)),
+                    func: Value::Const(Constant::Str("zaco_box_new".to_string())),
+                    args: vec![Value::Local(info.local_id)],
+                });
+                self.define_var(name, VarInfo { local_id: box_local, ir_type: info.ir_type.clone(), is_boxed: true });
+                box_local
+            };
+            ctx.emit(Instruction::Store {
+                ptr: Value::Const(Constant::GlobalAddr(global.clone())),
+                value: Value::Local(box_local),
+            });
+            self.module_globals.insert(name.clone(), (global, info.ir_type));
+        }
+    }
+
+    /// At the entry of a function declaration or class member: bind the
+    /// module-level variables `stmts` mention to the boxes in their globals.
+    /// Parameters and hoisted `var`s, already in scope, shadow them.
+    fn bind_module_globals(&mut self, ctx: &mut FuncCtx, stmts: &[Node<Stmt>]) {
+        if self.module_globals.is_empty() {
+            return;
+        }
+        let mut names: Vec<String> = Vec::new();
+        for stmt in stmts {
+            stmt.value.for_each_ident(&mut |ident| {
+                if !names.contains(&ident.name) {
+                    names.push(ident.name.clone());
+                }
+            });
+        }
+        for name in names {
+            let Some((global, ir_type)) = self.module_globals.get(&name).cloned() else {
+                continue;
+            };
+            if self.scopes.last().is_some_and(|scope| scope.vars.contains_key(&name)) {
+                continue;
+            }
+            let box_local = ctx.add_local(IrType::Ptr);
+            ctx.emit(Instruction::Load {
+                dest: Place::from_local(box_local),
+                ptr: Value::Const(Constant::GlobalAddr(global)),
+            });
+            self.define_var(&name, VarInfo { local_id: box_local, ir_type, is_boxed: true });
+        }
+    }
+
+    /// Bind imported names to their module. Type-only specifiers have no
+    /// run-time value and are skipped, as are names the module exports only
+    /// as types, which are remembered so that using them is an error.
+    fn lower_import(&mut self, import_decl: &ImportDecl) {
+        let source = &import_decl.source;
+        for spec in &import_decl.specifiers {
+            if import_decl.is_type_specifier(spec) {
+                continue;
+            }
+            match spec {
+                ImportSpecifier::Named { imported, local, .. } => {
+                    let local_name = local.as_ref().unwrap_or(imported).value.name.clone();
+                    if self.type_only_exports.get(source).is_some_and(|names| names.contains(&imported.value.name)) {
+                        self.type_only_bindings.insert(local_name, (source.clone(), imported.span));
+                        continue;
+                    }
+                    if let Some(exports) = self.module_exports.get(source) {
+                        let name = &imported.value.name;
+                        if let Some((params, ret)) = exports.functions.get(name) {
+                            self.imported_functions.insert(local_name.clone(), (name.clone(), params.clone(), ret.clone()));
+                        }
+                        if let Some(literal) = exports.constants.get(name) {
+                            self.imported_constants.insert(local_name.clone(), literal.clone());
+                        }
+                    }
+                    self.imported_bindings.insert(local_name, source.clone());
+                }
+                ImportSpecifier::Default(ident) => {
+                    self.imported_bindings.insert(ident.value.name.clone(), source.clone());
+                }
+                ImportSpecifier::Namespace(ident) => {
+                    self.imported_bindings.insert(ident.value.name.clone(), source.clone());
+                }
+            }
+        }
+    }
+
+    /// Report a run-time use of a name imported from a type-only export,
+    /// at the import that named it. Returns whether `name` is such an import.
+    fn check_type_only_use(&mut self, name: &str) -> bool {
+        let Some((source, span)) = self.type_only_bindings.remove(name) else {
+            return false;
+        };
+        self.errors.push(LowerError::new(
+            format!(
+                "'{}' is exported from '{}' as a type only and cannot be used as a value; import it with `import type`",
+                name, source
+            ),
+            span,
+        ));
+        true
+    }
 
     fn lower_export(&mut self, ctx: &mut FuncCtx, export_decl: &ExportDecl) {
         match export_decl {
             ExportDecl::Decl(decl) => {
                 // Lower the declaration normally
                 self.lower_decl(ctx, &decl.value, &decl.span);
+                if let Decl::Var(var_decl) = &decl.value {
+                    self.publish_module_globals(ctx, var_decl);
+                }
                 // Mark the last added function as public
                 if let Decl::Function(func_decl) = &decl.value {
                     let name = &func_decl.name.value.name;
@@ -403,6 +1398,10 @@ impl Lowerer {
     }
 
     fn lower_decl(&mut self, ctx: &mut FuncCtx, decl: &Decl, span: &Span) {
+        self.traced("decl", decl, span, |this| this.lower_decl_node(ctx, decl, span))
+    }
+
+    fn lower_decl_node(&mut self, ctx: &mut FuncCtx, decl: &Decl, span: &Span) {
         match decl {
             Decl::Var(var_decl) => {
                 self.lower_var_decl(ctx, var_decl, span);
@@ -412,17 +1411,379 @@ impl Lowerer {
             }
             Decl::Class(class_decl) => {
                 self.lower_class_decl(ctx, class_decl, span);
+                self.lower_class_decorators(ctx, class_decl);
+            }
+            Decl::Module(module) => {
+                self.lower_namespace(ctx, None, module);
             }
             Decl::Interface(_)
             | Decl::TypeAlias(_)
-            | Decl::Enum(_)
-            | Decl::Module(_) => {
+            | Decl::Enum(_) => {
                 // Type-level declarations — skip for codegen
             }
         }
     }
 
+    /// Run a class's decorators where the class is defined. The decorator
+    /// expressions are evaluated first, in source order, so factories such
+    /// as `@tag("x")` run once each. Then each decorated target has its
+    /// decorators called, nearest first, with the target and a context
+    /// object: a class decorator gets the constructor, a method decorator
+    /// the method and a field decorator `undefined`. A function returned by
+    /// a class or method decorator replaces the target, for the decorators
+    /// after it and for every later `new` or call.
+    fn lower_class_decorators(&mut self, ctx: &mut FuncCtx, class_decl: &ClassDecl) {
+        let applications = class_decl.decorator_applications();
+        let mut factories: Vec<&Node<Expr>> = applications
+            .iter()
+            .map(|application| application.decorator)
+            .filter(|decorator| matches!(decorator.value, Expr::Call { .. }))
+            .collect();
+        factories.sort_by_key(|decorator| decorator.span.start);
+        let factory_name = |decorator: &Node<Expr>| format!("__decorator_{}", decorator.span.start);
+        for decorator in factories {
+            let binding = VarDecl {
+                kind: VarDeclKind::Const,
+                declarations: vec![VarDeclarator {
+                    pattern: Node::new(
+                        Pattern::Ident {
+                            name: Node::new(Ident::new(factory_name(decorator)), decorator.span),
+                            type_annotation: None,
+                            ownership: None,
+                        },
+                        decorator.span,
+                    ),
+                    init: Some(decorator.clone()),
+                }],
+            };
+            self.lower_var_decl(ctx, &binding, &decorator.span);
+        }
+
+        let class_name = &class_decl.name.value.name;
+        let same_target = |a: &DecoratorApplication, b: &DecoratorApplication| {
+            a.kind == b.kind && a.member == b.member && a.is_static == b.is_static
+        };
+        for stack in applications.chunk_by(same_target) {
+            let name = match stack[0].member {
+                Some(member) => self.property_name_to_string(member),
+                None => class_name.clone(),
+            };
+            let target = match stack[0].kind {
+                DecoratorKind::Class => self.replaceable_constructor(class_name),
+                DecoratorKind::Method => self.replaceable_method(class_name, &name, stack[0].is_static),
+                DecoratorKind::Field => None,
+            };
+            // The target as a closure, replaced by what each decorator returns
+            let current = target.as_ref().map(|(func_name, sig, _)| {
+                self.ensure_extern("zaco_closure_new", vec![IrType::Ptr, IrType::Ptr], IrType::Ptr);
+                let current = ctx.add_local(IrType::FuncPtr(sig.clone()));
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_local(current)),
+                    func: Value::Const(Constant::Str("zaco_closure_new".to_string())),
+                    args: vec![Value::Const(Constant::FuncAddr(func_name.clone())), Value::Const(Constant::Null)],
+                });
+                current
+            });
+
+            for application in stack {
+                let span = &application.decorator.span;
+                let string = |value: &str| Node::new(Expr::Literal(Literal::String(value.to_string())), *span);
+                let boolean = |value: bool| Node::new(Expr::Literal(Literal::Boolean(value)), *span);
+                let property = |key: &str, value: Node<Expr>| ObjectProperty::Property {
+                    key: PropertyName::String(key.to_string()),
+                    value,
+                    shorthand: false,
+                };
+                let target_arg = match (current, &target) {
+                    (Some(current), Some((_, sig, _))) => {
+                        let binding = format!("__decorated_{}", span.start);
+                        self.define_var(&binding, VarInfo {
+                            local_id: current,
+                            ir_type: IrType::FuncPtr(sig.clone()),
+                            is_boxed: false,
+                        });
+                        Node::new(Expr::Ident(Ident::new(binding)), *span)
+                    }
+                    _ => Node::new(Expr::Literal(Literal::Undefined), *span),
+                };
+                let mut context = vec![property("kind", string(application.kind.as_str())), property("name", string(&name))];
+                if application.kind != DecoratorKind::Class {
+                    context.push(property("static", boolean(application.is_static)));
+                    context.push(property("private", boolean(application.is_private)));
+                }
+                let callee = match application.decorator.value {
+                    Expr::Call { .. } => Node::new(Expr::Ident(Ident::new(factory_name(application.decorator))), *span),
+                    _ => application.decorator.clone(),
+                };
+                let call = Expr::Call {
+                    callee: Box::new(callee),
+                    type_args: None,
+                    args: vec![target_arg, Node::new(Expr::Object(context), *span)],
+                };
+                let replacement = self.lower_expr(ctx, &call, span);
+                if let (Some(current), Some(replacement)) = (current, replacement) {
+                    self.assign_unless_nullish(ctx, current, replacement);
+                }
+            }
+
+            if let (Some(current), Some((_, _, slot))) = (current, target) {
+                ctx.emit(Instruction::Store {
+                    ptr: Value::Const(Constant::GlobalAddr(slot)),
+                    value: Value::Local(current),
+                });
+            }
+        }
+    }
+
+    /// `target = value`, unless `value` is null or undefined, as a decorator
+    /// that returns nothing leaves it
+    fn assign_unless_nullish(&mut self, ctx: &mut FuncCtx, target: LocalId, value: Value) {
+        let mut checks = Vec::new();
+        for nullish in [Constant::Null, Constant::Undefined] {
+            let check = ctx.add_temp(IrType::Bool);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(check),
+                value: RValue::BinaryOp { op: BinOp::Ne, left: value.clone(), right: Value::Const(nullish) },
+            });
+            checks.push(Value::Temp(check));
+        }
+        let defined = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(defined),
+            value: RValue::BinaryOp { op: BinOp::And, left: checks[0].clone(), right: checks[1].clone() },
+        });
+        let assign_block = ctx.new_block();
+        let done = ctx.new_block();
+        ctx.set_terminator(Terminator::Branch { cond: Value::Temp(defined), then_block: assign_block, else_block: done });
+        ctx.switch_to(assign_block);
+        ctx.emit(Instruction::Assign { dest: Place::from_local(target), value: RValue::Use(value) });
+        ctx.set_terminator(Terminator::Jump(done));
+        ctx.switch_to(done);
+    }
+
+    /// The constructor a class decorator receives, its signature and the
+    /// global `new` calls it through (set up by `lower_class_decl`)
+    fn replaceable_constructor(&self, class_name: &str) -> Option<(String, FuncSignature, String)> {
+        let func_name = format!("{}_constructor", class_name);
+        let constructor = self.module.find_function(&func_name)?;
+        let sig = FuncSignature {
+            params: constructor.params.iter().map(|(_, ty)| ty.clone()).collect(),
+            return_type: Box::new(constructor.return_type.clone()),
+        };
+        let slot = self.class_info.get(class_name)?.constructor_slot.clone()?;
+        Some((func_name, sig, slot))
+    }
+
+    /// Let decorators replace a method: its body moves to `C_m__original`
+    /// and `C_m`, which every call site names, becomes a dispatcher calling
+    /// the closure held in the global `__C_m_impl`. Closures take no
+    /// receiver, so for an instance method the dispatcher leaves it in
+    /// `__C_m_this` for `C_m__value`, the closure decorators receive, which
+    /// calls the body with it. The dispatcher puts back the receiver it
+    /// found, so calls nested inside a replacement see theirs, but a throw
+    /// out of the method skips that and threads share the global.
+    /// Returns the function decorators receive, its signature and the
+    /// global holding the current closure.
+    fn replaceable_method(&mut self, class_name: &str, method_name: &str, is_static: bool) -> Option<(String, FuncSignature, String)> {
+        let func_name = format!("{}_{}", class_name, method_name);
+        let original_name = format!("{}__original", func_name);
+        let original = self.module.functions.iter_mut().find(|f| f.name == func_name)?;
+        original.name = original_name.clone();
+        let params = original.params.clone();
+        let return_type = original.return_type.clone();
+        let receiver = (!is_static).then(|| params[0].1.clone());
+        let arg_types: Vec<IrType> = params[receiver.is_some() as usize..].iter().map(|(_, ty)| ty.clone()).collect();
+        let sig = FuncSignature { params: arg_types.clone(), return_type: Box::new(return_type.clone()) };
+        let slot = format!("__{}_impl", func_name);
+        let this_slot = format!("__{}_this", func_name);
+        self.module.add_global(slot.clone(), IrType::Ptr, None);
+        if receiver.is_some() {
+            self.module.add_global(this_slot.clone(), IrType::Ptr, None);
+        }
+
+        let mut dispatcher = IrFunction::new(self.alloc_func_id(), func_name.clone(), params.clone(), return_type.clone());
+        let entry = dispatcher.new_block();
+        dispatcher.entry_block = entry;
+        let mut dctx = FuncCtx { func: &mut dispatcher, current_block: entry };
+        let previous = receiver.as_ref().map(|receiver| {
+            let previous = dctx.add_temp(receiver.clone());
+            dctx.emit(Instruction::Load {
+                dest: Place::from_temp(previous),
+                ptr: Value::Const(Constant::GlobalAddr(this_slot.clone())),
+            });
+            dctx.emit(Instruction::Store {
+                ptr: Value::Const(Constant::GlobalAddr(this_slot.clone())),
+                value: Value::Local(LocalId(0)),
+            });
+            previous
+        });
+        let closure = dctx.add_temp(IrType::Ptr);
+        dctx.emit(Instruction::Load { dest: Place::from_temp(closure), ptr: Value::Const(Constant::GlobalAddr(slot.clone())) });
+        let args: Vec<Value> = params[receiver.is_some() as usize..].iter().map(|(id, _)| Value::Local(*id)).collect();
+        let result = self.emit_indirect_call(&mut dctx, Value::Temp(closure), &sig, &args);
+        if let Some(previous) = previous {
+            dctx.emit(Instruction::Store {
+                ptr: Value::Const(Constant::GlobalAddr(this_slot.clone())),
+                value: Value::Temp(previous),
+            });
+        }
+        dctx.set_terminator(Terminator::Return(result));
+        self.module.add_function(dispatcher);
+
+        let Some(receiver) = receiver else {
+            return Some((original_name, sig, slot));
+        };
+        let value_name = format!("{}__value", func_name);
+        let value_params: Vec<(LocalId, IrType)> = arg_types.into_iter().enumerate().map(|(i, ty)| (LocalId(i), ty)).collect();
+        let mut value_func = IrFunction::new(self.alloc_func_id(), value_name.clone(), value_params.clone(), return_type.clone());
+        let entry = value_func.new_block();
+        value_func.entry_block = entry;
+        let mut vctx = FuncCtx { func: &mut value_func, current_block: entry };
+        let this = vctx.add_temp(receiver);
+        vctx.emit(Instruction::Load { dest: Place::from_temp(this), ptr: Value::Const(Constant::GlobalAddr(this_slot)) });
+        let result = (return_type != IrType::Void).then(|| vctx.add_temp(return_type));
+        vctx.emit(Instruction::Call {
+            dest: result.map(Place::from_temp),
+            func: Value::Const(Constant::Str(original_name)),
+            args: std::iter::once(Value::Temp(this)).chain(value_params.iter().map(|(id, _)| Value::Local(*id))).collect(),
+        });
+        vctx.set_terminator(Terminator::Return(result.map(Value::Temp)));
+        self.module.add_function(value_func);
+        Some((value_name, sig, slot))
+    }
+
+    /// Record a namespace's exported members, so uses of them anywhere in
+    /// the module resolve, including ones lowered before the namespace.
+    /// Ambient namespaces and modules named by a string have no run-time part.
+    fn register_namespace(&mut self, parent: Option<&str>, module: &ModuleDecl) {
+        let ModuleName::Ident(name) = &module.name else { return };
+        if module.is_declare {
+            return;
+        }
+        let path = parent.map_or_else(|| name.value.name.clone(), |parent| format!("{}.{}", parent, name.value.name));
+        let mut members = HashMap::new();
+        let items = match &module.body {
+            ModuleBody::Block(items) => items,
+            ModuleBody::Namespace(nested) => {
+                self.namespaces.insert(path.clone(), members);
+                self.register_namespace(Some(&path), &nested.value);
+                return;
+            }
+        };
+        for item in items {
+            let (decl, exported) = match &item.value {
+                ModuleItem::Decl(decl) => (&decl.value, false),
+                ModuleItem::Export(ExportDecl::Decl(decl)) => (&decl.value, true),
+                _ => continue,
+            };
+            match decl {
+                Decl::Function(func_decl) if exported => {
+                    let func_name = &func_decl.name.value.name;
+                    members.insert(func_name.clone(), NamespaceMember::Function(Self::namespace_function_name(&path, func_name)));
+                }
+                Decl::Var(var_decl) if exported => {
+                    for declarator in &var_decl.declarations {
+                        let Pattern::Ident { name: var_name, .. } = &declarator.pattern.value else { continue };
+                        let var_name = var_name.value.name.clone();
+                        let member = match declarator.init.as_ref().map(|init| &init.value) {
+                            Some(Expr::Literal(literal @ (Literal::Number(_) | Literal::String(_) | Literal::Boolean(_))))
+                                if var_decl.kind == VarDeclKind::Const =>
+                            {
+                                NamespaceMember::Literal(literal.clone())
+                            }
+                            _ => NamespaceMember::Binding(format!("{}.{}", path, var_name)),
+                        };
+                        members.insert(var_name, member);
+                    }
+                }
+                Decl::Module(nested) => self.register_namespace(Some(&path), nested),
+                _ => {}
+            }
+        }
+        self.namespaces.insert(path, members);
+    }
+
+    /// Lower a namespace body in place. Its functions become module-level
+    /// functions with mangled names, its other statements run in a scope of
+    /// their own, and its exported variables stay reachable as `<path>.<name>`.
+    fn lower_namespace(&mut self, ctx: &mut FuncCtx, parent: Option<&str>, module: &ModuleDecl) {
+        let ModuleName::Ident(name) = &module.name else { return };
+        if module.is_declare {
+            return;
+        }
+        let path = parent.map_or_else(|| name.value.name.clone(), |parent| format!("{}.{}", parent, name.value.name));
+        let items = match &module.body {
+            ModuleBody::Block(items) => items,
+            ModuleBody::Namespace(nested) => {
+                self.lower_namespace(ctx, Some(&path), &nested.value);
+                return;
+            }
+        };
+
+        let functions = items
+            .iter()
+            .filter_map(|item| match Self::namespace_item_decl(&item.value)?.value {
+                Decl::Function(ref func_decl) => Some(&func_decl.name.value.name),
+                _ => None,
+            })
+            .map(|func_name| (func_name.clone(), Self::namespace_function_name(&path, func_name)))
+            .collect();
+        self.namespace_scopes.push((path.clone(), functions));
+        self.push_scope();
+
+        for item in items {
+            if let ModuleItem::Stmt(stmt) = &item.value {
+                self.lower_stmt(ctx, &stmt.value, &stmt.span);
+                continue;
+            }
+            let Some(decl) = Self::namespace_item_decl(&item.value) else { continue };
+            match &decl.value {
+                Decl::Function(func_decl) => {
+                    let mut func_decl = func_decl.clone();
+                    func_decl.name.value.name = Self::namespace_function_name(&path, &func_decl.name.value.name);
+                    self.lower_function_decl(ctx, &func_decl, &decl.span);
+                }
+                Decl::Module(nested) => self.lower_namespace(ctx, Some(&path), nested),
+                other => self.lower_decl(ctx, other, &decl.span),
+            }
+        }
+
+        let bindings: Vec<(String, VarInfo)> = self
+            .namespaces
+            .get(&path)
+            .into_iter()
+            .flatten()
+            .filter_map(|(member, how)| match how {
+                NamespaceMember::Binding(key) => Some((key.clone(), self.lookup_var(member)?.clone())),
+                _ => None,
+            })
+            .collect();
+        self.pop_scope();
+        self.namespace_scopes.pop();
+        for (key, info) in bindings {
+            self.define_var(&key, info);
+        }
+    }
+
+    /// The declaration of a namespace body item, exported or not
+    fn namespace_item_decl(item: &ModuleItem) -> Option<&Node<Decl>> {
+        match item {
+            ModuleItem::Decl(decl) => Some(decl),
+            ModuleItem::Export(ExportDecl::Decl(decl)) => Some(decl),
+            _ => None,
+        }
+    }
+
+    /// Name a function declared in namespace `path` is lowered under
+    fn namespace_function_name(path: &str, name: &str) -> String {
+        format!("{}_{}", path.replace('.', "_"), name)
+    }
+
     fn lower_stmt(&mut self, ctx: &mut FuncCtx, stmt: &Stmt, span: &Span) {
+        self.traced("stmt", stmt, span, |this| this.lower_stmt_node(ctx, stmt, span))
+    }
+
+    fn lower_stmt_node(&mut self, ctx: &mut FuncCtx, stmt: &Stmt, span: &Span) {
         match stmt {
             Stmt::Expr(expr_node) => {
                 // Lower expression for side effects
@@ -431,10 +1792,28 @@ impl Lowerer {
             Stmt::VarDecl(var_decl) => {
                 self.lower_var_decl(ctx, var_decl, span);
             }
+            Stmt::Return(opt_expr) if self.current_generator.is_some() => {
+                let val = match opt_expr {
+                    Some(expr_node) => self.lower_expr(ctx, &expr_node.value, &expr_node.span),
+                    None => None,
+                };
+                self.lower_generator_return(ctx, val);
+            }
+            Stmt::Return(opt_expr) if self.async_promise.is_some() => {
+                let val = match opt_expr {
+                    Some(expr_node) => self.lower_expr(ctx, &expr_node.value, &expr_node.span),
+                    None => None,
+                };
+                self.lower_async_return(ctx, val);
+            }
             Stmt::Return(opt_expr) => {
+                let returns_void = self.current_function.as_ref().is_some_and(|(_, ret)| *ret == IrType::Void);
                 if let Some(expr_node) = opt_expr {
                     if let Some(val) = self.lower_expr(ctx, &expr_node.value, &expr_node.span) {
-                        ctx.set_terminator(Terminator::Return(Some(val)));
+                        // `return log()` in a void function returns nothing
+                        let val = (!returns_void || self.infer_expr_type(&expr_node.value) != IrType::Void)
+                            .then(|| self.lower_returned_closure(ctx, val));
+                        ctx.set_terminator(Terminator::Return(val));
                     }
                 } else {
                     ctx.set_terminator(Terminator::Return(None));
@@ -504,149 +1883,344 @@ impl Lowerer {
                 left,
                 right,
                 body,
-                ..
+                is_await,
             } => {
-                self.lower_for_of(ctx, left, right, body, span);
+                self.lower_for_of(ctx, left, right, body, *is_await, span);
             }
+            Stmt::DoWhile { .. } => self.unsupported("a do...while loop", span),
+            Stmt::Labeled { .. } => self.unsupported("a labeled statement", span),
             Stmt::Empty | Stmt::Debugger => {}
-            _ => {
-                // Other statements not yet implemented
-            }
         }
     }
 
     fn lower_var_decl(&mut self, ctx: &mut FuncCtx, var_decl: &VarDecl, _span: &Span) {
         for declarator in &var_decl.declarations {
-            match &declarator.pattern.value {
-                Pattern::Ident { name, .. } => {
-                    let name = name.value.name.clone();
-                    let ir_type = if let Some(ref init) = declarator.init {
-                        self.infer_expr_type(&init.value)
-                    } else {
-                        IrType::F64
-                    };
-                    let local_id = ctx.add_local(ir_type.clone());
-                    self.define_var(&name, VarInfo { local_id, ir_type, is_boxed: false });
-                    if let Some(ref init) = declarator.init {
-                        if let Some(val) = self.lower_expr(ctx, &init.value, &init.span) {
-                            if let Value::Const(Constant::Str(ref func_name)) = val {
-                                if let Some(closure_info) = self.closure_bindings.get(func_name).cloned() {
-                                    self.closure_bindings.insert(name.clone(), closure_info);
-                                }
-                            }
-                            ctx.emit(Instruction::Assign {
-                                dest: Place::from_local(local_id),
-                                value: RValue::Use(val),
-                            });
-                        }
-                    }
+            if var_decl.kind == VarDeclKind::Var && self.lower_hoisted_declarator(ctx, declarator) {
+                continue;
+            }
+            self.lower_declarator(ctx, &declarator.pattern, declarator.init.as_ref());
+        }
+    }
+
+    /// Allocate the `var`s of a function body (or the top level) at its
+    /// entry, so they outlive the block they are written in and can be read
+    /// before their declaration runs. Until then they read as `undefined`:
+    /// for a number, the NaN the runtime prints as such. Parameters of the
+    /// same name are kept.
+    fn hoist_vars<'a>(&mut self, ctx: &mut FuncCtx, stmts: impl IntoIterator<Item = &'a Node<Stmt>>) {
+        let mut decls = Vec::new();
+        for stmt in stmts {
+            stmt.value.collect_hoisted_vars(&mut decls);
+        }
+        for declarator in decls.into_iter().flat_map(|decl| &decl.declarations) {
+            let Pattern::Ident { name, type_annotation, .. } = &declarator.pattern.value else {
+                continue;
+            };
+            let name = &name.value.name;
+            if self.scopes.last().is_some_and(|scope| scope.vars.contains_key(name)) {
+                continue;
+            }
+            let ir_type = match (type_annotation, &declarator.init) {
+                (Some(annotation), _) => self.ast_type_to_ir(&annotation.value),
+                (None, Some(init)) => Self::storage_type(self.infer_expr_type(&init.value)),
+                (None, None) => IrType::F64,
+            };
+            let initial = match ir_type {
+                IrType::F64 => Constant::F64(f64::from_bits(UNDEFINED_F64_BITS)),
+                IrType::I64 => Constant::I64(0),
+                IrType::Bool => Constant::Bool(false),
+                _ => Constant::Undefined,
+            };
+            let local_id = ctx.add_local(ir_type.clone());
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_local(local_id),
+                value: RValue::Use(Value::Const(initial)),
+            });
+            self.define_var(name, VarInfo { local_id, ir_type, is_boxed: false });
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.hoisted_vars.insert(name.clone(), ctx.func.id);
+            }
+        }
+    }
+
+    /// `var name = init` for a `var` hoisted by `hoist_vars`: assign to the
+    /// hoisted variable. Returns false to lower the declarator as a fresh
+    /// binding instead, which is needed when the value has a different type
+    /// than the one hoisted or is a closure.
+    fn lower_hoisted_declarator(&mut self, ctx: &mut FuncCtx, declarator: &VarDeclarator) -> bool {
+        let Pattern::Ident { name, type_annotation, .. } = &declarator.pattern.value else {
+            return false;
+        };
+        let name = &name.value.name;
+        let Some(scope) = self.scopes.iter().rev().find(|scope| scope.vars.contains_key(name)) else {
+            return false;
+        };
+        if scope.hoisted_vars.get(name) != Some(&ctx.func.id) {
+            return false;
+        }
+        let hoisted_type = scope.vars[name].ir_type.clone();
+        let Some(init) = &declarator.init else {
+            // A `var` without a value leaves the variable as it is
+            return true;
+        };
+        if matches!(init.value, Expr::Arrow { .. } | Expr::Function { .. }) || matches!(hoisted_type, IrType::FuncPtr(_)) {
+            return false;
+        }
+        let init_type = match type_annotation {
+            Some(annotation) => self.ast_type_to_ir(&annotation.value),
+            None => Self::storage_type(self.infer_expr_type(&init.value)),
+        };
+        if init_type != hoisted_type {
+            return false;
+        }
+        let assignment = Expr::Assignment {
+            target: Box::new(Node::new(Expr::Ident(Ident::new(name.clone())), declarator.pattern.span)),
+            op: AssignmentOp::Assign,
+            value: Box::new(init.clone()),
+        };
+        self.lower_expr(ctx, &assignment, &init.span);
+        true
+    }
+
+    /// One declarator of a `let`/`const`/`var`/`using` statement or a
+    /// for-loop initializer: evaluate the initializer, then bind the pattern.
+    /// Earlier declarators of the statement are already bound.
+    fn lower_declarator(&mut self, ctx: &mut FuncCtx, pattern: &Node<Pattern>, init: Option<&Node<Expr>>) {
+        match &pattern.value {
+            Pattern::Ident { name, type_annotation, .. } => {
+                self.lower_ident_declarator(ctx, &name.value.name, type_annotation.as_deref(), init);
+            }
+            Pattern::Object { .. } | Pattern::Array { .. } => {
+                let Some(init) = init else { return };
+                if let Some(value) = self.lower_expr(ctx, &init.value, &init.span) {
+                    self.bind_pattern(ctx, pattern, value, &init.value);
                 }
-                Pattern::Object { properties, .. } => {
-                    let init_val = declarator.init.as_ref().and_then(|init| {
-                        self.lower_expr(ctx, &init.value, &init.span)
-                    });
-                    let obj_val = match init_val {
-                        Some(v) => v,
-                        None => continue,
-                    };
-                    let obj_local = ctx.add_local(IrType::Ptr);
-                    ctx.emit(Instruction::Assign {
-                        dest: Place::from_local(obj_local),
-                        value: RValue::Use(obj_val),
-                    });
-                    for prop in properties {
-                        let key_str = match &prop.key {
-                            PropertyName::Ident(ident) => ident.value.name.clone(),
-                            PropertyName::String(s) => s.clone(),
-                            PropertyName::Number(n) => format!("{}", n),
-                            PropertyName::Computed(_) => continue,
-                        };
-                        let var_name = match &prop.value.value {
-                            Pattern::Ident { name, .. } => name.value.name.clone(),
-                            _ => continue,
-                        };
-                        let ir_type = IrType::F64;
-                        self.ensure_extern("zaco_object_get_f64", vec![IrType::Ptr, IrType::Ptr], ir_type.clone());
-                        self.module.intern_string(key_str.clone());
-                        let key_val = Value::Const(Constant::Str(key_str));
-                        let result_temp = ctx.add_temp(ir_type.clone());
+            }
+            Pattern::Assignment { .. } => self.unsupported("a default value in a destructuring pattern", &pattern.span),
+        }
+    }
+
+    /// The type of a variable holding a value of type `ty`. A call that
+    /// returns nothing evaluates to undefined, which is held as a pointer.
+    fn storage_type(ty: IrType) -> IrType {
+        if ty == IrType::Void { IrType::Ptr } else { ty }
+    }
+
+    /// `name = init`: the variable is in scope before its initializer is
+    /// lowered, so a closure can refer to itself
+    fn lower_ident_declarator(
+        &mut self,
+        ctx: &mut FuncCtx,
+        name: &str,
+        type_annotation: Option<&Node<Type>>,
+        init: Option<&Node<Expr>>,
+    ) {
+        // An array annotation says what an empty literal will hold
+        let ir_type = match (type_annotation, init) {
+            (Some(ann @ Node { value: Type::Array(_), .. }), _) => self.ast_type_to_ir(&ann.value),
+            (_, Some(init)) => Self::storage_type(self.infer_expr_type(&init.value)),
+            (_, None) => IrType::F64,
+        };
+        let local_id = ctx.add_local(ir_type.clone());
+        self.define_var(name, VarInfo { local_id, ir_type, is_boxed: false });
+        self.track_object_var(name, type_annotation, init);
+        let Some(init) = init else { return };
+        if Self::unwrap_function_expr(&init.value).is_some() {
+            self.closure_binding_name = Some(name.to_string());
+        }
+        let val = self.lower_expr(ctx, &init.value, &init.span);
+        self.closure_binding_name = None;
+        let Some(val) = val else { return };
+        if let Value::Const(Constant::Str(ref func_name)) = val {
+            if let Some(closure_info) = self.closure_bindings.get(func_name).cloned() {
+                self.closure_bindings.insert(name.to_string(), closure_info);
+            }
+        }
+        // `const f = make()` where make returns a closure: the call
+        // produced a closure value, which lives in f; calls to f go
+        // straight to the closure's function with the value's env.
+        // An arrow function's closure is recorded under its function
+        // name rather than the binding's.
+        if let Expr::Call { callee, .. } = &init.value {
+            if let Expr::Ident(callee_ident) = &callee.value {
+                let returned = self.returned_closures.get(&callee_ident.name).or_else(|| {
+                    let callee_closure = self.closure_bindings.get(&callee_ident.name)?;
+                    self.returned_closures.get(&callee_closure.func_name)
+                });
+                if let Some(mut closure_info) = returned.cloned() {
+                    closure_info.env_local = closure_info.env_struct_id.map(|_| {
+                        self.ensure_extern("zaco_closure_env", vec![IrType::Ptr], IrType::Ptr);
+                        let env_local = ctx.add_local(IrType::Ptr);
                         ctx.emit(Instruction::Call {
-                            dest: Some(Place::from_temp(result_temp)),
-                            func: Value::Const(Constant::Str("zaco_object_get_f64".to_string())),
-                            args: vec![Value::Local(obj_local), key_val],
-                        });
-                        let local_id = ctx.add_local(ir_type.clone());
-                        self.define_var(&var_name, VarInfo { local_id, ir_type, is_boxed: false });
-                        ctx.emit(Instruction::Assign {
-                            dest: Place::from_local(local_id),
-                            value: RValue::Use(Value::Temp(result_temp)),
+                            dest: Some(Place::from_local(env_local)),
+                            func: Value::Const(Constant::Str("zaco_closure_env".to_string())),
+                            args: vec![val.clone()],
                         });
-                    }
-                }
-                Pattern::Array { elements, .. } => {
-                    let init_val = declarator.init.as_ref().and_then(|init| {
-                        self.lower_expr(ctx, &init.value, &init.span)
-                    });
-                    let arr_val = match init_val {
-                        Some(v) => v,
-                        None => continue,
-                    };
-                    let arr_local = ctx.add_local(IrType::Ptr);
-                    ctx.emit(Instruction::Assign {
-                        dest: Place::from_local(arr_local),
-                        value: RValue::Use(arr_val),
+                        env_local
                     });
-                    self.ensure_extern("zaco_array_get_f64", vec![IrType::Ptr, IrType::I64], IrType::F64);
-                    for (i, elem) in elements.iter().enumerate() {
-                        let pat = match elem {
-                            Some(pat_node) => pat_node,
-                            None => continue,
-                        };
-                        let var_name = match &pat.value {
-                            Pattern::Ident { name, .. } => name.value.name.clone(),
-                            _ => continue,
-                        };
-                        let ir_type = IrType::F64;
-                        let idx_val = Value::Const(Constant::I64(i as i64));
-                        let result_temp = ctx.add_temp(ir_type.clone());
-                        ctx.emit(Instruction::Call {
-                            dest: Some(Place::from_temp(result_temp)),
-                            func: Value::Const(Constant::Str("zaco_array_get_f64".to_string())),
-                            args: vec![Value::Local(arr_local), idx_val],
-                        });
-                        let local_id = ctx.add_local(ir_type.clone());
-                        self.define_var(&var_name, VarInfo { local_id, ir_type, is_boxed: false });
-                        ctx.emit(Instruction::Assign {
-                            dest: Place::from_local(local_id),
-                            value: RValue::Use(Value::Temp(result_temp)),
-                        });
-                    }
+                    self.closure_bindings.insert(name.to_string(), closure_info);
                 }
-                _ => continue,
             }
         }
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_local(local_id),
+            value: RValue::Use(val),
+        });
     }
 
-    /// Lower an expression, returning the IR value it produces.
-    fn lower_expr(&mut self, ctx: &mut FuncCtx, expr: &Expr, span: &Span) -> Option<Value> {
-        match expr {
-            Expr::Literal(lit) => self.lower_literal(ctx, lit, span),
-
-            Expr::Ident(ident) => {
-                // Handle __dirname and __filename globals
-                match ident.name.as_str() {
-                    "__dirname" => {
-                        let dir = self.file_path.as_ref()
-                            .and_then(|p| {
-                                std::path::Path::new(p).parent()
-                                    .map(|d| d.to_string_lossy().into_owned())
-                            })
-                            .unwrap_or_else(|| ".".to_string());
-                        return Some(Value::Const(Constant::Str(dir)));
+    /// Bind a destructuring pattern to `value`, nested patterns included.
+    /// `source` is the expression `value` came from; it is only consulted
+    /// for types, never lowered again.
+    fn bind_pattern(&mut self, ctx: &mut FuncCtx, pattern: &Node<Pattern>, value: Value, source: &Expr) {
+        let holder = ctx.add_local(IrType::Ptr);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_local(holder),
+            value: RValue::Use(value),
+        });
+        match &pattern.value {
+            Pattern::Object { properties, .. } => {
+                for prop in properties {
+                    let key_str = match &prop.key {
+                        PropertyName::Ident(ident) => ident.value.name.clone(),
+                        PropertyName::String(s) => s.clone(),
+                        PropertyName::Number(n) => format!("{}", n),
+                        PropertyName::Computed(_) => {
+                            self.unsupported("a computed key in a destructuring pattern", &prop.value.span);
+                            continue;
+                        }
+                    };
+                    let field_source = Self::destructured_property_source(source, &key_str, &prop.value.span);
+                    // Fields of an object of known shape keep their types
+                    let ir_type = match &prop.value.value {
+                        Pattern::Ident { .. } => self.object_field_type(source, &key_str).unwrap_or(IrType::F64),
+                        Pattern::Object { .. } | Pattern::Array { .. } => self.infer_expr_type(&field_source),
+                        Pattern::Assignment { .. } => {
+                            self.unsupported("a default value in a destructuring pattern", &prop.value.span);
+                            continue;
+                        }
+                    };
+                    let field = self.lower_object_get(ctx, Value::Local(holder), &key_str, ir_type.clone());
+                    match &prop.value.value {
+                        Pattern::Ident { name, .. } => {
+                            let local_id = ctx.add_local(ir_type.clone());
+                            self.define_var(&name.value.name, VarInfo { local_id, ir_type, is_boxed: false });
+                            ctx.emit(Instruction::Assign {
+                                dest: Place::from_local(local_id),
+                                value: RValue::Use(field),
+                            });
+                        }
+                        _ => self.bind_pattern(ctx, &prop.value, field, &field_source),
+                    }
+                }
+            }
+            Pattern::Array { elements, .. } => {
+                for (i, elem) in elements.iter().enumerate() {
+                    let Some(pat) = elem else { continue };
+                    let idx_val = Value::Const(Constant::I64(i as i64));
+                    match &pat.value {
+                        Pattern::Ident { name, type_annotation, .. } => {
+                            let var_name = name.value.name.clone();
+                            let value_type = type_annotation
+                                .as_deref()
+                                .map(|ty| ty.value.clone())
+                                .or_else(|| self.destructured_value_type(source, i));
+                            let ir_type = match &value_type {
+                                Some(ty) => self.ast_type_to_ir(ty),
+                                None => self.destructured_element_type(source, i),
+                            };
+                            let value = self.emit_array_element(ctx, Value::Local(holder), idx_val, ir_type.clone());
+                            let local_id = ctx.add_local(ir_type.clone());
+                            self.define_var(&var_name, VarInfo { local_id, ir_type, is_boxed: false });
+                            self.track_object_var(&var_name, None, None);
+                            if let Some(ty) = value_type {
+                                self.value_types.insert(var_name.clone(), ty);
+                            }
+                            ctx.emit(Instruction::Assign {
+                                dest: Place::from_local(local_id),
+                                value: RValue::Use(value),
+                            });
+                        }
+                        Pattern::Object { .. } | Pattern::Array { .. } => {
+                            let ir_type = self.destructured_element_type(source, i);
+                            let value = self.emit_array_element(ctx, Value::Local(holder), idx_val, ir_type);
+                            let elem_source = Self::destructured_element_source(source, i, &pat.span);
+                            self.bind_pattern(ctx, pat, value, &elem_source);
+                        }
+                        Pattern::Assignment { .. } => {
+                            self.unsupported("a default value in a destructuring pattern", &pat.span)
+                        }
                     }
-                    "__filename" => {
-                        let path = self.file_path.clone()
-                            .unwrap_or_else(|| "<unknown>".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The expression for property `key` of a destructured `source`: the
+    /// property's value in an object literal, `source.key` otherwise
+    fn destructured_property_source(source: &Expr, key: &str, span: &Span) -> Expr {
+        if let Expr::Object(props) = source {
+            let value = props.iter().rev().find_map(|prop| match prop {
+                ObjectProperty::Property { key: PropertyName::Ident(ident), value, .. } if ident.value.name == key => {
+                    Some(value)
+                }
+                ObjectProperty::Property { key: PropertyName::String(name), value, .. } if name == key => Some(value),
+                _ => None,
+            });
+            if let Some(value) = value {
+                return value.value.clone();
+            }
+        }
+        Expr::Member {
+            object: Box::new(Node::new(source.clone(), *span)),
+            property: Node::new(Ident::new(key), *span),
+            computed: false,
+        }
+    }
+
+    /// The expression for element `index` of a destructured `source`: the
+    /// element of an array literal, `source[index]` otherwise
+    fn destructured_element_source(source: &Expr, index: usize, span: &Span) -> Expr {
+        if let Expr::Array(elems) = source {
+            let has_spread = elems.iter().flatten().any(|elem| matches!(elem.value, Expr::Spread(_)));
+            if let (false, Some(Some(elem))) = (has_spread, elems.get(index)) {
+                return elem.value.clone();
+            }
+        }
+        Expr::Index {
+            object: Box::new(Node::new(source.clone(), *span)),
+            index: Box::new(Node::new(Expr::Literal(Literal::Number(index as f64)), *span)),
+        }
+    }
+
+    /// Lower an expression, returning the IR value it produces.
+    fn lower_expr(&mut self, ctx: &mut FuncCtx, expr: &Expr, span: &Span) -> Option<Value> {
+        self.traced("expr", expr, span, |this| this.lower_expr_node(ctx, expr, span))
+    }
+
+    fn lower_expr_node(&mut self, ctx: &mut FuncCtx, expr: &Expr, span: &Span) -> Option<Value> {
+        if let Some(expr) = self
+            .strip_global_this(expr)
+            .or_else(|| self.symbol_member(expr))
+            .or_else(|| self.namespace_member(expr))
+            .or_else(|| self.array_of_literal(expr))
+            .or_else(|| self.define_literal(expr))
+        {
+            return self.lower_expr(ctx, &expr, span);
+        }
+        // `Array(n)` / `new Array(n)`: n empty slots
+        if let Some(([length], _)) = self.array_constructor(expr) {
+            return self.lower_array_with_length(ctx, length);
+        }
+        match expr {
+            Expr::Literal(lit) => self.lower_literal(ctx, lit, span),
+
+            Expr::Ident(ident) => {
+                // Handle __dirname and __filename globals
+                match ident.name.as_str() {
+                    "__dirname" | "__filename" => {
+                        let path = if ident.name == "__dirname" { self.module_dirname() } else { self.module_filename() };
+                        self.module.intern_string(path.clone());
                         return Some(Value::Const(Constant::Str(path)));
                     }
                     _ => {}
@@ -668,6 +2242,17 @@ impl Lowerer {
                     }
                 } else {
                     // Unknown identifier — might be a global like `console`
+                    self.check_type_only_use(&ident.name);
+                    if let Some(module) = self.imported_bindings.get(&ident.name).filter(|m| self.module_exports.contains_key(*m)) {
+                        let construct = format!("using `{}` imported from \"{}\" as a value", ident.name, module);
+                        self.unsupported(&construct, span);
+                    }
+                    if self.enums.get(&ident.name).is_some_and(|info| info.is_const) {
+                        self.errors.push(LowerError::new(
+                            format!("const enum '{}' has no run-time object; only its members can be used", ident.name),
+                            *span,
+                        ));
+                    }
                     None
                 }
             }
@@ -686,7 +2271,19 @@ impl Lowerer {
                 value,
             } => self.lower_assignment(ctx, target, *op, value, span),
 
-            Expr::Call { callee, args, .. } => self.lower_call(ctx, callee, args, span),
+            Expr::Call { callee, args, .. } => {
+                let result = match &callee.value {
+                    Expr::OptionalMember { object, property } => {
+                        self.lower_optional_method_call(ctx, object, property, args, span)
+                    }
+                    _ => self.lower_call(ctx, callee, args, span),
+                };
+                // A call returning nothing still has a value: undefined.
+                // Returning None would drop the enclosing expression.
+                result.or_else(|| {
+                    (self.infer_expr_type(expr) == IrType::Void).then_some(Value::Const(Constant::Undefined))
+                })
+            }
 
             Expr::Member { object, property, .. } => {
                 self.lower_member_expr(ctx, object, property, span)
@@ -694,6 +2291,20 @@ impl Lowerer {
 
             Expr::Paren(inner) => self.lower_expr(ctx, &inner.value, &inner.span),
 
+            // `expr!`, `as` and `satisfies` only affect type checking
+            Expr::NonNullAssertion(inner)
+            | Expr::TypeCast { expr: inner, .. }
+            | Expr::Satisfies { expr: inner, .. } => self.lower_expr(ctx, &inner.value, &inner.span),
+
+            Expr::Sequence(exprs) => {
+                // Evaluate left to right for side effects; the last one is the value
+                let mut result = None;
+                for expr in exprs {
+                    result = self.lower_expr(ctx, &expr.value, &expr.span);
+                }
+                result
+            }
+
             Expr::Template { parts, exprs } => self.lower_template(ctx, parts, exprs, span),
 
             Expr::Array(elements) => self.lower_array_literal(ctx, elements, span),
@@ -706,20 +2317,20 @@ impl Lowerer {
 
             Expr::This => self.lower_this_expr(),
 
-            Expr::Arrow { params, body, return_type, .. } => {
-                self.lower_arrow_expr(ctx, params, return_type.as_deref(), body, span)
+            Expr::Arrow { params, body, return_type, is_async, .. } => {
+                self.lower_arrow_expr(ctx, params, return_type.as_deref(), body, *is_async, span)
             }
 
-            Expr::Function { name, params, return_type, body, .. } => {
-                self.lower_function_expr(ctx, name.as_ref(), params, return_type.as_deref(), body, span)
+            Expr::Function { params, return_type, body, is_async, .. } => {
+                self.lower_function_expr(ctx, params, return_type.as_deref(), body, *is_async, span)
             }
 
             Expr::Ternary { condition, then_expr, else_expr } => {
                 self.lower_ternary(ctx, condition, then_expr, else_expr, span)
             }
 
-            Expr::TaggedTemplate { tag, parts, exprs } => {
-                self.lower_tagged_template(ctx, tag, parts, exprs, span)
+            Expr::TaggedTemplate { tag, parts, raw, exprs } => {
+                self.lower_tagged_template(ctx, tag, parts, raw, exprs, span)
             }
 
             Expr::Yield { argument, delegate } => {
@@ -738,8 +2349,23 @@ impl Lowerer {
                 self.lower_optional_index(ctx, object, index, span)
             }
 
-            _ => {
-                // Unsupported expression
+            Expr::Index { object, index } => self.lower_index(ctx, object, index),
+
+            Expr::Clone(_) => {
+                self.unsupported("`clone`", span);
+                None
+            }
+            Expr::MetaProperty { meta, property } => {
+                self.unsupported(&format!("`{}.{}`", meta.value.name, property.value.name), span);
+                None
+            }
+            // Calls, member accesses and literals handle these themselves
+            Expr::Super => {
+                self.unsupported("`super` outside a call or member access", span);
+                None
+            }
+            Expr::Spread(_) => {
+                self.unsupported("spread outside a call, array or object", span);
                 None
             }
         }
@@ -747,7 +2373,7 @@ impl Lowerer {
 
     fn lower_literal(
         &mut self,
-        _ctx: &mut FuncCtx,
+        ctx: &mut FuncCtx,
         lit: &Literal,
         _span: &Span,
     ) -> Option<Value> {
@@ -763,8 +2389,23 @@ impl Lowerer {
             }
             Literal::Boolean(b) => Some(Value::Const(Constant::Bool(*b))),
             Literal::Null => Some(Value::Const(Constant::Null)),
-            Literal::Undefined => Some(Value::Const(Constant::Null)),
-            Literal::RegExp { .. } => None,
+            Literal::Undefined => Some(Value::Const(Constant::Undefined)),
+            Literal::RegExp { pattern, flags } => {
+                // Each evaluation compiles a fresh regex handle
+                self.module.intern_string(pattern.clone());
+                self.module.intern_string(flags.clone());
+                self.ensure_extern("zaco_regex_new", vec![IrType::Str, IrType::Str], IrType::Ptr);
+                let regex = ctx.add_temp(IrType::Ptr);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(regex)),
+                    func: Value::Const(Constant::Str("zaco_regex_new".to_string())),
+                    args: vec![
+                        Value::Const(Constant::Str(pattern.clone())),
+                        Value::Const(Constant::Str(flags.clone())),
+                    ],
+                });
+                Some(Value::Temp(regex))
+            }
         }
     }
 
@@ -774,7 +2415,7 @@ impl Lowerer {
         left: &Node<Expr>,
         op: BinaryOp,
         right: &Node<Expr>,
-        _span: &Span,
+        span: &Span,
     ) -> Option<Value> {
         // Handle short-circuit logical operators before evaluating right side
         if matches!(op, BinaryOp::And | BinaryOp::Or) {
@@ -786,46 +2427,47 @@ impl Lowerer {
             return self.lower_nullish_coalesce(ctx, left, right);
         }
 
+        // `#name in obj` brand check: resolved from obj's static class, whose
+        // layout already includes every private member it was built with
+        if let (BinaryOp::In, Expr::Ident(ident)) = (op, &left.value) {
+            if ident.name.starts_with('#') {
+                self.lower_expr(ctx, &right.value, &right.span);
+                let branded = match self.infer_expr_type(&right.value) {
+                    IrType::Struct(struct_id) => self
+                        .class_name_for_struct(struct_id)
+                        .and_then(|class| self.class_info.get(&class))
+                        .is_some_and(|ci| {
+                            ci.fields.iter().any(|(n, _)| *n == ident.name) || ci.methods.contains(&ident.name)
+                        }),
+                    _ => false,
+                };
+                return Some(Value::Const(Constant::Bool(branded)));
+            }
+        }
+
         let lhs = self.lower_expr(ctx, &left.value, &left.span)?;
         let rhs = self.lower_expr(ctx, &right.value, &right.span)?;
 
+        // Class instances with `valueOf()` take part in arithmetic, string
+        // concatenation and ordering through its result
+        let left_ty = self.infer_expr_type(&left.value);
+        let right_ty = self.infer_expr_type(&right.value);
+        let left_operand = (lhs.clone(), left_ty.clone());
+        if let Some(result) = self.lower_value_of_binary(ctx, op, left_operand, (rhs.clone(), right_ty.clone())) {
+            return Some(result);
+        }
+
         // Check if this is string concatenation
-        if matches!(op, BinaryOp::Add) {
-            let left_ty = self.infer_expr_type(&left.value);
-            let right_ty = self.infer_expr_type(&right.value);
-            if left_ty == IrType::Str || right_ty == IrType::Str {
-                // Convert non-string operands to strings
-                let lhs_str = if left_ty != IrType::Str {
-                    self.ensure_extern("zaco_f64_to_str", vec![IrType::F64], IrType::Str);
-                    let conv_temp = ctx.add_temp(IrType::Str);
-                    ctx.emit(Instruction::Call {
-                        dest: Some(Place::from_temp(conv_temp)),
-                        func: Value::Const(Constant::Str("zaco_f64_to_str".to_string())),
-                        args: vec![lhs],
-                    });
-                    Value::Temp(conv_temp)
-                } else {
-                    lhs
-                };
-                let rhs_str = if right_ty != IrType::Str {
-                    self.ensure_extern("zaco_f64_to_str", vec![IrType::F64], IrType::Str);
-                    let conv_temp = ctx.add_temp(IrType::Str);
-                    ctx.emit(Instruction::Call {
-                        dest: Some(Place::from_temp(conv_temp)),
-                        func: Value::Const(Constant::Str("zaco_f64_to_str".to_string())),
-                        args: vec![rhs],
-                    });
-                    Value::Temp(conv_temp)
-                } else {
-                    rhs
-                };
-                let temp = ctx.add_temp(IrType::Str);
-                ctx.emit(Instruction::Assign {
-                    dest: Place::from_temp(temp),
-                    value: RValue::StrConcat(vec![lhs_str, rhs_str]),
-                });
-                return Some(Value::Temp(temp));
-            }
+        if op == BinaryOp::Add && (left_ty == IrType::Str || right_ty == IrType::Str) {
+            // Convert non-string operands to strings
+            let lhs_str = self.concat_operand(ctx, lhs, &left_ty);
+            let rhs_str = self.concat_operand(ctx, rhs, &right_ty);
+            let temp = ctx.add_temp(IrType::Str);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(temp),
+                value: RValue::StrConcat(vec![lhs_str, rhs_str]),
+            });
+            return Some(Value::Temp(temp));
         }
 
         // Handle string equality/inequality via runtime call
@@ -867,15 +2509,74 @@ impl Lowerer {
             }
         }
 
-        // `in` operator: check if property exists in object
-        // Placeholder: both operands evaluated for side effects, returns false
+        // Loose equality of mixed types coerces like JS
+        if matches!(op, BinaryOp::Eq | BinaryOp::NotEq) {
+            let left_ty = self.infer_expr_type(&left.value);
+            let right_ty = self.infer_expr_type(&right.value);
+            if let Some(eq) = self.lower_loose_eq_mixed(ctx, lhs.clone(), &left_ty, rhs.clone(), &right_ty) {
+                return Some(if op == BinaryOp::NotEq { Self::emit_not(ctx, eq) } else { eq });
+            }
+        }
+
+        // Strict equality never holds across primitive kinds; integer and
+        // float numbers compare as numbers
+        if matches!(op, BinaryOp::StrictEq | BinaryOp::StrictNotEq) {
+            let left_ty = self.infer_expr_type(&left.value);
+            let right_ty = self.infer_expr_type(&right.value);
+            let kind = |ty: &IrType| match ty {
+                IrType::F64 | IrType::I64 => 0,
+                IrType::Bool => 1,
+                _ => 2,
+            };
+            if kind(&left_ty) != kind(&right_ty) {
+                return Some(Value::Const(Constant::Bool(op == BinaryOp::StrictNotEq)));
+            }
+            if kind(&left_ty) == 0 && left_ty != right_ty {
+                let eq = self.lower_loose_eq_mixed(ctx, lhs, &left_ty, rhs, &right_ty)?;
+                return Some(if op == BinaryOp::StrictNotEq { Self::emit_not(ctx, eq) } else { eq });
+            }
+        }
+
+        // Loose equality of references: null == undefined
+        if matches!(op, BinaryOp::Eq | BinaryOp::NotEq) {
+            let left_ty = self.infer_expr_type(&left.value);
+            let right_ty = self.infer_expr_type(&right.value);
+            if left_ty.is_pointer() && right_ty.is_pointer() {
+                self.ensure_extern("zaco_loose_eq_ptr", vec![IrType::Ptr, IrType::Ptr], IrType::Bool);
+                let eq_temp = ctx.add_temp(IrType::Bool);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(eq_temp)),
+                    func: Value::Const(Constant::Str("zaco_loose_eq_ptr".to_string())),
+                    args: vec![lhs, rhs],
+                });
+                if op == BinaryOp::NotEq {
+                    return Some(Self::emit_not(ctx, Value::Temp(eq_temp)));
+                }
+                return Some(Value::Temp(eq_temp));
+            }
+        }
+
+        // `key in obj`: the key is converted to a string, as property keys
+        // are. Arrays have `length` and their indices; a class instance has
+        // a fixed set of names, and other objects are looked up at run time.
         if matches!(op, BinaryOp::In) {
-            self.ensure_extern("zaco_obj_has_prop", vec![IrType::Ptr, IrType::Str], IrType::Bool);
+            let key = self.concat_operand(ctx, lhs, &left_ty);
+            let (func, args) = match &right_ty {
+                IrType::Array(_) => ("zaco_array_has_key", vec![rhs, key]),
+                IrType::Struct(struct_id) => {
+                    let names = self.instance_property_names(*struct_id).join("\n");
+                    self.module.intern_string(names.clone());
+                    ("zaco_key_in_names", vec![key, Value::Const(Constant::Str(names))])
+                }
+                _ => ("zaco_obj_has_prop", vec![rhs, key]),
+            };
+            let param = if func == "zaco_key_in_names" { IrType::Str } else { IrType::Ptr };
+            self.ensure_extern(func, vec![param, IrType::Str], IrType::Bool);
             let temp = ctx.add_temp(IrType::Bool);
             ctx.emit(Instruction::Call {
                 dest: Some(Place::from_temp(temp)),
-                func: Value::Const(Constant::Str("zaco_obj_has_prop".to_string())),
-                args: vec![rhs, lhs],
+                func: Value::Const(Constant::Str(func.to_string())),
+                args,
             });
             return Some(Value::Temp(temp));
         }
@@ -893,6 +2594,28 @@ impl Lowerer {
             return Some(Value::Temp(temp));
         }
 
+        // `a ** b` is `Math.pow(a, b)`
+        if op == BinaryOp::Pow {
+            let mut args = Vec::new();
+            for (value, ty) in [(lhs, left_ty), (rhs, right_ty)] {
+                if ty == IrType::F64 {
+                    args.push(value);
+                    continue;
+                }
+                let num = ctx.add_temp(IrType::F64);
+                ctx.emit(Instruction::Assign { dest: Place::from_temp(num), value: RValue::Cast { value, ty: IrType::F64 } });
+                args.push(Value::Temp(num));
+            }
+            self.ensure_extern("zaco_math_pow", vec![IrType::F64, IrType::F64], IrType::F64);
+            let temp = ctx.add_temp(IrType::F64);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(temp)),
+                func: Value::Const(Constant::Str("zaco_math_pow".to_string())),
+                args,
+            });
+            return Some(Value::Temp(temp));
+        }
+
         let ir_op = match op {
             BinaryOp::Add => BinOp::Add,
             BinaryOp::Sub => BinOp::Sub,
@@ -910,7 +2633,10 @@ impl Lowerer {
             BinaryOp::BitXor => BinOp::BitXor,
             BinaryOp::LeftShift => BinOp::Shl,
             BinaryOp::RightShift => BinOp::Shr,
-            _ => return None, // Pow, >>> not yet handled
+            _ => {
+                self.unsupported(&format!("the `{}` operator", op), span);
+                return None;
+            }
         };
 
         let result_type = if matches!(
@@ -934,6 +2660,191 @@ impl Lowerer {
         Some(Value::Temp(temp))
     }
 
+    /// An operand of string concatenation as a string: numbers are
+    /// formatted and class instances go through `toString()`.
+    fn concat_operand(&mut self, ctx: &mut FuncCtx, val: Value, ty: &IrType) -> Value {
+        match ty {
+            IrType::Str => val,
+            IrType::I64 | IrType::Struct(_) => self.stringify_value(ctx, val, ty),
+            _ => self.stringify_value(ctx, val, &IrType::F64),
+        }
+    }
+
+    /// The public property names of an instance of the class laid out as
+    /// `struct_id`: its fields, methods and accessors, inherited ones
+    /// included
+    fn instance_property_names(&self, struct_id: StructId) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        let mut class = self.class_name_for_struct(struct_id);
+        while let Some(ci) = class.as_ref().and_then(|name| self.class_info.get(name)) {
+            let own = ci.fields.iter().map(|(name, _)| name).chain(&ci.methods).chain(&ci.getters).chain(&ci.setters);
+            for name in own {
+                if !name.starts_with('#') && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            class = ci.parent.clone();
+        }
+        names
+    }
+
+    /// `a op b` where an operand is an instance of a class with `valueOf()`:
+    /// that operand is replaced by its `valueOf()` result, then the
+    /// operation runs on the primitive values. Returns None when neither
+    /// operand has `valueOf()`, and for equality, which compares identity.
+    fn lower_value_of_binary(
+        &mut self,
+        ctx: &mut FuncCtx,
+        op: BinaryOp,
+        (lhs, left_ty): (Value, IrType),
+        (rhs, right_ty): (Value, IrType),
+    ) -> Option<Value> {
+        let ir_op = match op {
+            BinaryOp::Add => BinOp::Add,
+            BinaryOp::Sub => BinOp::Sub,
+            BinaryOp::Mul => BinOp::Mul,
+            BinaryOp::Div => BinOp::Div,
+            BinaryOp::Mod => BinOp::Mod,
+            BinaryOp::Lt => BinOp::Lt,
+            BinaryOp::LtEq => BinOp::Le,
+            BinaryOp::Gt => BinOp::Gt,
+            BinaryOp::GtEq => BinOp::Ge,
+            _ => return None,
+        };
+        let value_of_class = |this: &Self, ty: &IrType| match ty {
+            IrType::Struct(struct_id) => this
+                .class_name_for_struct(*struct_id)
+                .filter(|class| this.class_info.get(class).is_some_and(|ci| ci.methods.iter().any(|m| m == "valueOf"))),
+            _ => None,
+        };
+        if value_of_class(self, &left_ty).is_none() && value_of_class(self, &right_ty).is_none() {
+            return None;
+        }
+        let mut operands = Vec::new();
+        for (val, ty) in [(lhs, left_ty), (rhs, right_ty)] {
+            let Some(class) = value_of_class(self, &ty) else {
+                operands.push((val, ty));
+                continue;
+            };
+            let ret_type = self
+                .class_info
+                .get(&class)
+                .and_then(|ci| ci.method_returns.iter().find(|(m, _)| m == "valueOf"))
+                .map(|(_, ret)| self.ast_type_to_ir(ret))
+                .unwrap_or(IrType::F64);
+            let result = ctx.add_temp(ret_type.clone());
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str(format!("{}_valueOf", class))),
+                args: vec![val],
+            });
+            operands.push((Value::Temp(result), ret_type));
+        }
+        let (rhs, right_ty) = operands.pop()?;
+        let (lhs, left_ty) = operands.pop()?;
+
+        if ir_op == BinOp::Add && (left_ty == IrType::Str || right_ty == IrType::Str) {
+            let lhs_str = self.concat_operand(ctx, lhs, &left_ty);
+            let rhs_str = self.concat_operand(ctx, rhs, &right_ty);
+            let temp = ctx.add_temp(IrType::Str);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(temp),
+                value: RValue::StrConcat(vec![lhs_str, rhs_str]),
+            });
+            return Some(Value::Temp(temp));
+        }
+
+        let mut to_f64 = |val: Value, ty: &IrType| {
+            if *ty == IrType::F64 {
+                return val;
+            }
+            let temp = ctx.add_temp(IrType::F64);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(temp),
+                value: RValue::Cast { value: val, ty: IrType::F64 },
+            });
+            Value::Temp(temp)
+        };
+        let left = to_f64(lhs, &left_ty);
+        let right = to_f64(rhs, &right_ty);
+        let result_type = match ir_op {
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => IrType::Bool,
+            _ => IrType::F64,
+        };
+        let temp = ctx.add_temp(result_type);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(temp),
+            value: RValue::BinaryOp { op: ir_op, left, right },
+        });
+        Some(Value::Temp(temp))
+    }
+
+    /// `a == b` for operands of different primitive types. Booleans compare
+    /// as numbers, a number and a string compare through `zaco_loose_eq`
+    /// (ToNumber of the string), and a primitive never equals null or
+    /// undefined. Returns None when the types need no coercion.
+    fn lower_loose_eq_mixed(
+        &mut self,
+        ctx: &mut FuncCtx,
+        lhs: Value,
+        left_ty: &IrType,
+        rhs: Value,
+        right_ty: &IrType,
+    ) -> Option<Value> {
+        let is_num = |ty: &IrType| matches!(ty, IrType::F64 | IrType::I64 | IrType::Bool);
+        let to_f64 = |ctx: &mut FuncCtx, val: Value, ty: &IrType| {
+            if *ty == IrType::F64 {
+                return val;
+            }
+            let temp = ctx.add_temp(IrType::F64);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(temp),
+                value: RValue::Cast { value: val, ty: IrType::F64 },
+            });
+            Value::Temp(temp)
+        };
+        let mixed_numbers = is_num(left_ty) && is_num(right_ty) && left_ty != right_ty;
+        if is_num(left_ty) == is_num(right_ty) && !mixed_numbers {
+            return None;
+        }
+        let result = ctx.add_temp(IrType::Bool);
+        match (is_num(left_ty), is_num(right_ty)) {
+            (true, true) => {
+                let left = to_f64(ctx, lhs, left_ty);
+                let right = to_f64(ctx, rhs, right_ty);
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(result),
+                    value: RValue::BinaryOp { op: BinOp::Eq, left, right },
+                });
+            }
+            (true, false) | (false, true) => {
+                let (num, num_ty, other, other_ty) =
+                    if is_num(left_ty) { (lhs, left_ty, rhs, right_ty) } else { (rhs, right_ty, lhs, left_ty) };
+                if *other_ty != IrType::Str {
+                    return Some(Value::Const(Constant::Bool(false)));
+                }
+                let num = to_f64(ctx, num, num_ty);
+                self.ensure_extern("zaco_loose_eq", vec![IrType::F64, IrType::Str], IrType::Bool);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(result)),
+                    func: Value::Const(Constant::Str("zaco_loose_eq".to_string())),
+                    args: vec![num, other],
+                });
+            }
+            _ => unreachable!("same-kind operands were returned above"),
+        }
+        Some(Value::Temp(result))
+    }
+
+    fn emit_not(ctx: &mut FuncCtx, val: Value) -> Value {
+        let result = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(result),
+            value: RValue::UnaryOp { op: UnOp::Not, operand: val },
+        });
+        Value::Temp(result)
+    }
+
     /// Lower short-circuit logical operators (&&, ||).
     ///
     /// `a && b`: If `a` is falsy, return `a`. Otherwise return `b`.
@@ -1008,7 +2919,13 @@ impl Lowerer {
     ) -> Option<Value> {
         let lhs = self.lower_expr(ctx, &left.value, &left.span)?;
 
+        // Unboxed numbers and booleans are never null or undefined, so the
+        // right side is never evaluated (`0 ?? 1` and `false ?? true` keep
+        // the left side)
         let result_type = self.infer_expr_type(&left.value);
+        if !result_type.is_pointer() {
+            return Some(lhs);
+        }
         let result_local = ctx.add_local(result_type.clone());
 
         // Store LHS as initial result
@@ -1045,14 +2962,13 @@ impl Lowerer {
     }
 
     /// Emit a null check for a value, returning a boolean Value that is true if the value is null.
-    /// For pointer types (Ptr, Str, Struct, Array, FuncPtr, Promise): compare with 0/null.
+    /// For pointer types (Ptr, Str, Struct, Array, FuncPtr, Promise): null or undefined.
     /// For other types: compare with 0 (as i64).
-    fn emit_null_check(&self, ctx: &mut FuncCtx, val: Value, ty: &IrType) -> Value {
-        let null_val = if ty.is_pointer() {
-            Value::Const(Constant::Null)
-        } else {
-            Value::Const(Constant::I64(0))
-        };
+    fn emit_null_check(&mut self, ctx: &mut FuncCtx, val: Value, ty: &IrType) -> Value {
+        if ty.is_pointer() {
+            return self.emit_is_nullish(ctx, val);
+        }
+        let null_val = Value::Const(Constant::I64(0));
         let cmp_temp = ctx.add_temp(IrType::Bool);
         ctx.emit(Instruction::Assign {
             dest: Place::from_temp(cmp_temp),
@@ -1065,30 +2981,142 @@ impl Lowerer {
         Value::Temp(cmp_temp)
     }
 
-    fn lower_unary(
-        &mut self,
-        ctx: &mut FuncCtx,
-        op: UnaryOp,
-        operand: &Node<Expr>,
-        _span: &Span,
-    ) -> Option<Value> {
-        let val = self.lower_expr(ctx, &operand.value, &operand.span)?;
-
-        // void: evaluate operand for side effects, return undefined (null)
-        if op == UnaryOp::Void {
-            return Some(Value::Const(Constant::Null));
-        }
+    /// Call `zaco_is_nullish`: true for both null and the undefined sentinel.
+    fn emit_is_nullish(&mut self, ctx: &mut FuncCtx, val: Value) -> Value {
+        self.ensure_extern("zaco_is_nullish", vec![IrType::Ptr], IrType::Bool);
+        let temp = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(temp)),
+            func: Value::Const(Constant::Str("zaco_is_nullish".to_string())),
+            args: vec![val],
+        });
+        Value::Temp(temp)
+    }
 
-        // delete: evaluate operand for side effects, return true
-        if op == UnaryOp::Delete {
-            return Some(Value::Const(Constant::Bool(true)));
+    /// Lower `typeof`. Scalars are known statically; references may hold
+    /// null ("object") or undefined, so those ask the runtime.
+    fn lower_typeof(&mut self, ctx: &mut FuncCtx, val: Value, ty: &IrType) -> Value {
+        let kind = match ty {
+            IrType::F64 | IrType::I64 => "number",
+            IrType::Bool => "boolean",
+            IrType::Str => "string",
+            IrType::FuncPtr(_) => "function",
+            _ => "object",
+        };
+        self.module.intern_string(kind.to_string());
+        if !ty.is_pointer() {
+            return Value::Const(Constant::Str(kind.to_string()));
+        }
+        self.ensure_extern("zaco_typeof", vec![IrType::Ptr, IrType::Str], IrType::Str);
+        let temp = ctx.add_temp(IrType::Str);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(temp)),
+            func: Value::Const(Constant::Str("zaco_typeof".to_string())),
+            args: vec![val, Value::Const(Constant::Str(kind.to_string()))],
+        });
+        Value::Temp(temp)
+    }
+
+    /// Lower `delete obj.key` / `delete obj[key]`. A dynamic object loses
+    /// the property; an array element becomes a hole. Class instances have
+    /// a fixed layout, and the type checker rejects deleting their fields.
+    /// Deleting anything but a property evaluates it and yields true.
+    fn lower_delete(&mut self, ctx: &mut FuncCtx, operand: &Node<Expr>) -> Option<Value> {
+        let (object, key, key_ty) = match &operand.value {
+            Expr::Member { object, property, .. } | Expr::OptionalMember { object, property } => {
+                self.module.intern_string(property.value.name.clone());
+                (object, Value::Const(Constant::Str(property.value.name.clone())), IrType::Str)
+            }
+            Expr::Index { object, index } => {
+                let obj = self.lower_expr(ctx, &object.value, &object.span)?;
+                let key = self.lower_expr(ctx, &index.value, &index.span)?;
+                return self.emit_delete(ctx, (obj, self.infer_expr_type(&object.value)), key, self.infer_expr_type(&index.value));
+            }
+            _ => {
+                self.lower_expr(ctx, &operand.value, &operand.span);
+                return Some(Value::Const(Constant::Bool(true)));
+            }
+        };
+        let obj = self.lower_expr(ctx, &object.value, &object.span)?;
+        self.emit_delete(ctx, (obj, self.infer_expr_type(&object.value)), key, key_ty)
+    }
+
+    /// Remove `key` from `object` (see `lower_delete`)
+    fn emit_delete(&mut self, ctx: &mut FuncCtx, (object, object_ty): (Value, IrType), key: Value, key_ty: IrType) -> Option<Value> {
+        let (func, params, args) = match object_ty {
+            IrType::Array(elem) if matches!(key_ty, IrType::F64 | IrType::I64) => {
+                let index = if key_ty == IrType::I64 {
+                    let temp = ctx.add_temp(IrType::F64);
+                    ctx.emit(Instruction::Assign {
+                        dest: Place::from_temp(temp),
+                        value: RValue::Cast { value: key, ty: IrType::F64 },
+                    });
+                    Value::Temp(temp)
+                } else {
+                    key
+                };
+                let pointer_elems = Value::Const(Constant::I64(elem.is_pointer() as i64));
+                ("zaco_array_delete", vec![IrType::Ptr, IrType::F64, IrType::I64], vec![object, index, pointer_elems])
+            }
+            IrType::Array(_) | IrType::Struct(_) => return Some(Value::Const(Constant::Bool(true))),
+            _ => {
+                let key = self.concat_operand(ctx, key, &key_ty);
+                ("zaco_object_delete", vec![IrType::Ptr, IrType::Str], vec![object, key])
+            }
+        };
+        self.ensure_extern(func, params, IrType::Bool);
+        let temp = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(temp)),
+            func: Value::Const(Constant::Str(func.to_string())),
+            args,
+        });
+        Some(Value::Temp(temp))
+    }
+
+    fn lower_unary(
+        &mut self,
+        ctx: &mut FuncCtx,
+        op: UnaryOp,
+        operand: &Node<Expr>,
+        span: &Span,
+    ) -> Option<Value> {
+        if matches!(
+            op,
+            UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement
+        ) {
+            return self.lower_update(ctx, op, operand, span);
+        }
+
+        if op == UnaryOp::Delete {
+            return self.lower_delete(ctx, operand);
+        }
+
+        let mut val = self.lower_expr(ctx, &operand.value, &operand.span)?;
+
+        // void: evaluate operand for side effects, return undefined
+        if op == UnaryOp::Void {
+            return Some(Value::Const(Constant::Undefined));
+        }
+
+        if op == UnaryOp::TypeOf {
+            let ty = self.infer_expr_type(&operand.value);
+            return Some(self.lower_typeof(ctx, val, &ty));
+        }
+
+        // !ref: null and undefined are the falsy references
+        if op == UnaryOp::Not {
+            let ty = self.infer_expr_type(&operand.value);
+            if ty.is_pointer() {
+                val = self.truthiness(ctx, val, &ty);
+            }
         }
 
         let ir_op = match op {
             UnaryOp::Minus => UnOp::Neg,
             UnaryOp::Not => UnOp::Not,
             UnaryOp::BitNot => UnOp::BitNot,
-            _ => return None, // typeof, ++, -- not yet handled
+            _ => return None, // ++, -- handled above
         };
 
         let result_type = match ir_op {
@@ -1104,17 +3132,84 @@ impl Lowerer {
         Some(Value::Temp(temp))
     }
 
+    /// Lower `++x`, `x++`, `--x`, `x--` as `x = x ± 1`, reusing the assignment
+    /// path so boxed captures and member targets are written back correctly.
+    fn lower_update(
+        &mut self,
+        ctx: &mut FuncCtx,
+        op: UnaryOp,
+        operand: &Node<Expr>,
+        span: &Span,
+    ) -> Option<Value> {
+        let bin_op = match op {
+            UnaryOp::PreIncrement | UnaryOp::PostIncrement => BinaryOp::Add,
+            _ => BinaryOp::Sub,
+        };
+        let is_postfix = matches!(op, UnaryOp::PostIncrement | UnaryOp::PostDecrement);
+        // The operand is read and then written: evaluate its object and
+        // index once, so `a[i++]++` bumps `i` once
+        let operand = &self.bind_target(ctx, operand);
+
+        // Postfix yields the value before the update; snapshot it into a temp
+        // since a plain local would observe the write-back.
+        let old_val = if is_postfix {
+            let current = self.lower_expr(ctx, &operand.value, &operand.span)?;
+            let temp = ctx.add_temp(self.infer_expr_type(&operand.value));
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(temp),
+                value: RValue::Use(current),
+            });
+            Some(Value::Temp(temp))
+        } else {
+            None
+        };
+
+        let updated = Node::new(
+            Expr::Binary {
+                left: Box::new(operand.clone()),
+                op: bin_op,
+                right: Box::new(Node::new(Expr::Literal(Literal::Number(1.0)), *span)),
+            },
+            *span,
+        );
+        let new_val = self.lower_assignment(ctx, operand, AssignmentOp::Assign, &updated, span)?;
+
+        Some(old_val.unwrap_or(new_val))
+    }
+
     fn lower_assignment(
         &mut self,
         ctx: &mut FuncCtx,
         target: &Node<Expr>,
         op: AssignmentOp,
         value: &Node<Expr>,
-        _span: &Span,
+        span: &Span,
     ) -> Option<Value> {
         // Handle nullish assignment (??=) before evaluating RHS
         if op == AssignmentOp::NullishAssign {
-            return self.lower_nullish_assign(ctx, target, value);
+            return self.lower_nullish_assign(ctx, target, value, span);
+        }
+        if matches!(op, AssignmentOp::AndAssign | AssignmentOp::OrAssign) {
+            self.unsupported("`&&=` and `||=`", span);
+            return None;
+        }
+
+        // The target's object and index are evaluated once, before the value
+        let target = &self.bind_target(ctx, target);
+
+        // `o.x op= v` and `a[i] op= v` read and write through the bound
+        // target: `o.x = o.x op v`, as do the operators without an IR
+        // counterpart, `**=` and `>>>=`
+        if !matches!(target.value, Expr::Ident(_))
+            || matches!(op, AssignmentOp::PowAssign | AssignmentOp::UnsignedRightShiftAssign)
+        {
+            if let Some(bin_op) = Self::compound_binary_op(op) {
+                let updated = Node::new(
+                    Expr::Binary { left: Box::new(target.clone()), op: bin_op, right: Box::new(value.clone()) },
+                    value.span,
+                );
+                return self.lower_assignment(ctx, target, AssignmentOp::Assign, &updated, &value.span);
+            }
         }
 
         let rhs = self.lower_expr(ctx, &value.value, &value.span)?;
@@ -1124,10 +3219,19 @@ impl Lowerer {
             return self.lower_member_assignment(ctx, object, property, op, rhs);
         }
 
+        // Handle element assignment: arr[i] = value
+        if let (Expr::Index { object, index }, AssignmentOp::Assign) = (&target.value, op) {
+            let rhs_type = self.infer_expr_type(&value.value);
+            return self.lower_index_assignment(ctx, object, index, (rhs, rhs_type));
+        }
+
         // Get the target local
         let target_name = match &target.value {
             Expr::Ident(ident) => ident.name.clone(),
-            _ => return None, // Complex assignment targets not yet supported
+            _ => {
+                self.unsupported("assignment to this target", span);
+                return None;
+            }
         };
 
         let info = self.lookup_var(&target_name)?.clone();
@@ -1160,7 +3264,7 @@ impl Lowerer {
                 AssignmentOp::BitXorAssign => BinOp::BitXor,
                 AssignmentOp::LeftShiftAssign => BinOp::Shl,
                 AssignmentOp::RightShiftAssign => BinOp::Shr,
-                _ => return None,
+                _ => unreachable!("rewritten or rejected above"),
             };
             let temp = ctx.add_temp(info.ir_type.clone());
             ctx.emit(Instruction::Assign {
@@ -1174,27 +3278,147 @@ impl Lowerer {
             Value::Temp(temp)
         };
 
+        self.store_var(ctx, &info, final_val.clone());
+        Some(final_val)
+    }
+
+    /// Write `value` to a variable, through its box if it has one.
+    fn store_var(&mut self, ctx: &mut FuncCtx, info: &VarInfo, value: Value) {
         if info.is_boxed {
             // Boxed variable: write through box pointer
             self.ensure_extern("zaco_box_set", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
             ctx.emit(Instruction::Call {
                 dest: None,
                 func: Value::Const(Constant::Str("zaco_box_set".to_string())),
-                args: vec![Value::Local(info.local_id), final_val.clone()],
+                args: vec![Value::Local(info.local_id), value],
             });
         } else {
             ctx.emit(Instruction::Assign {
                 dest: Place::from_local(info.local_id),
-                value: RValue::Use(final_val.clone()),
+                value: RValue::Use(value),
             });
         }
+    }
 
-        Some(final_val)
+    /// The operator a compound assignment applies, e.g. `+` for `+=`.
+    /// `&&=`, `||=` and `??=` only assign conditionally, so have none.
+    fn compound_binary_op(op: AssignmentOp) -> Option<BinaryOp> {
+        let bin_op = match op {
+            AssignmentOp::AddAssign => BinaryOp::Add,
+            AssignmentOp::SubAssign => BinaryOp::Sub,
+            AssignmentOp::MulAssign => BinaryOp::Mul,
+            AssignmentOp::DivAssign => BinaryOp::Div,
+            AssignmentOp::ModAssign => BinaryOp::Mod,
+            AssignmentOp::PowAssign => BinaryOp::Pow,
+            AssignmentOp::LeftShiftAssign => BinaryOp::LeftShift,
+            AssignmentOp::RightShiftAssign => BinaryOp::RightShift,
+            AssignmentOp::UnsignedRightShiftAssign => BinaryOp::UnsignedRightShift,
+            AssignmentOp::BitAndAssign => BinaryOp::BitAnd,
+            AssignmentOp::BitOrAssign => BinaryOp::BitOr,
+            AssignmentOp::BitXorAssign => BinaryOp::BitXor,
+            AssignmentOp::Assign | AssignmentOp::AndAssign | AssignmentOp::OrAssign | AssignmentOp::NullishAssign => {
+                return None;
+            }
+        };
+        Some(bin_op)
+    }
+
+    /// An assignment target whose object, and index for an element, are
+    /// bound to hidden consts (see `bind_once`), so the target can be read
+    /// and written without evaluating them again. A literal index stays as
+    /// it is, since it picks a tuple element's type.
+    fn bind_target(&mut self, ctx: &mut FuncCtx, target: &Node<Expr>) -> Node<Expr> {
+        let bound = match &target.value {
+            Expr::Paren(inner) => return self.bind_target(ctx, inner),
+            Expr::Member { object, property, computed } => Expr::Member {
+                object: Box::new(self.bind_once(ctx, object)),
+                property: property.clone(),
+                computed: *computed,
+            },
+            Expr::Index { object, index } => {
+                let object = self.bind_once(ctx, object);
+                let index = match index.value {
+                    Expr::Literal(_) => (**index).clone(),
+                    _ => self.bind_once(ctx, index),
+                };
+                Expr::Index { object: Box::new(object), index: Box::new(index) }
+            }
+            _ => return target.clone(),
+        };
+        Node::new(bound, target.span)
+    }
+
+    /// Lower `array[index] = value` on an inline array. Writing past the end
+    /// grows the array into a copy, which is stored back in the variable or
+    /// property the array came from.
+    fn lower_index_assignment(
+        &mut self,
+        ctx: &mut FuncCtx,
+        object: &Node<Expr>,
+        index: &Node<Expr>,
+        (rhs, rhs_type): (Value, IrType),
+    ) -> Option<Value> {
+        let array_type = self.infer_expr_type(&object.value);
+        let elem_type = match (self.tuple_index_type(&object.value, &index.value), &array_type) {
+            (Some(elem_type), _) => elem_type,
+            (None, IrType::Array(elem_type)) => (**elem_type).clone(),
+            _ => {
+                self.unsupported("assignment to an element of a value not known to be an array", &object.span);
+                return None;
+            }
+        };
+        let (setter, slot_type) = match elem_type {
+            IrType::F64 | IrType::I64 => ("zaco_array_set_f64", IrType::F64),
+            IrType::Bool => {
+                self.unsupported("assignment to an element of a boolean array", &object.span);
+                return None;
+            }
+            _ => ("zaco_array_set_ptr", IrType::Ptr),
+        };
+        let array = self.lower_expr(ctx, &object.value, &object.span)?;
+        let idx = self.lower_expr(ctx, &index.value, &index.span)?;
+        let idx_int = ctx.add_temp(IrType::I64);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(idx_int),
+            value: RValue::Cast { value: idx, ty: IrType::I64 },
+        });
+        let slot = if slot_type == IrType::F64 && rhs_type != IrType::F64 {
+            let num = ctx.add_temp(IrType::F64);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(num),
+                value: RValue::Cast { value: rhs.clone(), ty: IrType::F64 },
+            });
+            Value::Temp(num)
+        } else {
+            rhs.clone()
+        };
+        self.ensure_extern(setter, vec![IrType::Ptr, IrType::I64, slot_type], IrType::Ptr);
+        let stored = ctx.add_temp(array_type);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(stored)),
+            func: Value::Const(Constant::Str(setter.to_string())),
+            args: vec![array, Value::Temp(idx_int), slot],
+        });
+        match &object.value {
+            Expr::Ident(ident) => {
+                let info = self.lookup_var(&ident.name)?.clone();
+                self.store_var(ctx, &info, Value::Temp(stored));
+            }
+            Expr::Member { object, property, .. } => {
+                self.lower_member_assignment(ctx, object, property, AssignmentOp::Assign, Value::Temp(stored));
+            }
+            _ => {}
+        }
+        Some(rhs)
     }
 
     /// Lower nullish assignment (`??=`): `a ??= b`
-    fn lower_nullish_assign(&mut self, ctx: &mut FuncCtx, target: &Node<Expr>, value: &Node<Expr>) -> Option<Value> {
-        let target_name = match &target.value { Expr::Ident(ident) => ident.name.clone(), _ => return None };
+    fn lower_nullish_assign(&mut self, ctx: &mut FuncCtx, target: &Node<Expr>, value: &Node<Expr>, span: &Span) -> Option<Value> {
+        let Expr::Ident(ident) = &target.value else {
+            self.unsupported("`??=` on a property or element", span);
+            return None;
+        };
+        let target_name = ident.name.clone();
         let info = self.lookup_var(&target_name)?.clone();
         let current_val = Value::Local(info.local_id);
         let val_type = info.ir_type.clone();
@@ -1213,6 +3437,7 @@ impl Lowerer {
 
     /// Lower optional member access (`obj?.prop`).
     fn lower_optional_member(&mut self, ctx: &mut FuncCtx, object: &Node<Expr>, property: &Node<Ident>, span: &Span) -> Option<Value> {
+        let object = &self.bind_once(ctx, object);
         let base = self.lower_expr(ctx, &object.value, &object.span)?;
         let base_type = self.infer_expr_type(&object.value);
         let result_type = self.infer_expr_type(&Expr::Member { object: Box::new(object.clone()), property: property.clone(), computed: false });
@@ -1232,8 +3457,51 @@ impl Lowerer {
         Some(Value::Local(result_local))
     }
 
+    /// Lower a method call through an optional member (`obj?.method(args)`):
+    /// when `obj` is null or undefined, neither the method nor its
+    /// arguments are evaluated.
+    fn lower_optional_method_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        object: &Node<Expr>,
+        property: &Node<Ident>,
+        args: &[Node<Expr>],
+        span: &Span,
+    ) -> Option<Value> {
+        let object = self.bind_once(ctx, object);
+        let base = self.lower_expr(ctx, &object.value, &object.span)?;
+        let base_type = self.infer_expr_type(&object.value);
+        let method = Node::new(Expr::Member { object: Box::new(object), property: property.clone(), computed: false }, *span);
+        let result_type = self.infer_expr_type(&Expr::Call { callee: Box::new(method.clone()), type_args: None, args: args.to_vec() });
+        let result_local = (result_type != IrType::Void).then(|| ctx.add_local(result_type.clone()));
+        if let Some(result_local) = result_local {
+            let null_val = if result_type.is_pointer() { Value::Const(Constant::Null) } else { Value::Const(Constant::I64(0)) };
+            ctx.emit(Instruction::Assign { dest: Place::from_local(result_local), value: RValue::Use(null_val) });
+        }
+        let then_block = ctx.new_block();
+        let merge_block = ctx.new_block();
+        let is_null = self.emit_null_check(ctx, base, &base_type);
+        ctx.set_terminator(Terminator::Branch { cond: is_null, then_block: merge_block, else_block: then_block });
+        ctx.switch_to(then_block);
+        let call_val = self.lower_call(ctx, &method, args, span);
+        if let (Some(result_local), Some(call_val)) = (result_local, call_val) {
+            ctx.emit(Instruction::Assign { dest: Place::from_local(result_local), value: RValue::Use(call_val) });
+        }
+        ctx.set_terminator(Terminator::Jump(merge_block));
+        ctx.switch_to(merge_block);
+        result_local.map(Value::Local)
+    }
+
     /// Lower optional call (`obj?.(args)`).
     fn lower_optional_call(&mut self, ctx: &mut FuncCtx, callee: &Node<Expr>, args: &[Node<Expr>], span: &Span) -> Option<Value> {
+        // A method keeps its receiver, which is what gets evaluated once
+        let callee = &match &callee.value {
+            Expr::Member { object, property, computed } => Node::new(
+                Expr::Member { object: Box::new(self.bind_once(ctx, object)), property: property.clone(), computed: *computed },
+                callee.span,
+            ),
+            _ => self.bind_once(ctx, callee),
+        };
         let base = self.lower_expr(ctx, &callee.value, &callee.span)?;
         let base_type = self.infer_expr_type(&callee.value);
         let result_type = self.infer_expr_type(&Expr::Call { callee: Box::new(callee.clone()), type_args: None, args: args.to_vec() });
@@ -1253,6 +3521,32 @@ impl Lowerer {
         Some(Value::Local(result_local))
     }
 
+    /// An optional chain lowers its base once for the null check and again
+    /// for the access itself. Unless reading it twice is harmless, bind the
+    /// base to a hidden const first and return a reference to that, so a
+    /// call in it runs once.
+    fn bind_once(&mut self, ctx: &mut FuncCtx, expr: &Node<Expr>) -> Node<Expr> {
+        fn is_reference(expr: &Expr) -> bool {
+            match expr {
+                Expr::Ident(_) | Expr::This => true,
+                Expr::Paren(inner) => is_reference(&inner.value),
+                Expr::Member { object, computed: false, .. } => is_reference(&object.value),
+                _ => false,
+            }
+        }
+        if is_reference(&expr.value) {
+            return expr.clone();
+        }
+        let name = format!("__chain_base_{}_{}", expr.span.start, expr.span.end);
+        let pattern = Pattern::Ident { name: Node::new(Ident::new(&name), expr.span), type_annotation: None, ownership: None };
+        let decl = VarDecl {
+            kind: VarDeclKind::Const,
+            declarations: vec![VarDeclarator { pattern: Node::new(pattern, expr.span), init: Some(expr.clone()) }],
+        };
+        self.lower_var_decl(ctx, &decl, &expr.span);
+        Node::new(Expr::Ident(Ident::new(&name)), expr.span)
+    }
+
     /// Lower optional index access (`obj?.[index]`).
     fn lower_optional_index(&mut self, ctx: &mut FuncCtx, object: &Node<Expr>, index: &Node<Expr>, _span: &Span) -> Option<Value> {
         let base = self.lower_expr(ctx, &object.value, &object.span)?;
@@ -1275,6 +3569,258 @@ impl Lowerer {
         Some(Value::Local(result_local))
     }
 
+    /// Lower `array[index]` on an inline array. Out-of-range reads give 0/null.
+    fn lower_index(&mut self, ctx: &mut FuncCtx, object: &Node<Expr>, index: &Node<Expr>) -> Option<Value> {
+        let elem_type = match (self.tuple_index_type(&object.value, &index.value), self.infer_expr_type(&object.value)) {
+            (Some(elem_type), _) => Box::new(elem_type),
+            (None, IrType::Array(elem_type)) => elem_type,
+            _ => {
+                self.unsupported("indexing a value not known to be an array", &object.span);
+                return None;
+            }
+        };
+        let array = self.lower_expr(ctx, &object.value, &object.span)?;
+        let idx = self.lower_expr(ctx, &index.value, &index.span)?;
+        let idx_int = ctx.add_temp(IrType::I64);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(idx_int),
+            value: RValue::Cast { value: idx, ty: IrType::I64 },
+        });
+        Some(self.emit_array_element(ctx, array, Value::Temp(idx_int), *elem_type))
+    }
+
+    /// Read the element at `index` (an i64) of an inline array as
+    /// `elem_type`. Numbers sit in f64 slots, booleans next to other values
+    /// in integer slots, and the rest are pointers.
+    fn emit_array_element(&mut self, ctx: &mut FuncCtx, array: Value, index: Value, elem_type: IrType) -> Value {
+        let (getter, slot_type) = match elem_type {
+            IrType::F64 | IrType::I64 => ("zaco_array_get_f64", IrType::F64),
+            IrType::Bool => ("zaco_array_get_ptr", IrType::Ptr),
+            ref other => ("zaco_array_get_ptr", other.clone()),
+        };
+        self.ensure_extern(getter, vec![IrType::Ptr, IrType::I64], slot_type.clone());
+        let slot = ctx.add_temp(slot_type.clone());
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(slot)),
+            func: Value::Const(Constant::Str(getter.to_string())),
+            args: vec![array, index],
+        });
+        if slot_type == elem_type {
+            return Value::Temp(slot);
+        }
+        let result = ctx.add_temp(elem_type.clone());
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(result),
+            value: RValue::Cast { value: Value::Temp(slot), ty: elem_type },
+        });
+        Value::Temp(result)
+    }
+
+    /// Type of `tuple[i]` for a literal index into a value of tuple type,
+    /// whose elements may differ in type.
+    fn tuple_index_type(&self, object: &Expr, index: &Expr) -> Option<IrType> {
+        let Type::Tuple(elems) = self.resolve_value_type(&self.expr_value_type(object)?) else {
+            return None;
+        };
+        Some(self.ast_type_to_ir(&Self::tuple_element(elems, index)?))
+    }
+
+    /// Lower `array.push(values...)` on an array variable. Inline arrays are
+    /// allocated to fit, so each value goes into a grown copy that is then
+    /// stored back in the variable. Returns the new length.
+    fn lower_array_push(
+        &mut self,
+        ctx: &mut FuncCtx,
+        name: &str,
+        elem: &IrType,
+        args: &[Node<Expr>],
+        span: &Span,
+    ) -> Option<Value> {
+        let info = self.lookup_var(name)?.clone();
+        let mut array = self.lower_expr(ctx, &Expr::Ident(Ident::new(name)), span)?;
+        for arg in args {
+            let (append, value_type, value) = match elem {
+                IrType::Str | IrType::Ptr | IrType::Array(_) | IrType::Struct(_) | IrType::Promise(_) => {
+                    ("zaco_array_append_ptr", IrType::Ptr, self.lower_expr(ctx, &arg.value, &arg.span)?)
+                }
+                _ => ("zaco_array_append_f64", IrType::F64, self.lower_array_slot(ctx, arg)?),
+            };
+            self.ensure_extern(append, vec![IrType::Ptr, value_type], IrType::Ptr);
+            let grown = ctx.add_temp(info.ir_type.clone());
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(grown)),
+                func: Value::Const(Constant::Str(append.to_string())),
+                args: vec![array, value],
+            });
+            array = Value::Temp(grown);
+        }
+
+        if info.is_boxed {
+            self.ensure_extern("zaco_box_set", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str("zaco_box_set".to_string())),
+                args: vec![Value::Local(info.local_id), array.clone()],
+            });
+        } else {
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_local(info.local_id),
+                value: RValue::Use(array.clone()),
+            });
+        }
+
+        self.ensure_extern("zaco_array_length", vec![IrType::Ptr], IrType::I64);
+        let length = ctx.add_temp(IrType::I64);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(length)),
+            func: Value::Const(Constant::Str("zaco_array_length".to_string())),
+            args: vec![array],
+        });
+        let result = ctx.add_temp(IrType::F64);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(result),
+            value: RValue::Cast { value: Value::Temp(length), ty: IrType::F64 },
+        });
+        Some(Value::Temp(result))
+    }
+
+    /// Lower `Array(n)` / `new Array(n)` to a new array of `n` empty slots.
+    fn lower_array_with_length(&mut self, ctx: &mut FuncCtx, length: &Node<Expr>) -> Option<Value> {
+        let length = self.lower_expr(ctx, &length.value, &length.span)?;
+        let count = ctx.add_temp(IrType::I64);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(count),
+            value: RValue::Cast { value: length, ty: IrType::I64 },
+        });
+        self.ensure_extern("zaco_array_with_capacity", vec![IrType::I64], IrType::Ptr);
+        let array = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(array)),
+            func: Value::Const(Constant::Str("zaco_array_with_capacity".to_string())),
+            args: vec![Value::Temp(count)],
+        });
+        Some(Value::Temp(array))
+    }
+
+    /// Type of `array.fill(value)`: the array's own, except that the empty
+    /// slots of an untyped `new Array(n)` take the type of the value.
+    fn array_fill_type(&self, array: &Expr, args: &[Node<Expr>]) -> Option<IrType> {
+        let array_type = self.infer_expr_type(array);
+        if !matches!(array_type, IrType::Array(_)) {
+            return None;
+        }
+        match (self.array_constructor(array), args.first()) {
+            (Some((_, None)), Some(value)) => Some(IrType::Array(Box::new(self.infer_expr_type(&value.value)))),
+            _ => Some(array_type),
+        }
+    }
+
+    /// Lower `array.fill(value, start?, end?)`, which stores `value` in the
+    /// slots from `start` up to `end` in place and returns the array.
+    fn lower_array_fill(&mut self, ctx: &mut FuncCtx, array: &Node<Expr>, args: &[Node<Expr>]) -> Option<Value> {
+        let array_type = self.array_fill_type(&array.value, args)?;
+        let IrType::Array(elem) = &array_type else { return None };
+        let receiver = self.lower_receiver(ctx, array, "fill")?;
+        let (fill, value_type, value) = match (elem.as_ref(), args.first()) {
+            (IrType::Str | IrType::Ptr | IrType::Array(_) | IrType::Struct(_) | IrType::Promise(_), Some(value)) => {
+                ("zaco_array_fill_ptr", IrType::Ptr, self.lower_expr(ctx, &value.value, &value.span)?)
+            }
+            (_, Some(value)) => ("zaco_array_fill_f64", IrType::F64, self.lower_array_slot(ctx, value)?),
+            (_, None) => ("zaco_array_fill_ptr", IrType::Ptr, Value::Const(Constant::Null)),
+        };
+        let mut call_args = vec![receiver, value];
+        call_args.extend(self.lower_array_positions(ctx, args, &[(1, 0), (2, i64::MAX)])?);
+        self.ensure_extern(fill, vec![IrType::Ptr, value_type, IrType::I64, IrType::I64], IrType::Ptr);
+        let result = ctx.add_temp(array_type.clone());
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(fill.to_string())),
+            args: call_args,
+        });
+        Some(Value::Temp(result))
+    }
+
+    /// Lower `array.reverse()` and `array.copyWithin(target, start?, end?)`,
+    /// which move the slots in place and return the array.
+    fn lower_array_reorder(&mut self, ctx: &mut FuncCtx, array: &Node<Expr>, method: &str, args: &[Node<Expr>]) -> Option<Value> {
+        let array_type = self.infer_expr_type(&array.value);
+        let mut call_args = vec![self.lower_receiver(ctx, array, method)?];
+        let func = if method == "reverse" {
+            "zaco_array_reverse_in_place"
+        } else {
+            call_args.extend(self.lower_array_positions(ctx, args, &[(0, 0), (1, 0), (2, i64::MAX)])?);
+            "zaco_array_copy_within"
+        };
+        let mut param_types = vec![IrType::Ptr];
+        param_types.resize(call_args.len(), IrType::I64);
+        self.ensure_extern(func, param_types, IrType::Ptr);
+        let result = ctx.add_temp(array_type);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(func.to_string())),
+            args: call_args,
+        });
+        Some(Value::Temp(result))
+    }
+
+    /// Lower `array.concat(items...)` to a new array: each array item is
+    /// joined on with `zaco_array_concat`, and any other item appended as
+    /// one element. Every step copies, so the receiver is left as it was.
+    fn lower_array_concat(&mut self, ctx: &mut FuncCtx, array: &Node<Expr>, args: &[Node<Expr>]) -> Option<Value> {
+        let array_type = self.infer_expr_type(&array.value);
+        let IrType::Array(elem) = &array_type else { return None };
+        let pointer_elems = elem.is_pointer();
+        let mut result = self.lower_receiver(ctx, array, "concat")?;
+        // `array.concat()` is still a copy
+        if args.is_empty() {
+            self.ensure_extern("zaco_array_concat", vec![IrType::Ptr, IrType::Ptr], IrType::Ptr);
+            let copy = ctx.add_temp(array_type.clone());
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(copy)),
+                func: Value::Const(Constant::Str("zaco_array_concat".to_string())),
+                args: vec![result, Value::Const(Constant::Null)],
+            });
+            return Some(Value::Temp(copy));
+        }
+        for arg in args {
+            let (func, value_type, value) = match self.infer_expr_type(&arg.value) {
+                IrType::Array(_) => ("zaco_array_concat", IrType::Ptr, self.lower_expr(ctx, &arg.value, &arg.span)?),
+                _ if pointer_elems => ("zaco_array_append_ptr", IrType::Ptr, self.lower_expr(ctx, &arg.value, &arg.span)?),
+                _ => ("zaco_array_append_f64", IrType::F64, self.lower_array_slot(ctx, arg)?),
+            };
+            self.ensure_extern(func, vec![IrType::Ptr, value_type], IrType::Ptr);
+            let next = ctx.add_temp(array_type.clone());
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(next)),
+                func: Value::Const(Constant::Str(func.to_string())),
+                args: vec![result, value],
+            });
+            result = Value::Temp(next);
+        }
+        Some(result)
+    }
+
+    /// The position arguments of an array method, such as the `start` and
+    /// `end` of `fill`, as i64s. Each is read from `args` at its index, or
+    /// defaults to the value paired with it.
+    fn lower_array_positions(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>], positions: &[(usize, i64)]) -> Option<Vec<Value>> {
+        let mut values = Vec::new();
+        for &(position, default) in positions {
+            let Some(arg) = args.get(position) else {
+                values.push(Value::Const(Constant::I64(default)));
+                continue;
+            };
+            let arg = self.lower_expr(ctx, &arg.value, &arg.span)?;
+            let index = ctx.add_temp(IrType::I64);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(index),
+                value: RValue::Cast { value: arg, ty: IrType::I64 },
+            });
+            values.push(Value::Temp(index));
+        }
+        Some(values)
+    }
+
     /// Lower a function call. Handles built-in modules specially.
     fn lower_call(
         &mut self,
@@ -1297,6 +3843,12 @@ impl Lowerer {
                 let obj_name = &obj_ident.name;
                 let method = &property.value.name;
 
+                if method == "push" {
+                    if let Some(IrType::Array(elem)) = self.lookup_var(obj_name).map(|info| info.ir_type.clone()) {
+                        return self.lower_array_push(ctx, obj_name, &elem, args, span);
+                    }
+                }
+
                 // Handle console methods
                 if obj_name == "console" {
                     match method.as_str() {
@@ -1306,6 +3858,9 @@ impl Lowerer {
                         "error" | "warn" | "info" | "debug" => {
                             return self.lower_console_method(ctx, args, method, span);
                         }
+                        "table" | "time" | "timeEnd" | "group" | "groupEnd" | "assert" => {
+                            return self.lower_console_extra(ctx, args, method, span);
+                        }
                         _ => {}
                     }
                 }
@@ -1320,15 +3875,55 @@ impl Lowerer {
                     return self.lower_json_method(ctx, method, args, span);
                 }
 
+                // Handle Object statics
+                if obj_name == "Object" && self.lookup_var(obj_name).is_none() {
+                    return self.lower_object_static_method(ctx, method, args, span);
+                }
+
+                // Handle Array.from (Array.of is rewritten to an array literal)
+                if obj_name == "Array" && method == "from" && self.lookup_var(obj_name).is_none() {
+                    return self.lower_array_from(ctx, args, span);
+                }
+
                 // Handle process methods
                 if obj_name == "process" {
                     return self.lower_process_method(ctx, method, args, span);
                 }
 
-                // Handle ClassName.staticMethod(args) — static method calls
-                if let Some(ci) = self.class_info.get(obj_name).cloned() {
-                    if ci.static_methods.contains(&method.to_string()) {
-                        let func_name = format!("{}_{}", obj_name, method);
+                // Promise.all(promises)
+                if obj_name == "Promise" && method == "all" && self.lookup_var(obj_name).is_none() {
+                    let promises = match args.first() {
+                        Some(arg) => self.lower_expr(ctx, &arg.value, &arg.span)?,
+                        None => Value::Const(Constant::Null),
+                    };
+                    self.ensure_extern("zaco_promise_all", vec![IrType::Ptr], IrType::Ptr);
+                    let result = ctx.add_temp(IrType::Ptr);
+                    ctx.emit(Instruction::Call {
+                        dest: Some(Place::from_temp(result)),
+                        func: Value::Const(Constant::Str("zaco_promise_all".to_string())),
+                        args: vec![promises],
+                    });
+                    return Some(Value::Temp(result));
+                }
+
+                // assert.equal(...) etc. from the "assert" module
+                if self.imported_bindings.get(obj_name).is_some_and(|m| m == "assert") {
+                    return self.lower_assert_call(ctx, method, args, span);
+                }
+
+                // path.join(...) through `import * as path`
+                if let Some(module) = self.imported_bindings.get(obj_name).filter(|_| self.lookup_var(obj_name).is_none()).cloned() {
+                    let exported = self.module_exports.get(&module).and_then(|exports| exports.functions.get(method)).cloned();
+                    if let Some((param_types, return_type)) = exported {
+                        return self.lower_local_import_call(ctx, method.clone(), &param_types, return_type, args);
+                    }
+                    return self.lower_imported_function_call(ctx, &module, method, args, span);
+                }
+
+                // Handle ClassName.staticMethod(args) — static method calls
+                if let Some(ci) = self.class_info.get(obj_name).cloned() {
+                    if ci.static_methods.contains(&method.to_string()) {
+                        let func_name = format!("{}_{}", obj_name, method);
                         let mut arg_vals = Vec::new();
                         for arg in args {
                             if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
@@ -1359,6 +3954,49 @@ impl Lowerer {
                     }
                 }
 
+                // Handle db.exec/query/close on a sqlite Database
+                if self.sqlite_databases.contains(obj_name) {
+                    return self.lower_sqlite_method(ctx, object, method, args, span);
+                }
+
+                // Handle gen.next(v) on a generator
+                if self.generator_vars.contains_key(obj_name) && method == "next" {
+                    return self.lower_generator_next(ctx, object, args);
+                }
+
+                // Handle res.text()/res.json() on a fetch Response
+                if self.fetch_responses.contains(obj_name) {
+                    return self.lower_response_method(ctx, object, method, args, span);
+                }
+
+                // Handle AbortSignal.timeout(ms)
+                if Self::is_abort_signal_timeout(&callee.value) && self.lookup_var(obj_name).is_none() {
+                    let ms = match args.first() {
+                        Some(arg) => self.lower_expr(ctx, &arg.value, &arg.span)?,
+                        None => Value::Const(Constant::F64(0.0)),
+                    };
+                    self.ensure_extern("zaco_abort_signal_timeout", vec![IrType::F64], IrType::Ptr);
+                    let result = ctx.add_temp(IrType::Ptr);
+                    ctx.emit(Instruction::Call {
+                        dest: Some(Place::from_temp(result)),
+                        func: Value::Const(Constant::Str("zaco_abort_signal_timeout".to_string())),
+                        args: vec![ms],
+                    });
+                    return Some(Value::Temp(result));
+                }
+
+                // Handle controller.abort() on an AbortController
+                if self.abort_controllers.contains(obj_name) && method == "abort" {
+                    let controller = self.lower_expr(ctx, &object.value, &object.span)?;
+                    self.ensure_extern("zaco_abort_controller_abort", vec![IrType::Ptr], IrType::Void);
+                    ctx.emit(Instruction::Call {
+                        dest: None,
+                        func: Value::Const(Constant::Str("zaco_abort_controller_abort".to_string())),
+                        args: vec![controller],
+                    });
+                    return None;
+                }
+
                 // Handle class instance method calls: obj.method(args)
                 if let Some(info) = self.lookup_var(obj_name).cloned() {
                     if let IrType::Struct(struct_id) = &info.ir_type {
@@ -1367,17 +4005,25 @@ impl Lowerer {
                             .find(|(_, ci)| ci.struct_id == *struct_id)
                             .map(|(k, v)| (k.clone(), v.clone()))
                         {
-                            return self.lower_method_call(ctx, &class_name, method, &info, args, span);
+                            let receiver = self.lower_expr(ctx, &object.value, &object.span)?;
+                            return self.lower_method_call(ctx, &class_name, method, receiver, args, span);
                         }
                     }
                 }
             }
 
+            // Handle res.headers.get(name) on a fetch Response
+            if let Expr::Member { object: response, property: headers, .. } = &object.value {
+                if headers.value.name == "headers" && self.is_fetch_response(&response.value) {
+                    return self.lower_response_method(ctx, response, &format!("headers.{}", property.value.name), args, span);
+                }
+            }
+
             // Handle this.method(args) — method call on `this`
             if matches!(&object.value, Expr::This) {
                 if let (Some(this_info), Some(class_name)) = (self.this_var.clone(), self.current_class.clone()) {
                     let method = &property.value.name;
-                    return self.lower_method_call(ctx, &class_name, method, &this_info, args, span);
+                    return self.lower_method_call(ctx, &class_name, method, Value::Local(this_info.local_id), args, span);
                 }
             }
 
@@ -1385,20 +4031,16 @@ impl Lowerer {
             if matches!(&object.value, Expr::Super) {
                 if let (Some(this_info), Some(parent_name)) = (self.this_var.clone(), self.current_class_parent.clone()) {
                     let method = &property.value.name;
-                    return self.lower_method_call(ctx, &parent_name, method, &this_info, args, span);
+                    return self.lower_method_call(ctx, &parent_name, method, Value::Local(this_info.local_id), args, span);
                 }
             }
 
-            // Handle Promise.then/catch/finally chaining
-            if let Expr::Ident(obj_ident) = &object.value {
-                let method = &property.value.name;
-                if matches!(method.as_str(), "then" | "catch" | "finally") {
-                    if let Some(info) = self.lookup_var(&obj_ident.name).cloned() {
-                        if matches!(info.ir_type, IrType::Promise(_)) {
-                            return self.lower_promise_chain_method(ctx, &info, method, args, span);
-                        }
-                    }
-                }
+            // Handle Promise.then/catch/finally chaining on any promise-typed receiver
+            if matches!(property.value.name.as_str(), "then" | "catch" | "finally")
+                && matches!(self.infer_expr_type(&object.value), IrType::Promise(_))
+            {
+                let promise_val = self.lower_expr(ctx, &object.value, &object.span)?;
+                return self.lower_promise_chain_method(ctx, promise_val, &property.value.name, args, span);
             }
 
             // Handle array.map/filter/forEach callbacks
@@ -1414,22 +4056,88 @@ impl Lowerer {
             }
         }
 
-        // Check for direct function calls (imported functions)
+        // Method call on any other receiver expression, e.g. `foo().bar()`,
+        // `"  hi  ".trim().toUpperCase()` or a string variable
+        if let Expr::Member { object, property, .. } = &callee.value {
+            let method = &property.value.name;
+            match self.infer_expr_type(&object.value) {
+                IrType::Array(_) if method == "fill" => return self.lower_array_fill(ctx, object, args),
+                IrType::Array(_) if matches!(method.as_str(), "reverse" | "copyWithin") => {
+                    return self.lower_array_reorder(ctx, object, method, args);
+                }
+                IrType::Array(_) if method == "concat" => return self.lower_array_concat(ctx, object, args),
+                IrType::Str if Self::string_method_signature(method).is_some() => {
+                    let receiver = self.lower_receiver(ctx, object, method)?;
+                    return self.lower_string_method(ctx, receiver, method, args);
+                }
+                IrType::Struct(struct_id) => {
+                    if let Some(class_name) = self.class_name_for_struct(struct_id) {
+                        let receiver = self.lower_expr(ctx, &object.value, &object.span)?;
+                        return self.lower_method_call(ctx, &class_name, method, receiver, args, span);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Immediately-invoked function expression: `(() => ...)()`, `(function () {...})()`
+        if let Some(func_expr) = Self::unwrap_function_expr(&callee.value) {
+            let Value::Const(Constant::Str(closure_name)) = self.lower_expr(ctx, func_expr, &callee.span)? else {
+                return None;
+            };
+            let closure_info = self.closure_bindings.get(&closure_name).cloned()?;
+            return self.lower_closure_call(ctx, &closure_info, args, span);
+        }
+
+        // Any other callee evaluates to a function value: an object's
+        // property, the result of a call, an array element
         let func_name = match &callee.value {
             Expr::Ident(ident) => ident.name.clone(),
-            _ => return None, // Complex callees not yet supported
+            _ => {
+                let sig = match self.infer_expr_type(&callee.value) {
+                    IrType::FuncPtr(sig) => sig,
+                    _ => match self.returned_closure_signature(&callee.value) {
+                        Some(sig) => sig,
+                        None => {
+                            let construct = match &callee.value {
+                                Expr::Member { property, .. } => format!("calling `{}` on this receiver", property.value.name),
+                                _ => "a call of a value not known to be a function".to_string(),
+                            };
+                            self.unsupported(&construct, span);
+                            return None;
+                        }
+                    },
+                };
+                let func = self.lower_expr(ctx, &callee.value, &callee.span)?;
+                return self.lower_indirect_call(ctx, func, &sig, args);
+            }
         };
 
-        // Handle global built-in functions (parseInt, parseFloat, isNaN, isFinite, timers)
+        if self.is_structured_clone_call(&Expr::Call { callee: Box::new(callee.clone()), type_args: None, args: args.to_vec() }) {
+            return self.lower_structured_clone(ctx, &args[0], span);
+        }
+
+        // test(name, fn) from the built-in test runner, unless the program has its own
+        if func_name == "test" && !self.has_user_test && self.lookup_var("test").is_none() {
+            return self.lower_test_call(ctx, args, span);
+        }
+
+        // Handle global built-in functions (parseInt, parseFloat, isNaN, isFinite,
+        // URI and base64 encoding, timers)
         if let Some((runtime_fn, param_types, ret_type)) = match func_name.as_str() {
             "parseInt" => Some(("zaco_parse_int", vec![IrType::Str], IrType::F64)),
             "parseFloat" => Some(("zaco_parse_float", vec![IrType::Str], IrType::F64)),
             "isNaN" => Some(("zaco_is_nan", vec![IrType::F64], IrType::Bool)),
             "isFinite" => Some(("zaco_is_finite", vec![IrType::F64], IrType::Bool)),
+            "encodeURIComponent" => Some(("zaco_encode_uri_component", vec![IrType::Str], IrType::Str)),
+            "decodeURIComponent" => Some(("zaco_decode_uri_component", vec![IrType::Str], IrType::Str)),
+            "btoa" => Some(("zaco_btoa", vec![IrType::Str], IrType::Str)),
+            "atob" => Some(("zaco_atob", vec![IrType::Str], IrType::Str)),
             "setTimeout" => Some(("zaco_set_timeout", vec![IrType::Ptr, IrType::Ptr, IrType::I64], IrType::I64)),
             "setInterval" => Some(("zaco_set_interval", vec![IrType::Ptr, IrType::Ptr, IrType::I64], IrType::I64)),
             "clearTimeout" => Some(("zaco_clear_timeout", vec![IrType::I64], IrType::Void)),
             "clearInterval" => Some(("zaco_clear_interval", vec![IrType::I64], IrType::Void)),
+            "fetch" => Some(("zaco_fetch", vec![IrType::Str, IrType::Ptr], IrType::Ptr)),
             _ => None,
         } {
             let mut arg_vals = Vec::new();
@@ -1455,6 +4163,13 @@ impl Lowerer {
                 }
             }
 
+            // fetch(url) without an options object
+            if func_name == "fetch" && arg_vals.len() < 2 {
+                arg_vals.push(Value::Const(Constant::Null));
+            }
+
+            self.ensure_extern(runtime_fn, param_types, ret_type.clone());
+
             if ret_type == IrType::Void {
                 ctx.emit(Instruction::Call {
                     dest: None,
@@ -1473,8 +4188,17 @@ impl Lowerer {
             return Some(dest.base);
         }
 
+        if self.check_type_only_use(&func_name) {
+            return None;
+        }
+
         // Check if this is an imported function
         if let Some(module) = self.imported_bindings.get(&func_name).cloned() {
+            // `assert(cond)` through a default or namespace import is `ok`
+            if module == "assert" {
+                let method = if Self::ASSERT_FUNCTIONS.contains(&func_name.as_str()) { func_name.as_str() } else { "ok" };
+                return self.lower_assert_call(ctx, method, args, span);
+            }
             return self.lower_imported_function_call(ctx, &module, &func_name, args, span);
         }
 
@@ -1483,6 +4207,21 @@ impl Lowerer {
             return self.lower_closure_call(ctx, &closure_info, args, span);
         }
 
+        if let Some(param_types) = self.arguments_functions.get(&func_name).cloned() {
+            return self.lower_arguments_call(ctx, &func_name, &param_types, args);
+        }
+
+        // Any other variable holds a function only known at run time (a
+        // parameter, say): a closure value, called indirectly
+        if let Some(info) = self.lookup_var(&func_name).filter(|_| self.module.find_function(&func_name).is_none()) {
+            let IrType::FuncPtr(sig) = info.ir_type.clone() else {
+                self.unsupported("a call to a function held in a variable", span);
+                return None;
+            };
+            let func = self.lower_expr(ctx, &callee.value, &callee.span)?;
+            return self.lower_indirect_call(ctx, func, &sig, args);
+        }
+
         // Regular function call — rename "main" to "_user_main" if needed
         let func_name = if func_name == "main" && self.has_user_main {
             "_user_main".to_string()
@@ -1490,13 +4229,12 @@ impl Lowerer {
             func_name
         };
 
+        let param_types = self.module.find_function(&func_name)
+            .map(|f| f.params.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>())
+            .unwrap_or_default();
         let mut arg_vals = Vec::new();
-        for arg in args {
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                arg_vals.push(val);
-            } else {
-                return None;
-            }
+        for (i, arg) in args.iter().enumerate() {
+            arg_vals.push(self.lower_arg(ctx, arg, param_types.get(i))?);
         }
 
         // Determine return type by looking up the called function's signature
@@ -1528,117 +4266,367 @@ impl Lowerer {
         dest.map(|p| p.base)
     }
 
+    /// Call a function that reads `arguments`. Declared parameters are passed
+    /// as usual (missing ones as zero/null), followed by an array holding
+    /// every argument actually passed.
+    fn lower_arguments_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        func_name: &str,
+        param_types: &[IrType],
+        args: &[Node<Expr>],
+    ) -> Option<Value> {
+        let mut passed = Vec::new();
+        for arg in args {
+            passed.push(self.lower_array_slot(ctx, arg)?);
+        }
+
+        let mut arg_vals: Vec<Value> = param_types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| match passed.get(i) {
+                Some(val) => val.clone(),
+                None => match ty {
+                    IrType::F64 => Value::Const(Constant::F64(0.0)),
+                    IrType::Bool => Value::Const(Constant::Bool(false)),
+                    IrType::I64 => Value::Const(Constant::I64(0)),
+                    _ => Value::Const(Constant::Null),
+                },
+            })
+            .collect();
+        let arguments = ctx.add_temp(IrType::Array(Box::new(IrType::F64)));
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(arguments),
+            value: RValue::ArrayInit(passed),
+        });
+        arg_vals.push(Value::Temp(arguments));
+
+        let func_name = if func_name == "main" && self.has_user_main {
+            "_user_main".to_string()
+        } else {
+            func_name.to_string()
+        };
+        let return_type = self.module.find_function(&func_name)
+            .map(|f| f.return_type.clone())
+            .or_else(|| {
+                self.current_function.as_ref()
+                    .filter(|(cur_name, _)| *cur_name == func_name)
+                    .map(|(_, cur_ret)| cur_ret.clone())
+            })
+            .unwrap_or(IrType::Void);
+        let dest = (return_type != IrType::Void).then(|| Place::from_temp(ctx.add_temp(return_type)));
+        ctx.emit(Instruction::Call {
+            dest: dest.clone(),
+            func: Value::Const(Constant::Str(func_name)),
+            args: arg_vals,
+        });
+        dest.map(|p| p.base)
+    }
+
+    /// Lower an element of an argument array. Array slots are 8 bytes wide,
+    /// so ints and booleans are widened to numbers.
+    fn lower_array_slot(&mut self, ctx: &mut FuncCtx, arg: &Node<Expr>) -> Option<Value> {
+        let val = self.lower_expr(ctx, &arg.value, &arg.span)?;
+        match self.infer_expr_type(&arg.value) {
+            IrType::I64 | IrType::Bool => {
+                let num = ctx.add_temp(IrType::F64);
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(num),
+                    value: RValue::Cast { value: val, ty: IrType::F64 },
+                });
+                Some(Value::Temp(num))
+            }
+            _ => Some(val),
+        }
+    }
+
     /// Lower `console.log(args...)` to appropriate runtime calls.
     fn lower_console_log(
         &mut self,
         ctx: &mut FuncCtx,
         args: &[Node<Expr>],
+        span: &Span,
+    ) -> Option<Value> {
+        self.lower_console_method(ctx, args, "log", span)
+    }
+
+    /// Lower `console.log/error/warn(args...)`. The pieces of the line are
+    /// appended to the runtime's console line buffer, which is then written
+    /// with a single call, so lines on stdout and stderr never tear.
+    fn lower_console_method(
+        &mut self,
+        ctx: &mut FuncCtx,
+        args: &[Node<Expr>],
+        method: &str,
         _span: &Span,
     ) -> Option<Value> {
-        for (i, arg) in args.iter().enumerate() {
-            // Print space separator between arguments (except first)
-            if i > 0 {
-                let space_str = " ".to_string();
-                self.module.intern_string(space_str.clone());
-                ctx.emit(Instruction::Call {
-                    dest: None,
-                    func: Value::Const(Constant::Str("zaco_print_str".to_string())),
-                    args: vec![Value::Const(Constant::Str(space_str))],
-                });
+        // log and info go to stdout; error, warn and debug to stderr
+        let write_fn = match method {
+            "error" => "zaco_console_error_str",
+            "warn" => "zaco_console_warn_str",
+            "debug" => "zaco_console_debug_str",
+            _ => "zaco_print_str",
+        };
+        self.emit_console_parts(ctx, args);
+        self.emit_console_line_end(ctx, write_fn);
+        None // console methods return undefined
+    }
+
+    /// Append the text of console arguments to the line buffer: printf-style
+    /// substitution when the first argument is a string literal with `%`
+    /// specifiers, then the remaining arguments separated by single spaces.
+    fn emit_console_parts(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>]) {
+        let mut rest = args;
+        let mut first = true;
+        if let Some(Expr::Literal(Literal::String(format))) = args.first().map(|arg| &arg.value) {
+            if format.contains('%') {
+                let consumed = self.lower_format_specifiers(ctx, format, &args[1..]);
+                rest = &args[1 + consumed..];
+                first = false;
+            }
+        }
+        for arg in rest {
+            if !first {
+                let space = self.str_const(" ");
+                self.emit_console_put(ctx, "zaco_console_put_str", vec![space]);
             }
+            first = false;
+            self.lower_console_arg(ctx, arg, 's');
+        }
+    }
 
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                let arg_type = self.infer_expr_type(&arg.value);
-                let runtime_fn = match arg_type {
-                    IrType::Str => "zaco_print_str",
-                    IrType::I64 => "zaco_print_i64",
-                    IrType::F64 => "zaco_print_f64",
-                    IrType::Bool => "zaco_print_bool",
-                    _ => "zaco_print_str", // fallback
-                };
+    /// Expand `%s %d %i %f %o %O %j %c %%` in a console format string.
+    /// Returns how many arguments were consumed; a specifier without an
+    /// argument is printed as written.
+    fn lower_format_specifiers(&mut self, ctx: &mut FuncCtx, format: &str, args: &[Node<Expr>]) -> usize {
+        let mut literal = String::new();
+        let mut consumed = 0;
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+            match chars.peek().copied() {
+                Some('%') => {
+                    chars.next();
+                    literal.push('%');
+                }
+                Some(spec @ ('s' | 'd' | 'i' | 'f' | 'o' | 'O' | 'j' | 'c')) if consumed < args.len() => {
+                    chars.next();
+                    if !literal.is_empty() {
+                        let text = self.str_const(&std::mem::take(&mut literal));
+                        self.emit_console_put(ctx, "zaco_console_put_str", vec![text]);
+                    }
+                    self.lower_console_arg(ctx, &args[consumed], spec);
+                    consumed += 1;
+                }
+                _ => literal.push('%'),
+            }
+        }
+        if !literal.is_empty() {
+            let text = self.str_const(&literal);
+            self.emit_console_put(ctx, "zaco_console_put_str", vec![text]);
+        }
+        consumed
+    }
 
+    /// Append one console argument formatted for specifier `spec` (`'s'`
+    /// for plain arguments). Numbers use the JS number formatter; objects
+    /// and arrays are inspected through their shape. `%c` (CSS) prints nothing.
+    fn lower_console_arg(&mut self, ctx: &mut FuncCtx, arg: &Node<Expr>, spec: char) {
+        let ty = self.infer_expr_type(&arg.value);
+        let shape = match ty {
+            IrType::Str => "s".to_string(),
+            _ if ty.is_pointer() => self.structured_clone_shape(&arg.value),
+            _ => String::new(),
+        };
+        let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) else {
+            return;
+        };
+        if spec == 'c' {
+            return;
+        }
+        let inspect = matches!(spec, 'o' | 'O' | 'j');
+        match ty {
+            // zaco_math_trunc returns the integer part as an i64
+            IrType::F64 if matches!(spec, 'd' | 'i') => {
+                let int = ctx.add_temp(IrType::I64);
                 ctx.emit(Instruction::Call {
-                    dest: None,
-                    func: Value::Const(Constant::Str(runtime_fn.to_string())),
+                    dest: Some(Place::from_temp(int)),
+                    func: Value::Const(Constant::Str("zaco_math_trunc".to_string())),
                     args: vec![val],
                 });
+                self.emit_console_put(ctx, "zaco_console_put_i64", vec![Value::Temp(int)]);
+            }
+            IrType::F64 => self.emit_console_put(ctx, "zaco_console_put_f64", vec![val]),
+            IrType::I64 => self.emit_console_put(ctx, "zaco_console_put_i64", vec![val]),
+            IrType::Bool => self.emit_console_put(ctx, "zaco_console_put_bool", vec![val]),
+            // Class instances print as their `toString()`
+            IrType::Struct(_) if !inspect => {
+                let text = self.stringify_value(ctx, val, &ty);
+                self.emit_console_put(ctx, "zaco_console_put_str", vec![text]);
+            }
+            // Plain strings (nullable ones too), and references of unknown
+            // shape, print as text
+            IrType::Str if !inspect => self.emit_console_put(ctx, "zaco_console_put_str", vec![val]),
+            _ if shape == "p" || (shape == "s" && !inspect) => {
+                self.emit_console_put(ctx, "zaco_console_put_str", vec![val])
+            }
+            _ => {
+                let shape = self.str_const(&shape);
+                let json = Value::Const(Constant::I64(i64::from(spec == 'j')));
+                self.emit_console_put(ctx, "zaco_console_put_inspect", vec![val, shape, json]);
             }
         }
+    }
+
+    /// Call a `zaco_console_put_*` line-buffer function.
+    fn emit_console_put(&mut self, ctx: &mut FuncCtx, func: &str, args: Vec<Value>) {
+        let params = match func {
+            "zaco_console_put_f64" => vec![IrType::F64],
+            "zaco_console_put_i64" => vec![IrType::I64],
+            "zaco_console_put_bool" => vec![IrType::Bool],
+            "zaco_console_put_inspect" => vec![IrType::Ptr, IrType::Str, IrType::I64],
+            _ => vec![IrType::Str],
+        };
+        self.ensure_extern(func, params, IrType::Void);
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str(func.to_string())),
+            args,
+        });
+    }
 
-        // Print newline at end — emit empty string println to get the newline
-        let empty = "".to_string();
-        self.module.intern_string(empty.clone());
+    /// Finish the console line and write it with `write_fn`.
+    fn emit_console_line_end(&mut self, ctx: &mut FuncCtx, write_fn: &str) {
+        self.ensure_extern("zaco_console_line_end", vec![], IrType::Str);
+        let line = ctx.add_temp(IrType::Str);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(line)),
+            func: Value::Const(Constant::Str("zaco_console_line_end".to_string())),
+            args: vec![],
+        });
         ctx.emit(Instruction::Call {
             dest: None,
-            func: Value::Const(Constant::Str("zaco_println_str".to_string())),
-            args: vec![Value::Const(Constant::Str(empty))],
+            func: Value::Const(Constant::Str(write_fn.to_string())),
+            args: vec![Value::Temp(line)],
         });
+    }
 
-        None // console.log returns undefined
+    /// An interned string constant.
+    fn str_const(&mut self, text: &str) -> Value {
+        self.module.intern_string(text.to_string());
+        Value::Const(Constant::Str(text.to_string()))
     }
 
-    /// Lower `console.log/error/warn(args...)` to appropriate runtime calls.
-    fn lower_console_method(
+    /// Lower `console.table/time/timeEnd/group/groupEnd/assert`.
+    fn lower_console_extra(
         &mut self,
         ctx: &mut FuncCtx,
         args: &[Node<Expr>],
         method: &str,
-        _span: &Span,
+        span: &Span,
     ) -> Option<Value> {
-        let prefix = match method {
-            "error" => "zaco_console_error",
-            "warn" => "zaco_console_warn",
-            "debug" => "zaco_console_debug",
-            "info" => "zaco_print", // info uses same as log
-            _ => "zaco_print",
-        };
-
-        for (i, arg) in args.iter().enumerate() {
-            // Print space separator between arguments (except first)
-            if i > 0 {
-                let space_str = " ".to_string();
-                self.module.intern_string(space_str.clone());
+        match method {
+            // table(data): rendered by the runtime from the data's shape
+            "table" => {
+                let data = args.first()?;
+                let shape = self.table_shape(&data.value);
+                let ty = self.infer_expr_type(&data.value);
+                let val = self.lower_expr(ctx, &data.value, &data.span)?;
+                if matches!(ty, IrType::F64 | IrType::I64 | IrType::Bool) {
+                    return self.lower_console_log(ctx, args, span);
+                }
+                self.ensure_extern("zaco_console_table", vec![IrType::Ptr, IrType::Str], IrType::Void);
+                self.module.intern_string(shape.clone());
                 ctx.emit(Instruction::Call {
                     dest: None,
-                    func: Value::Const(Constant::Str(format!("{}_str", prefix))),
-                    args: vec![Value::Const(Constant::Str(space_str))],
+                    func: Value::Const(Constant::Str("zaco_console_table".to_string())),
+                    args: vec![val, Value::Const(Constant::Str(shape))],
                 });
             }
-
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                let arg_type = self.infer_expr_type(&arg.value);
-                let runtime_fn = match arg_type {
-                    IrType::Str => format!("{}_str", prefix),
-                    IrType::I64 => format!("{}_i64", prefix),
-                    IrType::F64 => format!("{}_f64", prefix),
-                    IrType::Bool => format!("{}_bool", prefix),
-                    _ => format!("{}_str", prefix), // fallback
+            // time(label?) / timeEnd(label?); the label defaults to "default"
+            "time" | "timeEnd" => {
+                let runtime_fn = if method == "time" { "zaco_console_time" } else { "zaco_console_time_end" };
+                let label = match args.first() {
+                    Some(arg) => self.lower_expr(ctx, &arg.value, &arg.span)?,
+                    None => Value::Const(Constant::Null),
                 };
-
+                self.ensure_extern(runtime_fn, vec![IrType::Str], IrType::Void);
                 ctx.emit(Instruction::Call {
                     dest: None,
-                    func: Value::Const(Constant::Str(runtime_fn)),
-                    args: vec![val],
+                    func: Value::Const(Constant::Str(runtime_fn.to_string())),
+                    args: vec![label],
+                });
+            }
+            // group(label...) prints the label like log, then indents
+            "group" => {
+                if !args.is_empty() {
+                    self.lower_console_log(ctx, args, span);
+                }
+                self.ensure_extern("zaco_console_group", vec![], IrType::Void);
+                ctx.emit(Instruction::Call {
+                    dest: None,
+                    func: Value::Const(Constant::Str("zaco_console_group".to_string())),
+                    args: vec![],
+                });
+            }
+            "groupEnd" => {
+                self.ensure_extern("zaco_console_group_end", vec![], IrType::Void);
+                ctx.emit(Instruction::Call {
+                    dest: None,
+                    func: Value::Const(Constant::Str("zaco_console_group_end".to_string())),
+                    args: vec![],
                 });
             }
+            // assert(cond, msg...) prints "Assertion failed[: msg...]" to
+            // stderr when cond is falsy; unlike the assert module it does not throw
+            _ => {
+                let cond = args.first()?;
+                let ty = self.infer_expr_type(&cond.value);
+                let val = self.lower_expr(ctx, &cond.value, &cond.span)?;
+                let ok = self.truthiness(ctx, val, &ty);
+                let fail_block = ctx.new_block();
+                let done_block = ctx.new_block();
+                ctx.set_terminator(Terminator::Branch { cond: ok, then_block: done_block, else_block: fail_block });
+
+                ctx.switch_to(fail_block);
+                let prefix = if args.len() > 1 { "Assertion failed: " } else { "Assertion failed" };
+                let prefix = self.str_const(prefix);
+                self.emit_console_put(ctx, "zaco_console_put_str", vec![prefix]);
+                self.emit_console_parts(ctx, &args[1..]);
+                self.emit_console_line_end(ctx, "zaco_console_error_str");
+                ctx.set_terminator(Terminator::Jump(done_block));
+                ctx.switch_to(done_block);
+            }
         }
+        None
+    }
 
-        // Print newline using method-specific function
-        let println_fn = match method {
-            "error" => "zaco_console_errorln",
-            "warn" => "zaco_console_warnln",
-            "debug" => "zaco_console_debugln",
-            _ => "zaco_println_str",
+    /// Shape for `console.table`. The rows of an array literal of object
+    /// literals may have different fields, so their shapes are merged.
+    fn table_shape(&self, expr: &Expr) -> String {
+        let Expr::Array(elems) = expr else {
+            return self.structured_clone_shape(expr);
         };
-        let empty = "".to_string();
-        self.module.intern_string(empty.clone());
-        ctx.emit(Instruction::Call {
-            dest: None,
-            func: Value::Const(Constant::Str(println_fn.to_string())),
-            args: vec![Value::Const(Constant::Str(empty))],
-        });
-
-        None // console methods return undefined
+        let mut fields: Vec<String> = Vec::new();
+        let mut seen = HashSet::new();
+        for elem in elems.iter().flatten() {
+            let Expr::Object(props) = &elem.value else {
+                return self.structured_clone_shape(expr);
+            };
+            for prop in props {
+                if let ObjectProperty::Property { key, value, .. } = prop {
+                    if let Some(key) = Self::property_key(key) {
+                        if seen.insert(key.clone()) {
+                            fields.push(format!("{}:{}", key, self.structured_clone_shape(&value.value)));
+                        }
+                    }
+                }
+            }
+        }
+        format!("[{{{}}}", fields.join(","))
     }
 
     /// Lower Math method calls to runtime functions.
@@ -1690,55 +4678,409 @@ impl Lowerer {
         Some(Value::Temp(temp))
     }
 
-    /// Lower JSON method calls to runtime functions.
-    fn lower_json_method(
+    /// Lower `Object.keys/values/entries/fromEntries/assign/freeze(...)` to
+    /// runtime calls. Values are read with the argument's shape, so getters
+    /// run; `Object.assign` copies one source at a time onto the target.
+    fn lower_object_static_method(
         &mut self,
         ctx: &mut FuncCtx,
         method: &str,
         args: &[Node<Expr>],
-        _span: &Span,
+        span: &Span,
     ) -> Option<Value> {
-        let (runtime_fn, param_types, return_type) = match method {
-            "parse" => ("zaco_json_parse", vec![IrType::Str], IrType::Str),
-            "stringify" => ("zaco_json_stringify", vec![IrType::Ptr], IrType::Str),
-            _ => return None,
-        };
-
-        self.ensure_extern(runtime_fn, param_types, return_type.clone());
-
-        let mut arg_vals = Vec::new();
-        for arg in args {
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                arg_vals.push(val);
-            } else {
+        let (first, sources) = args.split_first()?;
+        if matches!(method, "values" | "entries") {
+            let shape = self.expr_object_shape(&first.value).unwrap_or_default();
+            if shape.iter().any(|(_, ty)| *ty != shape[0].1) {
+                self.errors.push(LowerError::new(
+                    format!("Object.{} needs an object whose properties all have one type", method),
+                    *span,
+                ));
                 return None;
             }
         }
-
-        let temp = ctx.add_temp(return_type);
+        let shape = self.value_shape(&first.value);
+        let object = self.lower_expr(ctx, &first.value, &first.span)?;
+        let (runtime_fn, mut call_args) = match method {
+            "keys" => ("zaco_object_keys", vec![object]),
+            "values" => ("zaco_object_values", vec![object]),
+            "entries" => ("zaco_object_entries", vec![object]),
+            "fromEntries" => ("zaco_object_from_entries", vec![object]),
+            "freeze" => ("zaco_object_freeze", vec![object]),
+            "assign" => {
+                self.ensure_extern("zaco_object_assign", vec![IrType::Ptr, IrType::Ptr, IrType::Ptr], IrType::Ptr);
+                let target = ctx.add_temp(IrType::Ptr);
+                ctx.emit(Instruction::Assign { dest: Place::from_temp(target), value: RValue::Use(object) });
+                for source in sources {
+                    let shape = self.value_shape(&source.value);
+                    let value = self.lower_expr(ctx, &source.value, &source.span)?;
+                    self.module.intern_string(shape.clone());
+                    ctx.emit(Instruction::Call {
+                        dest: None,
+                        func: Value::Const(Constant::Str("zaco_object_assign".to_string())),
+                        args: vec![Value::Temp(target), value, Value::Const(Constant::Str(shape))],
+                    });
+                }
+                return Some(Value::Temp(target));
+            }
+            _ => return None,
+        };
+        if matches!(method, "values" | "entries") {
+            self.module.intern_string(shape.clone());
+            call_args.push(Value::Const(Constant::Str(shape)));
+        }
+        let param_types = vec![IrType::Ptr; call_args.len()];
+        self.ensure_extern(runtime_fn, param_types, IrType::Ptr);
+        let result = ctx.add_temp(IrType::Ptr);
         ctx.emit(Instruction::Call {
-            dest: Some(Place::from_temp(temp)),
+            dest: Some(Place::from_temp(result)),
             func: Value::Const(Constant::Str(runtime_fn.to_string())),
-            args: arg_vals,
+            args: call_args,
         });
+        Some(Value::Temp(result))
+    }
 
-        Some(Value::Temp(temp))
+    /// Method name and arguments of an `Object.method(...)` call, unless a
+    /// local shadows `Object`.
+    fn object_static_call<'e>(&self, expr: &'e Expr) -> Option<(&'e str, &'e [Node<Expr>])> {
+        let Expr::Call { callee, args, .. } = expr else { return None };
+        let Expr::Member { object, property, .. } = &callee.value else { return None };
+        match &object.value {
+            Expr::Ident(ident) if ident.name == "Object" && self.lookup_var("Object").is_none() => {
+                Some((property.value.name.as_str(), args.as_slice()))
+            }
+            _ => None,
+        }
     }
 
-    /// Lower process method calls to runtime functions.
-    fn lower_process_method(
-        &mut self,
-        ctx: &mut FuncCtx,
-        method: &str,
-        args: &[Node<Expr>],
-        _span: &Span,
+    /// Annotated type of an `Object.keys/values/entries/freeze(...)` result.
+    /// Values and entries need an argument whose properties share one type.
+    fn object_static_value_type(&self, method: &str, args: &[Node<Expr>], span: &Span) -> Option<Type> {
+        let first = &args.first()?.value;
+        let node = |ty: Type| Box::new(Node::new(ty, *span));
+        let value_type = || {
+            let shape = self.expr_object_shape(first)?;
+            let ty = &shape.first()?.1;
+            if shape.iter().any(|(_, field)| field != ty) {
+                return None;
+            }
+            Some(Type::Primitive(match ty {
+                IrType::F64 | IrType::I64 => PrimitiveType::Number,
+                IrType::Str => PrimitiveType::String,
+                IrType::Bool => PrimitiveType::Boolean,
+                _ => return None,
+            }))
+        };
+        match method {
+            "keys" => Some(Type::Array(node(Type::Primitive(PrimitiveType::String)))),
+            "values" => Some(Type::Array(node(value_type()?))),
+            "entries" => {
+                let pair = vec![Node::new(Type::Primitive(PrimitiveType::String), *span), Node::new(value_type()?, *span)];
+                Some(Type::Array(node(Type::Tuple(pair))))
+            }
+            "freeze" => self.expr_value_type(first),
+            _ => None,
+        }
+    }
+
+    /// Lower `Array.from(source, mapper?)`. Without a mapper, a string,
+    /// generator or iterable class instance is collected into a new array and
+    /// an array is copied; otherwise the values are gathered with a hidden
+    /// `for...of` (or, for an array-like `{ length }` object, a counted loop)
+    /// that pushes what the mapper returns for each value and its index.
+    fn lower_array_from(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>], span: &Span) -> Option<Value> {
+        let source = args.first()?;
+        let mapper = args.get(1);
+        if mapper.is_none() && !self.is_array_like(&source.value) {
+            let is_array = matches!(self.infer_expr_type(&source.value), IrType::Array(_));
+            let values = self.lower_iterable_to_array(ctx, source)?;
+            if !is_array {
+                return Some(values);
+            }
+            return Some(self.emit_array_append_all(ctx, Value::Const(Constant::Null), values));
+        }
+        self.lower_array_collect(ctx, source, mapper, span)
+    }
+
+    /// Gather the values iterating `source` produces (passed through
+    /// `mapper` with their index, if given) into a new array, by lowering a
+    /// hidden loop that pushes each one.
+    fn lower_array_collect(
+        &mut self,
+        ctx: &mut FuncCtx,
+        source: &Node<Expr>,
+        mapper: Option<&Node<Expr>>,
+        span: &Span,
+    ) -> Option<Value> {
+        let node = |expr: Expr| Node::new(expr, *span);
+        let ident = |name: &str| node(Expr::Ident(Ident::new(name)));
+        let decl = |kind: VarDeclKind, name: &str, init: Node<Expr>| VarDecl {
+            kind,
+            declarations: vec![VarDeclarator {
+                pattern: Node::new(
+                    Pattern::Ident { name: Node::new(Ident::new(name), *span), type_annotation: None, ownership: None },
+                    *span,
+                ),
+                init: Some(init),
+            }],
+        };
+        let array_like = self.is_array_like(&source.value);
+        let out = format!("__array_from_{}", span.start);
+        let index = format!("__array_from_i_{}", span.start);
+        let value = format!("__array_from_v_{}", span.start);
+        self.push_scope();
+
+        // The mapper is called through a binding, with its unannotated
+        // parameters typed from the source's values and their index
+        let callee = match mapper.map(|mapper| &mapper.value) {
+            Some(Expr::Ident(name)) => Some(name.name.clone()),
+            Some(mapper) => {
+                let value_type = if array_like {
+                    Some(Type::Primitive(PrimitiveType::Undefined))
+                } else {
+                    self.iterable_element_value_type(source)
+                };
+                let mapper = Self::with_param_types(mapper, &[value_type, Some(Type::Primitive(PrimitiveType::Number))], span);
+                let name = format!("__array_from_fn_{}", span.start);
+                self.lower_var_decl(ctx, &decl(VarDeclKind::Const, &name, node(mapper)), span);
+                Some(name)
+            }
+            None => None,
+        };
+        let element = match &callee {
+            Some(callee) => {
+                let value = if array_like { node(Expr::Literal(Literal::Undefined)) } else { ident(&value) };
+                // Only as many arguments as the mapper declares params for
+                let mut args = vec![value, ident(&index)];
+                args.truncate(self.mapper_arity(mapper?, callee));
+                node(Expr::Call { callee: Box::new(ident(callee)), type_args: None, args })
+            }
+            None if array_like => node(Expr::Literal(Literal::Undefined)),
+            None => ident(&value),
+        };
+
+        let elem_type = match &element.value {
+            Expr::Literal(_) => IrType::Ptr,
+            Expr::Call { .. } => self.infer_expr_type(&element.value),
+            _ => self.iterable_element_type(&source.value),
+        };
+        let array_type = IrType::Array(Box::new(elem_type));
+        let out_local = ctx.add_local(array_type.clone());
+        self.define_var(&out, VarInfo { local_id: out_local, ir_type: array_type.clone(), is_boxed: false });
+        ctx.emit(Instruction::Assign { dest: Place::from_local(out_local), value: RValue::ArrayInit(vec![]) });
+        self.lower_var_decl(ctx, &decl(VarDeclKind::Let, &index, node(Expr::Literal(Literal::Number(0.0)))), span);
+
+        let push = node(Expr::Call {
+            callee: Box::new(node(Expr::Member {
+                object: Box::new(ident(&out)),
+                property: Node::new(Ident::new("push"), *span),
+                computed: false,
+            })),
+            type_args: None,
+            args: vec![element],
+        });
+        let step = node(Expr::Assignment {
+            target: Box::new(ident(&index)),
+            op: AssignmentOp::AddAssign,
+            value: Box::new(node(Expr::Literal(Literal::Number(1.0)))),
+        });
+        let body = |stmts: Vec<Node<Expr>>| {
+            let stmts = stmts.into_iter().map(|expr| Node::new(Stmt::Expr(expr), *span)).collect();
+            Box::new(Node::new(Stmt::Block(BlockStmt { stmts }), *span))
+        };
+        let stmt = if array_like {
+            // for (const length = source.length; index < length; index += 1) out.push(mapper(undefined, index))
+            let length = format!("__array_from_length_{}", span.start);
+            let source_length = node(Expr::Member {
+                object: Box::new(source.clone()),
+                property: Node::new(Ident::new("length"), *span),
+                computed: false,
+            });
+            Stmt::For {
+                init: Some(ForInit::VarDecl(decl(VarDeclKind::Const, &length, source_length))),
+                condition: Some(node(Expr::Binary {
+                    left: Box::new(ident(&index)),
+                    op: BinaryOp::Lt,
+                    right: Box::new(ident(&length)),
+                })),
+                update: Some(step),
+                body: body(vec![push]),
+            }
+        } else {
+            // for (const value of source) { out.push(mapper(value, index)); index += 1; }
+            Stmt::ForOf {
+                left: ForInLeft::Pattern(Node::new(
+                    Pattern::Ident { name: Node::new(Ident::new(&value), *span), type_annotation: None, ownership: None },
+                    *span,
+                )),
+                right: source.clone(),
+                body: body(vec![push, step]),
+                is_await: false,
+            }
+        };
+        self.lower_stmt(ctx, &stmt, span);
+        self.pop_scope();
+
+        let result = ctx.add_temp(array_type);
+        ctx.emit(Instruction::Assign { dest: Place::from_temp(result), value: RValue::Use(Value::Local(out_local)) });
+        Some(Value::Temp(result))
+    }
+
+    /// How many params a callback declares: those of an inline arrow or
+    /// function expression, or of the function or closure `callee` names.
+    fn mapper_arity(&self, mapper: &Node<Expr>, callee: &str) -> usize {
+        if let Some(Expr::Arrow { params, .. } | Expr::Function { params, .. }) = Self::unwrap_function_expr(&mapper.value) {
+            return params.len();
+        }
+        match self.closure_bindings.get(callee) {
+            Some(closure) => self.module.find_function(&closure.func_name).map_or(usize::MAX, |func| {
+                func.params.len() - usize::from(closure.env_struct_id.is_some())
+            }),
+            None => self.module.find_function(callee).map_or(usize::MAX, |func| func.params.len()),
+        }
+    }
+
+    /// A copy of an arrow or function expression whose unannotated
+    /// parameters are annotated with `types`, positionally.
+    fn with_param_types(func: &Expr, types: &[Option<Type>], span: &Span) -> Expr {
+        let mut func = Self::unwrap_function_expr(func).unwrap_or(func).clone();
+        if let Expr::Arrow { params, .. } | Expr::Function { params, .. } = &mut func {
+            for (param, ty) in params.iter_mut().zip(types) {
+                if let (None, Some(ty)) = (Self::param_annotation(param), ty) {
+                    param.type_annotation = Some(Box::new(Node::new(ty.clone(), *span)));
+                }
+            }
+        }
+        func
+    }
+
+    /// Type of `Array.from(source, mapper?)` before it is lowered: an array
+    /// of what the mapper returns, or of the source's values.
+    fn array_from_type(&self, args: &[Node<Expr>]) -> IrType {
+        let elem = match (args.first(), args.get(1)) {
+            (_, Some(mapper)) => match Self::unwrap_function_expr(&mapper.value) {
+                Some(func) => self.infer_function_expr_return_type(func),
+                None => self.infer_expr_type(&Expr::Call {
+                    callee: Box::new(mapper.clone()),
+                    type_args: None,
+                    args: Vec::new(),
+                }),
+            },
+            (Some(source), None) if self.is_array_like(&source.value) => IrType::Ptr,
+            (Some(source), None) => self.iterable_element_type(&source.value),
+            (None, None) => IrType::F64,
+        };
+        IrType::Array(Box::new(elem))
+    }
+
+    /// An object with a `length` that is not itself iterable, such as
+    /// `{ length: n }`, whose elements `Array.from` reads as `undefined`.
+    fn is_array_like(&self, expr: &Expr) -> bool {
+        !matches!(self.infer_expr_type(expr), IrType::Str | IrType::Array(_))
+            && self.expr_generator_types(expr).is_none()
+            && self.class_iterator(expr).is_none()
+            && self.expr_object_shape(expr).is_some_and(|shape| shape.iter().any(|(key, _)| key == "length"))
+    }
+
+    /// Type of the values iterating `expr` produces: the characters of a
+    /// string, the elements of an array, or what a generator or an iterable
+    /// class instance yields.
+    fn iterable_element_type(&self, expr: &Expr) -> IrType {
+        if let Some(types) = self.expr_generator_types(expr) {
+            return types.yield_ty;
+        }
+        if let Some((_, iterator_class)) = self.class_iterator(expr) {
+            return self.iterator_value_type(&iterator_class);
+        }
+        match self.infer_expr_type(expr) {
+            IrType::Str => IrType::Str,
+            IrType::Array(elem) => *elem,
+            _ => IrType::F64,
+        }
+    }
+
+    /// Annotated type of the values iterating `expr` produces, for typing
+    /// the parameters of a callback that receives them.
+    fn iterable_element_value_type(&self, expr: &Node<Expr>) -> Option<Type> {
+        if let Some(Type::Array(elem)) = self.expr_value_type(&expr.value).map(|ty| self.resolve_value_type(&ty)) {
+            return Some(elem.value);
+        }
+        Some(match self.iterable_element_type(&expr.value) {
+            IrType::F64 | IrType::I64 => Type::Primitive(PrimitiveType::Number),
+            IrType::Str => Type::Primitive(PrimitiveType::String),
+            IrType::Bool => Type::Primitive(PrimitiveType::Boolean),
+            IrType::Struct(struct_id) => Type::TypeRef {
+                name: Node::new(Ident::new(self.class_name_for_struct(struct_id)?), expr.span),
+                type_args: None,
+            },
+            _ => return None,
+        })
+    }
+
+    /// The `[Symbol.iterator]()` method of a class instance whose iterator is
+    /// a class instance too: the method's function and the iterator's class.
+    fn class_iterator(&self, expr: &Expr) -> Option<(String, String)> {
+        let IrType::Struct(struct_id) = self.infer_expr_type(expr) else {
+            return None;
+        };
+        let iterator_func = format!("{}_{}", self.class_name_for_struct(struct_id)?, symbol_key("iterator"));
+        match self.module.find_function(&iterator_func)?.return_type {
+            IrType::Struct(iterator_id) => Some((iterator_func, self.class_name_for_struct(iterator_id)?)),
+            _ => None,
+        }
+    }
+
+    /// Lower an iterable to an inline array of the values iterating it
+    /// produces: a string's characters, what a generator or iterable class
+    /// instance yields, or an array as it is.
+    fn lower_iterable_to_array(&mut self, ctx: &mut FuncCtx, iterable: &Node<Expr>) -> Option<Value> {
+        if self.expr_generator_types(&iterable.value).is_some() || self.class_iterator(&iterable.value).is_some() {
+            return self.lower_array_collect(ctx, iterable, None, &iterable.span);
+        }
+        let value = self.lower_expr(ctx, &iterable.value, &iterable.span)?;
+        if self.infer_expr_type(&iterable.value) != IrType::Str {
+            return Some(value);
+        }
+        Some(self.emit_str_chars(ctx, value))
+    }
+
+    /// The characters of a string value, as an array of strings.
+    fn emit_str_chars(&mut self, ctx: &mut FuncCtx, string: Value) -> Value {
+        let array_type = IrType::Array(Box::new(IrType::Str));
+        self.ensure_extern("zaco_str_chars", vec![IrType::Str], array_type.clone());
+        let chars = ctx.add_temp(array_type);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(chars)),
+            func: Value::Const(Constant::Str("zaco_str_chars".to_string())),
+            args: vec![string],
+        });
+        Value::Temp(chars)
+    }
+
+    /// A new array of the elements of `array` followed by those of `values`;
+    /// a null `array` copies `values`.
+    fn emit_array_append_all(&mut self, ctx: &mut FuncCtx, array: Value, values: Value) -> Value {
+        self.ensure_extern("zaco_array_append_all", vec![IrType::Ptr, IrType::Ptr], IrType::Ptr);
+        let result = ctx.add_temp(IrType::Array(Box::new(IrType::F64)));
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str("zaco_array_append_all".to_string())),
+            args: vec![array, values],
+        });
+        Value::Temp(result)
+    }
+
+    /// Lower JSON method calls to runtime functions.
+    fn lower_json_method(
+        &mut self,
+        ctx: &mut FuncCtx,
+        method: &str,
+        args: &[Node<Expr>],
+        _span: &Span,
     ) -> Option<Value> {
         let (runtime_fn, param_types, return_type) = match method {
-            "exit" => ("zaco_process_exit", vec![IrType::I64], IrType::Void),
-            "cwd" => ("zaco_process_cwd", vec![], IrType::Str),
-            "pid" => ("zaco_process_pid", vec![], IrType::I64),
-            "platform" => ("zaco_process_platform", vec![], IrType::Str),
-            "arch" => ("zaco_process_arch", vec![], IrType::Str),
+            "parse" => ("zaco_json_parse", vec![IrType::Str], IrType::Str),
+            "stringify" => return self.lower_json_stringify(ctx, args),
             _ => return None,
         };
 
@@ -1753,90 +5095,287 @@ impl Lowerer {
             }
         }
 
-        if return_type == IrType::Void {
-            ctx.emit(Instruction::Call {
-                dest: None,
-                func: Value::Const(Constant::Str(runtime_fn.to_string())),
-                args: arg_vals,
-            });
-            None
-        } else {
-            let temp = ctx.add_temp(return_type);
+        let temp = ctx.add_temp(return_type);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(temp)),
+            func: Value::Const(Constant::Str(runtime_fn.to_string())),
+            args: arg_vals,
+        });
+
+        Some(Value::Temp(temp))
+    }
+
+    /// Lower `JSON.stringify(value, replacer, space)`. References of known
+    /// shape are written by the runtime from their shape string, with a
+    /// class instance first turned into a plain object (see `json_value`).
+    /// The replacer is evaluated but not applied; `space` is a number of
+    /// spaces or an indent string.
+    fn lower_json_stringify(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>]) -> Option<Value> {
+        let value_arg = args.first()?;
+        let ty = self.infer_expr_type(&value_arg.value);
+        let val = self.lower_expr(ctx, &value_arg.value, &value_arg.span)?;
+        let (val, shape) = match ty {
+            IrType::Struct(_) => {
+                let shape = self.json_shape(&ty, &mut Vec::new());
+                (self.json_value(ctx, val, &ty).0, shape)
+            }
+            IrType::Str => (val, "s".to_string()),
+            _ if ty.is_pointer() => (val, self.structured_clone_shape(&value_arg.value)),
+            _ => (val, "p".to_string()),
+        };
+        // An array replacer lists the keys to keep; null and undefined
+        // keep them all
+        let mut keys = Value::Const(Constant::Null);
+        if let Some(replacer) = args.get(1) {
+            let no_replacer = matches!(replacer.value, Expr::Literal(Literal::Null | Literal::Undefined))
+                || matches!(&replacer.value, Expr::Ident(ident) if ident.name == "undefined");
+            if !no_replacer {
+                let keys_array = match &replacer.value {
+                    Expr::Array(elems) => elems.iter().flatten().all(|elem| self.infer_expr_type(&elem.value) == IrType::Str),
+                    other => self.infer_expr_type(other) == IrType::Array(Box::new(IrType::Str)),
+                };
+                if !keys_array {
+                    self.unsupported("a `JSON.stringify` replacer other than an array of keys", &replacer.span);
+                    return None;
+                }
+                keys = self.lower_expr(ctx, &replacer.value, &replacer.span)?;
+            }
+        }
+        let mut spaces = Value::Const(Constant::F64(0.0));
+        let mut gap = Value::Const(Constant::Null);
+        if let Some(space) = args.get(2) {
+            let space_val = self.lower_expr(ctx, &space.value, &space.span)?;
+            match ctx.func.value_type(&space_val) {
+                Some(IrType::Str) => gap = space_val,
+                Some(IrType::F64) => spaces = space_val,
+                _ => {}
+            }
+        }
+
+        // Values of unknown shape keep the plain string conversion
+        if shape == "p" {
+            self.ensure_extern("zaco_json_stringify", vec![IrType::Ptr], IrType::Str);
+            let temp = ctx.add_temp(IrType::Str);
             ctx.emit(Instruction::Call {
                 dest: Some(Place::from_temp(temp)),
-                func: Value::Const(Constant::Str(runtime_fn.to_string())),
-                args: arg_vals,
+                func: Value::Const(Constant::Str("zaco_json_stringify".to_string())),
+                args: vec![val],
             });
-            Some(Value::Temp(temp))
+            return Some(Value::Temp(temp));
         }
+        self.ensure_extern(
+            "zaco_json_stringify_value",
+            vec![IrType::Ptr, IrType::Str, IrType::F64, IrType::Ptr, IrType::Ptr],
+            IrType::Str,
+        );
+        let shape = self.str_const(&shape);
+        let temp = ctx.add_temp(IrType::Str);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(temp)),
+            func: Value::Const(Constant::Str("zaco_json_stringify_value".to_string())),
+            args: vec![val, shape, spaces, gap, keys],
+        });
+        Some(Value::Temp(temp))
     }
 
-    /// Look up the runtime function name, parameter types, and return type for an
-    /// imported (module, func_name) pair. Returns `None` for unknown imports.
-    fn imported_func_signature(module: &str, func_name: &str) -> Option<(&'static str, Vec<IrType>, IrType)> {
-        let sig = match (module, func_name) {
-            // fs module
-            ("fs", "readFileSync") => ("zaco_fs_read_file_sync", vec![IrType::Str, IrType::Str], IrType::Str),
-            ("fs", "writeFileSync") => ("zaco_fs_write_file_sync", vec![IrType::Str, IrType::Str], IrType::Void),
-            ("fs", "existsSync") => ("zaco_fs_exists_sync", vec![IrType::Str], IrType::Bool),
-            ("fs", "mkdirSync") => ("zaco_fs_mkdir_sync", vec![IrType::Str, IrType::I64], IrType::Void),
-            // TODO: fs.readFile async callback API not yet safely supported.
-            // Closures are lowered as struct pointers, but the runtime expects
-            // extern "C" fn(*const c_char, *const c_char). Needs a trampoline mechanism.
-            // ("fs", "readFile") => ("zaco_fs_read_file", vec![IrType::Str, IrType::Str, IrType::Ptr], IrType::Void),
+    /// A value as JSON sees it: a class instance becomes the result of its
+    /// `toJSON()`, or else a plain object of its fields built by the class's
+    /// serializer. Integers are stored as numbers.
+    fn json_value(&mut self, ctx: &mut FuncCtx, val: Value, ty: &IrType) -> (Value, IrType) {
+        match ty {
+            IrType::I64 => {
+                let num = ctx.add_temp(IrType::F64);
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(num),
+                    value: RValue::Cast { value: val, ty: IrType::F64 },
+                });
+                (Value::Temp(num), IrType::F64)
+            }
+            IrType::Struct(struct_id) => {
+                let Some(class_name) = self.class_name_for_struct(*struct_id) else {
+                    return (val, IrType::Ptr);
+                };
+                let (func, ret) = match self.to_json_return_type(&class_name) {
+                    Some(ret) => (format!("{}_toJSON", class_name), ret),
+                    None => (self.ensure_json_serializer(&class_name), IrType::Ptr),
+                };
+                let result = ctx.add_temp(ret.clone());
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(result)),
+                    func: Value::Const(Constant::Str(func)),
+                    args: vec![val],
+                });
+                (Value::Temp(result), ret)
+            }
+            _ => (val, ty.clone()),
+        }
+    }
 
-            // path module
-            ("path", "join") => ("zaco_path_join", vec![IrType::Str, IrType::Str], IrType::Str),
-            ("path", "resolve") => ("zaco_path_resolve", vec![IrType::Str], IrType::Str),
-            ("path", "dirname") => ("zaco_path_dirname", vec![IrType::Str], IrType::Str),
-            ("path", "basename") => ("zaco_path_basename", vec![IrType::Str], IrType::Str),
-            ("path", "extname") => ("zaco_path_extname", vec![IrType::Str], IrType::Str),
+    /// Return type of a class's `toJSON()`, or None when it has none.
+    fn to_json_return_type(&self, class_name: &str) -> Option<IrType> {
+        let ci = self.class_info.get(class_name)?;
+        if !ci.methods.iter().any(|m| m == "toJSON") {
+            return None;
+        }
+        if let Some(func) = self.module.find_function(&format!("{}_toJSON", class_name)) {
+            return Some(func.return_type.clone());
+        }
+        Some(match ci.method_returns.iter().find(|(name, _)| name == "toJSON") {
+            Some((_, ty)) => self.ast_type_to_ir(ty),
+            None => IrType::Ptr,
+        })
+    }
 
-            // os module
-            ("os", "platform") => ("zaco_os_platform", vec![], IrType::Str),
-            ("os", "arch") => ("zaco_os_arch", vec![], IrType::Str),
-            ("os", "homedir") => ("zaco_os_homedir", vec![], IrType::Str),
-            ("os", "tmpdir") => ("zaco_os_tmpdir", vec![], IrType::Str),
-            ("os", "hostname") => ("zaco_os_hostname", vec![], IrType::Str),
-            ("os", "cpus") => ("zaco_os_cpus", vec![], IrType::Ptr),
+    /// Fields of a class that JSON shows: private `#fields` are left out.
+    fn json_fields(ci: &ClassInfo) -> Vec<(String, IrType)> {
+        ci.fields
+            .iter()
+            .enumerate()
+            .filter(|(i, (name, _))| !name.starts_with('#') && ci.field_index(name) == Some(*i))
+            .map(|(_, field)| field.clone())
+            .collect()
+    }
 
-            // process module
-            ("process", "exit") => ("zaco_process_exit", vec![IrType::I64], IrType::Void),
-            ("process", "cwd") => ("zaco_process_cwd", vec![], IrType::Str),
+    /// Shape (see `type_clone_shape`) of what `json_value` gives for a value
+    /// of this type. `enclosing` holds the classes whose objects are being
+    /// described, innermost last; a reference back to one becomes `^N`.
+    fn json_shape(&self, ty: &IrType, enclosing: &mut Vec<String>) -> String {
+        match ty {
+            IrType::F64 | IrType::I64 => "n".to_string(),
+            IrType::Bool => "b".to_string(),
+            IrType::Str => "s".to_string(),
+            IrType::FuncPtr(_) => "f".to_string(),
+            // Array elements are not converted, so instances in them stay opaque
+            IrType::Array(elem) if matches!(**elem, IrType::Struct(_)) => "[p".to_string(),
+            IrType::Array(elem) => format!("[{}", self.json_shape(elem, enclosing)),
+            IrType::Struct(struct_id) => {
+                let Some(class_name) = self.class_name_for_struct(*struct_id) else {
+                    return "p".to_string();
+                };
+                if let Some(ret) = self.to_json_return_type(&class_name) {
+                    let annotated = self
+                        .class_info
+                        .get(&class_name)
+                        .and_then(|ci| ci.method_returns.iter().find(|(name, _)| name == "toJSON"));
+                    return match annotated {
+                        Some((_, ty)) => self.type_clone_shape(ty, &mut Vec::new()),
+                        None if matches!(ret, IrType::Struct(_)) => "p".to_string(),
+                        None => self.json_shape(&ret, &mut Vec::new()),
+                    };
+                }
+                if let Some(pos) = enclosing.iter().rposition(|name| *name == class_name) {
+                    return format!("^{}", enclosing.len() - 1 - pos);
+                }
+                let Some(ci) = self.class_info.get(&class_name) else {
+                    return "p".to_string();
+                };
+                let fields = Self::json_fields(ci);
+                enclosing.push(class_name);
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, ty)| format!("{}:{}", name, self.json_shape(ty, enclosing)))
+                    .collect();
+                enclosing.pop();
+                format!("{{{}}}", fields.join(","))
+            }
+            _ => "p".to_string(),
+        }
+    }
 
-            // http module
-            ("http", "get") => ("zaco_http_get", vec![IrType::Str], IrType::Str),
-            ("http", "post") => ("zaco_http_post", vec![IrType::Str, IrType::Str, IrType::Str], IrType::Str),
-            ("http", "put") => ("zaco_http_put", vec![IrType::Str, IrType::Str, IrType::Str], IrType::Str),
-            ("http", "delete") => ("zaco_http_delete", vec![IrType::Str], IrType::Str),
+    /// Emit `__zaco_json_<Class>(self)` once per class: a plain object with
+    /// the instance's fields, each passed through `json_value`, or null for
+    /// a null instance. Returns the function's name.
+    fn ensure_json_serializer(&mut self, class_name: &str) -> String {
+        let name = format!("__zaco_json_{}", class_name);
+        if !self.json_serializers.insert(class_name.to_string()) {
+            return name;
+        }
+        let Some(ci) = self.class_info.get(class_name).cloned() else {
+            return name;
+        };
 
-            _ => return None,
+        let self_param = LocalId(0);
+        let mut func = IrFunction::new(
+            self.alloc_func_id(),
+            name.clone(),
+            vec![(self_param, IrType::Struct(ci.struct_id))],
+            IrType::Ptr,
+        );
+        let entry = func.new_block();
+        func.entry_block = entry;
+        let mut ctx = FuncCtx {
+            func: &mut func,
+            current_block: entry,
         };
-        Some(sig)
+
+        let is_null = self.emit_is_nullish(&mut ctx, Value::Local(self_param));
+        let null_block = ctx.new_block();
+        let body_block = ctx.new_block();
+        ctx.set_terminator(Terminator::Branch {
+            cond: is_null,
+            then_block: null_block,
+            else_block: body_block,
+        });
+        ctx.switch_to(null_block);
+        ctx.set_terminator(Terminator::Return(Some(Value::Const(Constant::Null))));
+
+        ctx.switch_to(body_block);
+        self.ensure_extern("zaco_object_new", vec![], IrType::Ptr);
+        let obj = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(obj)),
+            func: Value::Const(Constant::Str("zaco_object_new".to_string())),
+            args: vec![],
+        });
+        for (field, ty) in Self::json_fields(&ci) {
+            let Some(val) = self.load_struct_field(&mut ctx, Value::Local(self_param), class_name, &field) else {
+                continue;
+            };
+            let (val, ty) = self.json_value(&mut ctx, val, &ty);
+            let key = self.str_const(&field);
+            self.emit_object_set(&mut ctx, Value::Temp(obj), key, val, &ty);
+        }
+        ctx.set_terminator(Terminator::Return(Some(Value::Temp(obj))));
+
+        self.module.add_function(func);
+        name
     }
 
-    /// Lower imported function calls (fs, path, os, process, http modules).
-    fn lower_imported_function_call(
+    /// Lower process method calls to runtime functions.
+    fn lower_process_method(
         &mut self,
         ctx: &mut FuncCtx,
-        module: &str,
-        func_name: &str,
+        method: &str,
         args: &[Node<Expr>],
         _span: &Span,
     ) -> Option<Value> {
-        let (runtime_fn, param_types, return_type) = match Self::imported_func_signature(module, func_name) {
-            Some(sig) => sig,
-            None => return None, // Unknown import
+        if method == "on" {
+            return self.lower_process_on(ctx, args);
+        }
+        let (runtime_fn, param_types, return_type) = match method {
+            "exit" => ("zaco_process_exit", vec![IrType::I64], IrType::Void),
+            "cwd" => ("zaco_process_cwd", vec![], IrType::Str),
+            "pid" => ("zaco_process_pid", vec![], IrType::I64),
+            "platform" => ("zaco_process_platform", vec![], IrType::Str),
+            "arch" => ("zaco_process_arch", vec![], IrType::Str),
+            _ => return None,
         };
 
-        self.ensure_extern(runtime_fn, param_types, return_type.clone());
+        self.ensure_extern(runtime_fn, param_types.clone(), return_type.clone());
 
         let mut arg_vals = Vec::new();
-        for arg in args {
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                arg_vals.push(val);
+        for (arg, param_type) in args.iter().zip(&param_types) {
+            let val = self.lower_expr(ctx, &arg.value, &arg.span)?;
+            // The exit code is an integer; numbers would reach it as raw f64 bits
+            if *param_type == IrType::I64 && ctx.func.value_type(&val) == Some(IrType::F64) {
+                let code = ctx.add_temp(IrType::I64);
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(code),
+                    value: RValue::Cast { value: val, ty: IrType::I64 },
+                });
+                arg_vals.push(Value::Temp(code));
             } else {
-                return None;
+                arg_vals.push(val);
             }
         }
 
@@ -1858,8 +5397,391 @@ impl Lowerer {
         }
     }
 
-    /// Lower ternary/conditional expression: `cond ? then : else`
-    fn lower_ternary(
+    /// Lower `process.on(event, callback)` to `zaco_process_on(event, fn, env)`.
+    /// The callback is passed by address; the runtime calls `fn(env)`, which
+    /// is how a closure expects to be called, so listeners may capture
+    /// variables.
+    fn lower_process_on(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>]) -> Option<Value> {
+        let [event, callback] = args else {
+            return None;
+        };
+        let event_val = self.lower_expr(ctx, &event.value, &event.span)?;
+        let (callback_val, context) = match self.lower_expr(ctx, &callback.value, &callback.span)? {
+            Value::Const(Constant::Str(name)) => {
+                let env = self.closure_bindings.get(&name).and_then(|c| c.env_local).map(Value::Local);
+                (Value::Const(Constant::FuncAddr(name)), env)
+            }
+            other => (other, None),
+        };
+        self.ensure_extern("zaco_process_on", vec![IrType::Str, IrType::Ptr, IrType::Ptr], IrType::Void);
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_process_on".to_string())),
+            args: vec![event_val, callback_val, context.unwrap_or(Value::Const(Constant::Null))],
+        });
+        None
+    }
+
+    /// Lower property reads on `process`, including `process.stdout.isTTY`
+    /// and `process.stdout.columns`, to runtime calls.
+    fn lower_process_property(&mut self, ctx: &mut FuncCtx, object: &Expr, property: &str) -> Option<Value> {
+        let (runtime_fn, return_type) = match (object, property) {
+            (Expr::Ident(obj), "pid") if obj.name == "process" => ("zaco_process_pid", IrType::I64),
+            (Expr::Ident(obj), "platform") if obj.name == "process" => ("zaco_process_platform", IrType::Str),
+            (Expr::Ident(obj), "arch") if obj.name == "process" => ("zaco_process_arch", IrType::Str),
+            (Expr::Member { object: inner, property: stream, .. }, _)
+                if matches!(&inner.value, Expr::Ident(obj) if obj.name == "process")
+                    && stream.value.name == "stdout" =>
+            {
+                match property {
+                    "isTTY" => ("zaco_process_stdout_is_tty", IrType::Bool),
+                    "columns" => ("zaco_process_stdout_columns", IrType::F64),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        self.ensure_extern(runtime_fn, vec![], return_type.clone());
+        let temp = ctx.add_temp(return_type);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(temp)),
+            func: Value::Const(Constant::Str(runtime_fn.to_string())),
+            args: vec![],
+        });
+        Some(Value::Temp(temp))
+    }
+
+    /// Call a function exported by a local module, which keeps its name
+    /// when the modules are merged, so is called directly.
+    fn lower_local_import_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        name: String,
+        param_types: &[IrType],
+        return_type: IrType,
+        args: &[Node<Expr>],
+    ) -> Option<Value> {
+        let mut arg_vals = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            arg_vals.push(self.lower_arg(ctx, arg, param_types.get(i))?);
+        }
+        let dest = (return_type != IrType::Void).then(|| Place::from_temp(ctx.add_temp(return_type)));
+        ctx.emit(Instruction::Call {
+            dest: dest.clone(),
+            func: Value::Const(Constant::Str(name)),
+            args: arg_vals,
+        });
+        dest.map(|p| p.base)
+    }
+
+    /// Look up the runtime function name, parameter types, and return type for an
+    /// imported (module, func_name) pair. Returns `None` for unknown imports.
+    fn imported_func_signature(module: &str, func_name: &str) -> Option<(&'static str, Vec<IrType>, IrType)> {
+        let sig = match (module, func_name) {
+            // fs module
+            ("fs", "readFileSync") => ("zaco_fs_read_file_sync", vec![IrType::Str, IrType::Str], IrType::Str),
+            ("fs", "writeFileSync") => ("zaco_fs_write_file_sync", vec![IrType::Str, IrType::Str], IrType::Void),
+            ("fs", "existsSync") => ("zaco_fs_exists_sync", vec![IrType::Str], IrType::Bool),
+            ("fs", "mkdirSync") => ("zaco_fs_mkdir_sync", vec![IrType::Str, IrType::I64], IrType::Void),
+            ("fs" | "glob", "globSync") => ("zaco_glob_sync", vec![IrType::Str, IrType::Ptr], IrType::Array(Box::new(IrType::Str))),
+            // TODO: fs.readFile async callback API not yet safely supported.
+            // Closures are lowered as struct pointers, but the runtime expects
+            // extern "C" fn(*const c_char, *const c_char). Needs a trampoline mechanism.
+            // ("fs", "readFile") => ("zaco_fs_read_file", vec![IrType::Str, IrType::Str, IrType::Ptr], IrType::Void),
+
+            // path module
+            ("path", "join") => ("zaco_path_join", vec![IrType::Str, IrType::Str], IrType::Str),
+            ("path", "resolve") => ("zaco_path_resolve", vec![IrType::Str], IrType::Str),
+            ("path", "dirname") => ("zaco_path_dirname", vec![IrType::Str], IrType::Str),
+            ("path", "basename") => ("zaco_path_basename", vec![IrType::Str], IrType::Str),
+            ("path", "extname") => ("zaco_path_extname", vec![IrType::Str], IrType::Str),
+
+            // os module
+            ("os", "platform") => ("zaco_os_platform", vec![], IrType::Str),
+            ("os", "arch") => ("zaco_os_arch", vec![], IrType::Str),
+            ("os", "homedir") => ("zaco_os_homedir", vec![], IrType::Str),
+            ("os", "tmpdir") => ("zaco_os_tmpdir", vec![], IrType::Str),
+            ("os", "hostname") => ("zaco_os_hostname", vec![], IrType::Str),
+            ("os", "cpus") => ("zaco_os_cpus", vec![], IrType::Ptr),
+
+            // process module
+            ("process", "exit") => ("zaco_process_exit", vec![IrType::I64], IrType::Void),
+            ("process", "cwd") => ("zaco_process_cwd", vec![], IrType::Str),
+
+            // http module
+            ("http", "get") => ("zaco_http_get", vec![IrType::Str], IrType::Str),
+            ("http", "post") => ("zaco_http_post", vec![IrType::Str, IrType::Str, IrType::Str], IrType::Str),
+            ("http", "put") => ("zaco_http_put", vec![IrType::Str, IrType::Str, IrType::Str], IrType::Str),
+            ("http", "delete") => ("zaco_http_delete", vec![IrType::Str], IrType::Str),
+
+            // sqlite module (Database methods: see lower_sqlite_method)
+            ("sqlite", "open") => ("zaco_sqlite_open", vec![IrType::Str], IrType::Ptr),
+
+            // timers module: sleep(ms, { signal }?) -> Promise<void>
+            ("timers", "sleep") => ("zaco_sleep", vec![IrType::F64, IrType::Ptr], IrType::Ptr),
+
+            // util module (isDeepStrictEqual is lowered by lower_is_deep_strict_equal)
+            ("util", "isDeepStrictEqual") => ("zaco_deep_equal", vec![IrType::Ptr, IrType::Ptr, IrType::Str], IrType::Bool),
+            ("util", "styleText") => ("zaco_style_text", vec![IrType::Str, IrType::Str], IrType::Str),
+
+            _ => return None,
+        };
+        Some(sig)
+    }
+
+    /// Lower imported function calls (fs, path, os, process, http, worker, sqlite modules).
+    fn lower_imported_function_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        module: &str,
+        func_name: &str,
+        args: &[Node<Expr>],
+        span: &Span,
+    ) -> Option<Value> {
+        if module == "worker" && func_name == "spawn" {
+            return self.lower_worker_spawn(ctx, args, span);
+        }
+        if module == "util" && func_name == "isDeepStrictEqual" {
+            return self.lower_is_deep_strict_equal(ctx, args, span);
+        }
+        if let Some((name, param_types, return_type)) = self.imported_functions.get(func_name).cloned() {
+            return self.lower_local_import_call(ctx, name, &param_types, return_type, args);
+        }
+
+        let Some((runtime_fn, param_types, return_type)) = Self::imported_func_signature(module, func_name) else {
+            self.unsupported(&format!("calling `{}` imported from \"{}\"", func_name, module), span);
+            return None;
+        };
+
+        let param_count = param_types.len();
+        self.ensure_extern(runtime_fn, param_types, return_type.clone());
+
+        let mut arg_vals = Vec::new();
+        for arg in args {
+            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
+                arg_vals.push(val);
+            } else {
+                return None;
+            }
+        }
+
+        // sleep(ms) / globSync(pattern) without an options object
+        if (module, func_name) == ("timers", "sleep") || func_name == "globSync" {
+            arg_vals.resize(param_count, Value::Const(Constant::Null));
+        }
+
+        if return_type == IrType::Void {
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str(runtime_fn.to_string())),
+                args: arg_vals,
+            });
+            None
+        } else {
+            let temp = ctx.add_temp(return_type);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(temp)),
+                func: Value::Const(Constant::Str(runtime_fn.to_string())),
+                args: arg_vals,
+            });
+            Some(Value::Temp(temp))
+        }
+    }
+
+    /// Lower `spawn("name", [args])` to `zaco_worker_spawn(name, args, shapes)`.
+    /// The runtime clones the arguments according to `shapes` and runs the
+    /// function's trampoline on a blocking thread.
+    fn lower_worker_spawn(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>], span: &Span) -> Option<Value> {
+        let name = match args.first().map(|a| &a.value) {
+            Some(Expr::Literal(Literal::String(name))) => name.clone(),
+            _ => {
+                self.errors.push(LowerError::new(
+                    "spawn() takes the name of an exported function as a string literal",
+                    *span,
+                ));
+                return None;
+            }
+        };
+        let Some(shapes) = self.worker_functions.get(&name).cloned() else {
+            self.errors.push(LowerError::new(
+                format!(
+                    "spawn(): '{}' is not an exported function of this module with structured-cloneable parameters",
+                    name
+                ),
+                *span,
+            ));
+            return None;
+        };
+
+        let args_val = match args.get(1) {
+            Some(Node { value: Expr::Array(elems), .. }) => {
+                let mut slots = Vec::new();
+                for elem in elems.iter().flatten() {
+                    slots.push(self.lower_array_slot(ctx, elem)?);
+                }
+                let temp = ctx.add_temp(IrType::Array(Box::new(IrType::F64)));
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(temp),
+                    value: RValue::ArrayInit(slots),
+                });
+                Value::Temp(temp)
+            }
+            Some(arg) => self.lower_expr(ctx, &arg.value, &arg.span)?,
+            None => Value::Const(Constant::Null),
+        };
+
+        self.ensure_extern("zaco_worker_spawn", vec![IrType::Str, IrType::Ptr, IrType::Str], IrType::Ptr);
+        self.module.intern_string(name.clone());
+        self.module.intern_string(shapes.clone());
+        let promise = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(promise)),
+            func: Value::Const(Constant::Str("zaco_worker_spawn".to_string())),
+            args: vec![
+                Value::Const(Constant::Str(name)),
+                args_val,
+                Value::Const(Constant::Str(shapes)),
+            ],
+        });
+        Some(Value::Temp(promise))
+    }
+
+    /// Result type of a spawned worker function. Like other calls, a function
+    /// not lowered yet is assumed to return a number.
+    fn worker_return_type(&self, name: &str) -> IrType {
+        self.module.find_function(name).map(|f| f.return_type.clone()).unwrap_or(IrType::F64)
+    }
+
+    /// Shape of a parameter for `zaco_structured_clone`, or `None` if values
+    /// of its type cannot be copied to another thread. Unannotated
+    /// parameters are numbers, as elsewhere in the lowerer.
+    fn param_clone_shape(param: &Param) -> Option<String> {
+        let annotation = param.type_annotation.as_ref().or(match &param.pattern.value {
+            Pattern::Ident { type_annotation, .. } => type_annotation.as_ref(),
+            _ => None,
+        });
+        match annotation {
+            Some(ty) => Self::clone_shape(&ty.value),
+            None => Some("n".to_string()),
+        }
+    }
+
+    /// Shape notation understood by the runtime: `n` number, `b` boolean,
+    /// `s` string, `[T` array of T, `{k:T,...}` plain object.
+    fn clone_shape(ty: &Type) -> Option<String> {
+        let shape = match ty {
+            Type::Primitive(PrimitiveType::Number) | Type::Literal(LiteralType::Number(_)) => "n".to_string(),
+            Type::Primitive(PrimitiveType::Boolean) | Type::Literal(LiteralType::Boolean(_)) => "b".to_string(),
+            Type::Primitive(PrimitiveType::String) | Type::Literal(LiteralType::String(_)) => "s".to_string(),
+            Type::Array(elem) => format!("[{}", Self::clone_shape(&elem.value)?),
+            Type::Generic { base, type_args } => match (&base.value, type_args.as_slice()) {
+                (Type::TypeRef { name, .. }, [elem]) if name.value.name == "Array" => {
+                    format!("[{}", Self::clone_shape(&elem.value)?)
+                }
+                _ => return None,
+            },
+            Type::Object(object) => {
+                let mut fields = Vec::new();
+                for member in &object.members {
+                    match member {
+                        ObjectTypeMember::Property { name: PropertyName::Ident(key), ty, .. } => {
+                            fields.push(format!("{}:{}", key.value.name, Self::clone_shape(&ty.value)?));
+                        }
+                        _ => return None,
+                    }
+                }
+                format!("{{{}}}", fields.join(","))
+            }
+            Type::Paren(inner) => Self::clone_shape(&inner.value)?,
+            _ => return None,
+        };
+        Some(shape)
+    }
+
+    /// Emit `__zaco_worker_<name>(args, out)` for each spawnable function.
+    /// The trampoline unpacks the argument array into parameters, calls the
+    /// function and stores its result to `out`, so the runtime can call any
+    /// of them through one signature.
+    fn lower_worker_trampolines(&mut self) {
+        let mut names: Vec<String> = self.worker_functions.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            let Some(target) = self.module.find_function(&name) else {
+                continue;
+            };
+            let param_types: Vec<IrType> = target.params.iter().map(|(_, ty)| ty.clone()).collect();
+            let return_type = target.return_type.clone();
+
+            let trampoline_name = format!("__zaco_worker_{}", name);
+            let (args_param, out_param) = (LocalId(0), LocalId(1));
+            let mut func = IrFunction::new(
+                self.alloc_func_id(),
+                trampoline_name.clone(),
+                vec![(args_param, IrType::Ptr), (out_param, IrType::Ptr)],
+                IrType::Void,
+            );
+            let entry = func.new_block();
+            func.entry_block = entry;
+            let mut ctx = FuncCtx {
+                func: &mut func,
+                current_block: entry,
+            };
+
+            let mut call_args = Vec::new();
+            for (i, ty) in param_types.iter().enumerate() {
+                let numeric = matches!(ty, IrType::F64 | IrType::I64 | IrType::Bool);
+                let (getter, slot_ty) = if numeric {
+                    ("zaco_array_get_f64", IrType::F64)
+                } else {
+                    ("zaco_array_get_ptr", IrType::Ptr)
+                };
+                self.ensure_extern(getter, vec![IrType::Ptr, IrType::I64], slot_ty.clone());
+                let slot = ctx.add_temp(if numeric { slot_ty } else { ty.clone() });
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(slot)),
+                    func: Value::Const(Constant::Str(getter.to_string())),
+                    args: vec![Value::Local(args_param), Value::Const(Constant::I64(i as i64))],
+                });
+                let arg = if matches!(ty, IrType::I64 | IrType::Bool) {
+                    let narrowed = ctx.add_temp(ty.clone());
+                    ctx.emit(Instruction::Assign {
+                        dest: Place::from_temp(narrowed),
+                        value: RValue::Cast { value: Value::Temp(slot), ty: ty.clone() },
+                    });
+                    narrowed
+                } else {
+                    slot
+                };
+                call_args.push(Value::Temp(arg));
+            }
+
+            if return_type == IrType::Void {
+                ctx.emit(Instruction::Call {
+                    dest: None,
+                    func: Value::Const(Constant::Str(name.clone())),
+                    args: call_args,
+                });
+            } else {
+                let result = ctx.add_temp(return_type);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(result)),
+                    func: Value::Const(Constant::Str(name.clone())),
+                    args: call_args,
+                });
+                ctx.emit(Instruction::Store {
+                    ptr: Value::Local(out_param),
+                    value: Value::Temp(result),
+                });
+            }
+            ctx.set_terminator(Terminator::Return(None));
+
+            self.module.add_function(func);
+            self.module.worker_entries.push((name, trampoline_name));
+        }
+    }
+
+    /// Lower ternary/conditional expression: `cond ? then : else`
+    fn lower_ternary(
         &mut self,
         ctx: &mut FuncCtx,
         condition: &Node<Expr>,
@@ -1867,9 +5789,15 @@ impl Lowerer {
         else_expr: &Node<Expr>,
         _span: &Span,
     ) -> Option<Value> {
+        // A constant condition (e.g. a `--define`d flag) picks its branch now
+        match self.constant_condition(&condition.value) {
+            Some(true) => return self.lower_expr(ctx, &then_expr.value, &then_expr.span),
+            Some(false) => return self.lower_expr(ctx, &else_expr.value, &else_expr.span),
+            None => {}
+        }
         let cond_val = self.lower_expr(ctx, &condition.value, &condition.span)?;
 
-        let result_type = self.infer_expr_type(&then_expr.value);
+        let result_type = Self::storage_type(self.infer_expr_type(&then_expr.value));
         let result_local = ctx.add_local(result_type.clone());
 
         let then_block = ctx.new_block();
@@ -1925,8 +5853,8 @@ impl Lowerer {
             }
             if i < exprs.len() {
                 if let Some(val) = self.lower_expr(ctx, &exprs[i].value, &exprs[i].span) {
-                    // TODO: coerce non-string values to string
-                    values.push(val);
+                    let ty = self.infer_expr_type(&exprs[i].value);
+                    values.push(self.stringify_value(ctx, val, &ty));
                 }
             }
         }
@@ -1948,18 +5876,94 @@ impl Lowerer {
         Some(Value::Temp(temp))
     }
 
+    /// Convert a number substitution to a string, and a class instance
+    /// through its `toString()`; other values pass through
+    fn stringify_value(&mut self, ctx: &mut FuncCtx, value: Value, ty: &IrType) -> Value {
+        let convert = match ty {
+            IrType::F64 => "zaco_f64_to_str",
+            IrType::I64 => "zaco_i64_to_str",
+            IrType::Struct(struct_id) => return self.stringify_instance(ctx, value, *struct_id),
+            _ => return value,
+        };
+        self.ensure_extern(convert, vec![ty.clone()], IrType::Str);
+        let result = ctx.add_temp(IrType::Str);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(convert.to_string())),
+            args: vec![value],
+        });
+        Value::Temp(result)
+    }
+
+    /// A class instance as a string: the result of its class's
+    /// `toString()`, or `[object ClassName]` when it has none.
+    fn stringify_instance(&mut self, ctx: &mut FuncCtx, value: Value, struct_id: StructId) -> Value {
+        let Some(class_name) = self.class_name_for_struct(struct_id) else {
+            return self.str_const("[object Object]");
+        };
+        let has_to_string = self
+            .class_info
+            .get(&class_name)
+            .is_some_and(|ci| ci.methods.iter().any(|m| m == "toString"));
+        if !has_to_string {
+            return self.str_const(&format!("[object {}]", class_name));
+        }
+        let result = ctx.add_temp(IrType::Str);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(format!("{}_toString", class_name))),
+            args: vec![value],
+        });
+        Value::Temp(result)
+    }
+
     fn lower_array_literal(
         &mut self,
         ctx: &mut FuncCtx,
         elements: &[Option<Node<Expr>>],
-        _span: &Span,
+        span: &Span,
     ) -> Option<Value> {
-        let mut vals = Vec::new();
-        for elem in elements {
-            if let Some(ref expr_node) = elem {
-                if let Some(val) = self.lower_expr(ctx, &expr_node.value, &expr_node.span) {
-                    vals.push(val);
+        // `[a, ...xs, b]`: each run of plain elements and each spread
+        // iterable is appended to the result in turn
+        if elements.iter().flatten().any(|elem| matches!(elem.value, Expr::Spread(_))) {
+            let mut result = Value::Const(Constant::Null);
+            let mut run = Vec::new();
+            for elem in elements.iter().flatten() {
+                let Expr::Spread(inner) = &elem.value else {
+                    run.push(Some(elem.clone()));
+                    continue;
+                };
+                if !run.is_empty() {
+                    let values = self.lower_expr(ctx, &Expr::Array(std::mem::take(&mut run)), span)?;
+                    result = self.emit_array_append_all(ctx, result, values);
                 }
+                let values = self.lower_iterable_to_array(ctx, inner)?;
+                result = self.emit_array_append_all(ctx, result, values);
+            }
+            if !run.is_empty() {
+                let values = self.lower_expr(ctx, &Expr::Array(run), span)?;
+                result = self.emit_array_append_all(ctx, result, values);
+            }
+            return Some(result);
+        }
+
+        // Booleans alone pack into bytes; next to other values, such as in
+        // a `[boolean, string]` tuple, they take an 8-byte slot too
+        let elem_types: Vec<IrType> =
+            elements.iter().flatten().map(|elem| self.infer_expr_type(&elem.value)).collect();
+        let mixed = elem_types.contains(&IrType::Bool) && elem_types.iter().any(|ty| *ty != IrType::Bool);
+        let mut vals = Vec::new();
+        for (expr_node, ty) in elements.iter().flatten().zip(elem_types) {
+            let Some(val) = self.lower_expr(ctx, &expr_node.value, &expr_node.span) else { continue };
+            if mixed && ty == IrType::Bool {
+                let slot = ctx.add_temp(IrType::I64);
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(slot),
+                    value: RValue::Cast { value: val, ty: IrType::I64 },
+                });
+                vals.push(Value::Temp(slot));
+            } else {
+                vals.push(val);
             }
         }
         let temp = ctx.add_temp(IrType::Array(Box::new(IrType::F64)));
@@ -1997,52 +6001,165 @@ impl Lowerer {
                     self.module.intern_string(key_str.clone());
                     let key_val = Value::Const(Constant::Str(key_str));
 
-                    if let Some(val) = self.lower_expr(ctx, &value.value, &value.span) {
+                    if let Some(val) = self.lower_function_value(ctx, value, None) {
                         let val_type = self.infer_expr_type(&value.value);
-                        let setter_name = match &val_type {
-                            IrType::Str => "zaco_object_set_str",
-                            IrType::F64 => "zaco_object_set_f64",
-                            IrType::I64 | IrType::Bool => "zaco_object_set_i64",
-                            _ => "zaco_object_set_ptr",
-                        };
-                        let setter_val_type = match &val_type {
-                            IrType::Str => IrType::Ptr,
-                            IrType::Bool => IrType::I64,
-                            other => other.clone(),
-                        };
-                        self.ensure_extern(
-                            setter_name,
-                            vec![IrType::Ptr, IrType::Ptr, setter_val_type],
-                            IrType::Void,
-                        );
-                        ctx.emit(Instruction::Call {
-                            dest: None,
-                            func: Value::Const(Constant::Str(setter_name.to_string())),
-                            args: vec![Value::Temp(obj_temp), key_val, val],
-                        });
+                        self.emit_object_set(ctx, Value::Temp(obj_temp), key_val, val, &val_type);
                     }
                 }
-                ObjectProperty::Spread(_) | ObjectProperty::Method { .. } => continue,
+                ObjectProperty::Spread(_)
+                | ObjectProperty::Method { .. }
+                | ObjectProperty::Getter { .. }
+                | ObjectProperty::Setter { .. } => continue,
             }
         }
 
+        // Accessors are compiled functions that get the object as `this`
+        let shape = self.object_literal_shape(props);
+        for prop in props {
+            let (key, getter, setter) = match prop {
+                ObjectProperty::Getter { key, body, .. } => {
+                    let Some(key) = Self::property_key(key) else { continue };
+                    let ty = shape.iter().find(|(name, _)| *name == key).map_or(IrType::F64, |(_, ty)| ty.clone());
+                    let getter = self.lower_object_accessor(&shape, &key, None, ty, body);
+                    (key, Value::Const(Constant::FuncAddr(getter)), Value::Const(Constant::Null))
+                }
+                ObjectProperty::Setter { key, param, body } => {
+                    let Some(key) = Self::property_key(key) else { continue };
+                    let setter = self.lower_object_accessor(&shape, &key, Some(param), IrType::Void, body);
+                    (key, Value::Const(Constant::Null), Value::Const(Constant::FuncAddr(setter)))
+                }
+                _ => continue,
+            };
+            self.ensure_extern(
+                "zaco_object_define_accessor",
+                vec![IrType::Ptr, IrType::Ptr, IrType::Ptr, IrType::Ptr],
+                IrType::Void,
+            );
+            self.module.intern_string(key.clone());
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str("zaco_object_define_accessor".to_string())),
+                args: vec![Value::Temp(obj_temp), Value::Const(Constant::Str(key)), getter, setter],
+            });
+        }
+
         Some(Value::Temp(obj_temp))
     }
 
-    fn lower_if(
+    /// Lower an object literal's getter (`param` None) or setter into a
+    /// function taking the object, and the new value for a setter. Returns
+    /// the function's name.
+    fn lower_object_accessor(
         &mut self,
-        ctx: &mut FuncCtx,
-        condition: &Node<Expr>,
-        then_stmt: &Node<Stmt>,
-        else_stmt: Option<&Node<Stmt>>,
-        _span: &Span,
-    ) {
-        let cond_val = match self.lower_expr(ctx, &condition.value, &condition.span) {
-            Some(v) => v,
-            None => return,
-        };
-
-        let then_block = ctx.new_block();
+        shape: &[(String, IrType)],
+        key: &str,
+        param: Option<&Param>,
+        ret_type: IrType,
+        body: &Node<BlockStmt>,
+    ) -> String {
+        let func_id = self.alloc_func_id();
+        let kind = if param.is_some() { "set" } else { "get" };
+        let func_name = format!("__object_{}_{}_{}", kind, key, func_id.0);
+        let mut ir_params = vec![(LocalId(0), IrType::Ptr)];
+        let param_type = param.map(|param| self.infer_param_type(param));
+        if let Some(ty) = &param_type {
+            ir_params.push((LocalId(1), ty.clone()));
+        }
+        let mut ir_func = IrFunction::new(func_id, func_name.clone(), ir_params, ret_type.clone());
+        let entry = ir_func.new_block();
+        ir_func.entry_block = entry;
+        let mut func_ctx = FuncCtx { func: &mut ir_func, current_block: entry };
+        self.push_scope();
+        let prev_this = self.this_var.replace(VarInfo { local_id: LocalId(0), ir_type: IrType::Ptr, is_boxed: false });
+        let prev_shape = self.this_object_shape.replace(shape.to_vec());
+        let prev_class = self.current_class.take();
+        let prev_function = self.current_function.replace((func_name.clone(), ret_type.clone()));
+        let prev_async_promise = self.async_promise.take();
+        if let (Some(param), Some(ty)) = (param, param_type) {
+            let name = match &param.pattern.value {
+                Pattern::Ident { name, .. } => name.value.name.clone(),
+                _ => "_value".to_string(),
+            };
+            self.define_var(&name, VarInfo { local_id: LocalId(1), ir_type: ty, is_boxed: false });
+            self.track_object_var(&name, Self::param_annotation(param), None);
+        }
+        self.hoist_vars(&mut func_ctx, &body.value.stmts);
+        for s in &body.value.stmts {
+            self.lower_stmt(&mut func_ctx, &s.value, &s.span);
+        }
+        if matches!(func_ctx.func.block(func_ctx.current_block).terminator, Terminator::Unreachable) {
+            if ret_type == IrType::Void {
+                func_ctx.set_terminator(Terminator::Return(None));
+            } else {
+                let t = func_ctx.add_temp(ret_type.clone());
+                func_ctx.emit(Instruction::Assign { dest: Place::from_temp(t), value: RValue::Use(Value::Const(Constant::I64(0))) });
+                func_ctx.set_terminator(Terminator::Return(Some(Value::Temp(t))));
+            }
+        }
+        self.this_var = prev_this;
+        self.this_object_shape = prev_shape;
+        self.current_class = prev_class;
+        self.current_function = prev_function;
+        self.async_promise = prev_async_promise;
+        self.pop_scope();
+        self.module.add_function(ir_func);
+        func_name
+    }
+
+    /// Store `val` under `key` in a plain object with the setter for its type.
+    fn emit_object_set(&mut self, ctx: &mut FuncCtx, obj: Value, key: Value, val: Value, val_type: &IrType) {
+        let setter_name = match val_type {
+            IrType::Str => "zaco_object_set_str",
+            IrType::F64 => "zaco_object_set_f64",
+            IrType::I64 | IrType::Bool => "zaco_object_set_i64",
+            _ => "zaco_object_set_ptr",
+        };
+        let setter_val_type = match val_type {
+            IrType::Str => IrType::Ptr,
+            IrType::Bool => IrType::I64,
+            other => other.clone(),
+        };
+        self.ensure_extern(
+            setter_name,
+            vec![IrType::Ptr, IrType::Ptr, setter_val_type],
+            IrType::Void,
+        );
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str(setter_name.to_string())),
+            args: vec![obj, key, val],
+        });
+    }
+
+    fn lower_if(
+        &mut self,
+        ctx: &mut FuncCtx,
+        condition: &Node<Expr>,
+        then_stmt: &Node<Stmt>,
+        else_stmt: Option<&Node<Stmt>>,
+        _span: &Span,
+    ) {
+        // A constant condition (e.g. a `--define`d flag) leaves only the
+        // branch it takes; the other is never lowered, so its strings and
+        // runtime externs are not emitted either
+        if let Some(taken) = self.constant_condition(&condition.value) {
+            let Some(branch) = (if taken { Some(then_stmt) } else { else_stmt }) else { return };
+            self.push_scope();
+            self.lower_stmt(ctx, &branch.value, &branch.span);
+            self.pop_scope();
+            // Code after a branch that returned lands in a fresh block
+            if !matches!(ctx.func.block(ctx.current_block).terminator, Terminator::Unreachable) {
+                let rest = ctx.new_block();
+                ctx.switch_to(rest);
+            }
+            return;
+        }
+        let cond_val = match self.lower_expr(ctx, &condition.value, &condition.span) {
+            Some(v) => v,
+            None => return,
+        };
+
+        let then_block = ctx.new_block();
         let else_block = ctx.new_block();
         let merge_block = ctx.new_block();
 
@@ -2133,14 +6250,14 @@ impl Lowerer {
         condition: Option<&Node<Expr>>,
         update: Option<&Node<Expr>>,
         body: &Node<Stmt>,
-        _span: &Span,
+        span: &Span,
     ) {
         self.push_scope();
 
         // Init
         if let Some(for_init) = init {
             match for_init {
-                ForInit::VarDecl(vd) => self.lower_var_decl(ctx, vd, &Span::new(0, 0, 0)),
+                ForInit::VarDecl(vd) => self.lower_var_decl(ctx, vd, &vd.span().unwrap_or(*span)),
                 ForInit::Expr(e) => {
                     let _ = self.lower_expr(ctx, &e.value, &e.span);
                 }
@@ -2237,7 +6354,8 @@ impl Lowerer {
         _span: &Span,
     ) {
         // Ensure runtime functions are declared
-        self.ensure_extern("zaco_try_push", vec![], IrType::I64);
+        self.ensure_extern("zaco_try_enter", vec![], IrType::Ptr);
+        self.ensure_extern("_setjmp", vec![IrType::Ptr], IrType::I64);
         self.ensure_extern("zaco_try_pop", vec![], IrType::Void);
         self.ensure_extern("zaco_get_error", vec![], IrType::Ptr);
         self.ensure_extern("zaco_clear_error", vec![], IrType::Void);
@@ -2247,12 +6365,19 @@ impl Lowerer {
         let finally_block = ctx.new_block();
         let continue_block = ctx.new_block();
 
-        // Call zaco_try_push() → returns 0 normally, 1 on exception
+        // Push a try slot and setjmp on it from this frame, so a throw in a
+        // callee lands back here → returns 0 normally, 1 on exception
+        let buf_temp = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(buf_temp)),
+            func: Value::Const(Constant::Str("zaco_try_enter".to_string())),
+            args: vec![],
+        });
         let result_temp = ctx.add_temp(IrType::I64);
         ctx.emit(Instruction::Call {
             dest: Some(Place::from_temp(result_temp)),
-            func: Value::Const(Constant::Str("zaco_try_push".to_string())),
-            args: vec![],
+            func: Value::Const(Constant::Str("_setjmp".to_string())),
+            args: vec![Value::Temp(buf_temp)],
         });
 
         // Compare result == 0 (normal path)
@@ -2422,6 +6547,10 @@ impl Lowerer {
             let local_id = LocalId(i);
             ir_params.push((local_id, ir_type));
         }
+        let uses_arguments = self.arguments_functions.contains_key(&func_decl.name.value.name);
+        if uses_arguments {
+            ir_params.push((LocalId(ir_params.len()), IrType::Array(Box::new(IrType::F64))));
+        }
 
         // Infer return type
         let return_type = if let Some(ref ret_ty) = func_decl.return_type {
@@ -2433,6 +6562,7 @@ impl Lowerer {
         // Track current function for recursive call detection
         let prev_function = self.current_function.take();
         self.current_function = Some((func_name.clone(), return_type.clone()));
+        let prev_async_promise = self.async_promise.take();
 
         let mut ir_func = IrFunction::new(func_id, func_name.clone(), ir_params.clone(), return_type.clone());
         let entry = ir_func.new_block();
@@ -2460,15 +6590,29 @@ impl Lowerer {
                     is_boxed: false,
                 },
             );
+            self.track_object_var(&param_name, Self::param_annotation(param), None);
+        }
+        if let (true, Some((local_id, ir_type))) = (uses_arguments, ir_params.last().cloned()) {
+            self.define_var("arguments", VarInfo { local_id, ir_type, is_boxed: false });
         }
 
         // Lower body
         if let Some(ref body) = func_decl.body {
+            self.hoist_vars(&mut func_ctx, &body.value.stmts);
+            self.bind_module_globals(&mut func_ctx, &body.value.stmts);
             for s in &body.value.stmts {
                 self.lower_stmt(&mut func_ctx, &s.value, &s.span);
             }
         }
 
+        // Without an annotation, return whatever the body's `return`s produce
+        let return_type = if func_decl.return_type.is_none() {
+            self.unify_returned_type(func_ctx.func, return_type, &func_decl.name.span)
+        } else {
+            return_type
+        };
+        func_ctx.func.return_type = return_type.clone();
+
         // If no terminator set, add implicit return
         if matches!(
             func_ctx.func.block(func_ctx.current_block).terminator,
@@ -2494,12 +6638,17 @@ impl Lowerer {
 
         self.pop_scope();
         self.current_function = prev_function;
+        self.async_promise = prev_async_promise;
 
         self.module.add_function(ir_func);
     }
 
     fn lower_async_function_decl(&mut self, func_decl: &FunctionDecl) {
-        let func_name = func_decl.name.value.name.clone();
+        let mut func_name = func_decl.name.value.name.clone();
+        // Rename user-defined "main" to avoid conflict with compiler wrapper
+        if func_name == "main" && self.has_user_main {
+            func_name = "_user_main".to_string();
+        }
         let func_id = self.alloc_func_id();
 
         // Build parameter list
@@ -2510,25 +6659,7 @@ impl Lowerer {
             ir_params.push((local_id, ir_type));
         }
 
-        // Infer inner return type (what the async function actually returns)
-        // If the user specified Promise<T>, extract T. Otherwise, wrap in Promise.
-        let return_type = if let Some(ref ret_ty) = func_decl.return_type {
-            let ir_type = self.ast_type_to_ir(&ret_ty.value);
-            // If it's already a Promise type, use it as-is
-            if matches!(ir_type, IrType::Promise(_)) {
-                ir_type
-            } else {
-                // Otherwise, wrap it in Promise
-                IrType::Promise(Box::new(ir_type))
-            }
-        } else {
-            // No return type specified → Promise<void>
-            IrType::Promise(Box::new(IrType::Void))
-        };
-
-        // Ensure all promise-related extern functions are declared up front
-        self.ensure_extern("zaco_promise_new", vec![], IrType::Ptr);
-        self.ensure_extern("zaco_promise_resolve", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
+        let return_type = self.async_return_type(func_decl.return_type.as_deref());
 
         let mut ir_func = IrFunction::new(func_id, func_name.clone(), ir_params.clone(), return_type.clone());
         let entry = ir_func.new_block();
@@ -2558,6 +6689,39 @@ impl Lowerer {
             );
         }
 
+        let stmts = func_decl.body.as_ref().map(|body| body.value.stmts.as_slice()).unwrap_or_default();
+        self.hoist_vars(&mut func_ctx, stmts);
+        self.bind_module_globals(&mut func_ctx, stmts);
+        self.lower_async_body(&mut func_ctx, stmts);
+
+        self.pop_scope();
+
+        self.module.add_function(ir_func);
+    }
+
+    /// Return type of an async function or method: its `Promise<T>`
+    /// annotation, a promise of any other annotation, or `Promise<void>`.
+    fn async_return_type(&self, return_type: Option<&Node<Type>>) -> IrType {
+        match return_type.map(|ty| self.ast_type_to_ir(&ty.value)) {
+            Some(ir_type @ IrType::Promise(_)) => ir_type,
+            Some(ir_type) => IrType::Promise(Box::new(ir_type)),
+            None => IrType::Promise(Box::new(IrType::Void)),
+        }
+    }
+
+    /// Body of an async function or method: create its promise, run the
+    /// statements, resolve the promise on return and reject it on a throw.
+    fn lower_async_body(&mut self, func_ctx: &mut FuncCtx, stmts: &[Node<Stmt>]) {
+        // Ensure all promise-related extern functions are declared up front
+        self.ensure_extern("zaco_promise_new", vec![], IrType::Ptr);
+        self.ensure_extern("zaco_promise_resolve", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
+        self.ensure_extern("zaco_promise_reject", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
+        self.ensure_extern("zaco_try_enter", vec![], IrType::Ptr);
+        self.ensure_extern("_setjmp", vec![IrType::Ptr], IrType::I64);
+        self.ensure_extern("zaco_try_pop", vec![], IrType::Void);
+        self.ensure_extern("zaco_get_error", vec![], IrType::Ptr);
+        self.ensure_extern("zaco_clear_error", vec![], IrType::Void);
+
         // Create a new Promise
         let promise_temp = func_ctx.add_temp(IrType::Ptr);
         func_ctx.emit(Instruction::Call {
@@ -2566,286 +6730,341 @@ impl Lowerer {
             args: vec![],
         });
 
-        // Lower body - for now we execute it synchronously and resolve the promise
-        // TODO: true async with task spawning
-        if let Some(ref body) = func_decl.body {
-            for s in &body.value.stmts {
-                self.lower_stmt(&mut func_ctx, &s.value, &s.span);
-            }
+        // The body runs synchronously inside a try slot: anything it throws
+        // rejects the promise instead of unwinding into the caller
+        let buf_temp = func_ctx.add_temp(IrType::Ptr);
+        func_ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(buf_temp)),
+            func: Value::Const(Constant::Str("zaco_try_enter".to_string())),
+            args: vec![],
+        });
+        let jump_temp = func_ctx.add_temp(IrType::I64);
+        func_ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(jump_temp)),
+            func: Value::Const(Constant::Str("_setjmp".to_string())),
+            args: vec![Value::Temp(buf_temp)],
+        });
+        let normal_temp = func_ctx.add_temp(IrType::Bool);
+        func_ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(normal_temp),
+            value: RValue::BinaryOp {
+                op: BinOp::Eq,
+                left: Value::Temp(jump_temp),
+                right: Value::Const(Constant::I64(0)),
+            },
+        });
+        let body_block = func_ctx.new_block();
+        let reject_block = func_ctx.new_block();
+        func_ctx.set_terminator(Terminator::Branch {
+            cond: Value::Temp(normal_temp),
+            then_block: body_block,
+            else_block: reject_block,
+        });
+
+        func_ctx.switch_to(body_block);
+        let prev_async_promise = self.async_promise.replace(promise_temp);
+        for s in stmts {
+            self.lower_stmt(func_ctx, &s.value, &s.span);
         }
 
-        // Get the return value and resolve the promise
-        // If the last statement was a return, we need to resolve the promise with that value
-        // For now, we'll just return the promise (simplified version)
+        // No explicit return: resolve with undefined
         if matches!(
             func_ctx.func.block(func_ctx.current_block).terminator,
             Terminator::Unreachable
         ) {
-            // No explicit return, resolve with undefined/void
-            func_ctx.emit(Instruction::Call {
-                dest: None,
-                func: Value::Const(Constant::Str("zaco_promise_resolve".to_string())),
-                args: vec![Value::Temp(promise_temp), Value::Const(Constant::Null)],
-            });
-
-            func_ctx.set_terminator(Terminator::Return(Some(Value::Temp(promise_temp))));
+            self.lower_async_return(func_ctx, None);
         }
+        self.async_promise = prev_async_promise;
 
-        self.pop_scope();
+        // A throw lands here: reject with the error
+        func_ctx.switch_to(reject_block);
+        let error_temp = func_ctx.add_temp(IrType::Ptr);
+        func_ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(error_temp)),
+            func: Value::Const(Constant::Str("zaco_get_error".to_string())),
+            args: vec![],
+        });
+        func_ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_clear_error".to_string())),
+            args: vec![],
+        });
+        func_ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_promise_reject".to_string())),
+            args: vec![Value::Temp(promise_temp), Value::Temp(error_temp)],
+        });
+        func_ctx.set_terminator(Terminator::Return(Some(Value::Temp(promise_temp))));
+    }
 
-        self.module.add_function(ir_func);
+
+    /// `return` inside an async function: leave its try slot, resolve its
+    /// promise with `val` (undefined if `None`) and return the promise.
+    fn lower_async_return(&mut self, ctx: &mut FuncCtx, val: Option<Value>) {
+        let Some(promise_temp) = self.async_promise else { return };
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_try_pop".to_string())),
+            args: vec![],
+        });
+        let (resolve_fn, payload) = self.promise_payload(ctx, val);
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str(resolve_fn.to_string())),
+            args: vec![Value::Temp(promise_temp), payload],
+        });
+        ctx.set_terminator(Terminator::Return(Some(Value::Temp(promise_temp))));
+        let dead_block = ctx.new_block();
+        ctx.switch_to(dead_block);
     }
 
+    /// The resolve function and payload that settle a promise with `val`.
+    /// Numbers keep their bits (read back by `zaco_async_block_on_f64`);
+    /// booleans are widened to a full 8-byte payload.
+    fn promise_payload(&mut self, ctx: &mut FuncCtx, val: Option<Value>) -> (&'static str, Value) {
+        let Some(val) = val else {
+            return ("zaco_promise_resolve", Value::Const(Constant::Null));
+        };
+        match ctx.func.value_type(&val) {
+            Some(IrType::F64) => {
+                self.ensure_extern("zaco_promise_resolve_f64", vec![IrType::Ptr, IrType::F64], IrType::Void);
+                ("zaco_promise_resolve_f64", val)
+            }
+            Some(IrType::Bool) => {
+                let widened = ctx.add_temp(IrType::I64);
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(widened),
+                    value: RValue::Cast { value: val, ty: IrType::I64 },
+                });
+                ("zaco_promise_resolve", Value::Temp(widened))
+            }
+            _ => ("zaco_promise_resolve", val),
+        }
+    }
 
     // =========================================================================
     // Generator function lowering (function* / yield)
     // =========================================================================
 
-    /// Lower a generator function (function*) using a state-machine transformation.
+    /// Lower a generator function (function*). The body becomes a coroutine
+    /// `<name>__body(gen, env)` that the runtime runs on its own stack; the
+    /// function itself packs its arguments into `<name>__env` and returns
+    /// the generator handle without running any of the body.
     fn lower_generator_function_decl(&mut self, func_decl: &FunctionDecl) {
         let func_name = func_decl.name.value.name.clone();
+        let body_name = format!("{func_name}__body");
+        let env_name = format!("{func_name}__env");
 
-        // Ensure generator runtime externs
         self.ensure_extern("zaco_generator_new", vec![IrType::Ptr, IrType::Ptr], IrType::Ptr);
-        self.ensure_extern("zaco_generator_set_value", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
-        self.ensure_extern("zaco_generator_set_done", vec![IrType::Ptr], IrType::Void);
-
-        // Collect yield points from the function body
-        let yield_values = self.collect_yield_values(func_decl);
-        let num_states = yield_values.len();
-
-        // 1) Create the state struct: { state_index: I64 }
-        let state_struct_id = self.alloc_struct_id();
-        let state_struct = IrStruct::new(
-            state_struct_id,
-            format!("{func_name}__state"),
-            vec![("state_index".to_string(), IrType::I64)],
-        );
-        self.module.add_struct(state_struct);
-
-        // 2) Create the "next" function: <name>__next(state_ptr: Ptr) -> Ptr
-        let next_func_id = self.alloc_func_id();
-        let next_func_name = format!("{func_name}__next");
-        let state_param = LocalId(0);
-        let mut next_func = IrFunction::new(
-            next_func_id,
-            next_func_name.clone(),
-            vec![(state_param, IrType::Ptr)],
-            IrType::Ptr,
-        );
-        let next_entry = next_func.new_block();
-        next_func.entry_block = next_entry;
-
-        {
-            let mut nctx = FuncCtx {
-                func: &mut next_func,
-                current_block: next_entry,
-            };
 
-            // Load state_index from state struct
-            let idx_temp = nctx.add_temp(IrType::I64);
-            nctx.emit(Instruction::Load {
-                dest: Place::from_temp(idx_temp),
-                ptr: Value::Local(state_param),
+        let mut ir_params = Vec::new();
+        let mut param_names = Vec::new();
+        for (i, param) in func_decl.params.iter().enumerate() {
+            let ir_type = self.infer_param_type(param);
+            ir_params.push((LocalId(i), ir_type));
+            param_names.push(match &param.pattern.value {
+                Pattern::Ident { name, .. } => name.value.name.clone(),
+                _ => format!("_param{}", i),
             });
+        }
 
-            // Create blocks for each state + a "done" block
-            let mut state_blocks = Vec::new();
-            for _ in 0..num_states {
-                state_blocks.push(nctx.new_block());
-            }
-            let done_block = nctx.new_block();
+        // The arguments travel to the body in an env struct, registered as a
+        // "class" so store/load_struct_field work (as for closure envs)
+        let env_fields: Vec<(String, IrType)> = param_names
+            .iter()
+            .cloned()
+            .zip(ir_params.iter().map(|(_, ty)| ty.clone()))
+            .collect();
+        let env_struct_id = self.alloc_struct_id();
+        self.module.add_struct(IrStruct::new(env_struct_id, env_name.clone(), env_fields.clone()));
+        self.class_info.insert(env_name.clone(), ClassInfo {
+            struct_id: env_struct_id,
+            fields: env_fields,
+            methods: Vec::new(),
+            parent: None,
+            parent_field_count: 0,
+            getters: Vec::new(),
+            setters: Vec::new(),
+            static_methods: Vec::new(),
+            static_properties: Vec::new(),
+            method_returns: Vec::new(),
+            constructor_slot: None,
+        });
 
-            // Chain of comparisons to dispatch to the right state
-            let mut current_check_block = nctx.current_block;
-            for (i, &state_block) in state_blocks.iter().enumerate() {
-                nctx.switch_to(current_check_block);
-                let cmp_temp = nctx.add_temp(IrType::Bool);
-                nctx.emit(Instruction::Assign {
-                    dest: Place::from_temp(cmp_temp),
-                    value: RValue::BinaryOp {
-                        op: BinOp::Eq,
-                        left: Value::Temp(idx_temp),
-                        right: Value::Const(Constant::I64(i as i64)),
-                    },
-                });
-                let next_check = if i + 1 < num_states {
-                    nctx.new_block()
-                } else {
-                    done_block
-                };
-                nctx.set_terminator(Terminator::Branch {
-                    cond: Value::Temp(cmp_temp),
-                    then_block: state_block,
-                    else_block: next_check,
-                });
-                current_check_block = next_check;
+        // 1) The body: <name>__body(gen: Ptr, env: Ptr) -> Void
+        let gen_param = LocalId(0);
+        let env_param = LocalId(1);
+        let body_id = self.alloc_func_id();
+        let mut body_func = IrFunction::new(
+            body_id,
+            body_name.clone(),
+            vec![(gen_param, IrType::Ptr), (env_param, IrType::Struct(env_struct_id))],
+            IrType::Void,
+        );
+        let body_entry = body_func.new_block();
+        body_func.entry_block = body_entry;
+
+        let mut types = self.declared_generator_types(func_decl);
+        let prev_function = self.current_function.replace((body_name.clone(), IrType::Void));
+        let prev_async_promise = self.async_promise.take();
+        let prev_generator = self.current_generator.replace(GeneratorCtx {
+            handle: gen_param,
+            next_ty: types.next_ty.clone(),
+            yield_ty: None,
+        });
+        {
+            let mut bctx = FuncCtx {
+                func: &mut body_func,
+                current_block: body_entry,
+            };
+            self.push_scope();
+            for param_name in &param_names {
+                let ir_type = self.class_info[&env_name]
+                    .fields
+                    .iter()
+                    .find(|(n, _)| n == param_name)
+                    .map(|(_, t)| t.clone())
+                    .unwrap_or(IrType::F64);
+                if let Some(val) = self.load_struct_field(&mut bctx, Value::Local(env_param), &env_name, param_name) {
+                    let local = bctx.add_local(ir_type.clone());
+                    bctx.emit(Instruction::Assign {
+                        dest: Place::from_local(local),
+                        value: RValue::Use(val),
+                    });
+                    self.define_var(param_name, VarInfo { local_id: local, ir_type, is_boxed: false });
+                }
             }
-
-            // Emit each state block
-            for (i, &state_block) in state_blocks.iter().enumerate() {
-                nctx.switch_to(state_block);
-
-                let yield_val = match &yield_values[i] {
-                    Some(expr) => self.lower_yield_value_simple(&mut nctx, expr),
-                    None => Value::Const(Constant::Null),
-                };
-
-                // Store yielded value
-                nctx.emit(Instruction::Call {
-                    dest: None,
-                    func: Value::Const(Constant::Str("zaco_generator_set_value".to_string())),
-                    args: vec![Value::Local(state_param), yield_val],
-                });
-
-                // Advance state index
-                let next_state = nctx.add_temp(IrType::I64);
-                nctx.emit(Instruction::Assign {
-                    dest: Place::from_temp(next_state),
-                    value: RValue::Use(Value::Const(Constant::I64((i + 1) as i64))),
-                });
-                nctx.emit(Instruction::Store {
-                    ptr: Value::Local(state_param),
-                    value: Value::Temp(next_state),
-                });
-
-                nctx.set_terminator(Terminator::Return(Some(Value::Local(state_param))));
+            if let Some(ref body) = func_decl.body {
+                self.hoist_vars(&mut bctx, &body.value.stmts);
+                self.bind_module_globals(&mut bctx, &body.value.stmts);
+                for s in &body.value.stmts {
+                    self.lower_stmt(&mut bctx, &s.value, &s.span);
+                }
             }
-
-            // Done block
-            nctx.switch_to(done_block);
-            nctx.emit(Instruction::Call {
-                dest: None,
-                func: Value::Const(Constant::Str("zaco_generator_set_done".to_string())),
-                args: vec![Value::Local(state_param)],
-            });
-            nctx.set_terminator(Terminator::Return(Some(Value::Local(state_param))));
+            if matches!(bctx.func.block(bctx.current_block).terminator, Terminator::Unreachable) {
+                bctx.set_terminator(Terminator::Return(None));
+            }
+            self.pop_scope();
         }
+        let generator = std::mem::replace(&mut self.current_generator, prev_generator);
+        self.current_function = prev_function;
+        self.async_promise = prev_async_promise;
+        self.module.add_function(body_func);
 
-        self.module.add_function(next_func);
-
-        // 3) Create the wrapper function: <name>(params...) -> Ptr
-        let wrapper_func_id = self.alloc_func_id();
-        let mut ir_params = Vec::new();
-        for (i, param) in func_decl.params.iter().enumerate() {
-            let ir_type = self.infer_param_type(param);
-            ir_params.push((LocalId(i), ir_type));
+        // Without an annotation, `next().value` has the type of the first yield
+        if func_decl.return_type.is_none() {
+            if let Some(yield_ty) = generator.and_then(|g| g.yield_ty) {
+                types.yield_ty = yield_ty;
+            }
         }
+        self.generator_functions.insert(func_name.clone(), types);
 
-        let mut wrapper_func = IrFunction::new(
-            wrapper_func_id,
-            func_name.clone(),
-            ir_params,
-            IrType::Ptr,
-        );
+        // 2) The function itself: <name>(params...) -> Ptr
+        let wrapper_id = self.alloc_func_id();
+        let mut wrapper_func = IrFunction::new(wrapper_id, func_name, ir_params.clone(), IrType::Ptr);
         let wrapper_entry = wrapper_func.new_block();
         wrapper_func.entry_block = wrapper_entry;
-
         {
             let mut wctx = FuncCtx {
                 func: &mut wrapper_func,
                 current_block: wrapper_entry,
             };
-
-            // Allocate state struct
-            let state_local = wctx.add_local(IrType::Ptr);
+            let env_local = wctx.add_local(IrType::Struct(env_struct_id));
             wctx.emit(Instruction::Alloc {
-                dest: Place::from_local(state_local),
-                ty: IrType::Struct(state_struct_id),
-            });
-
-            // Initialize state_index to 0
-            wctx.emit(Instruction::Store {
-                ptr: Value::Local(state_local),
-                value: Value::Const(Constant::I64(0)),
+                dest: Place::from_local(env_local),
+                ty: IrType::Struct(env_struct_id),
             });
-
-            // Create generator object
-            self.module.intern_string(next_func_name.clone());
+            for (param_name, (local_id, _)) in param_names.iter().zip(&ir_params) {
+                self.store_struct_field(&mut wctx, Value::Local(env_local), &env_name, param_name, Value::Local(*local_id));
+            }
             let gen_temp = wctx.add_temp(IrType::Ptr);
             wctx.emit(Instruction::Call {
                 dest: Some(Place::from_temp(gen_temp)),
                 func: Value::Const(Constant::Str("zaco_generator_new".to_string())),
-                args: vec![
-                    Value::Const(Constant::Str(next_func_name)),
-                    Value::Local(state_local),
-                ],
+                args: vec![Value::Const(Constant::FuncAddr(body_name)), Value::Local(env_local)],
             });
-
             wctx.set_terminator(Terminator::Return(Some(Value::Temp(gen_temp))));
         }
-
         self.module.add_function(wrapper_func);
     }
 
-    /// Collect yield values from a generator function body (simple sequential case).
-    fn collect_yield_values(&self, func_decl: &FunctionDecl) -> Vec<Option<Expr>> {
-        let mut yields = Vec::new();
-        if let Some(ref body) = func_decl.body {
-            for stmt in &body.value.stmts {
-                self.collect_yields_from_stmt(&stmt.value, &mut yields);
+    /// Types a generator function declares with `Generator<T, TReturn,
+    /// TNext>` (or `IterableIterator<T>` / `Iterator<T>`, and their `Async`
+    /// counterparts). Anything not written down defaults to number; TReturn
+    /// is carried as raw bits.
+    fn declared_generator_types(&self, func_decl: &FunctionDecl) -> GeneratorTypes {
+        let args: Vec<IrType> = match func_decl.return_type.as_deref().map(|t| &t.value) {
+            Some(Type::TypeRef { name, type_args: Some(args) })
+                if matches!(
+                    name.value.name.as_str(),
+                    "Generator"
+                        | "IterableIterator"
+                        | "Iterator"
+                        | "AsyncGenerator"
+                        | "AsyncIterableIterator"
+                        | "AsyncIterator"
+                ) =>
+            {
+                args.iter().map(|arg| self.ast_type_to_ir(&arg.value)).collect()
             }
-        }
-        yields
+            _ => Vec::new(),
+        };
+        let arg = |i: usize| match args.get(i) {
+            Some(IrType::Void) | None => IrType::F64,
+            Some(ty) => ty.clone(),
+        };
+        GeneratorTypes { yield_ty: arg(0), next_ty: arg(2), is_async: func_decl.is_async }
     }
 
-    fn collect_yields_from_stmt(&self, stmt: &Stmt, yields: &mut Vec<Option<Expr>>) {
-        match stmt {
-            Stmt::Expr(expr_node) => {
-                self.collect_yields_from_expr(&expr_node.value, yields);
-            }
-            Stmt::Return(Some(expr_node)) => {
-                self.collect_yields_from_expr(&expr_node.value, yields);
-            }
-            Stmt::Block(block) => {
-                for s in &block.stmts {
-                    self.collect_yields_from_stmt(&s.value, yields);
-                }
-            }
-            _ => {}
+    /// Runtime suffix (`f64`, `i64` or `ptr`) and parameter type for moving a
+    /// value of IR type `ty` in and out of a generator. Booleans travel as i64.
+    fn generator_slot(ty: &IrType) -> (&'static str, IrType) {
+        match ty {
+            IrType::F64 => ("f64", IrType::F64),
+            IrType::I64 | IrType::Bool => ("i64", IrType::I64),
+            _ => ("ptr", IrType::Ptr),
         }
     }
 
-    fn collect_yields_from_expr(&self, expr: &Expr, yields: &mut Vec<Option<Expr>>) {
-        match expr {
-            Expr::Yield { argument, .. } => {
-                yields.push(argument.as_ref().map(|a| a.value.clone()));
-            }
-            Expr::Binary { left, right, .. } => {
-                self.collect_yields_from_expr(&left.value, yields);
-                self.collect_yields_from_expr(&right.value, yields);
-            }
-            Expr::Call { callee, args, .. } => {
-                self.collect_yields_from_expr(&callee.value, yields);
-                for arg in args {
-                    self.collect_yields_from_expr(&arg.value, yields);
-                }
-            }
-            Expr::Paren(inner) => {
-                self.collect_yields_from_expr(&inner.value, yields);
-            }
-            _ => {}
-        }
+    /// Call `zaco_generator_<op>_<slot>(gen, val)` for `val` of its own IR type.
+    fn emit_generator_transfer(&mut self, ctx: &mut FuncCtx, op: &str, gen: Value, val: Value) {
+        let ty = ctx.func.value_type(&val).unwrap_or(IrType::Ptr);
+        let (slot, param_ty) = Self::generator_slot(&ty);
+        let val = if ty == IrType::Bool {
+            let widened = ctx.add_temp(IrType::I64);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(widened),
+                value: RValue::Cast { value: val, ty: IrType::I64 },
+            });
+            Value::Temp(widened)
+        } else {
+            val
+        };
+        let func = format!("zaco_generator_{op}_{slot}");
+        self.ensure_extern(&func, vec![IrType::Ptr, param_ty], IrType::Void);
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str(func)),
+            args: vec![gen, val],
+        });
     }
 
-    /// Lower a simple yield value expression.
-    fn lower_yield_value_simple(&mut self, ctx: &mut FuncCtx, expr: &Expr) -> Value {
-        match expr {
-            Expr::Literal(Literal::Number(n)) => Value::Const(Constant::F64(*n)),
-            Expr::Literal(Literal::String(s)) => {
-                self.module.intern_string(s.clone());
-                Value::Const(Constant::Str(s.clone()))
-            }
-            Expr::Literal(Literal::Boolean(b)) => Value::Const(Constant::Bool(*b)),
-            Expr::Literal(Literal::Null | Literal::Undefined) => Value::Const(Constant::Null),
-            _ => {
-                let span = Span { start: 0, end: 0, file_id: 0 };
-                self.lower_expr(ctx, expr, &span).unwrap_or(Value::Const(Constant::Null))
-            }
+    /// `return v` inside a generator body: `v` becomes the value of the
+    /// final `{ done: true }` result, then the coroutine finishes.
+    fn lower_generator_return(&mut self, ctx: &mut FuncCtx, val: Option<Value>) {
+        let Some(generator) = &self.current_generator else { return };
+        let gen = Value::Local(generator.handle);
+        if let Some(val) = val {
+            self.emit_generator_transfer(ctx, "return", gen, val);
         }
+        ctx.set_terminator(Terminator::Return(None));
+        let dead_block = ctx.new_block();
+        ctx.switch_to(dead_block);
     }
 
-    /// Lower a yield expression encountered during normal body lowering.
+    /// Lower a yield expression inside a generator body: hand the value to
+    /// the caller's `next()` and pause. On resumption the expression
+    /// evaluates to the argument of that `next(v)` call.
     fn lower_yield_expr(
         &mut self,
         ctx: &mut FuncCtx,
@@ -2857,27 +7076,105 @@ impl Lowerer {
             // TODO: implement yield* delegation
             self.errors.push(LowerError::new(
                 "yield* delegation is not yet implemented",
-                span.clone(),
+                *span,
             ));
             return None;
         }
+        let Some(generator) = &self.current_generator else {
+            self.errors.push(LowerError::new("yield is only valid inside a generator function", *span));
+            return None;
+        };
+        let gen = Value::Local(generator.handle);
+        let next_ty = generator.next_ty.clone();
 
-        let yield_val = if let Some(arg) = argument {
-            self.lower_expr(ctx, &arg.value, &arg.span)
-                .unwrap_or(Value::Const(Constant::Null))
-        } else {
-            Value::Const(Constant::Null)
+        let yield_val = match argument {
+            Some(arg) => self.lower_expr(ctx, &arg.value, &arg.span).unwrap_or(Value::Const(Constant::Null)),
+            None => Value::Const(Constant::Null),
         };
+        let yield_ty = ctx.func.value_type(&yield_val).unwrap_or(IrType::Ptr);
+        if let Some(generator) = self.current_generator.as_mut() {
+            generator.yield_ty.get_or_insert(yield_ty);
+        }
+        self.emit_generator_transfer(ctx, "yield", gen.clone(), yield_val);
+
+        let (slot, ret_ty) = Self::generator_slot(&next_ty);
+        let func = format!("zaco_generator_sent_{slot}");
+        self.ensure_extern(&func, vec![IrType::Ptr], ret_ty.clone());
+        let sent = ctx.add_temp(ret_ty);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(sent)),
+            func: Value::Const(Constant::Str(func)),
+            args: vec![gen],
+        });
+        if next_ty != IrType::Bool {
+            return Some(Value::Temp(sent));
+        }
+        let flag = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(flag),
+            value: RValue::Cast { value: Value::Temp(sent), ty: IrType::Bool },
+        });
+        Some(Value::Temp(flag))
+    }
 
-        self.ensure_extern("zaco_generator_yield", vec![IrType::Ptr], IrType::Ptr);
+    /// Lower `gen.next(v?)`: store `v` as the result of the paused `yield`,
+    /// then resume the body. Returns the `{ value, done }` result object, or
+    /// for an async generator a promise resolved with it. Awaits in the body
+    /// block, so the body has already run up to its next yield by then.
+    fn lower_generator_next(&mut self, ctx: &mut FuncCtx, gen: &Node<Expr>, args: &[Node<Expr>]) -> Option<Value> {
+        let is_async = match &gen.value {
+            Expr::Ident(ident) => self.generator_vars.get(&ident.name).is_some_and(|types| types.is_async),
+            _ => false,
+        };
+        let gen_val = self.lower_expr(ctx, &gen.value, &gen.span)?;
+        if let Some(arg) = args.first() {
+            let sent = self.lower_expr(ctx, &arg.value, &arg.span)?;
+            self.emit_generator_transfer(ctx, "send", gen_val.clone(), sent);
+        }
+        let result = self.emit_generator_next(ctx, gen_val);
+        if !is_async {
+            return Some(result);
+        }
+        self.ensure_extern("zaco_promise_new", vec![], IrType::Ptr);
+        self.ensure_extern("zaco_promise_resolve", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
+        let promise = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(promise)),
+            func: Value::Const(Constant::Str("zaco_promise_new".to_string())),
+            args: vec![],
+        });
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_promise_resolve".to_string())),
+            args: vec![Value::Temp(promise), result],
+        });
+        Some(Value::Temp(promise))
+    }
 
+    /// Resume a generator, returning its `{ value, done }` result object.
+    fn emit_generator_next(&mut self, ctx: &mut FuncCtx, gen: Value) -> Value {
+        self.ensure_extern("zaco_generator_next", vec![IrType::Ptr], IrType::Ptr);
         let result = ctx.add_temp(IrType::Ptr);
         ctx.emit(Instruction::Call {
             dest: Some(Place::from_temp(result)),
-            func: Value::Const(Constant::Str("zaco_generator_yield".to_string())),
-            args: vec![yield_val],
+            func: Value::Const(Constant::Str("zaco_generator_next".to_string())),
+            args: vec![gen],
         });
-        Some(Value::Temp(result))
+        Value::Temp(result)
+    }
+
+    /// Types of the generator an expression evaluates to: a variable holding
+    /// one, or a direct call of a generator function.
+    fn expr_generator_types(&self, expr: &Expr) -> Option<GeneratorTypes> {
+        match expr {
+            Expr::Ident(ident) => self.generator_vars.get(&ident.name).cloned(),
+            Expr::Call { callee, .. } => match &callee.value {
+                Expr::Ident(ident) => self.generator_functions.get(&ident.name).cloned(),
+                _ => None,
+            },
+            Expr::Paren(inner) => self.expr_generator_types(&inner.value),
+            _ => None,
+        }
     }
 
     // =========================================================================
@@ -2886,25 +7183,40 @@ impl Lowerer {
 
     /// Lower a tagged template literal: tag`hello ${name} world`
     /// → tag(["hello ", " world"], name)
+    /// Lower tag`...`: the tag is called with a TemplateStringsArray (the
+    /// cooked parts, carrying the raw parts; see `zaco_template_strings`)
+    /// followed by the substitutions. `String.raw` is built in.
     fn lower_tagged_template(
         &mut self,
         ctx: &mut FuncCtx,
         tag: &Node<Expr>,
         parts: &[String],
+        raw: &[String],
         exprs: &[Node<Expr>],
         span: &Span,
     ) -> Option<Value> {
-        // 1. Create an array of string parts (quasis)
-        let mut string_vals = Vec::new();
-        for part in parts {
-            self.module.intern_string(part.clone());
-            string_vals.push(Value::Const(Constant::Str(part.clone())));
+        // 1. Create the cooked and raw string arrays and join them
+        let cooked_vals = parts.iter().map(|part| self.str_const(part)).collect();
+        let raw_vals = raw.iter().map(|part| self.str_const(part)).collect();
+        let mut arrays = Vec::new();
+        for vals in [cooked_vals, raw_vals] {
+            let array = ctx.add_temp(IrType::Array(Box::new(IrType::Str)));
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(array),
+                value: RValue::ArrayInit(vals),
+            });
+            arrays.push(Value::Temp(array));
         }
-
+        self.ensure_extern(
+            "zaco_template_strings",
+            vec![IrType::Array(Box::new(IrType::Str)), IrType::Array(Box::new(IrType::Str))],
+            IrType::Array(Box::new(IrType::Str)),
+        );
         let strings_array = ctx.add_temp(IrType::Array(Box::new(IrType::Str)));
-        ctx.emit(Instruction::Assign {
-            dest: Place::from_temp(strings_array),
-            value: RValue::ArrayInit(string_vals),
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(strings_array)),
+            func: Value::Const(Constant::Str("zaco_template_strings".to_string())),
+            args: arrays,
         });
 
         // 2. Lower each interpolated expression
@@ -2915,51 +7227,212 @@ impl Lowerer {
             }
         }
 
+        // String.raw joins the raw parts with the substitutions as strings
+        if self.is_string_raw(&tag.value) {
+            let mut values = Vec::new();
+            for (val, expr_node) in expr_vals.into_iter().zip(exprs) {
+                let ty = self.infer_expr_type(&expr_node.value);
+                values.push(self.stringify_value(ctx, val, &ty));
+            }
+            let values_array = ctx.add_temp(IrType::Array(Box::new(IrType::Str)));
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(values_array),
+                value: RValue::ArrayInit(values),
+            });
+            self.ensure_extern(
+                "zaco_string_raw",
+                vec![IrType::Array(Box::new(IrType::Str)), IrType::Array(Box::new(IrType::Str))],
+                IrType::Str,
+            );
+            let result = ctx.add_temp(IrType::Str);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str("zaco_string_raw".to_string())),
+                args: vec![Value::Temp(strings_array), Value::Temp(values_array)],
+            });
+            return Some(Value::Temp(result));
+        }
+
         // 3. Build args: [strings_array, ...expression_values]
         let mut call_args = vec![Value::Temp(strings_array)];
         call_args.extend(expr_vals);
 
-        // 4. Call the tag function
+        // 4. Call the tag function: a module function by name, else the value
+        let result_type = self.infer_tagged_template_type(&tag.value);
+        if let Expr::Ident(ident) = &tag.value {
+            if self.lookup_var(&ident.name).is_none() {
+                let result = ctx.add_temp(result_type);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(result)),
+                    func: Value::Const(Constant::Str(ident.name.clone())),
+                    args: call_args,
+                });
+                return Some(Value::Temp(result));
+            }
+        }
         if let Some(tag_val) = self.lower_expr(ctx, &tag.value, &tag.span) {
-            let result = ctx.add_temp(IrType::Ptr);
+            let result = ctx.add_temp(result_type);
             ctx.emit(Instruction::Call {
                 dest: Some(Place::from_temp(result)),
                 func: tag_val,
                 args: call_args,
             });
             Some(Value::Temp(result))
-        } else if let Expr::Ident(ident) = &tag.value {
-            let result = ctx.add_temp(IrType::Ptr);
-            ctx.emit(Instruction::Call {
-                dest: Some(Place::from_temp(result)),
-                func: Value::Const(Constant::Str(ident.name.clone())),
-                args: call_args,
-            });
-            Some(Value::Temp(result))
         } else {
             self.errors.push(LowerError::new(
                 "unsupported tagged template tag expression",
-                span.clone(),
+                *span,
             ));
             None
         }
     }
 
+    /// Whether a tag expression is the built-in `String.raw`
+    fn is_string_raw(&self, tag: &Expr) -> bool {
+        matches!(tag, Expr::Member { object, property, .. }
+            if matches!(&object.value, Expr::Ident(id) if id.name == "String")
+                && property.value.name == "raw"
+                && self.lookup_var("String").is_none())
+    }
+
+    /// Result type of a tagged template: what the tag function returns
+    fn infer_tagged_template_type(&self, tag: &Expr) -> IrType {
+        if self.is_string_raw(tag) {
+            return IrType::Str;
+        }
+        match tag {
+            Expr::Ident(ident) => self
+                .module
+                .find_function(&ident.name)
+                .map(|f| f.return_type.clone())
+                .unwrap_or(IrType::Ptr),
+            _ => IrType::Ptr,
+        }
+    }
+
     fn lower_await(&mut self, ctx: &mut FuncCtx, expr: &Node<Expr>, _span: &Span) -> Option<Value> {
         // Lower the expression that should produce a Promise
         let promise_val = self.lower_expr(ctx, &expr.value, &expr.span)?;
+        let awaited_type = self.awaited_type(&expr.value);
+        Some(self.emit_await(ctx, promise_val, awaited_type))
+    }
 
-        // Call zaco_async_block_on to wait for the promise to resolve
-        self.ensure_extern("zaco_async_block_on", vec![IrType::Ptr], IrType::Ptr);
+    /// Settle a promise in place and read its value as `awaited_type`.
+    fn emit_await(&mut self, ctx: &mut FuncCtx, promise_val: Value, awaited_type: IrType) -> Value {
+        // Block until the promise settles. Its value is a raw 8-byte payload:
+        // numbers are read back as f64, everything else as a pointer.
+        let (block_on, result_type) = if awaited_type == IrType::F64 {
+            ("zaco_async_block_on_f64", IrType::F64)
+        } else {
+            ("zaco_async_block_on", IrType::Ptr)
+        };
+        self.ensure_extern(block_on, vec![IrType::Ptr], result_type.clone());
 
-        let result_temp = ctx.add_temp(IrType::Ptr);
+        let result_temp = ctx.add_temp(result_type);
         ctx.emit(Instruction::Call {
             dest: Some(Place::from_temp(result_temp)),
-            func: Value::Const(Constant::Str("zaco_async_block_on".to_string())),
+            func: Value::Const(Constant::Str(block_on.to_string())),
+            args: vec![promise_val.clone()],
+        });
+
+        // A rejected promise throws its error at the await, so the
+        // enclosing try/catch (or the uncaught-exception handler) sees it
+        self.ensure_extern("zaco_await_rethrow", vec![IrType::Ptr], IrType::Void);
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_await_rethrow".to_string())),
             args: vec![promise_val],
         });
 
-        Some(Value::Temp(result_temp))
+        if matches!(awaited_type, IrType::I64 | IrType::Bool) {
+            let narrowed = ctx.add_temp(awaited_type.clone());
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(narrowed),
+                value: RValue::Cast { value: Value::Temp(result_temp), ty: awaited_type },
+            });
+            return Value::Temp(narrowed);
+        }
+        Value::Temp(result_temp)
+    }
+
+    /// Type of `receiver.then/catch/finally(...)` on a promise: `finally`
+    /// passes the value through, `then` and `catch` may replace it with
+    /// their callback's result.
+    fn promise_chain_type(&self, receiver: &Expr, method: &str, args: &[Node<Expr>]) -> Option<IrType> {
+        if !matches!(method, "then" | "catch" | "finally") {
+            return None;
+        }
+        let receiver_type = self.infer_expr_type(receiver);
+        if !matches!(receiver_type, IrType::Promise(_)) {
+            return None;
+        }
+        let callback = match method {
+            "then" | "catch" => args.first(),
+            _ => None,
+        };
+        let value_type = match callback.and_then(|arg| Self::unwrap_function_expr(&arg.value)) {
+            Some(func_expr) => match self.infer_function_expr_return_type(func_expr) {
+                IrType::Void => IrType::Ptr,
+                ty => ty,
+            },
+            None => return Some(receiver_type),
+        };
+        // catch keeps the receiver's value on success; only a matching type
+        // can be read back the same way on both paths
+        if method == "catch" && receiver_type != IrType::Promise(Box::new(value_type.clone())) {
+            return Some(IrType::Promise(Box::new(IrType::Ptr)));
+        }
+        Some(IrType::Promise(Box::new(value_type)))
+    }
+
+    /// Constructor body with `this.x = x` for every parameter property, then
+    /// `this.y = <init>` for every instance field initializer, inserted right
+    /// after the `super(...)` call if there is one, else first.
+    fn with_field_initializers(params: &[Param], members: &[ClassMember], stmts: &[Node<Stmt>]) -> Vec<Node<Stmt>> {
+        let this_assign = |name: &Node<Ident>, value: Node<Expr>, span: Span| {
+            let target = Expr::Member {
+                object: Box::new(Node::new(Expr::This, span)),
+                property: name.clone(),
+                computed: false,
+            };
+            let assign = Expr::Assignment {
+                target: Box::new(Node::new(target, span)),
+                op: AssignmentOp::Assign,
+                value: Box::new(value),
+            };
+            Node::new(Stmt::Expr(Node::new(assign, span)), span)
+        };
+        let param_assignments = params.iter().filter(|p| p.property.is_some()).filter_map(|param| {
+            let Pattern::Ident { name, .. } = &param.pattern.value else {
+                return None;
+            };
+            let span = param.pattern.span;
+            Some(this_assign(name, Node::new(Expr::Ident(name.value.clone()), span), span))
+        });
+        let initializers = members.iter().filter_map(|member| match member {
+            ClassMember::Property { name: PropertyName::Ident(name), init: Some(init), is_static: false, .. } => {
+                Some(this_assign(name, init.clone(), init.span))
+            }
+            _ => None,
+        });
+        let super_call = stmts.iter().position(|s| {
+            matches!(&s.value, Stmt::Expr(e) if matches!(&e.value, Expr::Call { callee, .. } if matches!(callee.value, Expr::Super)))
+        });
+        let split = super_call.map_or(0, |i| i + 1);
+        let mut body = stmts[..split].to_vec();
+        body.extend(param_assignments);
+        body.extend(initializers);
+        body.extend_from_slice(&stmts[split..]);
+        body
+    }
+
+    /// Type of `await expr`: the promise's value type, or a pointer when the
+    /// promise type is unknown or void.
+    fn awaited_type(&self, expr: &Expr) -> IrType {
+        match self.infer_expr_type(expr) {
+            IrType::Promise(inner) if *inner != IrType::Void => *inner,
+            _ => IrType::Ptr,
+        }
     }
 
     // =========================================================================
@@ -3235,14 +7708,242 @@ impl Lowerer {
 
     /// Lower for-of loop (iterates over array values).
     /// Simplified: works for arrays, yields element values.
+    /// `for (const x of iterable)` where `iterable` is an instance of a class
+    /// with `[Symbol.iterator]()`: call it once, then call `next()` on the
+    /// iterator it returns until a result has `done` set, binding each
+    /// result's `value`. Under `for await`, `iterator_func` may be the
+    /// class's `[Symbol.asyncIterator]()`, whose `next()` results are
+    /// awaited.
+    fn lower_for_of_iterator(
+        &mut self,
+        ctx: &mut FuncCtx,
+        left: &ForInLeft,
+        right: &Node<Expr>,
+        body: &Node<Stmt>,
+        iterator_func: &str,
+        iterator_class: &str,
+    ) {
+        let value_type = self.iterator_value_type(iterator_class);
+
+        self.push_scope();
+        let Some(iterable) = self.lower_expr(ctx, &right.value, &right.span) else {
+            self.pop_scope();
+            return;
+        };
+        let iterator = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(iterator)),
+            func: Value::Const(Constant::Str(iterator_func.to_string())),
+            args: vec![iterable],
+        });
+
+        let var_local = self.extract_for_in_var_name(left).map(|name| {
+            let local = ctx.add_local(value_type.clone());
+            self.define_var(&name, VarInfo { local_id: local, ir_type: value_type.clone(), is_boxed: false });
+            local
+        });
+
+        let next_block = ctx.new_block();
+        let body_block = ctx.new_block();
+        let exit_block = ctx.new_block();
+        ctx.set_terminator(Terminator::Jump(next_block));
+
+        // `break` closes the iterator through its `return()` method, if any
+        let return_func = format!("{}_return", iterator_class);
+        let return_type = self
+            .module
+            .find_function(&return_func)
+            .filter(|f| f.params.len() == 1)
+            .map(|f| f.return_type.clone());
+        let break_block = match return_type {
+            Some(return_type) => {
+                let close_block = ctx.new_block();
+                let current = ctx.current_block;
+                ctx.switch_to(close_block);
+                let dest = (return_type != IrType::Void).then(|| Place::from_temp(ctx.add_temp(return_type)));
+                ctx.emit(Instruction::Call {
+                    dest,
+                    func: Value::Const(Constant::Str(return_func)),
+                    args: vec![Value::Temp(iterator)],
+                });
+                ctx.set_terminator(Terminator::Jump(exit_block));
+                ctx.switch_to(current);
+                close_block
+            }
+            None => exit_block,
+        };
+
+        // result = iterator.next(); stop once result.done
+        ctx.switch_to(next_block);
+        let next_func = format!("{}_next", iterator_class);
+        let next_type = self.module.find_function(&next_func).map(|f| f.return_type.clone());
+        let next_result = ctx.add_temp(next_type.clone().unwrap_or(IrType::Ptr));
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(next_result)),
+            func: Value::Const(Constant::Str(next_func)),
+            args: vec![Value::Temp(iterator)],
+        });
+        let result = match next_type {
+            Some(IrType::Promise(_)) => self.emit_await(ctx, Value::Temp(next_result), IrType::Ptr),
+            _ => Value::Temp(next_result),
+        };
+        let done = self.lower_object_get(ctx, result.clone(), "done", IrType::Bool);
+        ctx.set_terminator(Terminator::Branch { cond: done, then_block: exit_block, else_block: body_block });
+
+        ctx.switch_to(body_block);
+        if let Some(local) = var_local {
+            let value = self.lower_object_get(ctx, result, "value", value_type);
+            ctx.emit(Instruction::Assign { dest: Place::from_local(local), value: RValue::Use(value) });
+        }
+        self.loop_stack.push((next_block, break_block));
+        self.break_stack.push(break_block);
+        self.lower_stmt(ctx, &body.value, &body.span);
+        self.break_stack.pop();
+        self.loop_stack.pop();
+        if matches!(ctx.func.block(ctx.current_block).terminator, Terminator::Unreachable) {
+            ctx.set_terminator(Terminator::Jump(next_block));
+        }
+
+        ctx.switch_to(exit_block);
+        self.pop_scope();
+    }
+
+    /// `for (const x of gen)`: resume the generator until its result is
+    /// done, binding each yielded value. An async generator's results are
+    /// already settled when `next()` returns, so `for await` reads them the
+    /// same way.
+    fn lower_for_of_generator(
+        &mut self,
+        ctx: &mut FuncCtx,
+        left: &ForInLeft,
+        right: &Node<Expr>,
+        body: &Node<Stmt>,
+        types: &GeneratorTypes,
+    ) {
+        self.push_scope();
+        let Some(gen) = self.lower_expr(ctx, &right.value, &right.span) else {
+            self.pop_scope();
+            return;
+        };
+        let gen_temp = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Assign { dest: Place::from_temp(gen_temp), value: RValue::Use(gen) });
+
+        let value_type = types.yield_ty.clone();
+        let var_local = self.extract_for_in_var_name(left).map(|name| {
+            let local = ctx.add_local(value_type.clone());
+            self.define_var(&name, VarInfo { local_id: local, ir_type: value_type.clone(), is_boxed: false });
+            local
+        });
+
+        let next_block = ctx.new_block();
+        let body_block = ctx.new_block();
+        let exit_block = ctx.new_block();
+        ctx.set_terminator(Terminator::Jump(next_block));
+
+        ctx.switch_to(next_block);
+        let result = self.emit_generator_next(ctx, Value::Temp(gen_temp));
+        let done = self.lower_object_get(ctx, result.clone(), "done", IrType::Bool);
+        ctx.set_terminator(Terminator::Branch { cond: done, then_block: exit_block, else_block: body_block });
+
+        ctx.switch_to(body_block);
+        if let Some(local) = var_local {
+            let value = self.lower_object_get(ctx, result, "value", value_type);
+            ctx.emit(Instruction::Assign { dest: Place::from_local(local), value: RValue::Use(value) });
+        }
+        self.loop_stack.push((next_block, exit_block));
+        self.break_stack.push(exit_block);
+        self.lower_stmt(ctx, &body.value, &body.span);
+        self.break_stack.pop();
+        self.loop_stack.pop();
+        if matches!(ctx.func.block(ctx.current_block).terminator, Terminator::Unreachable) {
+            ctx.set_terminator(Terminator::Jump(next_block));
+        }
+
+        ctx.switch_to(exit_block);
+        self.pop_scope();
+    }
+
+    /// Type of `value` in the results of an iterator class's `next()`, from
+    /// its return annotation: `{ value: T; done: boolean }` or
+    /// `IteratorResult<T>`, or a promise of either. Unannotated values are
+    /// numbers.
+    fn iterator_value_type(&self, iterator_class: &str) -> IrType {
+        let annotation = self
+            .class_info
+            .get(iterator_class)
+            .and_then(|ci| ci.method_returns.iter().find(|(m, _)| m == "next"))
+            .map(|(_, ty)| match self.resolve_value_type(ty) {
+                Type::TypeRef { name, type_args: Some(args) } if name.value.name == "Promise" && !args.is_empty() => {
+                    self.resolve_value_type(&args[0].value)
+                }
+                ty => ty,
+            });
+        match annotation {
+            Some(Type::TypeRef { name, type_args: Some(args) }) if name.value.name == "IteratorResult" => {
+                args.first().map(|arg| self.ast_type_to_ir(&arg.value)).unwrap_or(IrType::F64)
+            }
+            Some(ty) => self
+                .annotation_shape(&ty)
+                .and_then(|shape| shape.into_iter().find(|(field, _)| field == "value"))
+                .map(|(_, ty)| ty)
+                .unwrap_or(IrType::F64),
+            None => IrType::F64,
+        }
+    }
+
     fn lower_for_of(
         &mut self,
         ctx: &mut FuncCtx,
         left: &ForInLeft,
         right: &Node<Expr>,
         body: &Node<Stmt>,
-        _span: &Span,
+        is_await: bool,
+        span: &Span,
     ) {
+        // Generators (sync, or async under `for await`) are resumed until done
+        if let Some(types) = self.expr_generator_types(&right.value) {
+            self.lower_for_of_generator(ctx, left, right, body, &types);
+            return;
+        }
+
+        // Instances of a class with `[Symbol.iterator]()` use the iterator
+        // protocol; `for await` prefers `[Symbol.asyncIterator]()`
+        if let IrType::Struct(struct_id) = self.infer_expr_type(&right.value) {
+            if let Some(class_name) = self.class_name_for_struct(struct_id) {
+                let has_method = |protocol: &str| {
+                    self.class_info.get(&class_name).is_some_and(|ci| ci.methods.contains(&symbol_key(protocol)))
+                };
+                let protocol = if is_await && has_method("asyncIterator") {
+                    Some("asyncIterator")
+                } else if has_method("iterator") {
+                    Some("iterator")
+                } else {
+                    None
+                };
+                if let Some(protocol) = protocol {
+                    let iterator_func = format!("{}_{}", class_name, symbol_key(protocol));
+                    let iterator_class = match self.module.find_function(&iterator_func).map(|f| f.return_type.clone()) {
+                        Some(IrType::Struct(struct_id)) => self.class_name_for_struct(struct_id),
+                        _ => None,
+                    };
+                    match iterator_class {
+                        Some(iterator_class) => {
+                            self.lower_for_of_iterator(ctx, left, right, body, &iterator_func, &iterator_class)
+                        }
+                        None => self.errors.push(LowerError::new(
+                            format!(
+                                "for...of over '{}' needs [Symbol.{}]() to return a class instance; \
+                                 only class-based iterators are supported, not object literals with next()",
+                                class_name, protocol
+                            ),
+                            *span,
+                        )),
+                    }
+                    return;
+                }
+            }
+        }
+
         self.push_scope();
 
         // Evaluate the right expression (array)
@@ -3254,6 +7955,13 @@ impl Lowerer {
             }
         };
 
+        // A string is iterated by its characters
+        let arr_type = self.infer_expr_type(&right.value);
+        let (arr_val, arr_type) = match arr_type {
+            IrType::Str => (self.emit_str_chars(ctx, arr_val), IrType::Array(Box::new(IrType::Str))),
+            arr_type => (arr_val, arr_type),
+        };
+
         // Store array in a temp for stability across blocks
         let arr_temp = ctx.add_temp(IrType::Ptr);
         ctx.emit(Instruction::Assign {
@@ -3271,7 +7979,6 @@ impl Lowerer {
         });
 
         // Determine element type from the array type
-        let arr_type = self.infer_expr_type(&right.value);
         let elem_type = match &arr_type {
             IrType::Array(inner) => (**inner).clone(),
             _ => IrType::F64,
@@ -3279,7 +7986,7 @@ impl Lowerer {
 
         // Choose runtime getter based on element type
         let (getter_name, getter_ret_type) = match &elem_type {
-            IrType::Str | IrType::Ptr | IrType::Array(_) | IrType::Struct(_) => {
+            IrType::Str | IrType::Ptr | IrType::Array(_) | IrType::Struct(_) | IrType::Promise(_) => {
                 ("zaco_array_get_ptr", IrType::Ptr)
             }
             _ => ("zaco_array_get_f64", IrType::F64),
@@ -3299,8 +8006,15 @@ impl Lowerer {
 
         // Create user-facing iteration variable
         let var_name = self.extract_for_in_var_name(left);
-        let user_elem_type = if getter_ret_type == IrType::Ptr && elem_type == IrType::Str {
-            IrType::Str
+        // `for await` settles each promise element before binding its value
+        let awaited_type = match &elem_type {
+            IrType::Promise(inner) if is_await => Some((**inner).clone()),
+            _ => None,
+        };
+        let user_elem_type = if let Some(awaited) = &awaited_type {
+            awaited.clone()
+        } else if getter_ret_type == IrType::Ptr && matches!(elem_type, IrType::Str | IrType::Array(_)) {
+            elem_type.clone()
         } else {
             getter_ret_type.clone()
         };
@@ -3314,6 +8028,14 @@ impl Lowerer {
                     is_boxed: false,
                 },
             );
+            match self.expr_element_shape(&right.value) {
+                Some(shape) => self.object_shapes.insert(name.clone(), shape),
+                None => self.object_shapes.remove(name),
+            };
+            match self.expr_value_type(&right.value).map(|ty| self.resolve_value_type(&ty)) {
+                Some(Type::Array(elem)) => self.value_types.insert(name.clone(), elem.value),
+                _ => self.value_types.remove(name),
+            };
         }
 
         // Create loop blocks
@@ -3353,9 +8075,13 @@ impl Lowerer {
                     func: Value::Const(Constant::Str(getter_name.to_string())),
                     args: vec![Value::Temp(arr_temp), Value::Local(counter_local)],
                 });
+                let elem = match &awaited_type {
+                    Some(awaited) => self.emit_await(ctx, Value::Temp(elem_temp), awaited.clone()),
+                    None => Value::Temp(elem_temp),
+                };
                 ctx.emit(Instruction::Assign {
                     dest: Place::from_local(info.local_id),
-                    value: RValue::Use(Value::Temp(elem_temp)),
+                    value: RValue::Use(elem),
                 });
             }
         }
@@ -3420,19 +8146,28 @@ impl Lowerer {
         }
         for member in &class_decl.members {
             if let ClassMember::Property {
-                name, type_annotation, is_static, ..
+                name, type_annotation, init, is_static, ..
             } = member
             {
                 if *is_static {
                     continue;
                 }
                 let field_name = self.property_name_to_string(name);
-                let field_type = type_annotation
-                    .as_ref()
-                    .map(|t| self.ast_type_to_ir(&t.value))
-                    .unwrap_or(IrType::F64);
+                let field_type = match (type_annotation, init) {
+                    (Some(t), _) => self.ast_type_to_ir(&t.value),
+                    (None, Some(init)) => self.infer_expr_type(&init.value),
+                    (None, None) => IrType::F64,
+                };
                 fields.push((field_name, field_type));
             }
+            // Constructor parameter properties are fields too
+            if let ClassMember::Constructor { params, .. } = member {
+                for param in params.iter().filter(|p| p.property.is_some()) {
+                    if let Pattern::Ident { name, .. } = &param.pattern.value {
+                        fields.push((name.value.name.clone(), self.infer_param_type(param)));
+                    }
+                }
+            }
         }
 
         // Step 2: Create IrStruct
@@ -3459,6 +8194,16 @@ impl Lowerer {
             }
         }
 
+        let mut method_returns: Vec<(String, Type)> =
+            parent_info.as_ref().map(|pi| pi.method_returns.clone()).unwrap_or_default();
+        for member in &class_decl.members {
+            if let ClassMember::Method { name, return_type: Some(ret), is_static: false, .. } = member {
+                let mname = self.property_name_to_string(name);
+                method_returns.retain(|(m, _)| *m != mname);
+                method_returns.push((mname, ret.value.clone()));
+            }
+        }
+
         // Collect getter/setter names
         let mut getter_names: Vec<String> = Vec::new();
         let mut setter_names: Vec<String> = Vec::new();
@@ -3510,15 +8255,27 @@ impl Lowerer {
             setters: setter_names,
             static_methods: static_method_names,
             static_properties: static_prop_info.clone(),
+            method_returns,
+            constructor_slot: None,
         });
 
+        // `new` reaches a decorated class through a global its class
+        // decorators can replace the constructor in
+        if !class_decl.decorators.is_empty() {
+            let slot = format!("__{}_constructor_impl", class_name);
+            self.module.add_global(slot.clone(), IrType::Ptr, None);
+            if let Some(info) = self.class_info.get_mut(&class_name) {
+                info.constructor_slot = Some(slot);
+            }
+        }
+
         // Step 3: Lower constructor
         self.lower_class_constructor(class_decl, &class_name, struct_id, &fields, parent_name.as_deref(), span);
 
         // Step 4: Lower own methods
         for member in &class_decl.members {
             if let ClassMember::Method {
-                name, params, return_type, body, is_static, ..
+                name, params, return_type, body, is_static, is_async, ..
             } = member
             {
                 if *is_static {
@@ -3533,6 +8290,7 @@ impl Lowerer {
                         params,
                         return_type.as_deref(),
                         body,
+                        *is_async,
                         &fields,
                         span,
                     );
@@ -3563,10 +8321,16 @@ impl Lowerer {
             }
         }
 
-        // Step 6: Lower static properties as module-level globals
+        // Step 6: Lower static properties as module-level globals. Reads
+        // and writes are not routed through them yet, so they are rejected
         for member in &class_decl.members {
             if let ClassMember::Property { name, type_annotation, is_static, init, .. } = member {
                 if *is_static {
+                    let name_span = match name {
+                        PropertyName::Ident(ident) => &ident.span,
+                        _ => span,
+                    };
+                    self.unsupported("a static property", name_span);
                     let prop_name = self.property_name_to_string(name);
                     let prop_type = type_annotation
                         .as_ref()
@@ -3637,13 +8401,23 @@ impl Lowerer {
         let child_func_name = format!("{}_{}", child_class, method_name);
 
         // Look up the parent method signature from the module
-        let (param_types, ret_type) = if let Some(parent_func) = self.module.find_function(&parent_func_name) {
+        let (param_types, mut ret_type) = if let Some(parent_func) = self.module.find_function(&parent_func_name) {
             let params: Vec<(LocalId, IrType)> = parent_func.params.clone();
             (params, parent_func.return_type.clone())
         } else {
             return; // Parent method not found, skip
         };
 
+        // A method returning `this` returns the child when called on one
+        let returns_this = self.class_info.get(child_class).is_some_and(|ci| {
+            ci.method_returns.iter().any(|(m, ty)| {
+                m == method_name && matches!(ty, Type::TypeRef { name, .. } if name.value.name == "this")
+            })
+        });
+        if returns_this {
+            ret_type = IrType::Struct(child_struct_id);
+        }
+
         let func_id = self.alloc_func_id();
 
         // Build params: same as parent but with child struct type for self
@@ -3709,6 +8483,8 @@ impl Lowerer {
         }
         let prev_class = self.current_class.take();
         self.current_class = Some(class_name.to_string());
+        self.hoist_vars(&mut func_ctx, &body.value.stmts);
+        self.bind_module_globals(&mut func_ctx, &body.value.stmts);
         for s in &body.value.stmts { self.lower_stmt(&mut func_ctx, &s.value, &s.span); }
         if matches!(func_ctx.func.block(func_ctx.current_block).terminator, Terminator::Unreachable) {
             if ret_type == IrType::Void { func_ctx.set_terminator(Terminator::Return(None)); }
@@ -3743,6 +8519,8 @@ impl Lowerer {
         let prev_class = self.current_class.take();
         self.this_var = Some(VarInfo { local_id: LocalId(0), ir_type: IrType::Struct(struct_id), is_boxed: false });
         self.current_class = Some(class_name.to_string());
+        self.hoist_vars(&mut func_ctx, &body.value.stmts);
+        self.bind_module_globals(&mut func_ctx, &body.value.stmts);
         for s in &body.value.stmts { self.lower_stmt(&mut func_ctx, &s.value, &s.span); }
         if matches!(func_ctx.func.block(func_ctx.current_block).terminator, Terminator::Unreachable) {
             let t = func_ctx.add_temp(ret_type.clone());
@@ -3772,6 +8550,8 @@ impl Lowerer {
         self.current_class = Some(class_name.to_string());
         let pn = match &param.pattern.value { Pattern::Ident { name, .. } => name.value.name.clone(), _ => "_value".to_string() };
         self.define_var(&pn, VarInfo { local_id: LocalId(1), ir_type: param_type, is_boxed: false });
+        self.hoist_vars(&mut func_ctx, &body.value.stmts);
+        self.bind_module_globals(&mut func_ctx, &body.value.stmts);
         for s in &body.value.stmts { self.lower_stmt(&mut func_ctx, &s.value, &s.span); }
         if matches!(func_ctx.func.block(func_ctx.current_block).terminator, Terminator::Unreachable) {
             func_ctx.set_terminator(Terminator::Return(None));
@@ -3790,27 +8570,55 @@ impl Lowerer {
         struct_id: StructId,
         fields: &[(String, IrType)],
         parent_name: Option<&str>,
-        _span: &Span,
+        span: &Span,
     ) {
         let constructor_name = format!("{}_constructor", class_name);
         let func_id = self.alloc_func_id();
 
         // Find constructor member
-        let (ctor_params, ctor_body) = class_decl.members.iter().find_map(|m| {
+        let ctor = class_decl.members.iter().find_map(|m| {
             if let ClassMember::Constructor { params, body, .. } = m {
                 Some((params.clone(), body.clone()))
             } else {
                 None
             }
-        }).unwrap_or_else(|| (Vec::new(), None));
+        });
 
-        // Build parameter list for the constructor function
+        // Build parameter list for the constructor function. A derived class
+        // without a constructor takes its parent's arguments and forwards them.
         let mut ir_params: Vec<(LocalId, IrType)> = Vec::new();
-        for (i, param) in ctor_params.iter().enumerate() {
-            let ir_type = self.infer_param_type(param);
-            let local_id = LocalId(i);
-            ir_params.push((local_id, ir_type));
-        }
+        let mut param_names: Vec<String> = Vec::new();
+        let (ctor_params, ctor_stmts) = match ctor {
+            Some((params, body)) => {
+                for (i, param) in params.iter().enumerate() {
+                    ir_params.push((LocalId(i), self.infer_param_type(param)));
+                    param_names.push(match &param.pattern.value {
+                        Pattern::Ident { name, .. } => name.value.name.clone(),
+                        _ => format!("_param{}", i),
+                    });
+                }
+                (params, body.map(|b| b.value.stmts).unwrap_or_default())
+            }
+            None => {
+                let parent_ctor = parent_name
+                    .and_then(|parent| self.module.find_function(&format!("{}_constructor", parent)))
+                    .map(|f| f.params.clone())
+                    .unwrap_or_default();
+                let mut stmts = Vec::new();
+                if parent_name.is_some() {
+                    let mut args = Vec::new();
+                    for (i, (_, ty)) in parent_ctor.into_iter().enumerate() {
+                        let name = format!("__super_arg{}", i);
+                        ir_params.push((LocalId(i), ty));
+                        args.push(Node::new(Expr::Ident(Ident::new(name.clone())), *span));
+                        param_names.push(name);
+                    }
+                    let call = Expr::Call { callee: Box::new(Node::new(Expr::Super, *span)), type_args: None, args };
+                    stmts.push(Node::new(Stmt::Expr(Node::new(call, *span)), *span));
+                }
+                (Vec::new(), stmts)
+            }
+        };
 
         // Return type is always Ptr (pointer to struct)
         let mut ir_func = IrFunction::new(
@@ -3830,13 +8638,9 @@ impl Lowerer {
         self.push_scope();
 
         // Register constructor params in scope
-        for (i, param) in ctor_params.iter().enumerate() {
-            let param_name = match &param.pattern.value {
-                Pattern::Ident { name, .. } => name.value.name.clone(),
-                _ => format!("_param{}", i),
-            };
+        for (i, param_name) in param_names.iter().enumerate() {
             let (local_id, ir_type) = &ir_params[i];
-            self.define_var(&param_name, VarInfo {
+            self.define_var(param_name, VarInfo {
                 local_id: *local_id,
                 ir_type: ir_type.clone(),
                 is_boxed: false,
@@ -3891,19 +8695,14 @@ impl Lowerer {
 
         // Lower constructor body — super() calls are handled in lower_stmt/lower_call
         // by detecting Call { callee: Expr::Super, args } pattern
-        if let Some(ref body) = ctor_body {
-            // Before lowering body, save parent info for super() resolution
-            let prev_parent = std::mem::replace(
-                &mut self.current_class_parent,
-                parent_for_super,
-            );
-
-            for s in &body.value.stmts {
-                self.lower_stmt(&mut func_ctx, &s.value, &s.span);
-            }
-
-            self.current_class_parent = prev_parent;
+        let prev_parent = std::mem::replace(&mut self.current_class_parent, parent_for_super);
+        self.hoist_vars(&mut func_ctx, &ctor_stmts);
+        let body_stmts = Self::with_field_initializers(&ctor_params, &class_decl.members, &ctor_stmts);
+        self.bind_module_globals(&mut func_ctx, &body_stmts);
+        for s in &body_stmts {
+            self.lower_stmt(&mut func_ctx, &s.value, &s.span);
         }
+        self.current_class_parent = prev_parent;
 
         // Return self
         if matches!(
@@ -3930,6 +8729,7 @@ impl Lowerer {
         params: &[Param],
         return_type: Option<&Node<Type>>,
         body: &Node<BlockStmt>,
+        is_async: bool,
         _fields: &[(String, IrType)],
         _span: &Span,
     ) {
@@ -3946,9 +8746,21 @@ impl Lowerer {
             ir_params.push((local_id, ir_type));
         }
 
-        let ret_type = return_type
-            .map(|t| self.ast_type_to_ir(&t.value))
-            .unwrap_or(IrType::Void);
+        // Set up `this` as first param; the class is current before the
+        // return type is resolved so a `this` return type names it
+        let prev_this = self.this_var.take();
+        let prev_class = self.current_class.replace(class_name.to_string());
+        self.this_var = Some(VarInfo {
+            local_id: LocalId(0),
+            ir_type: IrType::Struct(struct_id),
+            is_boxed: false,
+        });
+
+        let ret_type = if is_async {
+            self.async_return_type(return_type)
+        } else {
+            return_type.map(|t| self.ast_type_to_ir(&t.value)).unwrap_or(IrType::Void)
+        };
 
         let mut ir_func = IrFunction::new(func_id, func_name.clone(), ir_params.clone(), ret_type.clone());
         let entry = ir_func.new_block();
@@ -3961,16 +8773,6 @@ impl Lowerer {
 
         self.push_scope();
 
-        // Set up `this` as first param
-        let prev_this = self.this_var.take();
-        let prev_class = self.current_class.take();
-        self.this_var = Some(VarInfo {
-            local_id: LocalId(0),
-            ir_type: IrType::Struct(struct_id),
-            is_boxed: false,
-        });
-        self.current_class = Some(class_name.to_string());
-
         // Register non-self params in scope
         for (i, param) in params.iter().enumerate() {
             let param_name = match &param.pattern.value {
@@ -3983,11 +8785,18 @@ impl Lowerer {
                 ir_type: ir_type.clone(),
                 is_boxed: false,
             });
+            self.track_object_var(&param_name, Self::param_annotation(param), None);
         }
 
         // Lower body
-        for s in &body.value.stmts {
-            self.lower_stmt(&mut func_ctx, &s.value, &s.span);
+        self.hoist_vars(&mut func_ctx, &body.value.stmts);
+        self.bind_module_globals(&mut func_ctx, &body.value.stmts);
+        if is_async {
+            self.lower_async_body(&mut func_ctx, &body.value.stmts);
+        } else {
+            for s in &body.value.stmts {
+                self.lower_stmt(&mut func_ctx, &s.value, &s.span);
+            }
         }
 
         // Add implicit return if needed
@@ -4027,6 +8836,28 @@ impl Lowerer {
             Expr::Ident(ident) => ident.name.clone(),
             _ => return None,
         };
+        if self.check_type_only_use(&class_name) {
+            return None;
+        }
+
+        // `new f(args)` through a variable holding a constructor, such as a
+        // class decorator's target, calls it like any function value
+        if let Some(IrType::FuncPtr(sig)) = self.lookup_var(&class_name).map(|info| info.ir_type.clone()) {
+            let constructor = self.lower_expr(ctx, &callee.value, &callee.span)?;
+            return self.lower_indirect_call(ctx, constructor, &sig, args);
+        }
+
+        // new AbortController() -> runtime handle
+        if class_name == "AbortController" && !self.class_info.contains_key(&class_name) {
+            self.ensure_extern("zaco_abort_controller_new", vec![], IrType::Ptr);
+            let result = ctx.add_temp(IrType::Ptr);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str("zaco_abort_controller_new".to_string())),
+                args: vec![],
+            });
+            return Some(Value::Temp(result));
+        }
 
         // Verify it's a known class
         let class_info = self.class_info.get(&class_name)?.clone();
@@ -4043,6 +8874,17 @@ impl Lowerer {
 
         // Call ClassName_constructor(args) -> Ptr
         let constructor_name = format!("{}_constructor", class_name);
+        // A class decorator may have replaced the constructor; call whatever it left
+        if let Some(slot) = class_info.constructor_slot {
+            let constructor = self.module.find_function(&constructor_name)?;
+            let sig = FuncSignature {
+                params: constructor.params.iter().map(|(_, ty)| ty.clone()).collect(),
+                return_type: Box::new(IrType::Struct(class_info.struct_id)),
+            };
+            let closure = ctx.add_temp(IrType::Ptr);
+            ctx.emit(Instruction::Load { dest: Place::from_temp(closure), ptr: Value::Const(Constant::GlobalAddr(slot)) });
+            return self.emit_indirect_call(ctx, Value::Temp(closure), &sig, &arg_vals);
+        }
         let result = ctx.add_temp(IrType::Struct(class_info.struct_id));
         ctx.emit(Instruction::Call {
             dest: Some(Place::from_temp(result)),
@@ -4053,12 +8895,64 @@ impl Lowerer {
         Some(Value::Temp(result))
     }
 
-    /// Lower `this` expression
+    /// Lower `this` expression; outside a class it is `undefined`, as in
+    /// an ES module
     fn lower_this_expr(&self) -> Option<Value> {
-        self.this_var.as_ref().map(|info| Value::Local(info.local_id))
+        let this = self.this_var.as_ref().map(|info| Value::Local(info.local_id));
+        Some(this.unwrap_or(Value::Const(Constant::Undefined)))
     }
 
     /// Lower member expression: object.property (for reads)
+    /// Directory of the module being lowered (`__dirname`, `import.meta.dirname`)
+    fn module_dirname(&self) -> String {
+        self.file_path
+            .as_ref()
+            .and_then(|p| std::path::Path::new(p).parent().map(|d| d.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| ".".to_string())
+    }
+
+    /// Path of the module being lowered (`__filename`, `import.meta.filename`)
+    fn module_filename(&self) -> String {
+        self.file_path.clone().unwrap_or_else(|| "<unknown>".to_string())
+    }
+
+    /// `Symbol.<name>` for a well-known symbol, unless `Symbol` is shadowed
+    fn well_known_symbol<'e>(&self, object: &Node<Expr>, property: &'e Node<Ident>) -> Option<&'e str> {
+        let is_symbol = matches!(&object.value, Expr::Ident(id) if id.name == "Symbol") && self.lookup_var("Symbol").is_none();
+        let name = property.value.name.as_str();
+        (is_symbol && WELL_KNOWN_SYMBOLS.contains(&name)).then_some(name)
+    }
+
+    /// `import.meta.url` / `.dirname` / `.filename`, known at compile time
+    fn import_meta_property(&self, object: &Expr, property: &str) -> Option<String> {
+        let Expr::MetaProperty { meta, property: meta_property } = object else {
+            return None;
+        };
+        if meta.value.name != "import" || meta_property.value.name != "meta" {
+            return None;
+        }
+        match property {
+            "url" => {
+                // Percent-encode what a file: URL cannot hold literally
+                let mut url = String::from("file://");
+                for ch in self.module_filename().chars() {
+                    match ch {
+                        '%' => url.push_str("%25"),
+                        ' ' => url.push_str("%20"),
+                        '#' => url.push_str("%23"),
+                        '?' => url.push_str("%3F"),
+                        '\\' => url.push('/'),
+                        _ => url.push(ch),
+                    }
+                }
+                Some(url)
+            }
+            "dirname" => Some(self.module_dirname()),
+            "filename" => Some(self.module_filename()),
+            _ => None,
+        }
+    }
+
     fn lower_member_expr(
         &mut self,
         ctx: &mut FuncCtx,
@@ -4066,6 +8960,33 @@ impl Lowerer {
         property: &Node<Ident>,
         _span: &Span,
     ) -> Option<Value> {
+        if let Some(value) = self.import_meta_property(&object.value, &property.value.name) {
+            self.module.intern_string(value.clone());
+            return Some(Value::Const(Constant::Str(value)));
+        }
+
+        match self.enum_member_value(&object.value, &property.value.name) {
+            Some(EnumValue::Number(n)) => return Some(Value::Const(Constant::F64(n))),
+            Some(EnumValue::String(text)) => {
+                self.module.intern_string(text.clone());
+                return Some(Value::Const(Constant::Str(text)));
+            }
+            None => {}
+        }
+
+        // Symbol.iterator etc.: one runtime handle per well-known symbol
+        if let Some(symbol) = self.well_known_symbol(object, property) {
+            self.ensure_extern("zaco_symbol_well_known", vec![IrType::Str], IrType::Ptr);
+            self.module.intern_string(symbol.to_string());
+            let result = ctx.add_temp(IrType::Ptr);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str("zaco_symbol_well_known".to_string())),
+                args: vec![Value::Const(Constant::Str(symbol.to_string()))],
+            });
+            return Some(Value::Temp(result));
+        }
+
         // Check for Math.PI, Math.E, etc.
         if let Expr::Ident(obj_ident) = &object.value {
             if obj_ident.name == "Math" {
@@ -4077,6 +8998,12 @@ impl Lowerer {
             }
         }
 
+        if self.lookup_var("process").is_none() {
+            if let Some(val) = self.lower_process_property(ctx, &object.value, &property.value.name) {
+                return Some(val);
+            }
+        }
+
         // Handle ClassName.staticProp — static property access
         if let Expr::Ident(obj_ident) = &object.value {
             if let Some(ci) = self.class_info.get(&obj_ident.name).cloned() {
@@ -4115,7 +9042,7 @@ impl Lowerer {
                     return Some(Value::Temp(result));
                 }
                 let class_info = self.class_info.get(class_name)?;
-                let field_idx = class_info.fields.iter().position(|(n, _)| n == field_name)?;
+                let field_idx = class_info.field_index(field_name)?;
                 let field_type = class_info.fields[field_idx].1.clone();
 
                 let result = ctx.add_temp(field_type);
@@ -4132,700 +9059,2427 @@ impl Lowerer {
             }
         }
 
-        // Handle obj.field where obj is a class instance — check getter first
-        if let Expr::Ident(obj_ident) = &object.value {
-            if let Some(info) = self.lookup_var(&obj_ident.name).cloned() {
-                if let IrType::Struct(struct_id) = &info.ir_type {
-                    if let Some((class_name, _)) = self.class_info.iter()
-                        .find(|(_, ci)| ci.struct_id == *struct_id)
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                    {
-                        let field_name = &property.value.name;
-                        // Check if this property has a getter
-                        let has_getter = self.class_info.get(&class_name)
-                            .map(|ci| ci.getters.contains(&field_name.to_string()))
-                            .unwrap_or(false);
-                        if has_getter {
-                            let getter_func = format!("{}_get_{}", class_name, field_name);
-                            let ret_type = self.module.find_function(&getter_func)
-                                .map(|f| f.return_type.clone())
-                                .unwrap_or(IrType::F64);
-                            let result = ctx.add_temp(ret_type);
-                            ctx.emit(Instruction::Call {
-                                dest: Some(Place::from_temp(result)),
-                                func: Value::Const(Constant::Str(getter_func)),
-                                args: vec![Value::Local(info.local_id)],
-                            });
-                            return Some(Value::Temp(result));
-                        }
-                        return self.load_struct_field(ctx, Value::Local(info.local_id), &class_name, field_name);
-                    }
+        // Handle obj.field where obj is a class instance, e.g. `p.x`,
+        // `make().x` or `a.b.c` — check getter first
+        if let IrType::Struct(struct_id) = self.infer_expr_type(&object.value) {
+            if let Some(class_name) = self.class_name_for_struct(struct_id) {
+                let field_name = &property.value.name;
+                let receiver = self.lower_receiver(ctx, object, field_name)?;
+                let has_getter = self.class_info.get(&class_name)
+                    .map(|ci| ci.getters.contains(&field_name.to_string()))
+                    .unwrap_or(false);
+                if has_getter {
+                    let getter_func = format!("{}_get_{}", class_name, field_name);
+                    let ret_type = self.module.find_function(&getter_func)
+                        .map(|f| f.return_type.clone())
+                        .unwrap_or(IrType::F64);
+                    let result = ctx.add_temp(ret_type);
+                    ctx.emit(Instruction::Call {
+                        dest: Some(Place::from_temp(result)),
+                        func: Value::Const(Constant::Str(getter_func)),
+                        args: vec![receiver],
+                    });
+                    return Some(Value::Temp(result));
                 }
+                return self.load_struct_field(ctx, receiver, &class_name, field_name);
             }
         }
 
-        // For other member expressions, fall through
-        None
-    }
-
-    /// Load a field from a struct pointer by computing offset
-    fn load_struct_field(
-        &self,
-        ctx: &mut FuncCtx,
-        obj_ptr: Value,
-        class_name: &str,
-        field_name: &str,
-    ) -> Option<Value> {
-        let class_info = self.class_info.get(class_name)?;
-        let field_idx = class_info.fields.iter().position(|(n, _)| n == field_name)?;
-        let field_type = class_info.fields[field_idx].1.clone();
-
-        // Compute byte offset
-        let mut offset: i64 = 0;
-        for i in 0..field_idx {
-            offset += class_info.fields[i].1.size_bytes() as i64;
-        }
-
-        if offset == 0 {
-            // Field is at the start of struct, load directly from ptr
-            let result = ctx.add_temp(field_type);
-            ctx.emit(Instruction::Load {
-                dest: Place::from_temp(result),
-                ptr: obj_ptr,
-            });
-            Some(Value::Temp(result))
-        } else {
-            // Compute ptr + offset, then load
-            // We need to add offset to the pointer
-            let offset_temp = ctx.add_temp(IrType::I64);
-            ctx.emit(Instruction::Assign {
-                dest: Place::from_temp(offset_temp),
-                value: RValue::Use(Value::Const(Constant::I64(offset))),
+        // Handle arr.length on any array-valued expression
+        if property.value.name == "length" && matches!(self.infer_expr_type(&object.value), IrType::Array(_)) {
+            let array = self.lower_receiver(ctx, object, "length")?;
+            self.ensure_extern("zaco_array_length", vec![IrType::Ptr], IrType::I64);
+            let len = ctx.add_temp(IrType::I64);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(len)),
+                func: Value::Const(Constant::Str("zaco_array_length".to_string())),
+                args: vec![array],
             });
-            let addr_temp = ctx.add_temp(IrType::Ptr);
+            let result = ctx.add_temp(IrType::F64);
             ctx.emit(Instruction::Assign {
-                dest: Place::from_temp(addr_temp),
-                value: RValue::BinaryOp {
-                    op: BinOp::Add,
-                    left: obj_ptr,
-                    right: Value::Temp(offset_temp),
-                },
-            });
-            let result = ctx.add_temp(field_type);
-            ctx.emit(Instruction::Load {
                 dest: Place::from_temp(result),
-                ptr: Value::Temp(addr_temp),
+                value: RValue::Cast { value: Value::Temp(len), ty: IrType::F64 },
             });
-            Some(Value::Temp(result))
+            return Some(Value::Temp(result));
         }
-    }
 
-    /// Store a value to a struct field by computing offset
-    fn store_struct_field(
-        &self,
-        ctx: &mut FuncCtx,
-        obj_ptr: Value,
-        class_name: &str,
-        field_name: &str,
-        value: Value,
-    ) -> bool {
-        let class_info = match self.class_info.get(class_name) {
-            Some(ci) => ci,
-            None => return false,
-        };
-        let field_idx = match class_info.fields.iter().position(|(n, _)| n == field_name) {
-            Some(idx) => idx,
-            None => return false,
-        };
+        // strings.raw on a tag function's TemplateStringsArray (the type
+        // checker only allows `raw` there)
+        if property.value.name == "raw" && self.infer_expr_type(&object.value) == IrType::Array(Box::new(IrType::Str)) {
+            let strings = self.lower_expr(ctx, &object.value, &object.span)?;
+            self.ensure_extern("zaco_template_raw", vec![IrType::Ptr], IrType::Array(Box::new(IrType::Str)));
+            let result = ctx.add_temp(IrType::Array(Box::new(IrType::Str)));
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str("zaco_template_raw".to_string())),
+                args: vec![strings],
+            });
+            return Some(Value::Temp(result));
+        }
 
-        // Compute byte offset
-        let mut offset: i64 = 0;
-        for i in 0..field_idx {
-            offset += class_info.fields[i].1.size_bytes() as i64;
+        // match.index on a matchAll match (the type checker only allows
+        // `index` on a RegExpMatchArray)
+        if property.value.name == "index" && self.infer_expr_type(&object.value) == IrType::Array(Box::new(IrType::Str)) {
+            let matched = self.lower_expr(ctx, &object.value, &object.span)?;
+            self.ensure_extern("zaco_regex_match_index", vec![IrType::Ptr], IrType::F64);
+            let result = ctx.add_temp(IrType::F64);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str("zaco_regex_match_index".to_string())),
+                args: vec![matched],
+            });
+            return Some(Value::Temp(result));
         }
 
-        if offset == 0 {
-            ctx.emit(Instruction::Store {
-                ptr: obj_ptr,
-                value,
+        // Handle str.length on any string-valued expression
+        if property.value.name == "length" && self.infer_expr_type(&object.value) == IrType::Str {
+            let receiver = self.lower_receiver(ctx, object, "length")?;
+            self.ensure_extern("zaco_str_len", vec![IrType::Str], IrType::I64);
+            let len = ctx.add_temp(IrType::I64);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(len)),
+                func: Value::Const(Constant::Str("zaco_str_len".to_string())),
+                args: vec![receiver],
             });
-        } else {
-            let offset_temp = ctx.add_temp(IrType::I64);
+            let result = ctx.add_temp(IrType::F64);
             ctx.emit(Instruction::Assign {
-                dest: Place::from_temp(offset_temp),
-                value: RValue::Use(Value::Const(Constant::I64(offset))),
+                dest: Place::from_temp(result),
+                value: RValue::Cast { value: Value::Temp(len), ty: IrType::F64 },
             });
-            let addr_temp = ctx.add_temp(IrType::Ptr);
-            ctx.emit(Instruction::Assign {
-                dest: Place::from_temp(addr_temp),
-                value: RValue::BinaryOp {
-                    op: BinOp::Add,
-                    left: obj_ptr,
-                    right: Value::Temp(offset_temp),
-                },
+            return Some(Value::Temp(result));
+        }
+
+        // Handle res.status / res.ok on a fetch Response
+        if self.is_fetch_response(&object.value) {
+            let (getter, ty) = match property.value.name.as_str() {
+                "status" => ("zaco_response_status", IrType::F64),
+                "ok" => ("zaco_response_ok", IrType::Bool),
+                _ => return None,
+            };
+            let response = self.lower_expr(ctx, &object.value, &object.span)?;
+            self.ensure_extern(getter, vec![IrType::Ptr], ty.clone());
+            let result = ctx.add_temp(ty);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str(getter.to_string())),
+                args: vec![response],
             });
-            ctx.emit(Instruction::Store {
-                ptr: Value::Temp(addr_temp),
-                value,
+            return Some(Value::Temp(result));
+        }
+
+        // Handle controller.signal / signal.aborted on an AbortController.
+        // The controller handle is also its signal.
+        if property.value.name == "signal" && self.is_abort_signal(&object.value) {
+            return self.lower_expr(ctx, &object.value, &object.span);
+        }
+        if property.value.name == "aborted" && self.is_abort_signal(&object.value) {
+            let signal = self.lower_expr(ctx, &object.value, &object.span)?;
+            self.ensure_extern("zaco_abort_signal_aborted", vec![IrType::Ptr], IrType::Bool);
+            let result = ctx.add_temp(IrType::Bool);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str("zaco_abort_signal_aborted".to_string())),
+                args: vec![signal],
             });
+            return Some(Value::Temp(result));
         }
-        true
+
+        // Handle obj.field on a plain object of known shape
+        if let Some(field_ty) = self.object_field_type(&object.value, &property.value.name) {
+            let obj = self.lower_receiver(ctx, object, &property.value.name)?;
+            return Some(self.lower_object_get(ctx, obj, &property.value.name, field_ty));
+        }
+
+        // For other member expressions, fall through
+        None
     }
 
-    /// Lower member assignment: this.field = value or obj.field = value
-    fn lower_member_assignment(
-        &mut self,
-        ctx: &mut FuncCtx,
-        object: &Node<Expr>,
-        property: &Node<Ident>,
-        _op: AssignmentOp,
-        rhs: Value,
-    ) -> Option<Value> {
-        let field_name = &property.value.name;
+    /// Read `key` from a plain object (ZacoObject) as a value of type `ty`.
+    fn lower_object_get(&mut self, ctx: &mut FuncCtx, obj: Value, key: &str, ty: IrType) -> Value {
+        let (getter, ret_type) = match ty {
+            IrType::F64 => ("zaco_object_get_f64", IrType::F64),
+            IrType::Str => ("zaco_object_get_str", IrType::Str),
+            IrType::I64 | IrType::Bool => ("zaco_object_get_i64", IrType::I64),
+            _ => ("zaco_object_get_ptr", IrType::Ptr),
+        };
+        self.ensure_extern(getter, vec![IrType::Ptr, IrType::Ptr], ret_type.clone());
+        self.module.intern_string(key.to_string());
+        let result = ctx.add_temp(ret_type);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(getter.to_string())),
+            args: vec![obj, Value::Const(Constant::Str(key.to_string()))],
+        });
+        if ty != IrType::Bool {
+            return Value::Temp(result);
+        }
+        let flag = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(flag),
+            value: RValue::Cast { value: Value::Temp(result), ty: IrType::Bool },
+        });
+        Value::Temp(flag)
+    }
 
-        // Handle ClassName.staticProp = value — static property write
-        if let Expr::Ident(obj_ident) = &object.value {
-            if let Some(ci) = self.class_info.get(&obj_ident.name).cloned() {
-                if ci.static_properties.iter().any(|(n, _)| n == field_name) {
-                    let global_name = format!("{}_{}", obj_ident.name, field_name);
-                    ctx.emit(Instruction::Store {
-                        ptr: Value::Const(Constant::Str(global_name)),
-                        value: rhs.clone(),
-                    });
-                    return Some(rhs);
+    /// Record what is known about a new variable's value: a sqlite
+    /// Database, or the shape of a plain object or array of plain objects,
+    /// taken from its annotation or else its initializer.
+    fn track_object_var(&mut self, name: &str, annotation: Option<&Node<Type>>, init: Option<&Node<Expr>>) {
+        self.sqlite_databases.remove(name);
+        self.fetch_responses.remove(name);
+        self.generator_vars.remove(name);
+        self.abort_controllers.remove(name);
+        self.object_shapes.remove(name);
+        self.element_shapes.remove(name);
+        self.value_types.remove(name);
+        if let Some(ty) = annotation.map(|ty| ty.value.clone()).or_else(|| init.and_then(|i| self.expr_value_type(&i.value))) {
+            self.value_types.insert(name.to_string(), ty);
+        }
+
+        if let Some(Expr::Call { callee, .. }) = init.map(|i| &i.value) {
+            if let Expr::Ident(callee_ident) = &callee.value {
+                if let Some(types) = self.generator_functions.get(&callee_ident.name) {
+                    self.generator_vars.insert(name.to_string(), types.clone());
+                }
+                if self.imported_bindings.get(&callee_ident.name).is_some_and(|m| m == "sqlite")
+                    && callee_ident.name == "open"
+                {
+                    self.sqlite_databases.insert(name.to_string());
                 }
             }
         }
 
-        // Handle this.field = value — check setter first
-        if matches!(&object.value, Expr::This) {
-            if let (Some(this_info), Some(class_name)) = (self.this_var.clone(), self.current_class.clone()) {
-                let has_setter = self.class_info.get(&class_name)
-                    .map(|ci| ci.setters.contains(&field_name.to_string()))
-                    .unwrap_or(false);
-                if has_setter {
-                    let setter_func = format!("{}_set_{}", class_name, field_name);
-                    ctx.emit(Instruction::Call {
-                        dest: None,
-                        func: Value::Const(Constant::Str(setter_func)),
-                        args: vec![Value::Local(this_info.local_id), rhs.clone()],
-                    });
-                    return Some(rhs);
-                }
-                self.store_struct_field(ctx, Value::Local(this_info.local_id), &class_name, field_name, rhs.clone());
-                return Some(rhs);
+        let is_response_annotation = matches!(
+            annotation.map(|ty| &ty.value),
+            Some(Type::TypeRef { name: type_name, .. }) if type_name.value.name == "Response"
+        );
+        let is_awaited_fetch = matches!(
+            init.map(|i| &i.value),
+            Some(Expr::Await(inner)) if self.is_fetch_call(&inner.value)
+        );
+        if is_response_annotation || is_awaited_fetch {
+            self.fetch_responses.insert(name.to_string());
+        }
+        if init.is_some_and(|i| self.is_abort_controller_new(&i.value) || self.is_abort_signal(&i.value)) {
+            self.abort_controllers.insert(name.to_string());
+        }
+
+        let shape = annotation
+            .and_then(|ty| self.annotation_shape(&ty.value))
+            .or_else(|| init.and_then(|i| self.expr_object_shape(&i.value)));
+        if let Some(shape) = shape {
+            self.object_shapes.insert(name.to_string(), shape);
+        }
+        let element_shape = annotation
+            .and_then(|ty| match &ty.value {
+                Type::Array(elem) => self.annotation_shape(&elem.value),
+                _ => None,
+            })
+            .or_else(|| init.and_then(|i| self.expr_element_shape(&i.value)));
+        if let Some(shape) = element_shape {
+            self.element_shapes.insert(name.to_string(), shape);
+        }
+    }
+
+    /// Field types of an object type annotation (`{ id: number }`, or the
+    /// name of an interface or object type alias).
+    fn annotation_shape(&self, ty: &Type) -> Option<Vec<(String, IrType)>> {
+        match ty {
+            Type::Object(obj) => Some(self.object_members_shape(&obj.members)),
+            Type::TypeRef { name, .. } => self.object_types.get(&name.value.name).cloned(),
+            Type::Paren(inner) => self.annotation_shape(&inner.value),
+            _ => None,
+        }
+    }
+
+    fn object_members_shape(&self, members: &[ObjectTypeMember]) -> Vec<(String, IrType)> {
+        members
+            .iter()
+            .filter_map(|member| match member {
+                ObjectTypeMember::Property { name, ty, .. } => {
+                    Some((Self::property_key(name)?, self.ast_type_to_ir(&ty.value)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn property_key(name: &PropertyName) -> Option<String> {
+        match name {
+            PropertyName::Ident(ident) => Some(ident.value.name.clone()),
+            PropertyName::String(s) => Some(s.clone()),
+            PropertyName::Number(n) => Some(format!("{}", n)),
+            PropertyName::Computed(_) => None,
+        }
+    }
+
+    /// Shape of the plain object an expression evaluates to, if known.
+    fn expr_object_shape(&self, expr: &Expr) -> Option<Vec<(String, IrType)>> {
+        match expr {
+            Expr::Ident(ident) => self.object_shapes.get(&ident.name).cloned(),
+            Expr::Paren(inner) => self.expr_object_shape(&inner.value),
+            Expr::Index { object, .. } => self.expr_element_shape(&object.value),
+            Expr::Call { args, .. } if self.is_structured_clone_call(expr) => self.expr_object_shape(&args[0].value),
+            // `await gen.next()` on an async generator settles to its result object
+            Expr::Await(inner) => self.expr_object_shape(&inner.value),
+            Expr::Call { .. } if self.object_static_call(expr).is_some() => {
+                let (method, args) = self.object_static_call(expr)?;
+                self.object_static_shape(method, args)
+            }
+            Expr::Call { callee, .. } => match &callee.value {
+                Expr::Member { object, property, .. } if property.value.name == "next" => match &object.value {
+                    Expr::Ident(gen) => self.generator_vars.get(&gen.name).map(|types| {
+                        vec![("value".to_string(), types.yield_ty.clone()), ("done".to_string(), IrType::Bool)]
+                    }),
+                    _ => None,
+                },
+                _ => self.annotation_shape(&self.resolve_value_type(&self.expr_value_type(expr)?)),
+            },
+            Expr::Member { .. } | Expr::OptionalMember { .. } | Expr::NonNullAssertion(_) | Expr::TypeCast { .. } => {
+                self.annotation_shape(&self.resolve_value_type(&self.expr_value_type(expr)?))
             }
+            Expr::Satisfies { expr: inner, .. } => self.expr_object_shape(&inner.value),
+            Expr::Object(props) => Some(self.object_literal_shape(props)),
+            Expr::This if self.current_class.is_none() => self.this_object_shape.clone(),
+            _ => None,
         }
+    }
 
-        // Handle obj.field = value — check setter first
-        if let Expr::Ident(obj_ident) = &object.value {
-            if let Some(info) = self.lookup_var(&obj_ident.name).cloned() {
-                if let IrType::Struct(struct_id) = &info.ir_type {
-                    if let Some((class_name, _)) = self.class_info.iter()
-                        .find(|(_, ci)| ci.struct_id == *struct_id)
-                        .map(|(k, v)| (k.clone(), v.clone()))
-                    {
-                        let has_setter = self.class_info.get(&class_name)
-                            .map(|ci| ci.setters.contains(&field_name.to_string()))
-                            .unwrap_or(false);
-                        if has_setter {
-                            let setter_func = format!("{}_set_{}", class_name, field_name);
-                            ctx.emit(Instruction::Call {
-                                dest: None,
-                                func: Value::Const(Constant::Str(setter_func)),
-                                args: vec![Value::Local(info.local_id), rhs.clone()],
-                            });
-                            return Some(rhs);
+    /// Fields of an object literal: its data properties, then its accessor
+    /// properties. An accessor has its getter's type, or its setter's
+    /// parameter type when it has no getter.
+    fn object_literal_shape(&self, props: &[ObjectProperty]) -> Vec<(String, IrType)> {
+        let mut shape: Vec<(String, IrType)> = props
+            .iter()
+            .filter_map(|prop| match prop {
+                ObjectProperty::Property { key, value, .. } => {
+                    Some((Self::property_key(key)?, self.infer_expr_type(&value.value)))
+                }
+                _ => None,
+            })
+            .collect();
+        let data_fields = shape.len();
+        for prop in props {
+            let (key, ty, is_getter) = match prop {
+                ObjectProperty::Getter { key, return_type, body } => {
+                    (key, self.getter_return_type(&shape[..data_fields], return_type.as_deref(), body), true)
+                }
+                ObjectProperty::Setter { key, param, .. } => (key, self.infer_param_type(param), false),
+                _ => continue,
+            };
+            let Some(key) = Self::property_key(key) else { continue };
+            match shape[data_fields..].iter_mut().find(|(name, _)| *name == key) {
+                Some(field) if is_getter => field.1 = ty,
+                Some(_) => {}
+                None => shape.push((key, ty)),
+            }
+        }
+        shape
+    }
+
+    /// Type an object literal's getter returns: its annotation, or else
+    /// that of its first `return`, reading `this.<field>` from the
+    /// literal's data fields.
+    fn getter_return_type(&self, fields: &[(String, IrType)], return_type: Option<&Node<Type>>, body: &Node<BlockStmt>) -> IrType {
+        if let Some(return_type) = return_type {
+            return self.ast_type_to_ir(&return_type.value);
+        }
+        let returned = body.value.stmts.iter().find_map(|stmt| match &stmt.value {
+            Stmt::Return(Some(expr)) => Some(&expr.value),
+            _ => None,
+        });
+        let this_field = |expr: &Expr| match expr {
+            Expr::Member { object, property, computed: false } if matches!(object.value, Expr::This) => {
+                fields.iter().find(|(name, _)| *name == property.value.name).map(|(_, ty)| ty.clone())
+            }
+            _ => None,
+        };
+        match returned {
+            None => IrType::Void,
+            Some(expr) => this_field(expr).unwrap_or_else(|| match expr {
+                Expr::Member { object, property, computed: false }
+                    if property.value.name == "length"
+                        && matches!(this_field(&object.value), Some(IrType::Str | IrType::Array(_))) =>
+                {
+                    IrType::F64
+                }
+                _ => self.infer_expr_type(expr),
+            }),
+        }
+    }
+
+    /// Shape of the object an `Object.freeze/assign/fromEntries(...)` call
+    /// returns. `assign` keeps the target's keys first, as the runtime does;
+    /// `fromEntries` knows its keys from `Object.entries(o)` or literal pairs.
+    fn object_static_shape(&self, method: &str, args: &[Node<Expr>]) -> Option<Vec<(String, IrType)>> {
+        let first = &args.first()?.value;
+        match method {
+            "freeze" => self.expr_object_shape(first),
+            "assign" => {
+                let mut merged: Vec<(String, IrType)> = Vec::new();
+                for arg in args {
+                    for (key, ty) in self.expr_object_shape(&arg.value)? {
+                        match merged.iter_mut().find(|(name, _)| *name == key) {
+                            Some(field) => field.1 = ty,
+                            None => merged.push((key, ty)),
                         }
-                        self.store_struct_field(ctx, Value::Local(info.local_id), &class_name, field_name, rhs.clone());
-                        return Some(rhs);
                     }
                 }
+                Some(merged)
             }
+            "fromEntries" => match first {
+                Expr::Call { .. } => match self.object_static_call(first)? {
+                    ("entries", entries_args) => self.expr_object_shape(&entries_args.first()?.value),
+                    _ => None,
+                },
+                Expr::Array(pairs) => pairs
+                    .iter()
+                    .map(|pair| match &pair.as_ref()?.value {
+                        Expr::Array(elems) => match elems.as_slice() {
+                            [Some(key), Some(value)] => match &key.value {
+                                Expr::Literal(Literal::String(key)) => {
+                                    Some((key.clone(), self.infer_expr_type(&value.value)))
+                                }
+                                _ => None,
+                            },
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .collect(),
+                _ => None,
+            },
+            _ => None,
         }
+    }
 
-        None
+    /// Shape of the elements of an array of plain objects, if known.
+    fn expr_element_shape(&self, expr: &Expr) -> Option<Vec<(String, IrType)>> {
+        match expr {
+            Expr::Ident(ident) => self.element_shapes.get(&ident.name).cloned(),
+            Expr::Paren(inner) => self.expr_element_shape(&inner.value),
+            Expr::Array(elems) => match &elems.iter().flatten().next()?.value {
+                Expr::Spread(inner) => self.expr_element_shape(&inner.value),
+                first => self.expr_object_shape(first),
+            },
+            Expr::Call { args, .. } if self.is_structured_clone_call(expr) => self.expr_element_shape(&args[0].value),
+            Expr::Member { .. } | Expr::NonNullAssertion(_) => match self.resolve_value_type(&self.expr_value_type(expr)?) {
+                Type::Array(elem) => self.annotation_shape(&self.resolve_value_type(&elem.value)),
+                _ => None,
+            },
+            _ => None,
+        }
     }
 
-    /// Lower a method call on a class instance
-    fn lower_method_call(
+    /// Type of `object.field` when `object` is a plain object of known shape.
+    fn object_field_type(&self, object: &Expr, field: &str) -> Option<IrType> {
+        let shape = self.expr_object_shape(object)?;
+        shape.into_iter().find(|(name, _)| name == field).map(|(_, ty)| ty)
+    }
+
+    /// Lower `db.exec(sql)`, `db.query(sql, params)` and `db.close()` on a
+    /// Database from the "sqlite" module. The runtime throws SQL errors.
+    fn lower_sqlite_method(
         &mut self,
         ctx: &mut FuncCtx,
-        class_name: &str,
-        method_name: &str,
-        obj_info: &VarInfo,
+        db: &Node<Expr>,
+        method: &str,
         args: &[Node<Expr>],
-        _span: &Span,
+        span: &Span,
     ) -> Option<Value> {
-        let func_name = format!("{}_{}", class_name, method_name);
-
-        // First arg is self (the object pointer)
-        let mut arg_vals = vec![Value::Local(obj_info.local_id)];
-        for arg in args {
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                arg_vals.push(val);
-            } else {
-                return None;
+        let db_val = self.lower_expr(ctx, &db.value, &db.span)?;
+        let mut sql = || match args.first() {
+            Some(arg) => self.lower_expr(ctx, &arg.value, &arg.span),
+            None => Some(Value::Const(Constant::Null)),
+        };
+        match method {
+            "exec" => {
+                let sql_val = sql()?;
+                self.ensure_extern("zaco_sqlite_exec", vec![IrType::Ptr, IrType::Str], IrType::Void);
+                ctx.emit(Instruction::Call {
+                    dest: None,
+                    func: Value::Const(Constant::Str("zaco_sqlite_exec".to_string())),
+                    args: vec![db_val, sql_val],
+                });
+                None
+            }
+            "query" => {
+                let sql_val = sql()?;
+                let (params, kinds) = self.lower_sqlite_params(ctx, args.get(1))?;
+                self.module.intern_string(kinds.clone());
+                self.ensure_extern(
+                    "zaco_sqlite_query",
+                    vec![IrType::Ptr, IrType::Str, IrType::Ptr, IrType::Str],
+                    IrType::Array(Box::new(IrType::Ptr)),
+                );
+                let rows = ctx.add_temp(IrType::Array(Box::new(IrType::Ptr)));
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(rows)),
+                    func: Value::Const(Constant::Str("zaco_sqlite_query".to_string())),
+                    args: vec![db_val, sql_val, params, Value::Const(Constant::Str(kinds))],
+                });
+                Some(Value::Temp(rows))
+            }
+            "close" => {
+                self.ensure_extern("zaco_sqlite_close", vec![IrType::Ptr], IrType::Void);
+                ctx.emit(Instruction::Call {
+                    dest: None,
+                    func: Value::Const(Constant::Str("zaco_sqlite_close".to_string())),
+                    args: vec![db_val],
+                });
+                None
+            }
+            _ => {
+                self.errors.push(LowerError::new(
+                    format!("Database has no method '{}'", method),
+                    *span,
+                ));
+                None
             }
         }
+    }
 
-        // Look up return type
-        let return_type = self.module.find_function(&func_name)
-            .map(|f| f.return_type.clone())
-            .unwrap_or(IrType::Void);
+    /// Whether `expr` is a call to the global `fetch`.
+    fn is_fetch_call(&self, expr: &Expr) -> bool {
+        matches!(expr, Expr::Call { callee, .. }
+            if matches!(&callee.value, Expr::Ident(ident) if ident.name == "fetch" && self.lookup_var("fetch").is_none()))
+    }
 
-        if return_type == IrType::Void {
-            ctx.emit(Instruction::Call {
-                dest: None,
-                func: Value::Const(Constant::Str(func_name)),
-                args: arg_vals,
-            });
-            None
-        } else {
-            let result = ctx.add_temp(return_type);
-            ctx.emit(Instruction::Call {
-                dest: Some(Place::from_temp(result)),
-                func: Value::Const(Constant::Str(func_name)),
-                args: arg_vals,
-            });
-            Some(Value::Temp(result))
-        }
+    /// Whether `expr` is `new AbortController()`.
+    fn is_abort_controller_new(&self, expr: &Expr) -> bool {
+        matches!(expr, Expr::New { callee, .. }
+            if matches!(&callee.value, Expr::Ident(ident) if ident.name == "AbortController"))
+            && !self.class_info.contains_key("AbortController")
     }
 
-    /// Extract string from PropertyName
-    fn property_name_to_string(&self, name: &PropertyName) -> String {
-        match name {
-            PropertyName::Ident(ident) => ident.value.name.clone(),
-            PropertyName::String(s) => s.clone(),
-            PropertyName::Number(n) => format!("{}", n),
-            PropertyName::Computed(_) => "_computed".to_string(),
+    /// Whether `expr` is the callee `AbortSignal.timeout`.
+    fn is_abort_signal_timeout(expr: &Expr) -> bool {
+        matches!(expr, Expr::Member { object, property, .. }
+            if property.value.name == "timeout"
+                && matches!(&object.value, Expr::Ident(ident) if ident.name == "AbortSignal"))
+    }
+
+    /// Whether `expr` is an AbortController variable, its `.signal`, or a
+    /// call to `AbortSignal.timeout`.
+    fn is_abort_signal(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Ident(ident) => self.abort_controllers.contains(&ident.name),
+            Expr::Call { callee, .. } => Self::is_abort_signal_timeout(&callee.value),
+            Expr::Member { object, property, .. } => {
+                property.value.name == "signal" && self.is_abort_signal(&object.value)
+            }
+            _ => false,
         }
     }
 
-    /// Lower super(args) call — invokes parent constructor, copies parent fields to self
-    fn lower_super_call(
+    /// Whether `expr` names a variable holding a fetch Response.
+    fn is_fetch_response(&self, expr: &Expr) -> bool {
+        matches!(expr, Expr::Ident(ident) if self.fetch_responses.contains(&ident.name))
+    }
+
+    /// Lower `res.text()`, `res.json()` and `res.headers.get(name)` on a
+    /// fetch Response. The body is already read, so text() and json()
+    /// return promises that have settled.
+    fn lower_response_method(
         &mut self,
         ctx: &mut FuncCtx,
+        response: &Node<Expr>,
+        method: &str,
         args: &[Node<Expr>],
-        _span: &Span,
+        span: &Span,
     ) -> Option<Value> {
-        let parent_name = self.current_class_parent.clone()?;
-        let this_info = self.this_var.clone()?;
-        let class_name = self.current_class.clone()?;
-
-        // Lower arguments
-        let mut arg_vals = Vec::new();
-        for arg in args {
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                arg_vals.push(val);
-            } else {
+        let response_val = self.lower_expr(ctx, &response.value, &response.span)?;
+        let (runtime_fn, mut params, ret_type) = match method {
+            "text" | "json" => (format!("zaco_response_{}", method), vec![IrType::Ptr], IrType::Ptr),
+            "headers.get" => ("zaco_response_header".to_string(), vec![IrType::Ptr, IrType::Str], IrType::Str),
+            _ => {
+                self.errors.push(LowerError::new(
+                    format!("Response has no method '{}'", method),
+                    *span,
+                ));
                 return None;
             }
+        };
+        let mut arg_vals = vec![response_val];
+        for arg in args.iter().take(params.len() - 1) {
+            arg_vals.push(self.lower_expr(ctx, &arg.value, &arg.span)?);
         }
-
-        // Call parent constructor: ParentClass_constructor(args) -> parent_ptr
-        let parent_info = self.class_info.get(&parent_name)?.clone();
-        let parent_ctor = format!("{}_constructor", parent_name);
-        let parent_result = ctx.add_temp(IrType::Struct(parent_info.struct_id));
+        params.truncate(arg_vals.len());
+        self.ensure_extern(&runtime_fn, params, ret_type.clone());
+        let result = ctx.add_temp(ret_type);
         ctx.emit(Instruction::Call {
-            dest: Some(Place::from_temp(parent_result)),
-            func: Value::Const(Constant::Str(parent_ctor)),
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(runtime_fn)),
             args: arg_vals,
         });
+        Some(Value::Temp(result))
+    }
 
-        // Copy parent fields from parent_result to self (first N fields in the child struct)
-        let child_info = self.class_info.get(&class_name)?.clone();
-        for (i, (field_name, _)) in parent_info.fields.iter().enumerate() {
-            // Load field from parent instance
-            if let Some(val) = self.load_struct_field(ctx, Value::Temp(parent_result), &parent_name, field_name) {
-                // Store into child's corresponding field (same position due to layout)
-                let _ = self.store_struct_field(ctx, Value::Local(this_info.local_id), &class_name, field_name, val);
-                let _ = i;
-                let _ = &child_info;
+    /// Lower the positional parameters of `db.query` to an inline array plus
+    /// a kind letter per element (`n` number, `s` string). For an array that
+    /// is not a literal, one letter describes every element.
+    fn lower_sqlite_params(&mut self, ctx: &mut FuncCtx, params: Option<&Node<Expr>>) -> Option<(Value, String)> {
+        let Some(params) = params else {
+            return Some((Value::Const(Constant::Null), String::new()));
+        };
+        let kind = |ty: &IrType| match ty {
+            IrType::F64 | IrType::I64 => Some('n'),
+            IrType::Str => Some('s'),
+            _ => None,
+        };
+        let unsupported = |node: &Node<Expr>| {
+            LowerError::new("query parameters must be numbers or strings", node.span)
+        };
+        if let Expr::Array(elems) = &params.value {
+            let mut slots = Vec::new();
+            let mut kinds = String::new();
+            for elem in elems.iter().flatten() {
+                let Some(k) = kind(&self.infer_expr_type(&elem.value)) else {
+                    self.errors.push(unsupported(elem));
+                    return None;
+                };
+                kinds.push(k);
+                slots.push(self.lower_array_slot(ctx, elem)?);
             }
+            let temp = ctx.add_temp(IrType::Array(Box::new(IrType::F64)));
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(temp),
+                value: RValue::ArrayInit(slots),
+            });
+            return Some((Value::Temp(temp), kinds));
         }
-
-        None // super() doesn't produce a value
+        let kinds = match self.infer_expr_type(&params.value) {
+            IrType::Array(elem) => kind(&elem),
+            _ => None,
+        };
+        let Some(k) = kinds else {
+            self.errors.push(unsupported(params));
+            return None;
+        };
+        let val = self.lower_expr(ctx, &params.value, &params.span)?;
+        Some((val, k.to_string()))
     }
 
-    // =========================================================================
-    // Closure support
-    // =========================================================================
-
-    /// Lower an arrow function expression.
-    /// Creates a heap-allocated environment struct for captured variables.
-    /// Closure = (function_name, env_ptr) pair.
-    fn lower_arrow_expr(
-        &mut self,
+    /// Load a field from a struct pointer by computing offset
+    fn load_struct_field(
+        &self,
         ctx: &mut FuncCtx,
-        params: &[Param],
-        return_type: Option<&Node<Type>>,
-        body: &ArrowBody,
-        _span: &Span,
+        obj_ptr: Value,
+        class_name: &str,
+        field_name: &str,
     ) -> Option<Value> {
-        let closure_id = self.next_closure_id;
-        self.next_closure_id += 1;
-        let func_name = format!("__closure_{}", closure_id);
+        let class_info = self.class_info.get(class_name)?;
+        let field_idx = class_info.field_index(field_name)?;
+        let field_type = class_info.fields[field_idx].1.clone();
 
-        // Collect the body statements
-        let body_stmts: Vec<Node<Stmt>> = match body {
-            ArrowBody::Expr(expr) => {
-                vec![Node::new(
-                    Stmt::Return(Some((**expr).clone())),
-                    expr.span,
-                )]
-            }
-            ArrowBody::Block(block) => {
-                block.value.stmts.clone()
-            }
-        };
+        // Compute byte offset
+        let mut offset: i64 = 0;
+        for i in 0..field_idx {
+            offset += class_info.fields[i].1.size_bytes() as i64;
+        }
 
-        // Collect free variables
-        let param_names: HashSet<String> = params.iter().filter_map(|p| {
-            match &p.pattern.value {
-                Pattern::Ident { name, .. } => Some(name.value.name.clone()),
-                _ => None,
-            }
-        }).collect();
-        let captured_vars = self.collect_captured_vars(&body_stmts, &param_names);
+        if offset == 0 {
+            // Field is at the start of struct, load directly from ptr
+            let result = ctx.add_temp(field_type);
+            ctx.emit(Instruction::Load {
+                dest: Place::from_temp(result),
+                ptr: obj_ptr,
+            });
+            Some(Value::Temp(result))
+        } else {
+            // Compute ptr + offset, then load
+            // We need to add offset to the pointer
+            let offset_temp = ctx.add_temp(IrType::I64);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(offset_temp),
+                value: RValue::Use(Value::Const(Constant::I64(offset))),
+            });
+            let addr_temp = ctx.add_temp(IrType::Ptr);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(addr_temp),
+                value: RValue::BinaryOp {
+                    op: BinOp::Add,
+                    left: obj_ptr,
+                    right: Value::Temp(offset_temp),
+                },
+            });
+            let result = ctx.add_temp(field_type);
+            ctx.emit(Instruction::Load {
+                dest: Place::from_temp(result),
+                ptr: Value::Temp(addr_temp),
+            });
+            Some(Value::Temp(result))
+        }
+    }
+
+    /// Store a value to a struct field by computing offset
+    fn store_struct_field(
+        &self,
+        ctx: &mut FuncCtx,
+        obj_ptr: Value,
+        class_name: &str,
+        field_name: &str,
+        value: Value,
+    ) -> bool {
+        let class_info = match self.class_info.get(class_name) {
+            Some(ci) => ci,
+            None => return false,
+        };
+        let field_idx = match class_info.field_index(field_name) {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        // Compute byte offset
+        let mut offset: i64 = 0;
+        for i in 0..field_idx {
+            offset += class_info.fields[i].1.size_bytes() as i64;
+        }
+
+        if offset == 0 {
+            ctx.emit(Instruction::Store {
+                ptr: obj_ptr,
+                value,
+            });
+        } else {
+            let offset_temp = ctx.add_temp(IrType::I64);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(offset_temp),
+                value: RValue::Use(Value::Const(Constant::I64(offset))),
+            });
+            let addr_temp = ctx.add_temp(IrType::Ptr);
+            ctx.emit(Instruction::Assign {
+                dest: Place::from_temp(addr_temp),
+                value: RValue::BinaryOp {
+                    op: BinOp::Add,
+                    left: obj_ptr,
+                    right: Value::Temp(offset_temp),
+                },
+            });
+            ctx.emit(Instruction::Store {
+                ptr: Value::Temp(addr_temp),
+                value,
+            });
+        }
+        true
+    }
+
+    /// Lower member assignment: this.field = value or obj.field = value
+    fn lower_member_assignment(
+        &mut self,
+        ctx: &mut FuncCtx,
+        object: &Node<Expr>,
+        property: &Node<Ident>,
+        op: AssignmentOp,
+        rhs: Value,
+    ) -> Option<Value> {
+        let field_name = &property.value.name;
+
+        // Handle ClassName.staticProp = value — static property write
+        if let Expr::Ident(obj_ident) = &object.value {
+            if let Some(ci) = self.class_info.get(&obj_ident.name).cloned() {
+                if ci.static_properties.iter().any(|(n, _)| n == field_name) {
+                    let global_name = format!("{}_{}", obj_ident.name, field_name);
+                    ctx.emit(Instruction::Store {
+                        ptr: Value::Const(Constant::Str(global_name)),
+                        value: rhs.clone(),
+                    });
+                    return Some(rhs);
+                }
+            }
+        }
+
+        // Handle this.field = value — check setter first
+        if matches!(&object.value, Expr::This) {
+            if let (Some(this_info), Some(class_name)) = (self.this_var.clone(), self.current_class.clone()) {
+                let has_setter = self.class_info.get(&class_name)
+                    .map(|ci| ci.setters.contains(&field_name.to_string()))
+                    .unwrap_or(false);
+                if has_setter {
+                    let setter_func = format!("{}_set_{}", class_name, field_name);
+                    ctx.emit(Instruction::Call {
+                        dest: None,
+                        func: Value::Const(Constant::Str(setter_func)),
+                        args: vec![Value::Local(this_info.local_id), rhs.clone()],
+                    });
+                    return Some(rhs);
+                }
+                self.store_struct_field(ctx, Value::Local(this_info.local_id), &class_name, field_name, rhs.clone());
+                return Some(rhs);
+            }
+        }
+
+        // Handle obj.field = value — check setter first
+        if let Expr::Ident(obj_ident) = &object.value {
+            if let Some(info) = self.lookup_var(&obj_ident.name).cloned() {
+                if let IrType::Struct(struct_id) = &info.ir_type {
+                    if let Some((class_name, _)) = self.class_info.iter()
+                        .find(|(_, ci)| ci.struct_id == *struct_id)
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                    {
+                        let has_setter = self.class_info.get(&class_name)
+                            .map(|ci| ci.setters.contains(&field_name.to_string()))
+                            .unwrap_or(false);
+                        let receiver = self.lower_expr(ctx, &object.value, &object.span)?;
+                        if has_setter {
+                            let setter_func = format!("{}_set_{}", class_name, field_name);
+                            ctx.emit(Instruction::Call {
+                                dest: None,
+                                func: Value::Const(Constant::Str(setter_func)),
+                                args: vec![receiver, rhs.clone()],
+                            });
+                            return Some(rhs);
+                        }
+                        self.store_struct_field(ctx, receiver, &class_name, field_name, rhs.clone());
+                        return Some(rhs);
+                    }
+                }
+            }
+        }
+
+        // obj.field = value on a plain object of known shape
+        if op == AssignmentOp::Assign {
+            if let Some(field_ty) = self.object_field_type(&object.value, field_name) {
+                let obj_val = self.lower_expr(ctx, &object.value, &object.span)?;
+                self.module.intern_string(field_name.clone());
+                let key = Value::Const(Constant::Str(field_name.clone()));
+                self.emit_object_set(ctx, obj_val, key, rhs.clone(), &field_ty);
+                return Some(rhs);
+            }
+        }
+
+        None
+    }
+
+    /// Lower a method call on a class instance
+    fn lower_method_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        class_name: &str,
+        method_name: &str,
+        receiver: Value,
+        args: &[Node<Expr>],
+        _span: &Span,
+    ) -> Option<Value> {
+        let func_name = format!("{}_{}", class_name, method_name);
+
+        self.emit_receiver_guard(ctx, receiver.clone(), method_name);
+
+        // First arg is self (the object pointer)
+        let mut arg_vals = vec![receiver];
+        for arg in args {
+            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
+                arg_vals.push(val);
+            } else {
+                return None;
+            }
+        }
+
+        // Look up return type
+        let return_type = self.module.find_function(&func_name)
+            .map(|f| f.return_type.clone())
+            .unwrap_or(IrType::Void);
+
+        if return_type == IrType::Void {
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str(func_name)),
+                args: arg_vals,
+            });
+            None
+        } else {
+            let result = ctx.add_temp(return_type);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str(func_name)),
+                args: arg_vals,
+            });
+            Some(Value::Temp(result))
+        }
+    }
+
+    /// Name of the class whose instances have layout `struct_id`.
+    fn class_name_for_struct(&self, struct_id: StructId) -> Option<String> {
+        self.class_info
+            .iter()
+            .find(|(_, ci)| ci.struct_id == struct_id)
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Runtime signature of a string method: (function, params, return type,
+    /// type of the JS result). Integer returns are converted to the JS type.
+    fn string_method_signature(method: &str) -> Option<(&'static str, Vec<IrType>, IrType, IrType)> {
+        let sig = match method {
+            "toUpperCase" => ("zaco_str_to_upper", vec![IrType::Str], IrType::Str, IrType::Str),
+            "toLowerCase" => ("zaco_str_to_lower", vec![IrType::Str], IrType::Str, IrType::Str),
+            "trim" => ("zaco_str_trim", vec![IrType::Str], IrType::Str, IrType::Str),
+            "slice" => ("zaco_str_slice", vec![IrType::Str, IrType::I64, IrType::I64], IrType::Str, IrType::Str),
+            "indexOf" => ("zaco_str_index_of", vec![IrType::Str, IrType::Str], IrType::I64, IrType::F64),
+            "includes" => ("zaco_str_includes", vec![IrType::Str, IrType::Str], IrType::I64, IrType::Bool),
+            "startsWith" => ("zaco_str_starts_with", vec![IrType::Str, IrType::Str], IrType::I64, IrType::Bool),
+            "endsWith" => ("zaco_str_ends_with", vec![IrType::Str, IrType::Str], IrType::I64, IrType::Bool),
+            "replace" => ("zaco_str_replace", vec![IrType::Str, IrType::Str, IrType::Str], IrType::Str, IrType::Str),
+            "split" => (
+                "zaco_str_split",
+                vec![IrType::Str, IrType::Str],
+                IrType::Array(Box::new(IrType::Str)),
+                IrType::Array(Box::new(IrType::Str)),
+            ),
+            "charAt" => ("zaco_str_char_at", vec![IrType::Str, IrType::I64], IrType::Str, IrType::Str),
+            "repeat" => ("zaco_str_repeat", vec![IrType::Str, IrType::I64], IrType::Str, IrType::Str),
+            "padStart" => ("zaco_str_pad_start", vec![IrType::Str, IrType::I64, IrType::Str], IrType::Str, IrType::Str),
+            "padEnd" => ("zaco_str_pad_end", vec![IrType::Str, IrType::I64, IrType::Str], IrType::Str, IrType::Str),
+            "matchAll" => {
+                let matches = IrType::Array(Box::new(IrType::Array(Box::new(IrType::Str))));
+                ("zaco_str_match_all", vec![IrType::Str, IrType::Ptr], matches.clone(), matches)
+            }
+            _ => return None,
+        };
+        Some(sig)
+    }
+
+    /// Lower `receiver.method(args)` for a string receiver.
+    fn lower_string_method(
+        &mut self,
+        ctx: &mut FuncCtx,
+        receiver: Value,
+        method: &str,
+        args: &[Node<Expr>],
+    ) -> Option<Value> {
+        let (runtime_fn, params, ret, result_ty) = Self::string_method_signature(method)?;
+        self.ensure_extern(runtime_fn, params.clone(), ret.clone());
+
+        let mut arg_vals = vec![receiver];
+        for (i, param_ty) in params.iter().enumerate().skip(1) {
+            let val = match args.get(i - 1) {
+                Some(arg) => {
+                    let val = self.lower_expr(ctx, &arg.value, &arg.span)?;
+                    if *param_ty == IrType::I64 && self.infer_expr_type(&arg.value) == IrType::F64 {
+                        let int_val = ctx.add_temp(IrType::I64);
+                        ctx.emit(Instruction::Assign {
+                            dest: Place::from_temp(int_val),
+                            value: RValue::Cast { value: val, ty: IrType::I64 },
+                        });
+                        Value::Temp(int_val)
+                    } else {
+                        val
+                    }
+                }
+                // Omitted optional arguments: slice to the end, pad with spaces
+                None if *param_ty == IrType::I64 => Value::Const(Constant::I64(i64::MAX)),
+                None => Value::Const(Constant::Null),
+            };
+            arg_vals.push(val);
+        }
+
+        let result = ctx.add_temp(ret.clone());
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(runtime_fn.to_string())),
+            args: arg_vals,
+        });
+        if ret == result_ty {
+            return Some(Value::Temp(result));
+        }
+        let converted = ctx.add_temp(result_ty.clone());
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(converted),
+            value: RValue::Cast { value: Value::Temp(result), ty: result_ty },
+        });
+        Some(Value::Temp(converted))
+    }
+
+    /// Extract string from PropertyName
+    fn property_name_to_string(&self, name: &PropertyName) -> String {
+        match name {
+            PropertyName::Ident(ident) => ident.value.name.clone(),
+            PropertyName::String(s) => s.clone(),
+            PropertyName::Number(n) => format!("{}", n),
+            PropertyName::Computed(_) => name.symbol_key().unwrap_or_else(|| "_computed".to_string()),
+        }
+    }
+
+    /// Lower super(args) call — invokes parent constructor, copies parent fields to self
+    fn lower_super_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        args: &[Node<Expr>],
+        _span: &Span,
+    ) -> Option<Value> {
+        let parent_name = self.current_class_parent.clone()?;
+        let this_info = self.this_var.clone()?;
+        let class_name = self.current_class.clone()?;
+
+        // Lower arguments
+        let mut arg_vals = Vec::new();
+        for arg in args {
+            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
+                arg_vals.push(val);
+            } else {
+                return None;
+            }
+        }
+
+        // Call parent constructor: ParentClass_constructor(args) -> parent_ptr
+        let parent_info = self.class_info.get(&parent_name)?.clone();
+        let parent_ctor = format!("{}_constructor", parent_name);
+        let parent_result = ctx.add_temp(IrType::Struct(parent_info.struct_id));
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(parent_result)),
+            func: Value::Const(Constant::Str(parent_ctor)),
+            args: arg_vals,
+        });
+
+        // Copy parent fields from parent_result to self (first N fields in the child struct)
+        let child_info = self.class_info.get(&class_name)?.clone();
+        for (i, (field_name, _)) in parent_info.fields.iter().enumerate() {
+            // Load field from parent instance
+            if let Some(val) = self.load_struct_field(ctx, Value::Temp(parent_result), &parent_name, field_name) {
+                // Store into child's corresponding field (same position due to layout)
+                let _ = self.store_struct_field(ctx, Value::Local(this_info.local_id), &class_name, field_name, val);
+                let _ = i;
+                let _ = &child_info;
+            }
+        }
+
+        None // super() doesn't produce a value
+    }
+
+    // =========================================================================
+    // Closure support
+    // =========================================================================
+
+    /// Lower an arrow function expression.
+    /// Creates a heap-allocated environment struct for captured variables.
+    /// Closure = (function_name, env_ptr) pair.
+    fn lower_arrow_expr(
+        &mut self,
+        ctx: &mut FuncCtx,
+        params: &[Param],
+        return_type: Option<&Node<Type>>,
+        body: &ArrowBody,
+        is_async: bool,
+        span: &Span,
+    ) -> Option<Value> {
+        let closure_id = self.next_closure_id;
+        self.next_closure_id += 1;
+        let func_name = format!("__closure_{}", closure_id);
+        let binding_name = self.closure_binding_name.take();
+        let contextual_params = self.closure_param_types.take().unwrap_or_default();
+
+        // Collect the body statements
+        let body_stmts: Vec<Node<Stmt>> = match body {
+            ArrowBody::Expr(expr) => {
+                vec![Node::new(
+                    Stmt::Return(Some((**expr).clone())),
+                    expr.span,
+                )]
+            }
+            ArrowBody::Block(block) => {
+                block.value.stmts.clone()
+            }
+        };
+
+        // Collect free variables. The binding the closure is assigned to
+        // names the closure itself rather than a variable to capture.
+        let param_names: HashSet<String> = params.iter().filter_map(|p| {
+            match &p.pattern.value {
+                Pattern::Ident { name, .. } => Some(name.value.name.clone()),
+                _ => None,
+            }
+        }).chain(binding_name.clone()).collect();
+        let captured_vars = self.collect_captured_vars(&body_stmts, &param_names);
+
+        // Detect which captured variables are mutated inside the closure body
+        let mutated_captured = self.collect_mutated_captured_vars(&body_stmts, &param_names);
+
+        // Box any captured variables that are mutated (capture-by-reference)
+        for cap_name in &captured_vars {
+            if mutated_captured.contains(cap_name) {
+                if let Some(info) = self.lookup_var(cap_name).cloned() {
+                    if !info.is_boxed {
+                        // Allocate a box and store the current value
+                        self.ensure_extern("zaco_box_new", vec![IrType::Ptr], IrType::Ptr);
+                        let box_local = ctx.add_local(IrType::Ptr);
+                        ctx.emit(Instruction::Call {
+                            dest: Some(Place::from_local(box_local)),
+                            func: Value::Const(Constant::Str("zaco_box_new".to_string())),
+                            args: vec![Value::Local(info.local_id)],
+                        });
+                        // Redefine the variable to use the box pointer
+                        self.define_var(cap_name, VarInfo {
+                            local_id: box_local,
+                            ir_type: info.ir_type,
+                            is_boxed: true,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Captures that live in a box in the enclosing scope — including ones
+        // boxed by an earlier closure — share that box, keyed to their logical type
+        let boxed_captures: HashMap<String, IrType> = captured_vars.iter()
+            .filter_map(|name| {
+                self.lookup_var(name)
+                    .filter(|info| info.is_boxed)
+                    .map(|info| (name.clone(), info.ir_type.clone()))
+            })
+            .collect();
+
+        // Create environment struct if there are captured variables
+        let env_struct_id = if !captured_vars.is_empty() {
+            let env_id = self.alloc_struct_id();
+            let env_fields: Vec<(String, IrType)> = captured_vars.iter().map(|name| {
+                let info = self.lookup_var(name);
+                let ty = info.map(|i| {
+                    if i.is_boxed { IrType::Ptr } else { i.ir_type.clone() }
+                }).unwrap_or(IrType::F64);
+                (name.clone(), ty)
+            }).collect();
+            let env_struct = IrStruct::new(env_id, format!("__env_{}", closure_id), env_fields.clone());
+            self.module.add_struct(env_struct);
+
+            // Register env as a "class" so store/load_struct_field works
+            let env_name = format!("__env_{}", closure_id);
+            self.class_info.insert(env_name, ClassInfo {
+                struct_id: env_id,
+                fields: env_fields,
+                methods: Vec::new(),
+                parent: None,
+                parent_field_count: 0,
+                getters: Vec::new(),
+                setters: Vec::new(),
+                static_methods: Vec::new(),
+                static_properties: Vec::new(),
+                method_returns: Vec::new(),
+                constructor_slot: None,
+            });
+
+            Some(env_id)
+        } else {
+            None
+        };
+
+        // Allocate env struct on the heap and store captured values (in the calling context)
+        let env_local = if let Some(env_id) = env_struct_id {
+            let env_local = ctx.add_local(IrType::Struct(env_id));
+            ctx.emit(Instruction::Alloc {
+                dest: Place::from_local(env_local),
+                ty: IrType::Struct(env_id),
+            });
+
+            let env_name = format!("__env_{}", closure_id);
+            for cap_name in &captured_vars {
+                if let Some(info) = self.lookup_var(cap_name).cloned() {
+                    self.store_struct_field(
+                        ctx,
+                        Value::Local(env_local),
+                        &env_name,
+                        cap_name,
+                        Value::Local(info.local_id),
+                    );
+                }
+            }
+
+            Some(env_local)
+        } else {
+            None
+        };
+
+        // Build parameter list for closure function: env_ptr (if captures), then declared params
+        let mut ir_params: Vec<(LocalId, IrType)> = Vec::new();
+        let mut local_idx = 0;
+
+        if let Some(env_id) = env_struct_id {
+            ir_params.push((LocalId(local_idx), IrType::Struct(env_id)));
+            local_idx += 1;
+        }
+
+        for (i, param) in params.iter().enumerate() {
+            let ir_type = match contextual_params.get(i) {
+                Some(ty) if Self::param_annotation(param).is_none() => ty.clone(),
+                _ => self.infer_param_type(param),
+            };
+            ir_params.push((LocalId(local_idx), ir_type));
+            local_idx += 1;
+        }
+
+        // Infer return type; an expression body is typed as the `return`
+        // it was turned into
+        let ret_type = match (is_async, return_type) {
+            (true, Some(_)) => self.async_return_type(return_type),
+            (true, None) => IrType::Promise(Box::new(self.infer_body_return_type(None, &body_stmts))),
+            (false, _) => self.infer_body_return_type(return_type, &body_stmts),
+        };
+
+        let func_id = self.alloc_func_id();
+        let mut ir_func = IrFunction::new(func_id, func_name.clone(), ir_params.clone(), ret_type.clone());
+        let entry = ir_func.new_block();
+        ir_func.entry_block = entry;
+
+        let mut closure_ctx = FuncCtx {
+            func: &mut ir_func,
+            current_block: entry,
+        };
+
+        self.push_scope();
+
+        // Load captured vars from environment struct into local variables
+        if let Some(_env_id) = env_struct_id {
+            let env_param_local = LocalId(0);
+            let env_name = format!("__env_{}", closure_id);
+
+            for cap_name in &captured_vars {
+                if let Some(val) = self.load_struct_field(&mut closure_ctx, Value::Local(env_param_local), &env_name, cap_name) {
+                    let cap_type = self.class_info.get(&env_name)
+                        .and_then(|ci| ci.fields.iter().find(|(n, _)| n == cap_name))
+                        .map(|(_, t)| t.clone())
+                        .unwrap_or(IrType::F64);
+                    let cap_local = closure_ctx.add_local(cap_type.clone());
+                    closure_ctx.emit(Instruction::Assign {
+                        dest: Place::from_local(cap_local),
+                        value: RValue::Use(val),
+                    });
+                    // If this variable is boxed in the enclosing scope, mark it as boxed
+                    // so reads/writes inside the closure go through box_get/box_set
+                    let (logical_type, is_boxed_cap) = match boxed_captures.get(cap_name) {
+                        Some(ty) => (ty.clone(), true),
+                        None => (cap_type, false),
+                    };
+                    self.define_var(cap_name, VarInfo {
+                        local_id: cap_local,
+                        ir_type: logical_type,
+                        is_boxed: is_boxed_cap,
+                    });
+                }
+            }
+        }
+
+        // Register declared params in scope
+        let param_offset = if env_struct_id.is_some() { 1 } else { 0 };
+        for (i, param) in params.iter().enumerate() {
+            let param_name = match &param.pattern.value {
+                Pattern::Ident { name, .. } => name.value.name.clone(),
+                _ => format!("_param{}", i),
+            };
+            let idx = param_offset + i;
+            let (local_id, ir_type) = &ir_params[idx];
+            self.define_var(&param_name, VarInfo {
+                local_id: *local_id,
+                ir_type: ir_type.clone(),
+                is_boxed: false,
+            });
+            self.track_object_var(&param_name, Self::param_annotation(param), None);
+        }
+
+        // Inside its body, the closure's binding calls it with its own env
+        if let Some(binding_name) = binding_name {
+            self.closure_bindings.insert(binding_name, ClosureInfo {
+                func_name: func_name.clone(),
+                captured_vars: captured_vars.clone(),
+                env_struct_id,
+                env_local: env_struct_id.map(|_| LocalId(0)),
+            });
+        }
+
+        // Lower body
+        let prev_function = self.current_function.replace((func_name.clone(), ret_type.clone()));
+        let prev_async_promise = self.async_promise.take();
+        self.hoist_vars(&mut closure_ctx, &body_stmts);
+        if is_async {
+            self.lower_async_body(&mut closure_ctx, &body_stmts);
+        } else {
+            for s in &body_stmts {
+                self.lower_stmt(&mut closure_ctx, &s.value, &s.span);
+            }
+        }
+        self.current_function = prev_function;
+        self.async_promise = prev_async_promise;
+
+        // A body without an annotation returns whatever its `return`s produce,
+        // typed now that the params are in scope. An async one returns its
+        // promise.
+        let ret_type = if return_type.is_none() && !is_async {
+            self.unify_returned_type(closure_ctx.func, ret_type, span)
+        } else {
+            ret_type
+        };
+        closure_ctx.func.return_type = ret_type.clone();
+
+        // Add implicit return if needed
+        if matches!(
+            closure_ctx.func.block(closure_ctx.current_block).terminator,
+            Terminator::Unreachable
+        ) {
+            if ret_type == IrType::Void {
+                closure_ctx.set_terminator(Terminator::Return(None));
+            } else {
+                let default_val = match &ret_type {
+                    IrType::F64 => Value::Const(Constant::F64(0.0)),
+                    IrType::Bool => Value::Const(Constant::Bool(false)),
+                    _ => Value::Const(Constant::I64(0)),
+                };
+                let temp = closure_ctx.add_temp(ret_type);
+                closure_ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(temp),
+                    value: RValue::Use(default_val),
+                });
+                closure_ctx.set_terminator(Terminator::Return(Some(Value::Temp(temp))));
+            }
+        }
+
+        self.pop_scope();
+        self.module.add_function(ir_func);
+
+        // Store closure binding
+        self.closure_bindings.insert(func_name.clone(), ClosureInfo {
+            func_name: func_name.clone(),
+            captured_vars,
+            env_struct_id,
+            env_local,
+        });
+
+        // Return the function name as a string constant
+        self.module.intern_string(func_name.clone());
+        Some(Value::Const(Constant::Str(func_name)))
+    }
+
+    /// A returned closure value is its function name, which means nothing
+    /// outside this frame, so return a closure value instead (see
+    /// `lower_function_value`; its env is heap allocated, so it outlives
+    /// this frame) and remember which closure it is, for callers that bind
+    /// the result to a name.
+    fn lower_returned_closure(&mut self, ctx: &mut FuncCtx, val: Value) -> Value {
+        let Value::Const(Constant::Str(ref name)) = val else {
+            return val;
+        };
+        let Some(closure_info) = self.closure_bindings.get(name).cloned() else {
+            return val;
+        };
+        self.ensure_extern("zaco_closure_new", vec![IrType::Ptr, IrType::Ptr], IrType::Ptr);
+        let closure = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(closure)),
+            func: Value::Const(Constant::Str("zaco_closure_new".to_string())),
+            args: vec![
+                Value::Const(Constant::FuncAddr(closure_info.func_name.clone())),
+                closure_info.env_local.map_or(Value::Const(Constant::Null), Value::Local),
+            ],
+        });
+        if let Some((func_name, _)) = self.current_function.clone() {
+            self.returned_closures.insert(func_name, ClosureInfo { env_local: None, ..closure_info });
+        }
+        Value::Temp(closure)
+    }
+
+    /// Signature of the closure a call such as `make()` returns, when the
+    /// called function's return type is not annotated (see
+    /// `lower_returned_closure`). A closure with an env takes it first; the
+    /// signature leaves it out, as `lower_indirect_call` passes it.
+    fn returned_closure_signature(&self, expr: &Expr) -> Option<FuncSignature> {
+        let Expr::Call { callee, .. } = expr else { return None };
+        let Expr::Ident(callee_ident) = &callee.value else { return None };
+        let closure = self.returned_closures.get(&callee_ident.name).or_else(|| {
+            let callee_closure = self.closure_bindings.get(&callee_ident.name)?;
+            self.returned_closures.get(&callee_closure.func_name)
+        })?;
+        let func = self.module.find_function(&closure.func_name)?;
+        let skip = usize::from(closure.env_struct_id.is_some());
+        Some(FuncSignature {
+            params: func.params.iter().skip(skip).map(|(_, ty)| ty.clone()).collect(),
+            return_type: Box::new(func.return_type.clone()),
+        })
+    }
+
+    /// Type of the values returned by `func`, for functions without a return
+    /// annotation. `None` if every return is bare; the first two types that
+    /// disagree if they do.
+    fn infer_returned_type(func: &IrFunction) -> Result<Option<IrType>, (IrType, IrType)> {
+        let mut returned: Option<IrType> = None;
+        for block in &func.blocks {
+            let Terminator::Return(Some(val)) = &block.terminator else { continue };
+            let Some(ty) = func.value_type(val) else { continue };
+            returned = Some(match returned {
+                None => ty,
+                Some(prev) if prev == ty => prev,
+                // `return null` alongside an object, array or string
+                Some(IrType::Ptr) if ty.is_heap_allocated() => ty,
+                Some(prev) if ty == IrType::Ptr && prev.is_heap_allocated() => prev,
+                Some(prev) => return Err((prev, ty)),
+            });
+        }
+        Ok(returned)
+    }
+
+    /// The type `func` returns according to `infer_returned_type`, or
+    /// `fallback` if it returns no value. Returns of different types are
+    /// reported, and the first one's type kept.
+    fn unify_returned_type(&mut self, func: &IrFunction, fallback: IrType, span: &Span) -> IrType {
+        match Self::infer_returned_type(func) {
+            Ok(returned) => returned.unwrap_or(fallback),
+            Err((first, other)) => {
+                let construct = format!("a function without a return type annotation returning both {} and {}", first, other);
+                self.unsupported(&construct, span);
+                first
+            }
+        }
+    }
+
+    /// The return type of the function `name`: lowered already, or the one
+    /// being lowered (which a recursive call reaches before it is done).
+    fn function_return_type(&self, name: &str) -> Option<IrType> {
+        self.module.find_function(name).map(|f| f.return_type.clone()).or_else(|| {
+            let (current, ret) = self.current_function.as_ref()?;
+            (current == name).then(|| ret.clone())
+        })
+    }
+
+    /// The arrow or function expression inside a callee such as `(() => 1)`.
+    fn unwrap_function_expr(expr: &Expr) -> Option<&Expr> {
+        match expr {
+            Expr::Paren(inner) => Self::unwrap_function_expr(&inner.value),
+            Expr::Arrow { .. } | Expr::Function { .. } => Some(expr),
+            _ => None,
+        }
+    }
+
+    /// Return type of an arrow/function expression before it is lowered:
+    /// its annotation, its expression body, or its first top-level `return`.
+    fn infer_function_expr_return_type(&self, func_expr: &Expr) -> IrType {
+        let (return_type, is_async, returned) = match func_expr {
+            Expr::Arrow { return_type, is_async, body: ArrowBody::Expr(expr), .. } => {
+                (return_type, *is_async, self.infer_expr_type(&expr.value))
+            }
+            Expr::Arrow { return_type, is_async, body: ArrowBody::Block(block), .. } => {
+                (return_type, *is_async, self.infer_body_return_type(None, &block.value.stmts))
+            }
+            Expr::Function { return_type, is_async, body, .. } => {
+                (return_type, *is_async, self.infer_body_return_type(None, &body.value.stmts))
+            }
+            _ => return IrType::F64,
+        };
+        match (return_type, is_async) {
+            (Some(_), true) => self.async_return_type(return_type.as_deref()),
+            (Some(t), false) => self.ast_type_to_ir(&t.value),
+            (None, true) => IrType::Promise(Box::new(returned)),
+            (None, false) => returned,
+        }
+    }
+
+    /// Return type of a function body before it is lowered: its annotation,
+    /// or else the type of its first top-level `return`.
+    fn infer_body_return_type(&self, return_type: Option<&Node<Type>>, stmts: &[Node<Stmt>]) -> IrType {
+        if let Some(t) = return_type {
+            return self.ast_type_to_ir(&t.value);
+        }
+        stmts.iter()
+            .find_map(|s| match &s.value {
+                Stmt::Return(Some(expr)) => Some(self.infer_expr_type(&expr.value)),
+                _ => None,
+            })
+            .unwrap_or(IrType::Void)
+    }
 
-        // Detect which captured variables are mutated inside the closure body
-        let mutated_captured = self.collect_mutated_captured_vars(&body_stmts, &param_names);
+    /// Lower a function expression
+    fn lower_function_expr(
+        &mut self,
+        ctx: &mut FuncCtx,
+        params: &[Param],
+        return_type: Option<&Node<Type>>,
+        body: &Node<BlockStmt>,
+        is_async: bool,
+        span: &Span,
+    ) -> Option<Value> {
+        // Convert to arrow-like body and reuse arrow logic
+        let arrow_body = ArrowBody::Block(Box::new(body.clone()));
+        self.lower_arrow_expr(ctx, params, return_type, &arrow_body, is_async, span)
+    }
 
-        // Box any captured variables that are mutated (capture-by-reference)
-        // Save original types before boxing
-        let mut original_types: HashMap<String, IrType> = HashMap::new();
-        for cap_name in &captured_vars {
-            if mutated_captured.contains(cap_name) {
-                if let Some(info) = self.lookup_var(cap_name).cloned() {
-                    if !info.is_boxed {
-                        original_types.insert(cap_name.clone(), info.ir_type.clone());
-                        // Allocate a box and store the current value
-                        self.ensure_extern("zaco_box_new", vec![IrType::Ptr], IrType::Ptr);
-                        let box_local = ctx.add_local(IrType::Ptr);
-                        ctx.emit(Instruction::Call {
-                            dest: Some(Place::from_local(box_local)),
-                            func: Value::Const(Constant::Str("zaco_box_new".to_string())),
-                            args: vec![Value::Local(info.local_id)],
-                        });
-                        // Redefine the variable to use the box pointer
-                        self.define_var(cap_name, VarInfo {
-                            local_id: box_local,
-                            ir_type: info.ir_type,
-                            is_boxed: true,
-                        });
-                    }
+    /// Lower a closure call: prepend captured variable values to args
+    /// Lower a closure call: pass env_ptr as first arg, then actual args
+    fn lower_closure_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        closure_info: &ClosureInfo,
+        args: &[Node<Expr>],
+        _span: &Span,
+    ) -> Option<Value> {
+        let mut arg_vals: Vec<Value> = Vec::new();
+
+        // First arg: environment pointer (if closure captures variables)
+        if let Some(env_local) = closure_info.env_local {
+            arg_vals.push(Value::Local(env_local));
+        }
+
+        // Then: add actual arguments
+        let param_types = self.module.find_function(&closure_info.func_name)
+            .map(|f| f.params.iter().skip(arg_vals.len()).map(|(_, ty)| ty.clone()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for (i, arg) in args.iter().enumerate() {
+            arg_vals.push(self.lower_arg(ctx, arg, param_types.get(i))?);
+        }
+
+        // Look up return type
+        let return_type = self.function_return_type(&closure_info.func_name).unwrap_or(IrType::Void);
+
+        if return_type == IrType::Void {
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str(closure_info.func_name.clone())),
+                args: arg_vals,
+            });
+            None
+        } else {
+            let result = ctx.add_temp(return_type);
+            ctx.emit(Instruction::Call {
+                dest: Some(Place::from_temp(result)),
+                func: Value::Const(Constant::Str(closure_info.func_name.clone())),
+                args: arg_vals,
+            });
+            Some(Value::Temp(result))
+        }
+    }
+
+    /// Lower an argument passed as a parameter of type `param_type`. A
+    /// function passed where a function type is expected becomes a closure
+    /// value the callee can call.
+    fn lower_arg(&mut self, ctx: &mut FuncCtx, arg: &Node<Expr>, param_type: Option<&IrType>) -> Option<Value> {
+        match param_type {
+            Some(IrType::FuncPtr(sig)) => self.lower_function_value(ctx, arg, Some(sig)),
+            _ => self.lower_expr(ctx, &arg.value, &arg.span),
+        }
+    }
+
+    /// Lower `expr` where a function value is expected. A function known
+    /// statically (an arrow or function expression, a variable bound to
+    /// one, a declared function) becomes a closure value: its address and
+    /// env, the pair `lower_indirect_call` takes apart. `sig` types the
+    /// unannotated parameters of an arrow or function expression. Anything
+    /// else is lowered as it is.
+    fn lower_function_value(&mut self, ctx: &mut FuncCtx, expr: &Node<Expr>, sig: Option<&FuncSignature>) -> Option<Value> {
+        let (func_name, env_local) = match &expr.value {
+            Expr::Paren(inner) => return self.lower_function_value(ctx, inner, sig),
+            Expr::Arrow { .. } | Expr::Function { .. } => {
+                self.closure_param_types = sig.map(|sig| sig.params.clone());
+                let val = self.lower_expr(ctx, &expr.value, &expr.span);
+                self.closure_param_types = None;
+                let func_name = match val? {
+                    Value::Const(Constant::Str(func_name)) => func_name,
+                    other => return Some(other),
+                };
+                let env_local = self.closure_bindings.get(&func_name).and_then(|closure| closure.env_local);
+                (func_name, env_local)
+            }
+            Expr::Ident(ident) if self.closure_bindings.contains_key(&ident.name) => {
+                let closure = &self.closure_bindings[&ident.name];
+                (closure.func_name.clone(), closure.env_local)
+            }
+            Expr::Ident(ident) if self.lookup_var(&ident.name).is_none() => {
+                let func_name = if ident.name == "main" && self.has_user_main {
+                    "_user_main".to_string()
+                } else {
+                    ident.name.clone()
+                };
+                if self.module.find_function(&func_name).is_none() {
+                    return self.lower_expr(ctx, &expr.value, &expr.span);
                 }
+                (func_name, None)
             }
+            _ => return self.lower_expr(ctx, &expr.value, &expr.span),
+        };
+        self.ensure_extern("zaco_closure_new", vec![IrType::Ptr, IrType::Ptr], IrType::Ptr);
+        let closure = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(closure)),
+            func: Value::Const(Constant::Str("zaco_closure_new".to_string())),
+            args: vec![
+                Value::Const(Constant::FuncAddr(func_name)),
+                env_local.map_or(Value::Const(Constant::Null), Value::Local),
+            ],
+        });
+        Some(Value::Temp(closure))
+    }
+
+    /// Call the closure value `callee` (see `lower_function_value`), whose
+    /// function type is `sig`, with `args`. A closure with an env takes it
+    /// as its first argument; one without takes just the arguments.
+    fn lower_indirect_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        callee: Value,
+        sig: &FuncSignature,
+        args: &[Node<Expr>],
+    ) -> Option<Value> {
+        let mut arg_vals = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            arg_vals.push(self.lower_arg(ctx, arg, sig.params.get(i))?);
         }
+        self.emit_indirect_call(ctx, callee, sig, &arg_vals)
+    }
 
-        // Create environment struct if there are captured variables
-        let env_struct_id = if !captured_vars.is_empty() {
-            let env_id = self.alloc_struct_id();
-            let env_fields: Vec<(String, IrType)> = captured_vars.iter().map(|name| {
-                let info = self.lookup_var(name);
-                let ty = info.map(|i| {
-                    if i.is_boxed { IrType::Ptr } else { i.ir_type.clone() }
-                }).unwrap_or(IrType::F64);
-                (name.clone(), ty)
-            }).collect();
-            let env_struct = IrStruct::new(env_id, format!("__env_{}", closure_id), env_fields.clone());
-            self.module.add_struct(env_struct);
+    /// Call the closure value `callee` with arguments already lowered to
+    /// the types `sig` takes
+    fn emit_indirect_call(&mut self, ctx: &mut FuncCtx, callee: Value, sig: &FuncSignature, arg_vals: &[Value]) -> Option<Value> {
+        self.ensure_extern("zaco_closure_fn", vec![IrType::Ptr], IrType::Ptr);
+        self.ensure_extern("zaco_closure_env", vec![IrType::Ptr], IrType::Ptr);
+        let func = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(func)),
+            func: Value::Const(Constant::Str("zaco_closure_fn".to_string())),
+            args: vec![callee.clone()],
+        });
+        let env = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(env)),
+            func: Value::Const(Constant::Str("zaco_closure_env".to_string())),
+            args: vec![callee],
+        });
+        let has_env = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(has_env),
+            value: RValue::BinaryOp { op: BinOp::Ne, left: Value::Temp(env), right: Value::Const(Constant::Null) },
+        });
 
-            // Register env as a "class" so store/load_struct_field works
-            let env_name = format!("__env_{}", closure_id);
-            self.class_info.insert(env_name, ClassInfo {
-                struct_id: env_id,
-                fields: env_fields,
-                methods: Vec::new(),
-                parent: None,
-                parent_field_count: 0,
-                getters: Vec::new(),
-                setters: Vec::new(),
-                static_methods: Vec::new(),
-                static_properties: Vec::new(),
+        let result = (*sig.return_type != IrType::Void).then(|| ctx.add_local((*sig.return_type).clone()));
+        let with_env = ctx.new_block();
+        let without_env = ctx.new_block();
+        let done = ctx.new_block();
+        ctx.set_terminator(Terminator::Branch { cond: Value::Temp(has_env), then_block: with_env, else_block: without_env });
+        for (block, env_arg) in [(with_env, Some(Value::Temp(env))), (without_env, None)] {
+            ctx.switch_to(block);
+            ctx.emit(Instruction::Call {
+                dest: result.map(Place::from_local),
+                func: Value::Temp(func),
+                args: env_arg.into_iter().chain(arg_vals.iter().cloned()).collect(),
             });
+            ctx.set_terminator(Terminator::Jump(done));
+        }
+        ctx.switch_to(done);
+        result.map(Value::Local)
+    }
 
-            Some(env_id)
-        } else {
-            None
+    // =========================================================================
+    // assert module and test()
+    // =========================================================================
+
+    /// Functions of the "assert" module; the module itself is `ok`.
+    const ASSERT_FUNCTIONS: &'static [&'static str] =
+        &["ok", "equal", "strictEqual", "deepEqual", "deepStrictEqual", "throws"];
+
+    /// Lower `assert(cond, msg?)` / `assert.<method>(...)`. Failures throw a
+    /// catchable `AssertionError` from the runtime.
+    fn lower_assert_call(
+        &mut self,
+        ctx: &mut FuncCtx,
+        method: &str,
+        args: &[Node<Expr>],
+        span: &Span,
+    ) -> Option<Value> {
+        let message_index = match method {
+            "ok" | "throws" => 1,
+            "equal" | "strictEqual" | "deepEqual" | "deepStrictEqual" => 2,
+            _ => {
+                self.errors.push(LowerError::new(format!("assert.{} is not supported", method), *span));
+                return None;
+            }
+        };
+        let message = match args.get(message_index) {
+            Some(arg) => self.lower_expr(ctx, &arg.value, &arg.span)?,
+            None => Value::Const(Constant::Null),
         };
 
-        // Allocate env struct on the heap and store captured values (in the calling context)
-        let env_local = if let Some(env_id) = env_struct_id {
-            let env_local = ctx.add_local(IrType::Struct(env_id));
-            ctx.emit(Instruction::Alloc {
-                dest: Place::from_local(env_local),
-                ty: IrType::Struct(env_id),
+        if method == "throws" {
+            let closure = match args.first() {
+                Some(arg) => self.lower_callback_arg(ctx, arg, false)?,
+                None => return None,
+            };
+            self.ensure_extern("zaco_assert_not_thrown", vec![IrType::Str], IrType::Void);
+            self.ensure_extern("zaco_clear_error", vec![], IrType::Void);
+            let done_block = ctx.new_block();
+            let catch_block = self.lower_guarded_call(ctx, &closure);
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str("zaco_assert_not_thrown".to_string())),
+                args: vec![message],
+            });
+            ctx.set_terminator(Terminator::Jump(done_block));
+            ctx.switch_to(catch_block);
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str("zaco_clear_error".to_string())),
+                args: vec![],
             });
+            ctx.set_terminator(Terminator::Jump(done_block));
+            ctx.switch_to(done_block);
+            return None;
+        }
 
-            let env_name = format!("__env_{}", closure_id);
-            for cap_name in &captured_vars {
-                if let Some(info) = self.lookup_var(cap_name).cloned() {
-                    self.store_struct_field(
-                        ctx,
-                        Value::Local(env_local),
-                        &env_name,
-                        cap_name,
-                        Value::Local(info.local_id),
-                    );
+        if method == "ok" {
+            let cond = match args.first() {
+                Some(arg) => {
+                    let ty = self.infer_expr_type(&arg.value);
+                    let val = self.lower_expr(ctx, &arg.value, &arg.span)?;
+                    self.truthiness(ctx, val, &ty)
+                }
+                None => Value::Const(Constant::Bool(false)),
+            };
+            self.ensure_extern("zaco_assert", vec![IrType::Bool, IrType::Str], IrType::Void);
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str("zaco_assert".to_string())),
+                args: vec![cond, message],
+            });
+            return None;
+        }
+
+        let (Some(actual), Some(expected)) = (args.first(), args.get(1)) else {
+            self.errors.push(LowerError::new(format!("assert.{} takes two values to compare", method), *span));
+            return None;
+        };
+        let ty = self.infer_expr_type(&actual.value);
+        let deep = matches!(method, "deepEqual" | "deepStrictEqual");
+        let shape = deep.then(|| self.deep_equal_shape(&actual.value, &expected.value));
+        let mut actual_val = self.lower_expr(ctx, &actual.value, &actual.span)?;
+        let mut expected_val = self.lower_expr(ctx, &expected.value, &expected.span)?;
+        let (runtime_fn, operand) = match ty {
+            IrType::F64 | IrType::I64 => ("zaco_assert_equal_f64", IrType::F64),
+            IrType::Str => ("zaco_assert_equal_str", IrType::Str),
+            IrType::Bool => ("zaco_assert_equal_bool", IrType::Bool),
+            _ if deep => ("zaco_assert_deep_equal", IrType::Ptr),
+            _ => ("zaco_assert_equal_ptr", IrType::Ptr),
+        };
+        if operand == IrType::F64 {
+            for (val, expr) in [(&mut actual_val, &actual.value), (&mut expected_val, &expected.value)] {
+                if self.infer_expr_type(expr) == IrType::I64 {
+                    let number = ctx.add_temp(IrType::F64);
+                    ctx.emit(Instruction::Assign {
+                        dest: Place::from_temp(number),
+                        value: RValue::Cast { value: val.clone(), ty: IrType::F64 },
+                    });
+                    *val = Value::Temp(number);
                 }
             }
+        }
+        let mut params = vec![operand.clone(), operand];
+        let mut call_args = vec![actual_val, expected_val];
+        if let (Some(shape), "zaco_assert_deep_equal") = (shape, runtime_fn) {
+            self.module.intern_string(shape.clone());
+            params.push(IrType::Str);
+            call_args.push(Value::Const(Constant::Str(shape)));
+        }
+        params.push(IrType::Str);
+        call_args.push(message);
+        self.ensure_extern(runtime_fn, params, IrType::Void);
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str(runtime_fn.to_string())),
+            args: call_args,
+        });
+        None
+    }
 
-            Some(env_local)
-        } else {
-            None
+    /// Lower `test(name, fn)`: run `fn` right away in a try block and record
+    /// whether it threw. `main` reports the results (see `zaco_test_report`).
+    fn lower_test_call(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>], span: &Span) -> Option<Value> {
+        let (Some(name), Some(body)) = (args.first(), args.get(1)) else {
+            self.errors.push(LowerError::new("test() takes a name and a function", *span));
+            return None;
+        };
+        let name_val = self.lower_expr(ctx, &name.value, &name.span)?;
+        let closure = self.lower_callback_arg(ctx, body, false)?;
+        self.ensure_extern("zaco_test_begin", vec![IrType::Str], IrType::Void);
+        self.ensure_extern("zaco_test_pass", vec![], IrType::Void);
+        self.ensure_extern("zaco_test_fail", vec![IrType::Ptr], IrType::Void);
+        self.ensure_extern("zaco_get_error", vec![], IrType::Ptr);
+        self.ensure_extern("zaco_clear_error", vec![], IrType::Void);
+
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_test_begin".to_string())),
+            args: vec![name_val],
+        });
+        let done_block = ctx.new_block();
+        let catch_block = self.lower_guarded_call(ctx, &closure);
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_test_pass".to_string())),
+            args: vec![],
+        });
+        ctx.set_terminator(Terminator::Jump(done_block));
+
+        ctx.switch_to(catch_block);
+        let error = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(error)),
+            func: Value::Const(Constant::Str("zaco_get_error".to_string())),
+            args: vec![],
+        });
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_clear_error".to_string())),
+            args: vec![],
+        });
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_test_fail".to_string())),
+            args: vec![Value::Temp(error)],
+        });
+        ctx.set_terminator(Terminator::Jump(done_block));
+        ctx.switch_to(done_block);
+        None
+    }
+
+    /// Call a no-argument closure inside a try block. Lowering continues on
+    /// the normal path (with the try slot already popped); the returned
+    /// block is where a throw lands.
+    fn lower_guarded_call(&mut self, ctx: &mut FuncCtx, closure: &ClosureInfo) -> BlockId {
+        self.ensure_extern("zaco_try_enter", vec![], IrType::Ptr);
+        self.ensure_extern("_setjmp", vec![IrType::Ptr], IrType::I64);
+        self.ensure_extern("zaco_try_pop", vec![], IrType::Void);
+
+        let call_block = ctx.new_block();
+        let catch_block = ctx.new_block();
+        let buf = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(buf)),
+            func: Value::Const(Constant::Str("zaco_try_enter".to_string())),
+            args: vec![],
+        });
+        let jumped = ctx.add_temp(IrType::I64);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(jumped)),
+            func: Value::Const(Constant::Str("_setjmp".to_string())),
+            args: vec![Value::Temp(buf)],
+        });
+        let normal = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(normal),
+            value: RValue::BinaryOp {
+                op: BinOp::Eq,
+                left: Value::Temp(jumped),
+                right: Value::Const(Constant::I64(0)),
+            },
+        });
+        ctx.set_terminator(Terminator::Branch {
+            cond: Value::Temp(normal),
+            then_block: call_block,
+            else_block: catch_block,
+        });
+
+        ctx.switch_to(call_block);
+        let return_type = self
+            .module
+            .find_function(&closure.func_name)
+            .map(|f| f.return_type.clone())
+            .unwrap_or(IrType::Void);
+        let dest = (return_type != IrType::Void).then(|| Place::from_temp(ctx.add_temp(return_type)));
+        ctx.emit(Instruction::Call {
+            dest,
+            func: Value::Const(Constant::Str(closure.func_name.clone())),
+            args: closure.env_local.map(Value::Local).into_iter().collect(),
+        });
+        ctx.emit(Instruction::Call {
+            dest: None,
+            func: Value::Const(Constant::Str("zaco_try_pop".to_string())),
+            args: vec![],
+        });
+        catch_block
+    }
+
+    /// Narrow a value to a boolean the way a condition would: numbers are
+    /// true unless zero, pointers unless null.
+    fn truthiness(&mut self, ctx: &mut FuncCtx, val: Value, ty: &IrType) -> Value {
+        let zero = match ty {
+            IrType::Bool => return val,
+            IrType::F64 => Constant::F64(0.0),
+            IrType::I64 => Constant::I64(0),
+            _ => {
+                let nullish = self.emit_is_nullish(ctx, val);
+                let cond = ctx.add_temp(IrType::Bool);
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(cond),
+                    value: RValue::UnaryOp { op: UnOp::Not, operand: nullish },
+                });
+                return Value::Temp(cond);
+            }
         };
+        let cond = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(cond),
+            value: RValue::BinaryOp { op: BinOp::Ne, left: val, right: Value::Const(zero) },
+        });
+        Value::Temp(cond)
+    }
 
-        // Build parameter list for closure function: env_ptr (if captures), then declared params
-        let mut ir_params: Vec<(LocalId, IrType)> = Vec::new();
-        let mut local_idx = 0;
-
-        if let Some(env_id) = env_struct_id {
-            ir_params.push((LocalId(local_idx), IrType::Struct(env_id)));
-            local_idx += 1;
+    /// Shape to deep-compare two values with. Literals describe their
+    /// elements best, and the expected value usually is one.
+    fn deep_equal_shape(&self, actual: &Expr, expected: &Expr) -> String {
+        let is_literal = |expr: &Expr| matches!(expr, Expr::Array(_) | Expr::Object(_));
+        match self.value_shape(actual) {
+            shape if shape == "p" || (!is_literal(actual) && is_literal(expected)) => self.value_shape(expected),
+            shape => shape,
         }
+    }
 
-        for param in params {
-            let ir_type = self.infer_param_type(param);
-            ir_params.push((LocalId(local_idx), ir_type));
-            local_idx += 1;
+    /// Lower `isDeepStrictEqual(a, b)` from "util". Numbers compare like
+    /// `Object.is`; everything else goes through `zaco_deep_equal`.
+    fn lower_is_deep_strict_equal(&mut self, ctx: &mut FuncCtx, args: &[Node<Expr>], span: &Span) -> Option<Value> {
+        let (Some(a), Some(b)) = (args.first(), args.get(1)) else {
+            self.errors.push(LowerError::new("isDeepStrictEqual() takes two values to compare", *span));
+            return None;
+        };
+        let ty = self.infer_expr_type(&a.value);
+        let shape = self.deep_equal_shape(&a.value, &b.value);
+        let a_val = self.lower_expr(ctx, &a.value, &a.span)?;
+        let b_val = self.lower_expr(ctx, &b.value, &b.span)?;
+        let result = ctx.add_temp(IrType::Bool);
+        match ty {
+            IrType::F64 => {
+                self.ensure_extern("zaco_same_value_f64", vec![IrType::F64, IrType::F64], IrType::Bool);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(result)),
+                    func: Value::Const(Constant::Str("zaco_same_value_f64".to_string())),
+                    args: vec![a_val, b_val],
+                });
+            }
+            IrType::Bool | IrType::I64 => {
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(result),
+                    value: RValue::BinaryOp { op: BinOp::Eq, left: a_val, right: b_val },
+                });
+            }
+            _ => {
+                self.ensure_extern("zaco_deep_equal", vec![IrType::Ptr, IrType::Ptr, IrType::Str], IrType::Bool);
+                self.module.intern_string(shape.clone());
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(result)),
+                    func: Value::Const(Constant::Str("zaco_deep_equal".to_string())),
+                    args: vec![a_val, b_val, Value::Const(Constant::Str(shape))],
+                });
+            }
         }
+        Some(Value::Temp(result))
+    }
 
-        // Infer return type
-        let ret_type = return_type
-            .map(|t| self.ast_type_to_ir(&t.value))
-            .unwrap_or_else(|| {
-                match body {
-                    ArrowBody::Expr(expr) => self.infer_expr_type(&expr.value),
-                    _ => IrType::Void,
+    /// Deep-equality shape of an expression's value, in the notation of
+    /// `clone_shape`; `p` for values compared by identity.
+    fn value_shape(&self, expr: &Expr) -> String {
+        let fields = |shape: Vec<(String, IrType)>| {
+            let fields: Vec<String> = shape.iter().map(|(key, ty)| format!("{}:{}", key, Self::ir_shape(ty))).collect();
+            format!("{{{}}}", fields.join(","))
+        };
+        match expr {
+            Expr::Paren(inner) => self.value_shape(&inner.value),
+            // An element spread into an array literal has the iterable's element shape
+            Expr::Spread(inner) => {
+                let shape = self.value_shape(&inner.value);
+                match shape.strip_prefix('[') {
+                    Some(elem) => elem.to_string(),
+                    None => Self::ir_shape(&self.iterable_element_type(&inner.value)),
                 }
-            });
+            }
+            Expr::Array(elems) => match elems.iter().flatten().next() {
+                Some(first) => format!("[{}", self.value_shape(&first.value)),
+                None => "[n".to_string(),
+            },
+            Expr::Object(props) => {
+                let mut fields: Vec<String> = props
+                    .iter()
+                    .filter_map(|prop| match prop {
+                        ObjectProperty::Property { key, value, .. } => {
+                            Some(format!("{}:{}", Self::property_key(key)?, self.value_shape(&value.value)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                fields.extend(self.accessor_shape_fields(props, fields.len()));
+                format!("{{{}}}", fields.join(","))
+            }
+            Expr::Call { .. } if self.object_static_call(expr).is_some() && self.expr_value_type(expr).is_some() => {
+                self.structured_clone_shape(expr)
+            }
+            _ => {
+                if let Some(shape) = self.expr_object_shape(expr) {
+                    fields(shape)
+                } else if let Some(shape) = self.expr_element_shape(expr) {
+                    format!("[{}", fields(shape))
+                } else {
+                    Self::ir_shape(&self.infer_expr_type(expr))
+                }
+            }
+        }
+    }
 
-        let func_id = self.alloc_func_id();
-        let mut ir_func = IrFunction::new(func_id, func_name.clone(), ir_params.clone(), ret_type.clone());
-        let entry = ir_func.new_block();
-        ir_func.entry_block = entry;
+    /// Shape fields of an object literal's accessor properties, which
+    /// follow its `data_fields` data properties
+    fn accessor_shape_fields(&self, props: &[ObjectProperty], data_fields: usize) -> Vec<String> {
+        self.object_literal_shape(props)
+            .into_iter()
+            .skip(data_fields)
+            .map(|(key, ty)| format!("{}:{}", key, Self::ir_shape(&ty)))
+            .collect()
+    }
 
-        let mut closure_ctx = FuncCtx {
-            func: &mut ir_func,
-            current_block: entry,
-        };
+    fn ir_shape(ty: &IrType) -> String {
+        match ty {
+            IrType::F64 | IrType::I64 => "n".to_string(),
+            IrType::Bool => "b".to_string(),
+            IrType::Str => "s".to_string(),
+            IrType::Array(elem) => format!("[{}", Self::ir_shape(elem)),
+            _ => "p".to_string(),
+        }
+    }
 
-        self.push_scope();
+    // =========================================================================
+    // structuredClone
+    // =========================================================================
 
-        // Load captured vars from environment struct into local variables
-        if let Some(_env_id) = env_struct_id {
-            let env_param_local = LocalId(0);
-            let env_name = format!("__env_{}", closure_id);
+    /// `structuredClone(value)` with the global not shadowed by a local.
+    fn is_structured_clone_call(&self, expr: &Expr) -> bool {
+        matches!(expr, Expr::Call { callee, args, .. }
+            if args.len() == 1
+                && matches!(&callee.value, Expr::Ident(ident) if ident.name == "structuredClone")
+                && self.lookup_var("structuredClone").is_none())
+    }
 
-            for cap_name in &captured_vars {
-                if let Some(val) = self.load_struct_field(&mut closure_ctx, Value::Local(env_param_local), &env_name, cap_name) {
-                    let cap_type = self.class_info.get(&env_name)
-                        .and_then(|ci| ci.fields.iter().find(|(n, _)| n == cap_name))
-                        .map(|(_, t)| t.clone())
-                        .unwrap_or(IrType::F64);
-                    let cap_local = closure_ctx.add_local(cap_type.clone());
-                    closure_ctx.emit(Instruction::Assign {
-                        dest: Place::from_local(cap_local),
-                        value: RValue::Use(val),
-                    });
-                    // If this variable was boxed (mutated capture), mark it as boxed
-                    // so reads/writes inside the closure go through box_get/box_set
-                    let is_boxed_cap = mutated_captured.contains(cap_name);
-                    let logical_type = if is_boxed_cap {
-                        original_types.get(cap_name).cloned().unwrap_or(cap_type)
-                    } else {
-                        cap_type
-                    };
-                    self.define_var(cap_name, VarInfo {
-                        local_id: cap_local,
-                        ir_type: logical_type,
-                        is_boxed: is_boxed_cap,
-                    });
+    /// Lower `structuredClone(value)`. Numbers and booleans are returned as
+    /// they are; anything else is deep copied by `zaco_structured_clone`
+    /// with the shape of the argument's static type.
+    fn lower_structured_clone(&mut self, ctx: &mut FuncCtx, arg: &Node<Expr>, _span: &Span) -> Option<Value> {
+        let shape = self.structured_clone_shape(&arg.value);
+        let ty = self.infer_expr_type(&arg.value);
+        let value = self.lower_expr(ctx, &arg.value, &arg.span)?;
+        if matches!(ty, IrType::F64 | IrType::I64 | IrType::Bool) {
+            return Some(value);
+        }
+        self.ensure_extern("zaco_structured_clone", vec![IrType::Ptr, IrType::Str], IrType::Ptr);
+        self.module.intern_string(shape.clone());
+        let result = ctx.add_temp(ty);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str("zaco_structured_clone".to_string())),
+            args: vec![value, Value::Const(Constant::Str(shape))],
+        });
+        Some(Value::Temp(result))
+    }
+
+    /// Annotated type of an expression's value: a variable that has one, or
+    /// a field or element reached from it (or a clone of either).
+    fn expr_value_type(&self, expr: &Expr) -> Option<Type> {
+        match expr {
+            Expr::Ident(ident) => self.value_types.get(&ident.name).cloned(),
+            Expr::Paren(inner) | Expr::NonNullAssertion(inner) | Expr::Satisfies { expr: inner, .. } => {
+                self.expr_value_type(&inner.value)
+            }
+            Expr::TypeCast { ty, .. } => Some(ty.value.clone()),
+            Expr::Call { args, .. } if self.is_structured_clone_call(expr) => self.expr_value_type(&args[0].value),
+            Expr::Call { callee, .. } if self.object_static_call(expr).is_some() => {
+                let (method, args) = self.object_static_call(expr)?;
+                self.object_static_value_type(method, args, &callee.span)
+            }
+            // A call to a function with a return type annotation
+            Expr::Call { callee, .. } => match &callee.value {
+                Expr::Ident(ident) if self.lookup_var(&ident.name).is_none() => {
+                    self.function_return_types.get(&ident.name).cloned()
                 }
+                _ => None,
+            },
+            Expr::Member { object, property, .. } | Expr::OptionalMember { object, property } => {
+                let Type::Object(object_ty) = self.resolve_value_type(&self.expr_value_type(&object.value)?) else {
+                    return None;
+                };
+                object_ty.members.iter().find_map(|member| match member {
+                    ObjectTypeMember::Property { name, ty, .. }
+                        if Self::property_key(name).as_deref() == Some(property.value.name.as_str()) =>
+                    {
+                        Some(ty.value.clone())
+                    }
+                    _ => None,
+                })
             }
+            Expr::Index { object, index } => match self.resolve_value_type(&self.expr_value_type(&object.value)?) {
+                Type::Array(elem) => Some(elem.value),
+                Type::Tuple(elems) => Self::tuple_element(elems, &index.value),
+                _ => None,
+            },
+            _ => None,
         }
+    }
 
-        // Register declared params in scope
-        let param_offset = if env_struct_id.is_some() { 1 } else { 0 };
-        for (i, param) in params.iter().enumerate() {
-            let param_name = match &param.pattern.value {
-                Pattern::Ident { name, .. } => name.value.name.clone(),
-                _ => format!("_param{}", i),
-            };
-            let idx = param_offset + i;
-            let (local_id, ir_type) = &ir_params[idx];
-            self.define_var(&param_name, VarInfo {
-                local_id: *local_id,
-                ir_type: ir_type.clone(),
-                is_boxed: false,
-            });
+    /// Declared type of the element at `index` of a destructured value: a
+    /// tuple's element there, or an array's element type.
+    fn destructured_value_type(&self, init: &Expr, index: usize) -> Option<Type> {
+        match self.resolve_value_type(&self.expr_value_type(init)?) {
+            Type::Tuple(elems) => elems.into_iter().nth(index).map(|elem| elem.value),
+            Type::Array(elem) => Some(elem.value),
+            _ => None,
         }
+    }
 
-        // Lower body
-        for s in &body_stmts {
-            self.lower_stmt(&mut closure_ctx, &s.value, &s.span);
+    /// IR type of the element at `index` of a destructured value with no
+    /// declared type: that element of an array literal, or the element type
+    /// of an array.
+    fn destructured_element_type(&self, init: &Expr, index: usize) -> IrType {
+        if let Expr::Array(elems) = init {
+            let has_spread = elems.iter().flatten().any(|elem| matches!(elem.value, Expr::Spread(_)));
+            if let (false, Some(Some(elem))) = (has_spread, elems.get(index)) {
+                return self.infer_expr_type(&elem.value);
+            }
+        }
+        match self.infer_expr_type(init) {
+            IrType::Array(elem) => *elem,
+            _ => IrType::F64,
         }
+    }
 
-        // Add implicit return if needed
-        if matches!(
-            closure_ctx.func.block(closure_ctx.current_block).terminator,
-            Terminator::Unreachable
-        ) {
-            if ret_type == IrType::Void {
-                closure_ctx.set_terminator(Terminator::Return(None));
-            } else {
-                let temp = closure_ctx.add_temp(ret_type);
-                closure_ctx.emit(Instruction::Assign {
-                    dest: Place::from_temp(temp),
-                    value: RValue::Use(Value::Const(Constant::I64(0))),
-                });
-                closure_ctx.set_terminator(Terminator::Return(Some(Value::Temp(temp))));
+    /// Element of a tuple type at a literal index.
+    fn tuple_element(elems: Vec<Node<Type>>, index: &Expr) -> Option<Type> {
+        match index {
+            Expr::Literal(Literal::Number(n)) if n.fract() == 0.0 && *n >= 0.0 => {
+                elems.into_iter().nth(*n as usize).map(|elem| elem.value)
             }
+            _ => None,
         }
+    }
 
-        self.pop_scope();
-        self.module.add_function(ir_func);
+    /// Look through parentheses, `T | null` and names of interfaces and
+    /// type aliases.
+    fn resolve_value_type(&self, ty: &Type) -> Type {
+        let mut ty = ty.clone();
+        // Bounded, in case of alias cycles
+        for _ in 0..16 {
+            ty = match ty {
+                Type::Paren(inner) => inner.value,
+                Type::Union(members) => {
+                    let mut non_null = members.into_iter().filter(|member| {
+                        !matches!(member.value, Type::Primitive(PrimitiveType::Null | PrimitiveType::Undefined))
+                    });
+                    match (non_null.next(), non_null.next()) {
+                        (Some(only), None) => only.value,
+                        _ => return Type::Primitive(PrimitiveType::Any),
+                    }
+                }
+                Type::TypeRef { ref name, .. } => match self.named_types.get(&name.value.name) {
+                    Some(named) => named.clone(),
+                    None => return ty,
+                },
+                other => return other,
+            };
+        }
+        ty
+    }
 
-        // Store closure binding
-        self.closure_bindings.insert(func_name.clone(), ClosureInfo {
-            func_name: func_name.clone(),
-            captured_vars,
-            env_struct_id,
-            env_local,
-        });
+    /// Clone shape of an expression's value: from its annotated type when
+    /// known, so nested and recursive types are copied all the way down,
+    /// and otherwise as for deep equality.
+    fn structured_clone_shape(&self, expr: &Expr) -> String {
+        if let Some(ty) = self.expr_value_type(expr) {
+            return self.type_clone_shape(&ty, &mut Vec::new());
+        }
+        match expr {
+            Expr::Paren(inner) => self.structured_clone_shape(&inner.value),
+            Expr::Spread(inner) => {
+                let shape = self.structured_clone_shape(&inner.value);
+                match shape.strip_prefix('[') {
+                    Some(elem) => elem.to_string(),
+                    None => Self::ir_shape(&self.iterable_element_type(&inner.value)),
+                }
+            }
+            Expr::Arrow { .. } | Expr::Function { .. } => "f".to_string(),
+            Expr::Ident(ident) if self.closure_bindings.contains_key(&ident.name) => "f".to_string(),
+            Expr::Array(elems) => match elems.iter().flatten().next() {
+                Some(first) => format!("[{}", self.structured_clone_shape(&first.value)),
+                None => "[n".to_string(),
+            },
+            Expr::Object(props) => {
+                let mut fields: Vec<String> = props
+                    .iter()
+                    .filter_map(|prop| match prop {
+                        ObjectProperty::Property { key, value, .. } => {
+                            Some(format!("{}:{}", Self::property_key(key)?, self.structured_clone_shape(&value.value)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                fields.extend(self.accessor_shape_fields(props, fields.len()));
+                format!("{{{}}}", fields.join(","))
+            }
+            _ => self.value_shape(expr),
+        }
+    }
 
-        // Return the function name as a string constant
-        self.module.intern_string(func_name.clone());
-        Some(Value::Const(Constant::Str(func_name)))
+    /// Clone shape of a type annotation. `enclosing` holds one entry per
+    /// object shape being built, innermost last (the name for interfaces
+    /// and aliases); a reference back to one of them becomes `^N`.
+    fn type_clone_shape(&self, ty: &Type, enclosing: &mut Vec<Option<String>>) -> String {
+        match ty {
+            Type::Primitive(PrimitiveType::Number) | Type::Literal(LiteralType::Number(_)) => "n".to_string(),
+            Type::Primitive(PrimitiveType::Boolean) | Type::Literal(LiteralType::Boolean(_)) => "b".to_string(),
+            Type::Primitive(PrimitiveType::String) | Type::Literal(LiteralType::String(_)) => "s".to_string(),
+            Type::Function(_) => "f".to_string(),
+            Type::Paren(inner) => self.type_clone_shape(&inner.value, enclosing),
+            Type::Array(elem) => format!("[{}", self.type_clone_shape(&elem.value, enclosing)),
+            Type::Tuple(elems) => {
+                let elems: Vec<String> = elems.iter().map(|elem| self.type_clone_shape(&elem.value, enclosing)).collect();
+                format!("({})", elems.join(","))
+            }
+            Type::Generic { base, type_args } => match (&base.value, type_args.as_slice()) {
+                (Type::TypeRef { name, .. }, [elem]) if name.value.name == "Array" => {
+                    format!("[{}", self.type_clone_shape(&elem.value, enclosing))
+                }
+                _ => "p".to_string(),
+            },
+            // `T | null` and `T | undefined` are T, which may be null
+            Type::Union(members) => {
+                let mut non_null = members.iter().filter(|member| {
+                    !matches!(member.value, Type::Primitive(PrimitiveType::Null | PrimitiveType::Undefined))
+                });
+                match (non_null.next(), non_null.next()) {
+                    (Some(only), None) => self.type_clone_shape(&only.value, enclosing),
+                    _ => "p".to_string(),
+                }
+            }
+            Type::TypeRef { name, .. } => {
+                let name = &name.value.name;
+                if let Some(pos) = enclosing.iter().rposition(|entry| entry.as_deref() == Some(name)) {
+                    return format!("^{}", enclosing.len() - 1 - pos);
+                }
+                match self.named_types.get(name) {
+                    Some(Type::Object(object)) => {
+                        enclosing.push(Some(name.clone()));
+                        let shape = self.members_clone_shape(&object.members, enclosing);
+                        enclosing.pop();
+                        shape
+                    }
+                    Some(other) => self.type_clone_shape(other, enclosing),
+                    None => "p".to_string(),
+                }
+            }
+            Type::Object(object) => {
+                enclosing.push(None);
+                let shape = self.members_clone_shape(&object.members, enclosing);
+                enclosing.pop();
+                shape
+            }
+            _ => "p".to_string(),
+        }
     }
 
-    /// Lower a function expression
-    fn lower_function_expr(
-        &mut self,
-        ctx: &mut FuncCtx,
-        _name: Option<&Node<Ident>>,
-        params: &[Param],
-        return_type: Option<&Node<Type>>,
-        body: &Node<BlockStmt>,
-        span: &Span,
-    ) -> Option<Value> {
-        // Convert to arrow-like body and reuse arrow logic
-        let arrow_body = ArrowBody::Block(Box::new(body.clone()));
-        self.lower_arrow_expr(ctx, params, return_type, &arrow_body, span)
+    fn members_clone_shape(&self, members: &[ObjectTypeMember], enclosing: &mut Vec<Option<String>>) -> String {
+        let fields: Vec<String> = members
+            .iter()
+            .filter_map(|member| match member {
+                ObjectTypeMember::Property { name, ty, .. } => {
+                    Some(format!("{}:{}", Self::property_key(name)?, self.type_clone_shape(&ty.value, enclosing)))
+                }
+                ObjectTypeMember::Method { name, .. } => Some(format!("{}:f", Self::property_key(name)?)),
+                _ => None,
+            })
+            .collect();
+        format!("{{{}}}", fields.join(","))
     }
 
-    /// Lower a closure call: prepend captured variable values to args
-    /// Lower a closure call: pass env_ptr as first arg, then actual args
-    fn lower_closure_call(
+    // =========================================================================
+    // Promise chaining methods (.then, .catch, .finally)
+    // =========================================================================
+
+    /// Lower promise.then/catch/finally(callback) → runtime function call
+    /// Lower `promise.then(cb)`, `.catch(cb)` or `.finally(cb)`. Async code
+    /// runs synchronously, so the receiver is waited on here (which also
+    /// marks a rejection as handled) and the callback is called inline on
+    /// the matching path. The result is a new promise settled with the
+    /// callback's return value, or with the receiver's outcome passed through.
+    fn lower_promise_chain_method(
         &mut self,
         ctx: &mut FuncCtx,
-        closure_info: &ClosureInfo,
+        promise_val: Value,
+        method: &str,
         args: &[Node<Expr>],
         _span: &Span,
     ) -> Option<Value> {
-        let mut arg_vals: Vec<Value> = Vec::new();
-
-        // First arg: environment pointer (if closure captures variables)
-        if let Some(env_local) = closure_info.env_local {
-            arg_vals.push(Value::Local(env_local));
+        if !matches!(method, "then" | "catch" | "finally") {
+            return None;
         }
+        // then(onFulfilled, onRejected?) / catch(onRejected) / finally(onSettled)
+        let (on_fulfilled, on_rejected) = match method {
+            "then" => (args.first(), args.get(1)),
+            "catch" => (None, args.first()),
+            _ => (None, None),
+        };
+        let on_fulfilled = match on_fulfilled {
+            Some(arg) => Some(self.lower_callback_arg(ctx, arg, false)?),
+            None => None,
+        };
+        let on_rejected = match on_rejected {
+            Some(arg) => Some(self.lower_callback_arg(ctx, arg, true)?),
+            None => None,
+        };
+        let on_settled = match (method, args.first()) {
+            ("finally", Some(arg)) => Some(self.lower_callback_arg(ctx, arg, false)?),
+            _ => None,
+        };
 
-        // Then: add actual arguments
-        for arg in args {
-            if let Some(val) = self.lower_expr(ctx, &arg.value, &arg.span) {
-                arg_vals.push(val);
-            } else {
-                return None;
-            }
+        self.ensure_extern("zaco_promise_new", vec![], IrType::Ptr);
+        self.ensure_extern("zaco_promise_resolve", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
+        self.ensure_extern("zaco_promise_reject", vec![IrType::Ptr, IrType::Ptr], IrType::Void);
+        self.ensure_extern("zaco_promise_is_rejected", vec![IrType::Ptr], IrType::I64);
+        self.ensure_extern("zaco_async_block_on", vec![IrType::Ptr], IrType::Ptr);
+
+        let result_temp = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(result_temp)),
+            func: Value::Const(Constant::Str("zaco_promise_new".to_string())),
+            args: vec![],
+        });
+        let payload_temp = ctx.add_temp(IrType::Ptr);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(payload_temp)),
+            func: Value::Const(Constant::Str("zaco_async_block_on".to_string())),
+            args: vec![promise_val.clone()],
+        });
+        let rejected_temp = ctx.add_temp(IrType::I64);
+        ctx.emit(Instruction::Call {
+            dest: Some(Place::from_temp(rejected_temp)),
+            func: Value::Const(Constant::Str("zaco_promise_is_rejected".to_string())),
+            args: vec![promise_val.clone()],
+        });
+        let cond_temp = ctx.add_temp(IrType::Bool);
+        ctx.emit(Instruction::Assign {
+            dest: Place::from_temp(cond_temp),
+            value: RValue::BinaryOp {
+                op: BinOp::Ne,
+                left: Value::Temp(rejected_temp),
+                right: Value::Const(Constant::I64(0)),
+            },
+        });
+
+        if let Some(closure) = &on_settled {
+            self.call_promise_callback(ctx, closure, &promise_val, payload_temp);
         }
 
-        // Look up return type
-        let return_type = self.module.find_function(&closure_info.func_name)
-            .map(|f| f.return_type.clone())
-            .unwrap_or(IrType::Void);
+        let rejected_block = ctx.new_block();
+        let fulfilled_block = ctx.new_block();
+        let done_block = ctx.new_block();
+        ctx.set_terminator(Terminator::Branch {
+            cond: Value::Temp(cond_temp),
+            then_block: rejected_block,
+            else_block: fulfilled_block,
+        });
 
-        if return_type == IrType::Void {
+        for (block, handler, settle_fn) in [
+            (fulfilled_block, on_fulfilled, "zaco_promise_resolve"),
+            (rejected_block, on_rejected, "zaco_promise_reject"),
+        ] {
+            ctx.switch_to(block);
+            let (settle_fn, settle_val) = match handler {
+                Some(closure) => {
+                    let returned = self.call_promise_callback(ctx, &closure, &promise_val, payload_temp);
+                    self.promise_payload(ctx, returned)
+                }
+                None => (settle_fn, Value::Temp(payload_temp)),
+            };
             ctx.emit(Instruction::Call {
                 dest: None,
-                func: Value::Const(Constant::Str(closure_info.func_name.clone())),
-                args: arg_vals,
-            });
-            None
-        } else {
-            let result = ctx.add_temp(return_type);
-            ctx.emit(Instruction::Call {
-                dest: Some(Place::from_temp(result)),
-                func: Value::Const(Constant::Str(closure_info.func_name.clone())),
-                args: arg_vals,
+                func: Value::Const(Constant::Str(settle_fn.to_string())),
+                args: vec![Value::Temp(result_temp), settle_val],
             });
-            Some(Value::Temp(result))
+            ctx.set_terminator(Terminator::Jump(done_block));
         }
-    }
 
-    // =========================================================================
-    // Promise chaining methods (.then, .catch, .finally)
-    // =========================================================================
+        ctx.switch_to(done_block);
+        Some(Value::Temp(result_temp))
+    }
 
-    /// Lower promise.then/catch/finally(callback) → runtime function call
-    fn lower_promise_chain_method(
+    /// Lower a callback argument (a promise handler, a test body) and
+    /// return its closure. With `error_param`, an unannotated first parameter is typed as the
+    /// rejection error (a string) rather than defaulting to a number.
+    fn lower_callback_arg(
         &mut self,
         ctx: &mut FuncCtx,
-        promise_info: &VarInfo,
-        method: &str,
-        args: &[Node<Expr>],
-        _span: &Span,
-    ) -> Option<Value> {
-        if args.is_empty() {
-            return None;
-        }
-
-        // Lower the callback argument (should be a closure or function reference)
-        let callback_arg = &args[0];
-        let callback_val = self.lower_expr(ctx, &callback_arg.value, &callback_arg.span)?;
-
-        // Get the closure info for the callback (to pass env pointer)
-        let callback_closure_info = match &callback_arg.value {
-            Expr::Arrow { .. } | Expr::Function { .. } => {
-                let func_name = format!("__closure_{}", self.next_closure_id - 1);
-                self.closure_bindings.get(&func_name).cloned()
-            }
-            Expr::Ident(ident) => {
-                self.closure_bindings.get(&ident.name).cloned()
+        arg: &Node<Expr>,
+        error_param: bool,
+    ) -> Option<ClosureInfo> {
+        let (mut params, return_type, body) = match &arg.value {
+            Expr::Arrow { params, return_type, body, .. } => (params.clone(), return_type.as_deref(), body.clone()),
+            Expr::Function { params, return_type, body, .. } => {
+                (params.clone(), return_type.as_deref(), ArrowBody::Block(Box::new(*body.clone())))
             }
-            _ => None,
-        };
-
-        // Determine runtime function name
-        let runtime_fn = match method {
-            "then" => "zaco_promise_then",
-            "catch" => "zaco_promise_catch",
-            "finally" => "zaco_promise_finally",
+            Expr::Paren(inner) => return self.lower_callback_arg(ctx, inner, error_param),
+            Expr::Ident(ident) => return self.closure_bindings.get(&ident.name).cloned(),
             _ => return None,
         };
+        if let Some(first) = params.first_mut().filter(|_| error_param) {
+            let annotated = matches!(first.pattern.value, Pattern::Ident { type_annotation: Some(_), .. });
+            if first.type_annotation.is_none() && !annotated {
+                let span = first.pattern.span;
+                first.type_annotation = Some(Box::new(Node::new(Type::Primitive(PrimitiveType::String), span)));
+            }
+        }
+        self.lower_arrow_expr(ctx, &params, return_type, &body, false, &arg.span);
+        let func_name = format!("__closure_{}", self.next_closure_id - 1);
+        self.closure_bindings.get(&func_name).cloned()
+    }
 
-        // Declare the extern: (promise_ptr, callback_fn_ptr, callback_ctx_ptr) → new_promise_ptr
-        self.ensure_extern(
-            runtime_fn,
-            vec![IrType::Ptr, IrType::Ptr, IrType::Ptr],
-            IrType::Ptr,
-        );
+    /// Call a promise callback with the settled payload (if it takes a
+    /// parameter) and return its result, if any.
+    fn call_promise_callback(
+        &mut self,
+        ctx: &mut FuncCtx,
+        closure: &ClosureInfo,
+        promise_val: &Value,
+        payload_temp: TempId,
+    ) -> Option<Value> {
+        let (param_types, return_type) = self
+            .module
+            .find_function(&closure.func_name)
+            .map(|f| (f.params.iter().map(|(_, ty)| ty.clone()).collect::<Vec<_>>(), f.return_type.clone()))
+            .unwrap_or((Vec::new(), IrType::Void));
 
-        // Build args: promise pointer, callback function pointer, callback context (env) pointer
-        let promise_val = Value::Local(promise_info.local_id);
-        let env_val = callback_closure_info
-            .and_then(|ci| ci.env_local.map(|el| Value::Local(el)))
-            .unwrap_or(Value::Const(Constant::Null));
+        let mut cb_args = Vec::new();
+        if let Some(env_local) = closure.env_local {
+            cb_args.push(Value::Local(env_local));
+        }
+        // Payloads are raw 8-byte values: numbers are re-read as f64 and
+        // booleans narrowed, everything else is passed as the pointer
+        match param_types.get(cb_args.len()) {
+            Some(IrType::F64) => {
+                self.ensure_extern("zaco_async_block_on_f64", vec![IrType::Ptr], IrType::F64);
+                let number = ctx.add_temp(IrType::F64);
+                ctx.emit(Instruction::Call {
+                    dest: Some(Place::from_temp(number)),
+                    func: Value::Const(Constant::Str("zaco_async_block_on_f64".to_string())),
+                    args: vec![promise_val.clone()],
+                });
+                cb_args.push(Value::Temp(number));
+            }
+            Some(ty @ (IrType::Bool | IrType::I64)) => {
+                let narrowed = ctx.add_temp(ty.clone());
+                ctx.emit(Instruction::Assign {
+                    dest: Place::from_temp(narrowed),
+                    value: RValue::Cast { value: Value::Temp(payload_temp), ty: ty.clone() },
+                });
+                cb_args.push(Value::Temp(narrowed));
+            }
+            Some(_) => cb_args.push(Value::Temp(payload_temp)),
+            None => {}
+        }
 
-        let result_temp = ctx.add_temp(IrType::Ptr);
+        if return_type == IrType::Void {
+            ctx.emit(Instruction::Call {
+                dest: None,
+                func: Value::Const(Constant::Str(closure.func_name.clone())),
+                args: cb_args,
+            });
+            return None;
+        }
+        let result = ctx.add_temp(return_type);
         ctx.emit(Instruction::Call {
-            dest: Some(Place::from_temp(result_temp)),
-            func: Value::Const(Constant::Str(runtime_fn.to_string())),
-            args: vec![promise_val, callback_val, env_val],
+            dest: Some(Place::from_temp(result)),
+            func: Value::Const(Constant::Str(closure.func_name.clone())),
+            args: cb_args,
         });
-
-        Some(Value::Temp(result_temp))
+        Some(Value::Temp(result))
     }
 
         // =========================================================================
@@ -4852,14 +11506,14 @@ impl Lowerer {
         // Check if it's an inline arrow or function expr — lower it first
         let callback_closure_info = match &callback_arg.value {
             Expr::Arrow { params, return_type, body, .. } => {
-                self.lower_arrow_expr(ctx, params, return_type.as_deref(), body, &callback_arg.span);
+                self.lower_arrow_expr(ctx, params, return_type.as_deref(), body, false, &callback_arg.span);
                 // Get the closure info that was just registered
                 let func_name = format!("__closure_{}", self.next_closure_id - 1);
                 self.closure_bindings.get(&func_name).cloned()
             }
             Expr::Function { params, return_type, body, .. } => {
                 let arrow_body = ArrowBody::Block(Box::new(*body.clone()));
-                self.lower_arrow_expr(ctx, params, return_type.as_deref(), &arrow_body, &callback_arg.span);
+                self.lower_arrow_expr(ctx, params, return_type.as_deref(), &arrow_body, false, &callback_arg.span);
                 let func_name = format!("__closure_{}", self.next_closure_id - 1);
                 self.closure_bindings.get(&func_name).cloned()
             }
@@ -5135,10 +11789,31 @@ impl Lowerer {
                 self.collect_mutated_vars_in_expr(&left.value, local_names, mutated);
                 self.collect_mutated_vars_in_expr(&right.value, local_names, mutated);
             }
-            Expr::Unary { expr: operand, .. } => {
+            Expr::Unary { op, expr: operand } => {
+                // ++/-- write back to their operand just like an assignment
+                if matches!(
+                    op,
+                    UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement
+                ) {
+                    if let Expr::Ident(ident) = &operand.value {
+                        let name = &ident.name;
+                        if !local_names.contains(name) && self.lookup_var(name).is_some() {
+                            mutated.insert(name.clone());
+                        }
+                    }
+                }
                 self.collect_mutated_vars_in_expr(&operand.value, local_names, mutated);
             }
             Expr::Call { callee, args, .. } => {
+                // `array.push(..)` stores a grown copy back in the variable
+                if let Expr::Member { object, property, .. } = &callee.value {
+                    if let Expr::Ident(ident) = &object.value {
+                        let name = &ident.name;
+                        if property.value.name == "push" && !local_names.contains(name) && self.lookup_var(name).is_some() {
+                            mutated.insert(name.clone());
+                        }
+                    }
+                }
                 self.collect_mutated_vars_in_expr(&callee.value, local_names, mutated);
                 for arg in args {
                     self.collect_mutated_vars_in_expr(&arg.value, local_names, mutated);
@@ -5150,6 +11825,11 @@ impl Lowerer {
             Expr::Paren(inner) => {
                 self.collect_mutated_vars_in_expr(&inner.value, local_names, mutated);
             }
+            Expr::Sequence(exprs) => {
+                for expr in exprs {
+                    self.collect_mutated_vars_in_expr(&expr.value, local_names, mutated);
+                }
+            }
             Expr::Ternary { condition, then_expr, else_expr } => {
                 self.collect_mutated_vars_in_expr(&condition.value, local_names, mutated);
                 self.collect_mutated_vars_in_expr(&then_expr.value, local_names, mutated);
@@ -5217,7 +11897,7 @@ impl Lowerer {
             Expr::Unary { expr: operand, .. } => {
                 self.collect_free_vars_in_expr(&operand.value, local_names, captured, seen);
             }
-            Expr::Call { callee, args, .. } => {
+            Expr::Call { callee, args, .. } | Expr::New { callee, args, .. } => {
                 self.collect_free_vars_in_expr(&callee.value, local_names, captured, seen);
                 for arg in args {
                     self.collect_free_vars_in_expr(&arg.value, local_names, captured, seen);
@@ -5229,6 +11909,11 @@ impl Lowerer {
             Expr::Paren(inner) => {
                 self.collect_free_vars_in_expr(&inner.value, local_names, captured, seen);
             }
+            Expr::Sequence(exprs) => {
+                for expr in exprs {
+                    self.collect_free_vars_in_expr(&expr.value, local_names, captured, seen);
+                }
+            }
             Expr::Assignment { target, value, .. } => {
                 self.collect_free_vars_in_expr(&target.value, local_names, captured, seen);
                 self.collect_free_vars_in_expr(&value.value, local_names, captured, seen);
@@ -5257,7 +11942,7 @@ impl Lowerer {
                         ObjectProperty::Spread(expr) => {
                             self.collect_free_vars_in_expr(&expr.value, local_names, captured, seen);
                         }
-                        ObjectProperty::Method { .. } => {}
+                        ObjectProperty::Method { .. } | ObjectProperty::Getter { .. } | ObjectProperty::Setter { .. } => {}
                     }
                 }
             }
@@ -5272,24 +11957,171 @@ impl Lowerer {
                     self.collect_free_vars_in_expr(&arg.value, local_names, captured, seen);
                 }
             }
+            // A nested arrow's captures pass through this closure's env
+            Expr::Arrow { params, body, .. } => {
+                let mut inner_names = local_names.clone();
+                inner_names.extend(params.iter().filter_map(|p| match &p.pattern.value {
+                    Pattern::Ident { name, .. } => Some(name.value.name.clone()),
+                    _ => None,
+                }));
+                match body {
+                    ArrowBody::Expr(expr) => self.collect_free_vars_in_expr(&expr.value, &inner_names, captured, seen),
+                    ArrowBody::Block(block) => {
+                        for s in &block.value.stmts {
+                            self.collect_free_vars_in_stmt(&s.value, &inner_names, captured, seen);
+                        }
+                    }
+                }
+            }
             _ => {}
         }
     }
 
+    /// Whether a statement reads `arguments`. Nested function expressions
+    /// have their own `arguments`; arrow functions share the enclosing one.
+    fn stmt_uses_arguments(stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Throw(expr) => Self::expr_uses_arguments(&expr.value),
+            Stmt::Return(expr) => expr.as_ref().is_some_and(|e| Self::expr_uses_arguments(&e.value)),
+            Stmt::VarDecl(vd) => vd
+                .declarations
+                .iter()
+                .any(|d| d.init.as_ref().is_some_and(|init| Self::expr_uses_arguments(&init.value))),
+            Stmt::If { condition, then_stmt, else_stmt } => {
+                Self::expr_uses_arguments(&condition.value)
+                    || Self::stmt_uses_arguments(&then_stmt.value)
+                    || else_stmt.as_ref().is_some_and(|s| Self::stmt_uses_arguments(&s.value))
+            }
+            Stmt::Block(block) => block.stmts.iter().any(|s| Self::stmt_uses_arguments(&s.value)),
+            Stmt::For { init, condition, update, body } => {
+                let init_uses = match init {
+                    Some(ForInit::Expr(expr)) => Self::expr_uses_arguments(&expr.value),
+                    Some(ForInit::VarDecl(vd)) => Self::stmt_uses_arguments(&Stmt::VarDecl(vd.clone())),
+                    None => false,
+                };
+                init_uses
+                    || condition.as_ref().is_some_and(|c| Self::expr_uses_arguments(&c.value))
+                    || update.as_ref().is_some_and(|u| Self::expr_uses_arguments(&u.value))
+                    || Self::stmt_uses_arguments(&body.value)
+            }
+            Stmt::ForIn { right, body, .. } | Stmt::ForOf { right, body, .. } => {
+                Self::expr_uses_arguments(&right.value) || Self::stmt_uses_arguments(&body.value)
+            }
+            Stmt::While { condition, body } | Stmt::DoWhile { body, condition } => {
+                Self::expr_uses_arguments(&condition.value) || Self::stmt_uses_arguments(&body.value)
+            }
+            Stmt::Labeled { stmt, .. } => Self::stmt_uses_arguments(&stmt.value),
+            _ => false,
+        }
+    }
+
+    fn expr_uses_arguments(expr: &Expr) -> bool {
+        let uses = |e: &Node<Expr>| Self::expr_uses_arguments(&e.value);
+        match expr {
+            Expr::Ident(ident) => ident.name == "arguments",
+            Expr::Binary { left, right, .. } => uses(left) || uses(right),
+            Expr::Unary { expr: operand, .. } => uses(operand),
+            Expr::Assignment { target, value, .. } => uses(target) || uses(value),
+            Expr::Call { callee, args, .. } => uses(callee) || args.iter().any(uses),
+            Expr::Member { object, .. } => uses(object),
+            Expr::Index { object, index } => uses(object) || uses(index),
+            Expr::Paren(inner) => uses(inner),
+            Expr::Sequence(exprs) => exprs.iter().any(uses),
+            Expr::Ternary { condition, then_expr, else_expr } => {
+                uses(condition) || uses(then_expr) || uses(else_expr)
+            }
+            Expr::Template { exprs, .. } => exprs.iter().any(uses),
+            Expr::Array(elements) => elements.iter().flatten().any(uses),
+            Expr::Arrow { body, .. } => match body {
+                ArrowBody::Expr(e) => uses(e),
+                ArrowBody::Block(block) => block.value.stmts.iter().any(|s| Self::stmt_uses_arguments(&s.value)),
+            },
+            _ => false,
+        }
+    }
+
     // =========================================================================
     // Type inference helpers
     // =========================================================================
 
+    /// Result type of `object.method(...)` for string and class instance receivers.
+    fn infer_method_call_type(&self, object: &Expr, method: &str) -> IrType {
+        if let Expr::Ident(ident) = object {
+            if self.sqlite_databases.contains(&ident.name) {
+                return match method {
+                    "query" => IrType::Array(Box::new(IrType::Ptr)),
+                    _ => IrType::Void,
+                };
+            }
+            if let (Some(types), "next") = (self.generator_vars.get(&ident.name), method) {
+                return if types.is_async { IrType::Promise(Box::new(IrType::Ptr)) } else { IrType::Ptr };
+            }
+            if self.fetch_responses.contains(&ident.name) {
+                return match method {
+                    "text" => IrType::Promise(Box::new(IrType::Str)),
+                    _ => IrType::Promise(Box::new(IrType::Ptr)),
+                };
+            }
+            // path.join(...) through `import * as path`
+            if let Some(module) = self.imported_bindings.get(&ident.name).filter(|_| self.lookup_var(&ident.name).is_none()) {
+                if let Some((_, ret_type)) = self.module_exports.get(module).and_then(|exports| exports.functions.get(method)) {
+                    return ret_type.clone();
+                }
+                if let Some((_, _, ret_type)) = Self::imported_func_signature(module, method) {
+                    return ret_type;
+                }
+            }
+        }
+        // res.headers.get(name)
+        if let Expr::Member { object: response, property, .. } = object {
+            if property.value.name == "headers" && self.is_fetch_response(&response.value) {
+                return IrType::Str;
+            }
+        }
+        // ClassName.staticMethod(...)
+        if let Expr::Ident(ident) = object {
+            if self.class_info.contains_key(&ident.name) && self.lookup_var(&ident.name).is_none() {
+                if let Some(f) = self.module.find_function(&format!("{}_{}", ident.name, method)) {
+                    return f.return_type.clone();
+                }
+            }
+        }
+        match self.infer_expr_type(object) {
+            array @ IrType::Array(_) if matches!(method, "reverse" | "copyWithin" | "concat") => array,
+            IrType::Str => Self::string_method_signature(method)
+                .map(|(_, _, _, result_ty)| result_ty)
+                .unwrap_or(IrType::F64),
+            IrType::Struct(struct_id) => self
+                .class_name_for_struct(struct_id)
+                .and_then(|class_name| self.module.find_function(&format!("{}_{}", class_name, method)))
+                .map(|f| f.return_type.clone())
+                .unwrap_or(IrType::F64),
+            _ => IrType::F64,
+        }
+    }
+
     fn infer_expr_type(&self, expr: &Expr) -> IrType {
+        if let Some(expr) = self
+            .strip_global_this(expr)
+            .or_else(|| self.symbol_member(expr))
+            .or_else(|| self.namespace_member(expr))
+            .or_else(|| self.array_of_literal(expr))
+            .or_else(|| self.define_literal(expr))
+        {
+            return self.infer_expr_type(&expr);
+        }
+        if let Some((_, elem)) = self.array_constructor(expr) {
+            return IrType::Array(Box::new(elem.unwrap_or(IrType::F64)));
+        }
         match expr {
             Expr::Literal(Literal::Number(_)) => IrType::F64,
             Expr::Literal(Literal::String(_)) => IrType::Str,
             Expr::Literal(Literal::Boolean(_)) => IrType::Bool,
-            Expr::Literal(Literal::Null | Literal::Undefined) => IrType::Ptr,
+            Expr::Literal(Literal::Null | Literal::Undefined | Literal::RegExp { .. }) => IrType::Ptr,
             Expr::Template { .. } => IrType::Str,
-            Expr::TaggedTemplate { .. } => IrType::Ptr,
-            Expr::Yield { .. } => IrType::Ptr,
-            Expr::Binary { left, op, .. } => {
+            Expr::TaggedTemplate { tag, .. } => self.infer_tagged_template_type(&tag.value),
+            Expr::Yield { .. } => self.current_generator.as_ref().map_or(IrType::Ptr, |g| g.next_ty.clone()),
+            Expr::Binary { left, op, right } => {
                 if matches!(
                     op,
                     BinaryOp::Eq
@@ -5304,6 +12136,14 @@ impl Lowerer {
                         | BinaryOp::InstanceOf
                 ) {
                     IrType::Bool
+                } else if *op == BinaryOp::Add && self.infer_expr_type(&right.value) == IrType::Str {
+                    // `1 + "a"` concatenates
+                    IrType::Str
+                } else if matches!(self.infer_expr_type(&left.value), IrType::Struct(_))
+                    && matches!(op, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod)
+                {
+                    // Arithmetic on an instance uses its `valueOf()`
+                    IrType::F64
                 } else {
                     // For && and ||, the result type is the operand type
                     // (they return one of the operands, not a boolean)
@@ -5321,47 +12161,111 @@ impl Lowerer {
                     IrType::F64 // default: TypeScript number is f64
                 }
             }
-            Expr::Paren(inner) => self.infer_expr_type(&inner.value),
-            Expr::Array(_) => IrType::Array(Box::new(IrType::F64)),
+            Expr::Paren(inner)
+            | Expr::NonNullAssertion(inner)
+            | Expr::TypeCast { expr: inner, .. }
+            | Expr::Satisfies { expr: inner, .. } => self.infer_expr_type(&inner.value),
+            Expr::Sequence(exprs) => exprs
+                .last()
+                .map(|last| self.infer_expr_type(&last.value))
+                .unwrap_or(IrType::Void),
+            Expr::Await(inner) => self.awaited_type(&inner.value),
+            // Arrays of promises keep their element type for `for await`
+            Expr::Array(elems) => match elems.iter().flatten().next().map(|first| match &first.value {
+                Expr::Spread(inner) => self.iterable_element_type(&inner.value),
+                first => self.infer_expr_type(first),
+            }) {
+                // `[...iterable]` holds what iterating it produces
+                Some(elem) if matches!(elems.iter().flatten().next(), Some(Node { value: Expr::Spread(_), .. })) => {
+                    IrType::Array(Box::new(elem))
+                }
+                Some(promise @ IrType::Promise(_)) => IrType::Array(Box::new(promise)),
+                _ => IrType::Array(Box::new(IrType::F64)),
+            },
             Expr::Object(_) => IrType::Ptr,
-            Expr::Call { callee, .. } => {
+            Expr::Call { callee, args, .. } => {
+                // `obj?.method(args)` has the type of the call it may skip
+                if let Expr::OptionalMember { object, property } = &callee.value {
+                    let method = Expr::Member { object: object.clone(), property: property.clone(), computed: false };
+                    return self.infer_expr_type(&Expr::Call {
+                        callee: Box::new(Node::new(method, callee.span)),
+                        type_args: None,
+                        args: args.clone(),
+                    });
+                }
                 // Infer return type from known built-in calls
                 if let Expr::Member { object, property, .. } = &callee.value {
+                    // A property holding a closure value
+                    if let IrType::FuncPtr(sig) = self.infer_expr_type(&callee.value) {
+                        return *sig.return_type;
+                    }
+                    if let Some(ty) = self.promise_chain_type(&object.value, &property.value.name, args) {
+                        return ty;
+                    }
+                    if property.value.name == "fill" {
+                        if let Some(ty) = self.array_fill_type(&object.value, args) {
+                            return ty;
+                        }
+                    }
                     if let Expr::Ident(obj_ident) = &object.value {
                         match obj_ident.name.as_str() {
                             "Math" => IrType::F64, // All Math methods return f64
                             "JSON" => IrType::Str, // JSON.parse/stringify return strings
-                            _ if {
-                                // Check if it's a Promise method call
-                                if let Some(info) = self.lookup_var(&obj_ident.name) {
-                                    matches!(info.ir_type, IrType::Promise(_))
-                                        && matches!(property.value.name.as_str(), "then" | "catch" | "finally")
-                                } else {
-                                    false
-                                }
-                            } => IrType::Ptr, // Promise chain methods return a new promise (Ptr)
-                            _ => {
-                                // Check if it's a method call on a class instance
-                                if let Some(info) = self.lookup_var(&obj_ident.name) {
-                                    if let IrType::Struct(struct_id) = &info.ir_type {
-                                        // Find the class for this struct
-                                        if let Some((class_name, _)) = self.class_info.iter()
-                                            .find(|(_, ci)| ci.struct_id == *struct_id)
-                                        {
-                                            let method_func_name = format!("{}_{}", class_name, property.value.name);
-                                            if let Some(func) = self.module.find_function(&method_func_name) {
-                                                return func.return_type.clone();
-                                            }
-                                        }
-                                    }
-                                }
-                                IrType::F64
+                            "Object" if self.lookup_var("Object").is_none() => match property.value.name.as_str() {
+                                "keys" => IrType::Array(Box::new(IrType::Str)),
+                                "values" => match self.expr_value_type(expr) {
+                                    Some(ty) => self.ast_type_to_ir(&ty),
+                                    None => IrType::Array(Box::new(IrType::F64)),
+                                },
+                                "entries" => IrType::Array(Box::new(IrType::Array(Box::new(IrType::Ptr)))),
+                                "freeze" => args.first().map_or(IrType::Ptr, |arg| self.infer_expr_type(&arg.value)),
+                                _ => IrType::Ptr,
+                            },
+                            "Array" if property.value.name == "from" && self.lookup_var("Array").is_none() => {
+                                self.array_from_type(args)
                             }
+                            // Promise.all([p, ...]) resolves with an array of the promises' values
+                            "Promise" if property.value.name == "all" => {
+                                let elem = match args.first().map(|a| &a.value) {
+                                    Some(Expr::Array(elems)) => elems
+                                        .iter()
+                                        .flatten()
+                                        .next()
+                                        .map(|e| self.awaited_type(&e.value))
+                                        .unwrap_or(IrType::Ptr),
+                                    _ => IrType::Ptr,
+                                };
+                                IrType::Promise(Box::new(IrType::Array(Box::new(elem))))
+                            }
+                            "AbortSignal" if property.value.name == "timeout" => IrType::Ptr,
+                            _ => self.infer_method_call_type(&object.value, &property.value.name),
                         }
                     } else {
-                        IrType::F64
+                        self.infer_method_call_type(&object.value, &property.value.name)
                     }
                 } else if let Expr::Ident(func_ident) = &callee.value {
+                    // structuredClone(value) has the type of its argument
+                    if self.is_structured_clone_call(expr) {
+                        return self.infer_expr_type(&args[0].value);
+                    }
+                    // URI and base64 encoding produce strings
+                    if matches!(func_ident.name.as_str(), "encodeURIComponent" | "decodeURIComponent" | "btoa" | "atob") {
+                        return IrType::Str;
+                    }
+                    // fetch(url, options?) resolves with a Response
+                    if func_ident.name == "fetch" && self.lookup_var("fetch").is_none() {
+                        return IrType::Promise(Box::new(IrType::Ptr));
+                    }
+                    // spawn("name", args) resolves with the worker function's result
+                    if self.imported_bindings.get(&func_ident.name).is_some_and(|m| m == "worker")
+                        && func_ident.name == "spawn"
+                    {
+                        let ret = match args.first().map(|a| &a.value) {
+                            Some(Expr::Literal(Literal::String(name))) => self.worker_return_type(name),
+                            _ => IrType::Ptr,
+                        };
+                        return IrType::Promise(Box::new(ret));
+                    }
                     // Look up user-defined function return type
                     // Handle renamed user main
                     let lookup_name = if func_ident.name == "main" && self.has_user_main {
@@ -5369,19 +12273,17 @@ impl Lowerer {
                     } else {
                         func_ident.name.clone()
                     };
-                    self.module.find_function(&lookup_name)
-                        .map(|f| f.return_type.clone())
+                    self.function_return_type(&lookup_name)
                         .or_else(|| {
-                            // Check if this is a recursive call to the current function
-                            if let Some((ref cur_name, ref cur_ret)) = self.current_function {
-                                if *cur_name == lookup_name {
-                                    return Some(cur_ret.clone());
-                                }
-                            }
-                            None
+                            // A variable bound to a closure returns what the closure does
+                            let closure = self.closure_bindings.get(&func_ident.name)?;
+                            self.function_return_type(&closure.func_name)
                         })
                         .or_else(|| {
                             // Check if this is an imported function call
+                            if let Some((_, _, ret_type)) = self.imported_functions.get(&func_ident.name) {
+                                return Some(ret_type.clone());
+                            }
                             if let Some(module) = self.imported_bindings.get(&func_ident.name) {
                                 if let Some((_, _, ret_type)) = Self::imported_func_signature(module, &func_ident.name) {
                                     return Some(ret_type);
@@ -5389,18 +12291,61 @@ impl Lowerer {
                             }
                             None
                         })
+                        .or_else(|| match &self.lookup_var(&func_ident.name)?.ir_type {
+                            // A variable holding a closure value
+                            IrType::FuncPtr(sig) => Some((*sig.return_type).clone()),
+                            _ => None,
+                        })
                         .unwrap_or(IrType::F64)
+                } else if let Some(func_expr) = Self::unwrap_function_expr(&callee.value) {
+                    self.infer_function_expr_return_type(func_expr)
+                } else if let IrType::FuncPtr(sig) = self.infer_expr_type(&callee.value) {
+                    *sig.return_type
                 } else {
                     IrType::F64
                 }
             }
             Expr::Member { object, property, .. } => {
+                if self.import_meta_property(&object.value, &property.value.name).is_some() {
+                    return IrType::Str;
+                }
+                match self.enum_member_value(&object.value, &property.value.name) {
+                    Some(EnumValue::Number(_)) => return IrType::F64,
+                    Some(EnumValue::String(_)) => return IrType::Str,
+                    None => {}
+                }
+                if self.well_known_symbol(object, property).is_some() {
+                    return IrType::Ptr;
+                }
+                if self.is_abort_signal(&object.value) {
+                    match property.value.name.as_str() {
+                        "signal" => return IrType::Ptr,
+                        "aborted" => return IrType::Bool,
+                        _ => {}
+                    }
+                }
+                if let Expr::Member { object: inner, property: stream, .. } = &object.value {
+                    if matches!(&inner.value, Expr::Ident(obj) if obj.name == "process") && stream.value.name == "stdout" {
+                        match property.value.name.as_str() {
+                            "isTTY" => return IrType::Bool,
+                            "columns" => return IrType::F64,
+                            _ => {}
+                        }
+                    }
+                }
+                // strings.raw on a tag function's TemplateStringsArray
+                if property.value.name == "raw"
+                    && self.infer_expr_type(&object.value) == IrType::Array(Box::new(IrType::Str))
+                {
+                    return IrType::Array(Box::new(IrType::Str));
+                }
                 // Infer type of member access (e.g., Math.PI)
                 if let Expr::Ident(obj_ident) = &object.value {
                     match (obj_ident.name.as_str(), property.value.name.as_str()) {
                         ("Math", "PI" | "E") => IrType::F64,
                         ("process", "pid") => IrType::I64,
                         ("process", _) => IrType::Str,
+                        (_, "ok") if self.fetch_responses.contains(&obj_ident.name) => IrType::Bool,
                         _ => {
                             // Check if it's a static property on a class
                             if let Some(ci) = self.class_info.get(&obj_ident.name) {
@@ -5431,7 +12376,8 @@ impl Lowerer {
                                     }
                                 }
                             }
-                            IrType::F64
+                            self.object_field_type(&object.value, &property.value.name)
+                                .unwrap_or(IrType::F64)
                         }
                     }
                 } else if matches!(&object.value, Expr::This) {
@@ -5445,9 +12391,11 @@ impl Lowerer {
                             }
                         }
                     }
-                    IrType::F64
+                    self.object_field_type(&object.value, &property.value.name)
+                        .unwrap_or(IrType::F64)
                 } else {
-                    IrType::F64
+                    self.object_field_type(&object.value, &property.value.name)
+                        .unwrap_or(IrType::F64)
                 }
             }
             Expr::New { callee, .. } => {
@@ -5467,15 +12415,15 @@ impl Lowerer {
                     IrType::Ptr
                 }
             }
-            Expr::Arrow { .. } => {
-                // Arrow function — type is a function reference stored as Ptr
-                IrType::Ptr
-            }
-            Expr::Function { .. } => IrType::Ptr,
+            Expr::Arrow { params, .. } | Expr::Function { params, .. } => IrType::FuncPtr(FuncSignature {
+                params: params.iter().map(|param| self.infer_param_type(param)).collect(),
+                return_type: Box::new(self.infer_function_expr_return_type(expr)),
+            }),
             Expr::Unary { op, expr: operand } => {
                 match op {
                     UnaryOp::Not | UnaryOp::Delete => IrType::Bool,
                     UnaryOp::Void => IrType::Ptr,
+                    UnaryOp::TypeOf => IrType::Str,
                     _ => self.infer_expr_type(&operand.value),
                 }
             }
@@ -5497,7 +12445,10 @@ impl Lowerer {
                     args: args.clone(),
                 })
             }
-            Expr::OptionalIndex { object, .. } => {
+            Expr::Index { object, index } | Expr::OptionalIndex { object, index } => {
+                if let Some(ty) = self.tuple_index_type(&object.value, &index.value) {
+                    return ty;
+                }
                 let obj_ty = self.infer_expr_type(&object.value);
                 if let IrType::Array(elem) = obj_ty {
                     *elem
@@ -5510,16 +12461,45 @@ impl Lowerer {
     }
 
     fn infer_param_type(&self, param: &Param) -> IrType {
-        // Check Param-level type annotation first
-        if let Some(ref ty) = param.type_annotation {
-            return self.ast_type_to_ir(&ty.value);
+        match Self::param_annotation(param) {
+            Some(ty) => self.ast_type_to_ir(&ty.value),
+            None => IrType::F64, // default: TypeScript number is f64
         }
-        // Fall back to Pattern::Ident-level type annotation
-        // (the parser may place the annotation on the pattern instead of the param)
-        if let Pattern::Ident { type_annotation: Some(ref ty), .. } = param.pattern.value {
-            return self.ast_type_to_ir(&ty.value);
+    }
+
+    /// A parameter's type annotation. The parser may place it on the
+    /// pattern instead of the param.
+    fn param_annotation(param: &Param) -> Option<&Node<Type>> {
+        match &param.pattern.value {
+            _ if param.type_annotation.is_some() => param.type_annotation.as_deref(),
+            Pattern::Ident { type_annotation, .. } => type_annotation.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Compile-time value of `Enum.Member`, unless a variable shadows the enum
+    fn enum_member_value(&self, object: &Expr, member: &str) -> Option<EnumValue> {
+        let Expr::Ident(ident) = object else {
+            return None;
+        };
+        if self.lookup_var(&ident.name).is_some() {
+            return None;
+        }
+        let info = self.enums.get(&ident.name)?;
+        info.members.iter().find(|(name, _)| name == member)?.1.clone()
+    }
+
+    /// Type of an enum's values: F64 when all its members are numbers, Str
+    /// when all are strings, and otherwise Ptr
+    fn enum_ir_type(&self, name: &str) -> Option<IrType> {
+        let members = &self.enums.get(name)?.members;
+        if members.iter().all(|(_, value)| matches!(value, Some(EnumValue::Number(_)))) {
+            Some(IrType::F64)
+        } else if members.iter().all(|(_, value)| matches!(value, Some(EnumValue::String(_)))) {
+            Some(IrType::Str)
+        } else {
+            Some(IrType::Ptr)
         }
-        IrType::F64 // default: TypeScript number is f64
     }
 
     fn ast_type_to_ir(&self, ty: &Type) -> IrType {
@@ -5549,6 +12529,16 @@ impl Lowerer {
                     "string" => IrType::Str,
                     "boolean" => IrType::Bool,
                     "void" => IrType::Void,
+                    // Polymorphic `this`: the class whose method is being lowered
+                    "this" => self
+                        .current_class
+                        .as_ref()
+                        .and_then(|class_name| self.class_info.get(class_name))
+                        .map(|ci| IrType::Struct(ci.struct_id))
+                        .unwrap_or(IrType::Ptr),
+                    "TemplateStringsArray" if !self.class_info.contains_key("TemplateStringsArray") => {
+                        IrType::Array(Box::new(IrType::Str))
+                    }
                     "Promise" => {
                         // Promise without type args → Promise<any>
                         if let Some(args) = type_args {
@@ -5563,12 +12553,27 @@ impl Lowerer {
                         // Check if this is a known class name
                         if let Some(ci) = self.class_info.get(name.value.name.as_str()) {
                             IrType::Struct(ci.struct_id)
+                        } else if let Some(ty) = self.enum_ir_type(&name.value.name) {
+                            ty
                         } else {
                             IrType::Ptr // Unknown types → pointer
                         }
                     }
                 }
             }
+            Type::Paren(inner) => self.ast_type_to_ir(&inner.value),
+            // `C | null` of a class C is its struct: instances are pointers,
+            // so null is still representable
+            Type::Union(_) => match self.resolve_value_type(ty) {
+                Type::TypeRef { name, .. } if self.class_info.contains_key(name.value.name.as_str()) => {
+                    self.ast_type_to_ir(&Type::TypeRef { name, type_args: None })
+                }
+                _ => IrType::Ptr,
+            },
+            Type::Function(func) => IrType::FuncPtr(FuncSignature {
+                params: func.params.iter().map(|param| self.ast_type_to_ir(&param.ty.value)).collect(),
+                return_type: Box::new(self.ast_type_to_ir(&func.return_type.value)),
+            }),
             _ => IrType::Ptr,
         }
     }
@@ -5697,6 +12702,7 @@ mod tests {
                     ownership: None,
                     optional: false,
                     is_rest: false,
+                    property: None,
                 },
                 Param {
                     pattern: Node::new(
@@ -5714,6 +12720,7 @@ mod tests {
                     ownership: None,
                     optional: false,
                     is_rest: false,
+                    property: None,
                 },
             ],
             return_type: Some(Box::new(Node::new(
@@ -5761,6 +12768,46 @@ mod tests {
         assert!(module.find_function("main").is_some());
     }
 
+    #[test]
+    fn test_instrument_wraps_user_functions() {
+        // function f(): void {}
+        let func_decl = Decl::Function(FunctionDecl {
+            name: Node::new(Ident::new("f"), dummy_span()),
+            type_params: None,
+            params: vec![],
+            return_type: None,
+            body: Some(Node::new(BlockStmt { stmts: vec![] }, dummy_span())),
+            is_async: false,
+            is_generator: false,
+            is_declare: false,
+        });
+        let program = make_program(vec![make_decl_item(func_decl)]);
+
+        let module = Lowerer::new()
+            .with_instrument(true)
+            .lower_program(&program)
+            .expect("lowering failed");
+
+        let is_hook = |instr: &Instruction, hook: &str| matches!(
+            instr,
+            Instruction::Call { func: Value::Const(Constant::Str(name)), args, .. }
+                if name == hook && args == &vec![Value::Const(Constant::Str("f".to_string()))]
+        );
+        let f = module.find_function("f").expect("f function not found");
+        let entry = f.block(f.entry_block);
+        assert!(is_hook(&entry.instructions[0], "zaco_profile_enter"));
+        for block in f.blocks.iter().filter(|b| matches!(b.terminator, Terminator::Return(_))) {
+            assert!(is_hook(block.instructions.last().unwrap(), "zaco_profile_exit"));
+        }
+
+        // The module wrapper is not itself profiled
+        let main = module.find_function("main").unwrap();
+        assert!(main.blocks.iter().flat_map(|b| &b.instructions).all(|i| {
+            !matches!(i, Instruction::Call { func: Value::Const(Constant::Str(name)), .. }
+                if name == "zaco_profile_enter")
+        }));
+    }
+
     #[test]
     fn test_lower_math_floor() {
         // let x = Math.floor(3.7);
@@ -5921,6 +12968,7 @@ mod tests {
                 ownership: None,
                 optional: false,
                 is_rest: false,
+                property: None,
             }],
             return_type: Some(Box::new(Node::new(
                 Type::Primitive(PrimitiveType::String),
@@ -6109,6 +13157,11 @@ mod tests {
             module.extern_functions.iter().any(|f| f.name == "zaco_async_block_on"),
             "zaco_async_block_on should be in extern functions"
         );
+        // ...followed by zaco_await_rethrow so rejections reach try/catch
+        assert!(
+            module.extern_functions.iter().any(|f| f.name == "zaco_await_rethrow"),
+            "zaco_await_rethrow should be in extern functions"
+        );
     }
 
     #[test]
@@ -6310,6 +13363,7 @@ mod tests {
                         params: vec![],
                         return_type: None,
                         body: closure_body,
+                        is_async: false,
                     },
                     dummy_span(),
                 )),
@@ -6373,6 +13427,7 @@ mod tests {
                             Expr::Ident(Ident::new("x")),
                             dummy_span(),
                         ))),
+                        is_async: false,
                     },
                     dummy_span(),
                 )),
@@ -6497,12 +13552,14 @@ mod tests {
                             ownership: None,
                             optional: false,
                             is_rest: false,
+                            property: None,
                         }],
                         return_type: None,
                         body: ArrowBody::Block(Box::new(Node::new(
                             BlockStmt { stmts: vec![] },
                             dummy_span(),
                         ))),
+                        is_async: false,
                     },
                     dummy_span(),
                 )],
@@ -6522,11 +13579,254 @@ mod tests {
 
         let module = result.unwrap();
 
-        // Should have extern function for zaco_promise_then
+        // The callback is called inline once the promise has settled
         assert!(
-            module.extern_functions.iter().any(|f| f.name == "zaco_promise_then"),
-            "zaco_promise_then should be declared"
+            module.extern_functions.iter().any(|f| f.name == "zaco_promise_is_rejected"),
+            "zaco_promise_is_rejected should be declared"
         );
+        let main = module.find_function("main").unwrap();
+        let calls: Vec<&str> = main
+            .blocks
+            .iter()
+            .flat_map(|b| &b.instructions)
+            .filter_map(|i| match i {
+                Instruction::Call { func: Value::Const(Constant::Str(name)), .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(calls.contains(&"__closure_0"), "callback not called: {:?}", calls);
+        // A program using promises reports unhandled rejections on exit
+        assert!(calls.contains(&"zaco_check_unhandled_rejections"), "got {:?}", calls);
+    }
+
+
+    /// The index of each kind of statement, so a new kind fails to compile
+    /// until it has a sample below
+    fn stmt_kind(stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::Expr(_) => 0,
+            Stmt::VarDecl(_) => 1,
+            Stmt::Return(_) => 2,
+            Stmt::If { .. } => 3,
+            Stmt::For { .. } => 4,
+            Stmt::ForIn { .. } => 5,
+            Stmt::ForOf { .. } => 6,
+            Stmt::While { .. } => 7,
+            Stmt::DoWhile { .. } => 8,
+            Stmt::Block(_) => 9,
+            Stmt::Break(_) => 10,
+            Stmt::Continue(_) => 11,
+            Stmt::Throw(_) => 12,
+            Stmt::Try { .. } => 13,
+            Stmt::Switch { .. } => 14,
+            Stmt::Labeled { .. } => 15,
+            Stmt::Empty => 16,
+            Stmt::Debugger => 17,
+        }
+    }
+
+    /// The index of each kind of expression (see `stmt_kind`)
+    fn expr_kind(expr: &Expr) -> usize {
+        match expr {
+            Expr::Literal(_) => 0,
+            Expr::Ident(_) => 1,
+            Expr::Binary { .. } => 2,
+            Expr::Unary { .. } => 3,
+            Expr::Assignment { .. } => 4,
+            Expr::Call { .. } => 5,
+            Expr::Member { .. } => 6,
+            Expr::Index { .. } => 7,
+            Expr::Array(_) => 8,
+            Expr::Object(_) => 9,
+            Expr::Arrow { .. } => 10,
+            Expr::Function { .. } => 11,
+            Expr::Ternary { .. } => 12,
+            Expr::Template { .. } => 13,
+            Expr::New { .. } => 14,
+            Expr::TypeCast { .. } => 15,
+            Expr::Await(_) => 16,
+            Expr::Paren(_) => 17,
+            Expr::This => 18,
+            Expr::Super => 19,
+            Expr::Clone(_) => 20,
+            Expr::Sequence(_) => 21,
+            Expr::Spread(_) => 22,
+            Expr::OptionalCall { .. } => 23,
+            Expr::OptionalIndex { .. } => 24,
+            Expr::OptionalMember { .. } => 25,
+            Expr::TaggedTemplate { .. } => 26,
+            Expr::Satisfies { .. } => 27,
+            Expr::NonNullAssertion(_) => 28,
+            Expr::MetaProperty { .. } => 29,
+            Expr::Yield { .. } => 30,
+        }
+    }
+
+    /// Lower `lower` in a fresh `main` with a number variable `x` in scope,
+    /// inside a loop so `break` and `continue` have somewhere to go.
+    /// Returns whether the function changed and the errors reported.
+    fn lower_in_main(lower: impl FnOnce(&mut Lowerer, &mut FuncCtx) -> bool) -> (bool, Vec<LowerError>) {
+        let mut lowerer = Lowerer::new();
+        let mut func = IrFunction::new(FuncId(0), "main".to_string(), vec![], IrType::I64);
+        let entry = func.new_block();
+        let mut ctx = FuncCtx { func: &mut func, current_block: entry };
+        lowerer.push_scope();
+        let x = ctx.add_local(IrType::F64);
+        lowerer.define_var("x", VarInfo { local_id: x, ir_type: IrType::F64, is_boxed: false });
+        lowerer.loop_stack.push((entry, entry));
+        lowerer.break_stack.push(entry);
+        let before = format!("{:?}", ctx.func);
+        let produced = lower(&mut lowerer, &mut ctx);
+        let changed = produced || format!("{:?}", ctx.func) != before;
+        (changed, lowerer.errors)
     }
 
+    /// Every kind of statement and expression is either lowered or reported
+    /// as unsupported; none may drop out of the program silently
+    #[test]
+    fn test_every_construct_is_lowered_or_rejected() {
+        let span = dummy_span();
+        let node = |expr: Expr| Node::new(expr, dummy_span());
+        let x = || Box::new(node(Expr::Ident(Ident::new("x"))));
+        let num = |n: f64| Box::new(node(Expr::Literal(Literal::Number(n))));
+        let ident = |name: &str| Node::new(Ident::new(name), dummy_span());
+        let block = |stmts: Vec<Stmt>| BlockStmt { stmts: stmts.into_iter().map(|s| Node::new(s, dummy_span())).collect() };
+        let binding = |name: &str| {
+            Node::new(Pattern::Ident { name: ident(name), type_annotation: None, ownership: None }, dummy_span())
+        };
+        let let_decl = |name: &str, init: Expr| VarDecl {
+            kind: VarDeclKind::Let,
+            declarations: vec![VarDeclarator { pattern: binding(name), init: Some(node(init)) }],
+        };
+        let log = |arg: Box<Node<Expr>>| Expr::Call {
+            callee: Box::new(node(Expr::Member { object: Box::new(node(Expr::Ident(Ident::new("console")))), property: ident("log"), computed: false })),
+            type_args: None,
+            args: vec![*arg],
+        };
+        let array = || Box::new(node(Expr::Array(vec![Some(*num(1.0))])));
+        let number_type = || Box::new(Node::new(Type::Primitive(PrimitiveType::Number), dummy_span()));
+        let function_body = || Box::new(Node::new(block(vec![Stmt::Return(Some(*x()))]), dummy_span()));
+
+        let exprs = vec![
+            Expr::Literal(Literal::Number(1.0)),
+            Expr::Ident(Ident::new("x")),
+            Expr::Binary { left: x(), op: BinaryOp::Add, right: num(1.0) },
+            Expr::Unary { op: UnaryOp::Minus, expr: x() },
+            Expr::Assignment { target: x(), op: AssignmentOp::Assign, value: num(2.0) },
+            log(x()),
+            Expr::Member { object: array(), property: ident("length"), computed: false },
+            Expr::Index { object: array(), index: num(0.0) },
+            Expr::Array(vec![Some(*num(1.0))]),
+            Expr::Object(vec![ObjectProperty::Property { key: PropertyName::Ident(ident("a")), value: *num(1.0), shorthand: false }]),
+            Expr::Arrow { type_params: None, params: vec![], return_type: None, body: ArrowBody::Expr(x()), is_async: false },
+            Expr::Function { name: None, type_params: None, params: vec![], return_type: None, body: function_body(), is_async: false },
+            Expr::Ternary { condition: x(), then_expr: num(1.0), else_expr: num(2.0) },
+            Expr::Template { parts: vec!["x = ".to_string(), String::new()], exprs: vec![*x()] },
+            Expr::New { callee: Box::new(node(Expr::Ident(Ident::new("AbortController")))), type_args: None, args: vec![] },
+            Expr::TypeCast { expr: x(), ty: number_type() },
+            Expr::Await(x()),
+            Expr::Paren(x()),
+            Expr::This,
+            Expr::Super,
+            Expr::Clone(x()),
+            Expr::Sequence(vec![*num(1.0), *x()]),
+            Expr::Spread(array()),
+            Expr::OptionalCall {
+                callee: Box::new(node(Expr::Arrow { type_params: None, params: vec![], return_type: None, body: ArrowBody::Expr(x()), is_async: false })),
+                type_args: None,
+                args: vec![],
+            },
+            Expr::OptionalIndex { object: array(), index: num(0.0) },
+            Expr::OptionalMember { object: array(), property: ident("length") },
+            Expr::TaggedTemplate {
+                tag: Box::new(node(Expr::Member { object: Box::new(node(Expr::Ident(Ident::new("String")))), property: ident("raw"), computed: false })),
+                parts: vec!["a".to_string()],
+                raw: vec!["a".to_string()],
+                exprs: vec![],
+            },
+            Expr::Satisfies { expr: x(), ty: number_type() },
+            Expr::NonNullAssertion(x()),
+            Expr::MetaProperty { meta: ident("new"), property: ident("target") },
+            Expr::Yield { argument: Some(x()), delegate: false },
+        ];
+        let kinds: HashSet<usize> = exprs.iter().map(expr_kind).collect();
+        assert_eq!(kinds.len(), exprs.len(), "one sample per kind of expression");
+
+        for expr in &exprs {
+            let (lowered, errors) = lower_in_main(|lowerer, ctx| lowerer.lower_expr(ctx, expr, &span).is_some());
+            assert!(lowered || !errors.is_empty(), "{:?} was dropped without an error", expr);
+        }
+
+        // Every assignment operator on every kind of target
+        let object = || Box::new(node(Expr::Object(vec![ObjectProperty::Property { key: PropertyName::Ident(ident("a")), value: *num(1.0), shorthand: false }])));
+        let targets = vec![
+            *x(),
+            node(Expr::Paren(x())),
+            node(Expr::Index { object: array(), index: num(0.0) }),
+            node(Expr::Index { object: x(), index: num(0.0) }),
+            node(Expr::Member { object: object(), property: ident("a"), computed: false }),
+            node(Expr::Call { callee: x(), type_args: None, args: vec![] }),
+        ];
+        use AssignmentOp::*;
+        let ops = [
+            Assign, AddAssign, SubAssign, MulAssign, DivAssign, ModAssign, PowAssign, LeftShiftAssign, RightShiftAssign,
+            UnsignedRightShiftAssign, BitAndAssign, BitOrAssign, BitXorAssign, AndAssign, OrAssign, NullishAssign,
+        ];
+        for target in &targets {
+            for op in ops {
+                let expr = Expr::Assignment { target: Box::new(target.clone()), op, value: num(2.0) };
+                let (lowered, errors) = lower_in_main(|lowerer, ctx| lowerer.lower_expr(ctx, &expr, &span).is_some());
+                assert!(lowered || !errors.is_empty(), "{:?} was dropped without an error", expr);
+            }
+        }
+
+        // Calls of a method the receiver lacks and of an unknown import
+        let method_call = Expr::Call {
+            callee: Box::new(node(Expr::Member { object: x(), property: ident("frobnicate"), computed: false })),
+            type_args: None,
+            args: vec![],
+        };
+        let (lowered, errors) = lower_in_main(|lowerer, ctx| lowerer.lower_expr(ctx, &method_call, &span).is_some());
+        assert!(lowered || !errors.is_empty(), "{:?} was dropped without an error", method_call);
+        let import_call = Expr::Call { callee: Box::new(node(Expr::Ident(Ident::new("f")))), type_args: None, args: vec![] };
+        let (lowered, errors) = lower_in_main(|lowerer, ctx| {
+            lowerer.imported_bindings.insert("f".to_string(), "./lib".to_string());
+            lowerer.lower_expr(ctx, &import_call, &span).is_some()
+        });
+        assert!(lowered || !errors.is_empty(), "{:?} was dropped without an error", import_call);
+
+        let loop_body = || Box::new(Node::new(Stmt::Block(block(vec![])), dummy_span()));
+        let stmts = vec![
+            Stmt::Expr(node(log(x()))),
+            Stmt::VarDecl(let_decl("y", Expr::Ident(Ident::new("x")))),
+            Stmt::Return(Some(*num(0.0))),
+            Stmt::If { condition: *x(), then_stmt: Box::new(Node::new(Stmt::Expr(node(log(x()))), dummy_span())), else_stmt: None },
+            Stmt::For { init: None, condition: Some(*x()), update: None, body: loop_body() },
+            Stmt::ForIn { left: ForInLeft::VarDecl(let_decl("k", Expr::Literal(Literal::Undefined))), right: *array(), body: loop_body() },
+            Stmt::ForOf { left: ForInLeft::VarDecl(let_decl("v", Expr::Literal(Literal::Undefined))), right: *array(), body: loop_body(), is_await: false },
+            Stmt::While { condition: *x(), body: loop_body() },
+            Stmt::DoWhile { body: loop_body(), condition: *x() },
+            Stmt::Block(block(vec![Stmt::Expr(node(log(x())))])),
+            Stmt::Break(None),
+            Stmt::Continue(None),
+            Stmt::Throw(*x()),
+            Stmt::Try { block: Node::new(block(vec![Stmt::Expr(node(log(x())))]), dummy_span()), catch: None, finally: None },
+            Stmt::Switch { discriminant: *x(), cases: vec![SwitchCase { test: Some(*num(1.0)), consequent: vec![] }] },
+            Stmt::Labeled { label: ident("outer"), stmt: loop_body() },
+            Stmt::Empty,
+            Stmt::Debugger,
+        ];
+        let kinds: HashSet<usize> = stmts.iter().map(stmt_kind).collect();
+        assert_eq!(kinds.len(), stmts.len(), "one sample per kind of statement");
+
+        for stmt in &stmts {
+            let (lowered, errors) = lower_in_main(|lowerer, ctx| {
+                lowerer.lower_stmt(ctx, stmt, &span);
+                false
+            });
+            // `;` and `debugger` do nothing by design
+            let no_op = matches!(stmt, Stmt::Empty | Stmt::Debugger);
+            assert!(lowered || !errors.is_empty() || no_op, "{:?} was dropped without an error", stmt);
+        }
+    }
 }
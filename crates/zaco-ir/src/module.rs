@@ -36,6 +36,11 @@ pub struct IrModule {
     /// HashMap for O(1) string dedup lookups
     string_index_map: HashMap<String, usize>,
 
+    /// Functions that `spawn` from the "worker" module can run, as
+    /// (exported name, trampoline function name). Codegen emits them as
+    /// the `__zaco_worker_table` registry.
+    pub worker_entries: Vec<(String, String)>,
+
     /// Next available FuncId counter (set by the lowerer after lowering).
     /// Used by the driver to compute offsets for multi-module compilation.
     pub next_func_id: usize,
@@ -55,6 +60,7 @@ impl IrModule {
             string_literals: Vec::new(),
             extern_functions: Vec::new(),
             string_index_map: HashMap::new(),
+            worker_entries: Vec::new(),
             next_func_id: 0,
             next_struct_id: 0,
         }
@@ -108,6 +108,12 @@ pub enum Constant {
     Str(String),
     /// Null pointer constant
     Null,
+    /// The runtime's `undefined` sentinel, a pointer distinct from null
+    Undefined,
+    /// Address of a module function, for callbacks the runtime invokes
+    FuncAddr(String),
+    /// Address of one of the module's globals (`IrModule::globals`)
+    GlobalAddr(String),
 }
 
 /// Binary operators.
@@ -8,6 +8,14 @@ pub struct Lexer<'a> {
     current_pos: usize,
     current_char: Option<char>,
     file_id: usize,
+    /// One entry per template substitution being lexed: the number of `{`
+    /// opened inside it, so the `}` that ends it can be told apart
+    template_braces: Vec<usize>,
+    /// Whether a `/` here starts a regex literal rather than a division:
+    /// false right after a token that can end an expression
+    regex_allowed: bool,
+    /// Spans of the comments skipped so far, `//` or `/*` included
+    comments: Vec<Span>,
 }
 
 impl<'a> Lexer<'a> {
@@ -26,9 +34,19 @@ impl<'a> Lexer<'a> {
             current_pos: 0,
             current_char,
             file_id,
+            template_braces: Vec::new(),
+            regex_allowed: true,
+            comments: Vec::new(),
         }
     }
 
+    /// The comments skipped so far, in source order. After `tokenize` this
+    /// is every comment in the source; a single-line comment's span stops
+    /// before its newline.
+    pub fn comments(&self) -> &[Span] {
+        &self.comments
+    }
+
     /// Tokenizes the entire source code and returns all tokens.
     pub fn tokenize(&mut self) -> Vec<Token> {
         let mut tokens = Vec::new();
@@ -57,7 +75,7 @@ impl<'a> Lexer<'a> {
                 let token = match ch {
                     // String literals
                     '"' | '\'' => self.read_string_literal(ch),
-                    '`' => self.read_template_literal(),
+                    '`' => self.read_template_part(false),
 
                     // Numbers
                     '0'..='9' => self.read_number(),
@@ -65,6 +83,17 @@ impl<'a> Lexer<'a> {
                     // Identifiers and keywords
                     'a'..='z' | 'A'..='Z' | '_' | '$' => self.read_identifier_or_keyword(),
 
+                    // Private class member names
+                    '#' if self.peek().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$') => {
+                        self.advance();
+                        let name = self.read_identifier_or_keyword();
+                        Token::new(
+                            TokenKind::PrivateName,
+                            Span::new(start, self.current_pos, self.file_id),
+                            format!("#{}", name.value),
+                        )
+                    }
+
                     // Operators and delimiters
                     '+' => self.read_plus(),
                     '-' => self.read_minus(),
@@ -103,10 +132,20 @@ impl<'a> Lexer<'a> {
                         Token::new(TokenKind::RParen, Span::new(start, self.current_pos, self.file_id), ")".to_string())
                     }
                     '{' => {
+                        if let Some(open) = self.template_braces.last_mut() {
+                            *open += 1;
+                        }
                         self.advance();
                         Token::new(TokenKind::LBrace, Span::new(start, self.current_pos, self.file_id), "{".to_string())
                     }
+                    '}' if self.template_braces.last() == Some(&0) => {
+                        self.template_braces.pop();
+                        self.read_template_part(true)
+                    }
                     '}' => {
+                        if let Some(open) = self.template_braces.last_mut() {
+                            *open -= 1;
+                        }
                         self.advance();
                         Token::new(TokenKind::RBrace, Span::new(start, self.current_pos, self.file_id), "}".to_string())
                     }
@@ -148,11 +187,38 @@ impl<'a> Lexer<'a> {
                         )
                     }
                 };
+                self.regex_allowed = !Self::ends_expression(&token.kind);
                 token
             }
         }
     }
 
+    /// Tokens after which a `/` divides: anything that can end an operand
+    fn ends_expression(kind: &TokenKind) -> bool {
+        matches!(
+            kind,
+            TokenKind::Identifier
+                | TokenKind::PrivateName
+                | TokenKind::NumberLiteral
+                | TokenKind::BigIntLiteral
+                | TokenKind::StringLiteral
+                | TokenKind::TemplateLiteral
+                | TokenKind::TemplateTail
+                | TokenKind::RegexLiteral
+                | TokenKind::RParen
+                | TokenKind::RBracket
+                | TokenKind::RBrace
+                | TokenKind::PlusPlus
+                | TokenKind::MinusMinus
+                | TokenKind::This
+                | TokenKind::Super
+                | TokenKind::Null
+                | TokenKind::Undefined
+                | TokenKind::True
+                | TokenKind::False
+        )
+    }
+
     // Helper methods
 
     fn advance(&mut self) {
@@ -181,12 +247,14 @@ impl<'a> Lexer<'a> {
                     self.advance();
                 }
                 Some('/') => {
+                    let start = self.current_pos;
                     if self.peek() == Some('/') {
                         // Single-line comment
                         self.skip_single_line_comment();
+                        let end = self.source[start..self.current_pos].trim_end_matches(['\r', '\n']).len();
+                        self.comments.push(Span::new(start, start + end, self.file_id));
                     } else if self.peek() == Some('*') {
                         // Multi-line comment
-                        let start = self.current_pos;
                         if !self.skip_multi_line_comment() {
                             return Some(Token::new(
                                 TokenKind::Error,
@@ -194,6 +262,7 @@ impl<'a> Lexer<'a> {
                                 "Unterminated multi-line comment".to_string(),
                             ));
                         }
+                        self.comments.push(Span::new(start, self.current_pos, self.file_id));
                     } else {
                         break;
                     }
@@ -328,33 +397,58 @@ impl<'a> Lexer<'a> {
         char::from_u32(code).unwrap_or('\u{FFFD}')
     }
 
-    fn read_template_literal(&mut self) -> Token {
+    /// Read template text after the opening backtick, or after the `}` that
+    /// ends a substitution when `continuation` is set. The text runs to the
+    /// closing backtick or to the next `${`; a template without substitutions
+    /// is one TemplateLiteral token, otherwise it is a TemplateHead, then a
+    /// TemplateMiddle between each pair of substitutions and a TemplateTail,
+    /// with the substitution expressions lexed as ordinary tokens in between.
+    fn read_template_part(&mut self, continuation: bool) -> Token {
         let start = self.current_pos;
-        self.advance(); // Skip opening backtick
+        self.advance(); // Skip the backtick or `}`
+        let raw_start = self.current_pos;
 
         let mut value = String::new();
 
         while let Some(ch) = self.current_char {
-            if ch == '`' {
-                self.advance(); // Skip closing backtick
-                return Token::new(
-                    TokenKind::TemplateLiteral,
-                    Span::new(start, self.current_pos, self.file_id),
-                    value,
-                );
+            if ch == '`' || (ch == '$' && self.peek() == Some('{')) {
+                let raw = self.source[raw_start..self.current_pos].to_string();
+                let kind = if ch == '`' {
+                    self.advance(); // Skip closing backtick
+                    if continuation { TokenKind::TemplateTail } else { TokenKind::TemplateLiteral }
+                } else {
+                    self.advance(); // Skip `$`
+                    self.advance(); // Skip `{`
+                    self.template_braces.push(0);
+                    if continuation { TokenKind::TemplateMiddle } else { TokenKind::TemplateHead }
+                };
+                return Token::template(kind, Span::new(start, self.current_pos, self.file_id), value, raw);
             } else if ch == '\\' {
                 self.advance();
                 if let Some(escaped) = self.current_char {
-                    let unescaped = match escaped {
-                        'n' => '\n',
-                        'r' => '\r',
-                        't' => '\t',
-                        '\\' => '\\',
-                        '`' => '`',
-                        _ => escaped,
-                    };
-                    value.push(unescaped);
-                    self.advance();
+                    match escaped {
+                        'u' => {
+                            self.advance();
+                            value.push(self.read_unicode_escape());
+                        }
+                        'x' => {
+                            self.advance();
+                            value.push(self.read_hex_escape());
+                        }
+                        // Line continuation
+                        '\n' => self.advance(),
+                        _ => {
+                            let unescaped = match escaped {
+                                'n' => '\n',
+                                'r' => '\r',
+                                't' => '\t',
+                                '0' => '\0',
+                                _ => escaped,
+                            };
+                            value.push(unescaped);
+                            self.advance();
+                        }
+                    }
                 }
             } else {
                 value.push(ch);
@@ -651,6 +745,14 @@ impl<'a> Lexer<'a> {
 
     fn read_slash_or_regex(&mut self) -> Token {
         let start = self.current_pos;
+        if self.regex_allowed {
+            let saved = (self.chars.clone(), self.current_pos, self.current_char);
+            if let Some(token) = self.read_regex() {
+                return token;
+            }
+            // No closing `/` on the line: lex a plain slash instead
+            (self.chars, self.current_pos, self.current_char) = saved;
+        }
         self.advance();
 
         match self.current_char {
@@ -662,6 +764,51 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// `/pattern/flags`: the pattern as written (escapes included) in
+    /// `value`, the flags in `raw`. `None` when the line ends first.
+    fn read_regex(&mut self) -> Option<Token> {
+        let start = self.current_pos;
+        self.advance();
+        let mut pattern = String::new();
+        let mut in_class = false;
+        loop {
+            match self.current_char {
+                None | Some('\n') => return None,
+                Some('/') if !in_class => break,
+                Some('\\') => {
+                    pattern.push('\\');
+                    self.advance();
+                    if let Some(ch) = self.current_char.filter(|&ch| ch != '\n') {
+                        pattern.push(ch);
+                        self.advance();
+                    }
+                    continue;
+                }
+                Some(ch) => {
+                    match ch {
+                        '[' => in_class = true,
+                        ']' => in_class = false,
+                        _ => {}
+                    }
+                    pattern.push(ch);
+                }
+            }
+            self.advance();
+        }
+        self.advance();
+        let mut flags = String::new();
+        while let Some(ch) = self.current_char.filter(|ch| ch.is_ascii_alphabetic()) {
+            flags.push(ch);
+            self.advance();
+        }
+        Some(Token {
+            kind: TokenKind::RegexLiteral,
+            span: Span::new(start, self.current_pos, self.file_id),
+            value: pattern,
+            raw: Some(flags),
+        })
+    }
+
     fn read_percent(&mut self) -> Token {
         let start = self.current_pos;
         self.advance();
@@ -943,6 +1090,53 @@ mod tests {
         assert_eq!(tokens[2].value, "template");
     }
 
+    #[test]
+    fn test_regex_literal_or_division() {
+        let tokens = Lexer::new(r"x = a / b; s.matchAll(/[/\]](\d)/gi) / 2").tokenize();
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(kinds[3], TokenKind::Slash);
+        assert_eq!(kinds[10], TokenKind::RegexLiteral);
+        assert_eq!(tokens[10].value, r"[/\]](\d)");
+        assert_eq!(tokens[10].raw.as_deref(), Some("gi"));
+        assert_eq!(kinds[12], TokenKind::Slash);
+    }
+
+    #[test]
+    fn test_template_substitutions() {
+        let source = r"`a\t${x}b${ {c: 1} }c`";
+        let tokens = Lexer::new(source).tokenize();
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::TemplateHead,
+                TokenKind::Identifier,
+                TokenKind::TemplateMiddle,
+                TokenKind::LBrace,
+                TokenKind::Identifier,
+                TokenKind::Colon,
+                TokenKind::NumberLiteral,
+                TokenKind::RBrace,
+                TokenKind::TemplateTail,
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(tokens[0].value, "a\t");
+        assert_eq!(tokens[0].raw.as_deref(), Some(r"a\t"));
+        assert_eq!(tokens[2].value, "b");
+        assert_eq!(tokens[8].value, "c");
+    }
+
+    #[test]
+    fn test_private_names() {
+        let tokens = Lexer::new("this.#count #in obj").tokenize();
+        assert_eq!(tokens[2].kind, TokenKind::PrivateName);
+        assert_eq!(tokens[2].value, "#count");
+        assert_eq!(tokens[3].kind, TokenKind::PrivateName);
+        assert_eq!(tokens[3].value, "#in");
+        assert_eq!(tokens[4].kind, TokenKind::Identifier);
+    }
+
     #[test]
     fn test_operators() {
         let source = "+ - * / % ** += -= === !== <= >= && || ?? ?.";
@@ -985,6 +1179,9 @@ mod tests {
         assert_eq!(tokens[3].kind, TokenKind::NumberLiteral);
         assert_eq!(tokens[4].kind, TokenKind::Semicolon);
         assert_eq!(tokens[5].kind, TokenKind::Const);
+
+        let comments: Vec<&str> = lexer.comments().iter().map(|span| &source[span.start..span.end]).collect();
+        assert_eq!(comments, ["// Single line comment", "/* Multi-line\n               comment */"]);
     }
 
     #[test]
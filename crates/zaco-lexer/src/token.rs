@@ -82,12 +82,16 @@ pub enum TokenKind {
     // Literals
     NumberLiteral,
     StringLiteral,
-    TemplateLiteral,
+    TemplateLiteral,  // `text` (no substitutions)
+    TemplateHead,     // `text${
+    TemplateMiddle,   // }text${
+    TemplateTail,     // }text`
     RegexLiteral,
     BigIntLiteral,
 
     // Identifier
     Identifier,
+    PrivateName,       // #name (value keeps the `#`)
 
     // Operators
     Plus,              // +
@@ -156,16 +160,43 @@ pub enum TokenKind {
     Error,
 }
 
+impl TokenKind {
+    /// Whether this is a reserved or contextual keyword. Keywords are still
+    /// valid property names after `.`, as in `promise.catch(...)`.
+    pub fn is_keyword(&self) -> bool {
+        use TokenKind::*;
+        matches!(
+            self,
+            Let | Const | Var | Function | Return | If | Else | For | While | Do | Break | Continue
+                | Switch | Case | Default | Class | Extends | Implements | Interface | Type | Enum
+                | Import | Export | From | As | New | This | Super | Typeof | Instanceof | In | Of
+                | Void | Null | Undefined | True | False | Async | Await | Yield | Try | Catch
+                | Finally | Throw | Static | Public | Private | Protected | Readonly | Abstract
+                | Declare | Module | Namespace | Require | Keyof | Infer | Never | Unknown | Any
+                | Satisfies | Override | Is | Asserts | Out | Accessor | Using | Debugger | With
+                | Owned | Ref | Clone | Mut
+        )
+    }
+}
+
 /// Represents a token with its kind, span, and value.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
     pub value: String,
+    /// Source text of a template part before escape processing
+    /// (`value` holds the cooked text), or the flags of a regex literal
+    /// (`value` holds the pattern); `None` for other tokens
+    pub raw: Option<String>,
 }
 
 impl Token {
     pub(crate) fn new(kind: TokenKind, span: Span, value: String) -> Self {
-        Self { kind, span, value }
+        Self { kind, span, value, raw: None }
+    }
+
+    pub(crate) fn template(kind: TokenKind, span: Span, cooked: String, raw: String) -> Self {
+        Self { kind, span, value: cooked, raw: Some(raw) }
     }
 }
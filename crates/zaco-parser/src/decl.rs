@@ -17,6 +17,18 @@ impl Parser {
             false
         };
 
+        // Everything under `declare` is ambient: it describes values that
+        // exist at run time without being defined here
+        let was_ambient = self.in_ambient;
+        self.in_ambient |= is_declare;
+        let decl = self.parse_declaration_kind(is_declare, decorators);
+        self.in_ambient = was_ambient;
+
+        let span = start.merge(&self.previous_token().span);
+        Ok(Node::new(decl?, span))
+    }
+
+    fn parse_declaration_kind(&mut self, is_declare: bool, decorators: Vec<Node<Expr>>) -> ParseResult<Decl> {
         let decl = match self.current_token().kind {
             TokenKind::Function | TokenKind::Async => {
                 let func_decl = self.parse_function_declaration(is_declare)?;
@@ -39,10 +51,27 @@ impl Parser {
                 let enum_decl = self.parse_enum_declaration(is_declare)?;
                 Decl::Enum(enum_decl)
             }
+            TokenKind::Const if self.peek_kind(1) == Some(&TokenKind::Enum) => {
+                let enum_decl = self.parse_enum_declaration(is_declare)?;
+                Decl::Enum(enum_decl)
+            }
             TokenKind::Module | TokenKind::Namespace => {
                 let module_decl = self.parse_module_declaration(is_declare)?;
                 Decl::Module(module_decl)
             }
+            // declare global { ... }
+            TokenKind::Identifier
+                if is_declare
+                    && self.current_token().value == "global"
+                    && self.peek_kind(1) == Some(&TokenKind::LBrace) =>
+            {
+                let name = self.parse_identifier()?;
+                Decl::Module(ModuleDecl {
+                    name: ModuleName::Ident(name),
+                    body: ModuleBody::Block(self.parse_module_block()?),
+                    is_declare,
+                })
+            }
             TokenKind::Const | TokenKind::Let | TokenKind::Var => {
                 let var_decl = self.parse_var_declaration()?;
                 Decl::Var(var_decl)
@@ -55,8 +84,7 @@ impl Parser {
             }
         };
 
-        let span = start.merge(&self.previous_token().span);
-        Ok(Node::new(decl, span))
+        Ok(decl)
     }
 
     pub(crate) fn parse_function_declaration(&mut self, is_declare: bool) -> ParseResult<FunctionDecl> {
@@ -173,6 +201,7 @@ impl Parser {
         let mut is_readonly = false;
         let mut is_abstract = false;
         let mut is_override = false;
+        let mut is_async = false;
 
         loop {
             match self.current_token().kind {
@@ -204,6 +233,17 @@ impl Parser {
                     self.advance();
                     is_override = true;
                 }
+                // `async name()`, unless `async` is itself the member name
+                TokenKind::Async
+                    if !matches!(
+                        self.peek_kind(1),
+                        Some(TokenKind::LParen | TokenKind::Lt | TokenKind::Colon | TokenKind::Eq | TokenKind::Semicolon
+                            | TokenKind::Question | TokenKind::RBrace)
+                    ) =>
+                {
+                    self.advance();
+                    is_async = true;
+                }
                 _ => break,
             }
         }
@@ -212,7 +252,7 @@ impl Parser {
         if self.check(&TokenKind::Identifier) && self.current_token().value == "constructor" {
             self.advance();
             self.consume(TokenKind::LParen)?;
-            let params = self.parse_function_params()?;
+            let params = self.parse_constructor_params()?;
             self.consume(TokenKind::RParen)?;
 
             let body = if self.check(&TokenKind::Semicolon) {
@@ -229,8 +269,8 @@ impl Parser {
             });
         }
 
-        // Index signature
-        if self.check(&TokenKind::LBracket) {
+        // Index signature: `[key: K]: V`; `[expr]` is a computed member name
+        if self.check(&TokenKind::LBracket) && self.peek_kind(2) == Some(&TokenKind::Colon) {
             self.advance();
             let key_name = self.parse_identifier()?;
             self.consume(TokenKind::Colon)?;
@@ -264,13 +304,23 @@ impl Parser {
             self.advance();
         }
 
-        let name = self.parse_property_name()?;
+        // `#name` members keep the `#` in their name
+        let name = if self.check(&TokenKind::PrivateName) {
+            let token = self.advance();
+            PropertyName::Ident(Node::new(Ident::new(token.value.clone()), token.span))
+        } else {
+            self.parse_property_name()?
+        };
         let is_optional = if self.check(&TokenKind::Question) {
             self.advance();
             true
         } else {
             false
         };
+        let is_definite = !is_optional && self.check(&TokenKind::Bang);
+        if is_definite {
+            self.advance();
+        }
 
         // Getter
         if is_getter {
@@ -354,7 +404,7 @@ impl Parser {
                 body,
                 access,
                 is_static,
-                is_async: false,
+                is_async,
                 is_abstract,
                 is_optional,
                 is_override,
@@ -390,6 +440,7 @@ impl Parser {
                 is_readonly,
                 is_abstract,
                 is_optional,
+                is_definite,
                 is_override,
                 decorators: decorators.clone(),
             })
@@ -509,7 +560,12 @@ impl Parser {
         } else {
             self.advance();
         }
+        self.parse_module_rest(is_declare)
+    }
 
+    /// Name and body of a module declaration, after the keyword. In a dotted
+    /// name (`namespace A.B { .. }`), each segment after the first nests.
+    fn parse_module_rest(&mut self, is_declare: bool) -> ParseResult<ModuleDecl> {
         let name = if self.check(&TokenKind::StringLiteral) {
             ModuleName::String(self.advance().value.clone())
         } else {
@@ -519,19 +575,11 @@ impl Parser {
         let body = if self.check(&TokenKind::Dot) {
             self.advance();
             let nested_start = self.current_token().span;
-            let nested = self.parse_module_declaration(false)?;
+            let nested = self.parse_module_rest(is_declare)?;
             let span = nested_start.merge(&self.previous_token().span);
             ModuleBody::Namespace(Box::new(Node::new(nested, span)))
         } else {
-            self.consume(TokenKind::LBrace)?;
-            let mut items = Vec::new();
-
-            while !self.check(&TokenKind::RBrace) && !self.is_at_end() {
-                items.push(self.parse_module_item()?);
-            }
-
-            self.consume(TokenKind::RBrace)?;
-            ModuleBody::Block(items)
+            ModuleBody::Block(self.parse_module_block()?)
         };
 
         Ok(ModuleDecl {
@@ -541,6 +589,18 @@ impl Parser {
         })
     }
 
+    fn parse_module_block(&mut self) -> ParseResult<Vec<Node<ModuleItem>>> {
+        self.consume(TokenKind::LBrace)?;
+        let mut items = Vec::new();
+
+        while !self.check(&TokenKind::RBrace) && !self.is_at_end() {
+            items.push(self.parse_module_item()?);
+        }
+
+        self.consume(TokenKind::RBrace)?;
+        Ok(items)
+    }
+
     // =========================================================================
     // Statements
     // =========================================================================
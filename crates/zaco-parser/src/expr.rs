@@ -2,11 +2,34 @@
 
 use super::*;
 
+/// A template's cooked parts, its raw parts as written, and the
+/// substitutions between them (one fewer than the parts)
+pub(crate) type TemplateParts = (Vec<String>, Vec<String>, Vec<Node<Expr>>);
+
 impl Parser {
     pub(crate) fn parse_expression(&mut self) -> ParseResult<Node<Expr>> {
         self.parse_expression_with_precedence(0)
     }
 
+    /// Parse a comma-separated sequence `a, b, c`. A single expression is
+    /// returned as is; call arguments and array elements keep using
+    /// `parse_expression` since their commas are separators.
+    pub(crate) fn parse_sequence_expression(&mut self) -> ParseResult<Node<Expr>> {
+        let first = self.parse_expression()?;
+        if !self.check(&TokenKind::Comma) {
+            return Ok(first);
+        }
+
+        let start = first.span;
+        let mut exprs = vec![first];
+        while self.check(&TokenKind::Comma) {
+            self.advance();
+            exprs.push(self.parse_expression()?);
+        }
+        let span = start.merge(&self.previous_token().span);
+        Ok(Node::new(Expr::Sequence(exprs), span))
+    }
+
     pub(crate) fn parse_expression_with_precedence(&mut self, min_precedence: u8) -> ParseResult<Node<Expr>> {
         let _start = self.current_token().span;
         let mut left = self.parse_prefix_expression()?;
@@ -144,6 +167,32 @@ impl Parser {
         Ok(Node::new(expr, span))
     }
 
+    /// Parse a template from its TemplateLiteral or TemplateHead token
+    pub(crate) fn parse_template_parts(&mut self) -> ParseResult<TemplateParts> {
+        let first = self.advance().clone();
+        let mut parts = vec![first.value];
+        let mut raw = vec![first.raw.unwrap_or_default()];
+        let mut exprs = Vec::new();
+
+        if first.kind == TokenKind::TemplateHead {
+            loop {
+                exprs.push(self.parse_expression()?);
+                let kind = self.current_token().kind.clone();
+                if !matches!(kind, TokenKind::TemplateMiddle | TokenKind::TemplateTail) {
+                    return Err(self.error(format!("Expected TemplateTail, found {:?}", kind)));
+                }
+                let part = self.advance().clone();
+                parts.push(part.value);
+                raw.push(part.raw.unwrap_or_default());
+                if kind == TokenKind::TemplateTail {
+                    break;
+                }
+            }
+        }
+
+        Ok((parts, raw, exprs))
+    }
+
     fn parse_infix_expression(&mut self, left: Node<Expr>) -> ParseResult<Node<Expr>> {
         let start = left.span;
 
@@ -226,7 +275,7 @@ impl Parser {
             // Member access
             TokenKind::Dot => {
                 self.advance();
-                let property = self.parse_identifier()?;
+                let property = self.parse_member_property()?;
                 Expr::Member {
                     object: Box::new(left),
                     property,
@@ -274,7 +323,7 @@ impl Parser {
                 }
                 // ?.property for optional member
                 else {
-                    let property = self.parse_identifier()?;
+                    let property = self.parse_member_property()?;
                     Expr::OptionalMember {
                         object: Box::new(left),
                         property,
@@ -293,6 +342,17 @@ impl Parser {
                 }
             }
 
+            // Tagged template: tag`text ${expr}`
+            TokenKind::TemplateLiteral | TokenKind::TemplateHead => {
+                let (parts, raw, exprs) = self.parse_template_parts()?;
+                Expr::TaggedTemplate {
+                    tag: Box::new(left),
+                    parts,
+                    raw,
+                    exprs,
+                }
+            }
+
             // Function call
             TokenKind::LParen => {
                 self.advance();
@@ -386,6 +446,13 @@ impl Parser {
                 let value = self.advance().value.clone();
                 Expr::Literal(Literal::String(value))
             }
+            TokenKind::RegexLiteral => {
+                let token = self.advance();
+                Expr::Literal(Literal::RegExp {
+                    pattern: token.value.clone(),
+                    flags: token.raw.clone().unwrap_or_default(),
+                })
+            }
             TokenKind::True => {
                 self.advance();
                 Expr::Literal(Literal::Boolean(true))
@@ -404,13 +471,9 @@ impl Parser {
             }
 
             // Template literal
-            TokenKind::TemplateLiteral => {
-                let value = self.advance().value.clone();
-                // Simple template without expressions
-                Expr::Template {
-                    parts: vec![value],
-                    exprs: vec![],
-                }
+            TokenKind::TemplateLiteral | TokenKind::TemplateHead => {
+                let (parts, _raw, exprs) = self.parse_template_parts()?;
+                Expr::Template { parts, exprs }
             }
 
             // Identifiers
@@ -419,6 +482,26 @@ impl Parser {
                 Expr::Ident(Ident::new(name))
             }
 
+            // `#name in obj` brand check; a private name is only valid before `in`
+            TokenKind::PrivateName if self.peek_kind(1) == Some(&TokenKind::In) => {
+                let name = self.advance().value.clone();
+                Expr::Ident(Ident::new(name))
+            }
+
+            // import.meta
+            TokenKind::Import if self.peek_kind(1) == Some(&TokenKind::Dot) => {
+                let import_token = self.advance().clone();
+                self.advance();
+                let property = self.parse_identifier()?;
+                if property.value.name != "meta" {
+                    return Err(self.error(format!("Unknown meta property 'import.{}'", property.value.name)));
+                }
+                Expr::MetaProperty {
+                    meta: Node::new(Ident::new("import"), import_token.span),
+                    property,
+                }
+            }
+
             // This
             TokenKind::This => {
                 self.advance();
@@ -463,6 +546,27 @@ impl Parser {
                         self.advance();
                         let expr = self.parse_expression()?;
                         properties.push(ObjectProperty::Spread(expr));
+                    } else if self.is_object_accessor("get") {
+                        self.advance();
+                        let key = self.parse_property_name()?;
+                        self.consume(TokenKind::LParen)?;
+                        self.consume(TokenKind::RParen)?;
+                        let return_type = if self.check(&TokenKind::Colon) {
+                            self.advance();
+                            Some(Box::new(self.parse_type()?))
+                        } else {
+                            None
+                        };
+                        let body = self.parse_block_statement()?;
+                        properties.push(ObjectProperty::Getter { key, return_type, body });
+                    } else if self.is_object_accessor("set") {
+                        self.advance();
+                        let key = self.parse_property_name()?;
+                        self.consume(TokenKind::LParen)?;
+                        let param = self.parse_function_param()?;
+                        self.consume(TokenKind::RParen)?;
+                        let body = self.parse_block_statement()?;
+                        properties.push(ObjectProperty::Setter { key, param, body });
                     } else {
                         let key = self.parse_property_name()?;
 
@@ -524,7 +628,7 @@ impl Parser {
 
             // Parenthesized expression or arrow function
             TokenKind::LParen => {
-                return self.parse_paren_or_arrow();
+                return self.parse_paren_or_arrow(false);
             }
 
             // Function expression
@@ -635,9 +739,17 @@ impl Parser {
                         body,
                         is_async: true,
                     }
+                } else if self.check(&TokenKind::LParen) {
+                    // async (params) => body
+                    let arrow = self.parse_paren_or_arrow(true)?;
+                    if !matches!(arrow.value, Expr::Arrow { .. }) {
+                        return Err(self.error("Expected an arrow function after 'async'".to_string()));
+                    }
+                    return Ok(Node::new(arrow.value, start.merge(&arrow.span)));
                 } else {
-                    // async arrow function
-                    return self.parse_arrow_function(None, None, true);
+                    // async param => body
+                    let arrow = self.parse_arrow_function(None, None, true)?;
+                    return Ok(Node::new(arrow.value, start.merge(&arrow.span)));
                 }
             }
 
@@ -653,14 +765,27 @@ impl Parser {
         Ok(Node::new(expr, span))
     }
 
-    fn parse_paren_or_arrow(&mut self) -> ParseResult<Node<Expr>> {
+    /// `get`/`set` starting an accessor in an object literal, rather than
+    /// naming a property or method (`get: 1`, `get() {}`, `{ get }`)
+    fn is_object_accessor(&self, keyword: &str) -> bool {
+        self.check(&TokenKind::Identifier)
+            && self.current_token().value == keyword
+            && !matches!(
+                self.peek_kind(1),
+                None | Some(TokenKind::LParen | TokenKind::Colon | TokenKind::Comma | TokenKind::RBrace | TokenKind::Lt)
+            )
+    }
+
+    /// A parenthesized expression, or the arrow function whose parameter
+    /// list the parenthesis opens (an async one after `async`)
+    fn parse_paren_or_arrow(&mut self, is_async: bool) -> ParseResult<Node<Expr>> {
         let start = self.current_token().span;
         self.consume(TokenKind::LParen)?;
 
         // Empty params arrow function
         if self.check(&TokenKind::RParen) {
             self.advance();
-            return self.parse_arrow_function(Some(Vec::new()), None, false);
+            return self.parse_arrow_function(Some(Vec::new()), None, is_async);
         }
 
         // Try to determine if this is an arrow function or parenthesized expression
@@ -680,7 +805,7 @@ impl Parser {
                 };
 
                 if self.check(&TokenKind::FatArrow) {
-                    return self.parse_arrow_function(Some(params), return_type, false);
+                    return self.parse_arrow_function(Some(params), return_type, is_async);
                 }
             }
         }
@@ -688,14 +813,14 @@ impl Parser {
         // Reset and parse as parenthesized expression
         self.current = checkpoint;
 
-        let expr = self.parse_expression()?;
+        let expr = self.parse_sequence_expression()?;
         self.consume(TokenKind::RParen)?;
 
         // Check if it's actually an arrow function
         if self.check(&TokenKind::FatArrow) {
             // Convert expression to parameter
             let param = self.expr_to_param(expr)?;
-            return self.parse_arrow_function(Some(vec![param]), None, false);
+            return self.parse_arrow_function(Some(vec![param]), None, is_async);
         }
 
         let span = start.merge(&self.previous_token().span);
@@ -706,7 +831,7 @@ impl Parser {
         &mut self,
         params: Option<Vec<Param>>,
         return_type: Option<Box<Node<Type>>>,
-        _is_async: bool,
+        is_async: bool,
     ) -> ParseResult<Node<Expr>> {
         let start = self.current_token().span;
 
@@ -726,6 +851,7 @@ impl Parser {
                 ownership: None,
                 optional: false,
                 is_rest: false,
+                property: None,
             }]
         };
 
@@ -744,6 +870,7 @@ impl Parser {
                 params,
                 return_type,
                 body,
+                is_async,
             },
             span,
         ))
@@ -11,6 +11,15 @@ impl Parser {
         ))
     }
 
+    /// Property name after `.` or `?.`, where keywords are plain names
+    pub(crate) fn parse_member_property(&mut self) -> ParseResult<Node<Ident>> {
+        if self.current_token().kind.is_keyword() || self.check(&TokenKind::PrivateName) {
+            let token = self.advance();
+            return Ok(Node::new(Ident::new(token.value.clone()), token.span));
+        }
+        self.parse_identifier()
+    }
+
     pub(crate) fn parse_property_name(&mut self) -> ParseResult<PropertyName> {
         match self.current_token().kind {
             TokenKind::Identifier => {
@@ -32,15 +41,30 @@ impl Parser {
                 self.consume(TokenKind::RBracket)?;
                 Ok(PropertyName::Computed(expr))
             }
+            // Keywords name properties too (`return()` on an iterator)
+            ref kind if kind.is_keyword() => {
+                let token = self.advance();
+                Ok(PropertyName::Ident(Node::new(Ident::new(token.value.clone()), token.span)))
+            }
             _ => Err(self.error("Expected property name".to_string())),
         }
     }
 
     pub(crate) fn parse_function_params(&mut self) -> ParseResult<Vec<Param>> {
+        self.parse_param_list(false)
+    }
+
+    /// Constructor parameters, which may carry `public`/`private`/`protected`/
+    /// `readonly` to declare a field
+    pub(crate) fn parse_constructor_params(&mut self) -> ParseResult<Vec<Param>> {
+        self.parse_param_list(true)
+    }
+
+    fn parse_param_list(&mut self, allow_properties: bool) -> ParseResult<Vec<Param>> {
         let mut params = Vec::new();
 
         while !self.check(&TokenKind::RParen) && !self.is_at_end() {
-            params.push(self.parse_function_param()?);
+            params.push(self.parse_param(allow_properties)?);
             if !self.check(&TokenKind::RParen) {
                 self.consume(TokenKind::Comma)?;
             }
@@ -50,6 +74,19 @@ impl Parser {
     }
 
     pub(crate) fn parse_function_param(&mut self) -> ParseResult<Param> {
+        self.parse_param(false)
+    }
+
+    fn parse_param(&mut self, allow_properties: bool) -> ParseResult<Param> {
+        let start = self.current_token().span;
+        let property = self.parse_param_property_modifiers();
+        if property.is_some() && !allow_properties {
+            return Err(ParseError {
+                message: "A parameter property is only allowed in a constructor".to_string(),
+                span: start,
+            });
+        }
+
         let is_rest = if self.check(&TokenKind::DotDotDot) {
             self.advance();
             true
@@ -61,6 +98,12 @@ impl Parser {
         let ownership = self.parse_ownership_annotation()?;
 
         let pattern = self.parse_pattern()?;
+        if property.is_some() && (is_rest || !matches!(pattern.value, Pattern::Ident { .. })) {
+            return Err(ParseError {
+                message: "A parameter property must be a plain identifier".to_string(),
+                span: start,
+            });
+        }
 
         // Also check for ownership annotation after the pattern (alternative syntax)
         let ownership = if ownership.is_none() {
@@ -89,9 +132,43 @@ impl Parser {
             ownership,
             optional,
             is_rest,
+            property,
         })
     }
 
+    /// Access/`readonly` modifiers before a constructor parameter. A modifier
+    /// keyword directly followed by `:`, `,`, `)`, `?` or `=` is left for the
+    /// pattern parser as the parameter's name.
+    fn parse_param_property_modifiers(&mut self) -> Option<ParamProperty> {
+        let mut property: Option<ParamProperty> = None;
+        loop {
+            let access = match self.current_token().kind {
+                TokenKind::Public => Some(AccessModifier::Public),
+                TokenKind::Private => Some(AccessModifier::Private),
+                TokenKind::Protected => Some(AccessModifier::Protected),
+                TokenKind::Readonly | TokenKind::Override => None,
+                _ => break,
+            };
+            if matches!(
+                self.peek_kind(1),
+                Some(TokenKind::Colon | TokenKind::Comma | TokenKind::RParen | TokenKind::Question | TokenKind::Eq)
+            ) {
+                break;
+            }
+            let is_readonly = self.check(&TokenKind::Readonly);
+            self.advance();
+            let entry = property.get_or_insert(ParamProperty {
+                access: AccessModifier::Public,
+                is_readonly: false,
+            });
+            if let Some(access) = access {
+                entry.access = access;
+            }
+            entry.is_readonly |= is_readonly;
+        }
+        property
+    }
+
     pub(crate) fn parse_function_params_for_arrow(&mut self) -> ParseResult<Vec<Param>> {
         self.parse_function_params()
     }
@@ -229,6 +306,7 @@ impl Parser {
             ownership: None,
             optional: false,
             is_rest: false,
+            property: None,
         })
     }
 
@@ -274,7 +352,12 @@ impl Parser {
             TokenKind::StarStar => 14,
             TokenKind::As | TokenKind::Satisfies => 15,
             TokenKind::PlusPlus | TokenKind::MinusMinus | TokenKind::Bang => 16,
-            TokenKind::Dot | TokenKind::QuestionDot | TokenKind::LBracket | TokenKind::LParen => 17,
+            TokenKind::Dot
+            | TokenKind::QuestionDot
+            | TokenKind::LBracket
+            | TokenKind::LParen
+            | TokenKind::TemplateLiteral
+            | TokenKind::TemplateHead => 17,
             _ => 0,
         }
     }
@@ -361,9 +444,34 @@ impl Parser {
                 | Some(TokenKind::StringLiteral)
                 | Some(TokenKind::NumberLiteral)
                 | Some(TokenKind::TemplateLiteral)
+                | Some(TokenKind::TemplateHead)
         )
     }
 
+    /// Whether the current token closes a type parameter or argument list:
+    /// `>`, or `>>`/`>>>` ending nested lists (`Promise<Array<T>>`)
+    pub(crate) fn check_closing_angle(&self) -> bool {
+        matches!(self.peek_kind(0), Some(TokenKind::Gt | TokenKind::GtGt | TokenKind::GtGtGt))
+    }
+
+    /// Consume one `>`, splitting it off the front of a `>>` or `>>>`
+    pub(crate) fn consume_closing_angle(&mut self) -> ParseResult<()> {
+        let rest = match self.current_token().kind {
+            TokenKind::Gt => {
+                self.advance();
+                return Ok(());
+            }
+            TokenKind::GtGt => TokenKind::Gt,
+            TokenKind::GtGtGt => TokenKind::GtGt,
+            ref other => return Err(self.error(format!("Expected Gt, found {:?}", other))),
+        };
+        let token = &mut self.tokens[self.current];
+        token.kind = rest;
+        token.span.start += 1;
+        token.value.remove(0);
+        Ok(())
+    }
+
     pub(crate) fn error(&self, message: String) -> ParseError {
         ParseError {
             message,
@@ -64,6 +64,31 @@ mod tests {
         assert_eq!(program.items.len(), 1);
     }
 
+    #[test]
+    fn test_parse_async_arrow_function() {
+        let source = "(async () => { await main(); })(); const f = async (n: number) => n;";
+        let program = parse(source).unwrap();
+        assert_eq!(program.items.len(), 2);
+        let ModuleItem::Stmt(stmt) = &program.items[0].value else { panic!("expected a statement") };
+        let Stmt::Expr(expr) = &stmt.value else { panic!("expected an expression statement") };
+        let Expr::Call { callee, .. } = &expr.value else { panic!("expected a call") };
+        let Expr::Paren(arrow) = &callee.value else { panic!("expected a parenthesized callee") };
+        assert!(matches!(arrow.value, Expr::Arrow { is_async: true, .. }));
+    }
+
+    #[test]
+    fn test_top_level_await_skips_nested_functions() {
+        let nested = "async function f() { await g(); }\nconst h = async () => await g();\n";
+        assert!(parse(nested).unwrap().top_level_await().is_none());
+
+        let source = "const x = 1;\nif (x) { const y = [await g()]; }\n";
+        let span = parse(source).unwrap().top_level_await().expect("an await in a top-level block");
+        assert_eq!(&source[span.start..span.end], "if (x) { const y = [await g()]; }");
+
+        let for_await = parse("for await (const item of items()) {}").unwrap();
+        assert!(for_await.top_level_await().is_some());
+    }
+
     #[test]
     fn test_parse_class_declaration() {
         let source = r#"
@@ -81,6 +106,59 @@ mod tests {
         assert_eq!(program.items.len(), 1);
     }
 
+    #[test]
+    fn test_parse_private_class_members() {
+        let source = r#"
+            class Counter {
+                #count: number = 0;
+                static #instances: number = 0;
+                #bump(): void { this.#count += 1; }
+                static isCounter(obj: Counter): boolean { return #count in obj; }
+            }
+        "#;
+        let program = parse(source).unwrap();
+        assert_eq!(program.items.len(), 1);
+        // A private name on its own is not an expression
+        assert!(parse("const y = #count;").is_err());
+    }
+
+    #[test]
+    fn test_parse_import_meta() {
+        let program = parse("import { x } from \"./x\";\nimport.meta.url;").unwrap();
+        assert!(matches!(program.items[0].value, ModuleItem::Import(_)));
+        if let ModuleItem::Stmt(stmt) = &program.items[1].value {
+            if let Stmt::Expr(expr) = &stmt.value {
+                if let Expr::Member { object, property, .. } = &expr.value {
+                    assert!(matches!(&object.value, Expr::MetaProperty { meta, property }
+                        if meta.value.name == "import" && property.value.name == "meta"));
+                    assert_eq!(property.value.name, "url");
+                    return;
+                }
+            }
+        }
+        panic!("expected import.meta.url");
+    }
+
+    #[test]
+    fn test_parse_computed_class_member_is_not_index_signature() {
+        let source = r#"
+            class Bag {
+                [key: string]: number;
+                [Symbol.iterator](): BagIterator { return new BagIterator(); }
+            }
+        "#;
+        let program = parse(source).unwrap();
+        if let ModuleItem::Decl(decl) = &program.items[0].value {
+            if let Decl::Class(class) = &decl.value {
+                assert!(matches!(class.members[0], ClassMember::IndexSignature { .. }));
+                assert!(matches!(&class.members[1], ClassMember::Method { name, .. }
+                    if name.symbol_key().as_deref() == Some("@@iterator")));
+                return;
+            }
+        }
+        panic!("expected a class declaration");
+    }
+
     #[test]
     fn test_parse_interface_declaration() {
         let source = r#"
@@ -248,4 +326,207 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_keyword_member_names() {
+        let source = "p.then(f).catch(g).finally(h); x?.default;";
+        let program = parse(source).unwrap();
+        assert_eq!(program.items.len(), 2);
+        if let ModuleItem::Stmt(stmt) = &program.items[0].value {
+            if let Stmt::Expr(expr) = &stmt.value {
+                if let Expr::Call { callee, .. } = &expr.value {
+                    if let Expr::Member { property, .. } = &callee.value {
+                        assert_eq!(property.value.name, "finally");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_constructor_parameter_properties() {
+        let source = "class P { constructor(private readonly id: number, name: string) {} }";
+        let program = parse(source).unwrap();
+        if let ModuleItem::Decl(decl) = &program.items[0].value {
+            if let Decl::Class(class_decl) = &decl.value {
+                if let ClassMember::Constructor { params, .. } = &class_decl.members[0] {
+                    assert_eq!(
+                        params[0].property,
+                        Some(ParamProperty { access: AccessModifier::Private, is_readonly: true })
+                    );
+                    assert_eq!(params[1].property, None);
+                    return;
+                }
+            }
+        }
+        panic!("expected a class with a constructor");
+    }
+
+    #[test]
+    fn test_keywords_as_property_names() {
+        assert!(parse("class It { return(): number { return 1; } delete(): void {} }").is_ok());
+        assert!(parse("let o = { default: 1, new: 2 };").is_ok());
+    }
+
+    #[test]
+    fn test_parse_definite_assignment_assertion() {
+        let program = parse("class P { id!: number; name?: string; }").unwrap();
+        if let ModuleItem::Decl(decl) = &program.items[0].value {
+            if let Decl::Class(class_decl) = &decl.value {
+                let flags: Vec<(bool, bool)> = class_decl
+                    .members
+                    .iter()
+                    .filter_map(|m| match m {
+                        ClassMember::Property { is_optional, is_definite, .. } => Some((*is_optional, *is_definite)),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(flags, vec![(false, true), (true, false)]);
+                return;
+            }
+        }
+        panic!("expected a class declaration");
+    }
+
+    #[test]
+    fn test_parameter_properties_only_in_constructors() {
+        assert!(parse("function f(public x: number) {}").is_err());
+        assert!(parse("class P { m(private x: number) {} }").is_err());
+        assert!(parse("class P { constructor(public { x }: { x: number }) {} }").is_err());
+        assert!(parse("class P { constructor(readonly ...xs: number[]) {} }").is_err());
+        assert!(parse("class P { constructor(protected x: number, readonly y: number) {} }").is_ok());
+    }
+
+    #[test]
+    fn test_parse_comma_operator() {
+        let source = "let x = (f(), 5); i++, j--; g(a, b);";
+        let program = parse(source).unwrap();
+        let exprs: Vec<&Expr> = program
+            .items
+            .iter()
+            .filter_map(|item| match &item.value {
+                ModuleItem::Stmt(stmt) => match &stmt.value {
+                    Stmt::VarDecl(decl) => decl.declarations[0].init.as_ref().map(|init| &init.value),
+                    Stmt::Expr(expr) => Some(&expr.value),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(exprs[0], Expr::Paren(inner) if matches!(&inner.value, Expr::Sequence(seq) if seq.len() == 2)));
+        assert!(matches!(exprs[1], Expr::Sequence(seq) if seq.len() == 2));
+        // Call arguments are not a sequence
+        assert!(matches!(exprs[2], Expr::Call { args, .. } if args.len() == 2));
+    }
+
+    #[test]
+    fn test_parse_for_update_sequence() {
+        let source = "for (let i = 0, j = 10; i < j; i++, j--) {}";
+        let program = parse(source).unwrap();
+        if let ModuleItem::Stmt(stmt) = &program.items[0].value {
+            if let Stmt::For { update: Some(update), .. } = &stmt.value {
+                assert!(matches!(&update.value, Expr::Sequence(seq) if seq.len() == 2));
+                return;
+            }
+        }
+        panic!("expected a for loop with an update clause");
+    }
+
+    #[test]
+    fn test_parse_for_init_sequence() {
+        let source = "for (a = 1, b = 2; a < b; a++) {}";
+        let program = parse(source).unwrap();
+        if let ModuleItem::Stmt(stmt) = &program.items[0].value {
+            if let Stmt::For { init: Some(ForInit::Expr(init)), .. } = &stmt.value {
+                assert!(matches!(&init.value, Expr::Sequence(seq) if seq.len() == 2));
+                return;
+            }
+        }
+        panic!("expected a for loop with an expression init");
+    }
+
+    #[test]
+    fn test_parse_tagged_template_keeps_raw_parts() {
+        let source = r"tag`a\n${x}b${ {y: 1}.y }`;";
+        let program = parse(source).unwrap();
+        if let ModuleItem::Stmt(stmt) = &program.items[0].value {
+            if let Stmt::Expr(expr) = &stmt.value {
+                if let Expr::TaggedTemplate { parts, raw, exprs, .. } = &expr.value {
+                    assert_eq!(parts, &["a\n", "b", ""]);
+                    assert_eq!(raw, &["a\\n", "b", ""]);
+                    assert_eq!(exprs.len(), 2);
+                    return;
+                }
+            }
+        }
+        panic!("expected a tagged template");
+    }
+
+    #[test]
+    fn test_parse_nested_type_arguments_and_async_methods() {
+        let source = "class C { async next(): Promise<Array<number>> { return []; } async: number; }\nlet x = 8 >> 1;";
+        let program = parse(source).unwrap();
+        assert_eq!(program.items.len(), 2);
+        let ModuleItem::Decl(decl) = &program.items[0].value else { panic!("expected a class") };
+        let Decl::Class(class_decl) = &decl.value else { panic!("expected a class") };
+        let ClassMember::Method { is_async, return_type: Some(ret), .. } = &class_decl.members[0] else {
+            panic!("expected an async method");
+        };
+        assert!(*is_async);
+        let Type::TypeRef { type_args: Some(args), .. } = &ret.value else { panic!("expected Promise<...>") };
+        assert!(matches!(&args[0].value, Type::TypeRef { type_args: Some(inner), .. } if inner.len() == 1));
+        assert!(matches!(&class_decl.members[1], ClassMember::Property { .. }));
+    }
+
+    #[test]
+    fn test_parse_const_enum_and_member_values() {
+        let source = "const enum Flags { A = 1 << 0, B = 1 << 1, AB = A | Flags.B, C }\nexport const enum Dir { Up = \"UP\", Label = Up + \"!\" }";
+        let program = parse(source).unwrap();
+        let ModuleItem::Decl(decl) = &program.items[0].value else { panic!("expected a declaration") };
+        let Decl::Enum(flags) = &decl.value else { panic!("expected an enum") };
+        assert!(flags.is_const);
+        let values: Vec<_> = flags.member_values().into_iter().map(|(_, v)| v.unwrap()).collect();
+        assert_eq!(values, [1.0, 2.0, 3.0, 4.0].map(EnumValue::Number));
+        let ModuleItem::Export(ExportDecl::Decl(decl)) = &program.items[1].value else { panic!("expected an export") };
+        let Decl::Enum(dir) = &decl.value else { panic!("expected an enum") };
+        assert_eq!(dir.member_values()[1].1, Some(EnumValue::String("UP!".to_string())));
+    }
+
+    #[test]
+    fn test_parse_declare_global_and_ambient_const() {
+        let source = "declare global {\n  const FLAG: boolean;\n  function greet(name: string): string;\n}\ndeclare const LIMIT: number;";
+        let program = parse(source).unwrap();
+        assert_eq!(program.global_declarations().count(), 2);
+        assert!(matches!(&program.items[1].value, ModuleItem::Decl(decl) if matches!(decl.value, Decl::Var(_))));
+
+        // Outside `declare`, a const needs a value
+        let errors = parse("const LIMIT: number;").unwrap_err();
+        assert_eq!(errors[0].message, "const declarations must be initialized");
+    }
+
+    #[test]
+    fn test_parse_dotted_namespace() {
+        let program = parse("namespace A.B { export const x = 1; }").unwrap();
+        let ModuleItem::Decl(decl) = &program.items[0].value else { panic!("expected a declaration") };
+        let Decl::Module(ModuleDecl { name: ModuleName::Ident(outer), body: ModuleBody::Namespace(inner), .. }) = &decl.value else {
+            panic!("expected a nested namespace, got {:?}", decl.value)
+        };
+        assert_eq!(outer.value.name, "A");
+        assert!(matches!(&inner.value.name, ModuleName::Ident(name) if name.value.name == "B"));
+        assert!(matches!(&inner.value.body, ModuleBody::Block(items) if items.len() == 1));
+    }
+
+    #[test]
+    fn test_parse_object_accessors() {
+        let program = parse("const o = { get: 1, set() {}, get total() { return 1; }, set total(v) {} };").unwrap();
+        let ModuleItem::Stmt(stmt) = &program.items[0].value else { panic!("expected a statement") };
+        let Stmt::VarDecl(var_decl) = &stmt.value else { panic!("expected a variable, got {:?}", stmt.value) };
+        let Some(Expr::Object(props)) = var_decl.declarations[0].init.as_ref().map(|init| &init.value) else {
+            panic!("expected an object literal")
+        };
+        assert!(matches!(&props[0], ObjectProperty::Property { .. }));
+        assert!(matches!(&props[1], ObjectProperty::Method { .. }));
+        assert!(matches!(&props[2], ObjectProperty::Getter { key: PropertyName::Ident(key), .. } if key.value.name == "total"));
+        assert!(matches!(&props[3], ObjectProperty::Setter { key: PropertyName::Ident(key), .. } if key.value.name == "total"));
+    }
 }
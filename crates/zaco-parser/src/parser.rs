@@ -6,12 +6,14 @@ use super::*;
 pub struct Parser {
     pub(crate) tokens: Vec<Token>,
     pub(crate) current: usize,
+    /// Inside a `declare` declaration, where `const` needs no initializer
+    pub(crate) in_ambient: bool,
 }
 
 impl Parser {
     /// Creates a new parser from a token stream
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self { tokens, current: 0, in_ambient: false }
     }
 
     /// Parses a complete program
@@ -53,7 +55,8 @@ impl Parser {
         let start = self.current_token().span;
 
         let item = match self.current_token().kind {
-            TokenKind::Import => {
+            // `import.meta` starts an expression statement, not a declaration
+            TokenKind::Import if self.peek_kind(1) != Some(&TokenKind::Dot) => {
                 let import_decl = self.parse_import_decl()?;
                 let _span = start.merge(&self.previous_token().span);
                 ModuleItem::Import(import_decl)
@@ -81,6 +84,10 @@ impl Parser {
                 let decl = self.parse_declaration()?;
                 ModuleItem::Decl(decl)
             }
+            TokenKind::Const if self.peek_kind(1) == Some(&TokenKind::Enum) => {
+                let decl = self.parse_declaration()?;
+                ModuleItem::Decl(decl)
+            }
             TokenKind::Const | TokenKind::Let | TokenKind::Var => {
                 // Could be either declaration or statement
                 let stmt = self.parse_statement()?;
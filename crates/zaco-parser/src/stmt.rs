@@ -64,7 +64,7 @@ impl Parser {
                     let stmt = Box::new(self.parse_statement()?);
                     Stmt::Labeled { label, stmt }
                 } else {
-                    let expr = self.parse_expression()?;
+                    let expr = self.parse_sequence_expression()?;
                     self.consume_semicolon();
                     Stmt::Expr(expr)
                 }
@@ -120,6 +120,10 @@ impl Parser {
             self.advance();
         }
 
+        if kind == VarDeclKind::Const && !self.in_ambient && declarations.iter().any(|d| d.init.is_none()) {
+            return Err(self.error("const declarations must be initialized".to_string()));
+        }
+
         self.consume_semicolon();
 
         Ok(VarDecl { kind, declarations })
@@ -244,7 +248,7 @@ impl Parser {
         ) {
             Some(ForInit::VarDecl(self.parse_var_declaration_without_semicolon()?))
         } else {
-            Some(ForInit::Expr(self.parse_expression()?))
+            Some(ForInit::Expr(self.parse_sequence_expression()?))
         };
 
         self.consume(TokenKind::Semicolon)?;
@@ -260,7 +264,7 @@ impl Parser {
         let update = if self.check(&TokenKind::RParen) {
             None
         } else {
-            Some(self.parse_expression()?)
+            Some(self.parse_sequence_expression()?)
         };
 
         self.consume(TokenKind::RParen)?;
@@ -380,6 +380,23 @@ impl Parser {
                     types: vec![],
                 }
             }
+            // `prefix-${string}-suffix`
+            TokenKind::TemplateHead => {
+                let mut parts = vec![self.advance().value.clone()];
+                let mut types = Vec::new();
+                loop {
+                    types.push(self.parse_type()?);
+                    let kind = self.current_token().kind.clone();
+                    if !matches!(kind, TokenKind::TemplateMiddle | TokenKind::TemplateTail) {
+                        return Err(self.error(format!("Expected TemplateTail, found {:?}", kind)));
+                    }
+                    parts.push(self.advance().value.clone());
+                    if kind == TokenKind::TemplateTail {
+                        break;
+                    }
+                }
+                Type::TemplateLiteral { parts, types }
+            }
             TokenKind::True => {
                 self.advance();
                 Type::Literal(LiteralType::Boolean(true))
@@ -405,6 +422,12 @@ impl Parser {
                 Type::TypeofType(inner)
             }
 
+            // Polymorphic `this` type: the class of the receiver
+            TokenKind::This => {
+                let span = self.advance().span;
+                Type::TypeRef { name: Node::new(Ident::new("this"), span), type_args: None }
+            }
+
             // infer type: infer T
             TokenKind::Infer => {
                 self.advance();
@@ -574,7 +597,7 @@ impl Parser {
         self.advance();
         let mut params = Vec::new();
 
-        while !self.check(&TokenKind::Gt) && !self.is_at_end() {
+        while !self.check_closing_angle() && !self.is_at_end() {
             let name = self.parse_identifier()?;
 
             let constraint = if self.check(&TokenKind::Extends) {
@@ -597,12 +620,12 @@ impl Parser {
                 default,
             });
 
-            if !self.check(&TokenKind::Gt) {
+            if !self.check_closing_angle() {
                 self.consume(TokenKind::Comma)?;
             }
         }
 
-        self.consume(TokenKind::Gt)?;
+        self.consume_closing_angle()?;
         Ok(Some(params))
     }
 
@@ -614,14 +637,14 @@ impl Parser {
         self.advance();
         let mut args = Vec::new();
 
-        while !self.check(&TokenKind::Gt) && !self.is_at_end() {
+        while !self.check_closing_angle() && !self.is_at_end() {
             args.push(self.parse_type()?);
-            if !self.check(&TokenKind::Gt) {
+            if !self.check_closing_angle() {
                 self.consume(TokenKind::Comma)?;
             }
         }
 
-        self.consume(TokenKind::Gt)?;
+        self.consume_closing_angle()?;
         Ok(Some(args))
     }
 
@@ -4,7 +4,8 @@
 //! and global objects like Math, JSON, console.
 
 use std::collections::HashMap;
-use crate::types::Type;
+use crate::types::{LiteralType, Type};
+use zaco_ast::DecoratorKind;
 
 /// Registry of built-in module types
 pub struct BuiltinRegistry {
@@ -46,11 +47,19 @@ impl BuiltinRegistry {
         self.register_os_module();
         self.register_http_module();
         self.register_events_module();
+        self.register_worker_module();
+        self.register_sqlite_module();
+        self.register_timers_module();
+        self.register_assert_module();
+        self.register_util_module();
+        self.register_glob_module();
     }
 
     fn register_fs_module(&mut self) {
         let mut exports = HashMap::new();
 
+        exports.insert("globSync".to_string(), Self::glob_sync_type());
+
         // readFileSync(path: string, encoding: string) => string
         exports.insert(
             "readFileSync".to_string(),
@@ -364,6 +373,299 @@ impl BuiltinRegistry {
         self.register_module("events", exports);
     }
 
+    fn register_worker_module(&mut self) {
+        let mut exports = HashMap::new();
+
+        // spawn(fnName: string, args: any[]) => Promise<any>
+        // The checker additionally requires `args` to be structured-cloneable
+        exports.insert(
+            "spawn".to_string(),
+            Type::Function {
+                params: vec![Type::String, Type::Array(Box::new(Type::Any))],
+                return_type: Box::new(Type::Promise(Box::new(Type::Any))),
+            },
+        );
+
+        self.register_module("worker", exports);
+    }
+
+    fn register_sqlite_module(&mut self) {
+        let mut exports = HashMap::new();
+        let method = |params: Vec<Type>, ret: Type| Type::Function {
+            params,
+            return_type: Box::new(ret),
+        };
+
+        // class Database { exec(sql); query(sql, params) => row objects; close() }
+        // Rows are plain objects keyed by column name, so they are typed `any`
+        exports.insert(
+            "Database".to_string(),
+            Type::Class {
+                name: "Database".to_string(),
+                fields: vec![],
+                methods: vec![
+                    ("exec".to_string(), method(vec![Type::String], Type::Void)),
+                    (
+                        "query".to_string(),
+                        method(
+                            vec![Type::String, Type::Array(Box::new(Type::Any))],
+                            Type::Array(Box::new(Type::Any)),
+                        ),
+                    ),
+                    ("close".to_string(), method(vec![], Type::Void)),
+                ],
+                accessors: vec![],
+                statics: vec![],
+            },
+        );
+
+        // open(path: string) => Database
+        exports.insert(
+            "open".to_string(),
+            method(
+                vec![Type::String],
+                Type::TypeRef { name: "Database".to_string(), type_args: vec![] },
+            ),
+        );
+
+        self.register_module("sqlite", exports);
+    }
+
+    fn register_timers_module(&mut self) {
+        let mut exports = HashMap::new();
+
+        // sleep(ms: number, options?: { signal }) => Promise<void>
+        // Signatures have no optional parameters, so it takes a single `any`
+        exports.insert(
+            "sleep".to_string(),
+            Type::Function {
+                params: vec![Type::Any],
+                return_type: Box::new(Type::Promise(Box::new(Type::Void))),
+            },
+        );
+
+        self.register_module("timers", exports);
+    }
+
+    fn register_assert_module(&mut self) {
+        let mut exports = HashMap::new();
+
+        // ok(cond, msg?), equal(a, b, msg?), deepEqual(a, b, msg?), throws(fn, msg?)
+        // All return void and throw an AssertionError on failure; a single
+        // `any` param lets each take its optional message
+        for name in ["ok", "equal", "strictEqual", "deepEqual", "deepStrictEqual", "throws"] {
+            exports.insert(
+                name.to_string(),
+                Type::Function {
+                    params: vec![Type::Any],
+                    return_type: Box::new(Type::Void),
+                },
+            );
+        }
+
+        self.register_module("assert", exports);
+    }
+
+    fn register_util_module(&mut self) {
+        let mut exports = HashMap::new();
+
+        // isDeepStrictEqual(a: any, b: any) => boolean
+        exports.insert(
+            "isDeepStrictEqual".to_string(),
+            Type::Function {
+                params: vec![Type::Any, Type::Any],
+                return_type: Box::new(Type::Boolean),
+            },
+        );
+
+        // styleText(format: string, text: string) => string
+        exports.insert(
+            "styleText".to_string(),
+            Type::Function {
+                params: vec![Type::String, Type::String],
+                return_type: Box::new(Type::String),
+            },
+        );
+
+        self.register_module("util", exports);
+    }
+
+    /// globSync(pattern, options?: { cwd, dot }) => string[], also exported by fs
+    fn glob_sync_type() -> Type {
+        Type::Function {
+            params: vec![Type::Any],
+            return_type: Box::new(Type::Array(Box::new(Type::String))),
+        }
+    }
+
+    fn register_glob_module(&mut self) {
+        let mut exports = HashMap::new();
+        exports.insert("globSync".to_string(), Self::glob_sync_type());
+        self.register_module("glob", exports);
+    }
+
+}
+
+/// Type of a property on a string value (`s.length`, `s.trim`, ...).
+/// Methods with optional parameters take a single `any`, which the call
+/// checker treats as variadic.
+pub fn string_member_type(name: &str) -> Option<Type> {
+    let method = |params: Vec<Type>, ret: Type| Type::Function {
+        params,
+        return_type: Box::new(ret),
+    };
+    let ty = match name {
+        "length" => Type::Number,
+        "toUpperCase" | "toLowerCase" | "trim" => method(vec![], Type::String),
+        "indexOf" => method(vec![Type::String], Type::Number),
+        "includes" | "startsWith" | "endsWith" => method(vec![Type::String], Type::Boolean),
+        "charAt" | "repeat" => method(vec![Type::Number], Type::String),
+        "replace" => method(vec![Type::String, Type::String], Type::String),
+        "split" => method(vec![Type::String], Type::Array(Box::new(Type::String))),
+        "slice" | "padStart" | "padEnd" => method(vec![Type::Any], Type::String),
+        "matchAll" => method(vec![Type::Any], Type::Array(Box::new(regexp_match_array_type()))),
+        _ => return None,
+    };
+    Some(ty)
+}
+
+/// Type of a property on an array of `elem` (`a.length`, `a.push`)
+pub fn array_member_type(name: &str, elem: &Type) -> Option<Type> {
+    let ty = match name {
+        "length" => Type::Number,
+        "push" => Type::Function {
+            params: vec![elem.clone()],
+            return_type: Box::new(Type::Number),
+        },
+        // fill(value, start?, end?) and copyWithin(target, start?, end?)
+        // return the array itself
+        "fill" | "copyWithin" => Type::Function {
+            params: vec![Type::Any],
+            return_type: Box::new(Type::Array(Box::new(elem.clone()))),
+        },
+        "reverse" => Type::Function {
+            params: vec![],
+            return_type: Box::new(Type::Array(Box::new(elem.clone()))),
+        },
+        // concat(...items) returns a new array; array items are flattened
+        // into it one level deep
+        "concat" => Type::Function {
+            params: vec![Type::Any],
+            return_type: Box::new(Type::Array(Box::new(elem.clone()))),
+        },
+        _ => return None,
+    };
+    Some(ty)
+}
+
+/// `TemplateStringsArray`, the first argument of a tag function: the cooked
+/// template parts, plus the parts as written in `raw`
+pub fn template_strings_array_type() -> Type {
+    Type::Intersection(vec![
+        Type::Array(Box::new(Type::String)),
+        Type::Object {
+            properties: vec![("raw".to_string(), Type::Array(Box::new(Type::String)), false)],
+        },
+    ])
+}
+
+/// `RegExpMatchArray`, one match of `str.matchAll(re)`: the whole match and
+/// its capture groups, plus where the match starts in `index`
+pub fn regexp_match_array_type() -> Type {
+    Type::Intersection(vec![
+        Type::Array(Box::new(Type::String)),
+        Type::Object {
+            properties: vec![("index".to_string(), Type::Number, false)],
+        },
+    ])
+}
+
+/// The context object a decorator receives as its second argument, by the
+/// name of its type (`ClassDecoratorContext`, ...)
+pub fn decorator_context_type(type_name: &str) -> Option<Type> {
+    [DecoratorKind::Class, DecoratorKind::Method, DecoratorKind::Field]
+        .into_iter()
+        .find(|kind| kind.context_type_name() == type_name)
+        .map(decorator_context)
+}
+
+/// Context object for a decorator of the given kind
+pub fn decorator_context(kind: DecoratorKind) -> Type {
+    let mut properties = vec![
+        ("kind".to_string(), Type::Literal(LiteralType::String(kind.as_str().to_string())), false),
+        ("name".to_string(), Type::String, false),
+    ];
+    if kind != DecoratorKind::Class {
+        properties.push(("static".to_string(), Type::Boolean, false));
+        properties.push(("private".to_string(), Type::Boolean, false));
+    }
+    Type::Object { properties }
+}
+
+/// A generator object: `next(value?)` resumes it and returns `{ value, done }`
+pub fn generator_type(yield_ty: Type) -> Type {
+    iterator_type(iterator_result_type(yield_ty))
+}
+
+/// An async generator object: `next(value?)` returns a promise of `{ value, done }`
+pub fn async_generator_type(yield_ty: Type) -> Type {
+    iterator_type(Type::Promise(Box::new(iterator_result_type(yield_ty))))
+}
+
+fn iterator_result_type(value_ty: Type) -> Type {
+    Type::Object {
+        properties: vec![
+            ("value".to_string(), value_ty, false),
+            ("done".to_string(), Type::Boolean, false),
+        ],
+    }
+}
+
+fn iterator_type(next_result: Type) -> Type {
+    Type::Object {
+        properties: vec![(
+            "next".to_string(),
+            Type::Function { params: vec![Type::Any], return_type: Box::new(next_result) },
+            false,
+        )],
+    }
+}
+
+/// Type of the values a (sync or async) generator object produces: the
+/// `value` of what its `next()` returns, after awaiting it if it is a promise
+pub fn generator_value_type(ty: &Type) -> Option<Type> {
+    let Type::Object { properties } = ty else {
+        return None;
+    };
+    let next_result = properties.iter().find_map(|(name, ty, _)| match ty {
+        Type::Function { return_type, .. } if name == "next" => Some(&**return_type),
+        _ => None,
+    })?;
+    let result = match next_result {
+        Type::Promise(inner) => &**inner,
+        other => other,
+    };
+    match result {
+        Type::Object { properties } => {
+            properties.iter().find(|(name, _, _)| name == "value").map(|(_, ty, _)| ty.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Type of `promise.<name>` on a `Promise<value>`. Callbacks are untyped
+/// (a single `any` parameter accepts one or two of them); `finally` keeps
+/// the value type, `then` and `catch` may replace it.
+pub fn promise_member_type(name: &str, value: &Type) -> Option<Type> {
+    let result = match name {
+        "then" | "catch" => Type::Any,
+        "finally" => value.clone(),
+        _ => return None,
+    };
+    Some(Type::Function {
+        params: vec![Type::Any],
+        return_type: Box::new(Type::Promise(Box::new(result))),
+    })
 }
 
 impl Default for BuiltinRegistry {
@@ -376,6 +678,57 @@ impl Default for BuiltinRegistry {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_string_members() {
+        assert_eq!(string_member_type("length"), Some(Type::Number));
+        match string_member_type("trim") {
+            Some(Type::Function { params, return_type }) => {
+                assert!(params.is_empty());
+                assert_eq!(*return_type, Type::String);
+            }
+            other => panic!("expected method type, got {:?}", other),
+        }
+        assert_eq!(string_member_type("nope"), None);
+    }
+
+    #[test]
+    fn test_array_members() {
+        assert_eq!(array_member_type("length", &Type::String), Some(Type::Number));
+        assert_eq!(
+            array_member_type("push", &Type::String),
+            Some(Type::Function { params: vec![Type::String], return_type: Box::new(Type::Number) })
+        );
+        assert_eq!(
+            array_member_type("fill", &Type::Number),
+            Some(Type::Function { params: vec![Type::Any], return_type: Box::new(Type::Array(Box::new(Type::Number))) })
+        );
+        assert_eq!(
+            array_member_type("reverse", &Type::String),
+            Some(Type::Function { params: vec![], return_type: Box::new(Type::Array(Box::new(Type::String))) })
+        );
+        assert_eq!(array_member_type("nope", &Type::String), None);
+    }
+
+    #[test]
+    fn test_promise_members() {
+        match promise_member_type("finally", &Type::Number) {
+            Some(Type::Function { params, return_type }) => {
+                assert_eq!(params, vec![Type::Any]);
+                assert_eq!(*return_type, Type::Promise(Box::new(Type::Number)));
+            }
+            other => panic!("expected method type, got {:?}", other),
+        }
+        assert!(promise_member_type("catch", &Type::Number).is_some());
+        assert_eq!(promise_member_type("resolve", &Type::Number), None);
+    }
+
+    #[test]
+    fn test_generator_value_types() {
+        assert_eq!(generator_value_type(&generator_type(Type::String)), Some(Type::String));
+        assert_eq!(generator_value_type(&async_generator_type(Type::Number)), Some(Type::Number));
+        assert_eq!(generator_value_type(&Type::Array(Box::new(Type::Number))), None);
+    }
+
     #[test]
     fn test_builtin_module_recognition() {
         let registry = BuiltinRegistry::new();
@@ -386,6 +739,12 @@ mod tests {
         assert!(registry.is_builtin_module("os"));
         assert!(registry.is_builtin_module("http"));
         assert!(registry.is_builtin_module("events"));
+        assert!(registry.is_builtin_module("worker"));
+        assert!(registry.is_builtin_module("sqlite"));
+        assert!(registry.is_builtin_module("timers"));
+        assert!(registry.is_builtin_module("assert"));
+        assert!(registry.is_builtin_module("util"));
+        assert!(registry.is_builtin_module("glob"));
         assert!(!registry.is_builtin_module("unknown"));
     }
 
@@ -413,6 +772,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sqlite_exports() {
+        let registry = BuiltinRegistry::new();
+
+        match registry.get_export_type("sqlite", "open") {
+            Some(Type::Function { params, return_type }) => {
+                assert_eq!(params, &vec![Type::String]);
+                assert!(matches!(&**return_type, Type::TypeRef { name, .. } if name == "Database"));
+            }
+            other => panic!("Expected function type, got {:?}", other),
+        }
+        match registry.get_export_type("sqlite", "Database") {
+            Some(Type::Class { methods, .. }) => {
+                let names: Vec<&str> = methods.iter().map(|(n, _)| n.as_str()).collect();
+                assert_eq!(names, vec!["exec", "query", "close"]);
+            }
+            other => panic!("Expected class type, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_path_exports() {
         let registry = BuiltinRegistry::new();
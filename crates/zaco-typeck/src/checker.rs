@@ -1,9 +1,10 @@
 //! Main type checker struct
 
-use zaco_ast::{ModuleItem, Program, Span, ImportDecl, ImportSpecifier, ExportDecl};
+use std::collections::{HashMap, HashSet};
+use zaco_ast::{AccessModifier, Literal, ModuleItem, Node, Program, Span, ImportDecl, ImportSpecifier, ExportDecl};
 use crate::env::TypeEnv;
 use crate::error::{TypeError, TypeErrorKind};
-use crate::types::Type;
+use crate::types::{LiteralType, Type};
 use crate::ownership::{OwnershipState, VarInfo};
 use crate::typed_ast::{TypedDecl, TypedModuleItem, TypedProgram, TypedStmt};
 use crate::builtins::BuiltinRegistry;
@@ -15,6 +16,41 @@ pub struct TypeChecker {
     pub(crate) builtin_registry: BuiltinRegistry,
     /// The declared return type of the current function being checked (for return-type validation)
     pub(crate) current_return_type: Option<Type>,
+    /// Span of that return type's annotation
+    pub(crate) current_return_span: Option<Span>,
+    /// Types of `return <expr>` statements seen in the current function body,
+    /// used to infer the return type when there is no annotation
+    pub(crate) returned_types: Vec<Type>,
+    /// Inside a function declaration: whether its body reads `arguments`.
+    /// `None` where `arguments` is not available.
+    pub(crate) arguments_used: Option<bool>,
+    /// Local names bound to `spawn` from the "worker" module, whose
+    /// arguments must be structured-cloneable
+    pub(crate) worker_spawn_names: HashSet<String>,
+    /// Type of `super` inside the method being checked, if its class extends another
+    pub(crate) current_super_type: Option<Type>,
+    /// Class whose method bodies are being checked
+    pub(crate) current_class: Option<String>,
+    /// Classes whose constructor is private or protected
+    pub(crate) restricted_constructors: HashMap<String, AccessModifier>,
+    /// Built-in globals in registration order; these are also the
+    /// properties of `globalThis`
+    pub(crate) globals: Vec<(String, Type)>,
+    /// Type names exported by local modules, keyed by import source as
+    /// written. Type-only imports from a listed module are checked against it.
+    pub(crate) module_types: HashMap<String, HashSet<String>>,
+    /// Items of other modules' `declare global` blocks
+    pub(crate) ambient_globals: Vec<Node<ModuleItem>>,
+    /// Checking ambient declarations, whose variables need no initializer
+    pub(crate) in_ambient: bool,
+    /// Types the call being checked gives the parameters of its callback
+    /// argument, such as `Array.from`'s `(value, index)`. Taken by the next
+    /// arrow or function expression, for its unannotated parameters.
+    pub(crate) callback_params: Option<Vec<Type>>,
+    /// The array literal checked next is expected to be a tuple, such as the
+    /// `[ok, value]` a function declared to return `[boolean, string]`
+    /// returns, and is typed element by element instead of as an array
+    pub(crate) tuple_context: bool,
 }
 
 impl TypeChecker {
@@ -24,15 +60,50 @@ impl TypeChecker {
             errors: Vec::new(),
             builtin_registry: BuiltinRegistry::new(),
             current_return_type: None,
+            current_return_span: None,
+            returned_types: Vec::new(),
+            arguments_used: None,
+            worker_spawn_names: HashSet::new(),
+            current_super_type: None,
+            current_class: None,
+            restricted_constructors: HashMap::new(),
+            globals: Vec::new(),
+            module_types: HashMap::new(),
+            ambient_globals: Vec::new(),
+            in_ambient: false,
+            callback_params: None,
+            tuple_context: false,
         };
         checker.register_builtins();
         checker
     }
 
+    /// Provide the type names each local module exports (see `module_types`)
+    pub fn with_module_types(mut self, module_types: HashMap<String, HashSet<String>>) -> Self {
+        self.module_types = module_types;
+        self
+    }
+
+    /// Provide the `declare global` items of the other modules in the program
+    pub fn with_ambient_globals(mut self, ambient_globals: Vec<Node<ModuleItem>>) -> Self {
+        self.ambient_globals = ambient_globals;
+        self
+    }
+
+    /// Declare the constants given with `--define NAME=value` as globals of
+    /// their literal types
+    pub fn with_defines(mut self, defines: HashMap<String, Literal>) -> Self {
+        for (name, value) in &defines {
+            let ty = self.check_literal(value);
+            self.declare_global(name, ty);
+        }
+        self
+    }
+
     /// Register built-in global variables and functions
     fn register_builtins(&mut self) {
         // console object: console.log, console.error, console.warn, etc.
-        let console_methods = vec![
+        let mut console_methods = vec![
             ("log".to_string(), Type::Function {
                 params: vec![Type::Any],
                 return_type: Box::new(Type::Void),
@@ -54,12 +125,14 @@ impl TypeChecker {
                 return_type: Box::new(Type::Void),
             }, false),
         ];
-        self.env.declare("console".to_string(), VarInfo {
-            ty: Type::Object { properties: console_methods },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
-        });
+        // table, time, timeEnd, group, groupEnd and assert take any arguments
+        for name in ["table", "time", "timeEnd", "group", "groupEnd", "assert"] {
+            console_methods.push((name.to_string(), Type::Function {
+                params: vec![Type::Any],
+                return_type: Box::new(Type::Void),
+            }, false));
+        }
+        self.declare_global("console", Type::Object { properties: console_methods });
 
         // Math object - expanded with all methods
         let math_methods = vec![
@@ -118,12 +191,7 @@ impl TypeChecker {
             ("PI".to_string(), Type::Number, false),
             ("E".to_string(), Type::Number, false),
         ];
-        self.env.declare("Math".to_string(), VarInfo {
-            ty: Type::Object { properties: math_methods },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
-        });
+        self.declare_global("Math", Type::Object { properties: math_methods });
 
         // JSON object
         let json_methods = vec![
@@ -136,11 +204,62 @@ impl TypeChecker {
                 return_type: Box::new(Type::Any),
             }, false),
         ];
-        self.env.declare("JSON".to_string(), VarInfo {
-            ty: Type::Object { properties: json_methods },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
+        self.declare_global("JSON", Type::Object { properties: json_methods });
+
+        // Object statics; check_call types their results from the argument
+        let object_statics = vec![
+            ("keys", Type::Array(Box::new(Type::String))),
+            ("values", Type::Array(Box::new(Type::Any))),
+            ("entries", Type::Array(Box::new(Type::Tuple(vec![Type::String, Type::Any])))),
+            ("fromEntries", Type::Any),
+            ("assign", Type::Any),
+            ("freeze", Type::Any),
+        ];
+        let object_statics = object_statics
+            .into_iter()
+            .map(|(name, return_type)| {
+                let ty = Type::Function { params: vec![Type::Any], return_type: Box::new(return_type) };
+                (name.to_string(), ty, false)
+            })
+            .collect();
+        self.declare_global("Object", Type::Object { properties: object_statics });
+
+        // Array.from/of; check_call types their element type from the arguments
+        let array_statics = ["from", "of"]
+            .into_iter()
+            .map(|name| {
+                let ty = Type::Function {
+                    params: vec![Type::Any],
+                    return_type: Box::new(Type::Array(Box::new(Type::Any))),
+                };
+                (name.to_string(), ty, false)
+            })
+            .collect();
+        self.declare_global("Array", Type::Object { properties: array_statics });
+
+        // Promise object
+        let promise_methods = vec![
+            ("all".to_string(), Type::Function {
+                params: vec![Type::Array(Box::new(Type::Any))],
+                return_type: Box::new(Type::Promise(Box::new(Type::Array(Box::new(Type::Any))))),
+            }, false),
+        ];
+        self.declare_global("Promise", Type::Object { properties: promise_methods });
+
+        // String.raw`...` is a built-in tag
+        self.declare_global("String", Type::Object {
+            properties: vec![("raw".to_string(), Type::Function {
+                params: vec![crate::builtins::template_strings_array_type()],
+                return_type: Box::new(Type::String),
+            }, false)],
+        });
+
+        // Well-known symbols key protocol methods such as `[Symbol.iterator]()`
+        self.declare_global("Symbol", Type::Object {
+            properties: zaco_ast::WELL_KNOWN_SYMBOLS
+                .iter()
+                .map(|name| (name.to_string(), Type::Symbol, false))
+                .collect(),
         });
 
         // process object (available globally without import, like in Node.js)
@@ -158,109 +277,171 @@ impl TypeChecker {
             ("platform".to_string(), Type::String, false),
             ("arch".to_string(), Type::String, false),
             ("argv".to_string(), Type::Array(Box::new(Type::String)), false),
+            ("stdout".to_string(), Type::Object {
+                properties: vec![
+                    ("isTTY".to_string(), Type::Boolean, false),
+                    ("columns".to_string(), Type::Number, false),
+                ],
+            }, false),
+            // on("exit" | "SIGINT" | "SIGTERM" | "SIGHUP", listener)
+            ("on".to_string(), Type::Function {
+                params: vec![
+                    Type::Union(["exit", "SIGINT", "SIGTERM", "SIGHUP"].iter()
+                        .map(|event| Type::Literal(LiteralType::String(event.to_string())))
+                        .collect()),
+                    Type::Function { params: vec![], return_type: Box::new(Type::Void) },
+                ],
+                return_type: Box::new(Type::Void),
+            }, false),
         ];
-        self.env.declare("process".to_string(), VarInfo {
-            ty: Type::Object { properties: process_properties },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
-        });
+        self.declare_global("process", Type::Object { properties: process_properties });
 
         // Global functions
-        self.env.declare("parseInt".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::String],
-                return_type: Box::new(Type::Number),
-            },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
+        self.declare_global("parseInt", Type::Function {
+            params: vec![Type::String],
+            return_type: Box::new(Type::Number),
         });
-        self.env.declare("parseFloat".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::String],
-                return_type: Box::new(Type::Number),
-            },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
+        self.declare_global("parseFloat", Type::Function {
+            params: vec![Type::String],
+            return_type: Box::new(Type::Number),
         });
-        self.env.declare("isNaN".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::Any],
-                return_type: Box::new(Type::Boolean),
-            },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
+        self.declare_global("isNaN", Type::Function {
+            params: vec![Type::Any],
+            return_type: Box::new(Type::Boolean),
         });
-        self.env.declare("isFinite".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::Any],
-                return_type: Box::new(Type::Boolean),
-            },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
+        self.declare_global("isFinite", Type::Function {
+            params: vec![Type::Any],
+            return_type: Box::new(Type::Boolean),
         });
+        for name in ["encodeURIComponent", "decodeURIComponent", "btoa", "atob"] {
+            self.declare_global(name, Type::Function {
+                params: vec![Type::String],
+                return_type: Box::new(Type::String),
+            });
+        }
 
         // __dirname and __filename globals (Node.js-style)
-        self.env.declare("__dirname".to_string(), VarInfo {
-            ty: Type::String,
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
-        });
-        self.env.declare("__filename".to_string(), VarInfo {
-            ty: Type::String,
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
-        });
+        self.declare_global("__dirname", Type::String);
+        self.declare_global("__filename", Type::String);
 
         // Timer functions
-        self.env.declare("setTimeout".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::Any, Type::Number],
-                return_type: Box::new(Type::Number),
-            },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
+        self.declare_global("setTimeout", Type::Function {
+            params: vec![Type::Any, Type::Number],
+            return_type: Box::new(Type::Number),
         });
-        self.env.declare("setInterval".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::Any, Type::Number],
-                return_type: Box::new(Type::Number),
-            },
-            ownership: OwnershipState::Borrowed,
-            is_mutable: false,
-            is_initialized: true,
+        self.declare_global("setInterval", Type::Function {
+            params: vec![Type::Any, Type::Number],
+            return_type: Box::new(Type::Number),
         });
-        self.env.declare("clearTimeout".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::Number],
-                return_type: Box::new(Type::Void),
-            },
+        self.declare_global("clearTimeout", Type::Function {
+            params: vec![Type::Number],
+            return_type: Box::new(Type::Void),
+        });
+        self.declare_global("clearInterval", Type::Function {
+            params: vec![Type::Number],
+            return_type: Box::new(Type::Void),
+        });
+
+        // fetch(url, options?) => Promise<Response>
+        let method = |params, return_type| Type::Function {
+            params,
+            return_type: Box::new(return_type),
+        };
+        self.env.define_class("Response".to_string(), Type::Class {
+            name: "Response".to_string(),
+            fields: vec![
+                ("status".to_string(), Type::Number),
+                ("ok".to_string(), Type::Boolean),
+                ("headers".to_string(), Type::Object {
+                    properties: vec![("get".to_string(), method(vec![Type::String], Type::String), false)],
+                }),
+            ],
+            methods: vec![
+                ("text".to_string(), method(vec![], Type::Promise(Box::new(Type::String)))),
+                ("json".to_string(), method(vec![], Type::Promise(Box::new(Type::Any)))),
+            ],
+            accessors: vec![],
+            statics: vec![],
+        });
+        // Signatures have no optional parameters, so the single `any` param
+        // lets fetch take the url alone or with an options object
+        self.declare_global("fetch", method(
+            vec![Type::Any],
+            Type::Promise(Box::new(Type::TypeRef { name: "Response".to_string(), type_args: vec![] })),
+        ));
+
+        // new AbortController() => { signal: AbortSignal; abort(): void }
+        // AbortSignal.timeout(ms) => a signal that aborts itself after ms
+        let abort_signal_ref = Type::TypeRef { name: "AbortSignal".to_string(), type_args: vec![] };
+        let abort_signal = Type::Class {
+            name: "AbortSignal".to_string(),
+            fields: vec![("aborted".to_string(), Type::Boolean)],
+            methods: vec![],
+            accessors: vec![],
+            statics: vec![("timeout".to_string(), method(vec![Type::Number], abort_signal_ref.clone()))],
+        };
+        self.env.define_class("AbortSignal".to_string(), abort_signal.clone());
+        self.declare_global("AbortSignal", abort_signal);
+        let abort_controller = Type::Class {
+            name: "AbortController".to_string(),
+            fields: vec![("signal".to_string(), abort_signal_ref)],
+            methods: vec![("abort".to_string(), method(vec![], Type::Void))],
+            accessors: vec![],
+            statics: vec![],
+        };
+        self.env.define_class("AbortController".to_string(), abort_controller.clone());
+        self.declare_global("AbortController", abort_controller);
+
+        // structuredClone<T>(value: T): T deep copies its argument
+        let value_param = Type::Generic { name: "T".to_string(), constraint: None };
+        self.declare_global("structuredClone", method(vec![value_param.clone()], value_param));
+
+        // test(name, fn) registers a named test case for `zaco test`
+        self.declare_global("test", method(vec![Type::String, Type::Any], Type::Void));
+
+        // globalThis.X is the same binding as the bare global X
+        let properties = self.globals.iter()
+            .map(|(name, ty)| (name.clone(), ty.clone(), false))
+            .collect();
+        self.env.declare("globalThis".to_string(), VarInfo {
+            ty: Type::Object { properties },
             ownership: OwnershipState::Borrowed,
             is_mutable: false,
             is_initialized: true,
         });
-        self.env.declare("clearInterval".to_string(), VarInfo {
-            ty: Type::Function {
-                params: vec![Type::Number],
-                return_type: Box::new(Type::Void),
-            },
+    }
+
+    /// Declare a built-in global, also reachable as `globalThis.<name>`
+    fn declare_global(&mut self, name: &str, ty: Type) {
+        self.env.declare(name.to_string(), VarInfo {
+            ty: ty.clone(),
             ownership: OwnershipState::Borrowed,
             is_mutable: false,
             is_initialized: true,
         });
+        self.globals.push((name.to_string(), ty));
     }
 
     /// Main entry point: type check a program
     pub fn check_program(&mut self, program: &Program) -> Result<TypedProgram, Vec<TypeError>> {
         let mut typed_items = Vec::new();
 
+        // Global augmentations apply to the whole program, wherever they
+        // are written, so they're declared before anything is checked
+        let ambient_globals = std::mem::take(&mut self.ambient_globals);
+        self.in_ambient = true;
+        for item in program.global_declarations().chain(&ambient_globals) {
+            if let Err(err) = self.check_module_item(&item.value, &item.span) {
+                self.errors.push(err);
+            }
+        }
+        self.in_ambient = false;
+
+        self.hoist_var_declarations(program.items.iter().filter_map(|item| match &item.value {
+            ModuleItem::Stmt(stmt) => Some(stmt),
+            _ => None,
+        }));
+
         for item in &program.items {
             match self.check_module_item(&item.value, &item.span) {
                 Ok(typed_item) => typed_items.push(typed_item),
@@ -278,7 +459,7 @@ impl TypeChecker {
         }
     }
 
-    fn check_module_item(
+    pub(crate) fn check_module_item(
         &mut self,
         item: &ModuleItem,
         span: &Span,
@@ -313,6 +494,17 @@ impl TypeChecker {
     fn check_import(&mut self, import: &ImportDecl, span: &Span) -> Result<(), TypeError> {
         // Check if this is a built-in module
         if self.builtin_registry.is_builtin_module(&import.source) {
+            // Classes a module exports type the values its functions return
+            // (sqlite's `open` gives a Database), so define them even when
+            // only the functions are imported
+            if let Some(exports) = self.builtin_registry.get_module_exports(&import.source) {
+                for (name, ty) in exports {
+                    if matches!(ty, Type::Class { .. }) {
+                        self.env.define_class(name.clone(), ty.clone());
+                    }
+                }
+            }
+
             // Validate and register each imported symbol
             for specifier in &import.specifiers {
                 match specifier {
@@ -328,6 +520,9 @@ impl TypeChecker {
                                 .map(|n| n.value.name.clone())
                                 .unwrap_or_else(|| imported.value.name.clone());
 
+                            if import.source == "worker" && import_name == "spawn" {
+                                self.worker_spawn_names.insert(local_name.clone());
+                            }
                             self.env.declare(local_name, VarInfo {
                                 ty: symbol_type.clone(),
                                 ownership: OwnershipState::Borrowed,
@@ -336,13 +531,13 @@ impl TypeChecker {
                             });
                         } else {
                             // Symbol not found in built-in module
-                            return Err(TypeError {
-                                kind: TypeErrorKind::UndefinedVariable(format!(
-                                    "Module '{}' does not export '{}'",
-                                    import.source, import_name
-                                )),
-                                span: span.clone(),
-                            });
+                            return Err(TypeError::new(
+                                TypeErrorKind::UndefinedVariable {
+                                    name: format!("Module '{}' does not export '{}'", import.source, import_name),
+                                    suggestion: None,
+                                },
+                                span.clone(),
+                            ));
                         }
                     }
                     ImportSpecifier::Default(ident) => {
@@ -375,10 +570,26 @@ impl TypeChecker {
                 }
             }
         } else {
-            // For local modules (relative paths), we can't validate the imports yet
-            // This would require cross-module type information from the driver
-            // For now, register imported symbols as Any to avoid false errors
+            // Values from local modules aren't validated yet (that would need
+            // their types from the driver), so they are registered as Any.
+            // Type-only specifiers bind no value; their names are checked
+            // against the module's exported types when the driver gave them.
             for specifier in &import.specifiers {
+                if import.is_type_specifier(specifier) {
+                    let exported_types = self.module_types.get(&import.source);
+                    if let (ImportSpecifier::Named { imported, .. }, Some(types)) = (specifier, exported_types) {
+                        if !types.contains(&imported.value.name) {
+                            return Err(TypeError::new(
+                                TypeErrorKind::Generic(format!(
+                                    "Module '{}' has no exported type '{}'",
+                                    import.source, imported.value.name
+                                )),
+                                imported.span,
+                            ));
+                        }
+                    }
+                    continue;
+                }
                 match specifier {
                     ImportSpecifier::Named { imported, local, .. } => {
                         let local_name = local
@@ -419,7 +630,7 @@ impl TypeChecker {
     /// Check export declaration and register exported symbols
     fn check_export(&mut self, export: &ExportDecl, span: &Span) -> Result<(), TypeError> {
         match export {
-            ExportDecl::Named { specifiers, source, .. } => {
+            ExportDecl::Named { specifiers, source, type_only } => {
                 if source.is_some() {
                     // Re-export from another module: export { x } from "module"
                     // For now, just pass through without validation
@@ -427,9 +638,15 @@ impl TypeChecker {
                     return Ok(());
                 }
 
-                // export { name1, name2 }
+                // export { name1, name2 }. Interfaces and type aliases have
+                // no value to register, and a type-only specifier exports
+                // only the type.
                 for spec in specifiers {
                     let local_name = &spec.local.value.name;
+                    let is_type = self.env.lookup_type(local_name).is_some();
+                    if is_type && (*type_only || spec.type_only || self.env.lookup(local_name).is_none()) {
+                        continue;
+                    }
 
                     // Check if the local symbol exists in the current environment
                     if let Some(var_info) = self.env.lookup(local_name) {
@@ -442,13 +659,13 @@ impl TypeChecker {
                         self.env.export_symbol(export_name, var_info.ty.clone());
                     } else {
                         // Symbol being exported doesn't exist
-                        return Err(TypeError {
-                            kind: TypeErrorKind::UndefinedVariable(format!(
-                                "Cannot export undefined symbol '{}'",
-                                local_name
-                            )),
-                            span: span.clone(),
-                        });
+                        return Err(TypeError::new(
+                            TypeErrorKind::UndefinedVariable {
+                                name: format!("Cannot export undefined symbol '{}'", local_name),
+                                suggestion: None,
+                            },
+                            span.clone(),
+                        ));
                     }
                 }
             }
@@ -495,7 +712,14 @@ impl TypeChecker {
                         }
                         return Ok(());
                     }
-                    zaco_ast::Decl::Module(_) => ("module".to_string(), Type::Any),
+                    zaco_ast::Decl::Module(m) => match &m.name {
+                        zaco_ast::ModuleName::Ident(ident) => {
+                            let n = ident.value.name.clone();
+                            let t = self.env.lookup(&n).map(|v| v.ty.clone()).unwrap_or(Type::Any);
+                            (n, t)
+                        }
+                        zaco_ast::ModuleName::String(_) => return Ok(()),
+                    },
                 };
                 self.env.export_symbol(name, ty);
             }
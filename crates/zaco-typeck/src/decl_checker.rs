@@ -1,11 +1,13 @@
 //! Declaration checking methods
 
+use std::collections::HashMap;
 use zaco_ast::{
-    ClassDecl, ClassMember, Decl, EnumDecl, Expr, FunctionDecl, InterfaceDecl,
-    ObjectTypeMember, Param, Pattern, Span, TypeAliasDecl,
+    AccessModifier, AssignmentOp, BlockStmt, ClassDecl, ClassMember, Decl, DecoratorKind, EnumDecl, ExportDecl, Expr,
+    FunctionDecl, InterfaceDecl, ModuleBody, ModuleDecl, ModuleItem, ModuleName, Node, ObjectTypeMember, Param,
+    Pattern, PropertyName, Span, Stmt, TypeAliasDecl,
 };
 use crate::checker::TypeChecker;
-use crate::error::TypeError;
+use crate::error::{TypeError, TypeErrorKind};
 use crate::types::Type;
 use crate::ownership::{OwnershipState, VarInfo};
 use crate::helpers::TypeHelpers;
@@ -18,7 +20,10 @@ impl TypeChecker {
             Decl::Interface(interface) => self.check_interface_decl(interface, span),
             Decl::TypeAlias(alias) => self.check_type_alias(alias, span),
             Decl::Enum(enum_decl) => self.check_enum_decl(enum_decl, span),
-            Decl::Module(_) => Ok(()), // Module declarations are handled separately
+            Decl::Module(module) => {
+                self.check_module_decl(module);
+                Ok(())
+            }
             Decl::Var(var_decl) => self.check_var_decl(var_decl, span),
         }
     }
@@ -28,26 +33,32 @@ impl TypeChecker {
         func: &FunctionDecl,
         _span: &Span,
     ) -> Result<(), TypeError> {
-        // Convert parameters to types
+        // Convert parameters to types (`ref` params become `Type::Ref`)
         let mut param_types = Vec::new();
         for param in &func.params {
-            let param_ty = self.resolve_param_type(param)?;
+            let param_ty = self.resolve_signature_param_type(param)?;
             param_types.push(param_ty);
         }
 
-        // Get return type
-        let return_type = if let Some(ret_ty) = &func.return_type {
-            self.convert_ast_type(&ret_ty.value)?
-        } else {
-            Type::Void
+        // Get return type; calling a generator function returns its generator
+        let return_type = match func.return_type {
+            None if func.is_generator && func.is_async => crate::builtins::async_generator_type(Type::Any),
+            None if func.is_generator => crate::builtins::generator_type(Type::Any),
+            _ => self.resolve_return_type(func.return_type.as_deref())?,
         };
 
         let func_type = Type::Function {
-            params: param_types,
+            params: param_types.clone(),
             return_type: Box::new(return_type),
         };
 
-        // Declare function in environment
+        // Declare function in environment. Overload signatures (and ambient
+        // declarations) have no body and may precede the implementation.
+        if func.body.is_some() {
+            if let Some(err) = self.redeclaration_error(&func.name.value.name, func.name.span, false) {
+                return Err(err);
+            }
+        }
         self.env.declare(
             func.name.value.name.clone(),
             VarInfo {
@@ -60,12 +71,17 @@ impl TypeChecker {
 
         // Check function body
         if let Some(body) = &func.body {
-            self.env.push_scope();
+            self.env.push_function_scope();
 
             // Track the declared return type for return-statement validation
             let prev_return_type = self.current_return_type.take();
-            if let Some(ret_ty) = &func.return_type {
-                let rt = self.convert_ast_type(&ret_ty.value)?;
+            let prev_return_span = std::mem::replace(&mut self.current_return_span, func.return_type.as_ref().map(|t| t.span));
+            let prev_returned = std::mem::take(&mut self.returned_types);
+            let prev_arguments = self.arguments_used.replace(false);
+            // A generator's `return` finishes the generator rather than
+            // producing its declared type
+            if func.return_type.is_some() && !func.is_generator {
+                let rt = self.resolve_return_type(func.return_type.as_deref())?;
                 // Don't validate returns against Void — it just means no meaningful return
                 if rt != Type::Void {
                     self.current_return_type = Some(rt);
@@ -76,12 +92,36 @@ impl TypeChecker {
             for param in &func.params {
                 self.check_param(param)?;
             }
+            self.hoist_var_declarations(&body.value.stmts);
 
-            self.check_block_stmt(&body.value, &body.span)?;
+            let result = self.check_block_stmt(&body.value, &body.span);
             self.env.pop_scope();
 
             // Restore previous return type (for nested functions)
             self.current_return_type = prev_return_type;
+            self.current_return_span = prev_return_span;
+            let returned = std::mem::replace(&mut self.returned_types, prev_returned);
+            let uses_arguments = std::mem::replace(&mut self.arguments_used, prev_arguments) == Some(true);
+            result?;
+
+            // Without an annotation, callers see the type the body actually
+            // returns. A function that reads `arguments` accepts any number of
+            // arguments.
+            if !func.is_async && !func.is_generator {
+                let return_type = self.infer_return_type(func.return_type.as_deref(), &returned)?;
+                self.env.declare(
+                    func.name.value.name.clone(),
+                    VarInfo {
+                        ty: Type::Function {
+                            params: if uses_arguments { vec![Type::Any] } else { param_types },
+                            return_type: Box::new(return_type),
+                        },
+                        ownership: OwnershipState::Owned,
+                        is_mutable: false,
+                        is_initialized: true,
+                    },
+                );
+            }
         }
 
         Ok(())
@@ -92,12 +132,8 @@ impl TypeChecker {
 
         // Extract parameter name and declare it
         match &param.pattern.value {
-            Pattern::Ident { name, ownership, .. } => {
-                let ownership_state = if let Some(own) = ownership {
-                    TypeHelpers::convert_ownership(&own.kind)
-                } else {
-                    OwnershipState::Owned
-                };
+            Pattern::Ident { name, type_annotation, .. } => {
+                let ownership_state = self.param_ownership(param);
 
                 self.env.declare(
                     name.value.name.clone(),
@@ -108,6 +144,9 @@ impl TypeChecker {
                         is_initialized: true,
                     },
                 );
+                if let Some(type_ann) = param.type_annotation.as_ref().or(type_annotation.as_ref()) {
+                    self.env.record_annotation(name.value.name.clone(), type_ann.span);
+                }
             }
             _ => {
                 // Handle destructuring patterns
@@ -133,70 +172,298 @@ impl TypeChecker {
         Ok(Type::Unknown)
     }
 
+    /// Ownership of a parameter. The annotation may sit before the name
+    /// (`ref p: T`), on the pattern, or on the type itself (`p: ref T`).
+    pub(crate) fn param_ownership(&self, param: &Param) -> OwnershipState {
+        if let Some(own) = &param.ownership {
+            return TypeHelpers::convert_ownership(&own.kind);
+        }
+        if let Pattern::Ident { ownership: Some(own), .. } = &param.pattern.value {
+            return TypeHelpers::convert_ownership(&own.kind);
+        }
+        let type_ann = param.type_annotation.as_ref().or(match &param.pattern.value {
+            Pattern::Ident { type_annotation, .. } => type_annotation.as_ref(),
+            _ => None,
+        });
+        type_ann
+            .and_then(|ty| TypeHelpers::annotation_ownership(&ty.value))
+            .unwrap_or(OwnershipState::Owned)
+    }
+
+    /// Parameter type as it appears in a function signature: borrowed
+    /// params are wrapped in `Type::Ref` so call sites can see the borrow.
+    pub(crate) fn resolve_signature_param_type(&self, param: &Param) -> Result<Type, TypeError> {
+        let ty = self.resolve_param_type(param)?;
+        Ok(match self.param_ownership(param) {
+            OwnershipState::Borrowed => Type::Ref { inner: Box::new(ty), mutable: false },
+            OwnershipState::MutBorrowed => Type::Ref { inner: Box::new(ty), mutable: true },
+            _ => ty,
+        })
+    }
+
+    /// Declared return type of a function, defaulting to `void`.
+    /// A `ref T` / `mut ref T` return is kept as `Type::Ref`.
+    pub(crate) fn resolve_return_type(
+        &self,
+        return_type: Option<&Node<zaco_ast::Type>>,
+    ) -> Result<Type, TypeError> {
+        let Some(ret_ty) = return_type else {
+            return Ok(Type::Void);
+        };
+        let ty = self.convert_ast_type(&ret_ty.value)?;
+        Ok(match TypeHelpers::annotation_ownership(&ret_ty.value) {
+            Some(OwnershipState::Borrowed) => Type::Ref { inner: Box::new(ty), mutable: false },
+            Some(OwnershipState::MutBorrowed) => Type::Ref { inner: Box::new(ty), mutable: true },
+            _ => ty,
+        })
+    }
+
+    /// Return type of a function body: the annotation if present, otherwise
+    /// the type of its first `return <expr>`, defaulting to `void`.
+    pub(crate) fn infer_return_type(
+        &self,
+        annotation: Option<&Node<zaco_ast::Type>>,
+        returned: &[Type],
+    ) -> Result<Type, TypeError> {
+        match (annotation, returned.first()) {
+            (None, Some(ty)) => Ok(ty.clone()),
+            _ => self.resolve_return_type(annotation),
+        }
+    }
+
     fn check_class_decl(&mut self, class: &ClassDecl, _span: &Span) -> Result<(), TypeError> {
+        // An ambient `declare class` only describes a class defined elsewhere
+        if !class.is_declare {
+            if let Some(err) = self.redeclaration_error(&class.name.value.name, class.name.span, false) {
+                return Err(err);
+            }
+        }
         let mut fields = Vec::new();
         let mut methods = Vec::new();
+        let mut accessors: Vec<(String, Type)> = Vec::new();
+        let mut statics: Vec<(String, Type)> = Vec::new();
 
-        // Inherit fields and methods from parent class (if extends)
+        // Inherit instance and static members from parent class (if extends)
         if let Some(ref extends) = class.extends {
             if let Expr::Ident(parent_ident) = &extends.base.value {
                 if let Some(parent_type) = self.env.lookup_type(&parent_ident.name) {
-                    if let Type::Class { fields: parent_fields, methods: parent_methods, .. } = parent_type {
-                        fields.extend(parent_fields.clone());
-                        methods.extend(parent_methods.clone());
+                    if let Type::Class {
+                        fields: parent_fields,
+                        methods: parent_methods,
+                        accessors: parent_accessors,
+                        statics: parent_statics,
+                        ..
+                    } = parent_type
+                    {
+                        // `#name` members stay private to the parent
+                        let inherited = |members: &Vec<(String, Type)>| {
+                            members.iter().filter(|(name, _)| !name.starts_with('#')).cloned().collect::<Vec<_>>()
+                        };
+                        fields.extend(inherited(parent_fields));
+                        methods.extend(inherited(parent_methods));
+                        accessors.extend(inherited(parent_accessors));
+                        statics.extend(inherited(parent_statics));
                     }
                 }
             }
         }
 
+        // Getter and setter types per property, checked for agreement below
+        let mut getters: Vec<(String, Type)> = Vec::new();
+        let mut setters: Vec<(String, Type, Span)> = Vec::new();
+
+        let ctor = class.members.iter().find_map(|member| match member {
+            ClassMember::Constructor { params, body, .. } => Some((params, body)),
+            _ => None,
+        });
+        let ctor_params: Vec<&str> = ctor
+            .map(|(params, _)| {
+                params
+                    .iter()
+                    .filter_map(|param| match &param.pattern.value {
+                        Pattern::Ident { name, .. } => Some(name.value.name.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Fields the constructor has to assign, with where to report them
+        let mut unassigned: Vec<(String, Span)> = Vec::new();
+
         for member in &class.members {
             match member {
                 ClassMember::Property {
                     name,
                     type_annotation,
+                    init,
+                    is_static,
+                    is_abstract,
+                    is_optional,
+                    is_definite,
                     ..
                 } => {
                     let prop_name = TypeHelpers::property_name_to_string(name);
-                    let prop_ty = if let Some(type_ann) = type_annotation {
-                        self.convert_ast_type(&type_ann.value)?
-                    } else {
-                        Type::Unknown
+                    // Initializers run before the constructor body, so its
+                    // parameters aren't in scope yet
+                    let init_ty = match init {
+                        Some(init) => match self.check_expr(&init.value, &init.span) {
+                            Ok(ty) => Some(ty),
+                            Err(err) => match err.kind.as_ref() {
+                                TypeErrorKind::UndefinedVariable { name: param, .. }
+                                    if !*is_static && ctor_params.contains(&param.as_str()) =>
+                                {
+                                    return Err(TypeError::new(
+                                        TypeErrorKind::InitializerUsesParameter {
+                                            property: prop_name,
+                                            param: param.clone(),
+                                        },
+                                        init.span,
+                                    ));
+                                }
+                                _ => return Err(err),
+                            },
+                        },
+                        None => None,
+                    };
+                    let prop_ty = match (type_annotation, init_ty) {
+                        (Some(type_ann), init_ty) => {
+                            let annotated_ty = self.convert_ast_type(&type_ann.value)?;
+                            if let (Some(init), Some(init_ty)) = (init, init_ty) {
+                                if !TypeHelpers::is_assignable_with_env(&init_ty, &annotated_ty, Some(&self.env)) {
+                                    return Err(TypeError::new(
+                                        TypeErrorKind::TypeMismatch { expected: annotated_ty, found: init_ty },
+                                        init.span,
+                                    )
+                                    .declared_at(Some(type_ann.span)));
+                                }
+                            }
+                            annotated_ty
+                        }
+                        (None, Some(init_ty)) => TypeHelpers::widen_literal(init_ty),
+                        (None, None) => Type::Unknown,
                     };
-                    fields.push((prop_name, prop_ty));
+                    let needs_assignment = !*is_static
+                        && !*is_abstract
+                        && !*is_optional
+                        && !*is_definite
+                        && init.is_none()
+                        && !Self::admits_undefined(&prop_ty);
+                    if needs_assignment {
+                        let span = match (name, type_annotation) {
+                            (PropertyName::Ident(ident), _) => ident.span,
+                            (_, Some(type_ann)) => type_ann.span,
+                            _ => class.name.span,
+                        };
+                        unassigned.push((prop_name.clone(), span));
+                    }
+                    if *is_static {
+                        Self::define_static(&mut statics, prop_name, prop_ty);
+                    } else {
+                        fields.push((prop_name, prop_ty));
+                    }
                 }
                 ClassMember::Method {
                     name,
                     params,
                     return_type,
+                    is_static,
                     ..
                 } => {
                     let method_name = TypeHelpers::property_name_to_string(name);
                     let mut param_types = Vec::new();
                     for param in params {
-                        let param_ty = self.resolve_param_type(param)?;
+                        let param_ty = self.resolve_signature_param_type(param)?;
                         param_types.push(param_ty);
                     }
 
-                    let ret_ty = if let Some(ret_ty) = return_type {
-                        self.convert_ast_type(&ret_ty.value)?
-                    } else {
-                        Type::Void
-                    };
+                    let ret_ty = self.resolve_return_type(return_type.as_deref())?;
 
                     let method_ty = Type::Function {
                         params: param_types,
                         return_type: Box::new(ret_ty),
                     };
-                    methods.push((method_name, method_ty));
+                    if *is_static {
+                        Self::define_static(&mut statics, method_name, method_ty);
+                    } else {
+                        methods.push((method_name, method_ty));
+                    }
+                }
+                ClassMember::Getter { name, return_type, is_static, .. } => {
+                    let getter_ty = match return_type {
+                        Some(ret_ty) => self.convert_ast_type(&ret_ty.value)?,
+                        None => Type::Unknown,
+                    };
+                    if *is_static {
+                        Self::define_static(&mut statics, TypeHelpers::property_name_to_string(name), getter_ty);
+                    } else {
+                        getters.push((TypeHelpers::property_name_to_string(name), getter_ty));
+                    }
+                }
+                ClassMember::Setter { name, param, is_static, .. } => {
+                    let setter_ty = self.resolve_param_type(param)?;
+                    if *is_static {
+                        // A static getter of the same name already typed it
+                        let prop_name = TypeHelpers::property_name_to_string(name);
+                        if !class.members.iter().any(|m| Self::is_static_getter(m, &prop_name)) {
+                            Self::define_static(&mut statics, prop_name, setter_ty);
+                        }
+                        continue;
+                    }
+                    let setter_span = param
+                        .type_annotation
+                        .as_ref()
+                        .map(|ann| ann.span)
+                        .unwrap_or_else(|| param.pattern.span);
+                    setters.push((TypeHelpers::property_name_to_string(name), setter_ty, setter_span));
+                }
+                ClassMember::Constructor { params, .. } => {
+                    // Parameter properties declare fields of the parameter's type
+                    for param in params.iter().filter(|p| p.property.is_some()) {
+                        if let Pattern::Ident { name, .. } = &param.pattern.value {
+                            let field_ty = self.resolve_param_type(param)?;
+                            fields.push((name.value.name.clone(), field_ty));
+                        }
+                    }
                 }
                 _ => {} // Handle other members
             }
         }
 
+        // A getter's type must be assignable to its setter's parameter type
+        let mut accessor_error = None;
+        for (name, setter_ty, setter_span) in &setters {
+            let getter_ty = getters.iter().find(|(g, _)| g == name).map(|(_, ty)| ty);
+            if let Some(getter_ty) = getter_ty {
+                let known = !matches!(getter_ty, Type::Unknown) && !matches!(setter_ty, Type::Unknown);
+                if known
+                    && accessor_error.is_none()
+                    && !TypeHelpers::is_assignable_with_env(getter_ty, setter_ty, Some(&self.env))
+                {
+                    accessor_error = Some(TypeError::new(
+                        TypeErrorKind::AccessorTypeMismatch {
+                            property: name.clone(),
+                            getter: getter_ty.clone(),
+                            setter: setter_ty.clone(),
+                        },
+                        *setter_span,
+                    ));
+                }
+            } else {
+                accessors.retain(|(a, _)| a != name);
+                accessors.push((name.clone(), setter_ty.clone()));
+            }
+        }
+        for (name, getter_ty) in getters {
+            accessors.retain(|(a, _)| *a != name);
+            accessors.push((name, getter_ty));
+        }
+
         let class_type = Type::Class {
             name: class.name.value.name.clone(),
             fields,
             methods,
+            accessors,
+            statics,
         };
 
         self.env.define_class(class.name.value.name.clone(), class_type.clone());
@@ -220,9 +487,274 @@ impl TypeChecker {
             },
         );
 
+        let class_name = class.name.value.name.clone();
+        let constructor_access = class.members.iter().find_map(|member| match member {
+            ClassMember::Constructor { access, .. } => Some(*access),
+            _ => None,
+        });
+        match constructor_access {
+            Some(access @ (AccessModifier::Private | AccessModifier::Protected)) => {
+                self.restricted_constructors.insert(class_name.clone(), access);
+            }
+            _ => {
+                self.restricted_constructors.remove(&class_name);
+            }
+        }
+
+        if let Some(err) = accessor_error {
+            return Err(err);
+        }
+
+        // Every required field must be assigned on each path through the
+        // constructor (or be a parameter property)
+        if !class.is_declare {
+            let stmts = ctor.and_then(|(_, body)| body.as_ref()).map(|body| &body.value.stmts[..]).unwrap_or(&[]);
+            let param_properties: Vec<&str> = ctor
+                .map(|(params, _)| {
+                    params
+                        .iter()
+                        .filter(|param| param.property.is_some())
+                        .filter_map(|param| match &param.pattern.value {
+                            Pattern::Ident { name, .. } => Some(name.value.name.as_str()),
+                            _ => None,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let missing = unassigned.into_iter().find(|(field, _)| {
+                !param_properties.contains(&field.as_str()) && !Self::definitely_assigns(stmts, field)
+            });
+            if let Some((property, span)) = missing {
+                return Err(TypeError::new(
+                    TypeErrorKind::UninitializedField { class: class_name.clone(), property },
+                    span,
+                ));
+            }
+        }
+
+        // Check method bodies. `super` is the parent's instance type in
+        // instance methods and the parent class value in static methods.
+        let parent_name = class.extends.as_ref().and_then(|extends| match &extends.base.value {
+            Expr::Ident(parent_ident) => Some(parent_ident.name.clone()),
+            _ => None,
+        });
+        for member in &class.members {
+            if let ClassMember::Method { params, return_type, body: Some(body), is_static, .. } = member {
+                let super_type = parent_name.as_ref().and_then(|parent| {
+                    if *is_static {
+                        self.env.lookup(parent).map(|info| info.ty.clone())
+                    } else {
+                        Some(Type::TypeRef { name: parent.clone(), type_args: vec![] })
+                    }
+                });
+                let prev_super = std::mem::replace(&mut self.current_super_type, super_type);
+                let prev_class = self.current_class.replace(class_name.clone());
+                let result = self.check_method_body(params, return_type.as_deref(), body);
+                self.current_super_type = prev_super;
+                self.current_class = prev_class;
+                result?;
+            }
+        }
+
+        self.check_decorators(class)
+    }
+
+    /// Check each decorator as a call with the decorated value and its
+    /// context object. A class decorator receives the constructor (or the
+    /// class, when its parameter is not a function type) and a method
+    /// decorator the method; either may return a replacement of the same
+    /// type. A field decorator gets `undefined` and must return void.
+    fn check_decorators(&mut self, class: &ClassDecl) -> Result<(), TypeError> {
+        let class_name = &class.name.value.name;
+        let class_type = self.env.lookup_type(class_name).cloned();
+        let mut constructor_params = Vec::new();
+        for member in &class.members {
+            if let ClassMember::Constructor { params, .. } = member {
+                for param in params {
+                    constructor_params.push(self.resolve_signature_param_type(param)?);
+                }
+            }
+        }
+        let constructor_type = Type::Function {
+            params: constructor_params,
+            return_type: Box::new(Type::TypeRef { name: class_name.clone(), type_args: vec![] }),
+        };
+        for application in class.decorator_applications() {
+            let decorator = application.decorator;
+            let decorator_ty = self.check_expr(&decorator.value, &decorator.span)?;
+            let (params, return_type) = match decorator_ty {
+                Type::Function { params, return_type } => (params, return_type),
+                Type::Any => continue,
+                other => return Err(TypeError::new(TypeErrorKind::NotCallable(other), decorator.span)),
+            };
+            if params.len() > 2 {
+                return Err(TypeError::new(
+                    TypeErrorKind::ArityMismatch { expected: params.len(), found: 2 },
+                    decorator.span,
+                ));
+            }
+
+            let target = match application.member {
+                None if matches!(params.first(), Some(Type::Function { .. })) => constructor_type.clone(),
+                None => class_type.clone().unwrap_or(Type::Any),
+                Some(name) => {
+                    let name = TypeHelpers::property_name_to_string(name);
+                    match (&class_type, application.kind) {
+                        (Some(Type::Class { methods, statics, .. }), DecoratorKind::Method) => {
+                            let members = if application.is_static { statics } else { methods };
+                            members.iter().find(|(n, _)| *n == name).map(|(_, ty)| ty.clone()).unwrap_or(Type::Any)
+                        }
+                        // A field decorator runs before the field exists
+                        _ => Type::Undefined,
+                    }
+                }
+            };
+            let context = crate::builtins::decorator_context(application.kind);
+            for (param_ty, arg_ty) in params.iter().zip([target.clone(), context]) {
+                if !TypeHelpers::is_assignable_with_env(&arg_ty, param_ty, Some(&self.env)) {
+                    return Err(TypeError::new(
+                        TypeErrorKind::TypeMismatch { expected: param_ty.clone(), found: arg_ty },
+                        decorator.span,
+                    ));
+                }
+            }
+
+            if matches!(*return_type, Type::Void | Type::Undefined | Type::Any) {
+                continue;
+            }
+            if application.kind == DecoratorKind::Field {
+                return Err(TypeError::new(
+                    TypeErrorKind::InvalidOperation(
+                        "a field decorator must return void; initializer replacement is not supported".to_string(),
+                    ),
+                    decorator.span,
+                ));
+            }
+            // The replacement stands in for the target everywhere
+            let replaces = match &target {
+                Type::Function { .. } => TypeHelpers::is_assignable_with_env(&return_type, &target, Some(&self.env)),
+                _ => false,
+            };
+            if !replaces {
+                return Err(TypeError::new(
+                    TypeErrorKind::TypeMismatch { expected: target, found: *return_type },
+                    decorator.span,
+                ));
+            }
+        }
         Ok(())
     }
 
+    /// Check a method body in its own scope, validating returns against the
+    /// declared return type.
+    fn check_method_body(
+        &mut self,
+        params: &[Param],
+        return_type: Option<&Node<zaco_ast::Type>>,
+        body: &Node<BlockStmt>,
+    ) -> Result<(), TypeError> {
+        let prev_return_type = self.current_return_type.take();
+        let prev_return_span = std::mem::replace(&mut self.current_return_span, return_type.map(|t| t.span));
+        let prev_returned = std::mem::take(&mut self.returned_types);
+        let prev_arguments = self.arguments_used.replace(false);
+        if return_type.is_some() {
+            let mut rt = self.resolve_return_type(return_type)?;
+            if let Some(class) = &self.current_class {
+                let this_ty = Type::TypeRef { name: class.clone(), type_args: vec![] };
+                rt = TypeHelpers::substitute_type_params(&rt, &HashMap::from([("this".to_string(), this_ty)]));
+            }
+            if rt != Type::Void {
+                self.current_return_type = Some(rt);
+            }
+        }
+
+        self.env.push_function_scope();
+        let mut result = Ok(());
+        for param in params {
+            result = self.check_param(param);
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() {
+            self.hoist_var_declarations(&body.value.stmts);
+            result = self.check_block_stmt(&body.value, &body.span);
+        }
+        self.env.pop_scope();
+
+        self.current_return_type = prev_return_type;
+        self.current_return_span = prev_return_span;
+        self.returned_types = prev_returned;
+        self.arguments_used = prev_arguments;
+        result
+    }
+
+    /// Whether a field of this type may be left unassigned
+    fn admits_undefined(ty: &Type) -> bool {
+        match ty {
+            Type::Unknown | Type::Any | Type::Undefined | Type::Void => true,
+            Type::Union(members) => members.iter().any(Self::admits_undefined),
+            _ => false,
+        }
+    }
+
+    /// Whether running `stmts` in order assigns `this.<field>` on every path
+    /// that completes normally
+    fn definitely_assigns(stmts: &[Node<Stmt>], field: &str) -> bool {
+        for stmt in stmts {
+            if Self::stmt_assigns(&stmt.value, field) {
+                return true;
+            }
+            if matches!(stmt.value, Stmt::Return(_)) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn stmt_assigns(stmt: &Stmt, field: &str) -> bool {
+        match stmt {
+            Stmt::Expr(expr) => Self::expr_assigns(&expr.value, field),
+            Stmt::Block(block) => Self::definitely_assigns(&block.stmts, field),
+            Stmt::If { then_stmt, else_stmt: Some(else_stmt), .. } => {
+                Self::stmt_assigns(&then_stmt.value, field) && Self::stmt_assigns(&else_stmt.value, field)
+            }
+            // Paths that throw never hand out the instance
+            Stmt::Throw(_) => true,
+            _ => false,
+        }
+    }
+
+    fn expr_assigns(expr: &Expr, field: &str) -> bool {
+        match expr {
+            Expr::Assignment { target, op: AssignmentOp::Assign, value } => {
+                let assigns_field = matches!(
+                    &target.value,
+                    Expr::Member { object, property, computed: false }
+                        if matches!(object.value, Expr::This) && property.value.name == field
+                );
+                assigns_field || Self::expr_assigns(&value.value, field)
+            }
+            Expr::Sequence(exprs) => exprs.iter().any(|expr| Self::expr_assigns(&expr.value, field)),
+            Expr::Paren(inner) => Self::expr_assigns(&inner.value, field),
+            _ => false,
+        }
+    }
+
+    /// Add a static member, replacing one inherited from the parent class
+    fn define_static(statics: &mut Vec<(String, Type)>, name: String, ty: Type) {
+        statics.retain(|(existing, _)| *existing != name);
+        statics.push((name, ty));
+    }
+
+    fn is_static_getter(member: &ClassMember, prop_name: &str) -> bool {
+        matches!(
+            member,
+            ClassMember::Getter { name, is_static: true, .. }
+                if TypeHelpers::property_name_to_string(name) == prop_name
+        )
+    }
+
     fn check_interface_decl(
         &mut self,
         interface: &InterfaceDecl,
@@ -283,7 +815,110 @@ impl TypeChecker {
             members,
         };
 
-        self.env.define_enum(enum_decl.name.value.name.clone(), enum_type);
+        self.env.define_enum(enum_decl.name.value.name.clone(), enum_type.clone());
+        self.env.declare(enum_decl.name.value.name.clone(), VarInfo {
+            ty: enum_type,
+            ownership: OwnershipState::Borrowed,
+            is_mutable: false,
+            is_initialized: true,
+        });
+
+        // A const enum has no run-time object, so every member must be a
+        // compile-time constant that member accesses can be replaced with
+        if enum_decl.is_const {
+            let values = enum_decl.member_values();
+            if let Some((member, _)) = enum_decl.members.iter().zip(&values).find(|(_, (_, value))| value.is_none()) {
+                let span = member.init.as_ref().map_or(member.name.span, |init| init.span);
+                let message = if member.init.is_some() {
+                    format!("const enum member '{}' must be initialized with a constant expression", member.name.value.name)
+                } else {
+                    format!("const enum member '{}' must have an initializer", member.name.value.name)
+                };
+                return Err(TypeError::new(TypeErrorKind::Generic(message), span));
+            }
+        }
         Ok(())
     }
+
+    /// Declare a namespace as an object whose properties are its exported
+    /// values. `declare global` blocks were checked up front, and modules
+    /// named by a string describe other code, so neither declares anything.
+    fn check_module_decl(&mut self, module: &ModuleDecl) {
+        let ModuleName::Ident(name) = &module.name else {
+            return;
+        };
+        if module.is_declare && name.value.name == "global" {
+            return;
+        }
+
+        let was_ambient = self.in_ambient;
+        self.in_ambient |= module.is_declare;
+        let ty = self.namespace_type(&module.body);
+        self.in_ambient = was_ambient;
+
+        self.env.declare(name.value.name.clone(), VarInfo {
+            ty,
+            ownership: OwnershipState::Borrowed,
+            is_mutable: false,
+            is_initialized: true,
+        });
+    }
+
+    /// Check a namespace body in its own scope. Errors inside it are recorded
+    /// directly, like those of top-level items.
+    fn namespace_type(&mut self, body: &ModuleBody) -> Type {
+        let items = match body {
+            ModuleBody::Block(items) => items,
+            ModuleBody::Namespace(nested) => {
+                let ModuleName::Ident(name) = &nested.value.name else {
+                    return Type::Object { properties: vec![] };
+                };
+                let inner = self.namespace_type(&nested.value.body);
+                return Type::Object { properties: vec![(name.value.name.clone(), inner, false)] };
+            }
+        };
+
+        self.env.push_function_scope();
+        let mut exported = Vec::new();
+        for item in items {
+            let result = match &item.value {
+                ModuleItem::Export(ExportDecl::Decl(decl)) => {
+                    exported.extend(Self::declared_value_names(&decl.value));
+                    self.check_decl(&decl.value, &decl.span)
+                }
+                other => self.check_module_item(other, &item.span).map(|_| ()),
+            };
+            if let Err(err) = result {
+                self.errors.push(err);
+            }
+        }
+        let properties = exported
+            .into_iter()
+            .filter_map(|name| {
+                let ty = self.env.lookup(&name)?.ty.clone();
+                Some((name, ty, false))
+            })
+            .collect();
+        self.env.pop_scope();
+        Type::Object { properties }
+    }
+
+    /// Names a declaration binds as values (types are left out)
+    fn declared_value_names(decl: &Decl) -> Vec<String> {
+        match decl {
+            Decl::Function(func) => vec![func.name.value.name.clone()],
+            Decl::Class(class) => vec![class.name.value.name.clone()],
+            Decl::Enum(enum_decl) => vec![enum_decl.name.value.name.clone()],
+            Decl::Var(var_decl) => var_decl
+                .declarations
+                .iter()
+                .filter_map(|declarator| match &declarator.pattern.value {
+                    Pattern::Ident { name, .. } => Some(name.value.name.clone()),
+                    _ => None,
+                })
+                .collect(),
+            Decl::Module(ModuleDecl { name: ModuleName::Ident(name), .. }) => vec![name.value.name.clone()],
+            Decl::Module(_) | Decl::Interface(_) | Decl::TypeAlias(_) => vec![],
+        }
+    }
 }
@@ -1,13 +1,27 @@
 //! Type environment (scoped symbol table)
 
 use std::collections::HashMap;
+use zaco_ast::Span;
 use crate::types::Type;
 use crate::ownership::{OwnershipState, VarInfo};
+use crate::helpers::TypeHelpers;
 
 /// Type environment with scoped symbol tables
 #[derive(Debug, Clone)]
 pub struct TypeEnv {
     scopes: Vec<HashMap<String, VarInfo>>,
+    /// Per-scope borrow records: reference variable → variables it borrows from.
+    /// Released together with the scope that declared the reference.
+    borrows: Vec<HashMap<String, Vec<String>>>,
+    /// Per-scope spans of variables' type annotations, so a mismatch can
+    /// point at the declared type
+    annotations: Vec<HashMap<String, Span>>,
+    /// Per-scope sites of declared bindings, and whether each was a `var`
+    /// (which another `var` may redeclare)
+    declarations: Vec<HashMap<String, (Span, bool)>>,
+    /// Index of the scope `var`s are declared in for each enclosing function
+    /// (the global scope at the top level)
+    var_scopes: Vec<usize>,
     type_aliases: HashMap<String, Type>,
     interfaces: HashMap<String, Type>,
     classes: HashMap<String, Type>,
@@ -22,6 +36,10 @@ impl TypeEnv {
     pub fn new() -> Self {
         Self {
             scopes: vec![HashMap::new()],
+            borrows: vec![HashMap::new()],
+            annotations: vec![HashMap::new()],
+            declarations: vec![HashMap::new()],
+            var_scopes: vec![0],
             type_aliases: HashMap::new(),
             interfaces: HashMap::new(),
             classes: HashMap::new(),
@@ -33,20 +51,84 @@ impl TypeEnv {
 
     pub fn push_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.borrows.push(HashMap::new());
+        self.annotations.push(HashMap::new());
+        self.declarations.push(HashMap::new());
+    }
+
+    /// Enter a function body: a scope that also holds the `var`s declared
+    /// anywhere in it
+    pub fn push_function_scope(&mut self) {
+        self.push_scope();
+        self.var_scopes.push(self.scopes.len() - 1);
     }
 
     pub fn pop_scope(&mut self) {
         if self.scopes.len() > 1 {
             self.scopes.pop();
+            self.borrows.pop();
+            self.annotations.pop();
+            self.declarations.pop();
+            if self.var_scopes.last() == Some(&self.scopes.len()) {
+                self.var_scopes.pop();
+            }
         }
     }
 
     pub fn declare(&mut self, name: String, var_info: VarInfo) {
+        if let Some(annotations) = self.annotations.last_mut() {
+            annotations.remove(&name);
+        }
         if let Some(scope) = self.scopes.last_mut() {
             scope.insert(name, var_info);
         }
     }
 
+    /// Declare a `var` in the enclosing function's scope rather than the
+    /// block it is written in
+    pub fn declare_var(&mut self, name: String, var_info: VarInfo) {
+        let scope = self.var_scope();
+        self.annotations[scope].remove(&name);
+        self.scopes[scope].insert(name, var_info);
+    }
+
+    /// Whether `name` is declared in the enclosing function's own scope
+    pub fn has_in_var_scope(&self, name: &str) -> bool {
+        self.scopes[self.var_scope()].contains_key(name)
+    }
+
+    fn var_scope(&self) -> usize {
+        self.var_scopes.last().copied().unwrap_or(0)
+    }
+
+    /// Record where the variable just declared as `name` had its type annotated
+    pub fn record_annotation(&mut self, name: String, span: Span) {
+        let scope = self.scopes.iter().rposition(|scope| scope.contains_key(&name));
+        if let Some(annotations) = scope.and_then(|scope| self.annotations.get_mut(scope)) {
+            annotations.insert(name, span);
+        }
+    }
+
+    /// Span of the type annotation of the binding `name` resolves to
+    pub fn annotation_span(&self, name: &str) -> Option<Span> {
+        let scope = self.scopes.iter().rposition(|scope| scope.contains_key(name))?;
+        self.annotations[scope].get(name).copied()
+    }
+
+    /// Record where `name` is declared in the current scope, returning the
+    /// site of an earlier declaration it conflicts with. Only `var`s may be
+    /// declared twice; builtins, which have no site, may be shadowed.
+    pub fn record_declaration(&mut self, name: &str, span: Span, is_var: bool) -> Option<Span> {
+        let declarations = self.declarations.last_mut()?;
+        if let Some(&(first, first_is_var)) = declarations.get(name) {
+            if !(is_var && first_is_var) {
+                return Some(first);
+            }
+        }
+        declarations.insert(name.to_string(), (span, is_var));
+        None
+    }
+
     /// Check if a binding exists in the current (innermost) scope only
     pub fn has_in_current_scope(&self, name: &str) -> bool {
         if let Some(scope) = self.scopes.last() {
@@ -74,6 +156,47 @@ impl TypeEnv {
         None
     }
 
+    /// The binding in scope closest in spelling to an unknown `name`, for a
+    /// "did you mean" hint. Builtins live in the outermost scope, so they
+    /// are candidates too. Names more than a third of `name`'s length away
+    /// are not suggested; ties go to the alphabetically first.
+    pub fn similar_name(&self, name: &str) -> Option<String> {
+        let max_distance = (name.chars().count() / 3).max(1);
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.keys())
+            .filter(|candidate| candidate.as_str() != name)
+            .map(|candidate| (TypeHelpers::edit_distance(name, candidate), candidate))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min()
+            .map(|(_, candidate)| candidate.clone())
+    }
+
+    /// Whether `name` resolves to a binding in the outermost (module) scope
+    pub fn is_global(&self, name: &str) -> bool {
+        let innermost = self.scopes.iter().rposition(|scope| scope.contains_key(name));
+        innermost == Some(0)
+    }
+
+    /// Record that reference variable `reference` borrows from `owners`
+    pub fn record_borrow(&mut self, reference: String, owners: Vec<String>) {
+        if let Some(scope) = self.borrows.last_mut() {
+            scope.insert(reference, owners);
+        }
+    }
+
+    /// The variables a reference variable was borrowed from, if any
+    pub fn borrow_origins(&self, reference: &str) -> Option<&Vec<String>> {
+        self.borrows.iter().rev().find_map(|scope| scope.get(reference))
+    }
+
+    /// Check whether any live reference still borrows from `owner`
+    pub fn is_borrowed(&self, owner: &str) -> bool {
+        self.borrows
+            .iter()
+            .any(|scope| scope.values().any(|owners| owners.iter().any(|o| o == owner)))
+    }
+
     pub fn update_ownership(&mut self, name: &str, state: OwnershipState) -> Result<(), String> {
         if let Some(var_info) = self.lookup_mut(name) {
             var_info.ownership = state;
@@ -1,7 +1,7 @@
 //! Type checking errors
 
 use std::fmt;
-use zaco_ast::Span;
+use zaco_ast::{AccessModifier, Span};
 
 use crate::types::Type;
 
@@ -13,14 +13,21 @@ pub enum TypeErrorKind {
         expected: Type,
         found: Type,
     },
-    /// Undefined variable
-    UndefinedVariable(String),
+    /// Undefined variable, with the closest name in scope when one is near
+    UndefinedVariable {
+        name: String,
+        suggestion: Option<String>,
+    },
     /// Undefined type
     UndefinedType(String),
     /// Use after move
     UseAfterMove(String),
     /// Borrow conflict (trying to borrow mutably while borrowed)
     BorrowConflict(String),
+    /// Move out of a value that a live reference still borrows
+    MoveWhileBorrowed(String),
+    /// Returned reference points at a value owned by the returning function
+    ReturnLocalReference(String),
     /// Cannot assign to immutable variable
     AssignToImmutable(String),
     /// Missing initialization
@@ -43,20 +50,99 @@ pub enum TypeErrorKind {
     NotCallable(Type),
     /// Cannot index non-array/object
     NotIndexable(Type),
+    /// Value that cannot be structured-cloned (e.g. sent to a worker)
+    NotCloneable(Type),
+    /// Class getter and setter for the same property disagree on its type
+    AccessorTypeMismatch {
+        property: String,
+        getter: Type,
+        setter: Type,
+    },
+    /// `new C()` outside the class body when C's constructor is private or protected
+    PrivateConstructor {
+        class: String,
+        access: AccessModifier,
+    },
+    /// `#name` used outside the body of the class that declares it
+    PrivateMemberAccess {
+        class: String,
+        property: String,
+    },
+    /// Field with no initializer, `?` or `!` that the constructor doesn't
+    /// assign on every path
+    UninitializedField {
+        class: String,
+        property: String,
+    },
+    /// Field initializer naming a constructor parameter, which it runs before
+    InitializerUsesParameter {
+        property: String,
+        param: String,
+    },
+    /// `// @zaco-expect-error` before a line without a type error
+    UnusedExpectError,
     /// Generic error message
     Generic(String),
 }
 
+impl TypeErrorKind {
+    /// The diagnostic code of this kind of error. Codes are stable: a
+    /// new kind takes the next free number, and `E2000` is left for
+    /// errors without a kind of their own.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeErrorKind::TypeMismatch { .. } => "E2001",
+            TypeErrorKind::UndefinedVariable { .. } => "E2002",
+            TypeErrorKind::UndefinedType(_) => "E2003",
+            TypeErrorKind::UseAfterMove(_) => "E2004",
+            TypeErrorKind::BorrowConflict(_) => "E2005",
+            TypeErrorKind::MoveWhileBorrowed(_) => "E2006",
+            TypeErrorKind::ReturnLocalReference(_) => "E2007",
+            TypeErrorKind::AssignToImmutable(_) => "E2008",
+            TypeErrorKind::UninitializedVariable(_) => "E2009",
+            TypeErrorKind::DuplicateDeclaration(_) => "E2010",
+            TypeErrorKind::InvalidOperation(_) => "E2011",
+            TypeErrorKind::ArityMismatch { .. } => "E2012",
+            TypeErrorKind::PropertyNotFound { .. } => "E2013",
+            TypeErrorKind::NotCallable(_) => "E2014",
+            TypeErrorKind::NotIndexable(_) => "E2015",
+            TypeErrorKind::NotCloneable(_) => "E2016",
+            TypeErrorKind::AccessorTypeMismatch { .. } => "E2017",
+            TypeErrorKind::PrivateConstructor { .. } => "E2018",
+            TypeErrorKind::PrivateMemberAccess { .. } => "E2019",
+            TypeErrorKind::UninitializedField { .. } => "E2020",
+            TypeErrorKind::InitializerUsesParameter { .. } => "E2021",
+            TypeErrorKind::UnusedExpectError => "E2022",
+            TypeErrorKind::Generic(_) => "E2000",
+        }
+    }
+}
+
 /// Type error with location information
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypeError {
-    pub kind: TypeErrorKind,
+    pub kind: Box<TypeErrorKind>,
     pub span: Span,
+    /// A second location explaining the error and its label, e.g. the
+    /// annotation a mismatched value was checked against
+    pub related: Option<Box<(Span, String)>>,
 }
 
 impl TypeError {
     pub fn new(kind: TypeErrorKind, span: Span) -> Self {
-        Self { kind, span }
+        Self { kind: Box::new(kind), span, related: None }
+    }
+
+    /// Point at the annotation the expected type was declared by, if known
+    pub fn declared_at(mut self, annotation: Option<Span>) -> Self {
+        self.related = annotation.map(|span| Box::new((span, "expected type declared here".to_string())));
+        self
+    }
+
+    /// Point at the earlier declaration a duplicate one collides with
+    pub fn first_declared_at(mut self, span: Span) -> Self {
+        self.related = Some(Box::new((span, "first declared here".to_string())));
+        self
     }
 }
 
@@ -76,8 +162,12 @@ impl fmt::Display for TypeErrorKind {
             TypeErrorKind::TypeMismatch { expected, found } => {
                 write!(f, "type mismatch: expected {:?}, found {:?}", expected, found)
             }
-            TypeErrorKind::UndefinedVariable(name) => {
-                write!(f, "undefined variable '{}'", name)
+            TypeErrorKind::UndefinedVariable { name, suggestion } => {
+                write!(f, "undefined variable '{}'", name)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean `{}`?", suggestion)?;
+                }
+                Ok(())
             }
             TypeErrorKind::UndefinedType(name) => {
                 write!(f, "undefined type '{}'", name)
@@ -88,6 +178,16 @@ impl fmt::Display for TypeErrorKind {
             TypeErrorKind::BorrowConflict(name) => {
                 write!(f, "cannot borrow '{}' mutably while borrowed", name)
             }
+            TypeErrorKind::MoveWhileBorrowed(name) => {
+                write!(f, "cannot move out of '{}' while it is borrowed", name)
+            }
+            TypeErrorKind::ReturnLocalReference(name) => {
+                write!(
+                    f,
+                    "cannot return a reference to '{}', which is owned by the current function",
+                    name
+                )
+            }
             TypeErrorKind::AssignToImmutable(name) => {
                 write!(f, "cannot assign to immutable variable '{}'", name)
             }
@@ -116,6 +216,52 @@ impl fmt::Display for TypeErrorKind {
             TypeErrorKind::NotIndexable(ty) => {
                 write!(f, "cannot index value of type {:?}", ty)
             }
+            TypeErrorKind::NotCloneable(ty) => {
+                write!(
+                    f,
+                    "value of type {:?} cannot be structured-cloned; only numbers, booleans, strings, arrays and plain objects can",
+                    ty
+                )
+            }
+            TypeErrorKind::AccessorTypeMismatch { property, getter, setter } => {
+                write!(
+                    f,
+                    "getter for '{}' returns {:?}, which is not assignable to its setter type {:?}",
+                    property, getter, setter
+                )
+            }
+            TypeErrorKind::PrivateConstructor { class, access } => {
+                let access = if *access == AccessModifier::Protected { "protected" } else { "private" };
+                write!(
+                    f,
+                    "constructor of class '{}' is {} and only accessible within the class declaration",
+                    class, access
+                )
+            }
+            TypeErrorKind::PrivateMemberAccess { class, property } => {
+                write!(
+                    f,
+                    "property '{}' is not accessible outside class '{}' because it has a private identifier",
+                    property, class
+                )
+            }
+            TypeErrorKind::UninitializedField { class, property } => {
+                write!(
+                    f,
+                    "property '{}' has no initializer and is not definitely assigned in the constructor of '{}'",
+                    property, class
+                )
+            }
+            TypeErrorKind::InitializerUsesParameter { property, param } => {
+                write!(
+                    f,
+                    "initializer of property '{}' cannot reference constructor parameter '{}'; field initializers run before the constructor body",
+                    property, param
+                )
+            }
+            TypeErrorKind::UnusedExpectError => {
+                write!(f, "unused '@zaco-expect-error' directive: the next line has no type error")
+            }
             TypeErrorKind::Generic(msg) => write!(f, "{}", msg),
         }
     }
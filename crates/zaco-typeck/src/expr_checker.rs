@@ -3,12 +3,12 @@
 use std::collections::HashMap;
 use zaco_ast::{
     ArrowBody, AssignmentOp, BinaryOp, BlockStmt, Expr, Ident, Literal, Node,
-    ObjectProperty, Param, Span, UnaryOp,
+    ObjectProperty, Param, Pattern, Span, UnaryOp,
 };
 use crate::checker::TypeChecker;
 use crate::error::{TypeError, TypeErrorKind};
 use crate::types::{LiteralType, Type};
-use crate::ownership::OwnershipState;
+use crate::ownership::{OwnershipState, VarInfo};
 use crate::helpers::TypeHelpers;
 
 impl TypeChecker {
@@ -21,17 +21,49 @@ impl TypeChecker {
             Expr::Assignment { target, op, value } => {
                 self.check_assignment(target, *op, value, span)
             }
+            // `Array(n)` / `new Array<T>(n)`
+            Expr::Call { callee, type_args, args } | Expr::New { callee, type_args, args }
+                if matches!(&callee.value, Expr::Ident(ident) if ident.name == "Array") && self.env.is_global("Array") =>
+            {
+                let mut arg_types = Vec::new();
+                for arg in args {
+                    arg_types.push(TypeHelpers::widen_literal(self.check_expr(&arg.value, &arg.span)?));
+                }
+                let elem = match type_args.as_ref().and_then(|type_args| type_args.first()) {
+                    Some(elem) => Some(self.convert_ast_type(&elem.value)?),
+                    None => None,
+                };
+                Ok(self.array_constructor_type(elem, arg_types))
+            }
             Expr::Call {
                 callee,
                 type_args: _,
                 args,
-            } => self.check_call(callee, args, span),
+            } => match &callee.value {
+                // `obj?.method(args)`: the call is skipped along with the
+                // member when the object is null or undefined
+                Expr::OptionalMember { object, property } => {
+                    let object = Node::new(Expr::NonNullAssertion(object.clone()), object.span);
+                    let method = Expr::Member { object: Box::new(object), property: property.clone(), computed: false };
+                    let ty = self.check_call(&Node::new(method, callee.span), args, span)?;
+                    Ok(Type::Union(vec![ty, Type::Undefined]))
+                }
+                _ => self.check_call(callee, args, span),
+            },
             Expr::Member {
                 object,
                 property,
                 computed,
             } => self.check_member(object, property, *computed, span),
-            Expr::Index { object, index } => self.check_index(object, index, span),
+            // `obj[Symbol.iterator]` names the member declared as `[Symbol.iterator]`
+            Expr::Index { object, index } => match index.value.well_known_symbol() {
+                Some(symbol) => {
+                    self.check_expr(&index.value, &index.span)?;
+                    let key = Node::new(Ident::new(zaco_ast::symbol_key(symbol)), index.span);
+                    self.check_member(object, &key, false, span)
+                }
+                None => self.check_index(object, index, span),
+            },
             Expr::Array(elements) => self.check_array(elements, span),
             Expr::Object(properties) => self.check_object(properties, span),
             Expr::Arrow {
@@ -75,8 +107,12 @@ impl TypeChecker {
                 }
             }
             Expr::Paren(expr) => self.check_expr(&expr.value, &expr.span),
-            Expr::This => Ok(Type::Unknown), // Context-dependent
-            Expr::Super => Ok(Type::Unknown), // Context-dependent
+            // Inside a class body `this` is an instance of that class
+            Expr::This => Ok(match &self.current_class {
+                Some(class) => Type::TypeRef { name: class.clone(), type_args: vec![] },
+                None => Type::Unknown,
+            }),
+            Expr::Super => Ok(self.current_super_type.clone().unwrap_or(Type::Unknown)),
             Expr::Clone(expr) => {
                 let ty = self.check_expr(&expr.value, &expr.span)?;
                 // Clone creates a new owned copy
@@ -112,52 +148,69 @@ impl TypeChecker {
                 property,
                 ..
             } => {
-                // Optional chaining member: expr?.prop - similar to regular member but returns T | undefined
-                let ty = self.check_member(object, property, false, span)?;
+                // Optional chaining member: expr?.prop - similar to regular member but returns T | undefined.
+                // The property is only read when the object isn't null or undefined
+                let object = Node::new(Expr::NonNullAssertion(object.clone()), object.span);
+                let ty = self.check_member(&object, property, false, span)?;
                 Ok(Type::Union(vec![ty, Type::Undefined]))
             }
-            Expr::TaggedTemplate { tag, parts, exprs } => {
-                // Tagged template: tag`template` - check tag as function
-                let _tag_ty = self.check_expr(&tag.value, &tag.span)?;
-                let _template_ty = self.check_template(parts, exprs, span)?;
-                // Return type depends on the tag function's return type
-                Ok(Type::Unknown)
+            Expr::TaggedTemplate { tag, parts, exprs, .. } => {
+                // Tagged template: tag`template` calls the tag with a
+                // TemplateStringsArray and the substitutions
+                let tag_ty = self.check_expr(&tag.value, &tag.span)?;
+                self.check_template(parts, exprs, span)?;
+                match tag_ty {
+                    Type::Function { return_type, .. } => Ok(*return_type),
+                    _ => Ok(Type::Unknown),
+                }
             }
             Expr::Satisfies { expr, ty } => {
-                // Satisfies expression: expr satisfies Type - check expr against ty
+                // Satisfies expression: expr satisfies Type - the expression must be
+                // assignable to ty but keeps its own type. An inner `as` cast has
+                // already replaced that type, so `(e as T) satisfies U` stays a T
                 let expr_ty = self.check_expr(&expr.value, &expr.span)?;
-                let _target_ty = self.convert_ast_type(&ty.value);
-                // TODO: Actually check satisfies constraint
+                let target_ty = self.convert_ast_type(&ty.value)?;
+                if !TypeHelpers::is_assignable_with_env(&expr_ty, &target_ty, Some(&self.env)) {
+                    return Err(TypeError::new(
+                        TypeErrorKind::TypeMismatch { expected: target_ty, found: expr_ty },
+                        expr.span,
+                    )
+                    .declared_at(Some(ty.span)));
+                }
                 Ok(expr_ty)
             }
             Expr::NonNullAssertion(expr) => {
                 // Non-null assertion: expr! - strip null/undefined from type
                 let ty = self.check_expr(&expr.value, &expr.span)?;
-                // TODO: Strip null/undefined from union types
-                Ok(ty)
+                Ok(TypeHelpers::non_nullable(ty))
             }
             Expr::MetaProperty { meta, property } => {
                 // Meta property: new.target, import.meta
                 if meta.value.name == "new" && property.value.name == "target" {
                     Ok(Type::Unknown) // Function | undefined
                 } else if meta.value.name == "import" && property.value.name == "meta" {
-                    Ok(Type::Unknown) // ImportMeta
+                    // ImportMeta: the current module's location
+                    let properties = ["url", "dirname", "filename"]
+                        .iter()
+                        .map(|name| (name.to_string(), Type::String, false))
+                        .collect();
+                    Ok(Type::Object { properties })
                 } else {
                     Ok(Type::Unknown)
                 }
             }
             Expr::Yield { argument, .. } => {
-                // Yield expression: yield expr, yield* expr
+                // Yield expression: yield expr, yield* expr. Its result is
+                // whatever the caller passes to the next `next(value)`.
                 if let Some(arg) = argument {
-                    self.check_expr(&arg.value, &arg.span)
-                } else {
-                    Ok(Type::Undefined)
+                    self.check_expr(&arg.value, &arg.span)?;
                 }
+                Ok(Type::Any)
             }
         }
     }
 
-    fn check_literal(&self, lit: &Literal) -> Type {
+    pub(crate) fn check_literal(&self, lit: &Literal) -> Type {
         match lit {
             Literal::Number(n) => Type::Literal(LiteralType::Number(*n)),
             Literal::String(s) => Type::Literal(LiteralType::String(s.clone())),
@@ -197,14 +250,66 @@ impl TypeChecker {
             }
 
             Ok(var_info.ty.clone())
+        } else if let (Some(used), "arguments") = (self.arguments_used.as_mut(), name) {
+            *used = true;
+            Ok(Type::Array(Box::new(Type::Any)))
         } else {
             Err(TypeError::new(
-                TypeErrorKind::UndefinedVariable(name.to_string()),
+                TypeErrorKind::UndefinedVariable {
+                    name: name.to_string(),
+                    suggestion: self.env.similar_name(name),
+                },
                 span.clone(),
             ))
         }
     }
 
+    /// `key in obj` needs a string, number or symbol key and an object on
+    /// the right: `in` on a primitive throws
+    fn check_in_operands(&self, key: &Type, key_span: Span, object: &Type, object_span: Span) -> Result<(), TypeError> {
+        let resolved = TypeHelpers::resolve_type(key, Some(&self.env));
+        let members = match resolved {
+            Type::Union(members) => members.clone(),
+            other => vec![other.clone()],
+        };
+        let valid_key = members.iter().all(|ty| {
+            TypeHelpers::is_string(ty)
+                || TypeHelpers::is_numeric(ty)
+                || matches!(ty, Type::Symbol | Type::Any | Type::Unknown | Type::Generic { .. } | Type::Enum { .. })
+        });
+        if !valid_key {
+            return Err(TypeError::new(
+                TypeErrorKind::InvalidOperation(format!(
+                    "the left operand of 'in' must be a string, number or symbol, found {:?}",
+                    key
+                )),
+                key_span,
+            ));
+        }
+
+        let resolved = TypeHelpers::resolve_type(object, Some(&self.env));
+        let members = match resolved {
+            Type::Union(members) => members.clone(),
+            other => vec![other.clone()],
+        };
+        let primitive = members.iter().find(|ty| {
+            matches!(
+                ty,
+                Type::Number | Type::String | Type::Boolean | Type::Null | Type::Undefined | Type::Void | Type::Symbol | Type::Literal(_)
+            )
+        });
+        if let Some(primitive) = primitive {
+            return Err(TypeError::new(
+                TypeErrorKind::InvalidOperation(format!(
+                    "the right operand of 'in' must be an object, found {:?}",
+                    primitive
+                )),
+                object_span,
+            ));
+        }
+        Ok(())
+    }
+
     fn check_binary(
         &mut self,
         left: &Node<Expr>,
@@ -212,9 +317,50 @@ impl TypeChecker {
         right: &Node<Expr>,
         _span: &Span,
     ) -> Result<Type, TypeError> {
+        // `#name in obj` is a brand check: only the declaring class may ask
+        if let (BinaryOp::In, Expr::Ident(ident)) = (op, &left.value) {
+            if ident.name.starts_with('#') {
+                self.check_expr(&right.value, &right.span)?;
+                let Some(class) = self.current_class.clone() else {
+                    return Err(TypeError::new(
+                        TypeErrorKind::InvalidOperation(format!("private name '{}' used outside a class body", ident.name)),
+                        left.span,
+                    ));
+                };
+                if !self.class_declares(&class, &ident.name) {
+                    return Err(TypeError::new(
+                        TypeErrorKind::PropertyNotFound {
+                            ty: Type::TypeRef { name: class, type_args: vec![] },
+                            property: ident.name.clone(),
+                        },
+                        left.span,
+                    ));
+                }
+                return Ok(Type::Boolean);
+            }
+        }
+
         let left_ty = self.check_expr(&left.value, &left.span)?;
         let right_ty = self.check_expr(&right.value, &right.span)?;
 
+        if op == BinaryOp::In {
+            self.check_in_operands(&left_ty, left.span, &right_ty, right.span)?;
+        }
+
+        // A `void` result may be compared or passed through `??`, and `&&`/`||`
+        // may yield one, but it cannot be computed with or tested
+        let compares = matches!(op, BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::StrictEq | BinaryOp::StrictNotEq);
+        if !compares && op != BinaryOp::NullishCoalesce {
+            if let Some(err) = Self::void_value(&left_ty, left.span) {
+                return Err(err);
+            }
+            if !matches!(op, BinaryOp::And | BinaryOp::Or) {
+                if let Some(err) = Self::void_value(&right_ty, right.span) {
+                    return Err(err);
+                }
+            }
+        }
+
         match op {
             BinaryOp::Add
             | BinaryOp::Sub
@@ -243,10 +389,14 @@ impl TypeChecker {
                 // && returns the right operand type (if left is truthy)
                 Ok(right_ty)
             }
-            BinaryOp::Or | BinaryOp::NullishCoalesce => {
-                // || and ?? return a union of both operand types
+            BinaryOp::Or => {
+                // || returns a union of both operand types
                 Ok(TypeHelpers::union_type(vec![left_ty, right_ty]))
             }
+            BinaryOp::NullishCoalesce => {
+                // ?? returns the left operand unless it is null or undefined
+                Ok(TypeHelpers::union_type(vec![TypeHelpers::non_nullable(left_ty), right_ty]))
+            }
             BinaryOp::BitAnd
             | BinaryOp::BitOr
             | BinaryOp::BitXor
@@ -257,13 +407,51 @@ impl TypeChecker {
         }
     }
 
+    /// An error if `ty` is `void`: the result of a function that returns
+    /// nothing, used where a value is needed
+    pub(crate) fn void_value(ty: &Type, span: Span) -> Option<TypeError> {
+        matches!(ty, Type::Void).then(|| {
+            TypeError::new(
+                TypeErrorKind::InvalidOperation("an expression of type 'void' cannot be used as a value".to_string()),
+                span,
+            )
+        })
+    }
+
     fn check_unary(
         &mut self,
         op: UnaryOp,
         expr: &Node<Expr>,
         _span: &Span,
     ) -> Result<Type, TypeError> {
-        let _expr_ty = self.check_expr(&expr.value, &expr.span)?;
+        let expr_ty = self.check_expr(&expr.value, &expr.span)?;
+
+        if matches!(op, UnaryOp::Plus | UnaryOp::Minus | UnaryOp::BitNot | UnaryOp::Not) {
+            if let Some(err) = Self::void_value(&expr_ty, expr.span) {
+                return Err(err);
+            }
+        }
+
+        // `delete` takes a property, and not one of a class instance: their
+        // fields are always present
+        if op == UnaryOp::Delete {
+            let object = match &expr.value {
+                Expr::Member { object, .. } | Expr::OptionalMember { object, .. } | Expr::Index { object, .. } => object,
+                _ => {
+                    return Err(TypeError::new(
+                        TypeErrorKind::InvalidOperation("the operand of 'delete' must be a property reference".to_string()),
+                        expr.span,
+                    ))
+                }
+            };
+            let object_ty = self.check_expr(&object.value, &object.span)?;
+            if let Type::Class { name, .. } = TypeHelpers::resolve_type(&object_ty, Some(&self.env)) {
+                return Err(TypeError::new(
+                    TypeErrorKind::InvalidOperation(format!("cannot delete a property of class '{}'", name)),
+                    expr.span,
+                ));
+            }
+        }
 
         match op {
             UnaryOp::Plus | UnaryOp::Minus | UnaryOp::BitNot => Ok(Type::Number),
@@ -287,6 +475,13 @@ impl TypeChecker {
     ) -> Result<Type, TypeError> {
         let value_ty = self.check_expr(&value.value, &value.span)?;
 
+        // Writes to `obj.#name` follow the same access rule as reads
+        if let Expr::Member { property, .. } = &target.value {
+            if property.value.name.starts_with('#') {
+                self.check_expr(&target.value, &target.span)?;
+            }
+        }
+
         // Extract target variable name for ownership tracking
         if let Expr::Ident(ident) = &target.value {
             let var_name = &ident.name;
@@ -307,7 +502,8 @@ impl TypeChecker {
                             found: value_ty.clone(),
                         },
                         span.clone(),
-                    ));
+                    )
+                    .declared_at(self.env.annotation_span(var_name)));
                 }
 
                 // Handle move semantics
@@ -317,6 +513,13 @@ impl TypeChecker {
                     if let Expr::Ident(value_ident) = &value.value {
                         if let Some(value_var) = self.env.lookup(&value_ident.name) {
                             if matches!(value_var.ownership, OwnershipState::Owned) {
+                                // A live reference still points into this value
+                                if self.env.is_borrowed(&value_ident.name) {
+                                    return Err(TypeError::new(
+                                        TypeErrorKind::MoveWhileBorrowed(value_ident.name.clone()),
+                                        value.span.clone(),
+                                    ));
+                                }
                                 // Move the value
                                 let _ = self.env.update_ownership(
                                     &value_ident.name,
@@ -331,7 +534,10 @@ impl TypeChecker {
                 }
             } else {
                 return Err(TypeError::new(
-                    TypeErrorKind::UndefinedVariable(var_name.clone()),
+                    TypeErrorKind::UndefinedVariable {
+                        name: var_name.clone(),
+                        suggestion: self.env.similar_name(var_name),
+                    },
                     span.clone(),
                 ));
             }
@@ -346,6 +552,43 @@ impl TypeChecker {
         args: &[Node<Expr>],
         span: &Span,
     ) -> Result<Type, TypeError> {
+        if let Expr::Member { object, property, .. } = &callee.value {
+            let method = property.value.name.as_str();
+            if matches!(&object.value, Expr::Ident(ident) if ident.name == "Object")
+                && self.env.is_global("Object")
+                && matches!(method, "keys" | "values" | "entries" | "fromEntries" | "assign" | "freeze")
+            {
+                let mut arg_types = Vec::new();
+                for arg in args {
+                    arg_types.push(TypeHelpers::widen_literal(self.check_expr(&arg.value, &arg.span)?));
+                }
+                if let Some(err) = self.object_static_error(method, args, &arg_types, span) {
+                    return Err(err);
+                }
+                return Ok(self.object_static_type(method, arg_types));
+            }
+            if matches!(&object.value, Expr::Ident(ident) if ident.name == "Array")
+                && self.env.is_global("Array")
+                && matches!(method, "from" | "of")
+            {
+                let mut arg_types: Vec<Type> = Vec::new();
+                for (i, arg) in args.iter().enumerate() {
+                    // `Array.from(source, (value, index) => ...)`
+                    if method == "from" && i == 1 && matches!(arg.value, Expr::Arrow { .. } | Expr::Function { .. }) {
+                        let value_ty = self.array_from_element_type(&arg_types[0]).map_or(Type::Unknown, TypeHelpers::widen_literals);
+                        self.callback_params = Some(vec![value_ty, Type::Number]);
+                    }
+                    let ty = self.check_expr(&arg.value, &arg.span);
+                    self.callback_params = None;
+                    arg_types.push(TypeHelpers::widen_literal(ty?));
+                }
+                if let Some(err) = self.array_static_error(method, args, &arg_types, span) {
+                    return Err(err);
+                }
+                return Ok(self.array_static_type(method, arg_types));
+            }
+        }
+
         let callee_ty = self.check_expr(&callee.value, &callee.span)?;
 
         match &callee_ty {
@@ -366,9 +609,24 @@ impl TypeChecker {
                     ));
                 }
 
-                // Check argument types
+                let is_worker_spawn = matches!(
+                    &callee.value,
+                    Expr::Ident(ident) if self.worker_spawn_names.contains(&ident.name)
+                );
+
+                // Check argument types. A parameter typed by a bare type
+                // parameter accepts anything and binds it for the return type.
+                let mut bindings = HashMap::new();
                 for (i, arg) in args.iter().enumerate() {
                     let arg_ty = self.check_expr(&arg.value, &arg.span)?;
+                    // Worker arguments are copied to another thread
+                    if is_worker_spawn && i == 1 && !TypeHelpers::is_structured_cloneable(&arg_ty, Some(&self.env)) {
+                        return Err(TypeError::new(TypeErrorKind::NotCloneable(arg_ty), arg.span.clone()));
+                    }
+                    if let Some(Type::Generic { name, .. }) = params.get(i) {
+                        bindings.insert(name.clone(), TypeHelpers::widen_literal(arg_ty));
+                        continue;
+                    }
                     if let Some(param_ty) = params.get(i) {
                         if !TypeHelpers::is_assignable_with_env(&arg_ty, param_ty, Some(&self.env)) {
                             return Err(TypeError::new(
@@ -382,12 +640,22 @@ impl TypeChecker {
                     }
                 }
 
-                Ok((**return_type).clone())
+                // A returned `ref T` is used as a T; the borrow itself is
+                // tracked by the binding that receives it (see check_var_decl)
+                Ok(TypeHelpers::substitute_type_params(return_type.without_ref(), &bindings))
             }
             Type::Class { name, .. } => {
                 // Constructor call
                 Ok(Type::TypeRef { name: name.clone(), type_args: vec![] })
             }
+            // As in TypeScript, `any` can be called (e.g. the default export
+            // of "assert"); the result is `any` too
+            Type::Any => {
+                for arg in args {
+                    self.check_expr(&arg.value, &arg.span)?;
+                }
+                Ok(Type::Any)
+            }
             _ => Err(TypeError::new(
                 TypeErrorKind::NotCallable(callee_ty),
                 span.clone(),
@@ -395,6 +663,174 @@ impl TypeChecker {
         }
     }
 
+    /// Arity and argument errors for `Object.<method>(...)`: `assign` takes
+    /// one or more objects, `fromEntries` an array of pairs, the rest one value.
+    fn object_static_error(&self, method: &str, args: &[Node<Expr>], arg_types: &[Type], span: &Span) -> Option<TypeError> {
+        let arity_ok = if method == "assign" { !args.is_empty() } else { args.len() == 1 };
+        if !arity_ok {
+            return Some(TypeError::new(TypeErrorKind::ArityMismatch { expected: 1, found: args.len() }, *span));
+        }
+        let pairs = Type::Array(Box::new(Type::Any));
+        if method == "fromEntries" && !TypeHelpers::is_assignable_with_env(&arg_types[0], &pairs, Some(&self.env)) {
+            return Some(TypeError::new(
+                TypeErrorKind::TypeMismatch { expected: pairs, found: arg_types[0].clone() },
+                args[0].span,
+            ));
+        }
+        None
+    }
+
+    /// `Object.keys/values/entries/fromEntries/assign/freeze(...)`. Values
+    /// have the union of the argument's property types, entries are
+    /// `[string, V]` pairs and `assign` merges the properties of its
+    /// arguments, later ones winning.
+    fn object_static_type(&self, method: &str, mut arg_types: Vec<Type>) -> Type {
+        let properties = |ty: &Type| match TypeHelpers::resolve_type(ty, Some(&self.env)) {
+            Type::Object { properties } | Type::Interface { properties, .. } => Some(properties.clone()),
+            _ => None,
+        };
+        let value_type = || match properties(&arg_types[0]) {
+            Some(props) => {
+                let mut members: Vec<Type> = Vec::new();
+                for (_, ty, _) in props {
+                    let ty = TypeHelpers::widen_literal(ty);
+                    if !members.contains(&ty) {
+                        members.push(ty);
+                    }
+                }
+                TypeHelpers::union_type(members)
+            }
+            None => Type::Any,
+        };
+        match method {
+            "keys" => Type::Array(Box::new(Type::String)),
+            "values" => Type::Array(Box::new(value_type())),
+            "entries" => Type::Array(Box::new(Type::Tuple(vec![Type::String, value_type()]))),
+            "fromEntries" => Type::Any,
+            "assign" => {
+                let mut merged: Vec<(String, Type, bool)> = Vec::new();
+                for ty in &arg_types {
+                    let Some(props) = properties(ty) else { return Type::Any };
+                    for prop in props {
+                        merged.retain(|(name, _, _)| *name != prop.0);
+                        merged.push(prop);
+                    }
+                }
+                Type::Object { properties: merged }
+            }
+            _ => arg_types.remove(0),
+        }
+    }
+
+    /// Arity and argument errors for `Array.from(source, mapper?)`: the
+    /// source must be iterable or array-like, the mapper a function.
+    fn array_static_error(&self, method: &str, args: &[Node<Expr>], arg_types: &[Type], span: &Span) -> Option<TypeError> {
+        if method == "of" {
+            return None;
+        }
+        if args.is_empty() || args.len() > 2 {
+            return Some(TypeError::new(TypeErrorKind::ArityMismatch { expected: 1, found: args.len() }, *span));
+        }
+        if self.array_from_element_type(&arg_types[0]).is_none() {
+            return Some(TypeError::new(
+                TypeErrorKind::TypeMismatch {
+                    expected: Type::Array(Box::new(Type::Any)),
+                    found: arg_types[0].clone(),
+                },
+                args[0].span,
+            ));
+        }
+        let mapper = arg_types.get(1).map(|ty| TypeHelpers::resolve_type(ty, Some(&self.env)));
+        match mapper {
+            Some(Type::Function { .. } | Type::Any | Type::Unknown) | None => None,
+            Some(found) => Some(TypeError::new(
+                TypeErrorKind::TypeMismatch {
+                    expected: Type::Function { params: vec![Type::Any], return_type: Box::new(Type::Any) },
+                    found: found.clone(),
+                },
+                args[1].span,
+            )),
+        }
+    }
+
+    /// `Array.of(...)` holds its arguments; `Array.from(source)` the values
+    /// iterating `source` produces, or what the mapper returns for them.
+    fn array_static_type(&self, method: &str, arg_types: Vec<Type>) -> Type {
+        let elem = match (method, arg_types.get(1).map(|ty| TypeHelpers::resolve_type(ty, Some(&self.env)))) {
+            ("of", _) if arg_types.is_empty() => Type::Never,
+            ("of", _) => {
+                let mut members: Vec<Type> = Vec::new();
+                for ty in arg_types {
+                    if !members.contains(&ty) {
+                        members.push(ty);
+                    }
+                }
+                TypeHelpers::union_type(members)
+            }
+            (_, Some(Type::Function { return_type, .. })) => (**return_type).clone(),
+            (_, Some(_)) => Type::Any,
+            (_, None) => self.array_from_element_type(&arg_types[0]).unwrap_or(Type::Any),
+        };
+        Type::Array(Box::new(elem))
+    }
+
+    /// `Array(n)` / `new Array(n)` is `n` empty slots, of the element type
+    /// given as a type argument or `any`; with any other arguments it holds
+    /// them, like `Array.of`.
+    fn array_constructor_type(&self, elem: Option<Type>, arg_types: Vec<Type>) -> Type {
+        match (elem, arg_types.as_slice()) {
+            (Some(elem), _) => Type::Array(Box::new(elem)),
+            (None, [] | [Type::Number]) => Type::Array(Box::new(Type::Any)),
+            (None, _) => self.array_static_type("of", arg_types),
+        }
+    }
+
+    /// Type of the values `Array.from` reads from `source`: what iterating
+    /// it produces, or `undefined` for an array-like `{ length }` object.
+    fn array_from_element_type(&self, source: &Type) -> Option<Type> {
+        if let Some(elem) = self.iterable_element_type(source, false) {
+            return Some(elem);
+        }
+        match TypeHelpers::resolve_type(source, Some(&self.env)) {
+            Type::Object { properties } | Type::Interface { properties, .. }
+                if properties.iter().any(|(name, _, _)| name == "length") =>
+            {
+                Some(Type::Undefined)
+            }
+            Type::Any | Type::Unknown => Some(Type::Any),
+            _ => None,
+        }
+    }
+
+    /// `#name` members are reachable only from the body of the class that
+    /// declares them; subclasses do not inherit them.
+    fn private_access_error(&self, object_ty: &Type, name: &str, span: &Span) -> Option<TypeError> {
+        let class = match object_ty {
+            Type::Class { name, .. } | Type::TypeRef { name, .. } => name,
+            _ => return None,
+        };
+        if !name.starts_with('#') || self.current_class.as_ref() == Some(class) {
+            return None;
+        }
+        Some(TypeError::new(
+            TypeErrorKind::PrivateMemberAccess { class: class.clone(), property: name.to_string() },
+            *span,
+        ))
+    }
+
+    /// Whether a class declares an instance or static member called `name`
+    fn class_declares(&self, class: &str, name: &str) -> bool {
+        match self.env.lookup_type(class) {
+            Some(Type::Class { fields, methods, accessors, statics, .. }) => fields
+                .iter()
+                .chain(methods)
+                .chain(accessors)
+                .chain(statics)
+                .any(|(member, _)| member == name),
+            _ => false,
+        }
+    }
+
     fn check_member(
         &mut self,
         object: &Node<Expr>,
@@ -404,6 +840,9 @@ impl TypeChecker {
     ) -> Result<Type, TypeError> {
         let object_ty = self.check_expr(&object.value, &object.span)?;
         let prop_name = &property.value.name;
+        if let Some(err) = self.private_access_error(&object_ty, prop_name, span) {
+            return Err(err);
+        }
 
         match &object_ty {
             Type::Object { properties } => {
@@ -420,15 +859,9 @@ impl TypeChecker {
                     span.clone(),
                 ))
             }
-            Type::Class { fields, methods, .. } => {
-                // Check fields
-                for (name, ty) in fields {
-                    if name == prop_name {
-                        return Ok(ty.clone());
-                    }
-                }
-                // Check methods
-                for (name, ty) in methods {
+            Type::Class { statics, .. } => {
+                // The class value itself: `ClassName.member` reaches statics
+                for (name, ty) in statics {
                     if name == prop_name {
                         return Ok(ty.clone());
                     }
@@ -474,8 +907,8 @@ impl TypeChecker {
                 // Resolve TypeRef to the actual class/interface type
                 if let Some(resolved) = self.env.lookup_type(name) {
                     match resolved {
-                        Type::Class { fields, methods, .. } => {
-                            for (fname, fty) in fields {
+                        Type::Class { fields, methods, accessors, .. } => {
+                            for (fname, fty) in fields.iter().chain(accessors) {
                                 if fname == prop_name {
                                     let result_ty = if let Some(ref map) = subst_map {
                                         TypeHelpers::substitute_type_params(fty, map)
@@ -487,12 +920,11 @@ impl TypeChecker {
                             }
                             for (mname, mty) in methods {
                                 if mname == prop_name {
-                                    let result_ty = if let Some(ref map) = subst_map {
-                                        TypeHelpers::substitute_type_params(mty, map)
-                                    } else {
-                                        mty.clone()
-                                    };
-                                    return Ok(result_ty);
+                                    // A `this` return type is the receiver's class,
+                                    // which may be a subclass of the declaring one
+                                    let mut map = subst_map.clone().unwrap_or_default();
+                                    map.insert("this".to_string(), object_ty.clone());
+                                    return Ok(TypeHelpers::substitute_type_params(mty, &map));
                                 }
                             }
                             Err(TypeError::new(
@@ -536,6 +968,58 @@ impl TypeChecker {
                 }
                 Ok(Type::Any)
             }
+            Type::Tuple(_) if prop_name == "length" => Ok(Type::Number),
+            Type::Array(ref elem_ty) => crate::builtins::array_member_type(prop_name, elem_ty).ok_or_else(|| {
+                TypeError::new(
+                    TypeErrorKind::PropertyNotFound {
+                        ty: object_ty.clone(),
+                        property: prop_name.clone(),
+                    },
+                    *span,
+                )
+            }),
+            // An intersection has the members of its object and array parts
+            Type::Intersection(members) => members
+                .iter()
+                .find_map(|member| match member {
+                    Type::Object { properties } => properties
+                        .iter()
+                        .find(|(name, _, _)| name == prop_name)
+                        .map(|(_, ty, _)| ty.clone()),
+                    Type::Array(_) if prop_name == "length" => Some(Type::Number),
+                    _ => None,
+                })
+                .ok_or_else(|| {
+                    TypeError::new(
+                        TypeErrorKind::PropertyNotFound {
+                            ty: object_ty.clone(),
+                            property: prop_name.clone(),
+                        },
+                        *span,
+                    )
+                }),
+            Type::String | Type::Literal(LiteralType::String(_)) => {
+                crate::builtins::string_member_type(prop_name).ok_or_else(|| {
+                    TypeError::new(
+                        TypeErrorKind::PropertyNotFound {
+                            ty: object_ty.clone(),
+                            property: prop_name.clone(),
+                        },
+                        span.clone(),
+                    )
+                })
+            }
+            Type::Promise(value) => {
+                crate::builtins::promise_member_type(prop_name, value).ok_or_else(|| {
+                    TypeError::new(
+                        TypeErrorKind::PropertyNotFound {
+                            ty: object_ty.clone(),
+                            property: prop_name.clone(),
+                        },
+                        *span,
+                    )
+                })
+            }
             Type::Any | Type::Unknown => Ok(Type::Any),
             _ => Err(TypeError::new(
                 TypeErrorKind::PropertyNotFound {
@@ -561,9 +1045,21 @@ impl TypeChecker {
             Type::Tuple(types) => {
                 // If we can determine index statically, return that type
                 // Otherwise, return union of all types
-                Ok(TypeHelpers::union_type(types.clone()))
+                match &index.value {
+                    Expr::Literal(Literal::Number(n)) if n.fract() == 0.0 && *n >= 0.0 && (*n as usize) < types.len() => {
+                        Ok(types[*n as usize].clone())
+                    }
+                    _ => Ok(TypeHelpers::union_type(types.clone())),
+                }
             }
             Type::Object { .. } => Ok(Type::Any), // Object indexing
+            Type::Intersection(members) => match members.iter().find_map(|member| match member {
+                Type::Array(elem_ty) => Some((**elem_ty).clone()),
+                _ => None,
+            }) {
+                Some(elem_ty) => Ok(elem_ty),
+                None => Err(TypeError::new(TypeErrorKind::NotIndexable(object_ty.clone()), *span)),
+            },
             Type::Any | Type::Unknown => Ok(Type::Any),
             _ => Err(TypeError::new(
                 TypeErrorKind::NotIndexable(object_ty),
@@ -577,18 +1073,44 @@ impl TypeChecker {
         elements: &[Option<Node<Expr>>],
         _span: &Span,
     ) -> Result<Type, TypeError> {
+        let as_tuple = std::mem::take(&mut self.tuple_context);
         let mut elem_types = Vec::new();
 
         for elem in elements {
             if let Some(elem) = elem {
-                let elem_ty = self.check_expr(&elem.value, &elem.span)?;
+                let elem_ty = match &elem.value {
+                    // `[...iterable]` holds the values iterating it produces
+                    Expr::Spread(inner) => {
+                        let iterable_ty = self.check_expr(&inner.value, &inner.span)?;
+                        match self.iterable_element_type(&iterable_ty, false) {
+                            Some(ty) => ty,
+                            None if matches!(iterable_ty, Type::Any | Type::Unknown) => Type::Any,
+                            None => {
+                                return Err(TypeError::new(
+                                    TypeErrorKind::TypeMismatch {
+                                        expected: Type::Array(Box::new(Type::Any)),
+                                        found: iterable_ty,
+                                    },
+                                    inner.span,
+                                ))
+                            }
+                        }
+                    }
+                    _ => self.check_expr(&elem.value, &elem.span)?,
+                };
                 elem_types.push(elem_ty);
             }
         }
 
-        // Infer array type as union of all element types
+        let has_spread = elements.iter().flatten().any(|elem| matches!(elem.value, Expr::Spread(_)));
+        if as_tuple && !has_spread && elements.iter().all(Option::is_some) {
+            return Ok(Type::Tuple(elem_types));
+        }
+
+        // Infer array type as union of all element types. An empty literal
+        // has no elements to disagree with, so it fits any array type.
         let elem_ty = if elem_types.is_empty() {
-            Type::Unknown
+            Type::Never
         } else if elem_types.len() == 1 {
             elem_types[0].clone()
         } else {
@@ -635,6 +1157,28 @@ impl TypeChecker {
                     };
                     props.push((method_name, method_ty, false));
                 }
+                ObjectProperty::Getter { key, return_type, body } => {
+                    self.env.push_scope();
+                    let result = self.check_closure_body(return_type.as_deref(), body);
+                    self.env.pop_scope();
+                    let prop_name = TypeHelpers::property_name_to_string(key);
+                    props.retain(|(name, _, _)| *name != prop_name);
+                    props.push((prop_name, result?, false));
+                }
+                ObjectProperty::Setter { key, param, body } => {
+                    self.env.push_scope();
+                    let mut result = self.check_param(param);
+                    if result.is_ok() {
+                        result = self.check_closure_body(None, body).map(|_| ());
+                    }
+                    self.env.pop_scope();
+                    result?;
+                    // With a getter too, the property has the getter's type
+                    let prop_name = TypeHelpers::property_name_to_string(key);
+                    if !props.iter().any(|(name, _, _)| *name == prop_name) {
+                        props.push((prop_name, self.resolve_param_type(param)?, false));
+                    }
+                }
                 ObjectProperty::Spread(_) => {
                     // Handle spread
                 }
@@ -651,26 +1195,23 @@ impl TypeChecker {
         body: &ArrowBody,
         _span: &Span,
     ) -> Result<Type, TypeError> {
-        self.env.push_scope();
+        self.env.push_function_scope();
 
+        let context = self.callback_params.take();
         let mut param_types = Vec::new();
         for param in params {
-            let param_ty = self.resolve_param_type(param)?;
-            param_types.push(param_ty.clone());
+            let param_ty = self.resolve_signature_param_type(param)?;
+            param_types.push(param_ty);
 
             // Declare parameter
             self.check_param(param)?;
         }
+        self.declare_contextual_params(params, &mut param_types, context);
 
         let ret_ty = match body {
             ArrowBody::Expr(expr) => self.check_expr(&expr.value, &expr.span)?,
             ArrowBody::Block(block) => {
-                self.check_block_stmt(&block.value, &block.span)?;
-                if let Some(ret_ty) = return_type {
-                    self.convert_ast_type(&ret_ty.value)?
-                } else {
-                    Type::Void
-                }
+                self.check_closure_body(return_type.map(|t| t.as_ref()), block)?
             }
         };
 
@@ -689,23 +1230,25 @@ impl TypeChecker {
         body: &Node<BlockStmt>,
         _span: &Span,
     ) -> Result<Type, TypeError> {
-        self.env.push_scope();
+        self.env.push_function_scope();
 
+        let context = self.callback_params.take();
         let mut param_types = Vec::new();
         for param in params {
-            let param_ty = self.resolve_param_type(param)?;
+            let param_ty = self.resolve_signature_param_type(param)?;
             param_types.push(param_ty);
 
             self.check_param(param)?;
         }
+        self.declare_contextual_params(params, &mut param_types, context);
 
-        self.check_block_stmt(&body.value, &body.span)?;
-
-        let ret_ty = if let Some(ret_ty) = return_type {
-            self.convert_ast_type(&ret_ty.value)?
-        } else {
-            Type::Void
-        };
+        // `arguments` of a function expression is not supported (only
+        // function declarations materialize it), and must not resolve to the
+        // enclosing function's
+        let prev_arguments = self.arguments_used.take();
+        let ret_ty = self.check_closure_body(return_type.map(|t| t.as_ref()), body);
+        self.arguments_used = prev_arguments;
+        let ret_ty = ret_ty?;
 
         self.env.pop_scope();
 
@@ -715,6 +1258,51 @@ impl TypeChecker {
         })
     }
 
+    /// Give an arrow or function expression's unannotated parameters the
+    /// types the enclosing call provides in `callback_params`, if any, and
+    /// redeclare them with those types.
+    fn declare_contextual_params(&mut self, params: &[Param], param_types: &mut [Type], context: Option<Vec<Type>>) {
+        for ((param, param_ty), context_ty) in params.iter().zip(param_types.iter_mut()).zip(context.unwrap_or_default()) {
+            if let (Type::Unknown, Pattern::Ident { name, .. }) = (&param_ty, &param.pattern.value) {
+                *param_ty = context_ty;
+                self.env.declare(
+                    name.value.name.clone(),
+                    VarInfo {
+                        ty: param_ty.clone(),
+                        ownership: OwnershipState::Owned,
+                        is_mutable: true,
+                        is_initialized: true,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Check the block body of an arrow or function expression. Its `return`s
+    /// are validated against its own annotation, not the enclosing function's.
+    fn check_closure_body(
+        &mut self,
+        return_type: Option<&Node<zaco_ast::Type>>,
+        body: &Node<BlockStmt>,
+    ) -> Result<Type, TypeError> {
+        let declared = self.resolve_return_type(return_type)?;
+        let prev_return_type = std::mem::replace(
+            &mut self.current_return_type,
+            (declared != Type::Void).then_some(declared),
+        );
+        let prev_return_span = std::mem::replace(&mut self.current_return_span, return_type.map(|t| t.span));
+        let prev_returned = std::mem::take(&mut self.returned_types);
+
+        self.hoist_var_declarations(&body.value.stmts);
+        let result = self.check_block_stmt(&body.value, &body.span);
+
+        self.current_return_type = prev_return_type;
+        self.current_return_span = prev_return_span;
+        let returned = std::mem::replace(&mut self.returned_types, prev_returned);
+        result?;
+        self.infer_return_type(return_type, &returned)
+    }
+
     fn check_ternary(
         &mut self,
         condition: &Node<Expr>,
@@ -722,7 +1310,10 @@ impl TypeChecker {
         else_expr: &Node<Expr>,
         _span: &Span,
     ) -> Result<Type, TypeError> {
-        let _cond_ty = self.check_expr(&condition.value, &condition.span)?;
+        let cond_ty = self.check_expr(&condition.value, &condition.span)?;
+        if let Some(err) = Self::void_value(&cond_ty, condition.span) {
+            return Err(err);
+        }
         let then_ty = self.check_expr(&then_expr.value, &then_expr.span)?;
         let else_ty = self.check_expr(&else_expr.value, &else_expr.span)?;
 
@@ -745,10 +1336,22 @@ impl TypeChecker {
         &mut self,
         callee: &Node<Expr>,
         args: &[Node<Expr>],
-        _span: &Span,
+        span: &Span,
     ) -> Result<Type, TypeError> {
         let callee_ty = self.check_expr(&callee.value, &callee.span)?;
 
+        // A private or protected constructor is only callable inside its class
+        if let Expr::Ident(ident) = &callee.value {
+            if let Some(&access) = self.restricted_constructors.get(&ident.name) {
+                if self.current_class.as_ref() != Some(&ident.name) {
+                    return Err(TypeError::new(
+                        TypeErrorKind::PrivateConstructor { class: ident.name.clone(), access },
+                        *span,
+                    ));
+                }
+            }
+        }
+
         // Check constructor arguments
         for arg in args {
             self.check_expr(&arg.value, &arg.span)?;
@@ -827,6 +1430,32 @@ impl TypeChecker {
                 if type_name == "Promise" && converted_args.len() == 1 {
                     return Ok(Type::Promise(Box::new(converted_args.into_iter().next().unwrap())));
                 }
+                if matches!(type_name.as_str(), "Generator" | "IterableIterator")
+                    && self.env.lookup_type(&type_name).is_none()
+                {
+                    let yield_ty = converted_args.into_iter().next().unwrap_or(Type::Any);
+                    return Ok(crate::builtins::generator_type(yield_ty));
+                }
+                if matches!(type_name.as_str(), "AsyncGenerator" | "AsyncIterableIterator")
+                    && self.env.lookup_type(&type_name).is_none()
+                {
+                    let yield_ty = converted_args.into_iter().next().unwrap_or(Type::Any);
+                    return Ok(crate::builtins::async_generator_type(yield_ty));
+                }
+                if type_name == "TemplateStringsArray" && self.env.lookup_type(&type_name).is_none() {
+                    return Ok(crate::builtins::template_strings_array_type());
+                }
+                if type_name == "RegExpMatchArray" && self.env.lookup_type(&type_name).is_none() {
+                    return Ok(crate::builtins::regexp_match_array_type());
+                }
+                if self.env.lookup_type(&type_name).is_none() {
+                    if let Some(context) = crate::builtins::decorator_context_type(&type_name) {
+                        return Ok(context);
+                    }
+                }
+                if type_name == "symbol" && self.env.lookup_type(&type_name).is_none() {
+                    return Ok(Type::Symbol);
+                }
 
                 Ok(Type::TypeRef { name: type_name, type_args: converted_args })
             }
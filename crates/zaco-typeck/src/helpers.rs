@@ -41,12 +41,23 @@ impl TypeHelpers {
         }
     }
 
+    /// Ownership written on a type annotation itself (`ref T`, `mut ref T`, `owned T`)
+    pub fn annotation_ownership(ty: &zaco_ast::Type) -> Option<OwnershipState> {
+        match ty {
+            zaco_ast::Type::WithOwnership { ownership, .. } => {
+                Some(Self::convert_ownership(&ownership.kind))
+            }
+            zaco_ast::Type::Paren(inner) => Self::annotation_ownership(&inner.value),
+            _ => None,
+        }
+    }
+
     pub fn property_name_to_string(name: &PropertyName) -> String {
         match name {
             PropertyName::Ident(ident) => ident.value.name.clone(),
             PropertyName::String(s) => s.clone(),
             PropertyName::Number(n) => n.to_string(),
-            PropertyName::Computed(_) => "__computed__".to_string(),
+            PropertyName::Computed(_) => name.symbol_key().unwrap_or_else(|| "__computed__".to_string()),
         }
     }
 
@@ -82,6 +93,9 @@ impl TypeHelpers {
             (Type::TypeRef { .. }, _) | (_, Type::TypeRef { .. }) => true,
             // Everything is assignable to Unknown
             (_, Type::Unknown) => true,
+            // A reference is checked against the type it points to
+            (Type::Ref { inner, .. }, _) => Self::is_assignable_with_env(inner, to, env),
+            (_, Type::Ref { inner, .. }) => Self::is_assignable_with_env(from, inner, env),
             // Never is assignable to everything (bottom type)
             (Type::Never, _) => true,
             // Null and Undefined are distinct — do NOT treat as interchangeable
@@ -93,6 +107,14 @@ impl TypeHelpers {
             (Type::Array(from_elem), Type::Array(to_elem)) => {
                 Self::is_assignable_with_env(from_elem, to_elem, env)
             }
+            // Tuples match element by element, and fit arrays of a common element type
+            (Type::Tuple(from_elems), Type::Tuple(to_elems)) => {
+                from_elems.len() == to_elems.len()
+                    && from_elems.iter().zip(to_elems).all(|(from, to)| Self::is_assignable_with_env(from, to, env))
+            }
+            (Type::Tuple(from_elems), Type::Array(to_elem)) => {
+                from_elems.iter().all(|from| Self::is_assignable_with_env(from, to_elem, env))
+            }
             // Promise covariance
             (Type::Promise(from_inner), Type::Promise(to_inner)) => {
                 Self::is_assignable_with_env(from_inner, to_inner, env)
@@ -121,6 +143,17 @@ impl TypeHelpers {
                 }
                 Self::is_assignable_with_env(from_ret, to_ret, env)
             }
+            // Objects are structural: each required property of the target
+            // must be present with an assignable type; extra ones are allowed
+            (
+                Type::Object { properties: from_props } | Type::Interface { properties: from_props, .. },
+                Type::Object { properties: to_props } | Type::Interface { properties: to_props, .. },
+            ) => to_props.iter().all(|(name, to_ty, optional)| {
+                match from_props.iter().find(|(from_name, _, _)| from_name == name) {
+                    Some((_, from_ty, _)) => Self::is_assignable_with_env(from_ty, to_ty, env),
+                    None => *optional,
+                }
+            }),
             _ => false,
         }
     }
@@ -139,6 +172,73 @@ impl TypeHelpers {
         )
     }
 
+    /// Whether a value of this type can be structured-cloned: primitives,
+    /// arrays, tuples and plain objects of cloneable values. Functions,
+    /// class instances and promises hold state that cannot be copied.
+    pub fn is_structured_cloneable(ty: &Type, env: Option<&TypeEnv>) -> bool {
+        match Self::resolve_type(ty, env) {
+            Type::Number | Type::String | Type::Boolean | Type::Null | Type::Undefined
+            | Type::Literal(_) | Type::Enum { .. } => true,
+            // Unknown at compile time; nothing to reject
+            Type::Any | Type::Unknown | Type::Never | Type::Generic { .. } | Type::TypeRef { .. } => true,
+            Type::Array(elem) => Self::is_structured_cloneable(elem, env),
+            Type::Tuple(elems) | Type::Union(elems) => {
+                elems.iter().all(|e| Self::is_structured_cloneable(e, env))
+            }
+            Type::Object { properties } | Type::Interface { properties, .. } => properties
+                .iter()
+                .all(|(_, ty, _)| Self::is_structured_cloneable(ty, env)),
+            Type::Ref { inner, .. } => Self::is_structured_cloneable(inner, env),
+            Type::Void | Type::Function { .. } | Type::Class { .. } | Type::Promise(_)
+            | Type::Intersection(_) | Type::Symbol => false,
+        }
+    }
+
+    /// Levenshtein distance between two names: the fewest single-character
+    /// insertions, deletions and substitutions turning one into the other.
+    pub fn edit_distance(a: &str, b: &str) -> usize {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, ca) in a.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, cb) in b.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = if ca == *cb { diagonal } else { 1 + diagonal.min(above).min(row[j]) };
+                diagonal = above;
+            }
+        }
+        row[b.len()]
+    }
+
+    /// Widen a literal type to its primitive (`5` → `number`).
+    pub fn widen_literal(ty: Type) -> Type {
+        match ty {
+            Type::Literal(LiteralType::Number(_)) => Type::Number,
+            Type::Literal(LiteralType::String(_)) => Type::String,
+            Type::Literal(LiteralType::Boolean(_)) => Type::Boolean,
+            other => other,
+        }
+    }
+
+    /// `widen_literal`, applied to each member of a union as well, so
+    /// `1 | 2 | 3` widens to `number`
+    pub fn widen_literals(ty: Type) -> Type {
+        match ty {
+            Type::Union(members) => {
+                let mut widened: Vec<Type> = Vec::new();
+                for member in members {
+                    let member = Self::widen_literal(member);
+                    if !widened.contains(&member) {
+                        widened.push(member);
+                    }
+                }
+                Self::union_type(widened)
+            }
+            other => Self::widen_literal(other),
+        }
+    }
+
     pub fn union_type(types: Vec<Type>) -> Type {
         if types.is_empty() {
             Type::Never
@@ -149,6 +249,25 @@ impl TypeHelpers {
         }
     }
 
+    /// A type without its null and undefined members, as `expr!` and the
+    /// left side of `??` have. Nested unions, such as `a?.b` on a nullable
+    /// `b` produces, are flattened first.
+    pub fn non_nullable(ty: Type) -> Type {
+        match ty {
+            Type::Union(members) => Self::union_type(
+                members
+                    .into_iter()
+                    .flat_map(|member| match member {
+                        Type::Union(inner) => inner,
+                        other => vec![other],
+                    })
+                    .filter(|member| !matches!(member, Type::Null | Type::Undefined))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
     /// Substitute type parameters with concrete types.
     /// Walks the type tree recursively, replacing Generic/TypeRef names found in `params`
     /// with their concrete types.
@@ -184,6 +303,10 @@ impl TypeHelpers {
             Type::Promise(inner) => {
                 Type::Promise(Box::new(Self::substitute_type_params(inner, params)))
             }
+            Type::Ref { inner, mutable } => Type::Ref {
+                inner: Box::new(Self::substitute_type_params(inner, params)),
+                mutable: *mutable,
+            },
             Type::Tuple(types) => {
                 Type::Tuple(types.iter().map(|t| Self::substitute_type_params(t, params)).collect())
             }
@@ -206,7 +329,7 @@ impl TypeHelpers {
                         .collect(),
                 }
             }
-            Type::Class { name, fields, methods } => {
+            Type::Class { name, fields, methods, accessors, statics } => {
                 Type::Class {
                     name: name.clone(),
                     fields: fields.iter()
@@ -215,6 +338,12 @@ impl TypeHelpers {
                     methods: methods.iter()
                         .map(|(n, t)| (n.clone(), Self::substitute_type_params(t, params)))
                         .collect(),
+                    accessors: accessors.iter()
+                        .map(|(n, t)| (n.clone(), Self::substitute_type_params(t, params)))
+                        .collect(),
+                    statics: statics.iter()
+                        .map(|(n, t)| (n.clone(), Self::substitute_type_params(t, params)))
+                        .collect(),
                 }
             }
             Type::Interface { name, properties } => {
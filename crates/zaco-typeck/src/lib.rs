@@ -105,7 +105,7 @@ mod tests {
         if let Err(errors) = result {
             assert_eq!(errors.len(), 1);
             assert!(matches!(
-                errors[0].kind,
+                *errors[0].kind,
                 TypeErrorKind::TypeMismatch { .. }
             ));
         }
@@ -125,12 +125,44 @@ mod tests {
         if let Err(errors) = result {
             assert_eq!(errors.len(), 1);
             assert!(matches!(
-                errors[0].kind,
-                TypeErrorKind::UndefinedVariable(_)
+                *errors[0].kind,
+                TypeErrorKind::UndefinedVariable { .. }
             ));
         }
     }
 
+    #[test]
+    fn test_undefined_variable_suggests_close_name() {
+        // conosle.log("hi");
+        let program = Program {
+            items: vec![make_node(ModuleItem::Stmt(make_node(Stmt::Expr(make_node(Expr::Call {
+                callee: Box::new(make_node(Expr::Member {
+                    object: Box::new(make_node(Expr::Ident(Ident::new("conosle")))),
+                    property: make_node(Ident::new("log")),
+                    computed: false,
+                })),
+                type_args: None,
+                args: vec![make_node(Expr::Literal(Literal::String("hi".to_string())))],
+            })))))],
+            span: dummy_span(),
+        };
+        let errors = check_program(&program).unwrap_err();
+        assert_eq!(
+            errors[0].kind.to_string(),
+            "undefined variable 'conosle'; did you mean `console`?"
+        );
+
+        // Nothing in scope is close to `qqq`
+        let program = Program {
+            items: vec![make_node(ModuleItem::Stmt(make_node(Stmt::Expr(make_node(Expr::Ident(
+                Ident::new("qqq"),
+            ))))))],
+            span: dummy_span(),
+        };
+        let errors = check_program(&program).unwrap_err();
+        assert_eq!(errors[0].kind.to_string(), "undefined variable 'qqq'");
+    }
+
     #[test]
     fn test_function_type() {
         let program = Program {
@@ -153,6 +185,7 @@ mod tests {
                             ownership: None,
                             optional: false,
                             is_rest: false,
+                            property: None,
                         },
                         Param {
                             pattern: make_node(Pattern::Ident {
@@ -168,6 +201,7 @@ mod tests {
                             ownership: None,
                             optional: false,
                             is_rest: false,
+                            property: None,
                         },
                     ],
                     return_type: Some(Box::new(make_node(zaco_ast::Type::Primitive(
@@ -261,6 +295,49 @@ mod tests {
         assert!(result.is_ok() || result.is_err());
     }
 
+    #[test]
+    fn test_nullish_coalesce_drops_null_from_left() {
+        // let maybe: string | null = null; let s: string = maybe ?? <fallback>;
+        let declare = |name: &str, annotation: zaco_ast::Type, init: Expr| {
+            make_node(ModuleItem::Stmt(make_node(Stmt::VarDecl(VarDecl {
+                kind: VarDeclKind::Let,
+                declarations: vec![VarDeclarator {
+                    pattern: make_node(Pattern::Ident {
+                        name: make_node(Ident::new(name)),
+                        type_annotation: Some(Box::new(make_node(annotation))),
+                        ownership: None,
+                    }),
+                    init: Some(make_node(init)),
+                }],
+            }))))
+        };
+        let primitive = |ty: PrimitiveType| zaco_ast::Type::Primitive(ty);
+        let program = |fallback: Literal| Program {
+            items: vec![
+                declare(
+                    "maybe",
+                    zaco_ast::Type::Union(vec![
+                        make_node(primitive(PrimitiveType::String)),
+                        make_node(primitive(PrimitiveType::Null)),
+                    ]),
+                    Expr::Literal(Literal::Null),
+                ),
+                declare(
+                    "s",
+                    primitive(PrimitiveType::String),
+                    Expr::Binary {
+                        left: Box::new(make_node(Expr::Ident(Ident::new("maybe")))),
+                        op: BinaryOp::NullishCoalesce,
+                        right: Box::new(make_node(Expr::Literal(fallback))),
+                    },
+                ),
+            ],
+            span: dummy_span(),
+        };
+        assert!(check_program(&program(Literal::String("default".to_string()))).is_ok());
+        assert!(check_program(&program(Literal::Null)).is_err());
+    }
+
     #[test]
     fn test_satisfies_type() {
         // Satisfies expression should pass through the expression type
@@ -300,9 +377,57 @@ mod tests {
         };
 
         let result = check_program(&program);
-        // This should succeed or fail based on whether satisfies is implemented
-        // The test verifies the AST structure is correctly formed
-        assert!(result.is_ok() || result.is_err());
+        assert!(result.is_ok(), "got {:?}", result);
+    }
+
+    #[test]
+    fn test_satisfies_keeps_type_cast_result() {
+        let object_type = |fields: &[&str]| {
+            make_node(zaco_ast::Type::Object(ObjectType {
+                members: fields
+                    .iter()
+                    .map(|field| ObjectTypeMember::Property {
+                        name: PropertyName::Ident(make_node(Ident::new(*field))),
+                        ty: make_node(zaco_ast::Type::Primitive(PrimitiveType::Number)),
+                        optional: false,
+                        readonly: false,
+                    })
+                    .collect(),
+            }))
+        };
+        // let p: { x: number; y: number } = ({ x: 1 } as { x: number; y: number }) satisfies <target>;
+        let program = |target: &[&str]| Program {
+            items: vec![make_node(ModuleItem::Stmt(make_node(Stmt::VarDecl(VarDecl {
+                kind: VarDeclKind::Let,
+                declarations: vec![VarDeclarator {
+                    pattern: make_node(Pattern::Ident {
+                        name: make_node(Ident::new("p")),
+                        type_annotation: Some(Box::new(object_type(&["x", "y"]))),
+                        ownership: None,
+                    }),
+                    init: Some(make_node(Expr::Satisfies {
+                        expr: Box::new(make_node(Expr::Paren(Box::new(make_node(Expr::TypeCast {
+                            expr: Box::new(make_node(Expr::Object(vec![ObjectProperty::Property {
+                                key: PropertyName::Ident(make_node(Ident::new("x"))),
+                                value: make_node(Expr::Literal(Literal::Number(1.0))),
+                                shorthand: false,
+                            }]))),
+                            ty: Box::new(object_type(&["x", "y"])),
+                        }))))),
+                        ty: Box::new(object_type(target)),
+                    })),
+                }],
+            }))))],
+            span: dummy_span(),
+        };
+
+        // The cast type satisfies { x: number } and is what p receives
+        let result = check_program(&program(&["x"]));
+        assert!(result.is_ok(), "got {:?}", result);
+
+        // A satisfies violation is still reported after the cast
+        let errors = check_program(&program(&["z"])).unwrap_err();
+        assert!(matches!(*errors[0].kind, TypeErrorKind::TypeMismatch { .. }), "got {:?}", errors);
     }
 
     #[test]
@@ -450,6 +575,52 @@ mod tests {
         assert!(result.is_ok(), "Should successfully import and use readFileSync");
     }
 
+    #[test]
+    fn test_worker_spawn_requires_cloneable_args() {
+        // import { spawn } from "worker";
+        // spawn("f", [1, "a"]);
+        // spawn("f", [console.log]);
+        let spawn_call = |elems: Vec<Expr>| {
+            make_node(ModuleItem::Stmt(make_node(Stmt::Expr(make_node(Expr::Call {
+                callee: Box::new(make_node(Expr::Ident(Ident::new("spawn")))),
+                type_args: None,
+                args: vec![
+                    make_node(Expr::Literal(Literal::String("f".to_string()))),
+                    make_node(Expr::Array(elems.into_iter().map(|e| Some(make_node(e))).collect())),
+                ],
+            })))))
+        };
+        let program = |elems: Vec<Expr>| Program {
+            items: vec![
+                make_node(ModuleItem::Import(ImportDecl {
+                    specifiers: vec![ImportSpecifier::Named {
+                        imported: make_node(Ident::new("spawn")),
+                        local: None,
+                        type_only: false,
+                    }],
+                    source: "worker".to_string(),
+                    type_only: false,
+                })),
+                spawn_call(elems),
+            ],
+            span: dummy_span(),
+        };
+
+        let plain = program(vec![
+            Expr::Literal(Literal::Number(1.0)),
+            Expr::Literal(Literal::String("a".to_string())),
+        ]);
+        assert!(check_program(&plain).is_ok());
+
+        let with_function = program(vec![Expr::Member {
+            object: Box::new(make_node(Expr::Ident(Ident::new("console")))),
+            property: make_node(Ident::new("log")),
+            computed: false,
+        }]);
+        let errors = check_program(&with_function).unwrap_err();
+        assert!(matches!(*errors[0].kind, TypeErrorKind::NotCloneable(_)), "got {:?}", errors);
+    }
+
     #[test]
     fn test_import_unknown_symbol_from_builtin() {
         // import { unknownFunc } from "fs";
@@ -730,6 +901,8 @@ mod tests {
                 ("value".to_string(), TyType::TypeRef { name: "T".to_string(), type_args: vec![] }),
             ],
             methods: vec![],
+            accessors: vec![],
+            statics: vec![],
         };
         checker.env.define_class("Container".to_string(), container_type);
         checker.env.define_type_params("Container".to_string(), vec!["T".to_string()]);
@@ -759,6 +932,407 @@ mod tests {
         assert_eq!(result.unwrap(), TyType::Number, "Container<number>.value should be number");
     }
 
+    #[test]
+    fn test_class_accessor_types() {
+        use crate::types::Type as TyType;
+
+        // class Temp { get x(): number { ... } set x(v: <setter_ty>) { ... } }
+        let class_program = |setter_ty: PrimitiveType| Program {
+            items: vec![make_node(ModuleItem::Decl(make_node(Decl::Class(ClassDecl {
+                name: make_node(Ident::new("Temp")),
+                type_params: None,
+                extends: None,
+                implements: vec![],
+                members: vec![
+                    ClassMember::Getter {
+                        name: PropertyName::Ident(make_node(Ident::new("x"))),
+                        return_type: Some(Box::new(make_node(zaco_ast::Type::Primitive(
+                            PrimitiveType::Number,
+                        )))),
+                        body: None,
+                        access: AccessModifier::Public,
+                        is_static: false,
+                        is_abstract: false,
+                    },
+                    ClassMember::Setter {
+                        name: PropertyName::Ident(make_node(Ident::new("x"))),
+                        param: Param {
+                            pattern: make_node(Pattern::Ident {
+                                name: make_node(Ident::new("v")),
+                                type_annotation: None,
+                                ownership: None,
+                            }),
+                            type_annotation: Some(Box::new(make_node(zaco_ast::Type::Primitive(
+                                setter_ty,
+                            )))),
+                            ownership: None,
+                            optional: false,
+                            is_rest: false,
+                            property: None,
+                        },
+                        body: None,
+                        access: AccessModifier::Public,
+                        is_static: false,
+                        is_abstract: false,
+                    },
+                ],
+                is_abstract: false,
+                is_declare: false,
+                decorators: vec![],
+            }))))],
+            span: dummy_span(),
+        };
+
+        // get x(): number / set x(v: string) disagree
+        let errors = check_program(&class_program(PrimitiveType::String)).unwrap_err();
+        assert!(
+            matches!(
+                errors[0].kind.as_ref(),
+                TypeErrorKind::AccessorTypeMismatch { property, getter: TyType::Number, setter: TyType::String }
+                    if property == "x"
+            ),
+            "got {:?}",
+            errors
+        );
+
+        // A consistent pair types t.x as the getter's type
+        let mut checker = TypeChecker::new();
+        assert!(checker.check_program(&class_program(PrimitiveType::Number)).is_ok());
+        checker.env.declare("t".to_string(), VarInfo {
+            ty: TyType::TypeRef { name: "Temp".to_string(), type_args: vec![] },
+            ownership: OwnershipState::Owned,
+            is_mutable: false,
+            is_initialized: true,
+        });
+        let result = checker.check_expr(
+            &Expr::Member {
+                object: Box::new(make_node(Expr::Ident(Ident::new("t")))),
+                property: make_node(Ident::new("x")),
+                computed: false,
+            },
+            &dummy_span(),
+        );
+        assert_eq!(result, Ok(TyType::Number));
+    }
+
+    #[test]
+    fn test_class_static_members() {
+        use crate::types::Type as TyType;
+
+        let number = || Some(Box::new(make_node(zaco_ast::Type::Primitive(PrimitiveType::Number))));
+        // class MathUtil { static PI: number; static square(x: number): number; }
+        let class_decl = make_node(ModuleItem::Decl(make_node(Decl::Class(ClassDecl {
+            name: make_node(Ident::new("MathUtil")),
+            type_params: None,
+            extends: None,
+            implements: vec![],
+            members: vec![
+                ClassMember::Property {
+                    name: PropertyName::Ident(make_node(Ident::new("PI"))),
+                    type_annotation: number(),
+                    ownership: None,
+                    init: None,
+                    access: AccessModifier::Public,
+                    is_static: true,
+                    is_readonly: true,
+                    is_abstract: false,
+                    is_optional: false,
+                    is_definite: false,
+                    is_override: false,
+                    decorators: vec![],
+                },
+                ClassMember::Method {
+                    name: PropertyName::Ident(make_node(Ident::new("square"))),
+                    type_params: None,
+                    params: vec![Param {
+                        pattern: make_node(Pattern::Ident {
+                            name: make_node(Ident::new("x")),
+                            type_annotation: None,
+                            ownership: None,
+                        }),
+                        type_annotation: number(),
+                        ownership: None,
+                        optional: false,
+                        is_rest: false,
+                        property: None,
+                    }],
+                    return_type: number(),
+                    body: None,
+                    access: AccessModifier::Public,
+                    is_static: true,
+                    is_async: false,
+                    is_abstract: false,
+                    is_optional: false,
+                    is_override: false,
+                    decorators: vec![],
+                },
+            ],
+            is_abstract: false,
+            is_declare: false,
+            decorators: vec![],
+        }))));
+        let member = |name: &str| Expr::Member {
+            object: Box::new(make_node(Expr::Ident(Ident::new("MathUtil")))),
+            property: make_node(Ident::new(name)),
+            computed: false,
+        };
+        let square_call = |arg: Literal| {
+            make_node(ModuleItem::Stmt(make_node(Stmt::Expr(make_node(Expr::Call {
+                callee: Box::new(make_node(member("square"))),
+                type_args: None,
+                args: vec![make_node(Expr::Literal(arg))],
+            })))))
+        };
+
+        // MathUtil.square(2) checks; MathUtil.square("two") does not
+        let program = |arg: Literal| Program {
+            items: vec![class_decl.clone(), square_call(arg)],
+            span: dummy_span(),
+        };
+        assert!(check_program(&program(Literal::Number(2.0))).is_ok());
+        let errors = check_program(&program(Literal::String("two".to_string()))).unwrap_err();
+        assert!(
+            matches!(
+                errors[0].kind.as_ref(),
+                TypeErrorKind::TypeMismatch { expected: TyType::Number, found: TyType::Literal(_) }
+            ),
+            "got {:?}",
+            errors
+        );
+
+        // MathUtil.PI resolves to its declared type; statics are not instance members
+        let mut checker = TypeChecker::new();
+        assert!(checker
+            .check_program(&Program { items: vec![class_decl], span: dummy_span() })
+            .is_ok());
+        assert_eq!(checker.check_expr(&member("PI"), &dummy_span()), Ok(TyType::Number));
+        checker.env.declare("m".to_string(), VarInfo {
+            ty: TyType::TypeRef { name: "MathUtil".to_string(), type_args: vec![] },
+            ownership: OwnershipState::Owned,
+            is_mutable: false,
+            is_initialized: true,
+        });
+        let instance_pi = Expr::Member {
+            object: Box::new(make_node(Expr::Ident(Ident::new("m")))),
+            property: make_node(Ident::new("PI")),
+            computed: false,
+        };
+        assert!(checker.check_expr(&instance_pi, &dummy_span()).is_err());
+    }
+
+    #[test]
+    fn test_private_constructor_only_callable_inside_class() {
+        let new_singleton = || make_node(Expr::New {
+            callee: Box::new(make_node(Expr::Ident(Ident::new("Singleton")))),
+            type_args: None,
+            args: vec![],
+        });
+        // class Singleton { <access> constructor() {} static create(): Singleton { return new Singleton(); } }
+        let class_decl = |access| make_node(ModuleItem::Decl(make_node(Decl::Class(ClassDecl {
+            name: make_node(Ident::new("Singleton")),
+            type_params: None,
+            extends: None,
+            implements: vec![],
+            members: vec![
+                ClassMember::Constructor {
+                    params: vec![],
+                    body: Some(make_node(BlockStmt { stmts: vec![] })),
+                    access,
+                },
+                ClassMember::Method {
+                    name: PropertyName::Ident(make_node(Ident::new("create"))),
+                    type_params: None,
+                    params: vec![],
+                    return_type: Some(Box::new(make_node(zaco_ast::Type::TypeRef {
+                        name: make_node(Ident::new("Singleton")),
+                        type_args: None,
+                    }))),
+                    body: Some(make_node(BlockStmt {
+                        stmts: vec![make_node(Stmt::Return(Some(new_singleton())))],
+                    })),
+                    access: AccessModifier::Public,
+                    is_static: true,
+                    is_async: false,
+                    is_abstract: false,
+                    is_optional: false,
+                    is_override: false,
+                    decorators: vec![],
+                },
+            ],
+            is_abstract: false,
+            is_declare: false,
+            decorators: vec![],
+        }))));
+        let outside_new = make_node(ModuleItem::Stmt(make_node(Stmt::Expr(new_singleton()))));
+
+        // The static factory may construct it
+        let program = Program { items: vec![class_decl(AccessModifier::Private)], span: dummy_span() };
+        assert!(check_program(&program).is_ok());
+
+        for access in [AccessModifier::Private, AccessModifier::Protected] {
+            let program = Program { items: vec![class_decl(access), outside_new.clone()], span: dummy_span() };
+            let errors = check_program(&program).unwrap_err();
+            assert_eq!(
+                *errors[0].kind,
+                TypeErrorKind::PrivateConstructor { class: "Singleton".to_string(), access }
+            );
+        }
+
+        let program = Program { items: vec![class_decl(AccessModifier::Public), outside_new], span: dummy_span() };
+        assert!(check_program(&program).is_ok());
+    }
+
+    #[test]
+    fn test_private_names_only_reachable_in_declaring_class() {
+        use crate::types::Type as TyType;
+
+        // class Vault { #pin!: number; }  class Sub extends Vault {}
+        let class_decl = |name: &str, extends: Option<&str>, members: Vec<ClassMember>| {
+            make_node(ModuleItem::Decl(make_node(Decl::Class(ClassDecl {
+                name: make_node(Ident::new(name)),
+                type_params: None,
+                extends: extends.map(|parent| ClassExtends {
+                    base: Box::new(make_node(Expr::Ident(Ident::new(parent)))),
+                    type_args: None,
+                }),
+                implements: vec![],
+                members,
+                is_abstract: false,
+                is_declare: false,
+                decorators: vec![],
+            }))))
+        };
+        let pin = ClassMember::Property {
+            name: PropertyName::Ident(make_node(Ident::new("#pin"))),
+            type_annotation: Some(Box::new(make_node(zaco_ast::Type::Primitive(PrimitiveType::Number)))),
+            ownership: None,
+            init: None,
+            access: AccessModifier::Public,
+            is_static: false,
+            is_readonly: false,
+            is_abstract: false,
+            is_optional: false,
+            is_definite: true,
+            is_override: false,
+            decorators: vec![],
+        };
+        let program = Program {
+            items: vec![class_decl("Vault", None, vec![pin]), class_decl("Sub", Some("Vault"), vec![])],
+            span: dummy_span(),
+        };
+        let mut checker = TypeChecker::new();
+        assert!(checker.check_program(&program).is_ok());
+        for (var, class) in [("v", "Vault"), ("s", "Sub")] {
+            checker.env.declare(var.to_string(), VarInfo {
+                ty: TyType::TypeRef { name: class.to_string(), type_args: vec![] },
+                ownership: OwnershipState::Owned,
+                is_mutable: false,
+                is_initialized: true,
+            });
+        }
+        let pin_of = |var: &str| Expr::Member {
+            object: Box::new(make_node(Expr::Ident(Ident::new(var)))),
+            property: make_node(Ident::new("#pin")),
+            computed: false,
+        };
+        let brand_check = Expr::Binary {
+            left: Box::new(make_node(Expr::Ident(Ident::new("#pin")))),
+            op: BinaryOp::In,
+            right: Box::new(make_node(Expr::Ident(Ident::new("v")))),
+        };
+
+        // Outside any class body
+        assert_eq!(
+            *checker.check_expr(&pin_of("v"), &dummy_span()).unwrap_err().kind,
+            TypeErrorKind::PrivateMemberAccess { class: "Vault".to_string(), property: "#pin".to_string() }
+        );
+        assert!(checker.check_expr(&brand_check, &dummy_span()).is_err());
+
+        // Inside Vault
+        checker.current_class = Some("Vault".to_string());
+        assert_eq!(checker.check_expr(&pin_of("v"), &dummy_span()), Ok(TyType::Number));
+        assert_eq!(checker.check_expr(&brand_check, &dummy_span()), Ok(TyType::Boolean));
+
+        // Sub does not inherit Vault's private field
+        checker.current_class = Some("Sub".to_string());
+        assert!(checker.check_expr(&pin_of("s"), &dummy_span()).is_err());
+        assert!(checker.check_expr(&pin_of("v"), &dummy_span()).is_err());
+        assert!(checker.check_expr(&brand_check, &dummy_span()).is_err());
+    }
+
+    #[test]
+    fn test_super_method_call_uses_parent_signature() {
+        use crate::types::Type as TyType;
+
+        let string_ty = || Some(Box::new(make_node(zaco_ast::Type::Primitive(PrimitiveType::String))));
+        let method = |name: &str, return_type, body| ClassMember::Method {
+            name: PropertyName::Ident(make_node(Ident::new(name))),
+            type_params: None,
+            params: vec![],
+            return_type,
+            body,
+            access: AccessModifier::Public,
+            is_static: false,
+            is_async: false,
+            is_abstract: false,
+            is_optional: false,
+            is_override: false,
+            decorators: vec![],
+        };
+        let class = |name: &str, extends: Option<&str>, members| {
+            make_node(ModuleItem::Decl(make_node(Decl::Class(ClassDecl {
+                name: make_node(Ident::new(name)),
+                type_params: None,
+                extends: extends.map(|base| zaco_ast::ClassExtends {
+                    base: Box::new(make_node(Expr::Ident(Ident::new(base)))),
+                    type_args: None,
+                }),
+                implements: vec![],
+                members,
+                is_abstract: false,
+                is_declare: false,
+                decorators: vec![],
+            }))))
+        };
+        // class Base { greet(): string }
+        let base = class("Base", None, vec![method("greet", string_ty(), None)]);
+        // class Child extends Base { hello(): <ret> { return super.greet(<args>); } }
+        let child = |return_type, args: Vec<Node<Expr>>| {
+            let super_call = Expr::Call {
+                callee: Box::new(make_node(Expr::Member {
+                    object: Box::new(make_node(Expr::Super)),
+                    property: make_node(Ident::new("greet")),
+                    computed: false,
+                })),
+                type_args: None,
+                args,
+            };
+            let body = BlockStmt { stmts: vec![make_node(Stmt::Return(Some(make_node(super_call))))] };
+            class("Child", Some("Base"), vec![method("hello", return_type, Some(make_node(body)))])
+        };
+        let program = |child| Program { items: vec![base.clone(), child], span: dummy_span() };
+
+        assert!(check_program(&program(child(string_ty(), vec![]))).is_ok());
+
+        // super.greet() is a string, so it can't be returned as a number
+        let number_ty = Some(Box::new(make_node(zaco_ast::Type::Primitive(PrimitiveType::Number))));
+        let errors = check_program(&program(child(number_ty, vec![]))).unwrap_err();
+        assert!(
+            matches!(errors[0].kind.as_ref(), TypeErrorKind::TypeMismatch { expected: TyType::Number, found: TyType::String }),
+            "got {:?}",
+            errors
+        );
+
+        // greet takes no arguments
+        let extra_arg = vec![make_node(Expr::Literal(Literal::Number(1.0)))];
+        let errors = check_program(&program(child(string_ty(), extra_arg))).unwrap_err();
+        assert!(
+            matches!(errors[0].kind.as_ref(), TypeErrorKind::ArityMismatch { expected: 0, found: 1 }),
+            "got {:?}",
+            errors
+        );
+    }
+
     #[test]
     fn test_generic_interface_member_substitution() {
         use crate::types::Type as TyType;
@@ -799,4 +1373,652 @@ mod tests {
         assert!(result.is_ok(), "Should resolve generic interface member access");
         assert_eq!(result.unwrap(), TyType::String, "Wrapper<string>.data should be string");
     }
+
+    /// `function <name>(ref a: string): ref string { <body> }`
+    fn ref_returning_fn(name: &str, body: Vec<Node<Stmt>>) -> Node<ModuleItem> {
+        let ref_string = || {
+            Box::new(make_node(zaco_ast::Type::WithOwnership {
+                base: Box::new(make_node(zaco_ast::Type::Primitive(PrimitiveType::String))),
+                ownership: Ownership { kind: OwnershipKind::Ref, span: dummy_span() },
+            }))
+        };
+        make_node(ModuleItem::Decl(make_node(Decl::Function(FunctionDecl {
+            name: make_node(Ident::new(name)),
+            type_params: None,
+            params: vec![Param {
+                pattern: make_node(Pattern::Ident {
+                    name: make_node(Ident::new("a")),
+                    type_annotation: None,
+                    ownership: None,
+                }),
+                type_annotation: Some(ref_string()),
+                ownership: None,
+                optional: false,
+                is_rest: false,
+                property: None,
+            }],
+            return_type: Some(ref_string()),
+            body: Some(make_node(BlockStmt { stmts: body })),
+            is_async: false,
+            is_generator: false,
+            is_declare: false,
+        }))))
+    }
+
+    #[test]
+    fn test_return_reference_to_ref_param() {
+        let program = Program {
+            items: vec![ref_returning_fn(
+                "pick",
+                vec![make_node(Stmt::Return(Some(make_node(Expr::Ident(Ident::new("a"))))))],
+            )],
+            span: dummy_span(),
+        };
+
+        let result = check_program(&program);
+        assert!(result.is_ok(), "Returning a borrowed param by reference is fine");
+    }
+
+    #[test]
+    fn test_return_reference_to_local_rejected() {
+        let program = Program {
+            items: vec![ref_returning_fn(
+                "dangle",
+                vec![
+                    make_node(Stmt::VarDecl(VarDecl {
+                        kind: VarDeclKind::Let,
+                        declarations: vec![VarDeclarator {
+                            pattern: make_node(Pattern::Ident {
+                                name: make_node(Ident::new("local")),
+                                type_annotation: None,
+                                ownership: None,
+                            }),
+                            init: Some(make_node(Expr::Literal(Literal::String("temp".to_string())))),
+                        }],
+                    })),
+                    make_node(Stmt::Return(Some(make_node(Expr::Ident(Ident::new("local")))))),
+                ],
+            )],
+            span: dummy_span(),
+        };
+
+        let errors = check_program(&program).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(*errors[0].kind, TypeErrorKind::ReturnLocalReference("local".to_string()));
+    }
+
+    #[test]
+    fn test_returned_reference_keeps_argument_borrowed() {
+        use crate::types::Type as TyType;
+
+        // first(ref a: string): ref string
+        let mut checker = TypeChecker::new();
+        let ref_string = TyType::Ref { inner: Box::new(TyType::String), mutable: false };
+        checker.env.declare("first".to_string(), VarInfo {
+            ty: TyType::Function {
+                params: vec![ref_string.clone()],
+                return_type: Box::new(ref_string),
+            },
+            ownership: OwnershipState::Owned,
+            is_mutable: false,
+            is_initialized: true,
+        });
+        for name in ["s", "u"] {
+            checker.env.declare(name.to_string(), VarInfo {
+                ty: TyType::String,
+                ownership: OwnershipState::Owned,
+                is_mutable: true,
+                is_initialized: true,
+            });
+        }
+
+        // let r = first(s);
+        let decl = Stmt::VarDecl(VarDecl {
+            kind: VarDeclKind::Let,
+            declarations: vec![VarDeclarator {
+                pattern: make_node(Pattern::Ident {
+                    name: make_node(Ident::new("r")),
+                    type_annotation: None,
+                    ownership: None,
+                }),
+                init: Some(make_node(Expr::Call {
+                    callee: Box::new(make_node(Expr::Ident(Ident::new("first")))),
+                    type_args: None,
+                    args: vec![make_node(Expr::Ident(Ident::new("s")))],
+                })),
+            }],
+        });
+        checker.check_stmt(&decl, &dummy_span()).unwrap();
+        assert_eq!(checker.env.lookup("r").unwrap().ty, TyType::String);
+        assert_eq!(checker.env.lookup("r").unwrap().ownership, OwnershipState::Borrowed);
+
+        // u = s;  — s is still borrowed by r
+        let result = checker.check_expr(
+            &Expr::Assignment {
+                target: Box::new(make_node(Expr::Ident(Ident::new("u")))),
+                op: AssignmentOp::Assign,
+                value: Box::new(make_node(Expr::Ident(Ident::new("s")))),
+            },
+            &dummy_span(),
+        );
+        assert_eq!(
+            *result.unwrap_err().kind,
+            TypeErrorKind::MoveWhileBorrowed("s".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unannotated_function_returns_closure_type() {
+        use crate::types::{LiteralType as TyLiteral, Type as TyType};
+
+        // function make() { return () => 5; }
+        let make = make_node(ModuleItem::Decl(make_node(Decl::Function(FunctionDecl {
+            name: make_node(Ident::new("make")),
+            type_params: None,
+            params: vec![],
+            return_type: None,
+            body: Some(make_node(BlockStmt {
+                stmts: vec![make_node(Stmt::Return(Some(make_node(Expr::Arrow {
+                    type_params: None,
+                    params: vec![],
+                    return_type: None,
+                    body: ArrowBody::Expr(Box::new(make_node(Expr::Literal(Literal::Number(5.0))))),
+                    is_async: false,
+                }))))],
+            })),
+            is_async: false,
+            is_generator: false,
+            is_declare: false,
+        }))));
+
+        let mut checker = TypeChecker::new();
+        if let ModuleItem::Decl(decl) = &make.value {
+            checker.check_decl(&decl.value, &decl.span).unwrap();
+        }
+        assert_eq!(
+            checker.env.lookup("make").unwrap().ty,
+            TyType::Function {
+                params: vec![],
+                return_type: Box::new(TyType::Function {
+                    params: vec![],
+                    return_type: Box::new(TyType::Literal(TyLiteral::Number(5.0))),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_function_reading_arguments_is_variadic() {
+        use crate::types::Type as TyType;
+
+        // function count(a: number) { return arguments.length; }
+        let count = Decl::Function(FunctionDecl {
+            name: make_node(Ident::new("count")),
+            type_params: None,
+            params: vec![Param {
+                pattern: make_node(Pattern::Ident {
+                    name: make_node(Ident::new("a")),
+                    type_annotation: None,
+                    ownership: None,
+                }),
+                type_annotation: Some(Box::new(make_node(zaco_ast::Type::Primitive(PrimitiveType::Number)))),
+                ownership: None,
+                optional: false,
+                is_rest: false,
+                property: None,
+            }],
+            return_type: None,
+            body: Some(make_node(BlockStmt {
+                stmts: vec![make_node(Stmt::Return(Some(make_node(Expr::Member {
+                    object: Box::new(make_node(Expr::Ident(Ident::new("arguments")))),
+                    property: make_node(Ident::new("length")),
+                    computed: false,
+                }))))],
+            })),
+            is_async: false,
+            is_generator: false,
+            is_declare: false,
+        });
+
+        let mut checker = TypeChecker::new();
+        checker.check_decl(&count, &Span::new(0, 0, 0)).unwrap();
+        assert_eq!(
+            checker.env.lookup("count").unwrap().ty,
+            TyType::Function {
+                params: vec![TyType::Any],
+                return_type: Box::new(TyType::Number),
+            }
+        );
+
+        // Outside a function declaration `arguments` is undefined
+        let top_level = Expr::Ident(Ident::new("arguments"));
+        assert!(checker.check_expr(&top_level, &Span::new(0, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn test_structured_clone_returns_argument_type() {
+        use crate::types::Type as TyType;
+
+        let mut checker = TypeChecker::new();
+        let point = TyType::Object {
+            properties: vec![
+                ("x".to_string(), TyType::Number, false),
+                ("label".to_string(), TyType::String, true),
+            ],
+        };
+        checker.env.declare("p".to_string(), VarInfo {
+            ty: point.clone(),
+            ownership: OwnershipState::Owned,
+            is_mutable: false,
+            is_initialized: true,
+        });
+
+        // structuredClone(p) has the type of p
+        let call = Expr::Call {
+            callee: Box::new(make_node(Expr::Ident(Ident::new("structuredClone")))),
+            type_args: None,
+            args: vec![make_node(Expr::Ident(Ident::new("p")))],
+        };
+        assert_eq!(checker.check_expr(&call, &dummy_span()).unwrap(), point);
+
+        // Literal arguments are widened: structuredClone("a") is a string
+        let call = Expr::Call {
+            callee: Box::new(make_node(Expr::Ident(Ident::new("structuredClone")))),
+            type_args: None,
+            args: vec![make_node(Expr::Literal(Literal::String("a".to_string())))],
+        };
+        assert_eq!(checker.check_expr(&call, &dummy_span()).unwrap(), TyType::String);
+    }
+
+    #[test]
+    fn test_object_types_are_structural() {
+        use crate::helpers::TypeHelpers;
+        use crate::types::{LiteralType as TyLiteral, Type as TyType};
+
+        let target = TyType::Interface {
+            name: "Point".to_string(),
+            properties: vec![
+                ("x".to_string(), TyType::Number, false),
+                ("label".to_string(), TyType::String, true),
+            ],
+        };
+        let literal = TyType::Object {
+            properties: vec![
+                ("x".to_string(), TyType::Literal(TyLiteral::Number(1.0)), false),
+                ("extra".to_string(), TyType::Boolean, false),
+            ],
+        };
+        assert!(TypeHelpers::is_assignable(&literal, &target));
+
+        let missing_x = TyType::Object { properties: vec![("label".to_string(), TyType::String, false)] };
+        assert!(!TypeHelpers::is_assignable(&missing_x, &target));
+
+        let wrong_label = TyType::Object {
+            properties: vec![
+                ("x".to_string(), TyType::Number, false),
+                ("label".to_string(), TyType::Number, false),
+            ],
+        };
+        assert!(!TypeHelpers::is_assignable(&wrong_label, &target));
+    }
+
+    #[test]
+    fn test_global_this_reaches_globals() {
+        // let x: number = globalThis.Math.floor(3.7);
+        let member = |object: Expr, name: &str| Expr::Member {
+            object: Box::new(make_node(object)),
+            property: make_node(Ident::new(name)),
+            computed: false,
+        };
+        let floor = member(member(Expr::Ident(Ident::new("globalThis")), "Math"), "floor");
+        let program = Program {
+            items: vec![make_node(ModuleItem::Stmt(make_node(Stmt::VarDecl(VarDecl {
+                kind: VarDeclKind::Let,
+                declarations: vec![VarDeclarator {
+                    pattern: make_node(Pattern::Ident {
+                        name: make_node(Ident::new("x")),
+                        type_annotation: Some(Box::new(make_node(zaco_ast::Type::Primitive(
+                            PrimitiveType::Number,
+                        )))),
+                        ownership: None,
+                    }),
+                    init: Some(make_node(Expr::Call {
+                        callee: Box::new(make_node(floor)),
+                        type_args: None,
+                        args: vec![make_node(Expr::Literal(Literal::Number(3.7)))],
+                    })),
+                }],
+            }))))],
+            span: dummy_span(),
+        };
+        assert!(check_program(&program).is_ok());
+
+        // globalThis.nope is not a global
+        let program = Program {
+            items: vec![make_node(ModuleItem::Stmt(make_node(Stmt::Expr(make_node(member(
+                Expr::Ident(Ident::new("globalThis")),
+                "nope",
+            ))))))],
+            span: dummy_span(),
+        };
+        assert!(check_program(&program).is_err());
+    }
+
+    #[test]
+    fn test_well_known_symbols_are_symbol_typed() {
+        // let key: symbol = Symbol.iterator;  /  let key: number = Symbol.iterator;
+        let declare = |annotation: zaco_ast::Type| Program {
+            items: vec![make_node(ModuleItem::Stmt(make_node(Stmt::VarDecl(VarDecl {
+                kind: VarDeclKind::Let,
+                declarations: vec![VarDeclarator {
+                    pattern: make_node(Pattern::Ident {
+                        name: make_node(Ident::new("key")),
+                        type_annotation: Some(Box::new(make_node(annotation))),
+                        ownership: None,
+                    }),
+                    init: Some(make_node(Expr::Member {
+                        object: Box::new(make_node(Expr::Ident(Ident::new("Symbol")))),
+                        property: make_node(Ident::new("iterator")),
+                        computed: false,
+                    })),
+                }],
+            }))))],
+            span: dummy_span(),
+        };
+        let symbol = zaco_ast::Type::TypeRef { name: make_node(Ident::new("symbol")), type_args: None };
+        assert!(check_program(&declare(symbol)).is_ok());
+        assert!(check_program(&declare(zaco_ast::Type::Primitive(PrimitiveType::Number))).is_err());
+    }
+
+    #[test]
+    fn test_array_from_types_its_source_elements() {
+        // let chars: <annotation> = Array.from(<source>);
+        let declare = |annotation: zaco_ast::Type, source: Literal| Program {
+            items: vec![make_node(ModuleItem::Stmt(make_node(Stmt::VarDecl(VarDecl {
+                kind: VarDeclKind::Let,
+                declarations: vec![VarDeclarator {
+                    pattern: make_node(Pattern::Ident {
+                        name: make_node(Ident::new("chars")),
+                        type_annotation: Some(Box::new(make_node(annotation))),
+                        ownership: None,
+                    }),
+                    init: Some(make_node(Expr::Call {
+                        callee: Box::new(make_node(Expr::Member {
+                            object: Box::new(make_node(Expr::Ident(Ident::new("Array")))),
+                            property: make_node(Ident::new("from")),
+                            computed: false,
+                        })),
+                        type_args: None,
+                        args: vec![make_node(Expr::Literal(source))],
+                    })),
+                }],
+            }))))],
+            span: dummy_span(),
+        };
+        let array_of = |elem: PrimitiveType| {
+            zaco_ast::Type::Array(Box::new(make_node(zaco_ast::Type::Primitive(elem))))
+        };
+        let text = || Literal::String("ab".to_string());
+        assert!(check_program(&declare(array_of(PrimitiveType::String), text())).is_ok());
+        assert!(check_program(&declare(array_of(PrimitiveType::Number), text())).is_err());
+        // A number is neither iterable nor array-like
+        assert!(check_program(&declare(array_of(PrimitiveType::Number), Literal::Number(3.0))).is_err());
+    }
+
+    #[test]
+    fn test_destructured_tuple_elements_keep_their_types() {
+        // let pair: [boolean, string] = [true, "a"]; let [ok, msg] = pair; let n: <annotation> = msg;
+        let primitive = |ty: PrimitiveType| make_node(zaco_ast::Type::Primitive(ty));
+        let ident_pattern = |name: &str, annotation: Option<zaco_ast::Type>| {
+            make_node(Pattern::Ident {
+                name: make_node(Ident::new(name)),
+                type_annotation: annotation.map(|ty| Box::new(make_node(ty))),
+                ownership: None,
+            })
+        };
+        let declare = |pattern: Node<Pattern>, init: Expr| {
+            make_node(ModuleItem::Stmt(make_node(Stmt::VarDecl(VarDecl {
+                kind: VarDeclKind::Let,
+                declarations: vec![VarDeclarator { pattern, init: Some(make_node(init)) }],
+            }))))
+        };
+        let program = |annotation: PrimitiveType| Program {
+            items: vec![
+                declare(
+                    ident_pattern(
+                        "pair",
+                        Some(zaco_ast::Type::Tuple(vec![
+                            primitive(PrimitiveType::Boolean),
+                            primitive(PrimitiveType::String),
+                        ])),
+                    ),
+                    Expr::Array(vec![
+                        Some(make_node(Expr::Literal(Literal::Boolean(true)))),
+                        Some(make_node(Expr::Literal(Literal::String("a".to_string())))),
+                    ]),
+                ),
+                declare(
+                    make_node(Pattern::Array {
+                        elements: vec![Some(ident_pattern("ok", None)), Some(ident_pattern("msg", None))],
+                        rest: None,
+                    }),
+                    Expr::Ident(Ident::new("pair")),
+                ),
+                declare(
+                    ident_pattern("n", Some(zaco_ast::Type::Primitive(annotation))),
+                    Expr::Ident(Ident::new("msg")),
+                ),
+            ],
+            span: dummy_span(),
+        };
+        assert!(check_program(&program(PrimitiveType::String)).is_ok());
+        assert!(check_program(&program(PrimitiveType::Number)).is_err());
+    }
+
+    #[test]
+    fn test_redeclaring_a_binding_in_the_same_scope_errors() {
+        // <kind> x = 1; <kind> x = 2;  and  let x = 1; { let x = 2; }
+        let declare = |kind: VarDeclKind| {
+            make_node(Stmt::VarDecl(VarDecl {
+                kind,
+                declarations: vec![VarDeclarator {
+                    pattern: make_node(Pattern::Ident {
+                        name: make_node(Ident::new("x")),
+                        type_annotation: None,
+                        ownership: None,
+                    }),
+                    init: Some(make_node(Expr::Literal(Literal::Number(1.0)))),
+                }],
+            }))
+        };
+        let program = |stmts: Vec<Node<Stmt>>| Program {
+            items: stmts.into_iter().map(|stmt| make_node(ModuleItem::Stmt(stmt))).collect(),
+            span: dummy_span(),
+        };
+
+        let errors = check_program(&program(vec![declare(VarDeclKind::Const), declare(VarDeclKind::Const)]))
+            .expect_err("const declared twice");
+        assert_eq!(*errors[0].kind, TypeErrorKind::DuplicateDeclaration("x".to_string()));
+        assert_eq!(errors[0].related.as_deref().map(|(_, label)| label.as_str()), Some("first declared here"));
+
+        assert!(check_program(&program(vec![declare(VarDeclKind::Let), declare(VarDeclKind::Var)])).is_err());
+        assert!(check_program(&program(vec![declare(VarDeclKind::Var), declare(VarDeclKind::Var)])).is_ok());
+        let nested = make_node(Stmt::Block(BlockStmt { stmts: vec![declare(VarDeclKind::Let)] }));
+        assert!(check_program(&program(vec![declare(VarDeclKind::Let), nested])).is_ok());
+    }
+
+    #[test]
+    fn test_this_return_type_is_the_receiver_class() {
+        let this_ty = || {
+            Some(Box::new(make_node(zaco_ast::Type::TypeRef { name: make_node(Ident::new("this")), type_args: None })))
+        };
+        let method = |name: &str| ClassMember::Method {
+            name: PropertyName::Ident(make_node(Ident::new(name))),
+            type_params: None,
+            params: vec![],
+            return_type: this_ty(),
+            body: Some(make_node(BlockStmt { stmts: vec![make_node(Stmt::Return(Some(make_node(Expr::This))))] })),
+            access: AccessModifier::Public,
+            is_static: false,
+            is_async: false,
+            is_abstract: false,
+            is_optional: false,
+            is_override: false,
+            decorators: vec![],
+        };
+        let class = |name: &str, extends: Option<&str>, method_name: &str| {
+            make_node(ModuleItem::Decl(make_node(Decl::Class(ClassDecl {
+                name: make_node(Ident::new(name)),
+                type_params: None,
+                extends: extends.map(|base| zaco_ast::ClassExtends {
+                    base: Box::new(make_node(Expr::Ident(Ident::new(base)))),
+                    type_args: None,
+                }),
+                implements: vec![],
+                members: vec![method(method_name)],
+                is_abstract: false,
+                is_declare: false,
+                decorators: vec![],
+            }))))
+        };
+        let call = |object: Expr, method: &str| Expr::Call {
+            callee: Box::new(make_node(Expr::Member {
+                object: Box::new(make_node(object)),
+                property: make_node(Ident::new(method)),
+                computed: false,
+            })),
+            type_args: None,
+            args: vec![],
+        };
+        let new_instance = |class: &str| Expr::New {
+            callee: Box::new(make_node(Expr::Ident(Ident::new(class)))),
+            type_args: None,
+            args: vec![],
+        };
+        // class Base { set(): this }  class Child extends Base { extra(): this }
+        // let x: Child = new <receiver>().set().extra();
+        let program = |receiver: &str| Program {
+            items: vec![
+                class("Base", None, "set"),
+                class("Child", Some("Base"), "extra"),
+                make_node(ModuleItem::Stmt(make_node(Stmt::VarDecl(VarDecl {
+                    kind: VarDeclKind::Let,
+                    declarations: vec![VarDeclarator {
+                        pattern: make_node(Pattern::Ident {
+                            name: make_node(Ident::new("x")),
+                            type_annotation: Some(Box::new(make_node(zaco_ast::Type::TypeRef {
+                                name: make_node(Ident::new("Child")),
+                                type_args: None,
+                            }))),
+                            ownership: None,
+                        }),
+                        init: Some(make_node(call(call(new_instance(receiver), "set"), "extra"))),
+                    }],
+                })))),
+            ],
+            span: dummy_span(),
+        };
+
+        // The inherited set() returns Child when called on a Child
+        assert!(check_program(&program("Child")).is_ok());
+
+        // ...and Base when called on a Base, which has no extra()
+        let errors = check_program(&program("Base")).unwrap_err();
+        assert!(
+            matches!(errors[0].kind.as_ref(), TypeErrorKind::PropertyNotFound { property, .. } if property == "extra"),
+            "got {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_in_operator_needs_an_object_on_the_right() {
+        use crate::types::Type as TyType;
+
+        let mut checker = TypeChecker::new();
+        let config = TyType::Object { properties: vec![("port".to_string(), TyType::Number, false)] };
+        for (var, ty) in [("config", config), ("name", TyType::String), ("flag", TyType::Boolean)] {
+            checker.env.declare(var.to_string(), VarInfo {
+                ty,
+                ownership: OwnershipState::Owned,
+                is_mutable: false,
+                is_initialized: true,
+            });
+        }
+        let key_in = |key: Expr, object: &str| Expr::Binary {
+            left: Box::new(make_node(key)),
+            op: BinaryOp::In,
+            right: Box::new(make_node(Expr::Ident(Ident::new(object)))),
+        };
+        let port = || Expr::Literal(Literal::String("port".to_string()));
+
+        assert_eq!(checker.check_expr(&key_in(port(), "config"), &dummy_span()), Ok(TyType::Boolean));
+        assert_eq!(
+            checker.check_expr(&key_in(Expr::Literal(Literal::Number(0.0)), "config"), &dummy_span()),
+            Ok(TyType::Boolean)
+        );
+        assert!(checker.check_expr(&key_in(port(), "name"), &dummy_span()).is_err());
+        assert!(checker.check_expr(&key_in(Expr::Ident(Ident::new("flag")), "config"), &dummy_span()).is_err());
+    }
+
+    #[test]
+    fn test_delete_needs_a_property() {
+        use crate::types::Type as TyType;
+
+        let mut checker = TypeChecker::new();
+        let config = TyType::Object { properties: vec![("port".to_string(), TyType::Number, true)] };
+        checker.env.declare("config".to_string(), VarInfo {
+            ty: config,
+            ownership: OwnershipState::Owned,
+            is_mutable: false,
+            is_initialized: true,
+        });
+        let delete = |operand: Expr| Expr::Unary { op: UnaryOp::Delete, expr: Box::new(make_node(operand)) };
+        let port = Expr::Member {
+            object: Box::new(make_node(Expr::Ident(Ident::new("config")))),
+            property: make_node(Ident::new("port")),
+            computed: false,
+        };
+
+        assert_eq!(checker.check_expr(&delete(port), &dummy_span()), Ok(TyType::Boolean));
+        assert!(checker.check_expr(&delete(Expr::Ident(Ident::new("config"))), &dummy_span()).is_err());
+    }
+
+    #[test]
+    fn test_void_result_is_not_a_value() {
+        use crate::types::Type as TyType;
+
+        let mut checker = TypeChecker::new();
+        checker.env.declare("log".to_string(), VarInfo {
+            ty: TyType::Function { params: vec![], return_type: Box::new(TyType::Void) },
+            ownership: OwnershipState::Owned,
+            is_mutable: false,
+            is_initialized: true,
+        });
+        let call = || {
+            Box::new(make_node(Expr::Call {
+                callee: Box::new(make_node(Expr::Ident(Ident::new("log")))),
+                type_args: None,
+                args: vec![],
+            }))
+        };
+        let one = || Box::new(make_node(Expr::Literal(Literal::Number(1.0))));
+
+        let sum = Expr::Binary { left: call(), op: BinaryOp::Add, right: one() };
+        assert!(checker.check_expr(&sum, &dummy_span()).is_err());
+        let negated = Expr::Unary { op: UnaryOp::Not, expr: call() };
+        assert!(checker.check_expr(&negated, &dummy_span()).is_err());
+        let tested = Expr::Ternary { condition: call(), then_expr: one(), else_expr: one() };
+        assert!(checker.check_expr(&tested, &dummy_span()).is_err());
+
+        // Comparing a void result, or yielding one, is fine
+        let compared = Expr::Binary {
+            left: call(),
+            op: BinaryOp::StrictEq,
+            right: Box::new(make_node(Expr::Literal(Literal::Undefined))),
+        };
+        assert_eq!(checker.check_expr(&compared, &dummy_span()), Ok(TyType::Boolean));
+        let picked = Expr::Ternary { condition: one(), then_expr: call(), else_expr: call() };
+        assert!(checker.check_expr(&picked, &dummy_span()).is_ok());
+    }
 }
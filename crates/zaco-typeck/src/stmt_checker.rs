@@ -1,6 +1,6 @@
 //! Statement checking methods
 
-use zaco_ast::{BlockStmt, ForInit, Pattern, Span, Stmt, VarDecl, VarDeclKind};
+use zaco_ast::{BlockStmt, Expr, ForInLeft, ForInit, Node, Pattern, Span, Stmt, VarDecl, VarDeclKind};
 use crate::checker::TypeChecker;
 use crate::error::{TypeError, TypeErrorKind};
 use crate::types::Type;
@@ -17,7 +17,13 @@ impl TypeChecker {
             Stmt::VarDecl(var_decl) => self.check_var_decl(var_decl, span),
             Stmt::Return(expr) => {
                 if let Some(expr) = expr {
+                    self.tuple_context = matches!(expr.value, Expr::Array(_))
+                        && self.current_return_type.as_ref().is_some_and(|ret| match ret {
+                            Type::Promise(inner) => self.is_tuple_type(inner),
+                            ret => self.is_tuple_type(ret),
+                        });
                     let return_ty = self.check_expr(&expr.value, &expr.span)?;
+                    self.returned_types.push(TypeHelpers::widen_literal(return_ty.clone()));
                     // Validate return type against declared function return type
                     if let Some(ref declared_ret) = self.current_return_type {
                         // In async functions the declared return type is Promise<T>,
@@ -33,8 +39,13 @@ impl TypeChecker {
                                     expected: effective_ret.clone(),
                                     found: return_ty,
                                 },
-                                expr.span.clone(),
-                            ));
+                                expr.span,
+                            )
+                            .declared_at(self.current_return_span));
+                        }
+                        // A `ref` return must not point at something this function owns
+                        if matches!(effective_ret, Type::Ref { .. }) {
+                            self.check_reference_escape(&expr.value, &expr.span)?;
                         }
                     }
                 }
@@ -45,8 +56,10 @@ impl TypeChecker {
                 then_stmt,
                 else_stmt,
             } => {
-                let _cond_ty = self.check_expr(&condition.value, &condition.span)?;
-                // Condition should be boolean-ish
+                let cond_ty = self.check_expr(&condition.value, &condition.span)?;
+                if let Some(err) = Self::void_value(&cond_ty, condition.span) {
+                    return Err(err);
+                }
                 self.check_stmt(&then_stmt.value, &then_stmt.span)?;
                 if let Some(else_stmt) = else_stmt {
                     self.check_stmt(&else_stmt.value, &else_stmt.span)?;
@@ -73,7 +86,10 @@ impl TypeChecker {
                 }
 
                 if let Some(condition) = condition {
-                    self.check_expr(&condition.value, &condition.span)?;
+                    let cond_ty = self.check_expr(&condition.value, &condition.span)?;
+                    if let Some(err) = Self::void_value(&cond_ty, condition.span) {
+                        return Err(err);
+                    }
                 }
 
                 if let Some(update) = update {
@@ -93,25 +109,40 @@ impl TypeChecker {
                 Ok(())
             }
             Stmt::ForOf {
-                left: _,
+                left,
                 right,
                 body,
-                ..
+                is_await,
             } => {
                 self.env.push_scope();
-                self.check_expr(&right.value, &right.span)?;
+                let iterable_ty = self.check_expr(&right.value, &right.span)?;
+                let element_ty = self.iterable_element_type(&iterable_ty, *is_await).unwrap_or(Type::Unknown);
+                // `for await` receives settled values
+                let element_ty = match element_ty {
+                    Type::Promise(inner) if *is_await => *inner,
+                    ty => ty,
+                };
+                if let Some(err) = self.declare_loop_var(left, element_ty) {
+                    return Err(err);
+                }
                 self.check_stmt(&body.value, &body.span)?;
                 self.env.pop_scope();
                 Ok(())
             }
             Stmt::While { condition, body } => {
-                self.check_expr(&condition.value, &condition.span)?;
+                let cond_ty = self.check_expr(&condition.value, &condition.span)?;
+                if let Some(err) = Self::void_value(&cond_ty, condition.span) {
+                    return Err(err);
+                }
                 self.check_stmt(&body.value, &body.span)?;
                 Ok(())
             }
             Stmt::DoWhile { body, condition } => {
                 self.check_stmt(&body.value, &body.span)?;
-                self.check_expr(&condition.value, &condition.span)?;
+                let cond_ty = self.check_expr(&condition.value, &condition.span)?;
+                if let Some(err) = Self::void_value(&cond_ty, condition.span) {
+                    return Err(err);
+                }
                 Ok(())
             }
             Stmt::Block(block) => self.check_block_stmt(block, span),
@@ -179,7 +210,89 @@ impl TypeChecker {
         Ok(())
     }
 
-    pub(crate) fn check_var_decl(&mut self, var_decl: &VarDecl, span: &Span) -> Result<(), TypeError> {
+    /// Type of the values iterating `iterable` produces (with `for...of`,
+    /// spread or `Array.from`). `None` if it is not iterable.
+    pub(crate) fn iterable_element_type(&self, iterable: &Type, is_await: bool) -> Option<Type> {
+        match TypeHelpers::resolve_type(iterable, Some(&self.env)) {
+            Type::Array(elem) => Some((**elem).clone()),
+            Type::Tuple(elems) => Some(TypeHelpers::union_type(elems.clone())),
+            ty if TypeHelpers::is_string(ty) => Some(Type::String),
+            // Generators, including async ones under `for await`
+            ty => crate::builtins::generator_value_type(ty)
+                // Other iterables are driven through `[Symbol.iterator]()`,
+                // or `[Symbol.asyncIterator]()` under `for await`
+                .or_else(|| self.iterator_value_type(ty, is_await)),
+        }
+    }
+
+    /// Element type of a class instance iterated through its
+    /// `[Symbol.iterator]()` (or, for `for await`, its
+    /// `[Symbol.asyncIterator]()`): the `value` of what the iterator's
+    /// `next()` returns or promises
+    fn iterator_value_type(&self, iterable: &Type, is_await: bool) -> Option<Type> {
+        let iterator = is_await
+            .then(|| self.method_return_type(iterable, &zaco_ast::symbol_key("asyncIterator")))
+            .flatten()
+            .or_else(|| self.method_return_type(iterable, &zaco_ast::symbol_key("iterator")))?;
+        let result = match self.method_return_type(&iterator, "next") {
+            Some(Type::Promise(result)) => *result,
+            Some(result) => result,
+            None => return crate::builtins::generator_value_type(TypeHelpers::resolve_type(&iterator, Some(&self.env))),
+        };
+        match TypeHelpers::resolve_type(&result, Some(&self.env)) {
+            Type::Object { properties } | Type::Interface { properties, .. } => {
+                properties.iter().find(|(name, _, _)| name == "value").map(|(_, ty, _)| ty.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `ty` is (or names) a tuple type
+    fn is_tuple_type(&self, ty: &Type) -> bool {
+        matches!(TypeHelpers::resolve_type(ty, Some(&self.env)), Type::Tuple(_))
+    }
+
+    /// Return type of a method declared on the class an instance type names
+    fn method_return_type(&self, instance: &Type, method: &str) -> Option<Type> {
+        let Type::Class { methods, .. } = TypeHelpers::resolve_type(instance, Some(&self.env)) else {
+            return None;
+        };
+        methods.iter().find_map(|(name, ty)| match ty {
+            Type::Function { return_type, .. } if name == method => Some((**return_type).clone()),
+            _ => None,
+        })
+    }
+
+    /// Declare a `for...of` binding (`const x`, `let x: T`) with the type of
+    /// the elements it receives
+    fn declare_loop_var(&mut self, left: &ForInLeft, element_ty: Type) -> Option<TypeError> {
+        let ForInLeft::VarDecl(var_decl) = left else {
+            return None;
+        };
+        for declarator in &var_decl.declarations {
+            if let Pattern::Ident { name, type_annotation, .. } = &declarator.pattern.value {
+                let ty = match type_annotation {
+                    Some(type_ann) => match self.convert_ast_type(&type_ann.value) {
+                        Ok(ty) => ty,
+                        Err(err) => return Some(err),
+                    },
+                    None => element_ty.clone(),
+                };
+                self.env.declare(
+                    name.value.name.clone(),
+                    VarInfo {
+                        ty,
+                        ownership: OwnershipState::Owned,
+                        is_mutable: !matches!(var_decl.kind, VarDeclKind::Const),
+                        is_initialized: true,
+                    },
+                );
+            }
+        }
+        None
+    }
+
+    pub(crate) fn check_var_decl(&mut self, var_decl: &VarDecl, _span: &Span) -> Result<(), TypeError> {
         let is_const = matches!(var_decl.kind, VarDeclKind::Const);
 
         for declarator in &var_decl.declarations {
@@ -193,19 +306,40 @@ impl TypeChecker {
 
                     // Infer or check type
                     let ty = if let Some(init) = &declarator.init {
-                        let init_ty = self.check_expr(&init.value, &init.span)?;
+                        let annotated_ty = match type_annotation {
+                            Some(type_ann) => Some(self.convert_ast_type(&type_ann.value)?),
+                            None => None,
+                        };
+                        self.tuple_context = matches!(init.value, Expr::Array(_))
+                            && annotated_ty.as_ref().is_some_and(|ty| self.is_tuple_type(ty));
+                        // A function bound here can call itself through the
+                        // binding, which its signature stands in for until
+                        // the body has been checked
+                        let signature = self.recursive_signature(&init.value);
+                        if let Some(ty) = signature.clone() {
+                            self.env.push_scope();
+                            self.env.declare(
+                                var_name.clone(),
+                                VarInfo { ty, ownership: OwnershipState::Owned, is_mutable: false, is_initialized: true },
+                            );
+                        }
+                        let init_ty = self.check_expr(&init.value, &init.span);
+                        if signature.is_some() {
+                            self.env.pop_scope();
+                        }
+                        let init_ty = init_ty?;
 
                         // If type annotation exists, check compatibility
-                        if let Some(type_ann) = type_annotation {
-                            let annotated_ty = self.convert_ast_type(&type_ann.value)?;
+                        if let (Some(type_ann), Some(annotated_ty)) = (type_annotation, annotated_ty) {
                             if !TypeHelpers::is_assignable_with_env(&init_ty, &annotated_ty, Some(&self.env)) {
                                 return Err(TypeError::new(
                                     TypeErrorKind::TypeMismatch {
                                         expected: annotated_ty,
                                         found: init_ty,
                                     },
-                                    span.clone(),
-                                ));
+                                    init.span,
+                                )
+                                .declared_at(Some(type_ann.span)));
                             }
                             annotated_ty
                         } else {
@@ -217,45 +351,75 @@ impl TypeChecker {
                         Type::Unknown
                     };
 
+                    // A call returning `ref T` keeps its borrowed arguments borrowed
+                    // for as long as this binding is in scope
+                    let borrow = declarator
+                        .init
+                        .as_ref()
+                        .and_then(|init| self.returned_borrow(&init.value));
+
                     // Determine ownership
                     let ownership_state = if let Some(own) = ownership {
                         TypeHelpers::convert_ownership(&own.kind)
+                    } else if let Some((mutable, _)) = &borrow {
+                        if *mutable {
+                            OwnershipState::MutBorrowed
+                        } else {
+                            OwnershipState::Borrowed
+                        }
                     } else {
                         // Auto-inference: default to owned
                         OwnershipState::Owned
                     };
 
-                    // Duplicate variable detection: let/const cannot redeclare in same scope
+                    // let/const cannot redeclare a binding of the same scope
                     // (var redeclarations are allowed in JS/TS)
-                    if !matches!(var_decl.kind, VarDeclKind::Var)
-                        && self.env.has_in_current_scope(var_name)
-                    {
-                        return Err(TypeError::new(
-                            TypeErrorKind::DuplicateDeclaration(var_name.clone()),
-                            span.clone(),
-                        ));
+                    let is_var = matches!(var_decl.kind, VarDeclKind::Var);
+                    if let Some(err) = self.redeclaration_error(var_name, name.span, is_var) {
+                        return Err(err);
                     }
 
-                    self.env.declare(
-                        var_name.clone(),
-                        VarInfo {
-                            ty,
-                            ownership: ownership_state,
-                            is_mutable: !is_const,
-                            is_initialized: declarator.init.is_some(),
-                        },
-                    );
-                }
-                Pattern::Array { elements: _, .. } => {
-                    // Handle array destructuring
-                    if let Some(init) = &declarator.init {
-                        self.check_expr(&init.value, &init.span)?;
+                    if is_var {
+                        // A `var` lives in the function scope it was hoisted
+                        // to; redeclaring it without a value keeps its value
+                        let keeps_value = declarator.init.is_none()
+                            && type_annotation.is_none()
+                            && self.env.has_in_var_scope(var_name);
+                        if !keeps_value {
+                            self.env.declare_var(
+                                var_name.clone(),
+                                VarInfo { ty, ownership: ownership_state, is_mutable: true, is_initialized: true },
+                            );
+                        }
+                    } else {
+                        self.env.declare(
+                            var_name.clone(),
+                            VarInfo {
+                                ty,
+                                ownership: ownership_state,
+                                is_mutable: !is_const,
+                                // Only an ambient `const` (the parser rejects any
+                                // other) can lack an initializer
+                                is_initialized: declarator.init.is_some() || is_const || self.in_ambient,
+                            },
+                        );
+                    }
+                    if let Some(type_ann) = type_annotation {
+                        self.env.record_annotation(var_name.clone(), type_ann.span);
+                    }
+                    if let Some((_, owners)) = borrow {
+                        self.env.record_borrow(var_name.clone(), owners);
                     }
                 }
-                Pattern::Object { properties: _, .. } => {
-                    // Handle object destructuring
-                    if let Some(init) = &declarator.init {
-                        self.check_expr(&init.value, &init.span)?;
+                Pattern::Array { .. } | Pattern::Object { .. } => {
+                    // Destructuring declares each binding with the type of
+                    // the element or property it takes
+                    let init_ty = match &declarator.init {
+                        Some(init) => self.check_expr(&init.value, &init.span)?,
+                        None => Type::Unknown,
+                    };
+                    if let Some(err) = self.declare_pattern(&declarator.pattern.value, init_ty, &var_decl.kind) {
+                        return Err(err);
                     }
                 }
                 Pattern::Assignment { pattern: _, default } => {
@@ -267,4 +431,222 @@ impl TypeChecker {
 
         Ok(())
     }
+
+    /// The type an arrow or function expression has before its body is
+    /// checked: its annotations, with `any` for what is not annotated.
+    fn recursive_signature(&self, init: &Expr) -> Option<Type> {
+        let (params, return_type) = match init {
+            Expr::Arrow { params, return_type, .. } | Expr::Function { params, return_type, .. } => {
+                (params, return_type)
+            }
+            _ => return None,
+        };
+        let params = params
+            .iter()
+            .map(|param| match self.resolve_signature_param_type(param) {
+                Ok(Type::Unknown) | Err(_) => Type::Any,
+                Ok(ty) => ty,
+            })
+            .collect();
+        let return_type = match return_type {
+            Some(ann) => self.resolve_return_type(Some(ann)).unwrap_or(Type::Any),
+            None => Type::Any,
+        };
+        Some(Type::Function { params, return_type: Box::new(return_type) })
+    }
+
+    /// Declare the bindings of a destructuring pattern matched against a
+    /// value of type `ty`: the positional elements of a tuple, the elements
+    /// of other iterables, and the properties of objects. Returns the first
+    /// error in a default value.
+    fn declare_pattern(&mut self, pattern: &Pattern, ty: Type, kind: &VarDeclKind) -> Option<TypeError> {
+        match pattern {
+            Pattern::Ident { name, type_annotation, .. } => {
+                let is_var = matches!(kind, VarDeclKind::Var);
+                if let Some(err) = self.redeclaration_error(&name.value.name, name.span, is_var) {
+                    return Some(err);
+                }
+                let ty = match type_annotation {
+                    Some(annotation) => match self.convert_ast_type(&annotation.value) {
+                        Ok(annotated) => annotated,
+                        Err(err) => return Some(err),
+                    },
+                    None => ty,
+                };
+                self.env.declare(
+                    name.value.name.clone(),
+                    VarInfo {
+                        ty,
+                        ownership: OwnershipState::Owned,
+                        is_mutable: !matches!(kind, VarDeclKind::Const),
+                        is_initialized: true,
+                    },
+                );
+                None
+            }
+            Pattern::Array { elements, rest } => {
+                let resolved = TypeHelpers::resolve_type(&ty, Some(&self.env)).clone();
+                for (i, element) in elements.iter().enumerate() {
+                    let Some(element) = element else { continue };
+                    let element_ty = match &resolved {
+                        Type::Tuple(elems) => elems.get(i).cloned().unwrap_or(Type::Undefined),
+                        ty => self.iterable_element_type(ty, false).unwrap_or(Type::Any),
+                    };
+                    if let Some(err) = self.declare_pattern(&element.value, element_ty, kind) {
+                        return Some(err);
+                    }
+                }
+                let rest_ty = match &resolved {
+                    Type::Tuple(elems) => Type::Tuple(elems.iter().skip(elements.len()).cloned().collect()),
+                    ty => Type::Array(Box::new(self.iterable_element_type(ty, false).unwrap_or(Type::Any))),
+                };
+                rest.as_ref().and_then(|rest| self.declare_pattern(&rest.value, rest_ty, kind))
+            }
+            Pattern::Object { properties, rest } => {
+                for property in properties {
+                    let key = TypeHelpers::property_name_to_string(&property.key);
+                    let property_ty = match TypeHelpers::resolve_type(&ty, Some(&self.env)) {
+                        Type::Object { properties } | Type::Interface { properties, .. } => properties
+                            .iter()
+                            .find(|(name, _, _)| *name == key)
+                            .map_or(Type::Any, |(_, ty, _)| ty.clone()),
+                        Type::Class { fields, accessors, .. } => fields
+                            .iter()
+                            .chain(accessors)
+                            .find(|(name, _)| *name == key)
+                            .map_or(Type::Any, |(_, ty)| ty.clone()),
+                        _ => Type::Any,
+                    };
+                    if let Some(err) = self.declare_pattern(&property.value.value, property_ty, kind) {
+                        return Some(err);
+                    }
+                }
+                rest.as_ref().and_then(|rest| self.declare_pattern(&rest.value, Type::Any, kind))
+            }
+            // A default stands in for a missing (undefined) value
+            Pattern::Assignment { pattern, default } => {
+                let default_ty = match self.check_expr(&default.value, &default.span) {
+                    Ok(default_ty) => default_ty,
+                    Err(err) => return Some(err),
+                };
+                let ty = match ty {
+                    Type::Undefined | Type::Unknown => TypeHelpers::widen_literals(default_ty),
+                    ty => ty,
+                };
+                self.declare_pattern(&pattern.value, ty, kind)
+            }
+        }
+    }
+
+    /// Declare the `var`s of a function body or of the top level before it
+    /// is checked. `var` is function-scoped: it can be read before its
+    /// declaration runs, as `undefined` unless annotated, and after the block
+    /// it is written in. Parameters of the same name are kept.
+    pub(crate) fn hoist_var_declarations<'a>(&mut self, stmts: impl IntoIterator<Item = &'a Node<Stmt>>) {
+        let mut decls = Vec::new();
+        for stmt in stmts {
+            stmt.value.collect_hoisted_vars(&mut decls);
+        }
+        for declarator in decls.into_iter().flat_map(|decl| &decl.declarations) {
+            let Pattern::Ident { name, type_annotation, .. } = &declarator.pattern.value else {
+                continue;
+            };
+            if self.env.has_in_var_scope(&name.value.name) {
+                continue;
+            }
+            let ty = match type_annotation {
+                Some(annotation) => match self.convert_ast_type(&annotation.value) {
+                    Ok(ty) => ty,
+                    // Reported where the declaration is checked
+                    Err(_) => continue,
+                },
+                None => Type::Undefined,
+            };
+            self.env.declare_var(
+                name.value.name.clone(),
+                VarInfo { ty, ownership: OwnershipState::Owned, is_mutable: true, is_initialized: true },
+            );
+        }
+    }
+
+    /// Record that `name` is declared at `span`, or the error if it
+    /// redeclares a binding of the same scope (unless both are `var`s)
+    pub(crate) fn redeclaration_error(&mut self, name: &str, span: Span, is_var: bool) -> Option<TypeError> {
+        let first = self.env.record_declaration(name, span, is_var)?;
+        Some(TypeError::new(TypeErrorKind::DuplicateDeclaration(name.to_string()), span).first_declared_at(first))
+    }
+
+    /// The variable a reference expression ultimately points into, e.g. `p`
+    /// for `p.items[0].name`.
+    fn reference_root(expr: &Expr) -> Option<&str> {
+        match expr {
+            Expr::Ident(ident) => Some(&ident.name),
+            Expr::Member { object, .. } | Expr::Index { object, .. } => {
+                Self::reference_root(&object.value)
+            }
+            Expr::Paren(inner) | Expr::NonNullAssertion(inner) => Self::reference_root(&inner.value),
+            Expr::TypeCast { expr, .. } => Self::reference_root(&expr.value),
+            _ => None,
+        }
+    }
+
+    /// For a direct call to a function returning `ref T`, the mutability of
+    /// the returned reference and the variables passed to its `ref` params.
+    pub(crate) fn returned_borrow(&self, expr: &Expr) -> Option<(bool, Vec<String>)> {
+        let Expr::Call { callee, args, .. } = expr else {
+            return None;
+        };
+        let Expr::Ident(callee_ident) = &callee.value else {
+            return None;
+        };
+        let Type::Function { params, return_type } = &self.env.lookup(&callee_ident.name)?.ty else {
+            return None;
+        };
+        let Type::Ref { mutable, .. } = return_type.as_ref() else {
+            return None;
+        };
+        let owners = params
+            .iter()
+            .zip(args)
+            .filter(|(param, _)| matches!(param, Type::Ref { .. }))
+            .filter_map(|(_, arg)| Self::reference_root(&arg.value).map(str::to_string))
+            .collect();
+        Some((*mutable, owners))
+    }
+
+    /// Reject a returned reference that is derived from a value owned by the
+    /// current function (a local or an owned parameter) rather than from a
+    /// `ref` parameter or a module-level binding.
+    fn check_reference_escape(&self, expr: &Expr, span: &Span) -> Result<(), TypeError> {
+        let roots = match Self::reference_root(expr) {
+            Some(root) => vec![root.to_string()],
+            None => match self.returned_borrow(expr) {
+                Some((_, owners)) => owners,
+                None => return Ok(()),
+            },
+        };
+
+        let mut pending = roots;
+        let mut seen = Vec::new();
+        while let Some(name) = pending.pop() {
+            if seen.contains(&name) || self.env.is_global(&name) {
+                continue;
+            }
+            if let Some(origins) = self.env.borrow_origins(&name) {
+                pending.extend(origins.iter().cloned());
+            } else if let Some(info) = self.env.lookup(&name) {
+                if !matches!(
+                    info.ownership,
+                    OwnershipState::Borrowed | OwnershipState::MutBorrowed
+                ) {
+                    return Err(TypeError::new(
+                        TypeErrorKind::ReturnLocalReference(name),
+                        span.clone(),
+                    ));
+                }
+            }
+            seen.push(name);
+        }
+        Ok(())
+    }
 }
@@ -13,6 +13,8 @@ pub enum Type {
     Any,
     Never,
     Unknown,
+    /// `symbol`: only the well-known `Symbol.*` keys can be produced
+    Symbol,
 
     /// Array type
     Array(Box<Type>),
@@ -42,6 +44,12 @@ pub enum Type {
         name: String,
         fields: Vec<(String, Type)>,
         methods: Vec<(String, Type)>,
+        /// `get`/`set` properties, typed by the getter (or the setter's
+        /// parameter when there is no getter)
+        accessors: Vec<(String, Type)>,
+        /// `static` fields, methods and accessors, reached through the
+        /// class name (`Counter.count`) rather than an instance
+        statics: Vec<(String, Type)>,
     },
 
     /// Generic type parameter
@@ -56,6 +64,13 @@ pub enum Type {
     /// Promise type wrapping the resolved value type
     Promise(Box<Type>),
 
+    /// Borrowed reference (`ref T` / `mut ref T`). Only appears in function
+    /// signatures, where it records which params and returns are borrows.
+    Ref {
+        inner: Box<Type>,
+        mutable: bool,
+    },
+
     /// Literal type
     Literal(LiteralType),
 
@@ -72,6 +87,16 @@ pub enum Type {
     },
 }
 
+impl Type {
+    /// Strip a `Ref` wrapper, returning the referenced type.
+    pub fn without_ref(&self) -> &Type {
+        match self {
+            Type::Ref { inner, .. } => inner,
+            other => other,
+        }
+    }
+}
+
 /// Literal types
 #[derive(Debug, Clone, PartialEq)]
 pub enum LiteralType {
@@ -0,0 +1,19 @@
+// Allocation-heavy fixture for comparing `--alloc-strategy rc` and `arena`.
+// Every iteration allocates two class instances and a string.
+
+class Point {
+  x: number;
+  y: number;
+  constructor(x: number, y: number) { this.x = x; this.y = y; }
+}
+
+let total: number = 0;
+let label: string = "";
+for (let i: number = 0; i < 5000000; i = i + 1) {
+  let p: Point = new Point(i, i + 1);
+  let q: Point = new Point(p.y, p.x);
+  label = "point-" + "xy";
+  total = total + q.y;
+}
+console.log(total);
+console.log(label);
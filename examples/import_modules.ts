@@ -2,12 +2,13 @@
 
 import { readFileSync, writeFileSync } from "fs";
 import { join, dirname } from "path";
+import { tmpdir } from "os";
 
 // Use imported fs functions
 let content = readFileSync("test.txt", "utf-8");
 console.log("File content:", content);
 
-writeFileSync("output.txt", "Hello from Zaco!");
+writeFileSync(join(tmpdir(), "zaco_output.txt"), "Hello from Zaco!");
 
 // Use imported path functions
 let fullPath = join("/home", "user");
@@ -0,0 +1,5 @@
+import { base } from "./shared";
+
+export function left(): number {
+    return base() + 1;
+}
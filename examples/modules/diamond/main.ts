@@ -0,0 +1,9 @@
+// Diamond dependency: main imports left and right, which both import
+// shared. Compile with `--emit deps` to see the graph.
+import { readFileSync } from "fs";
+import { left } from "./left";
+import { right } from "./right";
+import type { Options } from "./options";
+
+const options: Options = { verbose: false };
+console.log(left() + right(), options.verbose);
@@ -0,0 +1,5 @@
+import { base } from "./shared";
+
+export function right(): number {
+    return base() + 2;
+}
@@ -0,0 +1,5 @@
+import { join } from "path";
+
+export function base(): number {
+    return 10;
+}
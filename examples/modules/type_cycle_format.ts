@@ -0,0 +1,9 @@
+import type { Version } from "./type_cycle_main";
+
+export interface Label {
+    text: string;
+}
+
+export function formatVersion(version: Version): string {
+    return "v" + version.major + "." + version.minor;
+}
@@ -0,0 +1,12 @@
+// Type-only imports are erased: type_cycle_format.ts imports a type back
+// from this module without forming a circular dependency
+import { type Label, formatVersion } from "./type_cycle_format";
+
+export interface Version {
+    major: number;
+    minor: number;
+}
+
+const label: Label = { text: "zaco" };
+const version: Version = { major: 1, minor: 2 };
+console.log(label.text + " v" + version.major + "." + version.minor);
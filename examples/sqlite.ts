@@ -0,0 +1,30 @@
+// Embedded SQLite: create a table, insert with bound parameters, read rows back
+import { open } from "sqlite";
+
+interface User {
+    id: number;
+    name: string;
+    score: number;
+}
+
+const db = open(":memory:");
+db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score REAL)");
+
+for (let i: number = 0; i < 4; i++) {
+    db.query("INSERT INTO users (name, score) VALUES (?, ?)", ["user" + i, i * 1.5]);
+}
+
+const rows: User[] = db.query("SELECT id, name, score FROM users WHERE score >= ? ORDER BY id", [1]);
+console.log(rows.length);
+for (let i: number = 0; i < rows.length; i++) {
+    const row = rows[i];
+    console.log(row.id + " " + row.name + " " + row.score);
+}
+
+try {
+    db.exec("SELECT * FROM missing");
+} catch (e) {
+    console.log(e);
+}
+
+db.close();
@@ -0,0 +1,23 @@
+// Worker threads: sum 0..1_000_000 in four parallel chunks
+import { spawn } from "worker";
+
+export function partialSum(start: number, end: number): number {
+    let sum: number = 0;
+    for (let i: number = start; i < end; i++) {
+        sum += i;
+    }
+    return sum;
+}
+
+const parts = await Promise.all([
+    spawn("partialSum", [0, 250000]),
+    spawn("partialSum", [250000, 500000]),
+    spawn("partialSum", [500000, 750000]),
+    spawn("partialSum", [750000, 1000000]),
+]);
+
+let total: number = 0;
+for (let i: number = 0; i < parts.length; i++) {
+    total += parts[i];
+}
+console.log(total);
@@ -5,6 +5,12 @@
  * string operations, and basic I/O for the Zaco TypeScript compiler.
  */
 
+#ifdef __APPLE__
+/* ucontext (generators) is only declared under X/Open */
+#define _XOPEN_SOURCE 700
+#define _DARWIN_C_SOURCE
+#endif
+
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
@@ -15,6 +21,11 @@
 #include <setjmp.h>
 #include <pthread.h>
 #include <unistd.h>
+#include <signal.h>
+#include <sys/resource.h>
+#include <sys/mman.h>
+#include <ucontext.h>
+#include <regex.h>
 
 /* ========== Memory Layout ==========
  * Every heap-allocated object has a header:
@@ -26,10 +37,129 @@
 #define RC_OFFSET   0
 #define SIZE_OFFSET  8
 
+/* ========== Raw Allocator ==========
+ * Every runtime heap allocation (objects, arrays, strings) goes through
+ * zaco_raw_alloc / zaco_raw_realloc / zaco_raw_free. The default build uses
+ * the C heap. Building with -DZACO_ALLOC_ARENA (`--alloc-strategy arena`)
+ * switches to a bump arena that is released wholesale at exit; individual
+ * frees and ref counting become no-ops.
+ */
+
+#ifdef ZACO_ALLOC_ARENA
+
+#define ARENA_CHUNK_SIZE (1 << 20)
+
+typedef struct ArenaChunk {
+    struct ArenaChunk* prev;
+    size_t used;
+    size_t capacity;
+    _Alignas(16) char data[];
+} ArenaChunk;
+
+/* Every chunk ever allocated (for release at exit), guarded by arena_lock.
+ * Each thread bumps from its own current chunk, so the fast path is lock-free. */
+static ArenaChunk* arena_chunks = NULL;
+static pthread_mutex_t arena_lock = PTHREAD_MUTEX_INITIALIZER;
+static __thread ArenaChunk* arena_current = NULL;
+
+static void zaco_arena_release(void) {
+    pthread_mutex_lock(&arena_lock);
+    ArenaChunk* chunk = arena_chunks;
+    while (chunk) {
+        ArenaChunk* prev = chunk->prev;
+        free(chunk);
+        chunk = prev;
+    }
+    arena_chunks = NULL;
+    pthread_mutex_unlock(&arena_lock);
+}
+
+static ArenaChunk* zaco_arena_grow(size_t size) {
+    size_t capacity = size > ARENA_CHUNK_SIZE ? size : ARENA_CHUNK_SIZE;
+    /* calloc so that bump allocations come back zeroed */
+    ArenaChunk* chunk = (ArenaChunk*)calloc(1, sizeof(ArenaChunk) + capacity);
+    if (!chunk) return NULL;
+    chunk->capacity = capacity;
+    pthread_mutex_lock(&arena_lock);
+    if (!arena_chunks) atexit(zaco_arena_release);
+    chunk->prev = arena_chunks;
+    arena_chunks = chunk;
+    pthread_mutex_unlock(&arena_lock);
+    /* Oversized requests get a dedicated chunk; keep bumping the current one */
+    if (capacity == ARENA_CHUNK_SIZE) arena_current = chunk;
+    return chunk;
+}
+
+static void* zaco_raw_alloc(size_t size) {
+    size = (size + 15) & ~(size_t)15;
+    ArenaChunk* chunk = arena_current;
+    if (!chunk || chunk->capacity - chunk->used < size) {
+        chunk = zaco_arena_grow(size);
+        if (!chunk) return NULL;
+    }
+    void* ptr = chunk->data + chunk->used;
+    chunk->used += size;
+    return ptr;
+}
+
+static void* zaco_raw_realloc(void* ptr, size_t old_size, size_t new_size) {
+    void* new_ptr = zaco_raw_alloc(new_size);
+    if (new_ptr && ptr) memcpy(new_ptr, ptr, old_size < new_size ? old_size : new_size);
+    return new_ptr;
+}
+
+static void zaco_raw_free(void* ptr) {
+    (void)ptr;
+}
+
+#else
+
+static void* zaco_raw_alloc(size_t size) {
+    return calloc(1, size);
+}
+
+static void* zaco_raw_realloc(void* ptr, size_t old_size, size_t new_size) {
+    (void)old_size;
+    return realloc(ptr, new_size);
+}
+
+static void zaco_raw_free(void* ptr) {
+    free(ptr);
+}
+
+#endif
+
+static char* zaco_raw_strdup(const char* s) {
+    size_t len = strlen(s) + 1;
+    char* copy = (char*)zaco_raw_alloc(len);
+    if (copy) memcpy(copy, s, len);
+    return copy;
+}
+
+/* ========== Debug Heap (ZACO_DEBUG_HEAP=1) ==========
+ * Leak, double-free and overrun tracking lives in the Rust runtime. The hooks
+ * are weak so a program linked without it simply runs untracked. */
+
+extern int zaco_debug_heap_enabled(void) __attribute__((weak));
+extern void* zaco_debug_heap_alloc(int64_t size) __attribute__((weak));
+extern void zaco_debug_heap_free(void* data_ptr) __attribute__((weak));
+extern void zaco_debug_heap_set_site(const char* site) __attribute__((weak));
+
+static int zaco_debug_heap_active(void) {
+    static int active = -1;
+    if (active < 0) {
+        active = zaco_debug_heap_enabled ? zaco_debug_heap_enabled() : 0;
+    }
+    return active;
+}
+
 /* ========== Allocation ========== */
 
 void* zaco_alloc(int64_t size) {
-    void* ptr = calloc(1, HEADER_SIZE + size);
+    if (zaco_debug_heap_active()) {
+        return zaco_debug_heap_alloc(size);
+    }
+    void* ptr = zaco_raw_alloc(HEADER_SIZE + size);
     if (!ptr) {
         fprintf(stderr, "zaco: out of memory\n");
         exit(1);
@@ -43,33 +173,127 @@ void* zaco_alloc(int64_t size) {
 
 void zaco_free(void* data_ptr) {
     if (!data_ptr) return;
+    if (zaco_debug_heap_active()) {
+        zaco_debug_heap_free(data_ptr);
+        return;
+    }
     void* real_ptr = (char*)data_ptr - HEADER_SIZE;
-    free(real_ptr);
+    zaco_raw_free(real_ptr);
+}
+
+/* Strings and arrays built by the Rust runtime come from zaco_alloc too, so
+ * they follow the same allocation strategy. Weak: the Rust runtime may not
+ * be linked. */
+extern void zaco_rs_set_alloc(void* (*alloc)(int64_t size)) __attribute__((weak));
+
+__attribute__((constructor))
+static void zaco_share_alloc(void) {
+    if (zaco_rs_set_alloc) zaco_rs_set_alloc(zaco_alloc);
 }
 
 /* ========== Reference Counting ========== */
 
+#ifdef ZACO_ALLOC_ARENA
+
+/* Arena memory lives until exit, so counts are never consulted. */
+void zaco_rc_inc(void* data_ptr) {
+    (void)data_ptr;
+}
+
+void zaco_rc_dec(void* data_ptr) {
+    (void)data_ptr;
+}
+
+#else
+
+/* Atomic so that values shared with timer/worker threads are counted safely */
 void zaco_rc_inc(void* data_ptr) {
     if (!data_ptr) return;
     int64_t* rc = (int64_t*)((char*)data_ptr - HEADER_SIZE);
-    (*rc)++;
+    __atomic_add_fetch(rc, 1, __ATOMIC_RELAXED);
 }
 
 void zaco_rc_dec(void* data_ptr) {
     if (!data_ptr) return;
     int64_t* rc = (int64_t*)((char*)data_ptr - HEADER_SIZE);
-    (*rc)--;
-    if (*rc <= 0) {
+    if (__atomic_sub_fetch(rc, 1, __ATOMIC_ACQ_REL) <= 0) {
         zaco_free(data_ptr);
     }
 }
 
+#endif
+
 int64_t zaco_rc_get(void* data_ptr) {
     if (!data_ptr) return 0;
     int64_t* rc = (int64_t*)((char*)data_ptr - HEADER_SIZE);
     return *rc;
 }
 
+/* ========== Boxes (mutable captures) ==========
+ * A variable captured by a closure that writes to it lives in a one-slot
+ * heap cell shared by the enclosing scope and every closure capturing it.
+ * The slot holds raw 64-bit values; numbers are passed bit-for-bit. */
+
+void* zaco_box_new(int64_t value) {
+    int64_t* box = (int64_t*)zaco_alloc(sizeof(int64_t));
+    *box = value;
+    return box;
+}
+
+int64_t zaco_box_get(void* box) {
+    return *(int64_t*)box;
+}
+
+void zaco_box_set(void* box, int64_t value) {
+    *(int64_t*)box = value;
+}
+
+/* ========== Closure values ==========
+ * A function used as a value (passed as an argument, stored in an object)
+ * is a pair of its code address and its captured environment. The env is
+ * NULL for a function that captures nothing, which then takes no env
+ * argument; callers test it to pick the calling convention. */
+
+void* zaco_closure_new(void* fn, void* env) {
+    void** closure = (void**)zaco_alloc(2 * sizeof(void*));
+    closure[0] = fn;
+    closure[1] = env;
+    return closure;
+}
+
+void* zaco_closure_fn(void* closure) {
+    return ((void**)closure)[0];
+}
+
+void* zaco_closure_env(void* closure) {
+    return ((void**)closure)[1];
+}
+
+/* ========== Undefined ==========
+ * `undefined` is a static, immortal value distinct from NULL (`null`). Its
+ * payload is the string "undefined", so printing or concatenating it needs
+ * no special case, and its count never reaches zero. */
+
+static struct {
+    int64_t rc;
+    int64_t size;
+    char data[16];
+} zaco_undefined_cell = { INT64_MAX / 2, 10, "undefined" };
+
+void* zaco_undefined(void) {
+    return zaco_undefined_cell.data;
+}
+
+/* `x == null`: true for both null and undefined */
+int64_t zaco_is_nullish(void* p) {
+    return p == NULL || p == zaco_undefined_cell.data;
+}
+
+/* Loose equality of two references: identical, or both nullish */
+int64_t zaco_loose_eq_ptr(void* a, void* b) {
+    return a == b || (zaco_is_nullish(a) && zaco_is_nullish(b));
+}
+
 /* ========== String Operations ========== */
 
 void* zaco_str_new(const char* s) {
@@ -99,10 +323,17 @@ int64_t zaco_str_len(void* s) {
 
 int64_t zaco_str_eq(void* a, void* b) {
     if (a == b) return 1;
-    if (!a || !b) return 0;
+    if (zaco_is_nullish(a) || zaco_is_nullish(b)) return 0;
     return strcmp((char*)a, (char*)b) == 0 ? 1 : 0;
 }
 
+/* `typeof` for a reference whose static type is `kind` */
+void* zaco_typeof(void* p, void* kind) {
+    if (p == zaco_undefined_cell.data) return zaco_str_new("undefined");
+    if (p == NULL) return zaco_str_new("object");
+    return zaco_str_new((const char*)kind);
+}
+
 /* ========== Number to String ========== */
 
 void* zaco_i64_to_str(int64_t n) {
@@ -111,48 +342,168 @@ void* zaco_i64_to_str(int64_t n) {
     return zaco_str_new(buf);
 }
 
-void* zaco_f64_to_str(double n) {
-    char buf[64];
-    if (floor(n) == n && fabs(n) < 1e15) {
-        snprintf(buf, sizeof(buf), "%.0f", n);
+/* A number-typed `var` read before its declaration runs holds this
+ * signalling NaN, which prints as undefined. Arithmetic on it quiets it
+ * into an ordinary NaN. */
+#define ZACO_UNDEFINED_F64_BITS 0x7FF4000000000000ULL
+
+static void zaco_format_f64(char* buf, size_t size, double n) {
+    uint64_t bits;
+    memcpy(&bits, &n, sizeof(bits));
+    if (bits == ZACO_UNDEFINED_F64_BITS) {
+        snprintf(buf, size, "undefined");
+    } else if (isnan(n)) {
+        snprintf(buf, size, "NaN");
+    } else if (isinf(n)) {
+        snprintf(buf, size, n < 0 ? "-Infinity" : "Infinity");
+    } else if (floor(n) == n && fabs(n) < 1e15) {
+        snprintf(buf, size, "%.0f", n);
     } else {
-        snprintf(buf, sizeof(buf), "%g", n);
+        snprintf(buf, size, "%g", n);
     }
+}
+
+void* zaco_f64_to_str(double n) {
+    char buf[64];
+    zaco_format_f64(buf, sizeof(buf), n);
     return zaco_str_new(buf);
 }
 
-/* ========== Console I/O ========== */
+/* ========== Console I/O ==========
+ * All console output goes through zaco_console_write, which indents each
+ * line by two spaces per open console.group(). console.log and info write
+ * to stdout; error, warn and debug write to stderr.
+ */
+
+/* Group depth, line starts and console.time labels are shared by every
+   thread, so they are only touched with console_mutex held */
+static pthread_mutex_t console_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int64_t zaco_console_depth = 0;
+static int zaco_stdout_line_start = 1;
+static int zaco_stderr_line_start = 1;
+
+static void zaco_console_write(FILE* out, const char* text) {
+    int* line_start = out == stderr ? &zaco_stderr_line_start : &zaco_stdout_line_start;
+    if (!*text) return;
+    pthread_mutex_lock(&console_mutex);
+    if (zaco_console_depth == 0) {
+        fwrite(text, 1, strlen(text), out);
+        *line_start = text[strlen(text) - 1] == '\n';
+        pthread_mutex_unlock(&console_mutex);
+        return;
+    }
+    /* Indent into one buffer so the text still reaches the stream in a
+       single write */
+    size_t lines = 1;
+    for (const char* p = text; *p; p++) lines += *p == '\n';
+    size_t indent = (size_t)zaco_console_depth * 2;
+    char* out_buf = (char*)malloc(strlen(text) + lines * indent + 1);
+    size_t pos = 0;
+    while (*text) {
+        if (*line_start) {
+            memset(out_buf + pos, ' ', indent);
+            pos += indent;
+        }
+        const char* newline = strchr(text, '\n');
+        size_t len = newline ? (size_t)(newline - text) + 1 : strlen(text);
+        memcpy(out_buf + pos, text, len);
+        pos += len;
+        *line_start = newline != NULL;
+        text += len;
+    }
+    fwrite(out_buf, 1, pos, out);
+    pthread_mutex_unlock(&console_mutex);
+    free(out_buf);
+}
+
+static void zaco_console_write_i64(FILE* out, int64_t n) {
+    char buf[32];
+    snprintf(buf, sizeof(buf), "%lld", (long long)n);
+    zaco_console_write(out, buf);
+}
+
+static void zaco_console_write_f64(FILE* out, double n) {
+    char buf[64];
+    zaco_format_f64(buf, sizeof(buf), n);
+    zaco_console_write(out, buf);
+}
+
+typedef struct {
+    char* data;
+    size_t len;
+    size_t capacity;
+} ZacoTextBuf;
+
+static void zaco_text_append(ZacoTextBuf* buf, const char* text) {
+    size_t len = strlen(text);
+    if (buf->len + len + 1 > buf->capacity) {
+        size_t capacity = buf->capacity ? buf->capacity : 64;
+        while (buf->len + len + 1 > capacity) capacity *= 2;
+        buf->data = (char*)realloc(buf->data, capacity);
+        buf->capacity = capacity;
+    }
+    memcpy(buf->data + buf->len, text, len + 1);
+    buf->len += len;
+}
+
+/* The console line being built: console.log and friends append each
+   piece, then write the whole line at once */
+static __thread ZacoTextBuf zaco_console_line = { NULL, 0, 0 };
+
+void zaco_console_put_str(void* s) {
+    zaco_text_append(&zaco_console_line, s ? (const char*)s : "null");
+}
+
+void zaco_console_put_f64(double n) {
+    char buf[64];
+    zaco_format_f64(buf, sizeof(buf), n);
+    zaco_text_append(&zaco_console_line, buf);
+}
+
+void zaco_console_put_i64(int64_t n) {
+    char buf[32];
+    snprintf(buf, sizeof(buf), "%lld", (long long)n);
+    zaco_text_append(&zaco_console_line, buf);
+}
+
+void zaco_console_put_bool(int8_t b) {
+    zaco_text_append(&zaco_console_line, b ? "true" : "false");
+}
+
+/* End the line and return it for one write by zaco_print_str or
+   zaco_console_*_str. The text stays valid until the next piece is added. */
+void* zaco_console_line_end(void) {
+    zaco_text_append(&zaco_console_line, "\n");
+    zaco_console_line.len = 0;
+    return zaco_console_line.data;
+}
 
 void zaco_print_str(void* s) {
     if (s) {
-        printf("%s", (char*)s);
+        zaco_console_write(stdout, (char*)s);
     }
 }
 
 void zaco_print_i64(int64_t n) {
-    printf("%lld", (long long)n);
+    zaco_console_write_i64(stdout, n);
 }
 
 void zaco_print_f64(double n) {
-    if (floor(n) == n && fabs(n) < 1e15) {
-        printf("%.0f", n);
-    } else {
-        printf("%g", n);
-    }
+    zaco_console_write_f64(stdout, n);
 }
 
 void zaco_print_bool(int64_t b) {
-    printf("%s", b ? "true" : "false");
+    zaco_console_write(stdout, b ? "true" : "false");
 }
 
 void zaco_println_str(void* s) {
     zaco_print_str(s);
-    printf("\n");
+    zaco_console_write(stdout, "\n");
 }
 
 void zaco_println_i64(int64_t n) {
     zaco_print_i64(n);
-    printf("\n");
+    zaco_console_write(stdout, "\n");
 }
 
 /* ========== Array Operations ========== */
@@ -201,6 +552,37 @@ int64_t zaco_array_len(void* array_ptr) {
     return arr->length;
 }
 
+/* `delete arr[index]`: leaves a hole, read back as undefined (NaN for
+ * number elements). The length is unchanged. */
+int64_t zaco_array_delete(void* arr, double index, int64_t pointer_elems) {
+    if (!arr) return 1;
+    int64_t length = *((int64_t*)arr);
+    if (index != floor(index) || index < 0 || index >= (double)length) return 1;
+    uint64_t hole;
+    if (pointer_elems) {
+        void* undefined = zaco_undefined_cell.data;
+        memcpy(&hole, &undefined, sizeof hole);
+    } else {
+        double nan = NAN;
+        memcpy(&hole, &nan, sizeof hole);
+    }
+    memcpy((char*)arr + 8 + (int64_t)index * 8, &hole, sizeof hole);
+    return 1;
+}
+
+/* `key in arr`: `length`, or an index (in canonical form) below the length */
+int64_t zaco_array_has_key(void* array_ptr, const char* key) {
+    if (!array_ptr || zaco_is_nullish((void*)key)) return 0;
+    if (strcmp(key, "length") == 0) return 1;
+    if (key[0] == '\0' || (key[0] == '0' && key[1] != '\0')) return 0;
+    int64_t index = 0;
+    for (const char* c = key; *c; c++) {
+        if (*c < '0' || *c > '9' || index > INT64_MAX / 10) return 0;
+        index = index * 10 + (*c - '0');
+    }
+    return index < ((ZacoArray*)array_ptr)->length;
+}
+
 /* Fix #2: Free array inner data buffer, then the array struct itself.
  * Call this instead of zaco_rc_dec for arrays, or use it when
  * the array ref count reaches 0. */
@@ -216,11 +598,12 @@ void zaco_array_destroy(void* array_ptr) {
 
 void zaco_array_rc_dec(void* array_ptr) {
     if (!array_ptr) return;
+#ifndef ZACO_ALLOC_ARENA
     int64_t* rc = (int64_t*)((char*)array_ptr - HEADER_SIZE);
-    (*rc)--;
-    if (*rc <= 0) {
+    if (__atomic_sub_fetch(rc, 1, __ATOMIC_ACQ_REL) <= 0) {
         zaco_array_destroy(array_ptr);
     }
+#endif
 }
 
 /* ========== Math Functions ========== */
@@ -294,6 +677,11 @@ int64_t zaco_math_trunc(double x) {
     return (int64_t)x;
 }
 
+/* JS `%`: the remainder takes the sign of the dividend, which is C fmod */
+double zaco_js_mod(double a, double b) {
+    return fmod(a, b);
+}
+
 double zaco_math_pi() {
     return M_PI;
 }
@@ -453,71 +841,63 @@ void* zaco_json_stringify(void* value) {
 
 void zaco_console_error_str(void* s) {
     if (s) {
-        fprintf(stderr, "%s", (char*)s);
+        zaco_console_write(stderr, (char*)s);
     }
 }
 
 void zaco_console_error_i64(int64_t n) {
-    fprintf(stderr, "%lld", (long long)n);
+    zaco_console_write_i64(stderr, n);
 }
 
 void zaco_console_error_f64(double n) {
-    if (floor(n) == n && fabs(n) < 1e15) {
-        fprintf(stderr, "%.0f", n);
-    } else {
-        fprintf(stderr, "%g", n);
-    }
+    zaco_console_write_f64(stderr, n);
 }
 
 void zaco_console_error_bool(int64_t b) {
-    fprintf(stderr, "%s", b ? "true" : "false");
+    zaco_console_write(stderr, b ? "true" : "false");
 }
 
 void zaco_console_errorln(void* s) {
     zaco_console_error_str(s);
-    fprintf(stderr, "\n");
+    zaco_console_write(stderr, "\n");
 }
 
 void zaco_console_warn_str(void* s) {
     if (s) {
-        fprintf(stderr, "%s", (char*)s);
+        zaco_console_write(stderr, (char*)s);
     }
 }
 
 void zaco_console_warn_i64(int64_t n) {
-    fprintf(stderr, "%lld", (long long)n);
+    zaco_console_write_i64(stderr, n);
 }
 
 void zaco_console_warnln(void* s) {
     zaco_console_warn_str(s);
-    fprintf(stderr, "\n");
+    zaco_console_write(stderr, "\n");
 }
 
 void zaco_console_debug_str(void* s) {
     if (s) {
-        fprintf(stdout, "%s", (char*)s);
+        zaco_console_write(stderr, (char*)s);
     }
 }
 
 void zaco_console_debug_i64(int64_t n) {
-    fprintf(stdout, "%lld", (long long)n);
+    zaco_console_write_i64(stderr, n);
 }
 
 void zaco_console_debug_f64(double n) {
-    if (floor(n) == n && fabs(n) < 1e15) {
-        fprintf(stdout, "%.0f", n);
-    } else {
-        fprintf(stdout, "%g", n);
-    }
+    zaco_console_write_f64(stderr, n);
 }
 
 void zaco_console_debug_bool(int64_t b) {
-    fprintf(stdout, "%s", b ? "true" : "false");
+    zaco_console_write(stderr, b ? "true" : "false");
 }
 
 void zaco_console_debugln(void* s) {
     zaco_console_debug_str(s);
-    fprintf(stdout, "\n");
+    zaco_console_write(stderr, "\n");
 }
 
 /* ========== String Methods ========== */
@@ -719,8 +1099,18 @@ void* zaco_str_char_at(void* s, int64_t index) {
     return zaco_str_new(buf);
 }
 
+void zaco_throw(void* error);
+
+/* A negative count throws a catchable RangeError, as in JS. */
 void* zaco_str_repeat(void* s, int64_t count) {
-    if (!s || count <= 0) return zaco_str_new("");
+    if (count < 0) {
+        const char* fmt = "RangeError: Invalid count value: %lld";
+        size_t len = strlen(fmt) + 21;
+        char* msg = (char*)zaco_alloc(len);
+        snprintf(msg, len, fmt, (long long)count);
+        zaco_throw(msg);
+    }
+    if (!s || count == 0) return zaco_str_new("");
 
     int64_t len = strlen((char*)s);
     /* Fix #8: overflow check before multiplication */
@@ -843,35 +1233,6 @@ void* zaco_array_slice(void* arr, int64_t start, int64_t end) {
     return result;
 }
 
-void* zaco_array_concat(void* a, void* b) {
-    if (!a && !b) return zaco_array_new(sizeof(void*), 0);
-    if (!a) {
-        zaco_rc_inc(b);
-        return b;
-    }
-    if (!b) {
-        zaco_rc_inc(a);
-        return a;
-    }
-
-    ZacoArray* arr_a = (ZacoArray*)a;
-    ZacoArray* arr_b = (ZacoArray*)b;
-
-    ZacoArray* result = (ZacoArray*)zaco_array_new(arr_a->elem_size, arr_a->length + arr_b->length);
-
-    for (int64_t i = 0; i < arr_a->length; i++) {
-        void* elem = (char*)arr_a->data + i * arr_a->elem_size;
-        zaco_array_push(result, elem);
-    }
-
-    for (int64_t i = 0; i < arr_b->length; i++) {
-        void* elem = (char*)arr_b->data + i * arr_b->elem_size;
-        zaco_array_push(result, elem);
-    }
-
-    return result;
-}
-
 int64_t zaco_array_index_of(void* arr, void* elem) {
     if (!arr || !elem) return -1;
 
@@ -990,19 +1351,37 @@ void zaco_exit(int64_t code) {
     exit((int)code);
 }
 
-/* ========== Exception Handling (setjmp/longjmp) ========== */
+/* ========== Exception Handling (setjmp/longjmp) ==========
+ * The try stack and error slot are per thread: a throw inside a timer
+ * callback can only unwind to a try block on that same thread, and
+ * zaco_get_error/zaco_clear_error see the calling thread's error. A running
+ * generator body swaps in its own try stack (see Generators).
+ */
 
 #define MAX_TRY_DEPTH 64
-static jmp_buf try_stack[MAX_TRY_DEPTH];
-static int try_depth = 0;
-static void* current_error = NULL;
+static __thread jmp_buf thread_try_stack[MAX_TRY_DEPTH];
+static __thread jmp_buf* try_stack_override = NULL;
+static __thread int try_depth = 0;
+static __thread void* current_error = NULL;
 
-int64_t zaco_try_push() {
+static jmp_buf* zaco_try_stack(void) {
+    return try_stack_override ? try_stack_override : thread_try_stack;
+}
+
+/* Defined with generators; returns only when no generator body is running */
+static void zaco_generator_throw_out(void* error);
+
+/* Reserve the next try slot and return its jmp_buf. Generated code calls
+ * _setjmp on it directly: setjmp must run in the frame of the function that
+ * contains the try block, since a jmp_buf filled inside a runtime helper
+ * would point at a frame that has already returned by the time zaco_throw
+ * jumps to it. */
+void* zaco_try_enter() {
     if (try_depth >= MAX_TRY_DEPTH) {
         fprintf(stderr, "zaco: try/catch nesting too deep\n");
         exit(1);
     }
-    return setjmp(try_stack[try_depth++]);
+    return &zaco_try_stack()[try_depth++];
 }
 
 void zaco_try_pop() {
@@ -1013,8 +1392,10 @@ void zaco_throw(void* error) {
     current_error = error;
     if (try_depth > 0) {
         try_depth--;
-        longjmp(try_stack[try_depth], 1);
+        longjmp(zaco_try_stack()[try_depth], 1);
     }
+    /* Not caught inside a generator body: rethrown from its next() call */
+    zaco_generator_throw_out(error);
     /* Uncaught exception */
     if (error) {
         fprintf(stderr, "Uncaught exception: %s\n", (char*)error);
@@ -1028,6 +1409,18 @@ void* zaco_get_error() {
     return current_error;
 }
 
+/* Checked-mode guard: throw a TypeError when a member is read off null or
+ * undefined, naming whichever one the receiver was. */
+void zaco_check_receiver(void* obj, char* prop) {
+    if (obj && obj != zaco_undefined_cell.data) return;
+    const char* fmt = "TypeError: cannot read property %s of %s";
+    const char* what = obj ? "undefined" : "null";
+    size_t len = strlen(fmt) + (prop ? strlen(prop) : 0) + strlen(what) + 1;
+    char* msg = (char*)zaco_alloc(len);
+    snprintf(msg, len, fmt, prop ? prop : "", what);
+    zaco_throw(msg);
+}
+
 void zaco_clear_error() {
     current_error = NULL;
 }
@@ -1058,6 +1451,64 @@ double zaco_parse_float(char* s) {
     return result;
 }
 
+/* JS ToNumber of a string: surrounding whitespace is ignored, "" is 0,
+   0x/0o/0b prefixes and Infinity are accepted, anything else is NaN */
+double zaco_to_number(void* str) {
+    if (!str || str == zaco_undefined()) return 0.0 / 0.0;
+    const char* s = (const char*)str;
+    while (*s && isspace((unsigned char)*s)) s++;
+    size_t len = strlen(s);
+    while (len > 0 && isspace((unsigned char)s[len - 1])) len--;
+    if (len == 0) return 0.0;
+
+    char buf[128];
+    if (len >= sizeof(buf)) return 0.0 / 0.0;
+    memcpy(buf, s, len);
+    buf[len] = '\0';
+
+    if (buf[0] == '0' && len > 2 && strchr("xXoObB", buf[1])) {
+        int base = (buf[1] == 'x' || buf[1] == 'X') ? 16 : (buf[1] == 'o' || buf[1] == 'O') ? 8 : 2;
+        double value = 0;
+        for (const char* p = buf + 2; *p; p++) {
+            int digit = isdigit((unsigned char)*p) ? *p - '0'
+                      : isalpha((unsigned char)*p) ? tolower((unsigned char)*p) - 'a' + 10 : base;
+            if (digit >= base) return 0.0 / 0.0;
+            value = value * base + digit;
+        }
+        return value;
+    }
+
+    const char* p = buf;
+    int negative = *p == '-';
+    if (*p == '+' || *p == '-') p++;
+    if (strcmp(p, "Infinity") == 0) return negative ? -INFINITY : INFINITY;
+
+    /* Decimal literal: digits [. digits] [e [+-] digits]; strtod alone
+       would also take "inf", "nan" and hex floats */
+    const char* q = p;
+    int digits = 0;
+    while (isdigit((unsigned char)*q)) { q++; digits++; }
+    if (*q == '.') {
+        q++;
+        while (isdigit((unsigned char)*q)) { q++; digits++; }
+    }
+    if (digits == 0) return 0.0 / 0.0;
+    if (*q == 'e' || *q == 'E') {
+        q++;
+        if (*q == '+' || *q == '-') q++;
+        if (!isdigit((unsigned char)*q)) return 0.0 / 0.0;
+        while (isdigit((unsigned char)*q)) q++;
+    }
+    if (*q) return 0.0 / 0.0;
+    return strtod(buf, NULL);
+}
+
+/* `n == s` for a number and a string; booleans reach here as numbers */
+int64_t zaco_loose_eq(double n, void* s) {
+    if (!s || s == zaco_undefined()) return 0;
+    return n == zaco_to_number(s);
+}
+
 int64_t zaco_is_nan(double n) {
     return isnan(n) ? 1 : 0;
 }
@@ -1066,6 +1517,121 @@ int64_t zaco_is_finite(double n) {
     return isfinite(n) ? 1 : 0;
 }
 
+/* ========== URI Components and Base64 ==========
+ * Strings are UTF-8. encodeURIComponent escapes each byte outside the
+ * unreserved set; btoa/atob treat a string as Latin-1 characters, one byte
+ * each, as in JS.
+ */
+
+void* zaco_encode_uri_component(void* s) {
+    const unsigned char* str = s ? (const unsigned char*)s : (const unsigned char*)"";
+    static const char hex[] = "0123456789ABCDEF";
+    char* result = (char*)zaco_alloc(strlen((const char*)str) * 3 + 1);
+    char* out = result;
+    for (const unsigned char* p = str; *p; p++) {
+        if (isalnum(*p) || strchr("-_.!~*'()", *p)) {
+            *out++ = (char)*p;
+        } else {
+            *out++ = '%';
+            *out++ = hex[*p >> 4];
+            *out++ = hex[*p & 0xF];
+        }
+    }
+    *out = '\0';
+    return result;
+}
+
+static int zaco_hex_digit(char c) {
+    if (c >= '0' && c <= '9') return c - '0';
+    if (c >= 'a' && c <= 'f') return c - 'a' + 10;
+    if (c >= 'A' && c <= 'F') return c - 'A' + 10;
+    return -1;
+}
+
+void* zaco_decode_uri_component(void* s) {
+    const char* str = s ? (const char*)s : "";
+    char* result = (char*)zaco_alloc(strlen(str) + 1);
+    char* out = result;
+    for (const char* p = str; *p; p++) {
+        if (*p != '%') {
+            *out++ = *p;
+            continue;
+        }
+        int high = zaco_hex_digit(p[1]);
+        int low = high < 0 ? -1 : zaco_hex_digit(p[2]);
+        if (low < 0) zaco_throw("URIError: URI malformed");
+        *out++ = (char)(high * 16 + low);
+        p += 2;
+    }
+    *out = '\0';
+    return result;
+}
+
+static const char zaco_base64_chars[] =
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+void* zaco_btoa(void* s) {
+    const unsigned char* str = s ? (const unsigned char*)s : (const unsigned char*)"";
+    /* UTF-8 back to Latin-1 bytes */
+    size_t len = strlen((const char*)str);
+    unsigned char* bytes = (unsigned char*)malloc(len + 1);
+    size_t count = 0;
+    for (size_t i = 0; i < len; i++) {
+        if (str[i] < 0x80) {
+            bytes[count++] = str[i];
+        } else if ((str[i] & 0xFE) == 0xC2 && i + 1 < len) {
+            bytes[count++] = (unsigned char)(((str[i] & 0x03) << 6) | (str[i + 1] & 0x3F));
+            i++;
+        } else {
+            free(bytes);
+            zaco_throw("InvalidCharacterError: btoa: the string contains characters outside of the Latin1 range");
+        }
+    }
+    char* result = (char*)zaco_alloc((count + 2) / 3 * 4 + 1);
+    char* out = result;
+    for (size_t i = 0; i < count; i += 3) {
+        uint32_t chunk = (uint32_t)bytes[i] << 16;
+        if (i + 1 < count) chunk |= (uint32_t)bytes[i + 1] << 8;
+        if (i + 2 < count) chunk |= bytes[i + 2];
+        *out++ = zaco_base64_chars[(chunk >> 18) & 0x3F];
+        *out++ = zaco_base64_chars[(chunk >> 12) & 0x3F];
+        *out++ = i + 1 < count ? zaco_base64_chars[(chunk >> 6) & 0x3F] : '=';
+        *out++ = i + 2 < count ? zaco_base64_chars[chunk & 0x3F] : '=';
+    }
+    *out = '\0';
+    free(bytes);
+    return result;
+}
+
+void* zaco_atob(void* s) {
+    const char* str = s ? (const char*)s : "";
+    /* each decoded byte is a Latin-1 character: up to two UTF-8 bytes */
+    char* result = (char*)zaco_alloc(strlen(str) * 2 + 1);
+    char* out = result;
+    uint32_t bits = 0;
+    int pending = 0;
+    for (const char* p = str; *p; p++) {
+        if (isspace((unsigned char)*p)) continue;
+        if (*p == '=') break;
+        const char* found = strchr(zaco_base64_chars, *p);
+        if (!found) zaco_throw("InvalidCharacterError: atob: the string to be decoded is not correctly encoded");
+        bits = (bits << 6) | (uint32_t)(found - zaco_base64_chars);
+        pending += 6;
+        if (pending >= 8) {
+            pending -= 8;
+            unsigned char byte = (unsigned char)((bits >> pending) & 0xFF);
+            if (byte < 0x80) {
+                *out++ = (char)byte;
+            } else {
+                *out++ = (char)(0xC0 | (byte >> 6));
+                *out++ = (char)(0x80 | (byte & 0x3F));
+            }
+        }
+    }
+    *out = '\0';
+    return result;
+}
+
 /* ========== Inline Array Helpers ==========
  * These work with the inline array format used by codegen:
  *   [length: i64][elem0][elem1]...
@@ -1091,100 +1657,597 @@ void* zaco_array_get_ptr(void* arr, int64_t index) {
     return *((void**)((char*)arr + 8 + index * 8));
 }
 
-/* ========== Object (Key-Value Map) ========== */
-
-typedef struct {
-    char* key;
-    uint64_t value_bits; /* Stores any 8-byte value via memcpy */
-} ZacoObjEntry;
-
-typedef struct {
-    int64_t count;
-    int64_t capacity;
-    ZacoObjEntry* entries;
-} ZacoObject;
-
-static int64_t zaco_object_find(ZacoObject* obj, const char* key) {
-    for (int64_t i = 0; i < obj->count; i++) {
-        if (obj->entries[i].key && strcmp(obj->entries[i].key, key) == 0) {
-            return i;
+/* `arr[index] = value`: the slot for `index`, growing the array first when
+ * it lies past the end. Arrays are allocated to fit, so the elements are
+ * copied into a new array, with the slots in between reading as 0 or NULL;
+ * `*arr` is updated and the caller stores it back in place of the old one. */
+static void* zaco_array_slot(void** arr, int64_t index) {
+    int64_t length = zaco_array_length(*arr);
+    if (index >= length) {
+        void* grown = zaco_alloc(8 + (index + 1) * 8);
+        if (length > 0) {
+            memcpy((char*)grown + 8, (char*)*arr + 8, length * 8);
         }
+        memset((char*)grown + 8 + length * 8, 0, (index - length) * 8);
+        *((int64_t*)grown) = index + 1;
+        *arr = grown;
     }
-    return -1;
+    return (char*)*arr + 8 + index * 8;
 }
 
-static void zaco_object_set_raw(ZacoObject* obj, const char* key, uint64_t bits) {
-    int64_t idx = zaco_object_find(obj, key);
-    if (idx >= 0) {
-        obj->entries[idx].value_bits = bits;
-        return;
-    }
-    if (obj->count >= obj->capacity) {
-        obj->capacity *= 2;
-        obj->entries = (ZacoObjEntry*)realloc(obj->entries, obj->capacity * sizeof(ZacoObjEntry));
-    }
-    obj->entries[obj->count].key = strdup(key);
-    obj->entries[obj->count].value_bits = bits;
-    obj->count++;
+/* A negative index names a property rather than an element, and leaves
+ * the elements as they are. */
+void* zaco_array_set_f64(void* arr, int64_t index, double value) {
+    if (index < 0) return arr;
+    *((double*)zaco_array_slot(&arr, index)) = value;
+    return arr;
 }
 
-static uint64_t zaco_object_get_raw(ZacoObject* obj, const char* key) {
-    int64_t idx = zaco_object_find(obj, key);
-    if (idx >= 0) return obj->entries[idx].value_bits;
-    return 0;
+void* zaco_array_set_ptr(void* arr, int64_t index, void* value) {
+    if (index < 0) return arr;
+    *((void**)zaco_array_slot(&arr, index)) = value;
+    return arr;
 }
 
-void* zaco_object_new(void) {
-    ZacoObject* obj = (ZacoObject*)malloc(sizeof(ZacoObject));
-    if (!obj) {
-        fprintf(stderr, "zaco: out of memory (object)\n");
-        exit(1);
+/* Append an element to an inline array. Arrays are allocated to fit, so
+ * the elements are copied into a new array one slot longer, which the
+ * caller stores back in place of the old one. */
+static void* zaco_array_grow(void* arr) {
+    int64_t length = arr ? *((int64_t*)arr) : 0;
+    void* grown = zaco_alloc(8 + (length + 1) * 8);
+    if (length > 0) {
+        memcpy((char*)grown + 8, (char*)arr + 8, length * 8);
     }
-    obj->count = 0;
-    obj->capacity = 8;
-    obj->entries = (ZacoObjEntry*)calloc(obj->capacity, sizeof(ZacoObjEntry));
-    return obj;
+    *((int64_t*)grown) = length + 1;
+    return grown;
 }
 
-void zaco_object_set_str(void* o, const char* key, const char* value) {
-    uint64_t bits;
-    memcpy(&bits, &value, sizeof(bits));
-    zaco_object_set_raw((ZacoObject*)o, key, bits);
+void* zaco_array_append_f64(void* arr, double value) {
+    void* grown = zaco_array_grow(arr);
+    int64_t index = *((int64_t*)grown) - 1;
+    *((double*)((char*)grown + 8 + index * 8)) = value;
+    return grown;
 }
 
-void zaco_object_set_f64(void* o, const char* key, double value) {
-    uint64_t bits;
-    memcpy(&bits, &value, sizeof(bits));
-    zaco_object_set_raw((ZacoObject*)o, key, bits);
+void* zaco_array_append_ptr(void* arr, void* value) {
+    void* grown = zaco_array_grow(arr);
+    int64_t index = *((int64_t*)grown) - 1;
+    *((void**)((char*)grown + 8 + index * 8)) = value;
+    return grown;
 }
 
-void zaco_object_set_i64(void* o, const char* key, int64_t value) {
-    uint64_t bits;
-    memcpy(&bits, &value, sizeof(bits));
-    zaco_object_set_raw((ZacoObject*)o, key, bits);
+/* A new inline array holding the elements of `arr` followed by those of
+ * `values`, for spreads. A NULL `arr` copies `values`. */
+void* zaco_array_append_all(void* arr, void* values) {
+    int64_t length = zaco_array_length(arr);
+    int64_t extra = zaco_array_length(values);
+    void* result = zaco_alloc(8 + (length + extra) * 8);
+    *((int64_t*)result) = length + extra;
+    if (length > 0) {
+        memcpy((char*)result + 8, (char*)arr + 8, length * 8);
+    }
+    if (extra > 0) {
+        memcpy((char*)result + 8 + length * 8, (char*)values + 8, extra * 8);
+    }
+    return result;
 }
 
-void zaco_object_set_ptr(void* o, const char* key, void* value) {
-    uint64_t bits;
-    memcpy(&bits, &value, sizeof(bits));
-    zaco_object_set_raw((ZacoObject*)o, key, bits);
+/* `a.concat(b)`: a new inline array holding the elements of `a` followed by
+ * those of `b`. Neither is changed. */
+void* zaco_array_concat(void* a, void* b) {
+    return zaco_array_append_all(a, b);
 }
 
-const char* zaco_object_get_str(void* o, const char* key) {
-    uint64_t bits = zaco_object_get_raw((ZacoObject*)o, key);
-    const char* result;
-    memcpy(&result, &bits, sizeof(result));
+/* A new inline array of `length` empty slots, which read as 0 or NULL,
+ * for `new Array(n)`. */
+void* zaco_array_with_capacity(int64_t length) {
+    if (length < 0) length = 0;
+    void* result = zaco_alloc(8 + length * 8);
+    *((int64_t*)result) = length;
+    memset((char*)result + 8, 0, length * 8);
     return result;
 }
 
-double zaco_object_get_f64(void* o, const char* key) {
-    uint64_t bits = zaco_object_get_raw((ZacoObject*)o, key);
-    double result;
-    memcpy(&result, &bits, sizeof(result));
-    return result;
+/* A position argument of an array method, such as the `start` of
+ * `arr.fill(value, start)`, clamped to the array; negative positions count
+ * from its end. */
+static int64_t zaco_array_position(void* arr, int64_t position) {
+    int64_t length = zaco_array_length(arr);
+    if (position < 0) return position + length < 0 ? 0 : position + length;
+    return position > length ? length : position;
+}
+
+void* zaco_array_fill_f64(void* arr, double value, int64_t start, int64_t end) {
+    end = zaco_array_position(arr, end);
+    for (int64_t i = zaco_array_position(arr, start); i < end; i++) {
+        *((double*)((char*)arr + 8 + i * 8)) = value;
+    }
+    return arr;
+}
+
+void* zaco_array_fill_ptr(void* arr, void* value, int64_t start, int64_t end) {
+    end = zaco_array_position(arr, end);
+    for (int64_t i = zaco_array_position(arr, start); i < end; i++) {
+        *((void**)((char*)arr + 8 + i * 8)) = value;
+    }
+    return arr;
+}
+
+void* zaco_array_reverse_in_place(void* arr) {
+    int64_t length = zaco_array_length(arr);
+    int64_t* slots = (int64_t*)((char*)arr + 8);
+    for (int64_t i = 0; i < length / 2; i++) {
+        int64_t slot = slots[i];
+        slots[i] = slots[length - 1 - i];
+        slots[length - 1 - i] = slot;
+    }
+    return arr;
+}
+
+/* `arr.copyWithin(target, start, end)`: copy the slots from `start` up to
+ * `end` over those from `target`, as far as the array reaches. */
+void* zaco_array_copy_within(void* arr, int64_t target, int64_t start, int64_t end) {
+    int64_t length = zaco_array_length(arr);
+    target = zaco_array_position(arr, target);
+    start = zaco_array_position(arr, start);
+    end = zaco_array_position(arr, end);
+    int64_t count = end - start;
+    if (count > length - target) count = length - target;
+    if (count > 0) {
+        memmove((char*)arr + 8 + target * 8, (char*)arr + 8 + start * 8, count * 8);
+    }
+    return arr;
+}
+
+/* The characters of a string as an inline string array, one element per
+ * UTF-8 encoded code point, which is what iterating a string produces. */
+void* zaco_str_chars(void* s) {
+    const unsigned char* str = s ? (const unsigned char*)s : (const unsigned char*)"";
+    int64_t len = strlen((const char*)str);
+    int64_t count = 0;
+    for (int64_t i = 0; i < len; i++) {
+        if ((str[i] & 0xC0) != 0x80) count++;
+    }
+    void* result = zaco_alloc(8 + count * 8);
+    *((int64_t*)result) = count;
+    int64_t i = 0;
+    for (int64_t index = 0; index < count; index++) {
+        int64_t end = i + 1;
+        while (end < len && (str[end] & 0xC0) == 0x80) end++;
+        char buf[8] = {0};
+        memcpy(buf, str + i, end - i < 7 ? end - i : 7);
+        *((void**)((char*)result + 8 + index * 8)) = zaco_str_new(buf);
+        i = end;
+    }
+    return result;
+}
+
+/* ========== Symbols ==========
+ * Only the well-known symbols exist. Each is a static handle holding its
+ * description, so `Symbol.iterator === Symbol.iterator` compares pointers.
+ */
+
+typedef struct {
+    const char* description;
+} ZacoSymbol;
+
+static ZacoSymbol zaco_well_known_symbols[] = {
+    {"Symbol.iterator"},
+    {"Symbol.asyncIterator"},
+    {"Symbol.dispose"},
+    {"Symbol.asyncDispose"},
+};
+
+void* zaco_symbol_well_known(const char* name) {
+    size_t count = sizeof(zaco_well_known_symbols) / sizeof(zaco_well_known_symbols[0]);
+    for (size_t i = 0; i < count; i++) {
+        if (strcmp(zaco_well_known_symbols[i].description + strlen("Symbol."), name) == 0) {
+            return &zaco_well_known_symbols[i];
+        }
+    }
+    return NULL;
+}
+
+/* ========== Template Strings Arrays ==========
+ * A tag function's first argument: an inline string array of the cooked
+ * template parts, with one extra slot past the last element holding the
+ * inline array of raw parts. Length and indexing only see the cooked parts.
+ */
+
+void* zaco_template_strings(void* cooked, void* raw) {
+    int64_t length = zaco_array_length(cooked);
+    int64_t* strings = (int64_t*)zaco_alloc(8 + (length + 1) * 8);
+    strings[0] = length;
+    if (length > 0) memcpy(strings + 1, (int64_t*)cooked + 1, length * 8);
+    ((void**)(strings + 1))[length] = raw;
+    return strings;
+}
+
+void* zaco_template_raw(void* strings) {
+    if (!strings) return NULL;
+    int64_t length = *((int64_t*)strings);
+    return ((void**)((int64_t*)strings + 1))[length];
+}
+
+/* String.raw`...`: the raw parts with the (stringified) substitutions between them */
+void* zaco_string_raw(void* strings, void* values) {
+    void* raw = zaco_template_raw(strings);
+    int64_t parts = zaco_array_length(raw);
+    int64_t count = zaco_array_length(values);
+    size_t total = 1;
+    for (int64_t i = 0; i < parts; i++) {
+        total += strlen((const char*)zaco_array_get_ptr(raw, i));
+        if (i + 1 < parts && i < count) {
+            const char* value = (const char*)zaco_array_get_ptr(values, i);
+            if (value) total += strlen(value);
+        }
+    }
+    char* result = (char*)zaco_alloc(total);
+    char* out = result;
+    for (int64_t i = 0; i < parts; i++) {
+        const char* part = (const char*)zaco_array_get_ptr(raw, i);
+        size_t len = strlen(part);
+        memcpy(out, part, len);
+        out += len;
+        if (i + 1 < parts && i < count) {
+            const char* value = (const char*)zaco_array_get_ptr(values, i);
+            if (value) {
+                len = strlen(value);
+                memcpy(out, value, len);
+                out += len;
+            }
+        }
+    }
+    *out = '\0';
+    return result;
+}
+
+/* ========== Regular Expressions ==========
+ * Regex literals compile to POSIX extended regexes, with the JS class
+ * escapes (\d, \w, \s and their negations) expanded to bracket expressions.
+ * A match is an inline string array of the whole match and its capture
+ * groups (NULL for a group that did not take part), with one extra slot past
+ * the last element holding the match index as an f64.
+ */
+
+typedef struct {
+    regex_t compiled;
+    int valid;
+    int global;
+} ZacoRegex;
+
+static const char* zaco_regex_class(char escape, int in_bracket) {
+    switch (escape) {
+        case 'd': return in_bracket ? "0-9" : "[0-9]";
+        case 'w': return in_bracket ? "A-Za-z0-9_" : "[A-Za-z0-9_]";
+        case 's': return in_bracket ? "[:space:]" : "[[:space:]]";
+        case 'D': return in_bracket ? NULL : "[^0-9]";
+        case 'W': return in_bracket ? NULL : "[^A-Za-z0-9_]";
+        case 'S': return in_bracket ? NULL : "[^[:space:]]";
+        default: return NULL;
+    }
+}
+
+void* zaco_regex_new(void* pattern, void* flags) {
+    const char* src = pattern ? (const char*)pattern : "";
+    const char* flag_str = flags ? (const char*)flags : "";
+    char* translated = (char*)malloc(strlen(src) * 16 + 1);
+    char* out = translated;
+    int in_bracket = 0;
+    for (const char* p = src; *p; p++) {
+        if (*p == '\\' && p[1]) {
+            char escape = *++p;
+            const char* cls = zaco_regex_class(escape, in_bracket);
+            if (cls) {
+                out += sprintf(out, "%s", cls);
+            } else if (escape == 'n' || escape == 't' || escape == 'r') {
+                *out++ = escape == 'n' ? '\n' : escape == 't' ? '\t' : '\r';
+            } else if (in_bracket) {
+                /* a backslash is literal inside a POSIX bracket expression */
+                *out++ = escape;
+            } else {
+                *out++ = '\\';
+                *out++ = escape;
+            }
+            continue;
+        }
+        if (*p == '[' && !in_bracket) {
+            in_bracket = 1;
+        } else if (*p == ']' && in_bracket) {
+            in_bracket = 0;
+        }
+        *out++ = *p;
+    }
+    *out = '\0';
+
+    ZacoRegex* re = (ZacoRegex*)zaco_alloc(sizeof(ZacoRegex));
+    int cflags = REG_EXTENDED;
+    if (strchr(flag_str, 'i')) cflags |= REG_ICASE;
+    if (strchr(flag_str, 'm')) cflags |= REG_NEWLINE;
+    re->valid = regcomp(&re->compiled, translated, cflags) == 0;
+    re->global = strchr(flag_str, 'g') != NULL;
+    free(translated);
+    return re;
+}
+
+/* str.matchAll(re): an inline array of every match of a global regex */
+void* zaco_str_match_all(void* s, void* regex) {
+    ZacoRegex* re = (ZacoRegex*)regex;
+    if (re && !re->global) {
+        zaco_throw("TypeError: matchAll must be called with a global RegExp");
+    }
+    const char* str = s ? (const char*)s : "";
+    int64_t count = 0, capacity = 4;
+    void** matches = (void**)malloc(capacity * sizeof(void*));
+    if (re && re->valid) {
+        size_t groups = re->compiled.re_nsub + 1;
+        regmatch_t* spans = (regmatch_t*)malloc(groups * sizeof(regmatch_t));
+        size_t offset = 0, length = strlen(str);
+        while (offset <= length
+               && regexec(&re->compiled, str + offset, groups, spans, offset > 0 ? REG_NOTBOL : 0) == 0) {
+            int64_t* match = (int64_t*)zaco_alloc(8 + (groups + 1) * 8);
+            match[0] = (int64_t)groups;
+            for (size_t g = 0; g < groups; g++) {
+                char* group = NULL;
+                if (spans[g].rm_so >= 0) {
+                    size_t len = spans[g].rm_eo - spans[g].rm_so;
+                    group = (char*)zaco_alloc(len + 1);
+                    memcpy(group, str + offset + spans[g].rm_so, len);
+                    group[len] = '\0';
+                }
+                ((void**)(match + 1))[g] = group;
+            }
+            ((double*)(match + 1))[groups] = (double)(offset + spans[0].rm_so);
+            if (count == capacity) {
+                capacity *= 2;
+                matches = (void**)realloc(matches, capacity * sizeof(void*));
+            }
+            matches[count++] = match;
+            /* step past an empty match so the scan always moves forward */
+            offset += spans[0].rm_eo > spans[0].rm_so ? (size_t)spans[0].rm_eo : (size_t)spans[0].rm_so + 1;
+        }
+        free(spans);
+    }
+    int64_t* result = (int64_t*)zaco_alloc(8 + count * 8);
+    result[0] = count;
+    if (count > 0) memcpy(result + 1, matches, count * 8);
+    free(matches);
+    return result;
+}
+
+/* match.index: where a matchAll match starts in the searched string */
+double zaco_regex_match_index(void* match) {
+    if (!match) return 0.0;
+    int64_t length = *((int64_t*)match);
+    return ((double*)((int64_t*)match + 1))[length];
+}
+
+/* ========== Object (Key-Value Map) ========== */
+
+typedef struct {
+    char* key;
+    uint64_t value_bits; /* Stores any 8-byte value via memcpy */
+    /* Accessor property: compiled functions taking the object (and the new
+     * value), typed like the property. NULL for data properties. */
+    void* getter;
+    void* setter;
+} ZacoObjEntry;
+
+typedef struct {
+    int64_t count;
+    int64_t capacity;
+    ZacoObjEntry* entries;
+    int64_t frozen; /* set by Object.freeze */
+} ZacoObject;
+
+static int64_t zaco_object_find(ZacoObject* obj, const char* key) {
+    for (int64_t i = 0; i < obj->count; i++) {
+        if (obj->entries[i].key && strcmp(obj->entries[i].key, key) == 0) {
+            return i;
+        }
+    }
+    return -1;
+}
+
+static int64_t zaco_object_entry(ZacoObject* obj, const char* key) {
+    int64_t idx = zaco_object_find(obj, key);
+    if (idx >= 0) return idx;
+    if (obj->count >= obj->capacity) {
+        int64_t old_capacity = obj->capacity;
+        obj->capacity *= 2;
+        obj->entries = (ZacoObjEntry*)zaco_raw_realloc(obj->entries,
+            old_capacity * sizeof(ZacoObjEntry), obj->capacity * sizeof(ZacoObjEntry));
+    }
+    obj->entries[obj->count].key = zaco_raw_strdup(key);
+    obj->entries[obj->count].value_bits = 0;
+    obj->entries[obj->count].getter = NULL;
+    obj->entries[obj->count].setter = NULL;
+    return obj->count++;
+}
+
+static void zaco_object_set_raw(ZacoObject* obj, const char* key, uint64_t bits) {
+    obj->entries[zaco_object_entry(obj, key)].value_bits = bits;
+}
+
+/* `key in obj`: whether the object has the property, data or accessor */
+int64_t zaco_obj_has_prop(void* o, const char* key) {
+    if (zaco_is_nullish(o) || zaco_is_nullish((void*)key)) return 0;
+    return zaco_object_find((ZacoObject*)o, key) >= 0;
+}
+
+/* `delete obj[key]`: removes the property, keeping the others in order.
+ * Deleting from a frozen object throws, as in strict mode. */
+int64_t zaco_object_delete(void* o, const char* key) {
+    if (zaco_is_nullish(o) || zaco_is_nullish((void*)key)) return 1;
+    ZacoObject* obj = (ZacoObject*)o;
+    int64_t idx = zaco_object_find(obj, key);
+    if (idx < 0) return 1;
+    if (obj->frozen) {
+        const char* fmt = "TypeError: Cannot delete property '%s' of object";
+        size_t len = strlen(fmt) + strlen(key) + 1;
+        char* msg = (char*)zaco_alloc(len);
+        snprintf(msg, len, fmt, key);
+        zaco_throw(msg);
+    }
+    zaco_raw_free(obj->entries[idx].key);
+    memmove(&obj->entries[idx], &obj->entries[idx + 1], (obj->count - idx - 1) * sizeof(ZacoObjEntry));
+    obj->count--;
+    return 1;
+}
+
+/* `key in instance` for a class instance, whose property names are fixed:
+ * `names` lists them, one per line */
+int64_t zaco_key_in_names(const char* key, const char* names) {
+    if (zaco_is_nullish((void*)key)) return 0;
+    size_t len = strlen(key);
+    for (const char* line = names; *line;) {
+        const char* end = strchr(line, '\n');
+        size_t line_len = end ? (size_t)(end - line) : strlen(line);
+        if (line_len == len && strncmp(line, key, len) == 0) return 1;
+        if (!end) break;
+        line = end + 1;
+    }
+    return 0;
+}
+
+static uint64_t zaco_object_get_raw(ZacoObject* obj, const char* key) {
+    int64_t idx = zaco_object_find(obj, key);
+    if (idx >= 0) return obj->entries[idx].value_bits;
+    return 0;
+}
+
+/* The accessor entry for `key`, if the property has a getter or setter */
+static ZacoObjEntry* zaco_object_accessor(ZacoObject* obj, const char* key) {
+    if (!obj) return NULL;
+    int64_t idx = zaco_object_find(obj, key);
+    if (idx < 0) return NULL;
+    ZacoObjEntry* entry = &obj->entries[idx];
+    return entry->getter || entry->setter ? entry : NULL;
+}
+
+/* Value of entry i, through its getter for an accessor property; `shape`
+ * says how the getter returns it. An accessor without a getter, or of
+ * unknown shape, reads as 0. */
+static uint64_t zaco_object_entry_bits(ZacoObject* obj, int64_t i, const char* shape) {
+    ZacoObjEntry* entry = &obj->entries[i];
+    if (!entry->getter && !entry->setter) return entry->value_bits;
+    uint64_t bits = 0;
+    if (!entry->getter || !shape) return bits;
+    if (shape && *shape == 'n') {
+        double value = ((double (*)(void*))entry->getter)(obj);
+        memcpy(&bits, &value, sizeof(bits));
+    } else if (shape && *shape == 'b') {
+        bits = ((uint8_t (*)(void*))entry->getter)(obj);
+    } else {
+        void* value = ((void* (*)(void*))entry->getter)(obj);
+        memcpy(&bits, &value, sizeof(bits));
+    }
+    return bits;
+}
+
+/* Make `key` an accessor property. Either function may be NULL. */
+void zaco_object_define_accessor(void* o, const char* key, void* getter, void* setter) {
+    ZacoObject* obj = (ZacoObject*)o;
+    ZacoObjEntry* entry = &obj->entries[zaco_object_entry(obj, key)];
+    if (getter) entry->getter = getter;
+    if (setter) entry->setter = setter;
+}
+
+void* zaco_object_new(void) {
+    ZacoObject* obj = (ZacoObject*)zaco_raw_alloc(sizeof(ZacoObject));
+    if (!obj) {
+        fprintf(stderr, "zaco: out of memory (object)\n");
+        exit(1);
+    }
+    obj->count = 0;
+    obj->capacity = 8;
+    obj->entries = (ZacoObjEntry*)zaco_raw_alloc(obj->capacity * sizeof(ZacoObjEntry));
+    obj->frozen = 0;
+    return obj;
+}
+
+/* Writing a data property of a frozen object throws a TypeError, with
+ * Node's messages. Accessors keep working, as their setters decide. */
+static void zaco_object_check_writable(ZacoObject* obj, const char* key) {
+    if (!obj->frozen) return;
+    const char* fmt = zaco_object_find(obj, key) >= 0
+        ? "TypeError: Cannot assign to read only property '%s' of object"
+        : "TypeError: Cannot add property %s, object is not extensible";
+    size_t len = strlen(fmt) + strlen(key) + 1;
+    char* msg = (char*)zaco_alloc(len);
+    snprintf(msg, len, fmt, key);
+    zaco_throw(msg);
+}
+
+void zaco_object_set_str(void* o, const char* key, const char* value) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) {
+        if (accessor->setter) ((void (*)(void*, const char*))accessor->setter)(o, value);
+        return;
+    }
+    zaco_object_check_writable((ZacoObject*)o, key);
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    zaco_object_set_raw((ZacoObject*)o, key, bits);
+}
+
+void zaco_object_set_f64(void* o, const char* key, double value) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) {
+        if (accessor->setter) ((void (*)(void*, double))accessor->setter)(o, value);
+        return;
+    }
+    zaco_object_check_writable((ZacoObject*)o, key);
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    zaco_object_set_raw((ZacoObject*)o, key, bits);
+}
+
+void zaco_object_set_i64(void* o, const char* key, int64_t value) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) {
+        if (accessor->setter) ((void (*)(void*, int64_t))accessor->setter)(o, value);
+        return;
+    }
+    zaco_object_check_writable((ZacoObject*)o, key);
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    zaco_object_set_raw((ZacoObject*)o, key, bits);
+}
+
+void zaco_object_set_ptr(void* o, const char* key, void* value) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) {
+        if (accessor->setter) ((void (*)(void*, void*))accessor->setter)(o, value);
+        return;
+    }
+    zaco_object_check_writable((ZacoObject*)o, key);
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    zaco_object_set_raw((ZacoObject*)o, key, bits);
+}
+
+const char* zaco_object_get_str(void* o, const char* key) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) return accessor->getter ? ((const char* (*)(void*))accessor->getter)(o) : 0;
+    uint64_t bits = zaco_object_get_raw((ZacoObject*)o, key);
+    const char* result;
+    memcpy(&result, &bits, sizeof(result));
+    return result;
+}
+
+double zaco_object_get_f64(void* o, const char* key) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) return accessor->getter ? ((double (*)(void*))accessor->getter)(o) : 0;
+    uint64_t bits = zaco_object_get_raw((ZacoObject*)o, key);
+    double result;
+    memcpy(&result, &bits, sizeof(result));
+    return result;
 }
 
 int64_t zaco_object_get_i64(void* o, const char* key) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) return accessor->getter ? ((int64_t (*)(void*))accessor->getter)(o) : 0;
     uint64_t bits = zaco_object_get_raw((ZacoObject*)o, key);
     int64_t result;
     memcpy(&result, &bits, sizeof(result));
@@ -1192,6 +2255,8 @@ int64_t zaco_object_get_i64(void* o, const char* key) {
 }
 
 void* zaco_object_get_ptr(void* o, const char* key) {
+    ZacoObjEntry* accessor = zaco_object_accessor((ZacoObject*)o, key);
+    if (accessor) return accessor->getter ? ((void* (*)(void*))accessor->getter)(o) : 0;
     uint64_t bits = zaco_object_get_raw((ZacoObject*)o, key);
     void* result;
     memcpy(&result, &bits, sizeof(result));
@@ -1207,76 +2272,461 @@ void zaco_object_free(void* o) {
     if (!o) return;
     ZacoObject* obj = (ZacoObject*)o;
     for (int64_t i = 0; i < obj->count; i++) {
-        free(obj->entries[i].key);
+        zaco_raw_free(obj->entries[i].key);
     }
-    free(obj->entries);
-    free(obj);
+    zaco_raw_free(obj->entries);
+    zaco_raw_free(obj);
 }
 
-/* ========== Missing Console Warn Functions ========== */
+/* ========== Generators ==========
+ * A generator body runs as a coroutine on its own stack. next() switches
+ * into the body, which runs until its next yield (or its end) and switches
+ * back. Values cross the switch as raw 8-byte bits: `value` holds what the
+ * body yielded or returned, `sent` what the caller passed to next(v), which
+ * becomes the result of the paused yield expression. The stack is released
+ * as soon as the body finishes.
+ *
+ * The body keeps its own try stack, swapped in while it runs, so a try left
+ * open across a yield is not clobbered by the caller. A throw the body does
+ * not catch finishes the generator and is rethrown from next() on the
+ * caller's stack. Below each stack is a PROT_NONE guard page that the stack
+ * overflow handler recognises.
+ */
 
-void zaco_console_warn_f64(double n) {
-    if (floor(n) == n && fabs(n) < 1e15) {
-        fprintf(stderr, "%.0f", n);
+#define GENERATOR_STACK_SIZE (256 * 1024)
+
+typedef struct {
+    ucontext_t caller;
+    ucontext_t body_ctx;
+    void (*body)(void* gen, void* env);
+    void* env;
+    char* stack;        /* mapping start, the guard page comes first */
+    size_t stack_size;  /* whole mapping, guard page included */
+    jmp_buf* try_stack;
+    int try_depth;
+    uint64_t value;
+    uint64_t sent;
+    void* error;
+    int started;
+    int done;
+    int threw;
+} ZacoGenerator;
+
+/* Innermost generator whose body is running on this thread */
+static __thread ZacoGenerator* generator_current = NULL;
+
+static size_t zaco_page_size(void) {
+    long size = sysconf(_SC_PAGESIZE);
+    return size > 0 ? (size_t)size : 4096;
+}
+
+/* makecontext only passes int arguments: the generator pointer arrives split
+ * into two 32-bit halves */
+static void zaco_generator_trampoline(unsigned int hi, unsigned int lo) {
+    ZacoGenerator* gen = (ZacoGenerator*)(((uintptr_t)hi << 32) | (uintptr_t)lo);
+    gen->body(gen, gen->env);
+    gen->done = 1;
+    /* Returning resumes gen->caller through uc_link */
+}
+
+static void zaco_generator_throw_out(void* error) {
+    ZacoGenerator* gen = generator_current;
+    if (!gen) return;
+    gen->error = error;
+    gen->threw = 1;
+    gen->done = 1;
+    setcontext(&gen->caller);
+}
+
+void* zaco_generator_new(void* body, void* env) {
+    ZacoGenerator* gen = (ZacoGenerator*)zaco_raw_alloc(sizeof(ZacoGenerator));
+    if (!gen) {
+        fprintf(stderr, "zaco: out of memory (generator)\n");
+        exit(1);
+    }
+    memset(gen, 0, sizeof(ZacoGenerator));
+    gen->body = (void (*)(void*, void*))body;
+    gen->env = env;
+    return gen;
+}
+
+static void zaco_generator_start(ZacoGenerator* gen) {
+    size_t guard = zaco_page_size();
+    gen->stack_size = guard + GENERATOR_STACK_SIZE;
+    void* stack = mmap(NULL, gen->stack_size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    gen->try_stack = (jmp_buf*)malloc(MAX_TRY_DEPTH * sizeof(jmp_buf));
+    if (stack == MAP_FAILED || !gen->try_stack || mprotect(stack, guard, PROT_NONE) != 0) {
+        fprintf(stderr, "zaco: out of memory (generator stack)\n");
+        exit(1);
+    }
+    gen->stack = (char*)stack;
+    getcontext(&gen->body_ctx);
+    gen->body_ctx.uc_stack.ss_sp = gen->stack + guard;
+    gen->body_ctx.uc_stack.ss_size = GENERATOR_STACK_SIZE;
+    gen->body_ctx.uc_link = &gen->caller;
+    uintptr_t bits = (uintptr_t)gen;
+    makecontext(&gen->body_ctx, (void (*)(void))zaco_generator_trampoline, 2,
+        (unsigned int)(bits >> 32), (unsigned int)(bits & 0xffffffffu));
+}
+
+/* Resume the body and return its next result: an object { value, done } */
+void* zaco_generator_next(void* g) {
+    ZacoGenerator* gen = (ZacoGenerator*)g;
+    if (!gen->done) {
+        if (!gen->started) {
+            gen->started = 1;
+            zaco_generator_start(gen);
+        }
+        /* Falling off the end of the body leaves value undefined */
+        gen->value = 0;
+        ZacoGenerator* outer = generator_current;
+        jmp_buf* caller_try_stack = try_stack_override;
+        int caller_try_depth = try_depth;
+        generator_current = gen;
+        try_stack_override = gen->try_stack;
+        try_depth = gen->try_depth;
+        swapcontext(&gen->caller, &gen->body_ctx);
+        gen->try_depth = try_depth;
+        generator_current = outer;
+        try_stack_override = caller_try_stack;
+        try_depth = caller_try_depth;
+        gen->sent = 0;
+        if (gen->done && gen->stack) {
+            munmap(gen->stack, gen->stack_size);
+            free(gen->try_stack);
+            gen->stack = NULL;
+            gen->try_stack = NULL;
+        }
+        if (gen->threw) {
+            gen->threw = 0;
+            gen->value = 0;
+            zaco_throw(gen->error);
+        }
     } else {
-        fprintf(stderr, "%g", n);
+        /* A finished generator keeps answering { value: undefined, done: true } */
+        gen->value = 0;
     }
+    ZacoObject* result = (ZacoObject*)zaco_object_new();
+    zaco_object_set_raw(result, "value", gen->value);
+    zaco_object_set_raw(result, "done", (uint64_t)gen->done);
+    return result;
 }
 
-void zaco_console_warn_bool(int64_t b) {
-    fprintf(stderr, "%s", b ? "true" : "false");
+/* Value passed to the next next() call; ignored before the body starts */
+static void zaco_generator_send_raw(ZacoGenerator* gen, uint64_t bits) {
+    if (gen->started) gen->sent = bits;
 }
 
-/* ========== Timer Functions (setTimeout/setInterval) ========== */
+void zaco_generator_send_f64(void* g, double value) {
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    zaco_generator_send_raw((ZacoGenerator*)g, bits);
+}
 
-typedef struct {
-    void (*callback)(void*);
-    void* context;
-    int64_t delay_ms;
-    int is_interval;
-    volatile int cancelled;
-} TimerContext;
+void zaco_generator_send_i64(void* g, int64_t value) {
+    zaco_generator_send_raw((ZacoGenerator*)g, (uint64_t)value);
+}
 
-#define MAX_TIMERS 1024
-static TimerContext* timer_table[MAX_TIMERS];
-static int64_t next_timer_id = 1;
-static pthread_mutex_t timer_mutex = PTHREAD_MUTEX_INITIALIZER;
+void zaco_generator_send_ptr(void* g, void* value) {
+    zaco_generator_send_raw((ZacoGenerator*)g, (uint64_t)(uintptr_t)value);
+}
 
-static void* timer_thread_fn(void* arg) {
-    TimerContext* tc = (TimerContext*)arg;
-    do {
-        usleep((useconds_t)(tc->delay_ms * 1000));
-        if (!tc->cancelled) {
-            tc->callback(tc->context);
-        }
-    } while (tc->is_interval && !tc->cancelled);
-    return NULL;
+/* Called from the body: hand a value to next() and pause until resumed */
+static void zaco_generator_yield_raw(ZacoGenerator* gen, uint64_t bits) {
+    gen->value = bits;
+    swapcontext(&gen->body_ctx, &gen->caller);
 }
 
-int64_t zaco_set_timeout(void (*callback)(void*), void* context, int64_t delay_ms) {
-    pthread_mutex_lock(&timer_mutex);
-    int64_t id = next_timer_id++;
-    if (id >= MAX_TIMERS) {
-        pthread_mutex_unlock(&timer_mutex);
-        return -1;
-    }
-    TimerContext* tc = (TimerContext*)malloc(sizeof(TimerContext));
-    tc->callback = callback;
-    tc->context = context;
-    tc->delay_ms = delay_ms;
-    tc->is_interval = 0;
-    tc->cancelled = 0;
-    timer_table[id] = tc;
-    pthread_mutex_unlock(&timer_mutex);
+void zaco_generator_yield_f64(void* g, double value) {
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    zaco_generator_yield_raw((ZacoGenerator*)g, bits);
+}
 
-    pthread_t thread;
-    pthread_create(&thread, NULL, timer_thread_fn, tc);
-    pthread_detach(thread);
-    return id;
+void zaco_generator_yield_i64(void* g, int64_t value) {
+    zaco_generator_yield_raw((ZacoGenerator*)g, (uint64_t)value);
 }
 
-int64_t zaco_set_interval(void (*callback)(void*), void* context, int64_t delay_ms) {
-    pthread_mutex_lock(&timer_mutex);
+void zaco_generator_yield_ptr(void* g, void* value) {
+    zaco_generator_yield_raw((ZacoGenerator*)g, (uint64_t)(uintptr_t)value);
+}
+
+/* Result of the yield the body just resumed from */
+double zaco_generator_sent_f64(void* g) {
+    double value;
+    memcpy(&value, &((ZacoGenerator*)g)->sent, sizeof(value));
+    return value;
+}
+
+int64_t zaco_generator_sent_i64(void* g) {
+    return (int64_t)((ZacoGenerator*)g)->sent;
+}
+
+void* zaco_generator_sent_ptr(void* g) {
+    return (void*)(uintptr_t)((ZacoGenerator*)g)->sent;
+}
+
+/* `return v` in the body: v becomes the value of the final { done: true } result */
+void zaco_generator_return_f64(void* g, double value) {
+    memcpy(&((ZacoGenerator*)g)->value, &value, sizeof(value));
+}
+
+void zaco_generator_return_i64(void* g, int64_t value) {
+    ((ZacoGenerator*)g)->value = (uint64_t)value;
+}
+
+void zaco_generator_return_ptr(void* g, void* value) {
+    ((ZacoGenerator*)g)->value = (uint64_t)(uintptr_t)value;
+}
+
+/* ========== JSON Values ==========
+ * Parses JSON text into runtime values, as opposed to zaco_json_parse which
+ * only unquotes primitives. Each value comes back as its 8-byte payload:
+ * numbers as f64 bits, strings as zaco strings, true/false as i64 1/0, null
+ * as 0, arrays as inline arrays and objects as ZacoObjects. Code reading the
+ * result needs the static type to know which it is, like any other value.
+ */
+
+static void zaco_json_skip_ws(const char** p) {
+    while (**p && isspace((unsigned char)**p)) (*p)++;
+}
+
+static int zaco_json_parse_at(const char** p, uint64_t* out, int depth);
+
+static void zaco_json_put_utf8(char* buf, size_t* len, uint32_t cp) {
+    if (cp < 0x80) {
+        buf[(*len)++] = (char)cp;
+    } else if (cp < 0x800) {
+        buf[(*len)++] = (char)(0xC0 | (cp >> 6));
+        buf[(*len)++] = (char)(0x80 | (cp & 0x3F));
+    } else if (cp < 0x10000) {
+        buf[(*len)++] = (char)(0xE0 | (cp >> 12));
+        buf[(*len)++] = (char)(0x80 | ((cp >> 6) & 0x3F));
+        buf[(*len)++] = (char)(0x80 | (cp & 0x3F));
+    } else {
+        buf[(*len)++] = (char)(0xF0 | (cp >> 18));
+        buf[(*len)++] = (char)(0x80 | ((cp >> 12) & 0x3F));
+        buf[(*len)++] = (char)(0x80 | ((cp >> 6) & 0x3F));
+        buf[(*len)++] = (char)(0x80 | (cp & 0x3F));
+    }
+}
+
+static int zaco_json_hex4(const char* s, uint32_t* out) {
+    uint32_t v = 0;
+    for (int i = 0; i < 4; i++) {
+        char c = s[i];
+        v <<= 4;
+        if (c >= '0' && c <= '9') v |= (uint32_t)(c - '0');
+        else if (c >= 'a' && c <= 'f') v |= (uint32_t)(c - 'a' + 10);
+        else if (c >= 'A' && c <= 'F') v |= (uint32_t)(c - 'A' + 10);
+        else return -1;
+    }
+    *out = v;
+    return 0;
+}
+
+/* Parse a string literal at *p (on the opening quote) into a malloc'd buffer */
+static char* zaco_json_parse_string(const char** p) {
+    const char* s = *p + 1;
+    /* Escapes never decode to more bytes than they take up */
+    const char* end = s;
+    while (*end && *end != '"') end += (*end == '\\' && end[1]) ? 2 : 1;
+    if (*end != '"') return NULL;
+    char* buf = (char*)malloc((size_t)(end - s) + 1);
+    size_t len = 0;
+    while (s < end) {
+        if (*s != '\\') {
+            buf[len++] = *s++;
+            continue;
+        }
+        s++;
+        switch (*s++) {
+            case 'n': buf[len++] = '\n'; break;
+            case 't': buf[len++] = '\t'; break;
+            case 'r': buf[len++] = '\r'; break;
+            case 'b': buf[len++] = '\b'; break;
+            case 'f': buf[len++] = '\f'; break;
+            case '"': buf[len++] = '"'; break;
+            case '\\': buf[len++] = '\\'; break;
+            case '/': buf[len++] = '/'; break;
+            case 'u': {
+                uint32_t cp, lo;
+                if (s + 4 > end || zaco_json_hex4(s, &cp) != 0) { free(buf); return NULL; }
+                s += 4;
+                /* Surrogate pair */
+                if (cp >= 0xD800 && cp < 0xDC00 && s + 6 <= end && s[0] == '\\' && s[1] == 'u'
+                        && zaco_json_hex4(s + 2, &lo) == 0 && lo >= 0xDC00 && lo < 0xE000) {
+                    cp = 0x10000 + ((cp - 0xD800) << 10) + (lo - 0xDC00);
+                    s += 6;
+                }
+                zaco_json_put_utf8(buf, &len, cp);
+                break;
+            }
+            default: free(buf); return NULL;
+        }
+    }
+    buf[len] = '\0';
+    *p = end + 1;
+    return buf;
+}
+
+static int zaco_json_parse_array(const char** p, uint64_t* out, int depth) {
+    int64_t len = 0, cap = 8;
+    uint64_t* items = (uint64_t*)malloc(cap * sizeof(uint64_t));
+    (*p)++;
+    zaco_json_skip_ws(p);
+    if (**p == ']') {
+        (*p)++;
+    } else {
+        for (;;) {
+            if (len == cap) {
+                cap *= 2;
+                items = (uint64_t*)realloc(items, cap * sizeof(uint64_t));
+            }
+            if (zaco_json_parse_at(p, &items[len], depth + 1) != 0) { free(items); return -1; }
+            len++;
+            zaco_json_skip_ws(p);
+            if (**p == ',') { (*p)++; continue; }
+            if (**p == ']') { (*p)++; break; }
+            free(items);
+            return -1;
+        }
+    }
+    char* arr = (char*)zaco_alloc(8 + len * 8);
+    *(int64_t*)arr = len;
+    memcpy(arr + 8, items, len * 8);
+    free(items);
+    memcpy(out, &arr, sizeof(arr));
+    return 0;
+}
+
+static int zaco_json_parse_object(const char** p, uint64_t* out, int depth) {
+    void* obj = zaco_object_new();
+    (*p)++;
+    zaco_json_skip_ws(p);
+    if (**p == '}') {
+        (*p)++;
+    } else {
+        for (;;) {
+            zaco_json_skip_ws(p);
+            if (**p != '"') return -1;
+            char* key = zaco_json_parse_string(p);
+            if (!key) return -1;
+            zaco_json_skip_ws(p);
+            uint64_t value;
+            if (**p != ':') { free(key); return -1; }
+            (*p)++;
+            if (zaco_json_parse_at(p, &value, depth + 1) != 0) { free(key); return -1; }
+            zaco_object_set_raw((ZacoObject*)obj, key, value);
+            free(key);
+            zaco_json_skip_ws(p);
+            if (**p == ',') { (*p)++; continue; }
+            if (**p == '}') { (*p)++; break; }
+            return -1;
+        }
+    }
+    memcpy(out, &obj, sizeof(obj));
+    return 0;
+}
+
+static int zaco_json_parse_at(const char** p, uint64_t* out, int depth) {
+    if (depth > 512) return -1;
+    zaco_json_skip_ws(p);
+    const char* s = *p;
+    if (*s == '{') return zaco_json_parse_object(p, out, depth);
+    if (*s == '[') return zaco_json_parse_array(p, out, depth);
+    if (*s == '"') {
+        char* buf = zaco_json_parse_string(p);
+        if (!buf) return -1;
+        void* str = zaco_str_new(buf);
+        free(buf);
+        memcpy(out, &str, sizeof(str));
+        return 0;
+    }
+    if (strncmp(s, "true", 4) == 0) { *out = 1; *p = s + 4; return 0; }
+    if (strncmp(s, "false", 5) == 0) { *out = 0; *p = s + 5; return 0; }
+    if (strncmp(s, "null", 4) == 0) { *out = 0; *p = s + 4; return 0; }
+    if (*s == '-' || isdigit((unsigned char)*s)) {
+        char* end;
+        double n = strtod(s, &end);
+        if (end == s) return -1;
+        memcpy(out, &n, sizeof(n));
+        *p = end;
+        return 0;
+    }
+    return -1;
+}
+
+/* Parse a complete JSON document into *out. Returns 0 on success, -1 if the
+ * text is not valid JSON; values built before the error are not freed. */
+int64_t zaco_json_parse_value(const char* text, uint64_t* out) {
+    if (!text) return -1;
+    const char* p = text;
+    if (zaco_json_parse_at(&p, out, 0) != 0) return -1;
+    zaco_json_skip_ws(&p);
+    return *p == '\0' ? 0 : -1;
+}
+
+/* ========== Missing Console Warn Functions ========== */
+
+void zaco_console_warn_f64(double n) {
+    zaco_console_write_f64(stderr, n);
+}
+
+void zaco_console_warn_bool(int64_t b) {
+    zaco_console_write(stderr, b ? "true" : "false");
+}
+
+/* ========== Timer Functions (setTimeout/setInterval) ========== */
+
+typedef struct {
+    void (*callback)(void*);
+    void* context;
+    int64_t delay_ms;
+    int is_interval;
+    int cancelled;        /* accessed with __atomic builtins */
+} TimerContext;
+
+#define MAX_TIMERS 1024
+static TimerContext* timer_table[MAX_TIMERS];
+static int64_t next_timer_id = 1;
+static pthread_mutex_t timer_mutex = PTHREAD_MUTEX_INITIALIZER;
+
+static void* timer_thread_fn(void* arg) {
+    TimerContext* tc = (TimerContext*)arg;
+    do {
+        usleep((useconds_t)(tc->delay_ms * 1000));
+        if (!__atomic_load_n(&tc->cancelled, __ATOMIC_ACQUIRE)) {
+            tc->callback(tc->context);
+        }
+    } while (tc->is_interval && !__atomic_load_n(&tc->cancelled, __ATOMIC_ACQUIRE));
+    return NULL;
+}
+
+int64_t zaco_set_timeout(void (*callback)(void*), void* context, int64_t delay_ms) {
+    pthread_mutex_lock(&timer_mutex);
+    int64_t id = next_timer_id++;
+    if (id >= MAX_TIMERS) {
+        pthread_mutex_unlock(&timer_mutex);
+        return -1;
+    }
+    TimerContext* tc = (TimerContext*)malloc(sizeof(TimerContext));
+    tc->callback = callback;
+    tc->context = context;
+    tc->delay_ms = delay_ms;
+    tc->is_interval = 0;
+    tc->cancelled = 0;
+    timer_table[id] = tc;
+    pthread_mutex_unlock(&timer_mutex);
+
+    pthread_t thread;
+    pthread_create(&thread, NULL, timer_thread_fn, tc);
+    pthread_detach(thread);
+    return id;
+}
+
+int64_t zaco_set_interval(void (*callback)(void*), void* context, int64_t delay_ms) {
+    pthread_mutex_lock(&timer_mutex);
     int64_t id = next_timer_id++;
     if (id >= MAX_TIMERS) {
         pthread_mutex_unlock(&timer_mutex);
@@ -1300,7 +2750,7 @@ int64_t zaco_set_interval(void (*callback)(void*), void* context, int64_t delay_
 void zaco_clear_timeout(int64_t timer_id) {
     pthread_mutex_lock(&timer_mutex);
     if (timer_id > 0 && timer_id < MAX_TIMERS && timer_table[timer_id]) {
-        timer_table[timer_id]->cancelled = 1;
+        __atomic_store_n(&timer_table[timer_id]->cancelled, 1, __ATOMIC_RELEASE);
     }
     pthread_mutex_unlock(&timer_mutex);
 }
@@ -1308,3 +2758,1619 @@ void zaco_clear_timeout(int64_t timer_id) {
 void zaco_clear_interval(int64_t timer_id) {
     zaco_clear_timeout(timer_id);
 }
+
+/* ========== Structured Clone ==========
+ * Deep copies a value, for structuredClone() and for another thread.
+ * Values carry no runtime type tags, so the compiler passes a shape string
+ * describing the static type:
+ *   n number   b boolean   s string   f function (cannot be cloned)
+ *   [T         inline array of T (boolean arrays have 1-byte elements)
+ *   (T,U,...)  tuple: inline array whose i-th element has the i-th shape
+ *   {k:T,...}  object; keys the shape does not list are copied as raw bits
+ *   ^N         the object shape N levels out (0 = innermost enclosing
+ *              object), for recursive types like `interface Node { next: Node }`
+ * Objects and arrays reached twice are copied once, so shared references
+ * and cycles in the source are preserved in the copy.
+ */
+
+static const char* zaco_shape_skip(const char* shape) {
+    switch (*shape) {
+    case '\0':
+        return shape;
+    case '[':
+        return zaco_shape_skip(shape + 1);
+    case '(':
+        shape++;
+        while (*shape && *shape != ')') {
+            shape = zaco_shape_skip(shape);
+            if (*shape == ',') shape++;
+        }
+        return *shape ? shape + 1 : shape;
+    case '^':
+        shape++;
+        while (*shape >= '0' && *shape <= '9') shape++;
+        return shape;
+    case '{': {
+        int depth = 0;
+        do {
+            if (*shape == '{') depth++;
+            else if (*shape == '}') depth--;
+            shape++;
+        } while (depth > 0 && *shape);
+        return shape;
+    }
+    default:
+        return shape + 1;
+    }
+}
+
+/* Shape of `key` inside an object shape, or NULL if it is not listed */
+static const char* zaco_shape_field(const char* shape, const char* key) {
+    size_t key_len = strlen(key);
+    const char* p = shape + 1;
+    while (*p && *p != '}') {
+        const char* colon = strchr(p, ':');
+        if (!colon) return NULL;
+        if ((size_t)(colon - p) == key_len && strncmp(p, key, key_len) == 0) {
+            return colon + 1;
+        }
+        p = zaco_shape_skip(colon + 1);
+        if (*p == ',') p++;
+    }
+    return NULL;
+}
+
+/* Shape of element i of a tuple shape, or NULL past its end */
+static const char* zaco_shape_tuple_element(const char* shape, int64_t i) {
+    const char* p = shape + 1;
+    for (int64_t k = 0; k < i && *p && *p != ')'; k++) {
+        p = zaco_shape_skip(p);
+        if (*p == ',') p++;
+    }
+    return *p && *p != ')' ? p : NULL;
+}
+
+/* Object shapes enclosing the value being cloned, innermost first */
+typedef struct ZacoShapeFrame {
+    const char* shape;
+    const struct ZacoShapeFrame* outer;
+} ZacoShapeFrame;
+
+/* Source pointer → copy, so each object or array is copied once */
+typedef struct {
+    void** keys;
+    void** values;
+    size_t capacity;
+    size_t count;
+    int found_function;
+} ZacoCloneState;
+
+static size_t zaco_clone_slot(ZacoCloneState* state, void* key) {
+    size_t hash = ((uintptr_t)key >> 4) * 0x9E3779B97F4A7C15ull;
+    size_t i = hash & (state->capacity - 1);
+    while (state->keys[i] && state->keys[i] != key) {
+        i = (i + 1) & (state->capacity - 1);
+    }
+    return i;
+}
+
+static void zaco_clone_remember(ZacoCloneState* state, void* src, void* copy) {
+    if (state->count * 2 >= state->capacity) {
+        void** old_keys = state->keys;
+        void** old_values = state->values;
+        size_t old_capacity = state->capacity;
+        state->capacity = old_capacity ? old_capacity * 2 : 64;
+        state->keys = (void**)calloc(state->capacity, sizeof(void*));
+        state->values = (void**)calloc(state->capacity, sizeof(void*));
+        for (size_t i = 0; i < old_capacity; i++) {
+            if (!old_keys[i]) continue;
+            size_t slot = zaco_clone_slot(state, old_keys[i]);
+            state->keys[slot] = old_keys[i];
+            state->values[slot] = old_values[i];
+        }
+        free(old_keys);
+        free(old_values);
+    }
+    size_t slot = zaco_clone_slot(state, src);
+    state->keys[slot] = src;
+    state->values[slot] = copy;
+    state->count++;
+}
+
+static void* zaco_clone_lookup(ZacoCloneState* state, void* src) {
+    if (state->capacity == 0) return NULL;
+    size_t slot = zaco_clone_slot(state, src);
+    return state->keys[slot] ? state->values[slot] : NULL;
+}
+
+static uint64_t zaco_clone_bits(ZacoCloneState* state, uint64_t bits, const char* shape,
+                                const ZacoShapeFrame* outer);
+
+static void* zaco_clone_array(ZacoCloneState* state, void* arr, const char* elem_shape,
+                              const ZacoShapeFrame* outer) {
+    if (!arr) return NULL;
+    void* seen = zaco_clone_lookup(state, arr);
+    if (seen) return seen;
+    int64_t len = *(int64_t*)arr;
+    int64_t elem_size = *elem_shape == 'b' ? 1 : 8;
+    char* copy = (char*)zaco_alloc(8 + len * elem_size);
+    memcpy(copy, arr, 8 + len * elem_size);
+    zaco_clone_remember(state, arr, copy);
+    if (elem_size == 8) {
+        for (int64_t i = 0; i < len; i++) {
+            uint64_t* slot = (uint64_t*)(copy + 8 + i * 8);
+            *slot = zaco_clone_bits(state, *slot, elem_shape, outer);
+        }
+    }
+    return copy;
+}
+
+static void* zaco_clone_tuple(ZacoCloneState* state, void* arr, const char* shape,
+                              const ZacoShapeFrame* outer) {
+    if (!arr) return NULL;
+    void* seen = zaco_clone_lookup(state, arr);
+    if (seen) return seen;
+    int64_t len = *(int64_t*)arr;
+    char* copy = (char*)zaco_alloc(8 + len * 8);
+    memcpy(copy, arr, 8 + len * 8);
+    zaco_clone_remember(state, arr, copy);
+    for (int64_t i = 0; i < len; i++) {
+        const char* elem_shape = zaco_shape_tuple_element(shape, i);
+        uint64_t* slot = (uint64_t*)(copy + 8 + i * 8);
+        if (elem_shape) *slot = zaco_clone_bits(state, *slot, elem_shape, outer);
+    }
+    return copy;
+}
+
+static void* zaco_clone_object(ZacoCloneState* state, void* o, const char* shape,
+                               const ZacoShapeFrame* outer) {
+    if (!o) return NULL;
+    void* seen = zaco_clone_lookup(state, o);
+    if (seen) return seen;
+    ZacoObject* obj = (ZacoObject*)o;
+    ZacoObject* copy = (ZacoObject*)zaco_object_new();
+    zaco_clone_remember(state, o, copy);
+    ZacoShapeFrame frame = { shape, outer };
+    for (int64_t i = 0; i < obj->count; i++) {
+        const char* key = obj->entries[i].key;
+        const char* field = zaco_shape_field(shape, key);
+        uint64_t bits = zaco_object_entry_bits(obj, i, field);
+        zaco_object_set_raw(copy, key, field ? zaco_clone_bits(state, bits, field, &frame) : bits);
+    }
+    return copy;
+}
+
+static uint64_t zaco_clone_bits(ZacoCloneState* state, uint64_t bits, const char* shape,
+                                const ZacoShapeFrame* outer) {
+    void* ptr;
+    memcpy(&ptr, &bits, sizeof(ptr));
+    switch (*shape) {
+    case 's':
+        ptr = ptr ? zaco_str_new((const char*)ptr) : NULL;
+        break;
+    case '[':
+        ptr = zaco_clone_array(state, ptr, shape + 1, outer);
+        break;
+    case '(':
+        ptr = zaco_clone_tuple(state, ptr, shape, outer);
+        break;
+    case '{':
+        ptr = zaco_clone_object(state, ptr, shape, outer);
+        break;
+    case '^': {
+        long levels = strtol(shape + 1, NULL, 10);
+        while (levels-- > 0 && outer) outer = outer->outer;
+        if (!outer) return bits;
+        ptr = zaco_clone_object(state, ptr, outer->shape, outer->outer);
+        break;
+    }
+    case 'f':
+        if (ptr) state->found_function = 1;
+        return bits;
+    default:
+        return bits;
+    }
+    memcpy(&bits, &ptr, sizeof(bits));
+    return bits;
+}
+
+void* zaco_structured_clone(void* value, const char* shape) {
+    ZacoCloneState state = { NULL, NULL, 0, 0, 0 };
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    bits = zaco_clone_bits(&state, bits, shape, NULL);
+    memcpy(&value, &bits, sizeof(value));
+    free(state.keys);
+    free(state.values);
+    if (state.found_function) {
+        zaco_throw((void*)zaco_str_new("DataCloneError: a function could not be cloned"));
+    }
+    return value;
+}
+
+/* ========== Object Statics ==========
+ * Object.keys/values/entries/fromEntries/assign/freeze. Values are read
+ * through getters, guided by the object's shape (see Structured Clone);
+ * an entry is a `[key, value]` inline array.
+ */
+
+void* zaco_object_keys(void* o) {
+    ZacoObject* obj = (ZacoObject*)o;
+    int64_t count = obj ? obj->count : 0;
+    char* keys = (char*)zaco_alloc(8 + count * 8);
+    *(int64_t*)keys = count;
+    for (int64_t i = 0; i < count; i++) {
+        ((void**)(keys + 8))[i] = zaco_str_new(obj->entries[i].key);
+    }
+    return keys;
+}
+
+void* zaco_object_values(void* o, const char* shape) {
+    ZacoObject* obj = (ZacoObject*)o;
+    int64_t count = obj ? obj->count : 0;
+    char* values = (char*)zaco_alloc(8 + count * 8);
+    *(int64_t*)values = count;
+    for (int64_t i = 0; i < count; i++) {
+        const char* field = zaco_shape_field(shape, obj->entries[i].key);
+        ((uint64_t*)(values + 8))[i] = zaco_object_entry_bits(obj, i, field);
+    }
+    return values;
+}
+
+void* zaco_object_entries(void* o, const char* shape) {
+    ZacoObject* obj = (ZacoObject*)o;
+    int64_t count = obj ? obj->count : 0;
+    char* entries = (char*)zaco_alloc(8 + count * 8);
+    *(int64_t*)entries = count;
+    for (int64_t i = 0; i < count; i++) {
+        const char* field = zaco_shape_field(shape, obj->entries[i].key);
+        char* pair = (char*)zaco_alloc(8 + 2 * 8);
+        *(int64_t*)pair = 2;
+        ((void**)(pair + 8))[0] = zaco_str_new(obj->entries[i].key);
+        ((uint64_t*)(pair + 8))[1] = zaco_object_entry_bits(obj, i, field);
+        ((void**)(entries + 8))[i] = pair;
+    }
+    return entries;
+}
+
+void* zaco_object_from_entries(void* pairs) {
+    ZacoObject* obj = (ZacoObject*)zaco_object_new();
+    int64_t count = pairs ? *(int64_t*)pairs : 0;
+    for (int64_t i = 0; i < count; i++) {
+        char* pair = ((char**)((char*)pairs + 8))[i];
+        if (!pair || *(int64_t*)pair < 1) continue;
+        const char* key = ((const char**)(pair + 8))[0];
+        uint64_t bits = *(int64_t*)pair > 1 ? ((uint64_t*)(pair + 8))[1] : 0;
+        if (key) zaco_object_set_raw(obj, key, bits);
+    }
+    return obj;
+}
+
+/* Copy the properties of `source` (of shape `shape`) onto `target`, as
+ * assignments: the target's setters run and a frozen target throws */
+void* zaco_object_assign(void* target, void* source, const char* shape) {
+    ZacoObject* dst = (ZacoObject*)target;
+    ZacoObject* src = (ZacoObject*)source;
+    if (!dst || !src) return target;
+    for (int64_t i = 0; i < src->count; i++) {
+        const char* key = src->entries[i].key;
+        const char* field = zaco_shape_field(shape, key);
+        uint64_t bits = zaco_object_entry_bits(src, i, field);
+        ZacoObjEntry* accessor = zaco_object_accessor(dst, key);
+        if (accessor) {
+            if (!accessor->setter) continue;
+            if (field && *field == 'n') {
+                double value;
+                memcpy(&value, &bits, sizeof(value));
+                ((void (*)(void*, double))accessor->setter)(dst, value);
+            } else if (field && *field == 'b') {
+                ((void (*)(void*, uint8_t))accessor->setter)(dst, (uint8_t)bits);
+            } else {
+                void* value;
+                memcpy(&value, &bits, sizeof(value));
+                ((void (*)(void*, void*))accessor->setter)(dst, value);
+            }
+            continue;
+        }
+        zaco_object_check_writable(dst, key);
+        zaco_object_set_raw(dst, key, bits);
+    }
+    return target;
+}
+
+void* zaco_object_freeze(void* o) {
+    if (o) ((ZacoObject*)o)->frozen = 1;
+    return o;
+}
+
+/* ========== Deep Equality ==========
+ * Compares two values structurally, guided by the same shape strings as
+ * structured clone, with the semantics of Node's util.isDeepStrictEqual:
+ * numbers compare like Object.is (NaN equals NaN, 0 and -0 differ), strings
+ * by content, arrays element-wise and objects by their key sets. Values the
+ * shape does not describe (class instances, functions, `any`) compare by
+ * identity. Recursion follows the shape, which is finite, so cyclic values
+ * cannot make the comparison loop.
+ */
+
+static int zaco_equal_bits(uint64_t a, uint64_t b, const char* shape);
+
+static int zaco_equal_arrays(void* a, void* b, const char* elem_shape) {
+    if (a == b) return 1;
+    if (!a || !b) return 0;
+    int64_t len = *(int64_t*)a;
+    if (len != *(int64_t*)b) return 0;
+    if (*elem_shape == 'b') return memcmp((char*)a + 8, (char*)b + 8, len) == 0;
+    for (int64_t i = 0; i < len; i++) {
+        uint64_t x = *(uint64_t*)((char*)a + 8 + i * 8);
+        uint64_t y = *(uint64_t*)((char*)b + 8 + i * 8);
+        if (!zaco_equal_bits(x, y, elem_shape)) return 0;
+    }
+    return 1;
+}
+
+static int zaco_equal_tuples(void* a, void* b, const char* shape) {
+    if (a == b) return 1;
+    if (!a || !b) return 0;
+    int64_t len = *(int64_t*)a;
+    if (len != *(int64_t*)b) return 0;
+    for (int64_t i = 0; i < len; i++) {
+        const char* elem_shape = zaco_shape_tuple_element(shape, i);
+        uint64_t x = *(uint64_t*)((char*)a + 8 + i * 8);
+        uint64_t y = *(uint64_t*)((char*)b + 8 + i * 8);
+        if (elem_shape ? !zaco_equal_bits(x, y, elem_shape) : x != y) return 0;
+    }
+    return 1;
+}
+
+static int zaco_equal_objects(void* a, void* b, const char* shape) {
+    if (a == b) return 1;
+    if (!a || !b) return 0;
+    ZacoObject* x = (ZacoObject*)a;
+    ZacoObject* y = (ZacoObject*)b;
+    if (x->count != y->count) return 0;
+    for (int64_t i = 0; i < x->count; i++) {
+        const char* key = x->entries[i].key;
+        if (zaco_object_find(y, key) < 0) return 0;
+        const char* field = zaco_shape_field(shape, key);
+        uint64_t other = zaco_object_get_raw(y, key);
+        if (field ? !zaco_equal_bits(x->entries[i].value_bits, other, field) : x->entries[i].value_bits != other) {
+            return 0;
+        }
+    }
+    return 1;
+}
+
+static int zaco_equal_bits(uint64_t a, uint64_t b, const char* shape) {
+    void* x;
+    void* y;
+    memcpy(&x, &a, sizeof(x));
+    memcpy(&y, &b, sizeof(y));
+    switch (*shape) {
+    case 'n': {
+        double m, n;
+        memcpy(&m, &a, sizeof(m));
+        memcpy(&n, &b, sizeof(n));
+        return a == b || (m != m && n != n);
+    }
+    case 'b':
+        return (a & 0xff) == (b & 0xff);
+    case 's':
+        return zaco_str_eq(x, y) != 0;
+    case '[':
+        return zaco_equal_arrays(x, y, shape + 1);
+    case '(':
+        return zaco_equal_tuples(x, y, shape);
+    case '{':
+        return zaco_equal_objects(x, y, shape);
+    default:
+        return a == b;
+    }
+}
+
+/* Object.is(a, b) for numbers */
+int64_t zaco_same_value_f64(double a, double b) {
+    uint64_t x, y;
+    memcpy(&x, &a, sizeof(x));
+    memcpy(&y, &b, sizeof(y));
+    return zaco_equal_bits(x, y, "n");
+}
+
+/* isDeepStrictEqual(a, b) for pointer values (arrays, objects, strings) */
+int64_t zaco_deep_equal(void* a, void* b, const char* shape) {
+    uint64_t x, y;
+    memcpy(&x, &a, sizeof(x));
+    memcpy(&y, &b, sizeof(y));
+    return zaco_equal_bits(x, y, shape);
+}
+
+/* ========== Console Extras ==========
+ * console.group/groupEnd (indentation, see zaco_console_write),
+ * console.time/timeEnd (label → start time) and console.table. The table
+ * is rendered from the dynamic value guided by a shape string, as for
+ * structured clone: an array or object of rows, each row an object (one
+ * column per field), an array (one column per index) or a primitive (the
+ * "Values" column). Columns appear in the order rows first use them.
+ */
+
+void zaco_console_group(void) {
+    pthread_mutex_lock(&console_mutex);
+    zaco_console_depth++;
+    pthread_mutex_unlock(&console_mutex);
+}
+
+void zaco_console_group_end(void) {
+    pthread_mutex_lock(&console_mutex);
+    if (zaco_console_depth > 0) zaco_console_depth--;
+    pthread_mutex_unlock(&console_mutex);
+}
+
+typedef struct {
+    char* label;
+    struct timespec start;
+} ZacoConsoleTimer;
+
+static ZacoConsoleTimer* zaco_console_timers = NULL;
+static int64_t zaco_console_timer_count = 0;
+
+/* Caller holds console_mutex */
+static int64_t zaco_console_timer_find(const char* label) {
+    for (int64_t i = 0; i < zaco_console_timer_count; i++) {
+        if (strcmp(zaco_console_timers[i].label, label) == 0) return i;
+    }
+    return -1;
+}
+
+void zaco_console_time(void* label) {
+    const char* name = label ? (const char*)label : "default";
+    pthread_mutex_lock(&console_mutex);
+    if (zaco_console_timer_find(name) >= 0) {
+        pthread_mutex_unlock(&console_mutex);
+        fprintf(stderr, "Warning: Label '%s' already exists for console.time()\n", name);
+        return;
+    }
+    zaco_console_timers = (ZacoConsoleTimer*)realloc(zaco_console_timers,
+        (zaco_console_timer_count + 1) * sizeof(ZacoConsoleTimer));
+    ZacoConsoleTimer* timer = &zaco_console_timers[zaco_console_timer_count++];
+    timer->label = strdup(name);
+    clock_gettime(CLOCK_MONOTONIC, &timer->start);
+    pthread_mutex_unlock(&console_mutex);
+}
+
+void zaco_console_time_end(void* label) {
+    const char* name = label ? (const char*)label : "default";
+    struct timespec now;
+    clock_gettime(CLOCK_MONOTONIC, &now);
+    pthread_mutex_lock(&console_mutex);
+    int64_t i = zaco_console_timer_find(name);
+    if (i < 0) {
+        pthread_mutex_unlock(&console_mutex);
+        fprintf(stderr, "Warning: No such label '%s' for console.timeEnd()\n", name);
+        return;
+    }
+    ZacoConsoleTimer timer = zaco_console_timers[i];
+    zaco_console_timers[i] = zaco_console_timers[--zaco_console_timer_count];
+    pthread_mutex_unlock(&console_mutex);
+    double ms = (now.tv_sec - timer.start.tv_sec) * 1000.0 + (now.tv_nsec - timer.start.tv_nsec) / 1e6;
+    char buf[512];
+    snprintf(buf, sizeof(buf), "%s: %.3fms\n", timer.label, ms);
+    zaco_console_write(stdout, buf);
+    free(timer.label);
+}
+
+/* Text of one table cell; the caller frees it */
+static char* zaco_table_cell(uint64_t bits, const char* shape) {
+    char buf[64];
+    void* ptr;
+    memcpy(&ptr, &bits, sizeof(ptr));
+    switch (*shape) {
+    case 'n': {
+        double n;
+        memcpy(&n, &bits, sizeof(n));
+        zaco_format_f64(buf, sizeof(buf), n);
+        return strdup(buf);
+    }
+    case 'b':
+        return strdup(bits ? "true" : "false");
+    case 's': {
+        if (!ptr) return strdup("null");
+        size_t len = strlen((const char*)ptr);
+        char* text = (char*)malloc(len + 3);
+        snprintf(text, len + 3, "'%s'", (const char*)ptr);
+        return text;
+    }
+    case '[':
+    case '(':
+        return strdup(ptr ? "[Array]" : "null");
+    default:
+        return strdup(ptr ? "[Object]" : "null");
+    }
+}
+
+/* Display width: UTF-8 code points */
+static size_t zaco_table_width(const char* text) {
+    size_t width = 0;
+    for (; *text; text++) {
+        if (((unsigned char)*text & 0xC0) != 0x80) width++;
+    }
+    return width;
+}
+
+typedef struct {
+    char** columns;
+    int64_t column_count;
+    char** cells; /* row-major, rows * column_capacity; NULL = empty */
+    int64_t column_capacity;
+    int64_t row_count;
+} ZacoTable;
+
+static int64_t zaco_table_column(ZacoTable* table, const char* name) {
+    for (int64_t i = 0; i < table->column_count; i++) {
+        if (strcmp(table->columns[i], name) == 0) return i;
+    }
+    if (table->column_count == table->column_capacity) {
+        int64_t capacity = table->column_capacity ? table->column_capacity * 2 : 8;
+        char** cells = (char**)calloc(table->row_count * capacity, sizeof(char*));
+        for (int64_t r = 0; r < table->row_count; r++) {
+            for (int64_t c = 0; c < table->column_count; c++) {
+                cells[r * capacity + c] = table->cells[r * table->column_capacity + c];
+            }
+        }
+        free(table->cells);
+        table->cells = cells;
+        table->columns = (char**)realloc(table->columns, capacity * sizeof(char*));
+        table->column_capacity = capacity;
+    }
+    table->columns[table->column_count] = strdup(name);
+    return table->column_count++;
+}
+
+static void zaco_table_row(ZacoTable* table, int64_t row, uint64_t bits, const char* shape) {
+    void* ptr;
+    memcpy(&ptr, &bits, sizeof(ptr));
+    if (*shape == '{' && ptr) {
+        ZacoObject* obj = (ZacoObject*)ptr;
+        for (int64_t i = 0; i < obj->count; i++) {
+            const char* field = zaco_shape_field(shape, obj->entries[i].key);
+            if (!field) continue;
+            int64_t col = zaco_table_column(table, obj->entries[i].key);
+            table->cells[row * table->column_capacity + col] = zaco_table_cell(obj->entries[i].value_bits, field);
+        }
+    } else if (*shape == '[' && ptr) {
+        int64_t len = *(int64_t*)ptr;
+        for (int64_t i = 0; i < len; i++) {
+            char name[32];
+            snprintf(name, sizeof(name), "%lld", (long long)i);
+            int64_t col = zaco_table_column(table, name);
+            uint64_t elem = 0;
+            if (shape[1] == 'b') elem = ((uint8_t*)ptr)[8 + i];
+            else memcpy(&elem, (char*)ptr + 8 + i * 8, 8);
+            table->cells[row * table->column_capacity + col] = zaco_table_cell(elem, shape + 1);
+        }
+    }
+}
+
+static void zaco_table_rule(char* line, const size_t* widths, int64_t count,
+                            const char* left, const char* mid, const char* right) {
+    strcat(line, left);
+    for (int64_t c = 0; c < count; c++) {
+        for (size_t i = 0; i < widths[c] + 2; i++) strcat(line, "─");
+        strcat(line, c + 1 < count ? mid : right);
+    }
+    strcat(line, "\n");
+}
+
+static void zaco_table_line(char* line, const size_t* widths, char** texts, int64_t count) {
+    strcat(line, "│");
+    for (int64_t c = 0; c < count; c++) {
+        const char* text = texts[c] ? texts[c] : "";
+        strcat(line, " ");
+        strcat(line, text);
+        for (size_t i = zaco_table_width(text); i < widths[c] + 1; i++) strcat(line, " ");
+        strcat(line, "│");
+    }
+    strcat(line, "\n");
+}
+
+void zaco_console_table(void* data, const char* shape) {
+    if (!data || (*shape != '[' && *shape != '{')) {
+        uint64_t bits;
+        memcpy(&bits, &data, sizeof(bits));
+        char* text = zaco_table_cell(bits, shape);
+        zaco_console_write(stdout, text);
+        zaco_console_write(stdout, "\n");
+        free(text);
+        return;
+    }
+
+    /* Rows: array elements (indexed 0..n-1) or object entries (by key) */
+    int64_t row_count;
+    ZacoObject* obj = NULL;
+    if (*shape == '[') {
+        row_count = *(int64_t*)data;
+    } else {
+        obj = (ZacoObject*)data;
+        row_count = obj->count;
+    }
+    ZacoTable table = { NULL, 0, NULL, 0, row_count };
+    zaco_table_column(&table, "(index)");
+    char** values = (char**)calloc(row_count ? row_count : 1, sizeof(char*));
+    int has_values = 0;
+
+    for (int64_t r = 0; r < row_count; r++) {
+        char index[32];
+        const char* row_shape;
+        uint64_t bits = 0;
+        if (obj) {
+            table.cells[r * table.column_capacity] = strdup(obj->entries[r].key);
+            row_shape = zaco_shape_field(shape, obj->entries[r].key);
+            bits = obj->entries[r].value_bits;
+            if (!row_shape) row_shape = "p";
+        } else {
+            snprintf(index, sizeof(index), "%lld", (long long)r);
+            table.cells[r * table.column_capacity] = strdup(index);
+            row_shape = shape + 1;
+            if (*row_shape == 'b') bits = ((uint8_t*)data)[8 + r];
+            else memcpy(&bits, (char*)data + 8 + r * 8, 8);
+        }
+        if (*row_shape == '{' || *row_shape == '[') {
+            zaco_table_row(&table, r, bits, row_shape);
+        } else {
+            values[r] = zaco_table_cell(bits, row_shape);
+            has_values = 1;
+        }
+    }
+    /* Primitive rows go in a trailing "Values" column */
+    if (has_values) {
+        int64_t col = zaco_table_column(&table, "Values");
+        for (int64_t r = 0; r < row_count; r++) {
+            table.cells[r * table.column_capacity + col] = values[r];
+        }
+    }
+    free(values);
+
+    int64_t cols = table.column_count;
+    size_t* widths = (size_t*)calloc(cols, sizeof(size_t));
+    size_t line_size = 16;
+    for (int64_t c = 0; c < cols; c++) {
+        widths[c] = zaco_table_width(table.columns[c]);
+        for (int64_t r = 0; r < row_count; r++) {
+            const char* cell = table.cells[r * table.column_capacity + c];
+            if (cell && zaco_table_width(cell) > widths[c]) widths[c] = zaco_table_width(cell);
+        }
+        /* Box-drawing characters are 3 bytes in UTF-8; cells may be wider */
+        line_size += (widths[c] + 3) * 3;
+        for (int64_t r = 0; r < row_count; r++) {
+            const char* cell = table.cells[r * table.column_capacity + c];
+            if (cell) line_size += strlen(cell);
+        }
+        line_size += strlen(table.columns[c]);
+    }
+
+    char* line = (char*)malloc(line_size);
+    line[0] = '\0';
+    zaco_table_rule(line, widths, cols, "┌", "┬", "┐");
+    zaco_console_write(stdout, line);
+    line[0] = '\0';
+    zaco_table_line(line, widths, table.columns, cols);
+    zaco_console_write(stdout, line);
+    line[0] = '\0';
+    zaco_table_rule(line, widths, cols, "├", "┼", "┤");
+    zaco_console_write(stdout, line);
+    for (int64_t r = 0; r < row_count; r++) {
+        line[0] = '\0';
+        zaco_table_line(line, widths, &table.cells[r * table.column_capacity], cols);
+        zaco_console_write(stdout, line);
+    }
+    line[0] = '\0';
+    zaco_table_rule(line, widths, cols, "└", "┴", "┘");
+    zaco_console_write(stdout, line);
+    free(line);
+
+    for (int64_t i = 0; i < row_count * table.column_capacity; i++) free(table.cells[i]);
+    for (int64_t c = 0; c < cols; c++) free(table.columns[c]);
+    free(table.cells);
+    free(table.columns);
+    free(widths);
+}
+
+/* ========== util.styleText ==========
+ * ANSI styling like Node's util.styleText(format, text). Escape codes are
+ * only emitted when stdout is a terminal and NO_COLOR is unset or empty;
+ * FORCE_COLOR (non-empty, not "0") emits them regardless.
+ */
+
+typedef struct {
+    const char* name;
+    int open;
+    int close;
+} ZacoTextStyle;
+
+static const ZacoTextStyle zaco_text_styles[] = {
+    { "reset", 0, 0 }, { "bold", 1, 22 }, { "dim", 2, 22 }, { "italic", 3, 23 },
+    { "underline", 4, 24 }, { "inverse", 7, 27 }, { "hidden", 8, 28 }, { "strikethrough", 9, 29 },
+    { "black", 30, 39 }, { "red", 31, 39 }, { "green", 32, 39 }, { "yellow", 33, 39 },
+    { "blue", 34, 39 }, { "magenta", 35, 39 }, { "cyan", 36, 39 }, { "white", 37, 39 },
+    { "gray", 90, 39 }, { "grey", 90, 39 },
+    { "bgBlack", 40, 49 }, { "bgRed", 41, 49 }, { "bgGreen", 42, 49 }, { "bgYellow", 43, 49 },
+    { "bgBlue", 44, 49 }, { "bgMagenta", 45, 49 }, { "bgCyan", 46, 49 }, { "bgWhite", 47, 49 },
+};
+
+static int zaco_colors_enabled(void) {
+    const char* force = getenv("FORCE_COLOR");
+    if (force && *force && strcmp(force, "0") != 0) return 1;
+    const char* no_color = getenv("NO_COLOR");
+    if (no_color && *no_color) return 0;
+    return isatty(fileno(stdout));
+}
+
+void* zaco_style_text(void* format, void* text) {
+    const char* name = format ? (const char*)format : "";
+    const ZacoTextStyle* style = NULL;
+    for (size_t i = 0; i < sizeof(zaco_text_styles) / sizeof(zaco_text_styles[0]); i++) {
+        if (strcmp(zaco_text_styles[i].name, name) == 0) style = &zaco_text_styles[i];
+    }
+    if (!style) {
+        const char* fmt = "TypeError: styleText: unknown format '%s'";
+        size_t len = strlen(fmt) + strlen(name) + 1;
+        char* msg = (char*)zaco_alloc(len);
+        snprintf(msg, len, fmt, name);
+        zaco_throw(msg);
+    }
+    const char* body = text ? (const char*)text : "";
+    if (!zaco_colors_enabled()) return zaco_str_new(body);
+    size_t len = strlen(body) + 16;
+    char* styled = (char*)zaco_alloc(len);
+    snprintf(styled, len, "\x1b[%dm%s\x1b[%dm", style->open, body, style->close);
+    return styled;
+}
+
+/* ========== Inspect ==========
+ * Text of a value for console output and format specifiers, guided by a
+ * shape string (see Structured Clone). Inspect style follows Node's
+ * util.inspect: `{ a: 1, b: 'x' }`, `[ 1, 2 ]`; JSON style (%j) gives
+ * `{"a":1,"b":"x"}`, and JSON.stringify's indent (`gap`) puts each entry
+ * on its own line. An object met again while printing itself is
+ * `[Circular]`.
+ */
+
+/* Pointers being printed, outermost first, for cycle detection */
+typedef struct ZacoInspectPath {
+    void* ptr;
+    const struct ZacoInspectPath* outer;
+} ZacoInspectPath;
+
+static void zaco_inspect_bits(ZacoTextBuf* buf, uint64_t bits, const char* shape, int json,
+                              const char* gap, const ZacoShapeFrame* frames, const ZacoInspectPath* path);
+
+/* The replacer array of the JSON.stringify call in progress on this thread */
+static __thread void* zaco_json_keys = NULL;
+
+/* Line break before an entry (or the closing bracket) of the container at
+   the head of `path`, indented by one gap per enclosing container */
+static void zaco_json_newline(ZacoTextBuf* buf, const char* gap, const ZacoInspectPath* path) {
+    zaco_text_append(buf, "\n");
+    for (const ZacoInspectPath* p = path; p; p = p->outer) zaco_text_append(buf, gap);
+}
+
+static void zaco_inspect_string(ZacoTextBuf* buf, const char* s, int json) {
+    if (!json) {
+        zaco_text_append(buf, "'");
+        zaco_text_append(buf, s);
+        zaco_text_append(buf, "'");
+        return;
+    }
+    zaco_text_append(buf, "\"");
+    for (; *s; s++) {
+        char esc[8] = { *s, '\0' };
+        switch (*s) {
+        case '"': strcpy(esc, "\\\""); break;
+        case '\\': strcpy(esc, "\\\\"); break;
+        case '\n': strcpy(esc, "\\n"); break;
+        case '\t': strcpy(esc, "\\t"); break;
+        case '\r': strcpy(esc, "\\r"); break;
+        default:
+            if ((unsigned char)*s < 0x20) snprintf(esc, sizeof(esc), "\\u%04x", (unsigned char)*s);
+            break;
+        }
+        zaco_text_append(buf, esc);
+    }
+    zaco_text_append(buf, "\"");
+}
+
+static void zaco_inspect_bits(ZacoTextBuf* buf, uint64_t bits, const char* shape, int json,
+                              const char* gap, const ZacoShapeFrame* frames, const ZacoInspectPath* path) {
+    void* ptr;
+    memcpy(&ptr, &bits, sizeof(ptr));
+    if (*shape == '^') {
+        long levels = strtol(shape + 1, NULL, 10);
+        while (levels-- > 0 && frames) frames = frames->outer;
+        if (!frames) {
+            zaco_text_append(buf, json ? "null" : "[Object]");
+            return;
+        }
+        shape = frames->shape;
+        frames = frames->outer;
+    }
+    if (*shape == 'n') {
+        char text[64];
+        double n;
+        memcpy(&n, &bits, sizeof(n));
+        zaco_format_f64(text, sizeof(text), n);
+        zaco_text_append(buf, text);
+        return;
+    }
+    if (*shape == 'b') {
+        zaco_text_append(buf, bits ? "true" : "false");
+        return;
+    }
+    if (!ptr) {
+        zaco_text_append(buf, "null");
+        return;
+    }
+    if (ptr == zaco_undefined()) {
+        zaco_text_append(buf, json ? "null" : "undefined");
+        return;
+    }
+    for (const ZacoInspectPath* p = path; p; p = p->outer) {
+        if (p->ptr == ptr) {
+            zaco_text_append(buf, json ? "null" : "[Circular]");
+            return;
+        }
+    }
+    ZacoInspectPath here = { ptr, path };
+    switch (*shape) {
+    case 's':
+        zaco_inspect_string(buf, (const char*)ptr, json);
+        break;
+    case 'f':
+        zaco_text_append(buf, json ? "null" : "[Function]");
+        break;
+    case '[': {
+        int64_t len = *(int64_t*)ptr;
+        const char* elem_shape = shape + 1;
+        if (len == 0) {
+            zaco_text_append(buf, "[]");
+            break;
+        }
+        zaco_text_append(buf, json ? "[" : "[ ");
+        for (int64_t i = 0; i < len; i++) {
+            if (i > 0) zaco_text_append(buf, json ? "," : ", ");
+            if (gap) zaco_json_newline(buf, gap, &here);
+            uint64_t elem = 0;
+            if (*elem_shape == 'b') elem = ((uint8_t*)ptr)[8 + i];
+            else memcpy(&elem, (char*)ptr + 8 + i * 8, 8);
+            zaco_inspect_bits(buf, elem, elem_shape, json, gap, frames, &here);
+        }
+        if (gap) zaco_json_newline(buf, gap, path);
+        zaco_text_append(buf, json ? "]" : " ]");
+        break;
+    }
+    case '(': {
+        int64_t len = *(int64_t*)ptr;
+        if (len == 0) {
+            zaco_text_append(buf, "[]");
+            break;
+        }
+        zaco_text_append(buf, json ? "[" : "[ ");
+        for (int64_t i = 0; i < len; i++) {
+            if (i > 0) zaco_text_append(buf, json ? "," : ", ");
+            if (gap) zaco_json_newline(buf, gap, &here);
+            const char* elem_shape = zaco_shape_tuple_element(shape, i);
+            uint64_t elem;
+            memcpy(&elem, (char*)ptr + 8 + i * 8, 8);
+            zaco_inspect_bits(buf, elem, elem_shape ? elem_shape : "p", json, gap, frames, &here);
+        }
+        if (gap) zaco_json_newline(buf, gap, path);
+        zaco_text_append(buf, json ? "]" : " ]");
+        break;
+    }
+    case '{': {
+        ZacoObject* obj = (ZacoObject*)ptr;
+        ZacoShapeFrame frame = { shape, frames };
+        int64_t printed = 0;
+        /* A replacer array picks the keys JSON writes, in its order */
+        char** keys = json && zaco_json_keys ? (char**)((char*)zaco_json_keys + 8) : NULL;
+        int64_t count = keys ? *(int64_t*)zaco_json_keys : obj->count;
+        for (int64_t k = 0; k < count; k++) {
+            int64_t i = k;
+            if (keys) {
+                int repeated = 0;
+                for (int64_t j = 0; j < k && !repeated; j++) repeated = strcmp(keys[j], keys[k]) == 0;
+                i = repeated ? -1 : zaco_object_find(obj, keys[k]);
+                if (i < 0) continue;
+            }
+            const char* field = zaco_shape_field(shape, obj->entries[i].key);
+            if (!field || (json && *field == 'f')) continue;
+            ZacoObjEntry* entry = &obj->entries[i];
+            int accessor = entry->getter || entry->setter;
+            if (json && accessor && !entry->getter) continue;
+            zaco_text_append(buf, printed == 0 ? (json ? "{" : "{ ") : (json ? "," : ", "));
+            if (gap) zaco_json_newline(buf, gap, &here);
+            if (json) {
+                zaco_inspect_string(buf, obj->entries[i].key, 1);
+                zaco_text_append(buf, gap ? ": " : ":");
+            } else {
+                zaco_text_append(buf, obj->entries[i].key);
+                zaco_text_append(buf, ": ");
+            }
+            if (accessor && !json) {
+                /* Like Node, show accessors without running them */
+                zaco_text_append(buf, !entry->setter ? "[Getter]" : !entry->getter ? "[Setter]" : "[Getter/Setter]");
+            } else {
+                zaco_inspect_bits(buf, zaco_object_entry_bits(obj, i, field), field, json, gap, &frame, &here);
+            }
+            printed++;
+        }
+        if (gap && printed > 0) zaco_json_newline(buf, gap, path);
+        zaco_text_append(buf, printed == 0 ? "{}" : (json ? "}" : " }"));
+        break;
+    }
+    default:
+        zaco_text_append(buf, json ? "{}" : "[Object]");
+        break;
+    }
+}
+
+/* Append an inspected reference to the console line */
+void zaco_console_put_inspect(void* value, const char* shape, int64_t json) {
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    zaco_inspect_bits(&zaco_console_line, bits, shape, (int)json, NULL, NULL, NULL);
+}
+
+/* JSON.stringify(value, keys, space) for a reference of known shape. The
+ * indent is `gap` (up to 10 characters) when space is a string, otherwise
+ * `spaces` spaces (at most 10); an empty indent gives compact output.
+ * `keys`, an array of strings or NULL, limits objects to those keys. */
+void* zaco_json_stringify_value(void* value, const char* shape, double spaces, void* gap, void* keys) {
+    char indent[11] = "";
+    if (gap) {
+        strncat(indent, (const char*)gap, 10);
+    } else {
+        int count = spaces > 10 ? 10 : (int)spaces;
+        for (int i = 0; i < count; i++) indent[i] = ' ';
+    }
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+    ZacoTextBuf buf = { NULL, 0, 0 };
+    zaco_json_keys = keys;
+    zaco_inspect_bits(&buf, bits, shape, 1, indent[0] ? indent : NULL, NULL, NULL);
+    zaco_json_keys = NULL;
+    void* result = zaco_str_new(buf.data ? buf.data : "");
+    free(buf.data);
+    return result;
+}
+
+/* Clone an argument list: an inline array whose i-th element has the i-th
+ * shape in `shapes` */
+static void* zaco_clone_args(void* args, const char* shapes) {
+    if (!args) return NULL;
+    int64_t len = *(int64_t*)args;
+    char* copy = (char*)zaco_alloc(8 + len * 8);
+    memcpy(copy, args, 8 + len * 8);
+    ZacoCloneState state = { NULL, NULL, 0, 0, 0 };
+    const char* shape = shapes;
+    for (int64_t i = 0; i < len && *shape; i++) {
+        uint64_t* slot = (uint64_t*)(copy + 8 + i * 8);
+        *slot = zaco_clone_bits(&state, *slot, shape, NULL);
+        shape = zaco_shape_skip(shape);
+    }
+    free(state.keys);
+    free(state.values);
+    return copy;
+}
+
+/* ========== Worker Threads ==========
+ * spawn(name, args) from the "worker" module. The compiler emits a trampoline
+ * for every exported function of a module that imports "worker" and lists
+ * them in __zaco_worker_table. The Rust runtime runs the trampoline on its
+ * blocking pool and returns a promise for the result.
+ */
+
+typedef void (*ZacoWorkerEntry)(void* args, uint64_t* out);
+
+typedef struct {
+    const char* name;
+    ZacoWorkerEntry entry;
+} ZacoWorkerTableEntry;
+
+/* Weak: the table only exists in programs that import "worker" */
+extern const ZacoWorkerTableEntry __zaco_worker_table[] __attribute__((weak));
+extern void* zaco_worker_run(ZacoWorkerEntry entry, void* args) __attribute__((weak));
+
+void* zaco_worker_spawn(const char* name, void* args, const char* shapes) {
+    ZacoWorkerEntry entry = NULL;
+    for (const ZacoWorkerTableEntry* e = __zaco_worker_table; e && e->name; e++) {
+        if (strcmp(e->name, name) == 0) {
+            entry = e->entry;
+            break;
+        }
+    }
+    if (!entry) {
+        fprintf(stderr, "zaco: spawn: '%s' is not an exported function\n", name);
+        exit(1);
+    }
+    if (!zaco_worker_run) {
+        fprintf(stderr, "zaco: spawn: workers need the Rust runtime\n");
+        exit(1);
+    }
+    /* The worker gets its own copy; nothing it touches is shared */
+    return zaco_worker_run(entry, zaco_clone_args(args, shapes));
+}
+
+/* ========== SQLite ==========
+ * open/exec/query/close from the "sqlite" module. The Rust runtime owns the
+ * connection; failures come back as null / nonzero and are rethrown here
+ * with zaco_throw so scripts can catch them. Query rows are materialized as
+ * plain objects keyed by column name: numbers (INTEGER and REAL) as f64,
+ * text as strings, NULL as null.
+ */
+
+typedef struct ZacoSqliteResult ZacoSqliteResult;
+
+extern void* zaco_sqlite_db_open(const char* path) __attribute__((weak));
+extern int32_t zaco_sqlite_db_exec(void* db, const char* sql) __attribute__((weak));
+extern ZacoSqliteResult* zaco_sqlite_db_query(void* db, const char* sql, void* params, const char* kinds) __attribute__((weak));
+extern void zaco_sqlite_db_close(void* db) __attribute__((weak));
+extern const char* zaco_sqlite_db_error(void) __attribute__((weak));
+extern int64_t zaco_sqlite_result_rows(ZacoSqliteResult* r) __attribute__((weak));
+extern int64_t zaco_sqlite_result_columns(ZacoSqliteResult* r) __attribute__((weak));
+extern const char* zaco_sqlite_result_column_name(ZacoSqliteResult* r, int64_t col) __attribute__((weak));
+extern int32_t zaco_sqlite_result_kind(ZacoSqliteResult* r, int64_t row, int64_t col) __attribute__((weak));
+extern double zaco_sqlite_result_f64(ZacoSqliteResult* r, int64_t row, int64_t col) __attribute__((weak));
+extern char* zaco_sqlite_result_text(ZacoSqliteResult* r, int64_t row, int64_t col) __attribute__((weak));
+extern void zaco_sqlite_result_free(ZacoSqliteResult* r) __attribute__((weak));
+
+static void zaco_sqlite_require_runtime(void) {
+    if (!zaco_sqlite_db_open) {
+        fprintf(stderr, "zaco: sqlite needs the Rust runtime\n");
+        exit(1);
+    }
+}
+
+static void zaco_sqlite_throw(void) {
+    const char* fmt = "SqliteError: %s";
+    const char* detail = zaco_sqlite_db_error();
+    size_t len = strlen(fmt) + strlen(detail) + 1;
+    char* msg = (char*)zaco_alloc(len);
+    snprintf(msg, len, fmt, detail);
+    zaco_throw(msg);
+}
+
+void* zaco_sqlite_open(const char* path) {
+    zaco_sqlite_require_runtime();
+    void* db = zaco_sqlite_db_open(path);
+    if (!db) zaco_sqlite_throw();
+    return db;
+}
+
+void zaco_sqlite_exec(void* db, const char* sql) {
+    zaco_sqlite_require_runtime();
+    if (zaco_sqlite_db_exec(db, sql) != 0) zaco_sqlite_throw();
+}
+
+/* params is an inline array of numbers and strings; kinds has one letter
+ * per element ('n' or 's'), the last repeating for the rest. Returns an
+ * inline array of row objects. */
+void* zaco_sqlite_query(void* db, const char* sql, void* params, const char* kinds) {
+    zaco_sqlite_require_runtime();
+    ZacoSqliteResult* r = zaco_sqlite_db_query(db, sql, params, kinds);
+    if (!r) zaco_sqlite_throw();
+
+    int64_t rows = zaco_sqlite_result_rows(r);
+    int64_t cols = zaco_sqlite_result_columns(r);
+    char* arr = (char*)zaco_alloc(8 + rows * 8);
+    *(int64_t*)arr = rows;
+    for (int64_t i = 0; i < rows; i++) {
+        void* row = zaco_object_new();
+        for (int64_t c = 0; c < cols; c++) {
+            const char* key = zaco_sqlite_result_column_name(r, c);
+            switch (zaco_sqlite_result_kind(r, i, c)) {
+                case 1: zaco_object_set_f64(row, key, zaco_sqlite_result_f64(r, i, c)); break;
+                case 2: zaco_object_set_str(row, key, zaco_sqlite_result_text(r, i, c)); break;
+                default: zaco_object_set_ptr(row, key, NULL); break;
+            }
+        }
+        *(void**)(arr + 8 + i * 8) = row;
+    }
+    zaco_sqlite_result_free(r);
+    return arr;
+}
+
+void zaco_sqlite_close(void* db) {
+    zaco_sqlite_require_runtime();
+    zaco_sqlite_db_close(db);
+}
+
+/* ========== fetch ==========
+ * Global fetch(url, options?). The Rust runtime sends the request on Tokio
+ * and returns a promise of an opaque response whose body has already been
+ * read, so text() and json() hand back promises that are already settled.
+ * options is a plain object with optional method, body, headers and signal
+ * fields; signal is an AbortController handle (see AbortController below).
+ */
+
+typedef struct ZacoFetchResponse ZacoFetchResponse;
+
+extern void* zaco_fetch_start(const char* url, const char* method, const char* headers, const char* body, void* signal) __attribute__((weak));
+extern int64_t zaco_fetch_response_status(ZacoFetchResponse* r) __attribute__((weak));
+extern char* zaco_fetch_response_header(ZacoFetchResponse* r, const char* name) __attribute__((weak));
+extern char* zaco_fetch_response_body(ZacoFetchResponse* r) __attribute__((weak));
+extern void* zaco_promise_new(void) __attribute__((weak));
+extern void zaco_promise_resolve(void* promise, void* value) __attribute__((weak));
+extern void zaco_promise_reject(void* promise, void* error) __attribute__((weak));
+
+void* zaco_fetch(const char* url, void* options) {
+    if (!zaco_fetch_start) {
+        fprintf(stderr, "zaco: fetch needs the Rust runtime\n");
+        exit(1);
+    }
+    const char* method = "";
+    const char* body = NULL;
+    void* signal = NULL;
+    char* headers = NULL;
+    if (options) {
+        if (zaco_object_has(options, "method")) method = zaco_object_get_str(options, "method");
+        if (zaco_object_has(options, "body")) body = zaco_object_get_str(options, "body");
+        if (zaco_object_has(options, "signal")) signal = zaco_object_get_ptr(options, "signal");
+        ZacoObject* h = zaco_object_has(options, "headers")
+            ? (ZacoObject*)zaco_object_get_ptr(options, "headers") : NULL;
+        /* Flatten headers to "Name: value" lines */
+        size_t len = 1;
+        for (int64_t i = 0; h && i < h->count; i++) {
+            const char* value = zaco_object_get_str(h, h->entries[i].key);
+            len += strlen(h->entries[i].key) + strlen(value ? value : "") + 3;
+        }
+        headers = (char*)malloc(len);
+        headers[0] = '\0';
+        for (int64_t i = 0; h && i < h->count; i++) {
+            const char* value = zaco_object_get_str(h, h->entries[i].key);
+            strcat(headers, h->entries[i].key);
+            strcat(headers, ": ");
+            strcat(headers, value ? value : "");
+            strcat(headers, "\n");
+        }
+    }
+    void* promise = zaco_fetch_start(url, method ? method : "", headers ? headers : "", body, signal);
+    free(headers);
+    return promise;
+}
+
+double zaco_response_status(void* r) {
+    return (double)zaco_fetch_response_status((ZacoFetchResponse*)r);
+}
+
+int64_t zaco_response_ok(void* r) {
+    int64_t status = zaco_fetch_response_status((ZacoFetchResponse*)r);
+    return status >= 200 && status <= 299;
+}
+
+const char* zaco_response_header(void* r, const char* name) {
+    return zaco_fetch_response_header((ZacoFetchResponse*)r, name);
+}
+
+void* zaco_response_text(void* r) {
+    void* promise = zaco_promise_new();
+    zaco_promise_resolve(promise, zaco_fetch_response_body((ZacoFetchResponse*)r));
+    return promise;
+}
+
+/* Resolves with the parsed body (see JSON Values), or rejects with a
+ * SyntaxError if the body is not JSON */
+void* zaco_response_json(void* r) {
+    void* promise = zaco_promise_new();
+    uint64_t value;
+    if (zaco_json_parse_value(zaco_fetch_response_body((ZacoFetchResponse*)r), &value) != 0) {
+        zaco_promise_reject(promise, zaco_str_new("SyntaxError: response body is not valid JSON"));
+        return promise;
+    }
+    void* payload;
+    memcpy(&payload, &value, sizeof(payload));
+    zaco_promise_resolve(promise, payload);
+    return promise;
+}
+
+/* ========== AbortController and Awaited Rejections ==========
+ * The Rust runtime owns AbortController handles (compiled code calls
+ * zaco_abort_controller_* directly) and the abortable sleep behind
+ * sleep() from "timers". An aborted operation rejects its promise with an
+ * AbortError message.
+ *
+ * Every await calls zaco_await_rethrow after blocking on the promise, so a
+ * rejection is thrown here, from C, into the enclosing try/catch. A program
+ * that uses promises ends with zaco_check_unhandled_rejections, which
+ * reports every rejection nothing awaited or caught and turns it into a
+ * nonzero exit code.
+ */
+
+extern void* zaco_timers_sleep(double ms, void* signal) __attribute__((weak));
+extern int64_t zaco_promise_is_rejected(void* promise) __attribute__((weak));
+extern void* zaco_async_block_on(void* promise) __attribute__((weak));
+extern void* zaco_promise_take_unhandled(void) __attribute__((weak));
+
+/* sleep(ms, options?) — options may carry a signal */
+void* zaco_sleep(double ms, void* options) {
+    if (!zaco_timers_sleep) {
+        fprintf(stderr, "zaco: sleep needs the Rust runtime\n");
+        exit(1);
+    }
+    void* signal = NULL;
+    if (options && zaco_object_has(options, "signal")) signal = zaco_object_get_ptr(options, "signal");
+    return zaco_timers_sleep(ms, signal);
+}
+
+void zaco_await_rethrow(void* promise) {
+    if (promise && zaco_promise_is_rejected && zaco_promise_is_rejected(promise)) {
+        zaco_throw(zaco_async_block_on(promise));
+    }
+}
+
+/* Exit code for the end of main: 1 if any rejection went unhandled, else 0 */
+int64_t zaco_check_unhandled_rejections() {
+    if (!zaco_promise_take_unhandled) return 0;
+    int64_t code = 0;
+    void* error;
+    while ((error = zaco_promise_take_unhandled()) != NULL) {
+        fflush(stdout);
+        fprintf(stderr, "Uncaught (in promise): %s\n", (char*)error);
+        code = 1;
+    }
+    return code;
+}
+
+/* ========== glob Module ==========
+ * globSync(pattern, options?) from the "glob" module. options may carry cwd
+ * (a string) and dot (match hidden entries). The Rust runtime walks the
+ * tree and returns the sorted relative paths as an inline string array.
+ */
+
+extern void* zaco_glob_walk(const char* pattern, const char* cwd, int64_t dot) __attribute__((weak));
+
+void* zaco_glob_sync(const char* pattern, void* options) {
+    if (!zaco_glob_walk) {
+        fprintf(stderr, "zaco: globSync needs the Rust runtime\n");
+        exit(1);
+    }
+    const char* cwd = NULL;
+    int64_t dot = 0;
+    if (options && zaco_object_has(options, "cwd")) cwd = zaco_object_get_str(options, "cwd");
+    if (options && zaco_object_has(options, "dot")) dot = zaco_object_get_i64(options, "dot") != 0;
+    return zaco_glob_walk(pattern, cwd, dot);
+}
+
+/* ========== assert Module ==========
+ * Failed assertions throw an "AssertionError: ..." string with zaco_throw,
+ * so they can be caught like any other error. The message is the caller's
+ * if one was given, otherwise it describes the compared values.
+ */
+
+static void zaco_assert_throw(const char* message, const char* detail) {
+    const char* text = message ? message : detail;
+    size_t len = strlen("AssertionError: ") + strlen(text) + 1;
+    char* msg = (char*)zaco_alloc(len);
+    snprintf(msg, len, "AssertionError: %s", text);
+    zaco_throw(msg);
+}
+
+void zaco_assert(int8_t cond, const char* message) {
+    if (!cond) zaco_assert_throw(message, "assertion failed");
+}
+
+void zaco_assert_equal_f64(double actual, double expected, const char* message) {
+    if (actual == expected || (actual != actual && expected != expected)) return;
+    char* a = (char*)zaco_f64_to_str(actual);
+    char* e = (char*)zaco_f64_to_str(expected);
+    char detail[160];
+    snprintf(detail, sizeof(detail), "expected %s to equal %s", a, e);
+    zaco_assert_throw(message, detail);
+}
+
+void zaco_assert_equal_str(const char* actual, const char* expected, const char* message) {
+    if (zaco_str_eq((void*)actual, (void*)expected)) return;
+    const char* a = actual ? actual : "null";
+    const char* e = expected ? expected : "null";
+    size_t len = strlen(a) + strlen(e) + 32;
+    char* detail = (char*)zaco_alloc(len);
+    snprintf(detail, len, "expected \"%s\" to equal \"%s\"", a, e);
+    zaco_assert_throw(message, detail);
+}
+
+void zaco_assert_equal_bool(int8_t actual, int8_t expected, const char* message) {
+    if (!actual == !expected) return;
+    char detail[48];
+    snprintf(detail, sizeof(detail), "expected %s to equal %s", actual ? "true" : "false", expected ? "true" : "false");
+    zaco_assert_throw(message, detail);
+}
+
+void zaco_assert_equal_ptr(void* actual, void* expected, const char* message) {
+    if (actual != expected) zaco_assert_throw(message, "expected values to be the same reference");
+}
+
+void zaco_assert_deep_equal(void* actual, void* expected, const char* shape, const char* message) {
+    if (!zaco_deep_equal(actual, expected, shape)) zaco_assert_throw(message, "expected values to be deeply equal");
+}
+
+/* assert.throws(fn) when fn returned normally */
+void zaco_assert_not_thrown(const char* message) {
+    zaco_assert_throw(message, "expected function to throw");
+}
+
+/* ========== test() Registration ==========
+ * test(name, fn) runs fn right away inside a try block; the compiler calls
+ * zaco_test_begin before it and zaco_test_pass or zaco_test_fail after.
+ * Each test prints one line, and main ORs zaco_test_report into its exit
+ * code so `zaco test` sees a failure even when every error was caught.
+ */
+
+static const char* test_current = NULL;
+static double test_started_ms = 0.0;
+static int64_t test_passed = 0;
+static int64_t test_failed = 0;
+
+static double zaco_test_now_ms(void) {
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return (double)ts.tv_sec * 1e3 + (double)ts.tv_nsec / 1e6;
+}
+
+void zaco_test_begin(const char* name) {
+    test_current = name ? name : "";
+    test_started_ms = zaco_test_now_ms();
+}
+
+void zaco_test_pass(void) {
+    test_passed++;
+    printf("  ok   %s (%.1f ms)\n", test_current, zaco_test_now_ms() - test_started_ms);
+    fflush(stdout);
+}
+
+void zaco_test_fail(void* error) {
+    test_failed++;
+    printf("  FAIL %s (%.1f ms)\n", test_current, zaco_test_now_ms() - test_started_ms);
+    printf("       %s\n", error ? (char*)error : "unknown error");
+    fflush(stdout);
+}
+
+/* Exit code for the end of main: 1 if any test failed, else 0 */
+int64_t zaco_test_report(void) {
+    if (test_passed + test_failed == 0) return 0;
+    printf("%lld passed, %lld failed\n", (long long)test_passed, (long long)test_failed);
+    fflush(stdout);
+    return test_failed > 0;
+}
+
+/* ========== Profiling (--instrument) ==========
+ * Instrumented code calls zaco_profile_enter/exit around every user function
+ * and zaco_profile_alloc before every allocation site. Names are string
+ * literals from the module's data section, so entries are keyed by pointer.
+ * The summary goes to stderr at exit, or to the JSON file named by
+ * ZACO_PROFILE_OUT. The profile stack is shared, so instrumented programs
+ * should only call user functions from the main thread.
+ */
+
+#define PROFILE_MAX_ENTRIES 1024
+#define PROFILE_MAX_DEPTH 4096
+
+typedef struct {
+    const char* name;
+    int64_t count;
+    int64_t active;       /* recursion depth, so time is counted once */
+    double total_ns;
+} ProfileEntry;
+
+typedef struct {
+    ProfileEntry* entry;
+    double start_ns;
+} ProfileFrame;
+
+static ProfileEntry profile_funcs[PROFILE_MAX_ENTRIES];
+static int64_t profile_func_count = 0;
+static ProfileEntry profile_allocs[PROFILE_MAX_ENTRIES];
+static int64_t profile_alloc_count = 0;
+static ProfileFrame profile_stack[PROFILE_MAX_DEPTH];
+static int64_t profile_depth = 0;
+static int profile_registered = 0;
+
+static double zaco_profile_now_ns(void) {
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return (double)ts.tv_sec * 1e9 + (double)ts.tv_nsec;
+}
+
+static ProfileEntry* zaco_profile_lookup(ProfileEntry* table, int64_t* count, const char* name) {
+    for (int64_t i = 0; i < *count; i++) {
+        if (table[i].name == name || strcmp(table[i].name, name) == 0) {
+            return &table[i];
+        }
+    }
+    if (*count >= PROFILE_MAX_ENTRIES) return NULL;
+    ProfileEntry* entry = &table[(*count)++];
+    entry->name = name;
+    return entry;
+}
+
+static int zaco_profile_cmp(const void* a, const void* b) {
+    return strcmp(((const ProfileEntry*)a)->name, ((const ProfileEntry*)b)->name);
+}
+
+static void zaco_profile_dump(void) {
+    /* Close frames left open by exit() or an uncaught throw */
+    double now = zaco_profile_now_ns();
+    while (profile_depth > 0) {
+        ProfileFrame* frame = &profile_stack[--profile_depth];
+        if (--frame->entry->active == 0) {
+            frame->entry->total_ns += now - frame->start_ns;
+        }
+    }
+
+    qsort(profile_funcs, profile_func_count, sizeof(ProfileEntry), zaco_profile_cmp);
+    qsort(profile_allocs, profile_alloc_count, sizeof(ProfileEntry), zaco_profile_cmp);
+
+    const char* out_path = getenv("ZACO_PROFILE_OUT");
+    if (out_path && *out_path) {
+        FILE* out = fopen(out_path, "w");
+        if (!out) {
+            fprintf(stderr, "zaco: cannot write profile to %s\n", out_path);
+            return;
+        }
+        fprintf(out, "{\"functions\":[");
+        for (int64_t i = 0; i < profile_func_count; i++) {
+            fprintf(out, "%s{\"name\":\"%s\",\"calls\":%lld,\"total_ms\":%.3f}",
+                    i ? "," : "", profile_funcs[i].name,
+                    (long long)profile_funcs[i].count, profile_funcs[i].total_ns / 1e6);
+        }
+        fprintf(out, "],\"allocations\":[");
+        for (int64_t i = 0; i < profile_alloc_count; i++) {
+            fprintf(out, "%s{\"type\":\"%s\",\"count\":%lld}",
+                    i ? "," : "", profile_allocs[i].name, (long long)profile_allocs[i].count);
+        }
+        fprintf(out, "]}\n");
+        fclose(out);
+        return;
+    }
+
+    fprintf(stderr, "== zaco profile: calls ==\n");
+    for (int64_t i = 0; i < profile_func_count; i++) {
+        fprintf(stderr, "%s: %lld calls, %.3f ms\n", profile_funcs[i].name,
+                (long long)profile_funcs[i].count, profile_funcs[i].total_ns / 1e6);
+    }
+    fprintf(stderr, "== zaco profile: allocations ==\n");
+    for (int64_t i = 0; i < profile_alloc_count; i++) {
+        fprintf(stderr, "%s: %lld\n", profile_allocs[i].name, (long long)profile_allocs[i].count);
+    }
+}
+
+static void zaco_profile_register(void) {
+    if (!profile_registered) {
+        profile_registered = 1;
+        atexit(zaco_profile_dump);
+    }
+}
+
+void zaco_profile_enter(const char* name) {
+    zaco_profile_register();
+    ProfileEntry* entry = zaco_profile_lookup(profile_funcs, &profile_func_count, name);
+    if (!entry) return;
+    entry->count++;
+    entry->active++;
+    if (profile_depth < PROFILE_MAX_DEPTH) {
+        profile_stack[profile_depth].entry = entry;
+        profile_stack[profile_depth].start_ns = zaco_profile_now_ns();
+        profile_depth++;
+    }
+}
+
+void zaco_profile_exit(const char* name) {
+    double now = zaco_profile_now_ns();
+    /* Pop to the matching frame; frames skipped by a throw are closed here too */
+    while (profile_depth > 0) {
+        ProfileFrame* frame = &profile_stack[--profile_depth];
+        if (--frame->entry->active == 0) {
+            frame->entry->total_ns += now - frame->start_ns;
+        }
+        if (frame->entry->name == name || strcmp(frame->entry->name, name) == 0) {
+            break;
+        }
+    }
+}
+
+void zaco_profile_alloc(const char* type_name) {
+    if (zaco_debug_heap_active()) {
+        zaco_debug_heap_set_site(type_name);
+    }
+    zaco_profile_register();
+    ProfileEntry* entry = zaco_profile_lookup(profile_allocs, &profile_alloc_count, type_name);
+    if (entry) entry->count++;
+}
+
+/* ========== Stack Overflow Guard ==========
+ * Unbounded recursion runs into the guard page below the main thread stack,
+ * or below a generator's stack when it recurses inside a generator body.
+ * A SIGSEGV handler on an alternate stack recognises faults in either
+ * region and reports a JS-style RangeError instead of a bare segfault. In
+ * --instrument builds the innermost profiled function is named as well.
+ * Building with -DZACO_STACK_SIZE=<bytes> (`--stack-size`) raises the stack
+ * limit at startup; Mach-O binaries get it from the linker instead.
+ */
+
+#define ZACO_EXIT_STACK_OVERFLOW 3
+#define STACK_GUARD_SLACK (1 << 20)
+
+static char stack_guard_altstack[64 * 1024];
+static uintptr_t stack_guard_top = 0;
+static uintptr_t stack_guard_limit = 0;
+
+static void stack_guard_write(const char* s) {
+    ssize_t ignored = write(STDERR_FILENO, s, strlen(s));
+    (void)ignored;
+}
+
+/* Whether addr is in (or a large frame's reach below) the guard page of the
+ * generator running on this thread */
+static int stack_guard_in_generator(uintptr_t addr) {
+    ZacoGenerator* gen = generator_current;
+    if (!gen || !gen->stack) return 0;
+    uintptr_t guard = (uintptr_t)gen->stack;
+    uintptr_t low = guard > STACK_GUARD_SLACK ? guard - STACK_GUARD_SLACK : 0;
+    return addr >= low && addr < guard + gen->stack_size - GENERATOR_STACK_SIZE;
+}
+
+static void stack_guard_handler(int sig, siginfo_t* info, void* ctx) {
+    (void)ctx;
+    uintptr_t addr = (uintptr_t)info->si_addr;
+    uintptr_t low = stack_guard_top > stack_guard_limit + STACK_GUARD_SLACK
+        ? stack_guard_top - stack_guard_limit - STACK_GUARD_SLACK
+        : 0;
+    int main_stack = stack_guard_top != 0 && addr >= low && addr < stack_guard_top;
+    if (!main_stack && !stack_guard_in_generator(addr)) {
+        /* Not a stack overflow: fall back to the default crash */
+        signal(sig, SIG_DFL);
+        raise(sig);
+        return;
+    }
+    /* Only async-signal-safe calls from here on */
+    stack_guard_write("RangeError: Maximum call stack size exceeded\n");
+    if (profile_depth > 0) {
+        stack_guard_write("    at ");
+        stack_guard_write(profile_stack[profile_depth - 1].entry->name);
+        stack_guard_write("\n");
+    }
+    _exit(ZACO_EXIT_STACK_OVERFLOW);
+}
+
+__attribute__((constructor))
+static void zaco_stack_guard_install(void) {
+    struct rlimit rl;
+#if defined(ZACO_STACK_SIZE) && defined(__linux__)
+    /* The main thread stack grows on demand up to the soft limit */
+    if (getrlimit(RLIMIT_STACK, &rl) == 0 && rl.rlim_cur != RLIM_INFINITY
+        && rl.rlim_cur < (rlim_t)ZACO_STACK_SIZE) {
+        rl.rlim_cur = (rlim_t)ZACO_STACK_SIZE;
+        if (rl.rlim_max != RLIM_INFINITY && rl.rlim_cur > rl.rlim_max) {
+            rl.rlim_cur = rl.rlim_max;
+        }
+        setrlimit(RLIMIT_STACK, &rl);
+    }
+#endif
+    stack_guard_top = (uintptr_t)__builtin_frame_address(0);
+    if (getrlimit(RLIMIT_STACK, &rl) == 0 && rl.rlim_cur != RLIM_INFINITY) {
+        stack_guard_limit = (uintptr_t)rl.rlim_cur;
+    } else {
+        stack_guard_limit = (uintptr_t)1 << 30;
+    }
+
+    stack_t ss;
+    ss.ss_sp = stack_guard_altstack;
+    ss.ss_size = sizeof(stack_guard_altstack);
+    ss.ss_flags = 0;
+    if (sigaltstack(&ss, NULL) != 0) return;
+
+    struct sigaction sa;
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_sigaction = stack_guard_handler;
+    sa.sa_flags = SA_SIGINFO | SA_ONSTACK;
+    sigemptyset(&sa.sa_mask);
+    sigaction(SIGSEGV, &sa, NULL);
+    sigaction(SIGBUS, &sa, NULL);
+}
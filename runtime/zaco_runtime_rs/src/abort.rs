@@ -0,0 +1,174 @@
+//! AbortController, AbortSignal.timeout and abortable sleeps
+//!
+//! A controller and its `signal` are the same runtime handle: compiled code
+//! gets one pointer from `new AbortController()` (or `AbortSignal.timeout`)
+//! and passes it wherever an options object takes `signal`. Handles are
+//! never freed, since a pending fetch or sleep may still be watching the
+//! signal when the controller's variable goes out of scope.
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+use crate::event_loop;
+use crate::promise::{zaco_promise_new, zaco_promise_reject, zaco_promise_resolve, ZacoPromise};
+
+/// Message an aborted operation's promise rejects with.
+pub(crate) const ABORT_ERROR: &str = "AbortError: This operation was aborted";
+
+pub struct AbortSignal {
+    aborted: AtomicBool,
+    notify: Notify,
+}
+
+impl AbortSignal {
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::Acquire)
+    }
+
+    /// Complete once the signal is aborted (immediately if it already is).
+    pub(crate) async fn aborted(&self) {
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            // Register before checking the flag so an abort in between is not missed
+            notified.as_mut().enable();
+            if self.is_aborted() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Borrow the signal behind a handle from compiled code, if any.
+    pub(crate) fn from_handle(handle: *const AbortSignal) -> Option<&'static AbortSignal> {
+        unsafe { handle.as_ref() }
+    }
+}
+
+/// Reject `promise` with an AbortError.
+pub(crate) fn reject_aborted(promise: *mut ZacoPromise) {
+    zaco_promise_reject(promise, crate::zaco_compatible_str_new(ABORT_ERROR) as *mut c_void);
+}
+
+/// zaco_abort_controller_new() -> controller handle, also used as its signal
+#[no_mangle]
+pub extern "C" fn zaco_abort_controller_new() -> *mut AbortSignal {
+    Box::into_raw(Box::new(AbortSignal {
+        aborted: AtomicBool::new(false),
+        notify: Notify::new(),
+    }))
+}
+
+/// zaco_abort_controller_abort(controller) — wakes every operation watching the signal
+#[no_mangle]
+pub extern "C" fn zaco_abort_controller_abort(signal: *mut AbortSignal) {
+    if let Some(signal) = AbortSignal::from_handle(signal) {
+        signal.aborted.store(true, Ordering::Release);
+        signal.notify.notify_waiters();
+    }
+}
+
+/// zaco_abort_signal_timeout(ms) -> signal that aborts itself after `ms` milliseconds
+#[no_mangle]
+pub extern "C" fn zaco_abort_signal_timeout(ms: f64) -> *mut AbortSignal {
+    let signal = zaco_abort_controller_new();
+    let signal_addr = signal as usize;
+    let delay = std::time::Duration::from_millis(ms.max(0.0) as u64);
+    event_loop::spawn(async move {
+        tokio::time::sleep(delay).await;
+        zaco_abort_controller_abort(signal_addr as *mut AbortSignal);
+    });
+    signal
+}
+
+/// zaco_abort_signal_aborted(signal) -> 1 once aborted, else 0
+#[no_mangle]
+pub extern "C" fn zaco_abort_signal_aborted(signal: *const AbortSignal) -> i64 {
+    AbortSignal::from_handle(signal).is_some_and(AbortSignal::is_aborted) as i64
+}
+
+/// zaco_timers_sleep(ms, signal) -> promise resolved after `ms` milliseconds,
+/// or rejected with an AbortError if `signal` (nullable) aborts first
+#[no_mangle]
+pub extern "C" fn zaco_timers_sleep(ms: f64, signal: *const AbortSignal) -> *mut ZacoPromise {
+    let promise = zaco_promise_new();
+    let promise_addr = promise as usize;
+    let signal = AbortSignal::from_handle(signal);
+    let delay = std::time::Duration::from_millis(ms.max(0.0) as u64);
+    event_loop::spawn(async move {
+        let completed = match signal {
+            Some(signal) => tokio::select! {
+                _ = tokio::time::sleep(delay) => true,
+                _ = signal.aborted() => false,
+            },
+            None => {
+                tokio::time::sleep(delay).await;
+                true
+            }
+        };
+        let promise = promise_addr as *mut ZacoPromise;
+        if completed {
+            zaco_promise_resolve(promise, std::ptr::null_mut());
+        } else {
+            reject_aborted(promise);
+        }
+    });
+    promise
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::promise::zaco_async_block_on;
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_sleep_resolves_after_delay() {
+        crate::zaco_runtime_init();
+        let start = Instant::now();
+        let promise = zaco_timers_sleep(50.0, std::ptr::null());
+        zaco_async_block_on(promise);
+        assert!(!unsafe { (*promise).is_rejected() });
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_abort_rejects_pending_sleep() {
+        crate::zaco_runtime_init();
+        let controller = zaco_abort_controller_new();
+        let promise = zaco_timers_sleep(60_000.0, controller);
+        assert_eq!(zaco_abort_signal_aborted(controller), 0);
+
+        let start = Instant::now();
+        let controller_addr = controller as usize;
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            zaco_abort_controller_abort(controller_addr as *mut AbortSignal);
+        });
+        let error = zaco_async_block_on(promise) as *const c_char;
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(unsafe { (*promise).is_rejected() });
+        assert_eq!(unsafe { CStr::from_ptr(error) }.to_str().unwrap(), ABORT_ERROR);
+        assert_eq!(zaco_abort_signal_aborted(controller), 1);
+
+        // An already-aborted signal rejects right away
+        let promise = zaco_timers_sleep(60_000.0, controller);
+        zaco_async_block_on(promise);
+        assert!(unsafe { (*promise).is_rejected() });
+    }
+
+    #[test]
+    fn test_timeout_signal_aborts_itself() {
+        crate::zaco_runtime_init();
+        let signal = zaco_abort_signal_timeout(50.0);
+        assert_eq!(zaco_abort_signal_aborted(signal), 0);
+        let promise = zaco_timers_sleep(60_000.0, signal);
+        zaco_async_block_on(promise);
+        assert!(unsafe { (*promise).is_rejected() });
+        assert_eq!(zaco_abort_signal_aborted(signal), 1);
+    }
+}
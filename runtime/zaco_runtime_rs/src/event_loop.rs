@@ -38,3 +38,19 @@ where
 {
     get_runtime().spawn(f)
 }
+
+/// Run blocking work on Tokio's blocking thread pool. Falls back to a plain
+/// thread when the runtime has not been initialized (e.g. in unit tests).
+pub fn spawn_blocking<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    match RUNTIME.get() {
+        Some(rt) => {
+            rt.spawn_blocking(f);
+        }
+        None => {
+            std::thread::spawn(f);
+        }
+    }
+}
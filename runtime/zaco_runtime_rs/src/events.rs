@@ -3,7 +3,7 @@
 
 use std::collections::HashMap;
 use std::os::raw::{c_char, c_void};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::sync::atomic::{AtomicI64, Ordering};
 
 /// Callback function type — fix #6: receives event data pointer
@@ -92,31 +92,26 @@ impl EventEmitter {
     }
 }
 
-/// Global registry of EventEmitters
-static EMITTERS: Mutex<Option<HashMap<i64, Arc<Mutex<EventEmitter>>>>> = Mutex::new(None);
 static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
 
-/// Initialize the global emitter registry
-fn ensure_registry() {
-    let mut registry = EMITTERS.lock().unwrap();
-    if registry.is_none() {
-        *registry = Some(HashMap::new());
-    }
+/// Global registry of EventEmitters
+fn emitters() -> &'static Mutex<HashMap<i64, Arc<Mutex<EventEmitter>>>> {
+    static EMITTERS: OnceLock<Mutex<HashMap<i64, Arc<Mutex<EventEmitter>>>>> = OnceLock::new();
+    EMITTERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Look up an emitter. The registry lock is released before the emitter is
+/// locked, so the two locks are never held together.
+fn lookup_emitter(handle: i64) -> Option<Arc<Mutex<EventEmitter>>> {
+    crate::lock(emitters()).get(&handle).cloned()
 }
 
 /// Create a new EventEmitter
 #[no_mangle]
 pub extern "C" fn zaco_events_new() -> i64 {
-    ensure_registry();
-
     let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
     let emitter = Arc::new(Mutex::new(EventEmitter::new()));
-
-    let mut registry = EMITTERS.lock().unwrap();
-    if let Some(ref mut map) = *registry {
-        map.insert(handle, emitter);
-    }
-
+    crate::lock(emitters()).insert(handle, emitter);
     handle
 }
 
@@ -130,12 +125,9 @@ pub extern "C" fn zaco_events_on(
 ) {
     let event_str = unsafe { crate::cstr_to_str(event) };
 
-    let registry = EMITTERS.lock().unwrap();
-    if let Some(ref map) = *registry {
-        if let Some(emitter) = map.get(&emitter) {
-            let mut em = emitter.lock().unwrap();
-            em.on(event_str, callback, context);
-        }
+    if let Some(emitter) = lookup_emitter(emitter) {
+        let mut em = crate::lock(&emitter);
+        em.on(event_str, callback, context);
     }
 }
 
@@ -149,12 +141,9 @@ pub extern "C" fn zaco_events_once(
 ) {
     let event_str = unsafe { crate::cstr_to_str(event) };
 
-    let registry = EMITTERS.lock().unwrap();
-    if let Some(ref map) = *registry {
-        if let Some(emitter) = map.get(&emitter) {
-            let mut em = emitter.lock().unwrap();
-            em.once(event_str, callback, context);
-        }
+    if let Some(emitter) = lookup_emitter(emitter) {
+        let mut em = crate::lock(&emitter);
+        em.once(event_str, callback, context);
     }
 }
 
@@ -168,23 +157,14 @@ pub extern "C" fn zaco_events_emit(
 ) -> i64 {
     let event_str = unsafe { crate::cstr_to_str(event) };
 
-    // Clone the emitter Arc while holding registry lock, then drop registry lock
-    let emitter_arc = {
-        let registry = EMITTERS.lock().unwrap();
-        match *registry {
-            Some(ref map) => map.get(&emitter).cloned(),
-            None => None,
-        }
-    };
-
-    let emitter_arc = match emitter_arc {
+    let emitter_arc = match lookup_emitter(emitter) {
         Some(e) => e,
         None => return 0,
     };
 
     // Take snapshot of listeners (and remove once listeners) while holding emitter lock
     let listeners = {
-        let mut em = emitter_arc.lock().unwrap();
+        let mut em = crate::lock(&emitter_arc);
         em.take_listeners_for_emit(event_str)
     };
     // Emitter lock is now dropped — callbacks can safely call events API
@@ -203,12 +183,9 @@ pub extern "C" fn zaco_events_emit(
 pub extern "C" fn zaco_events_remove_all(emitter: i64, event: *const c_char) {
     let event_str = unsafe { crate::cstr_to_str(event) };
 
-    let registry = EMITTERS.lock().unwrap();
-    if let Some(ref map) = *registry {
-        if let Some(emitter) = map.get(&emitter) {
-            let mut em = emitter.lock().unwrap();
-            em.remove_all(event_str);
-        }
+    if let Some(emitter) = lookup_emitter(emitter) {
+        let mut em = crate::lock(&emitter);
+        em.remove_all(event_str);
     }
 }
 
@@ -217,12 +194,9 @@ pub extern "C" fn zaco_events_remove_all(emitter: i64, event: *const c_char) {
 pub extern "C" fn zaco_events_listener_count(emitter: i64, event: *const c_char) -> i64 {
     let event_str = unsafe { crate::cstr_to_str(event) };
 
-    let registry = EMITTERS.lock().unwrap();
-    if let Some(ref map) = *registry {
-        if let Some(emitter) = map.get(&emitter) {
-            let em = emitter.lock().unwrap();
-            return em.listener_count(event_str);
-        }
+    if let Some(emitter) = lookup_emitter(emitter) {
+        let em = crate::lock(&emitter);
+        return em.listener_count(event_str);
     }
     0
 }
@@ -236,12 +210,9 @@ pub extern "C" fn zaco_events_remove_listener(
 ) -> i64 {
     let event_str = unsafe { crate::cstr_to_str(event) };
 
-    let registry = EMITTERS.lock().unwrap();
-    if let Some(ref map) = *registry {
-        if let Some(emitter) = map.get(&emitter) {
-            let mut em = emitter.lock().unwrap();
-            return if em.remove_listener(event_str, callback) { 1 } else { 0 };
-        }
+    if let Some(emitter) = lookup_emitter(emitter) {
+        let mut em = crate::lock(&emitter);
+        return if em.remove_listener(event_str, callback) { 1 } else { 0 };
     }
     0
 }
@@ -249,14 +220,11 @@ pub extern "C" fn zaco_events_remove_listener(
 /// Get all event names
 #[no_mangle]
 pub extern "C" fn zaco_events_event_names(emitter: i64) -> *mut c_char {
-    let registry = EMITTERS.lock().unwrap();
-    if let Some(ref map) = *registry {
-        if let Some(emitter) = map.get(&emitter) {
-            let em = emitter.lock().unwrap();
-            let names: Vec<String> = em.listeners.keys().cloned().collect();
-            let joined = names.join("\n");
-            return crate::zaco_compatible_str_new(&joined);
-        }
+    if let Some(emitter) = lookup_emitter(emitter) {
+        let em = crate::lock(&emitter);
+        let names: Vec<String> = em.listeners.keys().cloned().collect();
+        let joined = names.join("\n");
+        return crate::zaco_compatible_str_new(&joined);
     }
     std::ptr::null_mut()
 }
@@ -264,8 +232,62 @@ pub extern "C" fn zaco_events_event_names(emitter: i64) -> *mut c_char {
 /// Destroy an EventEmitter
 #[no_mangle]
 pub extern "C" fn zaco_events_destroy(emitter: i64) {
-    let mut registry = EMITTERS.lock().unwrap();
-    if let Some(ref mut map) = *registry {
-        map.remove(&emitter);
+    crate::lock(emitters()).remove(&emitter);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    extern "C" fn bump(ctx: *mut c_void, _data: *mut c_void) {
+        let counter = unsafe { &*(ctx as *const AtomicUsize) };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_concurrent_emitters() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let ctx = &CALLS as *const AtomicUsize as usize;
+
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let event = c"tick".as_ptr();
+                    let handle = zaco_events_new();
+                    zaco_events_on(handle, event, bump, ctx as *mut c_void);
+                    zaco_events_once(handle, event, bump, ctx as *mut c_void);
+                    let mut emitted = 0;
+                    for _ in 0..50 {
+                        emitted += zaco_events_emit(handle, event, std::ptr::null_mut());
+                    }
+                    assert_eq!(zaco_events_listener_count(handle, event), 1);
+                    zaco_events_destroy(handle);
+                    assert_eq!(zaco_events_emit(handle, event, std::ptr::null_mut()), 0);
+                    emitted
+                })
+            })
+            .collect();
+        let emitted: i64 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+
+        // Each thread: 50 emits of the persistent listener plus one `once`
+        assert_eq!(emitted, 8 * 51);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 8 * 51);
+    }
+
+    extern "C" fn emit_nested(ctx: *mut c_void, _data: *mut c_void) {
+        // Listeners may call back into the events API without deadlocking
+        let handle = ctx as i64;
+        zaco_events_remove_all(handle, c"nested".as_ptr());
+    }
+
+    #[test]
+    fn test_listener_can_reenter_events_api() {
+        let handle = zaco_events_new();
+        let event = c"nested".as_ptr();
+        zaco_events_on(handle, event, emit_nested, handle as *mut c_void);
+        assert_eq!(zaco_events_emit(handle, event, std::ptr::null_mut()), 1);
+        assert_eq!(zaco_events_listener_count(handle, event), 0);
+        zaco_events_destroy(handle);
     }
 }
@@ -0,0 +1,225 @@
+//! Global `fetch()` over reqwest's async client
+//!
+//! `zaco_fetch_start` sends the request on the Tokio runtime and returns a
+//! promise right away; it resolves with a `FetchResponse` once the status,
+//! headers and body have arrived, or rejects with a `TypeError` message if
+//! the request fails. If an `AbortSignal` is passed and aborted first, the
+//! promise rejects with an `AbortError` instead. The C runtime's `zaco_fetch` / `zaco_response_*`
+//! wrappers build the request from the options object and expose the
+//! response to compiled code.
+
+use std::ffi::c_void;
+use std::os::raw::c_char;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+
+use crate::abort::{reject_aborted, AbortSignal};
+use crate::event_loop;
+use crate::promise::{zaco_promise_new, zaco_promise_reject, zaco_promise_resolve, ZacoPromise};
+
+/// A finished response. The body is read eagerly, so `text()` and `json()`
+/// never wait on the network.
+pub struct FetchResponse {
+    status: u16,
+    headers: HeaderMap,
+    body: String,
+}
+
+/// Parse `Name: value` lines (as built by the C side from `options.headers`).
+fn parse_headers(lines: &str) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for line in lines.lines().filter(|line| !line.trim().is_empty()) {
+        let (name, value) = line.split_once(':').ok_or_else(|| format!("invalid header '{}'", line))?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|e| e.to_string())?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|e| e.to_string())?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+async fn send(url: String, method: String, headers: String, body: Option<String>) -> Result<FetchResponse, String> {
+    let method = match method.as_str() {
+        "" => Method::GET,
+        name => name.to_uppercase().parse().map_err(|_| format!("invalid method '{}'", name))?,
+    };
+    let mut request = reqwest::Client::new().request(method, &url).headers(parse_headers(&headers)?);
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    Ok(FetchResponse { status, headers, body })
+}
+
+/// zaco_fetch_start(url, method, headers, body, signal) -> promise of a response
+///
+/// `method` may be empty (GET), `headers` holds `Name: value` lines and
+/// `body` and `signal` may be null.
+#[no_mangle]
+pub extern "C" fn zaco_fetch_start(
+    url: *const c_char,
+    method: *const c_char,
+    headers: *const c_char,
+    body: *const c_char,
+    signal: *const AbortSignal,
+) -> *mut ZacoPromise {
+    let url = unsafe { crate::cstr_to_str(url) }.to_string();
+    let method = unsafe { crate::cstr_to_str(method) }.to_string();
+    let headers = unsafe { crate::cstr_to_str(headers) }.to_string();
+    let body = (!body.is_null()).then(|| unsafe { crate::cstr_to_str(body) }.to_string());
+    let signal = AbortSignal::from_handle(signal);
+
+    let promise = zaco_promise_new();
+    let promise_addr = promise as usize;
+    event_loop::spawn(async move {
+        let request = send(url, method, headers, body);
+        // None means the signal fired before the response finished
+        let result = match signal {
+            Some(signal) => tokio::select! {
+                biased;
+                _ = signal.aborted() => None,
+                result = request => Some(result),
+            },
+            None => Some(request.await),
+        };
+        let promise = promise_addr as *mut ZacoPromise;
+        match result {
+            None => reject_aborted(promise),
+            Some(Ok(response)) => zaco_promise_resolve(promise, Box::into_raw(Box::new(response)) as *mut c_void),
+            Some(Err(e)) => {
+                let message = crate::zaco_compatible_str_new(&format!("TypeError: fetch failed: {}", e));
+                zaco_promise_reject(promise, message as *mut c_void)
+            }
+        }
+    });
+    promise
+}
+
+/// zaco_fetch_response_status(response) -> HTTP status code
+#[no_mangle]
+pub extern "C" fn zaco_fetch_response_status(response: *const FetchResponse) -> i64 {
+    unsafe { &*response }.status as i64
+}
+
+/// zaco_fetch_response_header(response, name) -> header value, or null if absent
+///
+/// Names are case-insensitive; repeated headers are joined with ", ".
+#[no_mangle]
+pub extern "C" fn zaco_fetch_response_header(response: *const FetchResponse, name: *const c_char) -> *mut c_char {
+    let response = unsafe { &*response };
+    let name = unsafe { crate::cstr_to_str(name) };
+    let values: Vec<&str> = response
+        .headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    if values.is_empty() {
+        return std::ptr::null_mut();
+    }
+    crate::zaco_compatible_str_new(&values.join(", "))
+}
+
+/// zaco_fetch_response_body(response) -> body as a zaco string
+#[no_mangle]
+pub extern "C" fn zaco_fetch_response_body(response: *const FetchResponse) -> *mut c_char {
+    crate::zaco_compatible_str_new(&unsafe { &*response }.body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::promise::zaco_async_block_on;
+    use std::ffi::{CStr, CString};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve one canned response and hand back the raw request text.
+    fn serve_once(response: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/data", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Read until the headers and any Content-Length body are in
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_lowercase();
+                let Some(head_end) = text.find("\r\n\r\n") else { continue };
+                let body_len = text[..head_end]
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map_or(0, |len| len.trim().parse().unwrap());
+                if n == 0 || request.len() >= head_end + 4 + body_len {
+                    break;
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (url, handle)
+    }
+
+    fn text(ptr: *const c_char) -> String {
+        unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_fetch_resolves_with_status_headers_and_body() {
+        crate::zaco_runtime_init();
+        let (url, server) = serve_once(
+            "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nContent-Length: 9\r\n\r\n{\"id\":7}\n",
+        );
+        let url = CString::new(url).unwrap();
+        let method = CString::new("post").unwrap();
+        let headers = CString::new("X-Token: abc\n").unwrap();
+        let body = CString::new("payload").unwrap();
+        let promise = zaco_fetch_start(url.as_ptr(), method.as_ptr(), headers.as_ptr(), body.as_ptr(), std::ptr::null());
+        let response = zaco_async_block_on(promise) as *const FetchResponse;
+
+        assert_eq!(zaco_fetch_response_status(response), 201);
+        let content_type = CString::new("content-type").unwrap();
+        assert_eq!(text(zaco_fetch_response_header(response, content_type.as_ptr())), "application/json");
+        let missing = CString::new("x-missing").unwrap();
+        assert!(zaco_fetch_response_header(response, missing.as_ptr()).is_null());
+        assert_eq!(text(zaco_fetch_response_body(response)), "{\"id\":7}\n");
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /data HTTP/1.1"), "got {}", request);
+        assert!(request.to_lowercase().contains("x-token: abc"), "got {}", request);
+        assert!(request.ends_with("payload"), "got {}", request);
+    }
+
+    #[test]
+    fn test_fetch_rejects_when_connection_fails() {
+        crate::zaco_runtime_init();
+        // Bind then drop a listener to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let url = CString::new(format!("http://127.0.0.1:{}/", port)).unwrap();
+        let empty = CString::new("").unwrap();
+        let promise = zaco_fetch_start(url.as_ptr(), empty.as_ptr(), empty.as_ptr(), std::ptr::null(), std::ptr::null());
+        let error = zaco_async_block_on(promise) as *const c_char;
+        assert!(unsafe { (*promise).is_rejected() });
+        assert!(text(error).starts_with("TypeError: fetch failed"), "got {}", text(error));
+    }
+
+    #[test]
+    fn test_fetch_rejects_with_abort_error_when_signal_fires() {
+        crate::zaco_runtime_init();
+        // Accept the connection but never answer
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = CString::new(format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let empty = CString::new("").unwrap();
+        let signal = crate::abort::zaco_abort_signal_timeout(100.0);
+        let promise = zaco_fetch_start(url.as_ptr(), empty.as_ptr(), empty.as_ptr(), std::ptr::null(), signal);
+        let error = zaco_async_block_on(promise) as *const c_char;
+        assert!(unsafe { (*promise).is_rejected() });
+        assert_eq!(text(error), crate::abort::ABORT_ERROR);
+        drop(listener);
+    }
+}
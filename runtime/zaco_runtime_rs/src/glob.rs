@@ -0,0 +1,160 @@
+//! globSync(pattern, { cwd, dot }) from the "glob" module
+//!
+//! Matching is globset's with `literal_separator` on: `*` and `?` never
+//! cross a `/`, `**` matches any number of whole segments, and `[abc]`,
+//! `[!a-z]` and `{a,b}` work as in shells. walkdir does the walking,
+//! following symlinks; a symlink back up the tree is reported as an entry
+//! but not entered. A leading `!` negates the whole pattern. Entries whose
+//! name starts with a dot are only matched by wildcards when `dot` is set;
+//! a segment that spells the dot out (`.github/*`) always matches. Results
+//! are paths relative to `cwd`, joined with `/` and sorted, so output is
+//! reproducible.
+
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobMatcher};
+use walkdir::WalkDir;
+
+fn compile(pattern: &str) -> Option<GlobMatcher> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .backslash_escape(true)
+        .build()
+        .ok()
+        .map(|glob| glob.compile_matcher())
+}
+
+struct Glob {
+    matcher: Option<GlobMatcher>,
+    /// Pattern segments that start with a dot; a hidden name matches
+    /// without `dot` only through one of these
+    dot_segments: Vec<GlobMatcher>,
+    negated: bool,
+    dot: bool,
+    /// Deepest path a match can have, or `None` if `**` (or negation)
+    /// makes it unbounded
+    max_depth: Option<usize>,
+}
+
+impl Glob {
+    fn new(pattern: &str, dot: bool) -> Self {
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let pattern = pattern.trim_start_matches("./");
+        let dot_segments = pattern
+            .split(['/', '{', ',', '}'])
+            .filter(|segment| segment.starts_with('.') && *segment != "." && *segment != "..")
+            .filter_map(compile)
+            .collect();
+        // Every alternative of a brace set uses a subset of the slashes
+        let max_depth = (!negated && !pattern.contains("**")).then(|| pattern.matches('/').count() + 1);
+        Glob { matcher: compile(pattern), dot_segments, negated, dot, max_depth }
+    }
+
+    fn visible(&self, name: &str) -> bool {
+        self.dot || !name.starts_with('.') || self.dot_segments.iter().any(|m| m.is_match(name))
+    }
+
+    fn matches(&self, rel: &str) -> bool {
+        let hit = self.matcher.as_ref().is_some_and(|m| m.is_match(rel));
+        let visible = rel.split('/').all(|name| self.visible(name));
+        // A negated pattern selects every other visible entry
+        visible && hit != self.negated
+    }
+}
+
+/// Paths under `cwd` matching `pattern`, sorted
+fn glob_paths(pattern: &str, cwd: &Path, dot: bool) -> Vec<String> {
+    let glob = Glob::new(pattern, dot);
+    let mut walker = WalkDir::new(cwd).min_depth(1).follow_links(true).sort_by_file_name();
+    if let Some(max) = glob.max_depth {
+        walker = walker.max_depth(max);
+    }
+    let relative = |path: &Path| {
+        let rel = path.strip_prefix(cwd).ok()?;
+        Some(rel.iter().map(|name| name.to_string_lossy()).collect::<Vec<_>>().join("/"))
+    };
+    let mut out = Vec::new();
+    let entries = walker
+        .into_iter()
+        .filter_entry(|entry| glob.visible(&entry.file_name().to_string_lossy()));
+    for entry in entries {
+        let path = match &entry {
+            Ok(entry) => Some(entry.path()),
+            // A symlink cycle is still an entry, only its contents are skipped
+            Err(err) if err.loop_ancestor().is_some() => err.path(),
+            Err(_) => None,
+        };
+        if let Some(rel) = path.and_then(relative) {
+            if glob.matches(&rel) {
+                out.push(rel);
+            }
+        }
+    }
+    out.sort();
+    out
+}
+
+/// zaco_glob_walk(pattern, cwd, dot) -> inline array of strings
+/// (`[length: i64][ptr; length]`). An empty cwd means the current directory.
+#[no_mangle]
+pub extern "C" fn zaco_glob_walk(pattern: *const c_char, cwd: *const c_char, dot: i64) -> *mut c_void {
+    let pattern = unsafe { crate::cstr_to_str(pattern) };
+    let cwd = match unsafe { crate::cstr_to_str(cwd) } {
+        "" => ".",
+        dir => dir,
+    };
+    let paths = glob_paths(pattern, Path::new(cwd), dot != 0);
+    let array = crate::zaco_compatible_alloc(8 + paths.len() * 8) as *mut i64;
+    unsafe {
+        *array = paths.len() as i64;
+        for (i, path) in paths.iter().enumerate() {
+            *array.add(1 + i) = crate::zaco_compatible_str_new(path) as i64;
+        }
+    }
+    array as *mut c_void
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcards_classes_and_braces() {
+        let glob = Glob::new("src/*.{ts,js}", false);
+        assert!(glob.matches("src/main.ts") && glob.matches("src/main.js"));
+        assert!(!glob.matches("src/main.tsx") && !glob.matches("src/lib/main.ts"));
+        assert!(Glob::new("?.md", false).matches("a.md"));
+        assert!(Glob::new("[a-c]x", false).matches("bx"));
+        assert!(!Glob::new("[!a-c]x", false).matches("bx"));
+    }
+
+    #[test]
+    fn test_hidden_names_need_dot_or_a_literal_dot() {
+        assert!(!Glob::new("*", false).matches(".hidden"));
+        assert!(Glob::new("*", true).matches(".hidden"));
+        assert!(Glob::new(".git*", false).matches(".gitignore"));
+        assert!(Glob::new(".github/*", false).matches(".github/ci.yml"));
+    }
+
+    #[test]
+    fn test_globstar_spans_segments() {
+        let glob = Glob::new("src/**/*.ts", false);
+        assert!(glob.matches("src/a.ts"));
+        assert!(glob.matches("src/x/y/b.ts"));
+        assert!(!glob.matches("src/.cache/c.ts"));
+        assert!(!glob.matches("lib/a.ts"));
+        assert_eq!(glob.max_depth, None);
+        assert_eq!(Glob::new("*/*.ts", false).max_depth, Some(2));
+    }
+
+    #[test]
+    fn test_negation_selects_other_visible_entries() {
+        let glob = Glob::new("!**/*.ts", false);
+        assert!(glob.matches("docs") && glob.matches("docs/x.md"));
+        assert!(!glob.matches("src/a.ts") && !glob.matches(".hidden"));
+    }
+}
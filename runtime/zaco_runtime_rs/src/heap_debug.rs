@@ -0,0 +1,340 @@
+//! Debug heap: leak, double-free and overrun detection (ZACO_DEBUG_HEAP=1)
+//!
+//! When enabled, the C runtime's zaco_alloc/zaco_free and
+//! `zaco_compatible_str_new` route through a tracker that records every live
+//! allocation. Blocks keep the zaco_alloc layout, followed by a canary:
+//! [ref_count: i64][size: i64][data: size bytes][canary: 8 bytes]
+//! A leak report grouped by allocation site is printed to stderr at exit.
+//! Sites come from `--instrument` builds, which announce each allocation via
+//! zaco_profile_alloc; without it every block is attributed to `<unknown>`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::os::raw::c_char;
+use std::sync::{Mutex, Once, OnceLock};
+
+const HEADER_SIZE: usize = 16;
+const CANARY: [u8; 8] = [0xFD; 8];
+const UNKNOWN_SITE: &str = "<unknown>";
+
+struct Allocation {
+    size: usize,
+    site: Option<String>,
+}
+
+/// A misuse of the heap detected by the tracker.
+#[derive(Debug, PartialEq)]
+pub(crate) enum HeapError {
+    /// The block was already freed.
+    DoubleFree { addr: usize },
+    /// The pointer was never handed out by the tracker.
+    InvalidFree { addr: usize },
+    /// The canary after the data region was overwritten.
+    Overrun { addr: usize, size: usize, site: String },
+}
+
+impl fmt::Display for HeapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeapError::DoubleFree { addr } => write!(f, "double free of {:#x}", addr),
+            HeapError::InvalidFree { addr } => write!(f, "free of untracked pointer {:#x}", addr),
+            HeapError::Overrun { addr, size, site } => write!(
+                f,
+                "write past end of {}-byte allocation at {:#x} (site {})",
+                size, addr, site
+            ),
+        }
+    }
+}
+
+fn block_layout(size: usize) -> std::alloc::Layout {
+    std::alloc::Layout::from_size_align(HEADER_SIZE + size + CANARY.len(), 8).unwrap()
+}
+
+/// Table of live allocations, keyed by data pointer.
+pub(crate) struct HeapTracker {
+    live: HashMap<usize, Allocation>,
+    freed: HashSet<usize>,
+    pending_site: Option<String>,
+}
+
+impl HeapTracker {
+    pub(crate) fn new() -> Self {
+        Self {
+            live: HashMap::new(),
+            freed: HashSet::new(),
+            pending_site: None,
+        }
+    }
+
+    /// Attribute the next allocation to `site`.
+    pub(crate) fn set_site(&mut self, site: &str) {
+        self.pending_site = Some(site.to_string());
+    }
+
+    /// Allocate `size` zeroed bytes with a zaco_alloc header (ref count 1).
+    /// Returns a pointer to the data region.
+    pub(crate) fn alloc(&mut self, size: usize) -> *mut u8 {
+        let layout = block_layout(size);
+        unsafe {
+            let base = std::alloc::alloc_zeroed(layout);
+            if base.is_null() {
+                std::alloc::handle_alloc_error(layout);
+            }
+            *(base as *mut i64) = 1;
+            *((base as *mut i64).add(1)) = size as i64;
+            let data = base.add(HEADER_SIZE);
+            std::ptr::copy_nonoverlapping(CANARY.as_ptr(), data.add(size), CANARY.len());
+
+            let addr = data as usize;
+            self.freed.remove(&addr);
+            self.live.insert(addr, Allocation { size, site: self.pending_site.take() });
+            data
+        }
+    }
+
+    /// Release a block returned by `alloc`. Double frees and untracked
+    /// pointers are reported without touching memory; an overrun is reported
+    /// after the block is released.
+    ///
+    /// # Safety
+    /// `data` must not be in use by anyone else once this returns.
+    pub(crate) unsafe fn free(&mut self, data: *mut u8) -> Result<(), HeapError> {
+        let addr = data as usize;
+        let Some(alloc) = self.live.remove(&addr) else {
+            return Err(if self.freed.contains(&addr) {
+                HeapError::DoubleFree { addr }
+            } else {
+                HeapError::InvalidFree { addr }
+            });
+        };
+        self.freed.insert(addr);
+
+        let result = if Self::canary_intact(data, alloc.size) {
+            Ok(())
+        } else {
+            Err(HeapError::Overrun {
+                addr,
+                size: alloc.size,
+                site: alloc.site.unwrap_or_else(|| UNKNOWN_SITE.to_string()),
+            })
+        };
+        std::alloc::dealloc(data.sub(HEADER_SIZE), block_layout(alloc.size));
+        result
+    }
+
+    unsafe fn canary_intact(data: *const u8, size: usize) -> bool {
+        std::slice::from_raw_parts(data.add(size), CANARY.len()) == CANARY
+    }
+
+    /// Overruns in blocks that are still live.
+    pub(crate) fn check_overruns(&self) -> Vec<HeapError> {
+        let mut addrs: Vec<usize> = self.live.keys().copied().collect();
+        addrs.sort_unstable();
+        addrs
+            .into_iter()
+            .filter_map(|addr| {
+                let alloc = &self.live[&addr];
+                if unsafe { Self::canary_intact(addr as *const u8, alloc.size) } {
+                    return None;
+                }
+                Some(HeapError::Overrun {
+                    addr,
+                    size: alloc.size,
+                    site: alloc.site.clone().unwrap_or_else(|| UNKNOWN_SITE.to_string()),
+                })
+            })
+            .collect()
+    }
+
+    /// Leak report: totals, then one line per site ordered by bytes leaked.
+    pub(crate) fn report(&self) -> String {
+        let mut out = String::new();
+        for error in self.check_overruns() {
+            out.push_str(&format!("zaco: heap error: {}\n", error));
+        }
+        if self.live.is_empty() {
+            out.push_str("== zaco heap: no leaks ==\n");
+            return out;
+        }
+
+        let mut by_site: HashMap<&str, (usize, usize)> = HashMap::new();
+        for alloc in self.live.values() {
+            let site = alloc.site.as_deref().unwrap_or(UNKNOWN_SITE);
+            let entry = by_site.entry(site).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += alloc.size;
+        }
+        let mut sites: Vec<(&str, (usize, usize))> = by_site.into_iter().collect();
+        sites.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+
+        let total_bytes: usize = self.live.values().map(|a| a.size).sum();
+        out.push_str(&format!(
+            "== zaco heap: {} leaked, {} bytes ==\n",
+            plural(self.live.len(), "allocation"),
+            total_bytes
+        ));
+        for (site, (count, bytes)) in sites {
+            out.push_str(&format!("{}: {}, {} bytes\n", site, plural(count, "allocation"), bytes));
+        }
+        out
+    }
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+fn tracker() -> &'static Mutex<HeapTracker> {
+    static TRACKER: OnceLock<Mutex<HeapTracker>> = OnceLock::new();
+    TRACKER.get_or_init(|| Mutex::new(HeapTracker::new()))
+}
+
+/// True when ZACO_DEBUG_HEAP=1 (read once).
+pub(crate) fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| std::env::var("ZACO_DEBUG_HEAP").map(|v| v == "1").unwrap_or(false))
+}
+
+extern "C" fn report_at_exit() {
+    eprint!("{}", crate::lock(tracker()).report());
+}
+
+/// Allocate through the tracker, registering the exit report on first use.
+pub(crate) fn tracked_alloc(size: usize) -> *mut u8 {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| unsafe {
+        libc::atexit(report_at_exit);
+    });
+    crate::lock(tracker()).alloc(size)
+}
+
+/// zaco_debug_heap_enabled() -> 1 if ZACO_DEBUG_HEAP=1, else 0
+#[no_mangle]
+pub extern "C" fn zaco_debug_heap_enabled() -> i32 {
+    enabled() as i32
+}
+
+/// zaco_debug_heap_alloc(size) -> data pointer with a zaco_alloc header
+#[no_mangle]
+pub extern "C" fn zaco_debug_heap_alloc(size: i64) -> *mut u8 {
+    tracked_alloc(size.max(0) as usize)
+}
+
+/// zaco_debug_heap_free(ptr) — reports misuse on stderr instead of crashing
+#[no_mangle]
+pub extern "C" fn zaco_debug_heap_free(data: *mut u8) {
+    if data.is_null() {
+        return;
+    }
+    let result = unsafe { crate::lock(tracker()).free(data) };
+    if let Err(error) = result {
+        eprintln!("zaco: heap error: {}", error);
+    }
+}
+
+/// zaco_debug_heap_set_site(name) — attribute the next allocation to `name`
+#[no_mangle]
+pub extern "C" fn zaco_debug_heap_set_site(site: *const c_char) {
+    let site = unsafe { crate::cstr_to_str(site) };
+    crate::lock(tracker()).set_site(site);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alloc_writes_zaco_header() {
+        let mut t = HeapTracker::new();
+        let data = t.alloc(24);
+        unsafe {
+            let base = data.sub(HEADER_SIZE) as *const i64;
+            assert_eq!(*base, 1);
+            assert_eq!(*base.add(1), 24);
+            t.free(data).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_freed_blocks_are_not_reported() {
+        let mut t = HeapTracker::new();
+        let a = t.alloc(8);
+        let b = t.alloc(16);
+        unsafe {
+            t.free(a).unwrap();
+            t.free(b).unwrap();
+        }
+        assert_eq!(t.report(), "== zaco heap: no leaks ==\n");
+    }
+
+    #[test]
+    fn test_leak_report_groups_by_site() {
+        let mut t = HeapTracker::new();
+        t.set_site("Point");
+        t.alloc(16);
+        t.set_site("Point");
+        t.alloc(16);
+        t.set_site("String");
+        let freed = t.alloc(5);
+        t.alloc(40);
+        unsafe { t.free(freed).unwrap() };
+
+        assert_eq!(
+            t.report(),
+            "== zaco heap: 3 allocations leaked, 72 bytes ==\n\
+             <unknown>: 1 allocation, 40 bytes\n\
+             Point: 2 allocations, 32 bytes\n"
+        );
+    }
+
+    #[test]
+    fn test_double_free_is_detected() {
+        let mut t = HeapTracker::new();
+        let data = t.alloc(8);
+        unsafe {
+            t.free(data).unwrap();
+            assert_eq!(t.free(data), Err(HeapError::DoubleFree { addr: data as usize }));
+        }
+    }
+
+    #[test]
+    fn test_untracked_free_is_detected() {
+        let mut t = HeapTracker::new();
+        let mut local = 0u8;
+        let ptr = &mut local as *mut u8;
+        assert_eq!(unsafe { t.free(ptr) }, Err(HeapError::InvalidFree { addr: ptr as usize }));
+    }
+
+    #[test]
+    fn test_overrun_is_detected_on_free() {
+        let mut t = HeapTracker::new();
+        t.set_site("Array");
+        let data = t.alloc(4);
+        unsafe {
+            *data.add(4) = b'x';
+            let err = t.free(data).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("write past end of 4-byte allocation at {:#x} (site Array)", data as usize)
+            );
+        }
+    }
+
+    #[test]
+    fn test_overrun_in_live_block_is_reported() {
+        let mut t = HeapTracker::new();
+        let data = t.alloc(8);
+        unsafe { *data.add(9) = 0 };
+        let report = t.report();
+        assert!(report.starts_with(&format!(
+            "zaco: heap error: write past end of 8-byte allocation at {:#x} (site <unknown>)\n",
+            data as usize
+        )));
+        assert!(report.ends_with("<unknown>: 1 allocation, 8 bytes\n"));
+    }
+}
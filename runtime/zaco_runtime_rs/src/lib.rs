@@ -8,8 +8,14 @@ mod path;
 mod process_api;
 mod os;
 mod http;
+mod fetch;
 mod events;
 mod timer;
+mod heap_debug;
+mod worker;
+mod sqlite;
+mod abort;
+mod glob;
 
 pub use event_loop::*;
 pub use promise::*;
@@ -18,11 +24,18 @@ pub use path::*;
 pub use process_api::*;
 pub use os::*;
 pub use http::*;
+pub use fetch::*;
 pub use events::*;
 pub use timer::*;
+pub use heap_debug::*;
+pub use worker::*;
+pub use sqlite::*;
+pub use abort::*;
+pub use glob::*;
 
 use std::ffi::CStr;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
 
 /// Helper: Convert C string pointer to Rust &str
 /// Used by all submodules via `crate::cstr_to_str`
@@ -31,12 +44,37 @@ pub(crate) unsafe fn cstr_to_str<'a>(ptr: *const c_char) -> &'a str {
     CStr::from_ptr(ptr).to_str().unwrap_or("")
 }
 
-/// Allocate a string using the same memory layout as the C runtime's zaco_alloc.
-/// Layout: [ref_count: i64 = 1][size: i64 = len][data: char[len+1]]
+/// Lock a runtime mutex, recovering the data if a thread panicked while
+/// holding it. Unwrapping a poisoned lock would panic across the C boundary.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The C runtime's zaco_alloc, registered by the C runtime at startup
+static C_ALLOC: OnceLock<extern "C" fn(i64) -> *mut c_void> = OnceLock::new();
+
+/// Called by the C runtime at startup so that values built here come from
+/// the same allocator as its own, whichever `--alloc-strategy` it was
+/// built with
+#[no_mangle]
+pub extern "C" fn zaco_rs_set_alloc(alloc: extern "C" fn(i64) -> *mut c_void) {
+    let _ = C_ALLOC.set(alloc);
+}
+
+/// Allocate `size` zeroed bytes using the same memory layout as the C runtime's zaco_alloc.
+/// Layout: [ref_count: i64 = 1][size: i64][data: size bytes]
 /// Returns a pointer to the data portion (offset 16), compatible with zaco_free/zaco_rc_inc/zaco_rc_dec.
-pub(crate) fn zaco_compatible_str_new(s: &str) -> *mut c_char {
-    let len = s.len();
-    let total = 16 + len + 1; // header + data + null terminator
+/// Under ZACO_DEBUG_HEAP=1 the block comes from the debug heap tracker instead.
+/// Once the C runtime has registered zaco_alloc the block comes from it; the
+/// Rust heap is only used when this library runs without the C runtime.
+pub(crate) fn zaco_compatible_alloc(size: usize) -> *mut u8 {
+    if heap_debug::enabled() {
+        return heap_debug::tracked_alloc(size);
+    }
+    if let Some(alloc) = C_ALLOC.get() {
+        return alloc(size as i64) as *mut u8;
+    }
+    let total = 16 + size; // header + data
     unsafe {
         let layout = std::alloc::Layout::from_size_align(total, 8).unwrap();
         let base = std::alloc::alloc_zeroed(layout);
@@ -45,15 +83,25 @@ pub(crate) fn zaco_compatible_str_new(s: &str) -> *mut c_char {
         }
         // Write ref_count = 1 at offset 0
         *(base as *mut i64) = 1;
-        // Write size = len at offset 8
-        *((base as *mut i64).add(1)) = len as i64;
-        // Copy string data at offset 16
-        let data_ptr = base.add(16);
+        // Write size at offset 8
+        *((base as *mut i64).add(1)) = size as i64;
+        base.add(16)
+    }
+}
+
+/// Allocate a string with the zaco_alloc layout (see `zaco_compatible_alloc`).
+/// The size field holds the string length; the data is null-terminated.
+pub(crate) fn zaco_compatible_str_new(s: &str) -> *mut c_char {
+    let len = s.len();
+    let data_ptr = zaco_compatible_alloc(len + 1);
+    unsafe {
+        // The null terminator is already zeroed
         std::ptr::copy_nonoverlapping(s.as_ptr(), data_ptr, len);
-        // Null terminator (already zeroed, but be explicit)
-        *data_ptr.add(len) = 0;
-        data_ptr as *mut c_char
+        if !heap_debug::enabled() {
+            *((data_ptr as *mut i64).sub(1)) = len as i64;
+        }
     }
+    data_ptr as *mut c_char
 }
 
 /// Initialize the Tokio runtime (called once at program start)
@@ -66,4 +114,5 @@ pub extern "C" fn zaco_runtime_init() {
 #[no_mangle]
 pub extern "C" fn zaco_runtime_shutdown() {
     event_loop::shutdown_runtime();
+    process_api::run_exit_listeners();
 }
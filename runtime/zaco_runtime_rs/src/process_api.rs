@@ -1,11 +1,151 @@
 use std::ffi::CStr;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Mutex, Once};
+
+/// Listener registered with `process.on(event, callback)`. The callback is
+/// called as `callback(context)`, where context is the closure environment.
+#[derive(Clone, Copy)]
+struct ProcessListener {
+    callback: extern "C" fn(*mut c_void),
+    context: usize,
+}
+
+static EXIT_LISTENERS: Mutex<Vec<ProcessListener>> = Mutex::new(Vec::new());
+static SIGNAL_LISTENERS: Mutex<Vec<(c_int, ProcessListener)>> = Mutex::new(Vec::new());
+static EXITING: AtomicBool = AtomicBool::new(false);
+
+/// Write end of the pipe the signal handler forwards signal numbers through
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+fn signal_number(name: &str) -> Option<c_int> {
+    match name {
+        "SIGINT" => Some(libc::SIGINT),
+        "SIGTERM" => Some(libc::SIGTERM),
+        "SIGHUP" => Some(libc::SIGHUP),
+        _ => None,
+    }
+}
+
+/// Run the "exit" listeners once, whether the program calls `process.exit`
+/// or main returns.
+pub(crate) fn run_exit_listeners() {
+    if EXITING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let listeners = crate::lock(&EXIT_LISTENERS).clone();
+    for listener in listeners {
+        (listener.callback)(listener.context as *mut c_void);
+    }
+}
+
+/// Signal handler: only forwards the signal number, since listeners may
+/// allocate and print, which is not async-signal-safe.
+extern "C" fn forward_signal(sig: c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let byte = sig as u8;
+        unsafe {
+            libc::write(fd, &byte as *const u8 as *const c_void, 1);
+        }
+    }
+}
+
+/// Start the thread that runs signal listeners. After the listeners for a
+/// signal return, the signal's default action terminates the process.
+fn start_signal_thread() {
+    static START: Once = Once::new();
+    START.call_once(|| {
+        let mut fds = [0 as c_int; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return;
+        }
+        SIGNAL_PIPE.store(fds[1], Ordering::SeqCst);
+        let read_fd = fds[0];
+        std::thread::spawn(move || loop {
+            let mut byte = 0u8;
+            let n = unsafe { libc::read(read_fd, &mut byte as *mut u8 as *mut c_void, 1) };
+            if n <= 0 {
+                return;
+            }
+            let sig = byte as c_int;
+            let listeners: Vec<ProcessListener> = crate::lock(&SIGNAL_LISTENERS)
+                .iter()
+                .filter(|(s, _)| *s == sig)
+                .map(|(_, l)| *l)
+                .collect();
+            for listener in listeners {
+                (listener.callback)(listener.context as *mut c_void);
+            }
+            unsafe {
+                // Output the listeners wrote would be lost with the process
+                libc::fflush(std::ptr::null_mut());
+                libc::signal(sig, libc::SIG_DFL);
+                libc::raise(sig);
+            }
+        });
+    });
+}
+
+/// process.on(event, callback): "exit" listeners run before the process
+/// exits; signal listeners ("SIGINT", "SIGTERM", "SIGHUP") run off the main
+/// thread when the signal arrives. Other events are ignored.
+#[no_mangle]
+pub extern "C" fn zaco_process_on(
+    event: *const c_char,
+    callback: extern "C" fn(*mut c_void),
+    context: *mut c_void,
+) {
+    let event = unsafe { crate::cstr_to_str(event) };
+    let listener = ProcessListener { callback, context: context as usize };
+    if event == "exit" {
+        crate::lock(&EXIT_LISTENERS).push(listener);
+        return;
+    }
+    let Some(sig) = signal_number(event) else {
+        return;
+    };
+    start_signal_thread();
+    let mut listeners = crate::lock(&SIGNAL_LISTENERS);
+    if !listeners.iter().any(|(s, _)| *s == sig) {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = forward_signal as extern "C" fn(c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(sig, &action, std::ptr::null_mut());
+        }
+    }
+    listeners.push((sig, listener));
+}
 
 #[no_mangle]
 pub extern "C" fn zaco_process_exit(code: i64) {
+    run_exit_listeners();
     std::process::exit(code as i32);
 }
 
+/// process.stdout.isTTY -> 1 when stdout is a terminal, else 0
+#[no_mangle]
+pub extern "C" fn zaco_process_stdout_is_tty() -> i64 {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) as i64 }
+}
+
+/// process.stdout.columns: the terminal width, else $COLUMNS, else 80
+#[no_mangle]
+pub extern "C" fn zaco_process_stdout_columns() -> f64 {
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_col > 0 {
+            return size.ws_col as f64;
+        }
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .map_or(80.0, f64::from)
+}
+
 #[no_mangle]
 pub extern "C" fn zaco_process_cwd() -> *mut c_char {
     match std::env::current_dir() {
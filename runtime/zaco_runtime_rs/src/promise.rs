@@ -1,8 +1,17 @@
 //! Promise implementation for async/await support
+//!
+//! A rejected promise counts as handled once something blocks on it (an
+//! `await`, `.catch`, or `Promise.all`). The entry module asks for the
+//! remaining unhandled rejections at shutdown via
+//! `zaco_promise_take_unhandled` and exits nonzero if there are any.
 
-use std::sync::{Mutex, Condvar};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Condvar, PoisonError};
 use std::ffi::c_void;
 
+/// Addresses of every promise that has been rejected, in rejection order
+static REJECTED: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
 /// Promise state
 #[derive(Clone, Copy, PartialEq)]
 enum PromiseState {
@@ -16,6 +25,8 @@ pub struct ZacoPromise {
     state: Mutex<PromiseState>,
     value: Mutex<Option<*mut c_void>>,
     condvar: Condvar,
+    /// Whether anyone has observed the outcome
+    handled: AtomicBool,
 }
 
 impl ZacoPromise {
@@ -24,33 +35,40 @@ impl ZacoPromise {
             state: Mutex::new(PromiseState::Pending),
             value: Mutex::new(None),
             condvar: Condvar::new(),
+            handled: AtomicBool::new(false),
         }
     }
 
     fn resolve(&self, value: *mut c_void) {
-        let mut state = self.state.lock().unwrap();
+        let mut state = crate::lock(&self.state);
         if *state == PromiseState::Pending {
             *state = PromiseState::Resolved;
-            *self.value.lock().unwrap() = Some(value);
+            *crate::lock(&self.value) = Some(value);
             self.condvar.notify_all();
         }
     }
 
     fn reject(&self, error: *mut c_void) {
-        let mut state = self.state.lock().unwrap();
+        let mut state = crate::lock(&self.state);
         if *state == PromiseState::Pending {
             *state = PromiseState::Rejected;
-            *self.value.lock().unwrap() = Some(error);
+            *crate::lock(&self.value) = Some(error);
+            crate::lock(&REJECTED).push(self as *const ZacoPromise as usize);
             self.condvar.notify_all();
         }
     }
 
+    pub(crate) fn is_rejected(&self) -> bool {
+        *crate::lock(&self.state) == PromiseState::Rejected
+    }
+
     fn wait(&self) -> *mut c_void {
-        let mut state = self.state.lock().unwrap();
+        let mut state = crate::lock(&self.state);
         while *state == PromiseState::Pending {
-            state = self.condvar.wait(state).unwrap();
+            state = self.condvar.wait(state).unwrap_or_else(PoisonError::into_inner);
         }
-        self.value.lock().unwrap().unwrap_or(std::ptr::null_mut())
+        self.handled.store(true, Ordering::Release);
+        crate::lock(&self.value).unwrap_or(std::ptr::null_mut())
     }
 }
 
@@ -85,6 +103,22 @@ pub extern "C" fn zaco_promise_reject(promise: *mut ZacoPromise, error: *mut c_v
     }
 }
 
+/// Resolve a promise with a number, stored as its raw bits (the counterpart
+/// of `zaco_async_block_on_f64`)
+#[no_mangle]
+pub extern "C" fn zaco_promise_resolve_f64(promise: *mut ZacoPromise, value: f64) {
+    zaco_promise_resolve(promise, value.to_bits() as *mut c_void);
+}
+
+/// Whether a promise has been rejected (1) or not (0)
+#[no_mangle]
+pub extern "C" fn zaco_promise_is_rejected(promise: *mut ZacoPromise) -> i64 {
+    if promise.is_null() {
+        return 0;
+    }
+    unsafe { (*promise).is_rejected() as i64 }
+}
+
 /// Block on a promise until it resolves or rejects (returns the value/error)
 #[no_mangle]
 pub extern "C" fn zaco_async_block_on(promise: *mut ZacoPromise) -> *mut c_void {
@@ -96,6 +130,64 @@ pub extern "C" fn zaco_async_block_on(promise: *mut ZacoPromise) -> *mut c_void
     }
 }
 
+/// Block on a promise whose value is a number. Promise values are carried as
+/// raw 8-byte payloads, so the pointer bits are reinterpreted as an f64.
+#[no_mangle]
+pub extern "C" fn zaco_async_block_on_f64(promise: *mut ZacoPromise) -> f64 {
+    f64::from_bits(zaco_async_block_on(promise) as u64)
+}
+
+/// Error of the oldest rejection nobody has awaited or caught, or null if
+/// there is none. The returned rejection counts as handled from then on.
+#[no_mangle]
+pub extern "C" fn zaco_promise_take_unhandled() -> *mut c_void {
+    let rejected = crate::lock(&REJECTED);
+    for &addr in rejected.iter() {
+        let promise = unsafe { &*(addr as *const ZacoPromise) };
+        if !promise.handled.swap(true, Ordering::AcqRel) {
+            return crate::lock(&promise.value).unwrap_or(std::ptr::null_mut());
+        }
+    }
+    std::ptr::null_mut()
+}
+
+/// Promise.all(promises) -> promise of an array of their values
+///
+/// `promises` is an inline array ([len: i64][elem: 8 bytes]...). The returned
+/// promise resolves with an inline array of the same layout holding each
+/// value in order, or rejects with the first rejection.
+#[no_mangle]
+pub extern "C" fn zaco_promise_all(promises: *const i64) -> *mut ZacoPromise {
+    let result = zaco_promise_new();
+    let pending: Vec<usize> = if promises.is_null() {
+        Vec::new()
+    } else {
+        unsafe {
+            let len = (*promises).max(0) as usize;
+            (0..len).map(|i| *promises.add(1 + i) as usize).collect()
+        }
+    };
+    let result_addr = result as usize;
+    crate::event_loop::spawn_blocking(move || {
+        let result = result_addr as *mut ZacoPromise;
+        let values = crate::zaco_compatible_alloc(8 + pending.len() * 8) as *mut u64;
+        unsafe {
+            *values = pending.len() as u64;
+            for (i, &promise) in pending.iter().enumerate() {
+                let promise = &*(promise as *const ZacoPromise);
+                let value = promise.wait();
+                if promise.is_rejected() {
+                    (*result).reject(value);
+                    return;
+                }
+                *values.add(1 + i) = value as u64;
+            }
+            (*result).resolve(values as *mut c_void);
+        }
+    });
+    result
+}
+
 /// Spawn an async task (simplified version - just calls fn and resolves promise)
 /// In a real implementation, this would use tokio::spawn
 #[no_mangle]
@@ -121,8 +213,79 @@ pub extern "C" fn zaco_async_spawn(
 #[no_mangle]
 pub extern "C" fn zaco_promise_free(promise: *mut ZacoPromise) {
     if !promise.is_null() {
+        crate::lock(&REJECTED).retain(|&addr| addr != promise as usize);
         unsafe {
             let _ = Box::from_raw(promise);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_promise_all_collects_values_in_order() {
+        let first = zaco_promise_new();
+        let second = zaco_promise_new();
+        let promises: [i64; 3] = [2, first as i64, second as i64];
+        let all = zaco_promise_all(promises.as_ptr());
+
+        // Settle out of order; the result keeps the input order
+        zaco_promise_resolve(second, 2.5f64.to_bits() as *mut c_void);
+        zaco_promise_resolve(first, 1.5f64.to_bits() as *mut c_void);
+
+        let values = zaco_async_block_on(all) as *const u64;
+        unsafe {
+            assert_eq!(*values, 2);
+            assert_eq!(f64::from_bits(*values.add(1)), 1.5);
+            assert_eq!(f64::from_bits(*values.add(2)), 2.5);
+        }
+    }
+
+    #[test]
+    fn test_promise_all_rejects_on_first_rejection() {
+        let ok = zaco_promise_new();
+        let failed = zaco_promise_new();
+        let promises: [i64; 3] = [2, ok as i64, failed as i64];
+        let all = zaco_promise_all(promises.as_ptr());
+        zaco_promise_resolve(ok, std::ptr::null_mut());
+        zaco_promise_reject(failed, 7 as *mut c_void);
+
+        assert_eq!(zaco_async_block_on(all) as usize, 7);
+        assert!(unsafe { (*all).is_rejected() });
+    }
+
+    #[test]
+    fn test_block_on_f64_reads_number_payload() {
+        let promise = zaco_promise_new();
+        zaco_promise_resolve(promise, 42.0f64.to_bits() as *mut c_void);
+        assert_eq!(zaco_async_block_on_f64(promise), 42.0);
+
+        let promise = zaco_promise_new();
+        zaco_promise_resolve_f64(promise, -1.5);
+        assert_eq!(zaco_async_block_on_f64(promise), -1.5);
+    }
+
+    #[test]
+    fn test_take_unhandled_skips_awaited_rejections() {
+        let awaited = zaco_promise_new();
+        let dropped = zaco_promise_new();
+        zaco_promise_reject(awaited, 11 as *mut c_void);
+        zaco_promise_reject(dropped, 12 as *mut c_void);
+        zaco_async_block_on(awaited);
+
+        // Other tests' rejections may be pending too; drain them all
+        let mut unhandled = Vec::new();
+        loop {
+            let error = zaco_promise_take_unhandled();
+            if error.is_null() {
+                break;
+            }
+            unhandled.push(error as usize);
+        }
+        assert!(unhandled.contains(&12));
+        assert!(!unhandled.contains(&11));
+        assert!(zaco_promise_take_unhandled().is_null());
+    }
+}
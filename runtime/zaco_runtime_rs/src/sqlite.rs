@@ -0,0 +1,290 @@
+//! Embedded SQLite storage (`import { open } from "sqlite"`)
+//!
+//! The C runtime's `zaco_sqlite_*` wrappers call into this module and turn
+//! failures into catchable exceptions via zaco_throw, so no longjmp ever
+//! crosses a Rust frame. Functions here report failure with a null / nonzero
+//! result and leave the message in a per-thread slot read by
+//! `zaco_sqlite_db_error`.
+//!
+//! Query results are returned as an opaque `QueryResult` that the C side
+//! walks to build an array of row objects.
+
+use std::cell::RefCell;
+use std::ffi::{c_void, CString};
+use std::os::raw::c_char;
+
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = message);
+}
+
+/// A column value, converted to the types a zaco program can hold.
+#[derive(Debug, PartialEq)]
+enum Cell {
+    Null,
+    Number(f64),
+    Text(String),
+}
+
+/// Rows of a finished query, with the column names in select order.
+pub struct QueryResult {
+    columns: Vec<CString>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl QueryResult {
+    fn cell(&self, row: i64, col: i64) -> Option<&Cell> {
+        self.rows.get(usize::try_from(row).ok()?)?.get(usize::try_from(col).ok()?)
+    }
+}
+
+/// Decode positional parameters: `params` is an inline array
+/// `[len][8-byte slots]` and `kinds` has one letter per slot, `n` for a
+/// number (f64 bits) or `s` for a string pointer. A `kinds` shorter than
+/// the array repeats its last letter, so `"s"` describes a `string[]`.
+unsafe fn decode_params(params: *const u64, kinds: &str) -> Vec<Value> {
+    if params.is_null() {
+        return Vec::new();
+    }
+    let len = *params as usize;
+    let kinds = kinds.as_bytes();
+    (0..len)
+        .map(|i| {
+            let bits = *params.add(1 + i);
+            match kinds.get(i).or(kinds.last()) {
+                Some(b's') => Value::Text(crate::cstr_to_str(bits as *const c_char).to_string()),
+                _ => {
+                    // Integral numbers bind as INTEGER so they match integer keys
+                    let n = f64::from_bits(bits);
+                    if n.fract() == 0.0 && n.abs() < 9.0e15 {
+                        Value::Integer(n as i64)
+                    } else {
+                        Value::Real(n)
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+fn run_query(conn: &Connection, sql: &str, params: Vec<Value>) -> rusqlite::Result<QueryResult> {
+    let mut stmt = conn.prepare(sql)?;
+    let columns: Vec<CString> = stmt
+        .column_names()
+        .into_iter()
+        .map(|name| CString::new(name).unwrap_or_default())
+        .collect();
+    let mut rows = Vec::new();
+    let mut cursor = stmt.query(params_from_iter(params))?;
+    while let Some(row) = cursor.next()? {
+        let mut cells = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            cells.push(match row.get_ref(i)? {
+                ValueRef::Null => Cell::Null,
+                ValueRef::Integer(n) => Cell::Number(n as f64),
+                ValueRef::Real(n) => Cell::Number(n),
+                ValueRef::Text(bytes) | ValueRef::Blob(bytes) => {
+                    Cell::Text(String::from_utf8_lossy(bytes).into_owned())
+                }
+            });
+        }
+        rows.push(cells);
+    }
+    Ok(QueryResult { columns, rows })
+}
+
+/// zaco_sqlite_db_open(path) -> database handle, or null on error
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_db_open(path: *const c_char) -> *mut c_void {
+    let path = unsafe { crate::cstr_to_str(path) };
+    match Connection::open(path) {
+        Ok(conn) => Box::into_raw(Box::new(conn)) as *mut c_void,
+        Err(e) => {
+            set_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// zaco_sqlite_db_exec(db, sql) -> 0 on success; runs one or more statements
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_db_exec(db: *mut c_void, sql: *const c_char) -> i32 {
+    if db.is_null() {
+        set_error("database is closed");
+        return 1;
+    }
+    let conn = unsafe { &*(db as *const Connection) };
+    let sql = unsafe { crate::cstr_to_str(sql) };
+    match conn.execute_batch(sql) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            1
+        }
+    }
+}
+
+/// zaco_sqlite_db_query(db, sql, params, kinds) -> result, or null on error
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_db_query(
+    db: *mut c_void,
+    sql: *const c_char,
+    params: *const u64,
+    kinds: *const c_char,
+) -> *mut QueryResult {
+    if db.is_null() {
+        set_error("database is closed");
+        return std::ptr::null_mut();
+    }
+    let conn = unsafe { &*(db as *const Connection) };
+    let sql = unsafe { crate::cstr_to_str(sql) };
+    let params = unsafe { decode_params(params, crate::cstr_to_str(kinds)) };
+    match run_query(conn, sql, params) {
+        Ok(result) => Box::into_raw(Box::new(result)),
+        Err(e) => {
+            set_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// zaco_sqlite_db_close(db) — closes the connection
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_db_close(db: *mut c_void) {
+    if !db.is_null() {
+        drop(unsafe { Box::from_raw(db as *mut Connection) });
+    }
+}
+
+/// zaco_sqlite_db_error() -> message of this thread's last failure
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_db_error() -> *const c_char {
+    LAST_ERROR.with(|slot| slot.borrow().as_ptr())
+}
+
+/// zaco_sqlite_result_rows(result) -> number of rows
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_result_rows(result: *const QueryResult) -> i64 {
+    unsafe { &*result }.rows.len() as i64
+}
+
+/// zaco_sqlite_result_columns(result) -> number of columns
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_result_columns(result: *const QueryResult) -> i64 {
+    unsafe { &*result }.columns.len() as i64
+}
+
+/// zaco_sqlite_result_column_name(result, col) -> name, valid until the result is freed
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_result_column_name(result: *const QueryResult, col: i64) -> *const c_char {
+    let result = unsafe { &*result };
+    match usize::try_from(col).ok().and_then(|c| result.columns.get(c)) {
+        Some(name) => name.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+/// zaco_sqlite_result_kind(result, row, col) -> 0 null, 1 number, 2 string
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_result_kind(result: *const QueryResult, row: i64, col: i64) -> i32 {
+    match unsafe { &*result }.cell(row, col) {
+        Some(Cell::Number(_)) => 1,
+        Some(Cell::Text(_)) => 2,
+        _ => 0,
+    }
+}
+
+/// zaco_sqlite_result_f64(result, row, col) -> numeric cell value
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_result_f64(result: *const QueryResult, row: i64, col: i64) -> f64 {
+    match unsafe { &*result }.cell(row, col) {
+        Some(Cell::Number(n)) => *n,
+        _ => 0.0,
+    }
+}
+
+/// zaco_sqlite_result_text(result, row, col) -> text cell value as a zaco string
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_result_text(result: *const QueryResult, row: i64, col: i64) -> *mut c_char {
+    match unsafe { &*result }.cell(row, col) {
+        Some(Cell::Text(s)) => crate::zaco_compatible_str_new(s),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// zaco_sqlite_result_free(result)
+#[no_mangle]
+pub extern "C" fn zaco_sqlite_result_free(result: *mut QueryResult) {
+    if !result.is_null() {
+        drop(unsafe { Box::from_raw(result) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(zaco_sqlite_db_error()) }.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_query_binds_positional_params() {
+        let db = zaco_sqlite_db_open(c(":memory:").as_ptr());
+        let ddl = c("CREATE TABLE t (id INTEGER, name TEXT); \
+                     INSERT INTO t VALUES (1, 'one'), (2, 'two'), (3, NULL);");
+        assert_eq!(zaco_sqlite_db_exec(db, ddl.as_ptr()), 0);
+
+        let two = c("two");
+        let params: [u64; 3] = [2, 1.0f64.to_bits(), two.as_ptr() as u64];
+        let sql = c("SELECT id, name FROM t WHERE id > ? OR name = ? ORDER BY id");
+        let result = zaco_sqlite_db_query(db, sql.as_ptr(), params.as_ptr(), c("ns").as_ptr());
+        assert!(!result.is_null());
+
+        let r = unsafe { &*result };
+        assert_eq!(r.columns, vec![c("id"), c("name")]);
+        assert_eq!(
+            r.rows,
+            vec![
+                vec![Cell::Number(2.0), Cell::Text("two".to_string())],
+                vec![Cell::Number(3.0), Cell::Null],
+            ]
+        );
+        assert_eq!(zaco_sqlite_result_kind(result, 1, 1), 0);
+        assert_eq!(zaco_sqlite_result_f64(result, 1, 0), 3.0);
+        zaco_sqlite_result_free(result);
+
+        // A single kind letter covers every parameter
+        let (one, three) = (c("one"), c("three"));
+        let params: [u64; 3] = [2, one.as_ptr() as u64, three.as_ptr() as u64];
+        let sql = c("SELECT id FROM t WHERE name IN (?, ?)");
+        let result = zaco_sqlite_db_query(db, sql.as_ptr(), params.as_ptr(), c("s").as_ptr());
+        assert_eq!(unsafe { &*result }.rows, vec![vec![Cell::Number(1.0)]]);
+        zaco_sqlite_result_free(result);
+        zaco_sqlite_db_close(db);
+    }
+
+    #[test]
+    fn test_sql_errors_are_reported() {
+        let db = zaco_sqlite_db_open(c(":memory:").as_ptr());
+        assert_eq!(zaco_sqlite_db_exec(db, c("CREATE TABLE (").as_ptr()), 1);
+        assert!(last_error().contains("syntax error"), "got {}", last_error());
+
+        let result = zaco_sqlite_db_query(db, c("SELECT * FROM missing").as_ptr(), std::ptr::null(), c("").as_ptr());
+        assert!(result.is_null());
+        assert!(last_error().contains("no such table: missing"), "got {}", last_error());
+        zaco_sqlite_db_close(db);
+    }
+}
@@ -1,4 +1,7 @@
 //! Timer functions: setTimeout, setInterval, clearTimeout, clearInterval
+//!
+//! Every timer runs on its own thread, so callbacks execute off the main
+//! thread. Registration and cancellation may be called from any thread.
 
 use std::os::raw::c_void;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
@@ -30,10 +33,7 @@ pub extern "C" fn zaco_set_timeout(
         cancelled: AtomicBool::new(false),
     });
 
-    {
-        let mut t = timers().lock().unwrap();
-        t.insert(id, entry.clone());
-    }
+    crate::lock(timers()).insert(id, entry.clone());
 
     // context pointer needs to be sendable across threads
     let ctx = context as usize;
@@ -43,9 +43,7 @@ pub extern "C" fn zaco_set_timeout(
             callback(ctx as *mut c_void);
         }
         // Clean up
-        if let Ok(mut t) = timers().lock() {
-            t.remove(&id);
-        }
+        crate::lock(timers()).remove(&id);
     });
 
     id
@@ -64,10 +62,7 @@ pub extern "C" fn zaco_set_interval(
         cancelled: AtomicBool::new(false),
     });
 
-    {
-        let mut t = timers().lock().unwrap();
-        t.insert(id, entry.clone());
-    }
+    crate::lock(timers()).insert(id, entry.clone());
 
     let ctx = context as usize;
     std::thread::spawn(move || {
@@ -79,9 +74,7 @@ pub extern "C" fn zaco_set_interval(
             callback(ctx as *mut c_void);
         }
         // Clean up
-        if let Ok(mut t) = timers().lock() {
-            t.remove(&id);
-        }
+        crate::lock(timers()).remove(&id);
     });
 
     id
@@ -90,10 +83,8 @@ pub extern "C" fn zaco_set_interval(
 /// clearTimeout(timer_id)
 #[no_mangle]
 pub extern "C" fn zaco_clear_timeout(timer_id: i64) {
-    if let Ok(t) = timers().lock() {
-        if let Some(entry) = t.get(&timer_id) {
-            entry.cancelled.store(true, Ordering::SeqCst);
-        }
+    if let Some(entry) = crate::lock(timers()).get(&timer_id) {
+        entry.cancelled.store(true, Ordering::SeqCst);
     }
 }
 
@@ -102,3 +93,65 @@ pub extern "C" fn zaco_clear_timeout(timer_id: i64) {
 pub extern "C" fn zaco_clear_interval(timer_id: i64) {
     zaco_clear_timeout(timer_id);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    extern "C" fn bump(ctx: *mut c_void) {
+        let counter = unsafe { &*(ctx as *const AtomicUsize) };
+        counter.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn counter_ptr(counter: &'static AtomicUsize) -> *mut c_void {
+        counter as *const AtomicUsize as *mut c_void
+    }
+
+    #[test]
+    fn test_concurrent_timeout_registration_and_cancellation() {
+        static FIRED: AtomicUsize = AtomicUsize::new(0);
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    let mut ids = Vec::new();
+                    for i in 0..25 {
+                        let id = zaco_set_timeout(bump, counter_ptr(&FIRED), 20);
+                        if i % 2 == 0 {
+                            zaco_clear_timeout(id);
+                        }
+                        ids.push(id);
+                    }
+                    ids
+                })
+            })
+            .collect();
+        let ids: Vec<i64> = workers.into_iter().flat_map(|w| w.join().unwrap()).collect();
+
+        std::thread::sleep(Duration::from_millis(300));
+        // 8 threads x 12 uncancelled timers each
+        assert_eq!(FIRED.load(Ordering::SeqCst), 96);
+        let mut unique = ids.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+        let live = crate::lock(timers());
+        assert!(ids.iter().all(|id| !live.contains_key(id)));
+    }
+
+    #[test]
+    fn test_cleared_interval_stops_firing() {
+        static TICKS: AtomicUsize = AtomicUsize::new(0);
+        let id = zaco_set_interval(bump, counter_ptr(&TICKS), 1);
+        std::thread::sleep(Duration::from_millis(30));
+
+        let canceller = std::thread::spawn(move || zaco_clear_interval(id));
+        canceller.join().unwrap();
+        let at_clear = TICKS.load(Ordering::SeqCst);
+        assert!(at_clear > 0);
+
+        std::thread::sleep(Duration::from_millis(30));
+        // At most one callback can have been in flight when the flag was set
+        assert!(TICKS.load(Ordering::SeqCst) <= at_clear + 1);
+    }
+}
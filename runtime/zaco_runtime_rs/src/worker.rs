@@ -0,0 +1,57 @@
+//! Worker threads for CPU-bound work (`import { spawn } from "worker"`)
+//!
+//! For each exported function of a module that imports "worker", the compiler
+//! emits a trampoline and lists it in `__zaco_worker_table`. The C runtime's
+//! `zaco_worker_spawn` looks the trampoline up by name, structured-clones the
+//! arguments and passes both here. The worker never sees the caller's memory.
+
+use std::ffi::c_void;
+
+use crate::event_loop::spawn_blocking;
+use crate::promise::{zaco_promise_new, zaco_promise_resolve, ZacoPromise};
+
+/// Compiler-generated trampoline: unpacks `args` (an inline array) into the
+/// function's parameters, calls it, and writes the 8-byte result to `out`.
+pub type WorkerEntry = extern "C" fn(args: *mut c_void, out: *mut u64);
+
+/// zaco_worker_run(entry, args) -> promise resolved with the function's result
+///
+/// Runs on Tokio's blocking pool. `args` must already be a private copy.
+#[no_mangle]
+pub extern "C" fn zaco_worker_run(entry: WorkerEntry, args: *mut c_void) -> *mut ZacoPromise {
+    let promise = zaco_promise_new();
+    let (promise_addr, args_addr) = (promise as usize, args as usize);
+    spawn_blocking(move || {
+        let mut out = 0u64;
+        entry(args_addr as *mut c_void, &mut out);
+        zaco_promise_resolve(promise_addr as *mut ZacoPromise, out as *mut c_void);
+    });
+    promise
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::promise::zaco_async_block_on_f64;
+
+    extern "C" fn sum_range(args: *mut c_void, out: *mut u64) {
+        let args = args as *const f64;
+        let (from, to) = unsafe { (*args.add(1) as i64, *args.add(2) as i64) };
+        let sum: i64 = (from..to).sum();
+        unsafe { *out = (sum as f64).to_bits() };
+    }
+
+    #[test]
+    fn test_workers_run_in_parallel_and_resolve() {
+        let promises: Vec<*mut ZacoPromise> = (0..4)
+            .map(|i| {
+                // Inline array [len][from][to]; the len slot is read as an i64
+                let args = Box::leak(Box::new([0.0f64, (i * 250) as f64, ((i + 1) * 250) as f64]));
+                args[0] = f64::from_bits(2);
+                zaco_worker_run(sum_range, args.as_mut_ptr() as *mut c_void)
+            })
+            .collect();
+        let total: f64 = promises.into_iter().map(|p| zaco_async_block_on_f64(p)).sum();
+        assert_eq!(total, (0..1000).sum::<i64>() as f64);
+    }
+}
@@ -0,0 +1,263 @@
+/* Property test for zaco_deep_equal (and zaco_structured_clone) in the C runtime.
+ *
+ * Generates random shapes and pairs of values of each shape (numbers with
+ * NaN and -0, strings, booleans, nested arrays and objects with missing keys
+ * and shuffled insertion order), then checks that zaco_deep_equal agrees
+ * with a plain recursive comparison of the generator's own tagged trees.
+ * Built and run by tests/deep_equal.rs.
+ */
+
+#include <math.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+void* zaco_alloc(int64_t size);
+void* zaco_str_new(const char* s);
+void* zaco_object_new(void);
+void zaco_object_set_f64(void* o, const char* key, double value);
+void zaco_object_set_i64(void* o, const char* key, int64_t value);
+void zaco_object_set_ptr(void* o, const char* key, void* value);
+int64_t zaco_deep_equal(void* a, void* b, const char* shape);
+void* zaco_structured_clone(void* value, const char* shape);
+
+static uint64_t rng = 0x9e3779b97f4a7c15ull;
+
+static uint64_t next(void) {
+    rng ^= rng << 13;
+    rng ^= rng >> 7;
+    rng ^= rng << 17;
+    return rng;
+}
+
+static int chance(int percent) {
+    return (int)(next() % 100) < percent;
+}
+
+/* ---- Shapes ---- */
+
+static const char* KEYS[] = {"a", "b", "c", "d"};
+
+static void gen_shape(char* out, size_t cap, int depth) {
+    int kind = (int)(next() % (depth > 0 ? 5 : 3));
+    switch (kind) {
+    case 0: snprintf(out, cap, "n"); break;
+    case 1: snprintf(out, cap, "s"); break;
+    case 2: snprintf(out, cap, "b"); break;
+    case 3: {
+        out[0] = '[';
+        gen_shape(out + 1, cap - 1, depth - 1);
+        break;
+    }
+    default: {
+        int fields = 1 + (int)(next() % 3);
+        size_t len = 0;
+        out[len++] = '{';
+        for (int i = 0; i < fields; i++) {
+            if (i > 0) out[len++] = ',';
+            len += snprintf(out + len, cap - len, "%s:", KEYS[i]);
+            gen_shape(out + len, cap - len, depth - 1);
+            len = strlen(out);
+        }
+        out[len++] = '}';
+        out[len] = '\0';
+    }
+    }
+}
+
+/* Skip one shape (mirrors the runtime's notation) */
+static const char* skip_shape(const char* shape) {
+    if (*shape == '[') return skip_shape(shape + 1);
+    if (*shape == '{') {
+        int depth = 0;
+        do {
+            if (*shape == '{') depth++;
+            else if (*shape == '}') depth--;
+            shape++;
+        } while (depth > 0);
+        return shape;
+    }
+    return shape + 1;
+}
+
+/* ---- Reference values ---- */
+
+typedef struct Tree {
+    char kind; /* n s b [ { */
+    double num;
+    int flag;
+    char str[4];
+    int len;            /* array length or field count */
+    struct Tree* items; /* elements, or fields in shape order */
+    int present[4];     /* object fields the value has */
+    int order[4];       /* insertion order of object fields */
+} Tree;
+
+static void gen_value(Tree* t, const char* shape);
+
+static void gen_number(Tree* t) {
+    static const double pool[] = {0.0, -0.0, 1.0, -1.5, 2.0, 1e300};
+    if (chance(10)) t->num = NAN;
+    else t->num = pool[next() % (sizeof(pool) / sizeof(pool[0]))];
+}
+
+static void gen_value(Tree* t, const char* shape) {
+    memset(t, 0, sizeof(*t));
+    t->kind = *shape;
+    switch (*shape) {
+    case 'n': gen_number(t); break;
+    case 's': snprintf(t->str, sizeof(t->str), "%c", "xyz"[next() % 3]); break;
+    case 'b': t->flag = chance(50); break;
+    case '[':
+        t->len = (int)(next() % 4);
+        t->items = calloc(t->len ? t->len : 1, sizeof(Tree));
+        for (int i = 0; i < t->len; i++) gen_value(&t->items[i], shape + 1);
+        break;
+    case '{': {
+        const char* field = shape + 1;
+        t->items = calloc(4, sizeof(Tree));
+        while (*field != '}') {
+            const char* colon = strchr(field, ':');
+            gen_value(&t->items[t->len], colon + 1);
+            t->present[t->len] = !chance(15);
+            t->len++;
+            field = skip_shape(colon + 1);
+            if (*field == ',') field++;
+        }
+        for (int i = 0; i < t->len; i++) t->order[i] = i;
+        for (int i = t->len - 1; i > 0; i--) {
+            int j = (int)(next() % (i + 1));
+            int tmp = t->order[i];
+            t->order[i] = t->order[j];
+            t->order[j] = tmp;
+        }
+        break;
+    }
+    }
+}
+
+/* A copy of `t` that usually equals it and sometimes differs in one spot */
+static void copy_value(Tree* out, const Tree* t, const char* shape) {
+    *out = *t;
+    if (t->kind == '[' || t->kind == '{') {
+        out->items = calloc(4, sizeof(Tree));
+        const char* field = shape + 1;
+        for (int i = 0; i < t->len; i++) {
+            const char* sub = shape + 1;
+            if (t->kind == '{') {
+                sub = strchr(field, ':') + 1;
+                field = skip_shape(sub);
+                if (*field == ',') field++;
+            }
+            copy_value(&out->items[i], &t->items[i], sub);
+        }
+        /* Same key set, different insertion order */
+        for (int i = t->len - 1; i > 0 && t->kind == '{'; i--) {
+            int j = (int)(next() % (i + 1));
+            int tmp = out->order[i];
+            out->order[i] = out->order[j];
+            out->order[j] = tmp;
+        }
+    }
+    if (chance(8)) gen_value(out, shape);
+}
+
+static int ref_equal(const Tree* a, const Tree* b) {
+    switch (a->kind) {
+    case 'n':
+        if (isnan(a->num) || isnan(b->num)) return isnan(a->num) && isnan(b->num);
+        return a->num == b->num && signbit(a->num) == signbit(b->num);
+    case 's': return strcmp(a->str, b->str) == 0;
+    case 'b': return a->flag == b->flag;
+    case '[':
+        if (a->len != b->len) return 0;
+        for (int i = 0; i < a->len; i++) {
+            if (!ref_equal(&a->items[i], &b->items[i])) return 0;
+        }
+        return 1;
+    default:
+        for (int i = 0; i < a->len; i++) {
+            if (a->present[i] != b->present[i]) return 0;
+            if (a->present[i] && !ref_equal(&a->items[i], &b->items[i])) return 0;
+        }
+        return 1;
+    }
+}
+
+/* ---- Runtime values ---- */
+
+static uint64_t to_runtime(const Tree* t) {
+    uint64_t bits = 0;
+    void* ptr = NULL;
+    switch (t->kind) {
+    case 'n': memcpy(&bits, &t->num, sizeof(bits)); return bits;
+    case 'b': return (uint64_t)t->flag;
+    case 's': ptr = zaco_str_new(t->str); break;
+    case '[': {
+        int bools = t->len > 0 && t->items[0].kind == 'b';
+        char* arr = zaco_alloc(8 + t->len * 8);
+        *(int64_t*)arr = t->len;
+        for (int i = 0; i < t->len; i++) {
+            uint64_t elem = to_runtime(&t->items[i]);
+            if (bools) arr[8 + i] = (char)elem;
+            else memcpy(arr + 8 + i * 8, &elem, 8);
+        }
+        ptr = arr;
+        break;
+    }
+    default: {
+        ptr = zaco_object_new();
+        for (int k = 0; k < t->len; k++) {
+            int i = t->order[k];
+            if (!t->present[i]) continue;
+            uint64_t value = to_runtime(&t->items[i]);
+            zaco_object_set_i64(ptr, KEYS[i], (int64_t)value);
+        }
+    }
+    }
+    memcpy(&bits, &ptr, sizeof(bits));
+    return bits;
+}
+
+int main(void) {
+    int checked = 0, equal = 0;
+    for (int iter = 0; iter < 5000; iter++) {
+        char shape[256];
+        gen_shape(shape, sizeof(shape), 3);
+        if (shape[0] == 'n' || shape[0] == 'b') continue; /* scalars are not passed as pointers */
+
+        Tree a, b;
+        gen_value(&a, shape);
+        copy_value(&b, &a, shape);
+        uint64_t x = to_runtime(&a), y = to_runtime(&b);
+        void *px, *py;
+        memcpy(&px, &x, sizeof(px));
+        memcpy(&py, &y, sizeof(py));
+
+        int expected = ref_equal(&a, &b);
+        int actual = zaco_deep_equal(px, py, shape) != 0;
+        if (actual != expected) {
+            fprintf(stderr, "mismatch for shape %s: runtime says %d, reference %d\n", shape, actual, expected);
+            return 1;
+        }
+        /* Every value equals itself (NaN included) and its clone */
+        if (!zaco_deep_equal(px, px, shape)) {
+            fprintf(stderr, "value of shape %s is not equal to itself\n", shape);
+            return 1;
+        }
+        if (!zaco_deep_equal(px, zaco_structured_clone(px, shape), shape)) {
+            fprintf(stderr, "value of shape %s is not equal to its clone\n", shape);
+            return 1;
+        }
+        checked++;
+        equal += expected;
+    }
+    /* Make sure both outcomes were exercised */
+    if (equal == 0 || equal == checked) {
+        fprintf(stderr, "degenerate run: %d of %d pairs equal\n", equal, checked);
+        return 1;
+    }
+    printf("%d pairs checked, %d equal\n", checked, equal);
+    return 0;
+}
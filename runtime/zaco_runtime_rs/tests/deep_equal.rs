@@ -0,0 +1,37 @@
+//! Property test for `zaco_deep_equal` in the C runtime.
+//!
+//! Builds `tests/deep_equal.c` against `../zaco_runtime.c` with the system C
+//! compiler and runs it. The harness compares the runtime against a reference
+//! implementation on randomly generated values.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn deep_equal_matches_reference() {
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let harness = manifest.join("tests").join("deep_equal.c");
+    let runtime = manifest.join("..").join("zaco_runtime.c");
+    let exe = std::env::temp_dir().join(format!("zaco_deep_equal_{}", std::process::id()));
+
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&cc)
+        .arg("-O1")
+        .arg("-o")
+        .arg(&exe)
+        .arg(&harness)
+        .arg(&runtime)
+        .args(["-lm", "-lpthread"])
+        .status()
+        .expect("failed to run C compiler");
+    assert!(status.success(), "failed to compile deep equality harness");
+
+    let output = Command::new(&exe).output().expect("failed to run harness");
+    let _ = std::fs::remove_file(&exe);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
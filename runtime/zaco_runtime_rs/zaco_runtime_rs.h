@@ -35,6 +35,12 @@ void zaco_runtime_init(void);
  */
 void zaco_runtime_shutdown(void);
 
+/**
+ * Register the C runtime's zaco_alloc. Strings and arrays the Rust runtime
+ * returns are allocated through it from then on.
+ */
+void zaco_rs_set_alloc(void* (*alloc)(int64_t size));
+
 // ============================================================================
 // Path Module (path.*)
 // ============================================================================
@@ -0,0 +1,57 @@
+// Host for modules built with `zaco compile --emit wasm`.
+//
+//   node runtime/zaco_wasm_host.mjs program.wasm
+//
+// The module imports the runtime functions it calls from "zaco". This host
+// implements console output and the number helpers; the compiler rejects
+// calls to any other runtime function, so the trap below is a safety net. Strings are NUL-terminated bytes in the
+// module's exported memory, and `main`'s return value is the exit code.
+
+import { readFileSync } from "node:fs";
+
+const file = process.argv[2];
+if (!file) {
+  console.error("usage: node zaco_wasm_host.mjs <program.wasm>");
+  process.exit(2);
+}
+
+const module = new WebAssembly.Module(readFileSync(file));
+const decoder = new TextDecoder();
+let memory;
+let line = "";
+
+function readString(ptr) {
+  if (ptr === 0) return "null";
+  const bytes = new Uint8Array(memory.buffer, ptr);
+  return decoder.decode(bytes.subarray(0, bytes.indexOf(0)));
+}
+
+const runtime = {
+  zaco_console_put_str: (ptr) => { line += readString(ptr); },
+  zaco_console_put_f64: (n) => { line += String(n); },
+  zaco_console_put_i64: (n) => { line += String(n); },
+  zaco_console_put_bool: (b) => { line += b ? "true" : "false"; },
+  // The native runtime returns the finished line for zaco_print_str; here
+  // it is written directly and the null pointer prints nothing
+  zaco_console_line_end: () => {
+    process.stdout.write(line + "\n");
+    line = "";
+    return 0;
+  },
+  zaco_print_str: (ptr) => {
+    if (ptr !== 0) process.stdout.write(readString(ptr));
+  },
+  zaco_js_mod: (a, b) => a % b,
+};
+
+const imports = {};
+for (const { module: from, name } of WebAssembly.Module.imports(module)) {
+  imports[from] ??= {};
+  imports[from][name] = runtime[name] ?? (() => {
+    throw new Error(`${name} is not available in the WebAssembly host`);
+  });
+}
+
+const instance = new WebAssembly.Instance(module, imports);
+memory = instance.exports.memory;
+process.exitCode = Number(instance.exports.main());